@@ -190,9 +190,11 @@ ECMASCRIPT_CONDITIONAL_KEYWORDS = [
     # Words that are always allowed as identifiers, but are also keywords in
     # other contexts.
     'as',
+    'assert',
     'async',
     'from',
     'get',
+    'meta',
     'of',
     'set',
     'target',
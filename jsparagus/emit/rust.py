@@ -41,10 +41,14 @@ TERMINAL_NAMES = {
     '^=': 'BitwiseXorAssign',
     '|=': 'BitwiseOrAssign',
     '**=': 'ExponentiateAssign',
+    '||=': 'LogicalOrAssign',
+    '&&=': 'LogicalAndAssign',
+    '??=': 'CoalesceAssign',
     '.': 'Dot',
     '**': 'Exponentiate',
     '?': 'QuestionMark',
     '??': 'Coalesce',
+    '?.': 'OptionalChain',
     '*': 'Star',
     '/': 'Divide',
     '%': 'Remainder',
@@ -11,6 +11,8 @@ RUST_BUILTIN_TYPES = {
     'bool',
     'f64',
     'String',
+    'SourceAtomSetIndex',
+    'FunctionFlags',
 }
 
 RUST_PARAMETERIZED_TYPES = {
@@ -19,6 +21,15 @@ RUST_PARAMETERIZED_TYPES = {
 }
 
 
+def is_atom_type(ty):
+    """True for the handles of names in the parse's `SourceAtomSet`. They
+    depend on the order the names were first scanned, so they aren't part of
+    a tree's structure or of its JSON."""
+    if ty.name == 'Option':
+        ty = ty.params[0]
+    return ty.name == 'SourceAtomSetIndex'
+
+
 # name is a string; params is a tuple of 0 or more Types.
 TypeBase = collections.namedtuple("Type", "name params")
 
@@ -151,6 +162,11 @@ EXTRA_STACK_VALUE_TYPE_NAMES = [
     "Vec<Box<ClassElement>>",
     "Vec<BindingProperty>",
     "Vec<Option<Parameter>>",
+    "Vec<ModuleItems>",
+    "Vec<ImportSpecifier>",
+    "Vec<ImportAttribute>",
+    "Vec<ExportFromSpecifier>",
+    "Vec<TemplateExpressionElement>",
 ]
 
 
@@ -349,23 +365,78 @@ def pass_(ast):
         def to_method_name(name):
             return "visit_{}".format(to_snek_case(name))
 
-        # --- Pass ---
+        # Write calls to the `visit_` methods of `receiver` for the children
+        # of type `ty` in `var`.
+        def child_caller(receiver):
+            def emit_call(indent, ty, var):
+                if ty.name == 'Vec':
+                    write(indent, "for item in {} {{", var)
+                    emit_call(indent + 1, ty.params[0], "item")
+                    write(indent, "}")
+                elif ty.name == 'Option':
+                    write(indent, "if let Some(item) = {} {{", var)
+                    emit_call(indent + 1, ty.params[0], "item")
+                    write(indent, "}")
+                elif ty.name in RUST_BUILTIN_TYPES:
+                    pass
+                elif ty.name == 'Box':
+                    write(indent, "{}.{}({});", receiver, to_method_name(ty.params[0].name), var)
+                else:
+                    write(indent, "{}.{}({});", receiver, to_method_name(ty.name), var)
+            return emit_call
+
+        # Write a trait whose `visit_` methods call the `walk_` functions,
+        # followed by the `walk_` functions, which visit the children.
+        def write_traversal(trait, self_name, to_walk_name, borrow):
+            write(1, "/// Called with the kind of each node, the name of its struct or enum")
+            write(1, "/// variant in `types`, like `CallExpression`, before its `loc` and its")
+            write(1, "/// children are visited. A variant that only holds a node of the same")
+            write(1, "/// name, like `Expression::MemberExpression`, isn't a node of its own.")
+            write(1, "/// Does nothing by default.")
+            write(1, "fn visit_node(&mut self, kind: &'static str) {}")
+            write(0, "")
+            write(1, "/// Called with the `loc` of each node, before its children are")
+            write(1, "/// visited. Does nothing by default.")
+            write(1, "fn visit_source_location(&mut self, loc: {}SourceLocation) {{}}", borrow)
+            write(0, "")
+            for name, type_decl in ast.type_decls.items():
+                if name == "Void":
+                    # Hack in a quick fix
+                    continue
+
+                write(1, "fn {}(&mut self, ast: {}{}) {{",
+                      to_method_name(name), borrow, Type(name).to_rust_type(ast))
+                write(2, "{}(self, ast)", to_walk_name(name))
+                write(1, "}")
+                write(0, "")
+            write(0, "}")
+            write(0, "")
 
-        def emit_call(indent, ty, var):
-            if ty.name == 'Vec':
-                write(indent, "for item in {} {{", var)
-                emit_call(indent + 1, ty.params[0], "item")
-                write(indent, "}")
-            elif ty.name == 'Option':
-                write(indent, "if let Some(item) = {} {{", var)
-                emit_call(indent + 1, ty.params[0], "item")
-                write(indent, "}")
-            elif ty.name in RUST_BUILTIN_TYPES:
-                pass
-            elif ty.name == 'Box':
-                write(indent, "self.{}({});", to_method_name(ty.params[0].name), var)
-            else:
-                write(indent, "self.{}({});", to_method_name(ty.name), var)
+            emit_call = child_caller(self_name)
+            for name, type_decl in ast.type_decls.items():
+                if name == "Void":
+                    continue
+
+                write(0, "pub fn {}<'alloc, V: {}<'alloc> + ?Sized>({}: &mut V, ast: {}{}) {{",
+                      to_walk_name(name), trait, self_name, borrow,
+                      Type(name).to_rust_type(ast))
+                # The method bodies are written to be indented as in a trait.
+                def write_dedented(indent, *args):
+                    write(indent - 1, *args)
+
+                def emit_call_dedented(indent, ty, var):
+                    emit_call(indent - 1, ty, var)
+
+                def visit_node_dedented(indent, kind, loc):
+                    write(indent - 1, "{}.visit_node(\"{}\");", self_name, kind)
+                    if loc is not None:
+                        write(indent - 1, "{}.visit_source_location({});", self_name, loc)
+
+                type_decl.write_rust_pass_method_body(
+                    write_dedented, emit_call_dedented, visit_node_dedented,
+                    borrow=borrow)
+                write(0, "}")
+                write(0, "")
 
         write(0, "// WARNING: This file is auto-generated.")
         write(0, "")
@@ -375,23 +446,34 @@ def pass_(ast):
         write(0, "#![allow(dead_code)]")
         write(0, "")
         write(0, "use crate::arena;")
+        write(0, "use crate::function::FunctionFlags;")
+        write(0, "use crate::source_atom_set::SourceAtomSetIndex;")
+        write(0, "use crate::source_location::SourceLocation;")
         write(0, "use crate::types::*;")
         write(0, "use bumpalo;")
         write(0, "")
+
+        # --- Pass ---
+
+        write(0, "/// Traversal of the AST that can modify it.")
+        write(0, "///")
+        write(0, "/// Like `Visitor`, each `visit_` method calls the `walk_mut_` function of")
+        write(0, "/// the same name by default, which visits the node's children.")
         write(0, "pub trait Pass<'alloc> {")
-        for name, type_decl in ast.type_decls.items():
-            if name == "Void":
-                # Hack in a quick fix
-                continue
+        write_traversal("Pass", "pass",
+                        lambda name: "walk_mut_{}".format(to_snek_case(name)), "&mut ")
 
-            write(1, "fn {}(&mut self, ast: &mut {}) {{",
-                  to_method_name(type_decl.name), Type(name).to_rust_type(ast))
-            type_decl.write_rust_pass_method_body(write, emit_call)
+        # --- Visitor ---
 
-            write(1, "}")
-            write(0, "")
-        write(0, "}")
-        write(0, "")
+        write(0, "/// Read-only traversal of the AST.")
+        write(0, "///")
+        write(0, "/// Each `visit_` method calls the `walk_` function of the same name by")
+        write(0, "/// default, which visits the node's children. An implementation can")
+        write(0, "/// override only the methods for the nodes it cares about, and call the")
+        write(0, "/// `walk_` function from its override to keep visiting the children.")
+        write(0, "pub trait Visitor<'alloc> {")
+        write_traversal("Visitor", "visitor",
+                        lambda name: "walk_{}".format(to_snek_case(name)), "&")
 
         # --- PostfixPass ---
 
@@ -487,6 +569,8 @@ def ast_(ast):
             write_impl(f, *args)
         write(0, "// WARNING: This file is auto-generated.")
         write(0, "")
+        write(0, "use crate::function::FunctionFlags;")
+        write(0, "use crate::source_atom_set::SourceAtomSetIndex;")
         write(0, "use crate::source_location::SourceLocation;")
         write(0, "use crate::arena;")
         write(0, "")
@@ -530,9 +614,10 @@ class Struct(AggregateTypeDecl):
         write(0, "}")
         write(0, "")
 
-    def write_rust_pass_method_body(self, write, emit_call):
+    def write_rust_pass_method_body(self, write, emit_call, visit_node, borrow="&mut "):
+        visit_node(2, self.name, "{}ast.loc".format(borrow))
         for name, ty in self.fields.items():
-            emit_call(2, ty, "&mut ast.{}".format(name))
+            emit_call(2, ty, "{}ast.{}".format(borrow, name))
 
     def write_postfix_pass_method(
             self, ast, write, to_method_name, to_postfix_type, append_postfix_pass):
@@ -611,18 +696,24 @@ class Enum(AggregateTypeDecl):
         write(0, "}")
         write(0, "")
 
-    def write_rust_pass_method_body(self, write, emit_call):
+    def write_rust_pass_method_body(self, write, emit_call, visit_node, borrow="&mut "):
         write(2, "match ast {")
         for variant_name, variant_type in self.variants.items():
             if variant_type is None:
-                write(3, "{}::{} {{ .. }} => (),", self.name, variant_name)
+                write(3, "{}::{} {{ loc }} => {{", self.name, variant_name)
+                visit_node(4, variant_name, "loc")
+                write(3, "}")
             elif isinstance(variant_type, dict):
-                write(3, "{}::{} {{ {}, .. }} => {{", self.name, variant_name, ', '.join(variant_type.keys()))
+                write(3, "{}::{} {{ {}, loc }} => {{", self.name, variant_name, ', '.join(variant_type.keys()))
+                visit_node(4, variant_name, "loc")
                 for field_name, field_ty in variant_type.items():
                     emit_call(4, field_ty, field_name)
                 write(3, "}")
             else:
                 write(3, "{}::{}(ast) => {{", self.name, variant_name)
+                held = variant_type.params[0] if variant_type.name == 'Box' else variant_type
+                if held.name != variant_name:
+                    visit_node(4, variant_name, None)
                 emit_call(4, variant_type, "ast")
                 write(3, "}")
         write(2, "}")
@@ -716,6 +807,135 @@ class Ast:
                      and self._has_lifetime(ty.name))))
 
 
+def serialize(ast):
+    with open("src/serialize_generated.rs", "w+") as f:
+        def write(*args):
+            write_impl(f, *args)
+        write(0, "// WARNING: This file is auto-generated by rust/ast/generate_ast.py.")
+        write(0, "")
+        write(0, "use crate::types::*;")
+        write(0, "use serde::ser::{Serialize, SerializeStruct, Serializer};")
+        write(0, "")
+
+        # Every node is serialized as a map whose "type" is the name of the
+        # struct or the enum variant, loosely like ESTree. Enum variants that
+        # only wrap a node of the same name are serialized as that node.
+        def write_node(indent, type_name, fields, expr):
+            write(indent, 'let mut state = serializer.serialize_struct("{}", {})?;',
+                  type_name, len(fields) + 2)
+            write(indent, 'state.serialize_field("type", "{}")?;', type_name)
+            for field in fields:
+                write(indent, 'state.serialize_field("{}", {})?;', field, expr(field))
+            write(indent, 'state.serialize_field("loc", {})?;', expr("loc"))
+            write(indent, "state.end()")
+
+        for name, decl in ast.type_decls.items():
+            write(0, "impl{} Serialize for {}{} {{", decl.lifetime_params(), name,
+                  decl.lifetime_params())
+            if isinstance(decl, Enum) and len(decl.variants) == 0:
+                write(1, "fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {")
+                write(2, "match *self {}")
+            else:
+                write(1, "fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {")
+            if isinstance(decl, Struct):
+                fields = [field for field, ty in decl.fields.items() if not is_atom_type(ty)]
+                write_node(2, name, fields, lambda field: "&self.{}".format(field))
+            elif len(decl.variants) > 0:
+                write(2, "match self {")
+                for variant_name, variant_ty in decl.variants.items():
+                    if variant_ty is None or isinstance(variant_ty, dict):
+                        fields = list(variant_ty or {})
+                        write(3, "{}::{} {{ {} }} => {{", name, variant_name,
+                              ", ".join(fields + ["loc"]))
+                        write_node(4, variant_name, fields, lambda field: field)
+                        write(3, "}")
+                        continue
+
+                    if variant_ty.name == "Box":
+                        variant_ty = variant_ty.params[0]
+                    if variant_ty.name == variant_name:
+                        write(3, "{}::{}(content) => content.serialize(serializer),",
+                              name, variant_name)
+                    else:
+                        write(3, "{}::{}(content) => {{", name, variant_name)
+                        write(4, 'let mut state = serializer.serialize_struct("{}", 2)?;',
+                              variant_name)
+                        write(4, 'state.serialize_field("type", "{}")?;', variant_name)
+                        write(4, 'state.serialize_field("content", content)?;')
+                        write(4, "state.end()")
+                        write(3, "}")
+                write(2, "}")
+            write(1, "}")
+            write(0, "}")
+            write(0, "")
+
+
+def structural_eq(ast):
+    with open("src/structural_eq_generated.rs", "w+") as f:
+        def write(*args):
+            write_impl(f, *args)
+        write(0, "// WARNING: This file is auto-generated by rust/ast/generate_ast.py.")
+        write(0, "")
+        write(0, "use crate::structural_eq::StructuralEq;")
+        write(0, "use crate::types::*;")
+        write(0, "")
+
+        # Compare every field but `loc`.
+        def write_fields_eq(indent, fields, left, right):
+            if len(fields) == 0:
+                write(indent, "true")
+                return
+            for i, field in enumerate(fields):
+                write(indent, "{}{}.structurally_eq({})",
+                      "" if i == 0 else "&& ", left(field), right(field))
+
+        for name, decl in ast.type_decls.items():
+            write(0, "impl{} StructuralEq for {}{} {{", decl.lifetime_params(), name,
+                  decl.lifetime_params())
+            if isinstance(decl, Enum) and len(decl.variants) == 0:
+                write(1, "fn structurally_eq(&self, _other: &Self) -> bool {")
+                write(2, "match *self {}")
+            elif isinstance(decl, Struct):
+                fields = [field for field, ty in decl.fields.items() if not is_atom_type(ty)]
+                write(1, "fn structurally_eq(&self, {}other: &Self) -> bool {{",
+                      "" if fields else "_")
+                write_fields_eq(2, fields,
+                                lambda field: "self.{}".format(field),
+                                lambda field: "&other.{}".format(field))
+            else:
+                write(1, "fn structurally_eq(&self, other: &Self) -> bool {")
+                write(2, "match (self, other) {")
+                for variant_name, variant_ty in decl.variants.items():
+                    if variant_ty is None or isinstance(variant_ty, dict):
+                        fields = list(variant_ty or {})
+                        if len(fields) == 0:
+                            write(3, "({}::{} {{ .. }}, {}::{} {{ .. }}) => true,",
+                                  name, variant_name, name, variant_name)
+                            continue
+                        write(3, "(")
+                        write(4, "{}::{} {{ {}, .. }},", name, variant_name,
+                              ", ".join(fields))
+                        write(4, "{}::{} {{ {}, .. }},", name, variant_name,
+                              ", ".join("{}: other_{}".format(field, field)
+                                        for field in fields))
+                        write(3, ") => {")
+                        write_fields_eq(4, fields,
+                                        lambda field: field,
+                                        lambda field: "other_{}".format(field))
+                        write(3, "}")
+                    else:
+                        write(3, "({}::{}(content), {}::{}(other_content)) => {{",
+                              name, variant_name, name, variant_name)
+                        write(4, "content.structurally_eq(other_content)")
+                        write(3, "}")
+                if len(decl.variants) > 1:
+                    write(3, "_ => false,")
+                write(2, "}")
+            write(1, "}")
+            write(0, "}")
+            write(0, "")
+
+
 def main():
     with open("ast.json", "r") as json_file:
         ast_json = json.load(json_file)
@@ -725,6 +945,8 @@ def main():
     stack_value(ast)
     loc_trait(ast)
     pass_(ast)
+    serialize(ast)
+    structural_eq(ast)
 
 
 if __name__ == "__main__":
@@ -0,0 +1,592 @@
+//! Analyses of programs, for tools that need to know about a script without
+//! running it, such as bundlers, sandboxes and optimizers.
+
+use crate::types::*;
+use crate::visit::{
+    walk_catch_clause, walk_class_declaration, walk_class_element, walk_expression, walk_program,
+    walk_statement, walk_variable_declaration, Visitor,
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// The names the program refers to without declaring them, which are
+/// looked up in the global object, or are errors if it has no such
+/// property.
+///
+/// Names are resolved to the scopes the spec gives them: `var` and function
+/// declarations to their function, or the script or module, wherever they
+/// are in it, `let`, `const` and class declarations to their block, and
+/// parameters and catch bindings to their function and catch clause. The
+/// expressions in parameters that aren't simple don't see the declarations
+/// of the body. In non-strict code, a function declared in a block is also
+/// declared in its function, as in browsers, unless a `let`, `const` or
+/// class declaration of the same name in a block around it would conflict
+/// with that. `arguments` in a function other than an arrow function isn't
+/// free, and `this` is never a name.
+///
+/// Names in a `with` statement are free if nothing outside it declares
+/// them, even though they may be properties of its object.
+pub fn free_variables(program: &Program) -> BTreeSet<String> {
+    let mut resolver = Resolver {
+        scopes: vec![Scope {
+            parent: None,
+            kind: ScopeKind::Function { arguments: false },
+            names: HashSet::new(),
+        }],
+        current: 0,
+        declare_vars: false,
+        strict: false,
+        references: Vec::new(),
+        block_functions: Vec::new(),
+    };
+    resolver.visit_program(program);
+    resolver.hoist_block_functions();
+    resolver.free_variables()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScopeKind {
+    /// The scope of a function, script or module, or of a class static
+    /// block, where `var` declarations go. Only functions other than arrow
+    /// functions have `arguments`.
+    Function { arguments: bool },
+    /// A block, or the head of a loop or the cases of a `switch`, which
+    /// have their own `let` and `const` declarations, or a catch clause
+    /// with a pattern, or the scope of the name of a function or class
+    /// expression.
+    Block,
+    /// A catch clause whose parameter is a name, which a function declared
+    /// in a block in it can also declare in the function around it.
+    Catch,
+}
+
+struct Scope<'alloc> {
+    parent: Option<usize>,
+    kind: ScopeKind,
+    names: HashSet<&'alloc str>,
+}
+
+struct Resolver<'alloc> {
+    scopes: Vec<Scope<'alloc>>,
+    current: usize,
+
+    /// Whether the BindingIdentifiers being visited are in a `var`
+    /// declaration. Any others declare names in the current scope.
+    declare_vars: bool,
+
+    strict: bool,
+
+    /// The IdentifierExpressions and AssignmentTargetIdentifiers, with the
+    /// scope they're in. They're resolved at the end, once all the
+    /// declarations, which can come after uses, are known.
+    references: Vec<(&'alloc str, usize)>,
+
+    /// The names of the functions declared in blocks in non-strict code,
+    /// with the block. They're declared in their function at the end, if
+    /// no declaration in a block around them, which can come after them,
+    /// conflicts.
+    block_functions: Vec<(&'alloc str, usize)>,
+}
+
+impl<'alloc> Resolver<'alloc> {
+    fn enter_scope(&mut self, kind: ScopeKind) -> (usize, bool) {
+        self.scopes.push(Scope {
+            parent: Some(self.current),
+            kind,
+            names: HashSet::new(),
+        });
+        let outer = (self.current, self.declare_vars);
+        self.current = self.scopes.len() - 1;
+        self.declare_vars = false;
+        outer
+    }
+
+    fn leave_scope(&mut self, (current, declare_vars): (usize, bool)) {
+        self.current = current;
+        self.declare_vars = declare_vars;
+    }
+
+    /// The nearest function scope, where `var` declarations in the current
+    /// scope go.
+    fn var_scope(&self) -> usize {
+        self.var_scope_of(self.current)
+    }
+
+    fn var_scope_of(&self, mut scope: usize) -> usize {
+        while let ScopeKind::Block | ScopeKind::Catch = self.scopes[scope].kind {
+            scope = self.scopes[scope].parent.unwrap();
+        }
+        scope
+    }
+
+    fn declare(&mut self, scope: usize, name: &'alloc str) {
+        self.scopes[scope].names.insert(name);
+    }
+
+    /// A function declared in a statement or by `export`, whose name is in
+    /// the enclosing block, or if that is its function, the function.
+    fn function_declaration(&mut self, function: &Function<'alloc>) {
+        if let Some(name) = &function.name {
+            self.declare(self.current, name.name.value);
+            if !self.strict && self.var_scope() != self.current {
+                self.block_functions.push((name.name.value, self.current));
+            }
+        }
+        self.function(function);
+    }
+
+    /// Declare the functions declared in blocks in non-strict code in their
+    /// function too, as Annex B does if a `var` declaration of the name in
+    /// their place wouldn't be an error: if no block between them and their
+    /// function declares the name, other than a catch clause whose
+    /// parameter is the name.
+    /// https://tc39.es/ecma262/#sec-block-level-function-declarations-web-legacy-compatibility-semantics
+    fn hoist_block_functions(&mut self) {
+        for (name, block) in std::mem::take(&mut self.block_functions) {
+            let var_scope = self.var_scope_of(block);
+            let mut scope = self.scopes[block].parent.unwrap();
+            let mut conflicts = false;
+            while scope != var_scope {
+                let Scope {
+                    parent,
+                    kind,
+                    names,
+                } = &self.scopes[scope];
+                if *kind == ScopeKind::Block && names.contains(name) {
+                    conflicts = true;
+                    break;
+                }
+                scope = parent.unwrap();
+            }
+            if !conflicts {
+                self.declare(var_scope, name);
+            }
+        }
+    }
+
+    /// A function expression, whose name is only in the function.
+    fn function_expression(&mut self, function: &Function<'alloc>) {
+        let outer = self.enter_scope(ScopeKind::Block);
+        if let Some(name) = &function.name {
+            self.declare(self.current, name.name.value);
+        }
+        self.function(function);
+        self.leave_scope(outer);
+    }
+
+    fn function(&mut self, function: &Function<'alloc>) {
+        self.function_scope(true, |resolver| {
+            resolver.strict |= function.body.strict;
+            resolver.visit_formal_parameters(&function.params);
+            resolver.body_scope(function.params.is_simple(), |resolver| {
+                resolver.visit_function_body(&function.body)
+            });
+        });
+    }
+
+    fn function_scope(&mut self, arguments: bool, f: impl FnOnce(&mut Self)) {
+        let strict = self.strict;
+        let outer = self.enter_scope(ScopeKind::Function { arguments });
+        f(self);
+        self.leave_scope(outer);
+        self.strict = strict;
+    }
+
+    /// The body of a function, whose parameters are simple or not. If they
+    /// aren't, the body has its own scope for its declarations, which the
+    /// expressions in the parameters don't see.
+    /// https://tc39.es/ecma262/#sec-functiondeclarationinstantiation
+    fn body_scope(&mut self, simple_params: bool, f: impl FnOnce(&mut Self)) {
+        if simple_params {
+            f(self);
+        } else {
+            let outer = self.enter_scope(ScopeKind::Function { arguments: false });
+            f(self);
+            self.leave_scope(outer);
+        }
+    }
+
+    fn block_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        let outer = self.enter_scope(ScopeKind::Block);
+        f(self);
+        self.leave_scope(outer);
+    }
+
+    fn free_variables(&self) -> BTreeSet<String> {
+        self.references
+            .iter()
+            .filter(|(name, scope)| !self.is_declared(name, *scope))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    fn is_declared(&self, name: &str, scope: usize) -> bool {
+        let mut scope = Some(scope);
+        while let Some(index) = scope {
+            let Scope {
+                parent,
+                kind,
+                names,
+            } = &self.scopes[index];
+            if names.contains(name)
+                || (name == "arguments" && *kind == ScopeKind::Function { arguments: true })
+            {
+                return true;
+            }
+            scope = *parent;
+        }
+        false
+    }
+}
+
+impl<'alloc> Visitor<'alloc> for Resolver<'alloc> {
+    fn visit_program(&mut self, ast: &Program<'alloc>) {
+        self.strict = match ast {
+            Program::Script(script) => script.strict,
+            Program::Module(_) => true,
+        };
+        walk_program(self, ast);
+    }
+
+    fn visit_statement(&mut self, ast: &Statement<'alloc>) {
+        match ast {
+            Statement::FunctionDeclaration(function) => self.function_declaration(function),
+            // The `let` and `const` declarations in the head of the loop.
+            Statement::ForStatement { .. }
+            | Statement::ForInStatement { .. }
+            | Statement::ForOfStatement { .. } => {
+                self.block_scope(|resolver| walk_statement(resolver, ast))
+            }
+            Statement::SwitchStatement {
+                discriminant,
+                cases,
+                ..
+            } => {
+                self.visit_expression(discriminant);
+                self.block_scope(|resolver| {
+                    for case in cases.iter() {
+                        resolver.visit_switch_case(case);
+                    }
+                });
+            }
+            Statement::SwitchStatementWithDefault {
+                discriminant,
+                pre_default_cases,
+                default_case,
+                post_default_cases,
+                ..
+            } => {
+                self.visit_expression(discriminant);
+                self.block_scope(|resolver| {
+                    for case in pre_default_cases.iter() {
+                        resolver.visit_switch_case(case);
+                    }
+                    resolver.visit_switch_default(default_case);
+                    for case in post_default_cases.iter() {
+                        resolver.visit_switch_case(case);
+                    }
+                });
+            }
+            _ => walk_statement(self, ast),
+        }
+    }
+
+    fn visit_block(&mut self, ast: &Block<'alloc>) {
+        self.block_scope(|resolver| {
+            for statement in ast.statements.iter() {
+                resolver.visit_statement(statement);
+            }
+        });
+    }
+
+    fn visit_catch_clause(&mut self, ast: &CatchClause<'alloc>) {
+        let kind = match ast.binding.as_deref() {
+            Some(Binding::BindingIdentifier(_)) => ScopeKind::Catch,
+            _ => ScopeKind::Block,
+        };
+        let outer = self.enter_scope(kind);
+        walk_catch_clause(self, ast);
+        self.leave_scope(outer);
+    }
+
+    fn visit_variable_declaration(&mut self, ast: &VariableDeclaration<'alloc>) {
+        let declare_vars = self.declare_vars;
+        self.declare_vars = matches!(ast.kind, VariableDeclarationKind::Var { .. });
+        walk_variable_declaration(self, ast);
+        self.declare_vars = declare_vars;
+    }
+
+    fn visit_binding_identifier(&mut self, ast: &BindingIdentifier<'alloc>) {
+        let scope = if self.declare_vars {
+            self.var_scope()
+        } else {
+            self.current
+        };
+        self.declare(scope, ast.name.value);
+    }
+
+    fn visit_identifier_expression(&mut self, ast: &IdentifierExpression<'alloc>) {
+        self.references.push((ast.name.value, self.current));
+    }
+
+    fn visit_assignment_target_identifier(&mut self, ast: &AssignmentTargetIdentifier<'alloc>) {
+        self.references.push((ast.name.value, self.current));
+    }
+
+    // Functions declared by `export` and `export default`.
+    fn visit_function(&mut self, ast: &Function<'alloc>) {
+        self.function_declaration(ast);
+    }
+
+    fn visit_expression(&mut self, ast: &Expression<'alloc>) {
+        match ast {
+            Expression::FunctionExpression(function) => self.function_expression(function),
+            Expression::ArrowExpression { params, body, .. } => {
+                self.function_scope(false, |resolver| {
+                    if let ArrowExpressionBody::FunctionBody(body) = body {
+                        resolver.strict |= body.strict;
+                    }
+                    resolver.visit_formal_parameters(params);
+                    resolver.body_scope(params.is_simple(), |resolver| {
+                        resolver.visit_arrow_expression_body(body)
+                    });
+                })
+            }
+            _ => walk_expression(self, ast),
+        }
+    }
+
+    fn visit_method(&mut self, ast: &Method<'alloc>) {
+        self.visit_property_name(&ast.name);
+        self.function_scope(true, |resolver| {
+            resolver.strict |= ast.body.strict;
+            resolver.visit_formal_parameters(&ast.params);
+            resolver.body_scope(ast.params.is_simple(), |resolver| {
+                resolver.visit_function_body(&ast.body)
+            });
+        });
+    }
+
+    fn visit_getter(&mut self, ast: &Getter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+        self.function_scope(true, |resolver| {
+            resolver.strict |= ast.body.strict;
+            resolver.visit_function_body(&ast.body);
+        });
+    }
+
+    fn visit_setter(&mut self, ast: &Setter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+        self.function_scope(true, |resolver| {
+            resolver.strict |= ast.body.strict;
+            resolver.visit_parameter(&ast.param);
+            let simple = matches!(ast.param, Parameter::Binding(Binding::BindingIdentifier(_)));
+            resolver.body_scope(simple, |resolver| resolver.visit_function_body(&ast.body));
+        });
+    }
+
+    // Class bodies are strict mode code.
+    fn visit_class_declaration(&mut self, ast: &ClassDeclaration<'alloc>) {
+        let strict = self.strict;
+        self.strict = true;
+        walk_class_declaration(self, ast);
+        self.strict = strict;
+    }
+
+    fn visit_class_expression(&mut self, ast: &ClassExpression<'alloc>) {
+        let strict = self.strict;
+        self.strict = true;
+        self.block_scope(|resolver| {
+            if let Some(name) = &ast.name {
+                resolver.declare(resolver.current, name.name.value);
+            }
+            if let Some(super_) = &ast.super_ {
+                resolver.visit_expression(super_);
+            }
+            for element in ast.elements.iter() {
+                resolver.visit_class_element(element);
+            }
+        });
+        self.strict = strict;
+    }
+
+    fn visit_class_element(&mut self, ast: &ClassElement<'alloc>) {
+        match ast {
+            // A static block has its own `var` declarations, but no
+            // `arguments`.
+            ClassElement::StaticBlock { statements, .. } => {
+                self.function_scope(false, |resolver| {
+                    for statement in statements.iter() {
+                        resolver.visit_statement(statement);
+                    }
+                })
+            }
+            _ => walk_class_element(self, ast),
+        }
+    }
+}
+
+/// Whether evaluating `expr` certainly has no side effects and can't throw,
+/// so that an optimizer can remove it if its value isn't used.
+///
+/// This is conservative: literals, function and arrow expressions, and
+/// array and object literals of pure expressions are pure, and so are
+/// operators over pure operands when they can't call user code, as they
+/// would to convert an object to a primitive, or throw, as BigInts can.
+/// Identifiers aren't: without knowing the bindings, the name may not be
+/// declared, or be in its temporal dead zone, or be a getter of the global
+/// object or of a `with` statement's object. Calls, member accesses, which
+/// can run getters, `new`, assignments and all other expressions aren't
+/// pure either.
+pub fn is_pure(expr: &Expression) -> bool {
+    pure_value(expr).is_some()
+}
+
+/// The kind of value a pure expression has.
+#[derive(Clone, Copy, PartialEq)]
+enum PureValue {
+    /// A primitive other than a BigInt, which operators convert without
+    /// running user code or throwing.
+    Primitive,
+    BigInt,
+    /// Any value, possibly an object, which operators may convert by
+    /// calling its methods.
+    Any,
+}
+
+/// The kind of value that's one of two kinds.
+fn either(left: PureValue, right: PureValue) -> Option<PureValue> {
+    Some(if left == right { left } else { PureValue::Any })
+}
+
+/// The kind of value `expr` has if it's pure, or None if it isn't.
+fn pure_value(expr: &Expression) -> Option<PureValue> {
+    let primitive = |expr: &Expression| match pure_value(expr)? {
+        PureValue::Primitive => Some(PureValue::Primitive),
+        _ => None,
+    };
+    match expr {
+        Expression::LiteralBooleanExpression { .. }
+        | Expression::LiteralInfinityExpression { .. }
+        | Expression::LiteralNullExpression { .. }
+        | Expression::LiteralNumericExpression { .. }
+        | Expression::LiteralStringExpression { .. } => Some(PureValue::Primitive),
+        Expression::LiteralBigIntExpression { .. } => Some(PureValue::BigInt),
+        Expression::LiteralRegExpExpression { .. }
+        | Expression::FunctionExpression(_)
+        | Expression::ArrowExpression { .. } => Some(PureValue::Any),
+
+        Expression::ArrayExpression(array) => {
+            for element in array.elements.iter() {
+                match element {
+                    ArrayExpressionElement::Expression(expr) => {
+                        pure_value(expr)?;
+                    }
+                    ArrayExpressionElement::Elision { .. } => {}
+                    // Spreading runs the iterator of the operand.
+                    ArrayExpressionElement::SpreadElement(_) => return None,
+                }
+            }
+            Some(PureValue::Any)
+        }
+        Expression::ObjectExpression(object) => {
+            for property in object.properties.iter() {
+                let name = match &**property {
+                    ObjectProperty::NamedObjectProperty(NamedObjectProperty::DataProperty(
+                        property,
+                    )) => {
+                        pure_value(&property.expression)?;
+                        &property.property_name
+                    }
+                    ObjectProperty::NamedObjectProperty(NamedObjectProperty::MethodDefinition(
+                        method,
+                    )) => match method {
+                        MethodDefinition::Method(method) => &method.name,
+                        MethodDefinition::Getter(getter) => &getter.property_name,
+                        MethodDefinition::Setter(setter) => &setter.property_name,
+                    },
+                    ObjectProperty::ShorthandProperty(_) | ObjectProperty::SpreadProperty(_) => {
+                        return None
+                    }
+                };
+                if let PropertyName::ComputedPropertyName(name) = name {
+                    primitive(&name.expression)?;
+                }
+            }
+            Some(PureValue::Any)
+        }
+
+        Expression::TemplateExpression(TemplateExpression {
+            tag: None,
+            elements,
+            ..
+        }) => {
+            for element in elements.iter() {
+                if let TemplateExpressionElement::Expression(expr) = element {
+                    primitive(expr)?;
+                }
+            }
+            Some(PureValue::Primitive)
+        }
+
+        Expression::UnaryExpression {
+            operator, operand, ..
+        } => match operator {
+            UnaryOperator::LogicalNot { .. }
+            | UnaryOperator::Typeof { .. }
+            | UnaryOperator::Void { .. } => {
+                pure_value(operand)?;
+                Some(PureValue::Primitive)
+            }
+            // `+` throws for BigInts, unlike `-` and `~`.
+            UnaryOperator::Plus { .. } => primitive(operand),
+            UnaryOperator::Minus { .. } | UnaryOperator::BitwiseNot { .. } => {
+                match pure_value(operand)? {
+                    PureValue::Any => None,
+                    value => Some(value),
+                }
+            }
+            UnaryOperator::Delete { .. } => None,
+        },
+
+        Expression::BinaryExpression {
+            operator,
+            left,
+            right,
+            ..
+        } => match operator {
+            BinaryOperator::Comma { .. } => {
+                pure_value(left)?;
+                pure_value(right)
+            }
+            // The result is one of the operands.
+            BinaryOperator::LogicalAnd { .. }
+            | BinaryOperator::LogicalOr { .. }
+            | BinaryOperator::Coalesce { .. } => either(pure_value(left)?, pure_value(right)?),
+            // These never convert their operands.
+            BinaryOperator::StrictEquals { .. } | BinaryOperator::StrictNotEquals { .. } => {
+                pure_value(left)?;
+                pure_value(right)?;
+                Some(PureValue::Primitive)
+            }
+            // These throw a TypeError when `right` isn't an object, and may
+            // call its methods when it is.
+            BinaryOperator::In { .. } | BinaryOperator::Instanceof { .. } => None,
+            _ => {
+                primitive(left)?;
+                primitive(right)
+            }
+        },
+
+        Expression::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+            ..
+        } => {
+            pure_value(test)?;
+            either(pure_value(consequent)?, pure_value(alternate)?)
+        }
+
+        _ => None,
+    }
+}
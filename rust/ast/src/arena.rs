@@ -1,4 +1,5 @@
 use bumpalo::Bump;
+use serde::{Serialize, Serializer};
 
 use std::{
     self,
@@ -44,6 +45,15 @@ where
     }
 }
 
+impl<'alloc, T: ?Sized> Serialize for Box<'alloc, T>
+where
+    T: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
 impl<'alloc, T> PartialEq for Box<'alloc, T>
 where
     T: PartialEq<T> + ?Sized,
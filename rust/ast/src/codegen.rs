@@ -0,0 +1,1516 @@
+//! Generating JavaScript source from the AST.
+//!
+//! The output is not meant to look like the original source: comments and
+//! formatting are lost, every statement is on its own line and ends with a
+//! semicolon, and parentheses are only added where they're needed to keep
+//! the meaning of the program. Parsing the output gives back the same AST,
+//! except for source locations.
+
+use crate::types::*;
+use crate::visit::Visitor;
+
+/// Generate the source code of a program.
+pub fn generate(program: &Program) -> String {
+    let mut generator = Generator {
+        out: String::new(),
+        indent: 0,
+    };
+    // Keep the hashbang on the first line, where it's allowed.
+    let hashbang = match program {
+        Program::Script(script) => script.hashbang,
+        Program::Module(module) => module.hashbang,
+    };
+    if let Some(hashbang) = hashbang {
+        generator.out.push_str("#!");
+        generator.out.push_str(hashbang);
+        generator.out.push('\n');
+    }
+    match program {
+        Program::Script(script) => {
+            for directive in &script.directives {
+                generator.directive(directive);
+                generator.out.push('\n');
+            }
+            for statement in &script.statements {
+                generator.statement(statement);
+                generator.out.push('\n');
+            }
+        }
+        Program::Module(module) => {
+            for directive in &module.directives {
+                generator.directive(directive);
+                generator.out.push('\n');
+            }
+            for item in &module.items {
+                generator.module_item(item);
+                generator.out.push('\n');
+            }
+        }
+    }
+    generator.out
+}
+
+// Operator precedence, from loosest to tightest. An expression is
+// parenthesized if its precedence is lower than its context requires.
+type Precedence = u8;
+const COMMA: Precedence = 0;
+const ASSIGNMENT: Precedence = 1;
+const CONDITIONAL: Precedence = 2;
+const COALESCE: Precedence = 3;
+const LOGICAL_OR: Precedence = 4;
+const LOGICAL_AND: Precedence = 5;
+const BITWISE_OR: Precedence = 6;
+const BITWISE_XOR: Precedence = 7;
+const BITWISE_AND: Precedence = 8;
+const EQUALITY: Precedence = 9;
+const RELATIONAL: Precedence = 10;
+const SHIFT: Precedence = 11;
+const ADDITIVE: Precedence = 12;
+const MULTIPLICATIVE: Precedence = 13;
+const EXPONENT: Precedence = 14;
+const UNARY: Precedence = 15;
+const UPDATE: Precedence = 16;
+const LEFT_HAND_SIDE: Precedence = 17;
+const PRIMARY: Precedence = 18;
+
+fn precedence(ast: &Expression) -> Precedence {
+    match ast {
+        Expression::BinaryExpression { operator, .. } => binary_operator(operator).1,
+        Expression::AssignmentExpression { .. }
+        | Expression::CompoundAssignmentExpression { .. }
+        | Expression::ArrowExpression { .. }
+        | Expression::YieldExpression { .. }
+        | Expression::YieldGeneratorExpression { .. } => ASSIGNMENT,
+        Expression::ConditionalExpression { .. } => CONDITIONAL,
+        Expression::UnaryExpression { .. } | Expression::AwaitExpression { .. } => UNARY,
+        Expression::UpdateExpression { .. } => UPDATE,
+        Expression::CallExpression(_)
+        | Expression::NewExpression { .. }
+        | Expression::MemberExpression(_)
+        | Expression::OptionalChain { .. }
+        | Expression::OptionalMember(_)
+        | Expression::OptionalCall(_)
+        | Expression::ImportCallExpression { .. } => LEFT_HAND_SIDE,
+        Expression::TemplateExpression(TemplateExpression { tag: Some(_), .. }) => LEFT_HAND_SIDE,
+        // The parser never produces negative numbers, but other passes may.
+        // They're generated as a unary minus.
+        Expression::LiteralNumericExpression { value, .. } if is_negative(*value) => UNARY,
+        _ => PRIMARY,
+    }
+}
+
+// True for negative numbers, including -0.
+fn is_negative(value: f64) -> bool {
+    value.is_sign_negative() && !value.is_nan()
+}
+
+fn binary_operator(ast: &BinaryOperator) -> (&'static str, Precedence) {
+    match ast {
+        BinaryOperator::Equals { .. } => ("==", EQUALITY),
+        BinaryOperator::NotEquals { .. } => ("!=", EQUALITY),
+        BinaryOperator::StrictEquals { .. } => ("===", EQUALITY),
+        BinaryOperator::StrictNotEquals { .. } => ("!==", EQUALITY),
+        BinaryOperator::LessThan { .. } => ("<", RELATIONAL),
+        BinaryOperator::LessThanOrEqual { .. } => ("<=", RELATIONAL),
+        BinaryOperator::GreaterThan { .. } => (">", RELATIONAL),
+        BinaryOperator::GreaterThanOrEqual { .. } => (">=", RELATIONAL),
+        BinaryOperator::In { .. } => ("in", RELATIONAL),
+        BinaryOperator::Instanceof { .. } => ("instanceof", RELATIONAL),
+        BinaryOperator::LeftShift { .. } => ("<<", SHIFT),
+        BinaryOperator::RightShift { .. } => (">>", SHIFT),
+        BinaryOperator::RightShiftExt { .. } => (">>>", SHIFT),
+        BinaryOperator::Add { .. } => ("+", ADDITIVE),
+        BinaryOperator::Sub { .. } => ("-", ADDITIVE),
+        BinaryOperator::Mul { .. } => ("*", MULTIPLICATIVE),
+        BinaryOperator::Div { .. } => ("/", MULTIPLICATIVE),
+        BinaryOperator::Mod { .. } => ("%", MULTIPLICATIVE),
+        BinaryOperator::Pow { .. } => ("**", EXPONENT),
+        BinaryOperator::Comma { .. } => (",", COMMA),
+        BinaryOperator::Coalesce { .. } => ("??", COALESCE),
+        BinaryOperator::LogicalOr { .. } => ("||", LOGICAL_OR),
+        BinaryOperator::LogicalAnd { .. } => ("&&", LOGICAL_AND),
+        BinaryOperator::BitwiseOr { .. } => ("|", BITWISE_OR),
+        BinaryOperator::BitwiseXor { .. } => ("^", BITWISE_XOR),
+        BinaryOperator::BitwiseAnd { .. } => ("&", BITWISE_AND),
+    }
+}
+
+fn unary_operator(ast: &UnaryOperator) -> &'static str {
+    match ast {
+        UnaryOperator::Plus { .. } => "+",
+        UnaryOperator::Minus { .. } => "-",
+        UnaryOperator::LogicalNot { .. } => "!",
+        UnaryOperator::BitwiseNot { .. } => "~",
+        UnaryOperator::Typeof { .. } => "typeof ",
+        UnaryOperator::Void { .. } => "void ",
+        UnaryOperator::Delete { .. } => "delete ",
+    }
+}
+
+fn update_operator(ast: &UpdateOperator) -> &'static str {
+    match ast {
+        UpdateOperator::Increment { .. } => "++",
+        UpdateOperator::Decrement { .. } => "--",
+    }
+}
+
+/// The flags of a regexp literal, in the order of `RegExp.prototype.flags`.
+/// Empty for other expressions.
+pub(crate) fn regexp_flags(ast: &Expression) -> String {
+    match ast {
+        Expression::LiteralRegExpExpression {
+            global,
+            ignore_case,
+            multi_line,
+            dot_all,
+            sticky,
+            unicode,
+            has_indices,
+            ..
+        } => [
+            (has_indices, 'd'),
+            (global, 'g'),
+            (ignore_case, 'i'),
+            (multi_line, 'm'),
+            (dot_all, 's'),
+            (unicode, 'u'),
+            (sticky, 'y'),
+        ]
+        .iter()
+        .filter(|(set, _)| **set)
+        .map(|(_, flag)| flag)
+        .collect(),
+        _ => String::new(),
+    }
+}
+
+fn compound_assignment_operator(ast: &CompoundAssignmentOperator) -> &'static str {
+    match ast {
+        CompoundAssignmentOperator::Add { .. } => "+=",
+        CompoundAssignmentOperator::Sub { .. } => "-=",
+        CompoundAssignmentOperator::Mul { .. } => "*=",
+        CompoundAssignmentOperator::Div { .. } => "/=",
+        CompoundAssignmentOperator::Mod { .. } => "%=",
+        CompoundAssignmentOperator::Pow { .. } => "**=",
+        CompoundAssignmentOperator::LeftShift { .. } => "<<=",
+        CompoundAssignmentOperator::RightShift { .. } => ">>=",
+        CompoundAssignmentOperator::RightShiftExt { .. } => ">>>=",
+        CompoundAssignmentOperator::Or { .. } => "|=",
+        CompoundAssignmentOperator::Xor { .. } => "^=",
+        CompoundAssignmentOperator::And { .. } => "&=",
+        CompoundAssignmentOperator::LogicalOr { .. } => "||=",
+        CompoundAssignmentOperator::LogicalAnd { .. } => "&&=",
+        CompoundAssignmentOperator::Coalesce { .. } => "??=",
+    }
+}
+
+fn is_coalesce(ast: &Expression) -> bool {
+    matches!(
+        ast,
+        Expression::BinaryExpression {
+            operator: BinaryOperator::Coalesce { .. },
+            ..
+        }
+    )
+}
+
+// True if `ast` contains an `in` operator, even inside a nested function.
+// The initializer of a `for` statement can't contain one unless it's
+// parenthesized.
+fn contains_in(ast: &Expression) -> bool {
+    struct ContainsIn(bool);
+
+    impl<'alloc> Visitor<'alloc> for ContainsIn {
+        fn visit_binary_operator(&mut self, ast: &BinaryOperator) {
+            if let BinaryOperator::In { .. } = ast {
+                self.0 = true;
+            }
+        }
+    }
+
+    let mut visitor = ContainsIn(false);
+    visitor.visit_expression(ast);
+    visitor.0
+}
+
+// True if a call or an optional chain is the leftmost part of a member
+// expression. The callee of `new` must be parenthesized in that case:
+// `new (f().g)()` is not `new f().g()`, and `new a?.b()` is a syntax error.
+fn contains_call(ast: &Expression) -> bool {
+    let object_contains_call = |object: &ExpressionOrSuper| match object {
+        ExpressionOrSuper::Expression(object) => contains_call(object),
+        ExpressionOrSuper::Super { .. } => false,
+    };
+    match ast {
+        Expression::CallExpression(_)
+        | Expression::ImportCallExpression { .. }
+        | Expression::OptionalChain { .. } => true,
+        Expression::MemberExpression(MemberExpression::ComputedMemberExpression(e)) => {
+            object_contains_call(&e.object)
+        }
+        Expression::MemberExpression(MemberExpression::StaticMemberExpression(e)) => {
+            object_contains_call(&e.object)
+        }
+        Expression::MemberExpression(MemberExpression::PrivateFieldExpression(e)) => {
+            contains_call(&e.object)
+        }
+        Expression::TemplateExpression(TemplateExpression { tag: Some(tag), .. }) => {
+            contains_call(tag)
+        }
+        _ => false,
+    }
+}
+
+// The minimum precedence of the object of a member expression, a callee or a
+// tag. A whole optional chain must be parenthesized there, since the rest of
+// the chain isn't skipped if it short-circuits: `(a?.b).c` is not `a?.b.c`.
+fn optional_chain_min(ast: &Expression) -> Precedence {
+    match ast {
+        Expression::OptionalChain { .. } => PRIMARY,
+        _ => LEFT_HAND_SIDE,
+    }
+}
+
+// True if an `else` following `ast` would be taken as part of it.
+fn ends_with_if_without_else(ast: &Statement) -> bool {
+    match ast {
+        Statement::IfStatement(IfStatement {
+            alternate: None, ..
+        }) => true,
+        Statement::IfStatement(IfStatement {
+            alternate: Some(body),
+            ..
+        })
+        | Statement::LabeledStatement { body, .. }
+        | Statement::WithStatement { body, .. }
+        | Statement::WhileStatement { block: body, .. }
+        | Statement::ForStatement { block: body, .. }
+        | Statement::ForInStatement { block: body, .. }
+        | Statement::ForOfStatement { block: body, .. } => ends_with_if_without_else(body),
+        _ => false,
+    }
+}
+
+// True if `source`, at the start of an expression statement, would be
+// parsed as something else.
+fn is_ambiguous_statement_start(source: &str) -> bool {
+    let starts_with_keyword = |keyword: &str| {
+        source.starts_with(keyword)
+            && !source[keyword.len()..].starts_with(|c: char| is_identifier_part(c))
+    };
+    source.starts_with('{')
+        || source.starts_with("let[")
+        || starts_with_keyword("function")
+        || starts_with_keyword("class")
+        || source.starts_with("async function")
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+fn is_identifier_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if is_identifier_start(c) => chars.all(is_identifier_part),
+        _ => false,
+    }
+}
+
+struct Generator {
+    out: String,
+    indent: usize,
+}
+
+impl Generator {
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    // If the text generated since `start` satisfies `parenthesize`, wrap it
+    // in parentheses.
+    fn parenthesize_from(&mut self, start: usize, parenthesize: impl Fn(&str) -> bool) {
+        if parenthesize(&self.out[start..]) {
+            self.out.insert(start, '(');
+            self.out.push(')');
+        }
+    }
+
+    fn string_literal(&mut self, value: &str) {
+        self.out.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                '\u{2028}' => self.out.push_str("\\u2028"),
+                '\u{2029}' => self.out.push_str("\\u2029"),
+                c if c < ' ' => self.out.push_str(&format!("\\x{:02x}", c as u32)),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn numeric_literal(&mut self, value: f64) {
+        if is_negative(value) {
+            self.out.push('-');
+        }
+        let value = value.abs();
+        if value.is_infinite() {
+            // Too large to be represented, like `Infinity`.
+            self.out.push_str("2e308");
+        } else {
+            self.out.push_str(&value.to_string());
+        }
+    }
+
+    fn directive(&mut self, ast: &Directive) {
+        let quote = if ast.raw_value.contains('"') {
+            '\''
+        } else {
+            '"'
+        };
+        self.out.push(quote);
+        self.out.push_str(ast.raw_value);
+        self.out.push(quote);
+        self.out.push(';');
+    }
+
+    // --- Statements ---
+
+    fn module_item(&mut self, ast: &ModuleItems) {
+        match ast {
+            ModuleItems::ImportDeclaration(import) => self.import_declaration(import),
+            ModuleItems::ExportDeclaration(export) => self.export_declaration(export),
+            ModuleItems::Statement(statement) => self.statement(statement),
+        }
+    }
+
+    fn import_declaration(&mut self, ast: &ImportDeclaration) {
+        self.out.push_str("import ");
+        let (module_specifier, attributes) = match ast {
+            ImportDeclaration::Import(import) => {
+                if let Some(binding) = &import.default_binding {
+                    self.out.push_str(binding.name.value);
+                    if !import.named_imports.is_empty() {
+                        self.out.push_str(", ");
+                    }
+                }
+                if !import.named_imports.is_empty() {
+                    self.out.push_str("{ ");
+                    for (i, specifier) in import.named_imports.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(", ");
+                        }
+                        if let Some(name) = &specifier.name {
+                            self.out.push_str(name.value);
+                            self.out.push_str(" as ");
+                        }
+                        self.out.push_str(specifier.binding.name.value);
+                    }
+                    self.out.push_str(" }");
+                }
+                if import.default_binding.is_some() || !import.named_imports.is_empty() {
+                    self.out.push_str(" from ");
+                }
+                (import.module_specifier, &import.attributes)
+            }
+            ImportDeclaration::ImportNamespace(import) => {
+                if let Some(binding) = &import.default_binding {
+                    self.out.push_str(binding.name.value);
+                    self.out.push_str(", ");
+                }
+                self.out.push_str("* as ");
+                self.out.push_str(import.namespace_binding.name.value);
+                self.out.push_str(" from ");
+                (import.module_specifier, &import.attributes)
+            }
+        };
+        self.string_literal(module_specifier);
+        self.import_attributes(attributes);
+        self.out.push(';');
+    }
+
+    // The legacy `assert` form is written as `with`.
+    fn import_attributes(&mut self, attributes: &[ImportAttribute]) {
+        if attributes.is_empty() {
+            return;
+        }
+        self.out.push_str(" with {");
+        for (i, attribute) in attributes.iter().enumerate() {
+            self.out.push_str(if i > 0 { ", " } else { " " });
+            if is_identifier_name(attribute.key) {
+                self.out.push_str(attribute.key);
+            } else {
+                self.string_literal(attribute.key);
+            }
+            self.out.push_str(": ");
+            self.string_literal(attribute.value);
+        }
+        self.out.push_str(" }");
+    }
+
+    fn export_declaration(&mut self, ast: &ExportDeclaration) {
+        self.out.push_str("export ");
+        match ast {
+            ExportDeclaration::ExportAllFrom(export) => {
+                self.out.push_str("* from ");
+                self.string_literal(export.module_specifier);
+                self.import_attributes(&export.attributes);
+                self.out.push(';');
+            }
+            ExportDeclaration::ExportFrom(export) => {
+                self.out.push('{');
+                for (i, specifier) in export.named_exports.iter().enumerate() {
+                    self.out.push_str(if i > 0 { ", " } else { " " });
+                    self.out.push_str(specifier.name.value);
+                    if let Some(exported_name) = &specifier.exported_name {
+                        self.out.push_str(" as ");
+                        self.out.push_str(exported_name.value);
+                    }
+                }
+                self.out.push_str(" } from ");
+                self.string_literal(export.module_specifier);
+                self.import_attributes(&export.attributes);
+                self.out.push(';');
+            }
+            ExportDeclaration::ExportLocals(export) => {
+                self.out.push('{');
+                for (i, specifier) in export.named_exports.iter().enumerate() {
+                    self.out.push_str(if i > 0 { ", " } else { " " });
+                    self.out.push_str(specifier.name.name.value);
+                    if let Some(exported_name) = &specifier.exported_name {
+                        self.out.push_str(" as ");
+                        self.out.push_str(exported_name.value);
+                    }
+                }
+                self.out.push_str(" };");
+            }
+            ExportDeclaration::Export(Export::FunctionDeclaration(function)) => {
+                self.function(function)
+            }
+            ExportDeclaration::Export(Export::ClassDeclaration(class)) => {
+                self.class(Some(&class.name), &class.super_, &class.elements)
+            }
+            ExportDeclaration::Export(Export::VariableDeclaration(declaration)) => {
+                self.variable_declaration(declaration, false);
+                self.out.push(';');
+            }
+            ExportDeclaration::ExportDefault(export) => {
+                self.out.push_str("default ");
+                match export {
+                    ExportDefault::FunctionDeclaration(function) => self.function(function),
+                    ExportDefault::ClassDeclaration(class) => {
+                        self.class(Some(&class.name), &class.super_, &class.elements)
+                    }
+                    ExportDefault::Expression(expression) => {
+                        let start = self.out.len();
+                        self.expression(expression, ASSIGNMENT);
+                        self.parenthesize_from(start, is_ambiguous_statement_start);
+                        self.out.push(';');
+                    }
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, ast: &Statement) {
+        match ast {
+            Statement::BlockStatement { block, .. } => self.block(block),
+            Statement::BreakStatement { label, .. } => {
+                self.out.push_str("break");
+                if let Some(label) = label {
+                    self.out.push(' ');
+                    self.out.push_str(label.value);
+                }
+                self.out.push(';');
+            }
+            Statement::ContinueStatement { label, .. } => {
+                self.out.push_str("continue");
+                if let Some(label) = label {
+                    self.out.push(' ');
+                    self.out.push_str(label.value);
+                }
+                self.out.push(';');
+            }
+            Statement::DebuggerStatement { .. } => self.out.push_str("debugger;"),
+            Statement::DoWhileStatement { block, test, .. } => {
+                self.out.push_str("do ");
+                self.statement(block);
+                self.out.push_str(" while (");
+                self.expression(test, COMMA);
+                self.out.push_str(");");
+            }
+            Statement::EmptyStatement { .. } => self.out.push(';'),
+            Statement::ExpressionStatement(expression) => {
+                let start = self.out.len();
+                self.expression(expression, COMMA);
+                if let Expression::LiteralStringExpression { .. } = **expression {
+                    // Not a directive.
+                    self.parenthesize_from(start, |_| true);
+                } else {
+                    self.parenthesize_from(start, is_ambiguous_statement_start);
+                }
+                self.out.push(';');
+            }
+            Statement::ForInStatement {
+                left, right, block, ..
+            } => {
+                self.out.push_str("for (");
+                self.for_in_or_of_head(left);
+                self.out.push_str(" in ");
+                self.expression(right, COMMA);
+                self.out.push_str(") ");
+                self.statement(block);
+            }
+            Statement::ForOfStatement {
+                left, right, block, ..
+            } => {
+                self.out.push_str("for (");
+                self.for_in_or_of_head(left);
+                self.out.push_str(" of ");
+                self.expression(right, ASSIGNMENT);
+                self.out.push_str(") ");
+                self.statement(block);
+            }
+            Statement::ForStatement {
+                init,
+                test,
+                update,
+                block,
+                ..
+            } => {
+                self.out.push_str("for (");
+                match init {
+                    Some(VariableDeclarationOrExpression::VariableDeclaration(declaration)) => {
+                        self.variable_declaration(declaration, true)
+                    }
+                    Some(VariableDeclarationOrExpression::Expression(expression)) => {
+                        let start = self.out.len();
+                        self.expression(expression, COMMA);
+                        let has_in = contains_in(expression);
+                        self.parenthesize_from(start, |source| {
+                            has_in || is_ambiguous_statement_start(source)
+                        });
+                    }
+                    None => {}
+                }
+                self.out.push(';');
+                if let Some(test) = test {
+                    self.out.push(' ');
+                    self.expression(test, COMMA);
+                }
+                self.out.push(';');
+                if let Some(update) = update {
+                    self.out.push(' ');
+                    self.expression(update, COMMA);
+                }
+                self.out.push_str(") ");
+                self.statement(block);
+            }
+            Statement::IfStatement(if_statement) => self.if_statement(if_statement),
+            Statement::LabeledStatement { label, body, .. } => {
+                self.out.push_str(label.value);
+                self.out.push_str(": ");
+                self.statement(body);
+            }
+            Statement::ReturnStatement { expression, .. } => {
+                self.out.push_str("return");
+                if let Some(expression) = expression {
+                    self.out.push(' ');
+                    self.expression(expression, COMMA);
+                }
+                self.out.push(';');
+            }
+            Statement::SwitchStatement {
+                discriminant,
+                cases,
+                ..
+            } => {
+                self.switch_head(discriminant);
+                for case in cases {
+                    self.switch_case(case);
+                }
+                self.switch_tail();
+            }
+            Statement::SwitchStatementWithDefault {
+                discriminant,
+                pre_default_cases,
+                default_case,
+                post_default_cases,
+                ..
+            } => {
+                self.switch_head(discriminant);
+                for case in pre_default_cases {
+                    self.switch_case(case);
+                }
+                self.newline();
+                self.out.push_str("default:");
+                self.case_body(&default_case.consequent);
+                for case in post_default_cases {
+                    self.switch_case(case);
+                }
+                self.switch_tail();
+            }
+            Statement::ThrowStatement { expression, .. } => {
+                self.out.push_str("throw ");
+                self.expression(expression, COMMA);
+                self.out.push(';');
+            }
+            Statement::TryCatchStatement {
+                body, catch_clause, ..
+            } => {
+                self.out.push_str("try ");
+                self.block(body);
+                self.catch_clause(catch_clause);
+            }
+            Statement::TryFinallyStatement {
+                body,
+                catch_clause,
+                finalizer,
+                ..
+            } => {
+                self.out.push_str("try ");
+                self.block(body);
+                if let Some(catch_clause) = catch_clause {
+                    self.catch_clause(catch_clause);
+                }
+                self.out.push_str(" finally ");
+                self.block(finalizer);
+            }
+            Statement::WhileStatement { test, block, .. } => {
+                self.out.push_str("while (");
+                self.expression(test, COMMA);
+                self.out.push_str(") ");
+                self.statement(block);
+            }
+            Statement::WithStatement { object, body, .. } => {
+                self.out.push_str("with (");
+                self.expression(object, COMMA);
+                self.out.push_str(") ");
+                self.statement(body);
+            }
+            Statement::VariableDeclarationStatement(declaration) => {
+                self.variable_declaration(declaration, false);
+                self.out.push(';');
+            }
+            Statement::FunctionDeclaration(function) => self.function(function),
+            Statement::ClassDeclaration(class) => {
+                self.class(Some(&class.name), &class.super_, &class.elements)
+            }
+            Statement::ErrorStatement { .. } => self.out.push_str("/* syntax error */;"),
+        }
+    }
+
+    fn block(&mut self, ast: &Block) {
+        self.statement_list_block(&[], &ast.statements);
+    }
+
+    // A `{ ... }` block with one statement per line.
+    fn statement_list_block(&mut self, directives: &[Directive], statements: &[Statement]) {
+        if directives.is_empty() && statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.indent += 1;
+        for directive in directives {
+            self.newline();
+            self.directive(directive);
+        }
+        for statement in statements {
+            self.newline();
+            self.statement(statement);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn if_statement(&mut self, ast: &IfStatement) {
+        self.out.push_str("if (");
+        self.expression(&ast.test, COMMA);
+        self.out.push_str(") ");
+        match &ast.alternate {
+            None => self.statement(&ast.consequent),
+            Some(alternate) => {
+                if ends_with_if_without_else(&ast.consequent) {
+                    self.out.push('{');
+                    self.indent += 1;
+                    self.newline();
+                    self.statement(&ast.consequent);
+                    self.indent -= 1;
+                    self.newline();
+                    self.out.push('}');
+                } else {
+                    self.statement(&ast.consequent);
+                }
+                self.out.push_str(" else ");
+                self.statement(alternate);
+            }
+        }
+    }
+
+    fn for_in_or_of_head(&mut self, ast: &VariableDeclarationOrAssignmentTarget) {
+        match ast {
+            VariableDeclarationOrAssignmentTarget::VariableDeclaration(declaration) => {
+                self.variable_declaration(declaration, false)
+            }
+            VariableDeclarationOrAssignmentTarget::AssignmentTarget(target) => {
+                self.assignment_target(target)
+            }
+        }
+    }
+
+    fn switch_head(&mut self, discriminant: &Expression) {
+        self.out.push_str("switch (");
+        self.expression(discriminant, COMMA);
+        self.out.push_str(") {");
+        self.indent += 1;
+    }
+
+    fn switch_tail(&mut self) {
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn switch_case(&mut self, ast: &SwitchCase) {
+        self.newline();
+        self.out.push_str("case ");
+        self.expression(&ast.test, COMMA);
+        self.out.push(':');
+        self.case_body(&ast.consequent);
+    }
+
+    fn case_body(&mut self, statements: &[Statement]) {
+        self.indent += 1;
+        for statement in statements {
+            self.newline();
+            self.statement(statement);
+        }
+        self.indent -= 1;
+    }
+
+    fn catch_clause(&mut self, ast: &CatchClause) {
+        self.out.push_str(" catch ");
+        if let Some(binding) = &ast.binding {
+            self.out.push('(');
+            self.binding(binding);
+            self.out.push_str(") ");
+        }
+        self.block(&ast.body);
+    }
+
+    // `no_in` is true in the head of a `for (;;)` statement, where
+    // initializers containing `in` must be parenthesized.
+    fn variable_declaration(&mut self, ast: &VariableDeclaration, no_in: bool) {
+        self.out.push_str(match ast.kind {
+            VariableDeclarationKind::Var { .. } => "var ",
+            VariableDeclarationKind::Let { .. } => "let ",
+            VariableDeclarationKind::Const { .. } => "const ",
+        });
+        for (i, declarator) in ast.declarators.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.binding(&declarator.binding);
+            if let Some(init) = &declarator.init {
+                self.out.push_str(" = ");
+                let min = if no_in && contains_in(init) {
+                    PRIMARY
+                } else {
+                    ASSIGNMENT
+                };
+                self.expression(init, min);
+            }
+        }
+    }
+
+    // --- Functions and classes ---
+
+    fn function(&mut self, ast: &Function) {
+        if ast.is_async {
+            self.out.push_str("async ");
+        }
+        self.out.push_str("function");
+        if ast.is_generator {
+            self.out.push('*');
+        }
+        if let Some(name) = &ast.name {
+            self.out.push(' ');
+            self.out.push_str(name.name.value);
+        }
+        self.formal_parameters(&ast.params);
+        self.out.push(' ');
+        self.function_body(&ast.body);
+    }
+
+    fn function_body(&mut self, ast: &FunctionBody) {
+        self.statement_list_block(&ast.directives, &ast.statements);
+    }
+
+    fn formal_parameters(&mut self, ast: &FormalParameters) {
+        self.out.push('(');
+        for (i, item) in ast.items.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.parameter(item);
+        }
+        if let Some(rest) = &ast.rest {
+            if !ast.items.is_empty() {
+                self.out.push_str(", ");
+            }
+            self.out.push_str("...");
+            self.binding(rest);
+        }
+        self.out.push(')');
+    }
+
+    fn class(
+        &mut self,
+        name: Option<&BindingIdentifier>,
+        super_: &Option<crate::arena::Box<Expression>>,
+        elements: &[crate::arena::Box<ClassElement>],
+    ) {
+        self.out.push_str("class");
+        if let Some(name) = name {
+            self.out.push(' ');
+            self.out.push_str(name.name.value);
+        }
+        if let Some(super_) = super_ {
+            self.out.push_str(" extends ");
+            self.expression(super_, LEFT_HAND_SIDE);
+        }
+        self.out.push(' ');
+        if elements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.indent += 1;
+        for element in elements {
+            self.newline();
+            match &**element {
+                ClassElement::MethodDefinition {
+                    is_static, method, ..
+                } => {
+                    if *is_static {
+                        self.out.push_str("static ");
+                    }
+                    self.method_definition(method);
+                }
+                ClassElement::FieldDefinition {
+                    is_static,
+                    name,
+                    init,
+                    ..
+                } => {
+                    if *is_static {
+                        self.out.push_str("static ");
+                    }
+                    match name {
+                        ClassElementName::ComputedPropertyName(name) => {
+                            self.computed_property_name(name)
+                        }
+                        ClassElementName::StaticPropertyName(name) => {
+                            self.static_property_name(name)
+                        }
+                        ClassElementName::PrivateFieldName(name) => self.out.push_str(name.value),
+                    }
+                    if let Some(init) = init {
+                        self.out.push_str(" = ");
+                        self.expression(init, ASSIGNMENT);
+                    }
+                    self.out.push(';');
+                }
+                ClassElement::StaticBlock { statements, .. } => {
+                    self.out.push_str("static ");
+                    self.statement_list_block(&[], statements);
+                }
+            }
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn method_definition(&mut self, ast: &MethodDefinition) {
+        match ast {
+            MethodDefinition::Method(method) => {
+                if method.is_async {
+                    self.out.push_str("async ");
+                }
+                if method.is_generator {
+                    self.out.push('*');
+                }
+                self.property_name(&method.name);
+                self.formal_parameters(&method.params);
+                self.out.push(' ');
+                self.function_body(&method.body);
+            }
+            MethodDefinition::Getter(getter) => {
+                self.out.push_str("get ");
+                self.property_name(&getter.property_name);
+                self.out.push_str("() ");
+                self.function_body(&getter.body);
+            }
+            MethodDefinition::Setter(setter) => {
+                self.out.push_str("set ");
+                self.property_name(&setter.property_name);
+                self.out.push('(');
+                self.parameter(&setter.param);
+                self.out.push_str(") ");
+                self.function_body(&setter.body);
+            }
+        }
+    }
+
+    fn property_name(&mut self, ast: &PropertyName) {
+        match ast {
+            PropertyName::ComputedPropertyName(name) => self.computed_property_name(name),
+            PropertyName::StaticPropertyName(name) => self.static_property_name(name),
+        }
+    }
+
+    fn computed_property_name(&mut self, ast: &ComputedPropertyName) {
+        self.out.push('[');
+        self.expression(&ast.expression, ASSIGNMENT);
+        self.out.push(']');
+    }
+
+    fn static_property_name(&mut self, ast: &StaticPropertyName) {
+        if is_identifier_name(ast.value) {
+            self.out.push_str(ast.value);
+        } else {
+            self.string_literal(ast.value);
+        }
+    }
+
+    // --- Bindings and assignment targets ---
+
+    fn binding(&mut self, ast: &Binding) {
+        match ast {
+            Binding::BindingIdentifier(binding) => self.out.push_str(binding.name.value),
+            Binding::BindingPattern(BindingPattern::ObjectBinding(pattern)) => {
+                self.out.push('{');
+                for (i, property) in pattern.properties.iter().enumerate() {
+                    self.out.push_str(if i > 0 { ", " } else { " " });
+                    match property {
+                        BindingProperty::BindingPropertyIdentifier(property) => {
+                            self.out.push_str(property.binding.name.value);
+                            if let Some(init) = &property.init {
+                                self.out.push_str(" = ");
+                                self.expression(init, ASSIGNMENT);
+                            }
+                        }
+                        BindingProperty::BindingPropertyProperty(property) => {
+                            self.property_name(&property.name);
+                            self.out.push_str(": ");
+                            self.parameter(&property.binding);
+                        }
+                    }
+                }
+                if let Some(rest) = &pattern.rest {
+                    self.out.push_str(if pattern.properties.is_empty() {
+                        " ..."
+                    } else {
+                        ", ..."
+                    });
+                    self.out.push_str(rest.name.value);
+                }
+                self.out.push_str(" }");
+            }
+            Binding::BindingPattern(BindingPattern::ArrayBinding(pattern)) => {
+                self.out.push('[');
+                for (i, element) in pattern.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    if let Some(element) = element {
+                        self.parameter(element);
+                    }
+                }
+                if let Some(rest) = &pattern.rest {
+                    if !pattern.elements.is_empty() {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str("...");
+                    self.binding(rest);
+                } else if let Some(None) = pattern.elements.last() {
+                    self.out.push(',');
+                }
+                self.out.push(']');
+            }
+        }
+    }
+
+    fn parameter(&mut self, ast: &Parameter) {
+        match ast {
+            Parameter::Binding(binding) => self.binding(binding),
+            Parameter::BindingWithDefault(binding) => {
+                self.binding(&binding.binding);
+                self.out.push_str(" = ");
+                self.expression(&binding.init, ASSIGNMENT);
+            }
+        }
+    }
+
+    fn assignment_target(&mut self, ast: &AssignmentTarget) {
+        match ast {
+            AssignmentTarget::SimpleAssignmentTarget(target) => {
+                self.simple_assignment_target(target)
+            }
+            AssignmentTarget::AssignmentTargetPattern(
+                AssignmentTargetPattern::ObjectAssignmentTarget(pattern),
+            ) => {
+                self.out.push('{');
+                for (i, property) in pattern.properties.iter().enumerate() {
+                    self.out.push_str(if i > 0 { ", " } else { " " });
+                    match property {
+                        AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(property) => {
+                            self.out.push_str(property.binding.name.value);
+                            if let Some(init) = &property.init {
+                                self.out.push_str(" = ");
+                                self.expression(init, ASSIGNMENT);
+                            }
+                        }
+                        AssignmentTargetProperty::AssignmentTargetPropertyProperty(property) => {
+                            self.property_name(&property.name);
+                            self.out.push_str(": ");
+                            self.assignment_target_maybe_default(&property.binding);
+                        }
+                    }
+                }
+                if let Some(rest) = &pattern.rest {
+                    self.out.push_str(if pattern.properties.is_empty() {
+                        " ..."
+                    } else {
+                        ", ..."
+                    });
+                    self.assignment_target(rest);
+                }
+                self.out.push_str(" }");
+            }
+            AssignmentTarget::AssignmentTargetPattern(
+                AssignmentTargetPattern::ArrayAssignmentTarget(pattern),
+            ) => {
+                self.out.push('[');
+                for (i, element) in pattern.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    if let Some(element) = element {
+                        self.assignment_target_maybe_default(element);
+                    }
+                }
+                if let Some(rest) = &pattern.rest {
+                    if !pattern.elements.is_empty() {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str("...");
+                    self.assignment_target(rest);
+                } else if let Some(None) = pattern.elements.last() {
+                    self.out.push(',');
+                }
+                self.out.push(']');
+            }
+        }
+    }
+
+    fn assignment_target_maybe_default(&mut self, ast: &AssignmentTargetMaybeDefault) {
+        match ast {
+            AssignmentTargetMaybeDefault::AssignmentTarget(target) => {
+                self.assignment_target(target)
+            }
+            AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(target) => {
+                self.assignment_target(&target.binding);
+                self.out.push_str(" = ");
+                self.expression(&target.init, ASSIGNMENT);
+            }
+        }
+    }
+
+    fn simple_assignment_target(&mut self, ast: &SimpleAssignmentTarget) {
+        match ast {
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(target) => {
+                self.out.push_str(target.name.value)
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::ComputedMemberAssignmentTarget(target),
+            ) => {
+                self.member_object(&target.object);
+                self.out.push('[');
+                self.expression(&target.expression, COMMA);
+                self.out.push(']');
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::StaticMemberAssignmentTarget(target),
+            ) => {
+                self.member_object(&target.object);
+                self.out.push('.');
+                self.out.push_str(target.property.value);
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::PrivateFieldAssignmentTarget(target),
+            ) => {
+                self.expression(&target.object, optional_chain_min(&target.object));
+                self.out.push('.');
+                self.out.push_str(target.field.value);
+            }
+        }
+    }
+
+    // --- Expressions ---
+
+    // Generate `ast`, parenthesized if its precedence is lower than `min`.
+    fn expression(&mut self, ast: &Expression, min: Precedence) {
+        if precedence(ast) < min {
+            self.out.push('(');
+            self.expression_unparenthesized(ast);
+            self.out.push(')');
+        } else {
+            self.expression_unparenthesized(ast);
+        }
+    }
+
+    fn expression_unparenthesized(&mut self, ast: &Expression) {
+        match ast {
+            Expression::MemberExpression(member) => self.member_expression(member, false),
+            Expression::OptionalChain { expression, .. } => {
+                self.expression(expression, LEFT_HAND_SIDE)
+            }
+            Expression::OptionalMember(member) => self.member_expression(member, true),
+            Expression::OptionalCall(call) => {
+                self.member_object(&call.callee);
+                self.out.push_str("?.");
+                self.arguments(&call.arguments);
+            }
+            Expression::ClassExpression(class) => {
+                self.class(class.name.as_ref(), &class.super_, &class.elements)
+            }
+            Expression::LiteralBigIntExpression { value, .. } => {
+                self.out.push_str(value);
+                self.out.push('n');
+            }
+            Expression::LiteralBooleanExpression { value, .. } => {
+                self.out.push_str(if *value { "true" } else { "false" })
+            }
+            Expression::LiteralInfinityExpression { .. } => self.out.push_str("2e308"),
+            Expression::LiteralNullExpression { .. } => self.out.push_str("null"),
+            Expression::LiteralNumericExpression { value, .. } => self.numeric_literal(*value),
+            Expression::LiteralRegExpExpression { pattern, .. } => {
+                self.out.push('/');
+                self.out.push_str(pattern);
+                self.out.push('/');
+                self.out.push_str(&regexp_flags(ast));
+            }
+            Expression::LiteralStringExpression { value, .. } => self.string_literal(value),
+            Expression::ArrayExpression(array) => {
+                self.out.push('[');
+                for (i, element) in array.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    match element {
+                        ArrayExpressionElement::SpreadElement(expression) => {
+                            self.out.push_str("...");
+                            self.expression(expression, ASSIGNMENT);
+                        }
+                        ArrayExpressionElement::Expression(expression) => {
+                            self.expression(expression, ASSIGNMENT)
+                        }
+                        ArrayExpressionElement::Elision { .. } => {}
+                    }
+                }
+                if let Some(ArrayExpressionElement::Elision { .. }) = array.elements.last() {
+                    self.out.push(',');
+                }
+                self.out.push(']');
+            }
+            Expression::ArrowExpression {
+                is_async,
+                params,
+                body,
+                ..
+            } => {
+                if *is_async {
+                    self.out.push_str("async ");
+                }
+                self.formal_parameters(params);
+                self.out.push_str(" => ");
+                match body {
+                    ArrowExpressionBody::FunctionBody(body) => self.function_body(body),
+                    ArrowExpressionBody::Expression(expression) => {
+                        let start = self.out.len();
+                        self.expression(expression, ASSIGNMENT);
+                        self.parenthesize_from(start, |source| source.starts_with('{'));
+                    }
+                }
+            }
+            Expression::AssignmentExpression {
+                binding,
+                expression,
+                ..
+            } => {
+                self.assignment_target(binding);
+                self.out.push_str(" = ");
+                self.expression(expression, ASSIGNMENT);
+            }
+            Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let (text, precedence) = binary_operator(operator);
+                let (left_min, right_min) = match operator {
+                    // Right-associative, and the left operand can't be a
+                    // unary expression: `(-a) ** b`.
+                    BinaryOperator::Pow { .. } => (UPDATE, EXPONENT),
+                    // Can't be mixed with `||` or `&&` without parentheses.
+                    BinaryOperator::Coalesce { .. } => {
+                        let left_min = if is_coalesce(left) {
+                            COALESCE
+                        } else {
+                            BITWISE_OR
+                        };
+                        (left_min, BITWISE_OR)
+                    }
+                    _ => (precedence, precedence + 1),
+                };
+                self.expression(left, left_min);
+                if let BinaryOperator::Comma { .. } = operator {
+                    self.out.push_str(", ");
+                } else {
+                    self.out.push(' ');
+                    self.out.push_str(text);
+                    self.out.push(' ');
+                }
+                self.expression(right, right_min);
+            }
+            Expression::CallExpression(call) => {
+                self.member_object(&call.callee);
+                self.arguments(&call.arguments);
+            }
+            Expression::CompoundAssignmentExpression {
+                operator,
+                binding,
+                expression,
+                ..
+            } => {
+                self.simple_assignment_target(binding);
+                self.out.push(' ');
+                self.out.push_str(compound_assignment_operator(operator));
+                self.out.push(' ');
+                self.expression(expression, ASSIGNMENT);
+            }
+            Expression::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+                ..
+            } => {
+                self.expression(test, COALESCE);
+                self.out.push_str(" ? ");
+                self.expression(consequent, ASSIGNMENT);
+                self.out.push_str(" : ");
+                self.expression(alternate, ASSIGNMENT);
+            }
+            Expression::FunctionExpression(function) => self.function(function),
+            Expression::IdentifierExpression(identifier) => {
+                self.out.push_str(identifier.name.value)
+            }
+            Expression::NewExpression {
+                callee, arguments, ..
+            } => {
+                self.out.push_str("new ");
+                let min = if contains_call(callee) {
+                    PRIMARY
+                } else {
+                    LEFT_HAND_SIDE
+                };
+                self.expression(callee, min);
+                self.arguments(arguments);
+            }
+            Expression::NewTargetExpression { .. } => self.out.push_str("new.target"),
+            Expression::ImportMetaExpression { .. } => self.out.push_str("import.meta"),
+            Expression::ObjectExpression(object) => {
+                if object.properties.is_empty() {
+                    self.out.push_str("{}");
+                    return;
+                }
+                self.out.push('{');
+                for (i, property) in object.properties.iter().enumerate() {
+                    self.out.push_str(if i > 0 { ", " } else { " " });
+                    self.object_property(property);
+                }
+                self.out.push_str(" }");
+            }
+            Expression::UnaryExpression {
+                operator, operand, ..
+            } => {
+                let text = unary_operator(operator);
+                self.out.push_str(text);
+                let start = self.out.len();
+                self.expression(operand, UNARY);
+                // `- -a` and `+ ++a`, not `--a` and `+++a`.
+                if (text == "-" || text == "+") && self.out[start..].starts_with(text) {
+                    self.out.insert(start, ' ');
+                }
+            }
+            Expression::TemplateExpression(template) => {
+                if let Some(tag) = &template.tag {
+                    self.expression(tag, optional_chain_min(tag));
+                }
+                self.out.push('`');
+                for element in &template.elements {
+                    match element {
+                        TemplateExpressionElement::Expression(expression) => {
+                            self.out.push_str("${");
+                            self.expression(expression, COMMA);
+                            self.out.push('}');
+                        }
+                        // The raw value is the source text, with its escape
+                        // sequences.
+                        TemplateExpressionElement::TemplateElement(element) => {
+                            self.out.push_str(element.raw_value)
+                        }
+                    }
+                }
+                self.out.push('`');
+            }
+            Expression::ThisExpression { .. } => self.out.push_str("this"),
+            Expression::UpdateExpression {
+                is_prefix,
+                operator,
+                operand,
+                ..
+            } => {
+                if *is_prefix {
+                    self.out.push_str(update_operator(operator));
+                    self.simple_assignment_target(operand);
+                } else {
+                    self.simple_assignment_target(operand);
+                    self.out.push_str(update_operator(operator));
+                }
+            }
+            Expression::YieldExpression { expression, .. } => {
+                self.out.push_str("yield");
+                if let Some(expression) = expression {
+                    self.out.push(' ');
+                    self.expression(expression, ASSIGNMENT);
+                }
+            }
+            Expression::YieldGeneratorExpression { expression, .. } => {
+                self.out.push_str("yield* ");
+                self.expression(expression, ASSIGNMENT);
+            }
+            Expression::AwaitExpression { expression, .. } => {
+                self.out.push_str("await ");
+                self.expression(expression, UNARY);
+            }
+            Expression::ImportCallExpression {
+                argument, options, ..
+            } => {
+                self.out.push_str("import(");
+                self.expression(argument, ASSIGNMENT);
+                if let Some(options) = options {
+                    self.out.push_str(", ");
+                    self.expression(options, ASSIGNMENT);
+                }
+                self.out.push(')');
+            }
+        }
+    }
+
+    // `optional` is true for the links of an optional chain that start with
+    // `?.`.
+    fn member_expression(&mut self, ast: &MemberExpression, optional: bool) {
+        let dot = if optional { "?." } else { "." };
+        match ast {
+            MemberExpression::ComputedMemberExpression(member) => {
+                self.member_object(&member.object);
+                if optional {
+                    self.out.push_str("?.");
+                }
+                self.out.push('[');
+                self.expression(&member.expression, COMMA);
+                self.out.push(']');
+            }
+            MemberExpression::StaticMemberExpression(member) => {
+                self.member_object(&member.object);
+                self.out.push_str(dot);
+                self.out.push_str(member.property.value);
+            }
+            MemberExpression::PrivateFieldExpression(member) => {
+                self.expression(&member.object, optional_chain_min(&member.object));
+                self.out.push_str(dot);
+                self.out.push_str(member.field.value);
+            }
+        }
+    }
+
+    // The object of a member expression, or the callee of a call.
+    fn member_object(&mut self, ast: &ExpressionOrSuper) {
+        match ast {
+            ExpressionOrSuper::Expression(expression) => {
+                if let Expression::LiteralNumericExpression { .. } = **expression {
+                    // `(1).x`, not `1.x`.
+                    self.expression(expression, PRIMARY + 1);
+                } else {
+                    self.expression(expression, optional_chain_min(expression));
+                }
+            }
+            ExpressionOrSuper::Super { .. } => self.out.push_str("super"),
+        }
+    }
+
+    fn arguments(&mut self, ast: &Arguments) {
+        self.out.push('(');
+        for (i, argument) in ast.args.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            match argument {
+                Argument::SpreadElement(expression) => {
+                    self.out.push_str("...");
+                    self.expression(expression, ASSIGNMENT);
+                }
+                Argument::Expression(expression) => self.expression(expression, ASSIGNMENT),
+            }
+        }
+        self.out.push(')');
+    }
+
+    fn object_property(&mut self, ast: &ObjectProperty) {
+        match ast {
+            ObjectProperty::NamedObjectProperty(NamedObjectProperty::MethodDefinition(method)) => {
+                self.method_definition(method)
+            }
+            ObjectProperty::NamedObjectProperty(NamedObjectProperty::DataProperty(property)) => {
+                self.property_name(&property.property_name);
+                self.out.push_str(": ");
+                self.expression(&property.expression, ASSIGNMENT);
+            }
+            ObjectProperty::ShorthandProperty(property) => {
+                self.out.push_str(property.name.name.value)
+            }
+            ObjectProperty::SpreadProperty(expression) => {
+                self.out.push_str("...");
+                self.expression(expression, ASSIGNMENT);
+            }
+        }
+    }
+}
@@ -0,0 +1,652 @@
+//! Conversion to ESTree, the JSON AST format read by acorn, babel and other
+//! JavaScript tools: <https://github.com/estree/estree>
+//!
+//! Every node has a `type` and `start` and `end` source offsets, as acorn
+//! produces. Literals have no `raw` property, since the source text isn't
+//! available here.
+//!
+//! Not all nodes are mapped yet. Any other node is converted to
+//! `{"type": "Unsupported", "node": <name of the jsparagus type>, "start",
+//! "end"}` instead, without its children. These are:
+//!
+//! - import and export declarations
+//! - statements other than blocks, `if`, `return`, and empty, expression,
+//!   variable and function declaration statements
+//! - array, object, arrow function, class, `await`, `new.target`,
+//!   `import.meta` and `Infinity` expressions
+//! - destructuring patterns in bindings and assignment targets
+
+use crate::codegen::regexp_flags;
+use crate::source_location_accessor::SourceLocationAccessor;
+use crate::types::*;
+use crate::SourceLocation;
+use serde_json::{json, Value};
+
+/// Convert a program to an ESTree `Program` node.
+pub fn to_estree(program: &Program) -> Value {
+    match program {
+        Program::Script(script) => {
+            let mut body: Vec<Value> = script.directives.iter().map(directive).collect();
+            body.extend(script.statements.iter().map(statement));
+            node(
+                "Program",
+                script.loc,
+                json!({ "sourceType": "script", "body": body }),
+            )
+        }
+        Program::Module(module) => {
+            let mut body: Vec<Value> = module.directives.iter().map(directive).collect();
+            body.extend(module.items.iter().map(|item| match item {
+                ModuleItems::Statement(s) => statement(s),
+                ModuleItems::ImportDeclaration(_) => unsupported("ImportDeclaration", item),
+                ModuleItems::ExportDeclaration(_) => unsupported("ExportDeclaration", item),
+            }));
+            node(
+                "Program",
+                module.loc,
+                json!({ "sourceType": "module", "body": body }),
+            )
+        }
+    }
+}
+
+/// An ESTree node with the given type and location. `fields` must be an
+/// object.
+fn node(type_: &str, loc: SourceLocation, fields: Value) -> Value {
+    let mut object = match fields {
+        Value::Object(object) => object,
+        _ => panic!("ESTree node fields must be an object"),
+    };
+    object.insert("type".to_string(), json!(type_));
+    object.insert("start".to_string(), json!(loc.start));
+    object.insert("end".to_string(), json!(loc.end));
+    Value::Object(object)
+}
+
+fn unsupported<'alloc>(name: &str, ast: &impl SourceLocationAccessor<'alloc>) -> Value {
+    node("Unsupported", ast.get_loc(), json!({ "node": name }))
+}
+
+fn directive(ast: &Directive) -> Value {
+    let literal = node("Literal", ast.loc, json!({ "value": ast.raw_value }));
+    node(
+        "ExpressionStatement",
+        ast.loc,
+        json!({ "expression": literal, "directive": ast.raw_value }),
+    )
+}
+
+fn statement(ast: &Statement) -> Value {
+    match ast {
+        Statement::BlockStatement { block, .. } => self::block(block),
+        Statement::EmptyStatement { loc } => node("EmptyStatement", *loc, json!({})),
+        Statement::ExpressionStatement(expr) => node(
+            "ExpressionStatement",
+            ast.get_loc(),
+            json!({ "expression": expression(expr) }),
+        ),
+        Statement::IfStatement(if_statement) => node(
+            "IfStatement",
+            if_statement.loc,
+            json!({
+                "test": expression(&if_statement.test),
+                "consequent": statement(&if_statement.consequent),
+                "alternate": if_statement.alternate.as_ref().map(|s| statement(s)),
+            }),
+        ),
+        Statement::ReturnStatement {
+            expression: argument,
+            loc,
+        } => node(
+            "ReturnStatement",
+            *loc,
+            json!({ "argument": argument.as_ref().map(|e| expression(e)) }),
+        ),
+        Statement::VariableDeclarationStatement(declaration) => variable_declaration(declaration),
+        Statement::FunctionDeclaration(function) => self::function("FunctionDeclaration", function),
+        Statement::BreakStatement { .. } => unsupported("BreakStatement", ast),
+        Statement::ContinueStatement { .. } => unsupported("ContinueStatement", ast),
+        Statement::DebuggerStatement { .. } => unsupported("DebuggerStatement", ast),
+        Statement::DoWhileStatement { .. } => unsupported("DoWhileStatement", ast),
+        Statement::ForInStatement { .. } => unsupported("ForInStatement", ast),
+        Statement::ForOfStatement { .. } => unsupported("ForOfStatement", ast),
+        Statement::ForStatement { .. } => unsupported("ForStatement", ast),
+        Statement::LabeledStatement { .. } => unsupported("LabeledStatement", ast),
+        Statement::SwitchStatement { .. } => unsupported("SwitchStatement", ast),
+        Statement::SwitchStatementWithDefault { .. } => {
+            unsupported("SwitchStatementWithDefault", ast)
+        }
+        Statement::ThrowStatement { .. } => unsupported("ThrowStatement", ast),
+        Statement::TryCatchStatement { .. } => unsupported("TryCatchStatement", ast),
+        Statement::TryFinallyStatement { .. } => unsupported("TryFinallyStatement", ast),
+        Statement::WhileStatement { .. } => unsupported("WhileStatement", ast),
+        Statement::WithStatement { .. } => unsupported("WithStatement", ast),
+        Statement::ClassDeclaration(_) => unsupported("ClassDeclaration", ast),
+        Statement::ErrorStatement { .. } => unsupported("ErrorStatement", ast),
+    }
+}
+
+fn block(ast: &Block) -> Value {
+    let body: Vec<Value> = ast.statements.iter().map(statement).collect();
+    node("BlockStatement", ast.loc, json!({ "body": body }))
+}
+
+fn variable_declaration(ast: &VariableDeclaration) -> Value {
+    let kind = match ast.kind {
+        VariableDeclarationKind::Var { .. } => "var",
+        VariableDeclarationKind::Let { .. } => "let",
+        VariableDeclarationKind::Const { .. } => "const",
+    };
+    let declarations: Vec<Value> = ast
+        .declarators
+        .iter()
+        .map(|declarator| {
+            node(
+                "VariableDeclarator",
+                declarator.loc,
+                json!({
+                    "id": binding(&declarator.binding),
+                    "init": declarator.init.as_ref().map(|e| expression(e)),
+                }),
+            )
+        })
+        .collect();
+    node(
+        "VariableDeclaration",
+        ast.loc,
+        json!({ "kind": kind, "declarations": declarations }),
+    )
+}
+
+/// A `FunctionDeclaration` or `FunctionExpression`.
+fn function(type_: &str, ast: &Function) -> Value {
+    let mut body: Vec<Value> = ast.body.directives.iter().map(directive).collect();
+    body.extend(ast.body.statements.iter().map(statement));
+    node(
+        type_,
+        ast.loc,
+        json!({
+            "id": ast.name.as_ref().map(binding_identifier),
+            "params": formal_parameters(&ast.params),
+            "body": node("BlockStatement", ast.body.loc, json!({ "body": body })),
+            "generator": ast.is_generator,
+            "async": ast.is_async,
+            "expression": false,
+        }),
+    )
+}
+
+fn formal_parameters(ast: &FormalParameters) -> Vec<Value> {
+    let mut params: Vec<Value> = ast.items.iter().map(parameter).collect();
+    if let Some(rest) = &ast.rest {
+        params.push(node(
+            "RestElement",
+            rest.get_loc(),
+            json!({ "argument": binding(rest) }),
+        ));
+    }
+    params
+}
+
+fn parameter(ast: &Parameter) -> Value {
+    match ast {
+        Parameter::Binding(b) => binding(b),
+        Parameter::BindingWithDefault(b) => node(
+            "AssignmentPattern",
+            b.loc,
+            json!({ "left": binding(&b.binding), "right": expression(&b.init) }),
+        ),
+    }
+}
+
+fn binding(ast: &Binding) -> Value {
+    match ast {
+        Binding::BindingIdentifier(b) => binding_identifier(b),
+        Binding::BindingPattern(_) => unsupported("BindingPattern", ast),
+    }
+}
+
+fn binding_identifier(ast: &BindingIdentifier) -> Value {
+    identifier(&ast.name)
+}
+
+fn identifier(ast: &Identifier) -> Value {
+    node("Identifier", ast.loc, json!({ "name": ast.value }))
+}
+
+fn expression(ast: &Expression) -> Value {
+    match ast {
+        Expression::IdentifierExpression(e) => identifier(&e.name),
+        Expression::ThisExpression { loc } => node("ThisExpression", *loc, json!({})),
+        Expression::LiteralBooleanExpression { value, loc } => {
+            node("Literal", *loc, json!({ "value": value }))
+        }
+        Expression::LiteralNullExpression { loc } => {
+            node("Literal", *loc, json!({ "value": null }))
+        }
+        Expression::LiteralNumericExpression { value, loc } => {
+            node("Literal", *loc, json!({ "value": value }))
+        }
+        // JSON has no BigInt values.
+        Expression::LiteralBigIntExpression { value, loc } => {
+            node("Literal", *loc, json!({ "value": null, "bigint": value }))
+        }
+        Expression::LiteralStringExpression { value, loc } => {
+            node("Literal", *loc, json!({ "value": value }))
+        }
+        Expression::LiteralRegExpExpression { pattern, loc, .. } => node(
+            "Literal",
+            *loc,
+            json!({ "value": null, "regex": { "pattern": pattern, "flags": regexp_flags(ast) } }),
+        ),
+        Expression::BinaryExpression {
+            operator: BinaryOperator::Comma { .. },
+            loc,
+            ..
+        } => {
+            let mut expressions = Vec::new();
+            sequence_expressions(ast, &mut expressions);
+            node(
+                "SequenceExpression",
+                *loc,
+                json!({ "expressions": expressions }),
+            )
+        }
+        Expression::BinaryExpression {
+            operator,
+            left,
+            right,
+            loc,
+        } => {
+            let type_ = match operator {
+                BinaryOperator::LogicalOr { .. }
+                | BinaryOperator::LogicalAnd { .. }
+                | BinaryOperator::Coalesce { .. } => "LogicalExpression",
+                _ => "BinaryExpression",
+            };
+            node(
+                type_,
+                *loc,
+                json!({
+                    "operator": binary_operator(operator),
+                    "left": expression(left),
+                    "right": expression(right),
+                }),
+            )
+        }
+        Expression::UnaryExpression {
+            operator,
+            operand,
+            loc,
+        } => node(
+            "UnaryExpression",
+            *loc,
+            json!({
+                "operator": unary_operator(operator),
+                "prefix": true,
+                "argument": expression(operand),
+            }),
+        ),
+        Expression::UpdateExpression {
+            is_prefix,
+            operator,
+            operand,
+            loc,
+        } => {
+            let operator = match operator {
+                UpdateOperator::Increment { .. } => "++",
+                UpdateOperator::Decrement { .. } => "--",
+            };
+            node(
+                "UpdateExpression",
+                *loc,
+                json!({
+                    "operator": operator,
+                    "prefix": is_prefix,
+                    "argument": simple_assignment_target(operand),
+                }),
+            )
+        }
+        Expression::AssignmentExpression {
+            binding,
+            expression: right,
+            loc,
+        } => node(
+            "AssignmentExpression",
+            *loc,
+            json!({
+                "operator": "=",
+                "left": assignment_target(binding),
+                "right": expression(right),
+            }),
+        ),
+        Expression::CompoundAssignmentExpression {
+            operator,
+            binding,
+            expression: right,
+            loc,
+        } => node(
+            "AssignmentExpression",
+            *loc,
+            json!({
+                "operator": compound_assignment_operator(operator),
+                "left": simple_assignment_target(binding),
+                "right": expression(right),
+            }),
+        ),
+        Expression::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+            loc,
+        } => node(
+            "ConditionalExpression",
+            *loc,
+            json!({
+                "test": expression(test),
+                "consequent": expression(consequent),
+                "alternate": expression(alternate),
+            }),
+        ),
+        Expression::CallExpression(call) => call_expression(call, false),
+        Expression::OptionalCall(call) => call_expression(call, true),
+        Expression::OptionalChain { expression: e, loc } => node(
+            "ChainExpression",
+            *loc,
+            json!({ "expression": expression(e) }),
+        ),
+        Expression::NewExpression {
+            callee,
+            arguments: args,
+            loc,
+        } => node(
+            "NewExpression",
+            *loc,
+            json!({ "callee": expression(callee), "arguments": arguments(args) }),
+        ),
+        Expression::MemberExpression(member) => member_expression(member, false),
+        Expression::OptionalMember(member) => member_expression(member, true),
+        Expression::FunctionExpression(function) => self::function("FunctionExpression", function),
+        Expression::ClassExpression(_) => unsupported("ClassExpression", ast),
+        Expression::LiteralInfinityExpression { .. } => {
+            unsupported("LiteralInfinityExpression", ast)
+        }
+        Expression::ArrayExpression(_) => unsupported("ArrayExpression", ast),
+        Expression::ArrowExpression { .. } => unsupported("ArrowExpression", ast),
+        Expression::NewTargetExpression { .. } => unsupported("NewTargetExpression", ast),
+        Expression::ImportMetaExpression { .. } => unsupported("ImportMetaExpression", ast),
+        Expression::ObjectExpression(_) => unsupported("ObjectExpression", ast),
+        Expression::TemplateExpression(template) => template_expression(template),
+        Expression::YieldExpression {
+            expression: argument,
+            loc,
+        } => node(
+            "YieldExpression",
+            *loc,
+            json!({
+                "argument": argument.as_ref().map(|argument| expression(argument)),
+                "delegate": false,
+            }),
+        ),
+        Expression::YieldGeneratorExpression {
+            expression: argument,
+            loc,
+        } => node(
+            "YieldExpression",
+            *loc,
+            json!({ "argument": expression(argument), "delegate": true }),
+        ),
+        Expression::AwaitExpression { .. } => unsupported("AwaitExpression", ast),
+        Expression::ImportCallExpression {
+            argument,
+            options,
+            loc,
+        } => node(
+            "ImportExpression",
+            *loc,
+            json!({
+                "source": expression(argument),
+                "options": options.as_deref().map(expression),
+            }),
+        ),
+    }
+}
+
+fn template_expression(ast: &TemplateExpression) -> Value {
+    let mut quasis = vec![];
+    let mut expressions = vec![];
+    for (i, element) in ast.elements.iter().enumerate() {
+        match element {
+            TemplateExpressionElement::TemplateElement(element) => {
+                // The element's location includes the `` ` ``, `}` or `${`
+                // around it, but ESTree's doesn't.
+                let tail = i == ast.elements.len() - 1;
+                let end = element.loc.end - if tail { 1 } else { 2 };
+                quasis.push(node(
+                    "TemplateElement",
+                    SourceLocation::new(element.loc.start + 1, end),
+                    json!({
+                        "value": { "raw": element.raw_value, "cooked": element.cooked_value },
+                        "tail": tail,
+                    }),
+                ));
+            }
+            TemplateExpressionElement::Expression(e) => expressions.push(expression(e)),
+        }
+    }
+
+    match &ast.tag {
+        None => node(
+            "TemplateLiteral",
+            ast.loc,
+            json!({ "quasis": quasis, "expressions": expressions }),
+        ),
+        Some(tag) => {
+            let first = ast.elements.first().unwrap().get_loc();
+            let last = ast.elements.last().unwrap().get_loc();
+            let quasi = node(
+                "TemplateLiteral",
+                SourceLocation::from_parts(first, last),
+                json!({ "quasis": quasis, "expressions": expressions }),
+            );
+            node(
+                "TaggedTemplateExpression",
+                ast.loc,
+                json!({ "tag": expression(tag), "quasi": quasi }),
+            )
+        }
+    }
+}
+
+// `a, b, c` is parsed as `(a, b), c`, but ESTree has a single
+// SequenceExpression with all three.
+fn sequence_expressions(ast: &Expression, expressions: &mut Vec<Value>) {
+    match ast {
+        Expression::BinaryExpression {
+            operator: BinaryOperator::Comma { .. },
+            left,
+            right,
+            ..
+        } => {
+            sequence_expressions(left, expressions);
+            sequence_expressions(right, expressions);
+        }
+        _ => expressions.push(expression(ast)),
+    }
+}
+
+fn call_expression(ast: &CallExpression, optional: bool) -> Value {
+    node(
+        "CallExpression",
+        ast.loc,
+        json!({
+            "callee": expression_or_super(&ast.callee),
+            "arguments": arguments(&ast.arguments),
+            "optional": optional,
+        }),
+    )
+}
+
+fn member_expression(ast: &MemberExpression, optional: bool) -> Value {
+    let (object, property, computed) = match ast {
+        MemberExpression::ComputedMemberExpression(e) => (
+            expression_or_super(&e.object),
+            expression(&e.expression),
+            true,
+        ),
+        MemberExpression::StaticMemberExpression(e) => (
+            expression_or_super(&e.object),
+            identifier_name(&e.property),
+            false,
+        ),
+        MemberExpression::PrivateFieldExpression(e) => {
+            (expression(&e.object), private_identifier(&e.field), false)
+        }
+    };
+    node(
+        "MemberExpression",
+        ast.get_loc(),
+        json!({
+            "object": object,
+            "property": property,
+            "computed": computed,
+            "optional": optional,
+        }),
+    )
+}
+
+fn private_identifier(ast: &PrivateIdentifier) -> Value {
+    node(
+        "PrivateIdentifier",
+        ast.loc,
+        json!({ "name": ast.value.trim_start_matches('#') }),
+    )
+}
+
+fn identifier_name(ast: &IdentifierName) -> Value {
+    node("Identifier", ast.loc, json!({ "name": ast.value }))
+}
+
+fn expression_or_super(ast: &ExpressionOrSuper) -> Value {
+    match ast {
+        ExpressionOrSuper::Expression(e) => expression(e),
+        ExpressionOrSuper::Super { loc } => node("Super", *loc, json!({})),
+    }
+}
+
+fn arguments(ast: &Arguments) -> Vec<Value> {
+    ast.args
+        .iter()
+        .map(|arg| match arg {
+            Argument::Expression(e) => expression(e),
+            Argument::SpreadElement(e) => node(
+                "SpreadElement",
+                arg.get_loc(),
+                json!({ "argument": expression(e) }),
+            ),
+        })
+        .collect()
+}
+
+fn assignment_target(ast: &AssignmentTarget) -> Value {
+    match ast {
+        AssignmentTarget::SimpleAssignmentTarget(target) => simple_assignment_target(target),
+        AssignmentTarget::AssignmentTargetPattern(_) => unsupported("AssignmentTargetPattern", ast),
+    }
+}
+
+fn simple_assignment_target(ast: &SimpleAssignmentTarget) -> Value {
+    match ast {
+        SimpleAssignmentTarget::AssignmentTargetIdentifier(target) => identifier(&target.name),
+        SimpleAssignmentTarget::MemberAssignmentTarget(target) => {
+            let (object, property, computed) = match target {
+                MemberAssignmentTarget::ComputedMemberAssignmentTarget(t) => (
+                    expression_or_super(&t.object),
+                    expression(&t.expression),
+                    true,
+                ),
+                MemberAssignmentTarget::StaticMemberAssignmentTarget(t) => (
+                    expression_or_super(&t.object),
+                    identifier_name(&t.property),
+                    false,
+                ),
+                MemberAssignmentTarget::PrivateFieldAssignmentTarget(t) => {
+                    (expression(&t.object), private_identifier(&t.field), false)
+                }
+            };
+            node(
+                "MemberExpression",
+                target.get_loc(),
+                json!({
+                    "object": object,
+                    "property": property,
+                    "computed": computed,
+                    "optional": false,
+                }),
+            )
+        }
+    }
+}
+
+fn binary_operator(ast: &BinaryOperator) -> &'static str {
+    match ast {
+        BinaryOperator::Equals { .. } => "==",
+        BinaryOperator::NotEquals { .. } => "!=",
+        BinaryOperator::StrictEquals { .. } => "===",
+        BinaryOperator::StrictNotEquals { .. } => "!==",
+        BinaryOperator::LessThan { .. } => "<",
+        BinaryOperator::LessThanOrEqual { .. } => "<=",
+        BinaryOperator::GreaterThan { .. } => ">",
+        BinaryOperator::GreaterThanOrEqual { .. } => ">=",
+        BinaryOperator::In { .. } => "in",
+        BinaryOperator::Instanceof { .. } => "instanceof",
+        BinaryOperator::LeftShift { .. } => "<<",
+        BinaryOperator::RightShift { .. } => ">>",
+        BinaryOperator::RightShiftExt { .. } => ">>>",
+        BinaryOperator::Add { .. } => "+",
+        BinaryOperator::Sub { .. } => "-",
+        BinaryOperator::Mul { .. } => "*",
+        BinaryOperator::Div { .. } => "/",
+        BinaryOperator::Mod { .. } => "%",
+        BinaryOperator::Pow { .. } => "**",
+        BinaryOperator::Comma { .. } => ",",
+        BinaryOperator::Coalesce { .. } => "??",
+        BinaryOperator::LogicalOr { .. } => "||",
+        BinaryOperator::LogicalAnd { .. } => "&&",
+        BinaryOperator::BitwiseOr { .. } => "|",
+        BinaryOperator::BitwiseXor { .. } => "^",
+        BinaryOperator::BitwiseAnd { .. } => "&",
+    }
+}
+
+fn unary_operator(ast: &UnaryOperator) -> &'static str {
+    match ast {
+        UnaryOperator::Plus { .. } => "+",
+        UnaryOperator::Minus { .. } => "-",
+        UnaryOperator::LogicalNot { .. } => "!",
+        UnaryOperator::BitwiseNot { .. } => "~",
+        UnaryOperator::Typeof { .. } => "typeof",
+        UnaryOperator::Void { .. } => "void",
+        UnaryOperator::Delete { .. } => "delete",
+    }
+}
+
+fn compound_assignment_operator(ast: &CompoundAssignmentOperator) -> &'static str {
+    match ast {
+        CompoundAssignmentOperator::Add { .. } => "+=",
+        CompoundAssignmentOperator::Sub { .. } => "-=",
+        CompoundAssignmentOperator::Mul { .. } => "*=",
+        CompoundAssignmentOperator::Div { .. } => "/=",
+        CompoundAssignmentOperator::Mod { .. } => "%=",
+        CompoundAssignmentOperator::Pow { .. } => "**=",
+        CompoundAssignmentOperator::LeftShift { .. } => "<<=",
+        CompoundAssignmentOperator::RightShift { .. } => ">>=",
+        CompoundAssignmentOperator::RightShiftExt { .. } => ">>>=",
+        CompoundAssignmentOperator::Or { .. } => "|=",
+        CompoundAssignmentOperator::Xor { .. } => "^=",
+        CompoundAssignmentOperator::And { .. } => "&=",
+        CompoundAssignmentOperator::LogicalOr { .. } => "||=",
+        CompoundAssignmentOperator::LogicalAnd { .. } => "&&=",
+        CompoundAssignmentOperator::Coalesce { .. } => "??=",
+    }
+}
@@ -0,0 +1,104 @@
+//! What kind of function a function node is, for code that treats kinds of
+//! functions differently, such as transforms and the emitter.
+
+use crate::types::{Binding, Expression, FormalParameters, MethodDefinition, Parameter};
+use serde::Serialize;
+
+/// The kind of a function, method or arrow function, as the parser made it.
+/// The AstBuilder stores these in the `flags` of each function node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FunctionFlags {
+    pub is_arrow: bool,
+    pub is_generator: bool,
+    pub is_async: bool,
+    /// A method of a class or object literal, including getters, setters
+    /// and class constructors.
+    pub is_method: bool,
+    pub is_getter: bool,
+    pub is_setter: bool,
+    /// The parameters are identifiers, without defaults, patterns or a rest
+    /// parameter; see `FormalParameters::is_simple`.
+    pub has_simple_params: bool,
+}
+
+impl FunctionFlags {
+    /// The flags of a function declaration or expression.
+    pub fn function(is_generator: bool, is_async: bool, params: &FormalParameters) -> Self {
+        FunctionFlags {
+            is_generator,
+            is_async,
+            has_simple_params: params.is_simple(),
+            ..FunctionFlags::default()
+        }
+    }
+
+    /// The flags of an arrow function.
+    pub fn arrow(is_async: bool, params: &FormalParameters) -> Self {
+        FunctionFlags {
+            is_arrow: true,
+            is_async,
+            has_simple_params: params.is_simple(),
+            ..FunctionFlags::default()
+        }
+    }
+
+    /// The flags of a method, other than a getter or setter.
+    pub fn method(is_generator: bool, is_async: bool, params: &FormalParameters) -> Self {
+        FunctionFlags {
+            is_method: true,
+            ..FunctionFlags::function(is_generator, is_async, params)
+        }
+    }
+
+    /// The flags of a getter, which has no parameters.
+    pub fn getter() -> Self {
+        FunctionFlags {
+            is_method: true,
+            is_getter: true,
+            has_simple_params: true,
+            ..FunctionFlags::default()
+        }
+    }
+
+    /// The flags of a setter with the parameter `param`.
+    pub fn setter(param: &Parameter) -> Self {
+        FunctionFlags {
+            is_method: true,
+            is_setter: true,
+            has_simple_params: matches!(param, Parameter::Binding(Binding::BindingIdentifier(_))),
+            ..FunctionFlags::default()
+        }
+    }
+
+    /// The flags of a function or arrow function expression, or None if
+    /// `expr` isn't one.
+    pub fn of_expression(expr: &Expression) -> Option<FunctionFlags> {
+        match expr {
+            Expression::FunctionExpression(function) => Some(function.flags),
+            Expression::ArrowExpression { flags, .. } => Some(*flags),
+            _ => None,
+        }
+    }
+}
+
+impl<'alloc> FormalParameters<'alloc> {
+    /// True if the parameters are all identifiers, without defaults, and
+    /// there's no rest parameter: IsSimpleParameterList in the spec.
+    pub fn is_simple(&self) -> bool {
+        self.rest.is_none()
+            && self
+                .items
+                .iter()
+                .all(|param| matches!(param, Parameter::Binding(Binding::BindingIdentifier(_))))
+    }
+}
+
+impl<'alloc> MethodDefinition<'alloc> {
+    pub fn flags(&self) -> FunctionFlags {
+        match self {
+            MethodDefinition::Method(method) => method.flags,
+            MethodDefinition::Getter(getter) => getter.flags,
+            MethodDefinition::Setter(setter) => setter.flags,
+        }
+    }
+}
@@ -1,11 +1,15 @@
+//! Serializing the AST to JSON.
+//!
+//! Every node is a JSON object whose `"type"` is the name of its struct or
+//! enum variant, loosely like ESTree, with its fields and its `"loc"`.
+
+use crate::types::Program;
 use serde_json::Result;
 
-pub fn to_string<'alloc>(_ast: &super::Program<'alloc>) -> Result<String> {
-    // serde_json::to_string(ast)
-    unimplemented!();
+pub fn to_string<'alloc>(ast: &Program<'alloc>) -> Result<String> {
+    serde_json::to_string(ast)
 }
 
-pub fn to_string_pretty<'alloc>(_ast: &super::Program<'alloc>) -> Result<String> {
-    // serde_json::to_string_pretty(ast)
-    unimplemented!();
+pub fn to_string_pretty<'alloc>(ast: &Program<'alloc>) -> Result<String> {
+    serde_json::to_string_pretty(ast)
 }
@@ -1,7 +1,18 @@
 //! The Visage AST (abstract syntax tree).
 
+pub mod analysis;
 pub mod arena;
+pub mod codegen;
+pub mod estree;
+mod function;
+pub mod json;
+mod program;
+pub mod source_atom_set;
 pub mod source_location;
+pub mod structural_eq;
+
+mod serialize_generated;
+mod structural_eq_generated;
 
 mod source_location_accessor_generated;
 pub mod source_location_accessor {
@@ -16,4 +27,5 @@ pub mod visit {
     pub use crate::visit_generated::*;
 }
 
+pub use function::FunctionFlags;
 pub use source_location::SourceLocation;
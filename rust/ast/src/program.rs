@@ -0,0 +1,57 @@
+//! Conversions and accessors for `Program`, for code that takes either a
+//! script or a module, such as the emitter.
+
+use crate::types::{Module, Program, Script};
+
+impl<'alloc> From<Script<'alloc>> for Program<'alloc> {
+    fn from(script: Script<'alloc>) -> Self {
+        Program::Script(script)
+    }
+}
+
+impl<'alloc> From<Module<'alloc>> for Program<'alloc> {
+    fn from(module: Module<'alloc>) -> Self {
+        Program::Module(module)
+    }
+}
+
+impl<'alloc> Program<'alloc> {
+    pub fn as_script(&self) -> Option<&Script<'alloc>> {
+        match self {
+            Program::Script(script) => Some(script),
+            Program::Module(_) => None,
+        }
+    }
+
+    pub fn as_module(&self) -> Option<&Module<'alloc>> {
+        match self {
+            Program::Module(module) => Some(module),
+            Program::Script(_) => None,
+        }
+    }
+
+    /// The script, or None if this is a module.
+    pub fn into_script(self) -> Option<Script<'alloc>> {
+        match self {
+            Program::Script(script) => Some(script),
+            Program::Module(_) => None,
+        }
+    }
+
+    /// The module, or None if this is a script.
+    pub fn into_module(self) -> Option<Module<'alloc>> {
+        match self {
+            Program::Module(module) => Some(module),
+            Program::Script(_) => None,
+        }
+    }
+
+    /// True if the code is strict mode code: a module, or a script with a
+    /// "use strict" directive or parsed with `ParserOptions::strict`.
+    pub fn is_strict(&self) -> bool {
+        match self {
+            Program::Module(module) => module.strict,
+            Program::Script(script) => script.strict,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! The names in a source, each stored once.
+
+use std::collections::HashMap;
+
+/// A handle for a name in a `SourceAtomSet`, which is the same for every
+/// occurrence of the name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceAtomSetIndex(u32);
+
+impl SourceAtomSetIndex {
+    /// The position of the name in the set, in the order the names were
+    /// first inserted.
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The distinct names in a source, such as the identifiers and keywords
+/// scanned by the lexer.
+///
+/// Inserting a name that's already in the set gives back the string that
+/// was inserted first, so that all the occurrences of a name share its
+/// storage.
+#[derive(Debug, Default)]
+pub struct SourceAtomSet<'alloc> {
+    atoms: Vec<&'alloc str>,
+    indices: HashMap<&'alloc str, SourceAtomSetIndex>,
+}
+
+impl<'alloc> SourceAtomSet<'alloc> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `name`, if it isn't in the set already, and return its handle
+    /// and the string stored for it.
+    pub fn insert(&mut self, name: &'alloc str) -> (SourceAtomSetIndex, &'alloc str) {
+        if let Some(&index) = self.indices.get(name) {
+            return (index, self.atoms[index.as_usize()]);
+        }
+        let index = SourceAtomSetIndex(self.atoms.len() as u32);
+        self.atoms.push(name);
+        self.indices.insert(name, index);
+        (index, name)
+    }
+
+    /// The handle of `name`, or None if it isn't in the set.
+    pub fn index_of(&self, name: &str) -> Option<SourceAtomSetIndex> {
+        self.indices.get(name).copied()
+    }
+
+    /// The name `index` is the handle of.
+    pub fn get(&self, index: SourceAtomSetIndex) -> &'alloc str {
+        self.atoms[index.as_usize()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    /// The names, in the order they were first inserted.
+    pub fn iter(&self) -> impl Iterator<Item = (SourceAtomSetIndex, &'alloc str)> + '_ {
+        self.atoms
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (SourceAtomSetIndex(i as u32), name))
+    }
+}
@@ -0,0 +1,63 @@
+//! Comparing ASTs while ignoring source locations.
+
+use crate::arena;
+use crate::function::FunctionFlags;
+
+/// Equality of AST nodes that ignores where they are in the source.
+///
+/// Two programs are structurally equal if they have the same nodes with the
+/// same values, even if formatting or comments differ. Literals are compared
+/// by value, so `16` equals `1.6e1` and `"\x41"` equals `'A'`.
+pub trait StructuralEq {
+    fn structurally_eq(&self, other: &Self) -> bool;
+}
+
+impl StructuralEq for bool {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl StructuralEq for f64 {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl StructuralEq for FunctionFlags {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl StructuralEq for &str {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl<T: StructuralEq> StructuralEq for Option<T> {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(left), Some(right)) => left.structurally_eq(right),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'alloc, T: StructuralEq> StructuralEq for arena::Box<'alloc, T> {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        (**self).structurally_eq(&**other)
+    }
+}
+
+impl<'alloc, T: StructuralEq> StructuralEq for arena::Vec<'alloc, T> {
+    fn structurally_eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(left, right)| left.structurally_eq(right))
+    }
+}
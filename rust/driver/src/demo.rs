@@ -1,26 +1,309 @@
 //! Functions to exercise the parser from the command line.
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::io::prelude::*; // flush() at least
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use ast::{
     self,
-    types::{Program, Script},
+    types::{Module, Program, Script},
+    visit::Visitor,
 };
 use bumpalo::Bump;
-use emitter;
-use parser::{parse_script, ParseError};
+use emitter::{self, EmitOptions, EmitResult};
+use interpreter::{EvalError, Realm, Value};
+use parser::{
+    decode_source, parse_json, parse_module, parse_script, parse_script_recovering, tokenize,
+    JsonValue, ParseError, ParseErrorKind,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+
+/// How `parse_file_or_dir` reports results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// One `path: ok` or `path:line:col: error: message` line per result,
+    /// printed as soon as each file is parsed.
+    Text,
+
+    /// One JSON object per file, sorted by path, printed after all files
+    /// are parsed. See `FileRecord`.
+    Json,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DemoOptions {
+    /// Parse scripts with error recovery and report all errors found.
+    pub recover: bool,
+
+    pub format: OutputFormat,
+
+    /// Print nothing for each file, only the summary at the end.
+    pub quiet: bool,
+
+    /// Report the progress through a directory on stderr, as `Progress`
+    /// says.
+    pub progress: bool,
+
+    /// Skip files larger than this, in bytes, without reading them; see
+    /// `parse_file`. The default is `DEFAULT_MAX_FILE_SIZE`.
+    pub max_file_size: u64,
+
+    /// At the end, print the `ERROR_SUMMARY_LENGTH` most common kinds of
+    /// error, each with a file it's in, instead of the count of each kind.
+    /// Text output only.
+    pub error_summary: bool,
+
+    /// Skip files that look like something other than JavaScript, as
+    /// `non_js_reason` says, instead of counting them as failing to parse.
+    pub skip_non_js: bool,
+
+    /// Count the nodes of each kind in the files that parse, and print the
+    /// counts at the end; see `count_nodes`.
+    pub node_stats: bool,
+}
+
+/// The default `DemoOptions::max_file_size`, 64 MiB. Parsing a file takes
+/// several times its size in memory, so much larger files, which are
+/// usually generated, are better left out.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// How many kinds of error `DemoOptions::error_summary` prints.
+pub const ERROR_SUMMARY_LENGTH: usize = 10;
+
+impl Default for DemoOptions {
+    fn default() -> Self {
+        Self {
+            recover: false,
+            format: OutputFormat::Text,
+            quiet: false,
+            progress: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            error_summary: false,
+            skip_non_js: false,
+            node_stats: false,
+        }
+    }
+}
+
+/// Which files `parse_file_or_dir` parses in a directory. A file named
+/// directly is parsed whatever its name.
+#[derive(Clone, Debug)]
+pub struct FileFilter {
+    /// The extensions of the files to parse, without the dot.
+    pub extensions: Vec<String>,
+
+    /// A pattern that the paths of the files to parse must match, relative
+    /// to the directory, as in `src/**/*.js`. See `glob_matches`.
+    pub glob: Option<String>,
+
+    /// The names of the directories to skip, with everything in them.
+    pub exclude: Vec<String>,
+
+    /// Follow symlinks to files and directories, visiting each directory
+    /// once; see `dir_files`.
+    pub follow_symlinks: bool,
+
+    /// Skip the paths that `.gitignore` files in the directory, or in the
+    /// directories it's in, say to ignore, as git does.
+    pub gitignore: bool,
+
+    /// Include the files and directories whose names start with `.`, which
+    /// are skipped otherwise.
+    pub hidden: bool,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self {
+            extensions: vec!["js".to_string(), "mjs".to_string(), "cjs".to_string()],
+            glob: None,
+            exclude: vec!["node_modules".to_string(), ".git".to_string()],
+            follow_symlinks: false,
+            gitignore: false,
+            hidden: false,
+        }
+    }
+}
+
+impl FileFilter {
+    fn includes_dir(&self, name: &OsStr) -> bool {
+        !self
+            .exclude
+            .iter()
+            .any(|excluded| OsStr::new(excluded) == name)
+    }
 
-#[derive(Clone, Debug, Default)]
+    /// Whether to parse the file at `relative_path` in the directory.
+    fn includes_file(&self, relative_path: &Path) -> bool {
+        let has_extension = match relative_path.extension().and_then(OsStr::to_str) {
+            Some(extension) => self.extensions.iter().any(|e| e == extension),
+            None => false,
+        };
+        has_extension
+            && match &self.glob {
+                Some(glob) => {
+                    let components: Vec<_> = relative_path
+                        .components()
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect();
+                    glob_matches(glob, &components.join("/"))
+                }
+                None => true,
+            }
+    }
+}
+
+/// Whether `path`, with `/` between its components, matches `pattern`. In
+/// the pattern, `**` as a whole component matches any number of components,
+/// `*` matches any part of one component, and `?` any one character in it.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn components_match(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| components_match(rest, &path[i..])),
+            Some((first, rest)) => match path.split_first() {
+                Some((component, path_rest)) => {
+                    let first: Vec<char> = first.chars().collect();
+                    let component: Vec<char> = component.chars().collect();
+                    chars_match(&first, &component) && components_match(rest, path_rest)
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn chars_match(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|i| chars_match(rest, &name[i..])),
+            Some(('?', rest)) => !name.is_empty() && chars_match(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && chars_match(rest, &name[1..]),
+        }
+    }
+
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    components_match(&pattern, &path)
+}
+
+/// A parse error, for JSON output.
+#[derive(Clone, Debug, Serialize)]
+struct ErrorRecord {
+    kind: &'static str,
+    message: String,
+    location: Option<LocationRecord>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct LocationRecord {
+    offset: usize,
+    line: u32,
+    column: u32,
+}
+
+impl ErrorRecord {
+    fn new(source: &str, err: &ParseError) -> ErrorRecord {
+        ErrorRecord {
+            kind: err.code(),
+            message: err.message(),
+            location: err.location(source).map(|loc| LocationRecord {
+                offset: loc.offset,
+                line: loc.line,
+                column: loc.column,
+            }),
+        }
+    }
+}
+
+/// The result of parsing one file, for JSON output. Only deterministic
+/// results are included, so the output can be diffed across runs.
+#[derive(Clone, Debug, Serialize)]
+struct FileRecord {
+    path: String,
+    bytes: u64,
+    ok: bool,
+
+    /// The first error found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorRecord>,
+
+    /// All errors found, when parsing with error recovery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<Vec<ErrorRecord>>,
+}
+
+#[derive(Clone, Default)]
 pub struct DemoStats {
     files_attempted: usize,
     files_parsed: usize,
 
+    /// The number of directories and directory entries that couldn't be
+    /// read, and were skipped.
+    io_errors: usize,
+
+    /// The number of files larger than `DemoOptions::max_file_size`, which
+    /// were skipped, and aren't counted as attempted.
+    skipped_too_large: usize,
+
+    /// The number of files skipped by `DemoOptions::skip_non_js`, which
+    /// aren't counted as attempted either.
+    skipped_non_js: usize,
+
     /// Total size of all the files attempted, in bytes.
     total_bytes: u64,
+
+    /// The most memory the allocator held after parsing any one file, in
+    /// bytes. The allocator is reused across files, so this is also its
+    /// final footprint.
+    peak_arena_bytes: usize,
+
+    /// Total time spent parsing, not including reading files.
+    total_parse_nanos: u128,
+
+    /// The file that took the longest to parse, its size in bytes, and how
+    /// long it took.
+    slowest: Option<(PathBuf, u64, Duration)>,
+
+    /// The number of errors of each kind found, with the first file, by
+    /// path, that one was found in. Errors reading files count as `IOError`.
+    errors: BTreeMap<ParseErrorKind, (usize, PathBuf)>,
+
+    /// Per-file results, collected only for JSON output, and not with
+    /// `DemoOptions::quiet`.
+    records: Vec<FileRecord>,
+
+    /// The number of nodes of each kind, counted only with
+    /// `DemoOptions::node_stats`.
+    node_counts: BTreeMap<&'static str, usize>,
+}
+
+// Not derived, to leave out the per-file errors and records, and the node
+// counts, which are printed as a table instead. The times, the slowest file
+// and the arena size are measurements, which `print_timing` prints: they
+// change from one run to the next, or with the number of threads, while the
+// rest is the same for the same files.
+impl fmt::Debug for DemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DemoStats")
+            .field("files_attempted", &self.files_attempted)
+            .field("files_parsed", &self.files_parsed)
+            .field("io_errors", &self.io_errors)
+            .field("skipped_too_large", &self.skipped_too_large)
+            .field("skipped_non_js", &self.skipped_non_js)
+            .field("total_bytes", &self.total_bytes)
+            .finish()
+    }
 }
 
 impl DemoStats {
@@ -33,110 +316,1703 @@ impl DemoStats {
             files_attempted: 1,
             files_parsed: if success { 1 } else { 0 },
             total_bytes: size_bytes,
+            ..DemoStats::default()
         }
     }
 
     pub fn add(&mut self, other: &DemoStats) {
         self.files_attempted += other.files_attempted;
         self.files_parsed += other.files_parsed;
+        self.io_errors += other.io_errors;
+        self.skipped_too_large += other.skipped_too_large;
+        self.skipped_non_js += other.skipped_non_js;
         self.total_bytes += other.total_bytes;
+        self.peak_arena_bytes = self.peak_arena_bytes.max(other.peak_arena_bytes);
+        self.total_parse_nanos += other.total_parse_nanos;
+        if let Some((_, _, other_time)) = &other.slowest {
+            let is_slower = match &self.slowest {
+                Some((_, _, time)) => other_time > time,
+                None => true,
+            };
+            if is_slower {
+                self.slowest = other.slowest.clone();
+            }
+        }
+        for (kind, (count, path)) in &other.errors {
+            self.add_errors(*kind, *count, path);
+        }
+        self.records.extend(other.records.iter().cloned());
+        for (kind, count) in &other.node_counts {
+            *self.node_counts.entry(kind).or_insert(0) += count;
+        }
+    }
+
+    /// Warn about `err`, which made a directory or entry be skipped, and
+    /// count it.
+    fn add_io_error(&mut self, err: &io::Error) {
+        eprintln!("warning: {}", err);
+        self.io_errors += 1;
+    }
+
+    /// Count `count` errors of kind `kind`, found in `path`.
+    fn add_errors(&mut self, kind: ParseErrorKind, count: usize, path: &Path) {
+        match self.errors.get_mut(&kind) {
+            Some((total, sample)) => {
+                *total += count;
+                if path < sample.as_path() {
+                    *sample = path.to_path_buf();
+                }
+            }
+            None => {
+                self.errors.insert(kind, (count, path.to_path_buf()));
+            }
+        }
+    }
+
+    /// Count the errors found, by kind.
+    pub fn error_histogram(&self) -> BTreeMap<String, usize> {
+        self.errors
+            .iter()
+            .map(|(kind, (count, _))| (kind.to_str().to_string(), *count))
+            .collect()
+    }
+
+    /// The kinds of error found, with their counts and sample files, most
+    /// common first, and in the order of `ParseErrorKind` for equal counts.
+    fn errors_by_count(&self) -> Vec<(ParseErrorKind, usize, &Path)> {
+        let mut counts: Vec<(ParseErrorKind, usize, &Path)> = self
+            .errors
+            .iter()
+            .map(|(kind, (count, path))| (*kind, *count, path.as_path()))
+            .collect();
+        counts.sort_by(|(_, a, _), (_, b, _)| b.cmp(a));
+        counts
+    }
+
+    /// Print the number of errors of each kind, most common first.
+    pub fn print_error_histogram(&self) {
+        for (kind, count, _) in self.errors_by_count() {
+            println!("{:>8} {}", count, kind.to_str());
+        }
+    }
+
+    /// Print the `ERROR_SUMMARY_LENGTH` most common kinds of error as a
+    /// table; see `write_error_summary`.
+    pub fn print_error_summary(&self) {
+        // Failing to write to stdout isn't reported, as with `println!`.
+        let _ = self.write_error_summary(&mut io::stdout());
+    }
+
+    /// Write the `ERROR_SUMMARY_LENGTH` most common kinds of error to `out`
+    /// as a table, with the share of all errors each one is, and a file it's
+    /// in.
+    pub fn write_error_summary(&self, out: &mut impl Write) -> io::Result<()> {
+        let counts = self.errors_by_count();
+        let total: usize = counts.iter().map(|(_, count, _)| count).sum();
+        if total == 0 {
+            return writeln!(out, "no errors");
+        }
+        let width = counts
+            .iter()
+            .take(ERROR_SUMMARY_LENGTH)
+            .map(|(kind, _, _)| kind.to_str().len())
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            out,
+            "top {} of {} kinds of error, {} errors in all:",
+            counts.len().min(ERROR_SUMMARY_LENGTH),
+            counts.len(),
+            total
+        )?;
+        for (kind, count, path) in counts.iter().take(ERROR_SUMMARY_LENGTH) {
+            writeln!(
+                out,
+                "{:>8} {:>5.1}%  {:<width$}  e.g. {}",
+                count,
+                *count as f64 / total as f64 * 100.0,
+                kind.to_str(),
+                path.display(),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Print the number of nodes of each kind, most common first, and in
+    /// alphabetical order for equal counts.
+    pub fn print_node_counts(&self) {
+        let mut counts: Vec<(&str, usize)> = self
+            .node_counts
+            .iter()
+            .map(|(kind, count)| (*kind, *count))
+            .collect();
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        println!("{} nodes of {} kinds:", total, counts.len());
+        for (kind, count) in counts {
+            println!("{:>8} {}", count, kind);
+        }
+    }
+
+    /// Print the average and total parse time, the slowest file, and the
+    /// most memory an allocator held.
+    pub fn print_timing(&self) {
+        let total_ms = self.total_parse_nanos as f64 / 1_000_000.0;
+        if self.files_attempted > 0 {
+            println!(
+                "average parse time: {:.3} ms/file",
+                total_ms / self.files_attempted as f64
+            );
+        }
+        println!("total parse time: {:.3} ms", total_ms);
+        if let Some((path, size, time)) = &self.slowest {
+            println!(
+                "slowest file: {} ({} bytes, {:.3} ms)",
+                path.display(),
+                size,
+                time.as_secs_f64() * 1000.0
+            );
+        }
+        println!("peak arena size: {} bytes", self.peak_arena_bytes);
+    }
+
+    /// A one-line summary of how much was parsed, how fast, how much of it
+    /// parsed successfully, and what was skipped, if anything. Rates are 0 if
+    /// no time was spent parsing, and the success rate is 0 if no file was
+    /// attempted.
+    pub fn summary_line(&self) -> String {
+        let megabytes = self.total_bytes as f64 / 1_000_000.0;
+        let seconds = self.total_parse_nanos as f64 / 1_000_000_000.0;
+        let per_second = |amount: f64| if seconds > 0.0 { amount / seconds } else { 0.0 };
+        let success_rate = if self.files_attempted > 0 {
+            self.files_parsed as f64 / self.files_attempted as f64 * 100.0
+        } else {
+            0.0
+        };
+        let mut line = format!(
+            "{:.3} MB in {:.3} s, {:.1} MB/s, {:.1} files/s, {:.1}% parsed ({}/{})",
+            megabytes,
+            seconds,
+            per_second(megabytes),
+            per_second(self.files_attempted as f64),
+            success_rate,
+            self.files_parsed,
+            self.files_attempted
+        );
+        let skipped: Vec<String> = [
+            (self.io_errors, "unreadable"),
+            (self.skipped_too_large, "too large"),
+            (self.skipped_non_js, "not JavaScript"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, why)| format!("{} {}", count, why))
+        .collect();
+        if !skipped.is_empty() {
+            line.push_str(&format!("; skipped {}", skipped.join(", ")));
+        }
+        line
+    }
+
+    /// Print the per-file records, sorted by path, one JSON object per line,
+    /// followed by a summary object.
+    /// The summary has the node counts only if any were counted.
+    pub fn print_json(&self) {
+        let mut records: Vec<&FileRecord> = self.records.iter().collect();
+        records.sort_by(|a, b| a.path.cmp(&b.path));
+        for record in records {
+            println!("{}", serde_json::to_string(record).unwrap());
+        }
+        let mut summary = json!({
+            "files_attempted": self.files_attempted,
+            "files_parsed": self.files_parsed,
+            "io_errors": self.io_errors,
+            "skipped_too_large": self.skipped_too_large,
+            "skipped_non_js": self.skipped_non_js,
+            "total_bytes": self.total_bytes,
+            "errors": self.error_histogram(),
+        });
+        if !self.node_counts.is_empty() {
+            summary["node_counts"] = json!(self.node_counts);
+        }
+        println!("{}", json!({ "summary": summary }));
     }
 }
 
-/// Try parsing a file.
+/// Try parsing a file. Files with the `.mjs` extension are parsed as
+/// modules, everything else as scripts.
+///
+/// `allocator` is reset before parsing, and the AST is dropped before
+/// returning, so the same allocator can be reused for the next file.
+///
+/// A file larger than `options.max_file_size`, going by `size_bytes`, isn't
+/// read. A note says it's skipped, unless `options.quiet` is set, and it's
+/// counted in `skipped_too_large`.
+/// With `options.skip_non_js`, so is a file `non_js_reason` rejects, in
+/// `skipped_non_js`.
 ///
 /// Returns an Err only if opening or reading the file fails;
-/// parse errors are simply printed to stdout.
-fn parse_file(path: &Path, size_bytes: u64) -> io::Result<DemoStats> {
-    print!("{}:", path.display());
-    io::stdout().flush()?;
-    let contents = match fs::read_to_string(path) {
+/// parse errors are simply reported as `options.format` says.
+fn parse_file(
+    allocator: &mut Bump,
+    path: &Path,
+    size_bytes: u64,
+    options: DemoOptions,
+) -> io::Result<DemoStats> {
+    if size_bytes > options.max_file_size {
+        if !options.quiet {
+            eprintln!(
+                "note: skipping {}: {} bytes is more than the maximum of {} (see --max-file-size)",
+                path.display(),
+                size_bytes,
+                options.max_file_size
+            );
+        }
+        return Ok(DemoStats {
+            skipped_too_large: 1,
+            ..DemoStats::default()
+        });
+    }
+    let is_module = path.extension() == Some(OsStr::new("mjs"));
+    parse_contents(
+        allocator,
+        path,
+        size_bytes,
+        fs::read(path),
+        is_module,
+        options,
+    )
+}
+
+/// The number of nodes of each kind in `program`, by the names of their
+/// structs or enum variants in `ast::types`, like `CallExpression`; see
+/// `Visitor::visit_node`.
+pub fn count_nodes(program: &Program) -> BTreeMap<&'static str, usize> {
+    let mut counter = NodeCounter::default();
+    counter.visit_program(program);
+    counter.counts
+}
+
+#[derive(Default)]
+struct NodeCounter {
+    counts: BTreeMap<&'static str, usize>,
+}
+
+impl<'alloc> Visitor<'alloc> for NodeCounter {
+    fn visit_node(&mut self, kind: &'static str) {
+        *self.counts.entry(kind).or_insert(0) += 1;
+    }
+}
+
+/// The programs a `#!` line can run a JavaScript file with.
+const JS_INTERPRETERS: &[&str] = &["node", "nodejs", "deno", "bun"];
+
+/// Why `source` is plausibly not JavaScript, if it isn't: it's a JSON
+/// object, or it starts with a `#!` line that runs some other program, such
+/// as a shell. A JSON array or string, or an object without members, is left
+/// alone, since it's also a valid script: `{}` is an empty block.
+fn non_js_reason(source: &str) -> Option<&'static str> {
+    if let Some(line) = source.strip_prefix("#!") {
+        let line = line.lines().next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let mut program = words.next().unwrap_or("");
+        if program.rsplit('/').next() == Some("env") {
+            program = words.find(|word| !word.starts_with('-')).unwrap_or("");
+        }
+        let name = program.rsplit('/').next().unwrap_or("");
+        return if JS_INTERPRETERS.contains(&name) {
+            None
+        } else {
+            Some("the #! line runs a program other than node")
+        };
+    }
+    if source.trim_start().starts_with('{') {
+        if let Ok(JsonValue::Object(members)) = parse_json(source) {
+            if !members.is_empty() {
+                return Some("it's JSON");
+            }
+        }
+    }
+    None
+}
+
+/// Read all of stdin and parse it, as a module if `is_module` is true or
+/// as a script otherwise. It's reported as a file named `-`.
+pub fn parse_stdin(is_module: bool, options: DemoOptions) -> io::Result<DemoStats> {
+    let mut contents = Vec::new();
+    let result = io::stdin().read_to_end(&mut contents);
+    let size_bytes = contents.len() as u64;
+    parse_contents(
+        &mut Bump::new(),
+        Path::new("-"),
+        size_bytes,
+        result.map(|_| contents),
+        is_module,
+        options,
+    )
+}
+
+/// Decode and parse the `contents` read from `path`, or report the error if
+/// reading failed. See `parse_file`.
+fn parse_contents(
+    allocator: &mut Bump,
+    path: &Path,
+    size_bytes: u64,
+    contents: io::Result<Vec<u8>>,
+    is_module: bool,
+    options: DemoOptions,
+) -> io::Result<DemoStats> {
+    let contents = match contents {
         Err(err) => {
-            println!(" error reading file: {}", err);
-            return Ok(DemoStats::new_single(size_bytes, false));
+            let mut stats = DemoStats::new_single(size_bytes, false);
+            stats.add_errors(ParseErrorKind::IOError, 1, path);
+            match options.format {
+                _ if options.quiet => {}
+                OutputFormat::Text => {
+                    println!("{}: error reading file: {}", path.display(), err)
+                }
+                OutputFormat::Json => {
+                    let error = ErrorRecord {
+                        kind: ParseErrorKind::IOError.to_str(),
+                        message: format!("error reading file: {}", err),
+                        location: None,
+                    };
+                    stats.records.push(FileRecord {
+                        path: path.display().to_string(),
+                        bytes: size_bytes,
+                        ok: false,
+                        error: Some(error.clone()),
+                        errors: if options.recover {
+                            Some(vec![error])
+                        } else {
+                            None
+                        },
+                    });
+                }
+            }
+            return Ok(stats);
         }
         Ok(s) => s,
     };
-    let allocator = &Bump::new();
-    let result = parse_script(allocator, &contents);
-    let stats = DemoStats::new_single(size_bytes, result.is_ok());
-    match result {
-        Ok(_ast) => println!(" ok"),
-        Err(err) => println!(" error: {}", err.message()),
+    allocator.reset();
+    let allocator = &*allocator;
+    let start = Instant::now();
+    let decoded = decode_source(allocator, &contents);
+    if let (true, Ok(contents)) = (options.skip_non_js, &decoded) {
+        if let Some(reason) = non_js_reason(contents) {
+            if !options.quiet {
+                eprintln!(
+                    "note: skipping {}: {} (see --skip-non-js)",
+                    path.display(),
+                    reason
+                );
+            }
+            return Ok(DemoStats {
+                skipped_non_js: 1,
+                ..DemoStats::default()
+            });
+        }
+    }
+    // The program is kept only to count its nodes, after the parse is timed.
+    let (contents, program, errors) = match decoded {
+        // There's no text to locate the error in.
+        Err(err) => ("", None, vec![err]),
+        Ok(contents) => {
+            let (program, errors) = if is_module {
+                match parse_module(allocator, contents) {
+                    Ok(module) => (Some(Program::Module(module.unbox())), Vec::new()),
+                    Err(err) => (None, vec![err]),
+                }
+            } else if options.recover {
+                let (script, errors) = parse_script_recovering(allocator, contents);
+                (Some(Program::Script(script.unbox())), errors)
+            } else {
+                match parse_script(allocator, contents) {
+                    Ok(script) => (Some(Program::Script(script.unbox())), Vec::new()),
+                    Err(err) => (None, vec![err]),
+                }
+            };
+            (contents, program, errors)
+        }
+    };
+    let parse_time = start.elapsed();
+    let mut stats = DemoStats::new_single(size_bytes, errors.is_empty());
+    if let (true, Some(program)) = (options.node_stats, &program) {
+        stats.node_counts = count_nodes(program);
+    }
+    stats.peak_arena_bytes = allocator.allocated_bytes();
+    stats.total_parse_nanos = parse_time.as_nanos();
+    stats.slowest = Some((path.to_path_buf(), size_bytes, parse_time));
+    for err in &errors {
+        stats.add_errors(err.kind(), 1, path);
+    }
+    match options.format {
+        _ if options.quiet => {}
+        OutputFormat::Text => {
+            if errors.is_empty() {
+                println!("{}: ok", path.display());
+            }
+            for err in errors {
+                print_error(path, contents, &err);
+            }
+            io::stdout().flush()?;
+        }
+        OutputFormat::Json => {
+            let error_records: Vec<ErrorRecord> = errors
+                .iter()
+                .map(|err| ErrorRecord::new(contents, err))
+                .collect();
+            stats.records.push(FileRecord {
+                path: path.display().to_string(),
+                bytes: size_bytes,
+                ok: error_records.is_empty(),
+                error: error_records.first().cloned(),
+                errors: if options.recover {
+                    Some(error_records)
+                } else {
+                    None
+                },
+            });
+        }
     }
     Ok(stats)
 }
 
-/// Try parsing all the files in a directory, recursively.
+fn print_error(path: &Path, source: &str, err: &ParseError) {
+    match err.location(source) {
+        Some(loc) => println!(
+            "{}:{}:{}: error: {}",
+            path.display(),
+            loc.line,
+            loc.column,
+            err.message()
+        ),
+        None => println!("{}: error: {}", path.display(), err.message()),
+    }
+    if err.kind() == ParseErrorKind::ModuleDeclarationInScript {
+        println!("{}: note: {}", path.display(), module_hint(path));
+    }
+}
+
+/// How to have the source read from `path` parsed as a module, for when it
+/// was parsed as a script but looks like a module.
+fn module_hint(path: &Path) -> &'static str {
+    if path == Path::new("-") {
+        "to parse stdin as a module, pipe it to `parser --module`"
+    } else {
+        "files are parsed as modules if their names end in .mjs"
+    }
+}
+
+/// The identity of a file, the same for every path to it, or None if the
+/// platform doesn't tell.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// The files in the directory `path`, recursively, that `filter` includes,
+/// with their sizes, or the errors reading the directories and entries in
+/// it.
+///
+/// Entries whose names start with `.` are skipped unless `filter.hidden` is
+/// set.
+///
+/// Symlinks are followed only if `filter.follow_symlinks` is set. Then
+/// each directory is visited once, however many links there are to it, and
+/// a link to a directory it's in is skipped, so the walk doesn't go on
+/// forever. (Where directories can't be identified, directories linked from
+/// elsewhere are visited again.)
+fn dir_files<'a>(
+    path: &'a Path,
+    filter: &'a FileFilter,
+) -> impl Iterator<Item = io::Result<(PathBuf, u64)>> + 'a {
+    let exclude = filter.clone();
+    let visited_dirs = Mutex::new(HashSet::new());
+    ignore::WalkBuilder::new(path)
+        .standard_filters(false)
+        .git_ignore(filter.gitignore)
+        .parents(filter.gitignore)
+        .require_git(false)
+        .follow_links(filter.follow_symlinks)
+        .filter_entry(move |entry| {
+            // The name is checked first, so the entries skipped here aren't
+            // even looked at.
+            if entry.depth() > 0
+                && !exclude.hidden
+                && entry.file_name().to_string_lossy().starts_with('.')
+            {
+                return false;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if !is_dir {
+                return true;
+            }
+            if entry.depth() > 0 && !exclude.includes_dir(entry.file_name()) {
+                return false;
+            }
+            if !exclude.follow_symlinks {
+                return true;
+            }
+            match fs::metadata(entry.path()).ok().as_ref().and_then(file_id) {
+                Some(id) => visited_dirs.lock().unwrap().insert(id),
+                None => true,
+            }
+        })
+        .build()
+        .filter_map(move |entry_result| {
+            let entry = match entry_result {
+                Ok(entry) => entry,
+                Err(err) if is_loop(&err) => return None,
+                Err(err) => return Some(Err(io::Error::other(err))),
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file())
+                || !filter.includes_file(entry.path().strip_prefix(path).unwrap())
+            {
+                return None;
+            }
+            Some(match entry.metadata() {
+                Ok(metadata) => Ok((entry.into_path(), metadata.len())),
+                Err(err) => Err(io::Error::other(err)),
+            })
+        })
+}
+
+/// Whether `err` is the walker finding a symlink to a directory that it's
+/// in, which isn't a problem with the directory.
+fn is_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Try parsing the files in a directory that `filter` includes,
+/// recursively, on `jobs` threads, or one per CPU if `jobs` is None; see
+/// `parse_files_parallel`.
+///
+/// All the files are found before any is parsed, so that the progress
+/// reported with `options.progress` can count toward the total.
 ///
-/// Returns an Err only if reading a file or directory fails;
-/// parse errors are simply printed to stdout.
-fn parse_dir(path: &Path) -> io::Result<DemoStats> {
+/// A directory or entry that can't be read is skipped with a warning, and
+/// counted in `io_errors`. Returns an Err only if writing the output fails;
+/// parse errors are simply reported as `options.format` says.
+fn parse_dir(
+    allocator: &mut Bump,
+    path: &Path,
+    options: DemoOptions,
+    filter: &FileFilter,
+    jobs: Option<usize>,
+) -> io::Result<DemoStats> {
     let mut summary = DemoStats::new();
-    for entry_result in fs::read_dir(&path)? {
-        let entry = entry_result?;
-        let file = entry.path();
-        let metadata = entry.metadata()?;
-        let stats = if metadata.is_file() {
-            parse_file(&file, metadata.len())?
-        } else if metadata.is_dir() {
-            parse_dir(&file)?
-        } else {
-            DemoStats::new()
-        };
-        summary.add(&stats);
+    let mut files = Vec::new();
+    for file in dir_files(path, filter) {
+        match file {
+            Ok(file) => files.push(file),
+            Err(err) => summary.add_io_error(&err),
+        }
+    }
+
+    let progress = if options.progress {
+        Some(Progress::new(files.len()))
+    } else {
+        None
+    };
+    if jobs == Some(1) {
+        for (file, size_bytes) in &files {
+            summary.add(&parse_file(allocator, file, *size_bytes, options)?);
+            if let Some(progress) = &progress {
+                progress.file_done(*size_bytes);
+            }
+        }
+    } else {
+        summary.add(&parse_files_parallel(
+            &files,
+            options,
+            progress.as_ref(),
+            jobs,
+        )?);
     }
     Ok(summary)
 }
 
-/// Try parsing a file, or all the files in a directory recursively.
+/// Parse `files`, with their sizes, on `jobs` threads, or one per CPU if
+/// `jobs` is None. Each thread has its own allocator.
+///
+/// The per-file output lines are printed in no particular order. The
+/// returned stats are the same as parsing the files one after another, as
+/// their `Debug` form shows them.
+fn parse_files_parallel(
+    files: &[(PathBuf, u64)],
+    options: DemoOptions,
+    progress: Option<&Progress>,
+    jobs: Option<usize>,
+) -> io::Result<DemoStats> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .map_err(io::Error::other)?;
+    pool.install(|| {
+        files
+            .par_iter()
+            .map_init(Bump::new, |allocator, (file, size_bytes)| {
+                let stats = parse_file(allocator, file, *size_bytes, options);
+                if let Some(progress) = progress {
+                    progress.file_done(*size_bytes);
+                }
+                stats
+            })
+            .try_reduce(DemoStats::new, |mut summary, stats| {
+                summary.add(&stats);
+                Ok(summary)
+            })
+    })
+}
+
+/// Reports on stderr how many of a number of files have been parsed, and
+/// how fast, at most once a second and when the last one is done.
+struct Progress {
+    total_files: usize,
+    start: Instant,
+    /// The files done, their total size in bytes, and when the last report
+    /// was printed.
+    state: Mutex<(usize, u64, Instant)>,
+}
+
+impl Progress {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    fn new(total_files: usize) -> Progress {
+        let start = Instant::now();
+        Progress {
+            total_files,
+            start,
+            state: Mutex::new((0, 0, start)),
+        }
+    }
+
+    /// Count a file of `size_bytes` bytes as done.
+    fn file_done(&self, size_bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        let (files, bytes, last_report) = &mut *state;
+        *files += 1;
+        *bytes += size_bytes;
+        let now = Instant::now();
+        if *files == self.total_files || now - *last_report >= Self::INTERVAL {
+            *last_report = now;
+            let seconds = (now - self.start).as_secs_f64();
+            let megabytes = *bytes as f64 / 1_000_000.0;
+            eprintln!(
+                "{}/{} files, {:.1} MB/s",
+                files,
+                self.total_files,
+                if seconds > 0.0 {
+                    megabytes / seconds
+                } else {
+                    0.0
+                }
+            );
+        }
+    }
+}
+
+/// Try parsing a file, or the files in a directory that `filter` includes,
+/// recursively.
+///
+/// Directories are parsed on `jobs` threads, or one per CPU if `jobs` is
+/// None. What can't be read in them is skipped; see `parse_dir`. Files
+/// larger than `options.max_file_size` are skipped too, including
+/// `filename` itself; see `parse_file`.
 ///
-/// Returns an Err only if reading a file or directory fails;
-/// parse errors are simply printed to stdout.
-pub fn parse_file_or_dir(filename: &impl AsRef<OsStr>) -> io::Result<DemoStats> {
+/// Returns an Err only if `filename` can't be found or the output can't be
+/// written; parse errors are simply reported as `options.format` says.
+pub fn parse_file_or_dir(
+    filename: &impl AsRef<OsStr>,
+    options: DemoOptions,
+    filter: &FileFilter,
+    jobs: Option<usize>,
+) -> io::Result<DemoStats> {
     let path = Path::new(filename);
     let metadata = path.metadata()?;
+    let mut allocator = Bump::new();
     if metadata.is_dir() {
-        parse_dir(path)
+        parse_dir(&mut allocator, path, options, filter, jobs)
     } else {
         // No `if metadata.is_file()` here, we instead try opening it and let
         // that fail if this is some exotic filesystem thingy. That way the
         // user gets an error message.
-        parse_file(Path::new(filename), metadata.len())
+        parse_file(&mut allocator, Path::new(filename), metadata.len(), options)
     }
 }
 
-fn handle_script<'alloc>(script: Script<'alloc>) {
-    println!("{:#?}", script);
-    let mut program = Program::Script(script);
-    match emitter::emit(&mut program) {
+/// Print the tokens of a script, one per line, as `start..end kind lexeme`.
+///
+/// Returns an Err only if reading the file fails; a lexical or syntax error
+/// is printed to stdout and ends the listing.
+pub fn print_tokens(filename: &impl AsRef<OsStr>) -> io::Result<()> {
+    let path = Path::new(filename);
+    let contents = fs::read_to_string(path)?;
+    let allocator = &Bump::new();
+    for result in tokenize(allocator, &contents) {
+        match result {
+            Ok((token, lexeme)) => println!(
+                "{}..{} {:?} {}",
+                token.loc.start,
+                token.loc.end,
+                token.terminal_id,
+                lexeme.escape_debug()
+            ),
+            Err(err) => print_error(path, &contents, &err),
+        }
+    }
+    io::stdout().flush()
+}
+
+/// Print the AST of a file as JSON; see `ast::json`. Files with the `.mjs`
+/// extension are parsed as modules, others as scripts.
+///
+/// Returns an Err only if reading the file fails; a parse error is printed
+/// instead of the AST.
+pub fn print_ast_json(filename: &impl AsRef<OsStr>) -> io::Result<()> {
+    let path = Path::new(filename);
+    let contents = fs::read_to_string(path)?;
+    let allocator = &Bump::new();
+    let result = if path.extension() == Some(OsStr::new("mjs")) {
+        parse_module(allocator, &contents).map(|module| Program::Module(module.unbox()))
+    } else {
+        parse_script(allocator, &contents).map(|script| Program::Script(script.unbox()))
+    };
+    match result {
+        Ok(program) => println!("{}", ast::json::to_string_pretty(&program)?),
+        Err(err) => print_error(path, &contents, &err),
+    }
+    io::stdout().flush()
+}
+
+/// Compile a script with `options` and print its bytecode, followed by its
+/// source map as JSON; see `emitter::source_map_json`.
+///
+/// Returns an Err only if reading the file fails; parse and emit errors are
+/// printed instead.
+pub fn print_source_map(filename: &impl AsRef<OsStr>, options: &EmitOptions) -> io::Result<()> {
+    let path = Path::new(filename);
+    let contents = fs::read_to_string(path)?;
+    let allocator = &Bump::new();
+    match parse_script(allocator, &contents) {
+        Ok(script) => {
+            let mut program = Program::Script(script.unbox());
+            let source_name = path.display().to_string();
+            let options = EmitOptions {
+                source_map: true,
+                source: &contents,
+                source_name: &source_name,
+                ..*options
+            };
+            match emitter::emit_with_options(&mut program, &options) {
+                Ok(emit_result) => {
+                    emitter::dis_to(&emit_result.bytecode, &mut io::stdout().lock())?;
+                    println!();
+                    println!("{}", emitter::source_map_json(&emit_result).unwrap());
+                }
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+        Err(err) => print_error(path, &contents, &err),
+    }
+    io::stdout().flush()
+}
+
+/// Compile a script with `options` and print how many instructions there are
+/// with each opcode, most used first; see `emitter::opcode_histogram`.
+///
+/// Returns an Err only if reading the file fails; parse and emit errors are
+/// printed instead.
+pub fn print_opcode_stats(filename: &impl AsRef<OsStr>, options: &EmitOptions) -> io::Result<()> {
+    let path = Path::new(filename);
+    let contents = fs::read_to_string(path)?;
+    let allocator = &Bump::new();
+    match parse_script(allocator, &contents) {
+        Ok(script) => {
+            let mut program = Program::Script(script.unbox());
+            match emitter::emit_with_options(&mut program, options) {
+                Ok(emit_result) => {
+                    let mut counts: Vec<_> = emitter::opcode_histogram(&emit_result)
+                        .into_iter()
+                        .collect();
+                    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+                    for (name, count) in counts {
+                        println!("{:>8} {}", count, name);
+                    }
+                }
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+        Err(err) => print_error(path, &contents, &err),
+    }
+    io::stdout().flush()
+}
+
+/// Compile a script with `options` and save the result to `output`, to be
+/// run later by `run_bytecode_file`; see `emitter::serialize`.
+///
+/// Returns an Err only if reading or writing a file fails; parse and emit
+/// errors are printed instead, and no file is written.
+pub fn compile_file(
+    filename: &impl AsRef<OsStr>,
+    output: &impl AsRef<OsStr>,
+    options: &EmitOptions,
+) -> io::Result<()> {
+    let path = Path::new(filename);
+    let contents = fs::read_to_string(path)?;
+    let allocator = &Bump::new();
+    match parse_script(allocator, &contents) {
+        Ok(script) => {
+            let mut program = Program::Script(script.unbox());
+            match emitter::emit_with_options(&mut program, options) {
+                Ok(emit_result) => fs::write(Path::new(output), emitter::serialize(&emit_result))?,
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+        Err(err) => print_error(path, &contents, &err),
+    }
+    Ok(())
+}
+
+/// Run bytecode saved by `compile_file` and print the result.
+///
+/// Returns an Err only if reading the file or writing what the script
+/// printed fails; a file that isn't valid bytecode for this version is
+/// reported instead.
+pub fn run_bytecode_file(filename: &impl AsRef<OsStr>, max_steps: Option<u64>) -> io::Result<()> {
+    let path = Path::new(filename);
+    let data = fs::read(path)?;
+    match emitter::deserialize(&data) {
+        Ok(emit_result) => {
+            let out = &mut io::stdout();
+            let result = Evaluator::new(max_steps).evaluate_to(&emit_result, out)?;
+            println!("{:?}", result);
+        }
+        Err(err) => eprintln!("{}: error: {}", path.display(), err),
+    }
+    Ok(())
+}
+
+/// Runs scripts in a realm with a `print` function that prints its arguments
+/// on a line, stopping those that take more than `max_steps` instructions.
+pub struct Evaluator {
+    realm: Realm,
+    max_steps: Option<u64>,
+    /// The lines printed by the script that's running.
+    printed: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Evaluator {
+    pub fn new(max_steps: Option<u64>) -> Self {
+        let mut realm = Realm::new();
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let print_to = Rc::clone(&printed);
+        realm.register_fn(
+            "print",
+            Box::new(move |args| {
+                let args: Vec<String> = args.iter().map(Value::to_js_string).collect();
+                let mut printed = print_to.borrow_mut();
+                printed.extend_from_slice(args.join(" ").as_bytes());
+                printed.push(b'\n');
+                Value::Undefined
+            }),
+        );
+        Evaluator {
+            realm,
+            max_steps,
+            printed,
+        }
+    }
+
+    /// Run the script and write what it printed to `out`, once it's
+    /// stopped. The error is from writing to `out`.
+    fn evaluate_to(
+        &self,
+        emit_result: &EmitResult,
+        out: &mut impl Write,
+    ) -> io::Result<Result<Value, EvalError>> {
+        let result = match self.max_steps {
+            Some(max_steps) => self.realm.evaluate_with_limit(emit_result, max_steps),
+            None => self.realm.evaluate(emit_result),
+        };
+        out.write_all(&self.printed.take())?;
+        Ok(result)
+    }
+}
+
+/// Which of the results of a script typed at the prompt to print: a set of
+/// the flags below, combined with `|`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputSelect(u8);
+
+impl OutputSelect {
+    /// The AST.
+    pub const AST: OutputSelect = OutputSelect(1);
+
+    /// The `EmitResult`.
+    pub const EMIT: OutputSelect = OutputSelect(2);
+
+    /// The disassembled bytecode.
+    pub const DIS: OutputSelect = OutputSelect(4);
+
+    /// The value of the script, after running it.
+    pub const EVAL: OutputSelect = OutputSelect(8);
+
+    pub const NONE: OutputSelect = OutputSelect(0);
+    pub const ALL: OutputSelect = OutputSelect(15);
+
+    /// The flag named `name`, as in `--show ast,dis`: `ast`, `emit`, `dis`
+    /// or `eval`.
+    pub fn from_name(name: &str) -> Option<OutputSelect> {
+        match name {
+            "ast" => Some(OutputSelect::AST),
+            "emit" => Some(OutputSelect::EMIT),
+            "dis" => Some(OutputSelect::DIS),
+            "eval" => Some(OutputSelect::EVAL),
+            _ => None,
+        }
+    }
+
+    /// True if all the flags in `other` are set.
+    pub fn contains(self, other: OutputSelect) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for OutputSelect {
+    type Output = OutputSelect;
+
+    fn bitor(self, other: OutputSelect) -> OutputSelect {
+        OutputSelect(self.0 | other.0)
+    }
+}
+
+/// How `handle_script_to` prints the AST.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AstFormat {
+    /// `{:#?}`, one field per line.
+    #[default]
+    Pretty,
+
+    /// `{:?}`, on one line.
+    Compact,
+
+    /// Not at all, even if `OutputSelect::AST` is selected, as when only the
+    /// bytecode is of interest.
+    None,
+}
+
+impl AstFormat {
+    /// The format named `name`, as in `--ast-format compact`: `pretty`,
+    /// `compact` or `none`.
+    pub fn from_name(name: &str) -> Option<AstFormat> {
+        match name {
+            "pretty" => Some(AstFormat::Pretty),
+            "compact" => Some(AstFormat::Compact),
+            "none" => Some(AstFormat::None),
+            _ => None,
+        }
+    }
+
+    /// `what`, without the AST if it isn't printed.
+    fn select(self, what: OutputSelect) -> OutputSelect {
+        match self {
+            AstFormat::None => OutputSelect(what.0 & !OutputSelect::AST.0),
+            _ => what,
+        }
+    }
+
+    fn write(self, out: &mut impl Write, ast: &impl fmt::Debug) -> io::Result<()> {
+        match self {
+            AstFormat::Pretty => writeln!(out, "{:#?}", ast),
+            AstFormat::Compact => writeln!(out, "{:?}", ast),
+            AstFormat::None => Ok(()),
+        }
+    }
+}
+
+/// Write the parts of `out` selected by `what`, each after a blank line but
+/// the first.
+struct Sections<'a, W: Write> {
+    out: &'a mut W,
+    what: OutputSelect,
+    empty: bool,
+}
+
+impl<'a, W: Write> Sections<'a, W> {
+    /// True if `part` is selected, after starting it.
+    fn start(&mut self, part: OutputSelect) -> io::Result<bool> {
+        if !self.what.contains(part) {
+            return Ok(false);
+        }
+        if !self.empty {
+            writeln!(self.out)?;
+        }
+        self.empty = false;
+        Ok(true)
+    }
+}
+
+/// Print the parts of `script` selected by `what` to `out`: its AST, in
+/// `ast_format`, and then compile and run it and print the `EmitResult`, the
+/// bytecode and its value, after the lines it printed. The script isn't
+/// compiled if only the AST is selected. Errors from compiling and running
+/// it go to stderr; the error is from writing to `out`.
+pub fn handle_script_to<'alloc>(
+    script: Script<'alloc>,
+    options: &EmitOptions,
+    evaluator: &Evaluator,
+    out: &mut impl Write,
+    what: OutputSelect,
+    ast_format: AstFormat,
+) -> io::Result<()> {
+    let what = ast_format.select(what);
+    if what.contains(OutputSelect::AST) {
+        ast_format.write(out, &script)?;
+    }
+    handle_program_to(script.into(), options, evaluator, out, what)
+}
+
+/// `handle_script_to`, printing to stdout. An error writing to it is
+/// printed on stderr.
+pub fn handle_script<'alloc>(
+    script: Script<'alloc>,
+    options: &EmitOptions,
+    evaluator: &Evaluator,
+    what: OutputSelect,
+    ast_format: AstFormat,
+) {
+    let result = handle_script_to(
+        script,
+        options,
+        evaluator,
+        &mut io::stdout(),
+        what,
+        ast_format,
+    );
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+    }
+}
+
+/// `handle_module_to`, printing to stdout, like `handle_script`.
+pub fn handle_module<'alloc>(
+    module: Module<'alloc>,
+    options: &EmitOptions,
+    evaluator: &Evaluator,
+    what: OutputSelect,
+    ast_format: AstFormat,
+) {
+    let result = handle_module_to(
+        module,
+        options,
+        evaluator,
+        &mut io::stdout(),
+        what,
+        ast_format,
+    );
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+    }
+}
+
+/// Print the parts of `module` selected by `what` to `out`, like
+/// `handle_script_to`.
+pub fn handle_module_to<'alloc>(
+    module: Module<'alloc>,
+    options: &EmitOptions,
+    evaluator: &Evaluator,
+    out: &mut impl Write,
+    what: OutputSelect,
+    ast_format: AstFormat,
+) -> io::Result<()> {
+    let what = ast_format.select(what);
+    if what.contains(OutputSelect::AST) {
+        ast_format.write(out, &module)?;
+    }
+    handle_program_to(module.into(), options, evaluator, out, what)
+}
+
+fn handle_program_to<'alloc>(
+    mut program: Program<'alloc>,
+    options: &EmitOptions,
+    evaluator: &Evaluator,
+    out: &mut impl Write,
+    what: OutputSelect,
+) -> io::Result<()> {
+    let mut sections = Sections {
+        out,
+        what,
+        empty: !what.contains(OutputSelect::AST),
+    };
+    if !what.contains(OutputSelect::EMIT)
+        && !what.contains(OutputSelect::DIS)
+        && !what.contains(OutputSelect::EVAL)
+    {
+        return Ok(());
+    }
+    match emitter::emit_with_options(&mut program, options) {
         Err(err) => {
             eprintln!("error: {}", err);
         }
         Ok(emit_result) => {
-            println!("\n{:#?}", emit_result);
-            println!("\n{}", emitter::dis(&emit_result.bytecode));
+            if sections.start(OutputSelect::EMIT)? {
+                writeln!(sections.out, "{:#?}", emit_result)?;
+            }
+            if sections.start(OutputSelect::DIS)? {
+                emitter::dis_to(&emit_result.bytecode, sections.out)?;
+            }
+            if sections.start(OutputSelect::EVAL)? {
+                match evaluator.evaluate_to(&emit_result, sections.out)? {
+                    Ok(value) => writeln!(sections.out, "{:?}", value)?,
+                    Err(err) => eprintln!("error: {}", err),
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-            let eval_result = interpreter::evaluate(&emit_result);
-            println!("{:?}", eval_result);
+/// Print the stats returned by `parse_file_or_dir` or `parse_stdin`, as
+/// `options.format` and `options.error_summary` say.
+pub fn print_stats(result: io::Result<DemoStats>, options: DemoOptions) {
+    match result {
+        Ok(stats) => match options.format {
+            OutputFormat::Text => {
+                println!("{:#?}", stats);
+                stats.print_timing();
+                println!("{}", stats.summary_line());
+                if options.error_summary {
+                    stats.print_error_summary();
+                } else {
+                    stats.print_error_histogram();
+                }
+                if options.node_stats {
+                    stats.print_node_counts();
+                }
+            }
+            OutputFormat::Json => stats.print_json(),
+        },
+        Err(err) => {
+            eprintln!("{}", err);
         }
     }
 }
 
-pub fn read_print_loop() {
+/// Read and run scripts, or modules if `module` is true, typed at a prompt
+/// until the end of input. Scripts can call `print` to print values, and
+/// are stopped after `max_steps` instructions if it's given. They're
+/// compiled with `options`, except that no source map or line notes are
+/// made, since the source isn't kept. What's printed for each one is
+/// selected by `show` and `ast_format`; see `handle_script_to`.
+///
+/// Each input runs in the same realm, so the variables it declares at the
+/// top level are there for the next ones, and its value, the value of the
+/// last expression statement that ran, is printed. As in browser consoles,
+/// and unlike in a single script, an input can declare a `let` or `const`
+/// variable that an earlier input already declared, which then gets the new
+/// value; within one input, that's still an error.
+///
+/// If stdin isn't a terminal, e.g. when the input is piped in, there's no
+/// one to prompt: parse all of it at once instead, as `parse_stdin` does.
+pub fn read_print_loop(
+    module: bool,
+    options: &EmitOptions,
+    max_steps: Option<u64>,
+    show: OutputSelect,
+    ast_format: AstFormat,
+) {
+    let options = &EmitOptions {
+        source_map: false,
+        line_notes: false,
+        ..*options
+    };
+    if !io::stdin().is_terminal() {
+        let options = DemoOptions::default();
+        print_stats(parse_stdin(module, options), options);
+        return;
+    }
+
+    // Globals set by one input are kept for the next ones.
+    let evaluator = &Evaluator::new(max_steps);
     loop {
         let allocator = &Bump::new();
-        match parser::read_script_interactively(allocator, "js> ", "..> ") {
+        let result = if module {
+            parser::read_module_interactively(allocator, "js> ", "..> ")
+                .map(|module| handle_module(module.unbox(), options, evaluator, show, ast_format))
+        } else {
+            parser::read_script_interactively(allocator, "js> ", "..> ")
+                .map(|script| handle_script(script.unbox(), options, evaluator, show, ast_format))
+        };
+        match result {
             Err(ParseError::UnexpectedEnd) => {
                 println!();
                 break;
             }
             Err(err) => {
                 eprintln!("error: {}", err);
+                if err.kind() == ParseErrorKind::ModuleDeclarationInScript {
+                    eprintln!("note: run `parser --module` to enter modules");
+                }
             }
-            Ok(script) => {
-                handle_script(script.unbox());
-            }
+            Ok(()) => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A new directory for a test to make files in, named after it.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jsparagus-{}-{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // The paths of the files `dir_files` finds in `dir`, relative to it,
+    // sorted.
+    fn files(dir: &Path, filter: &FileFilter) -> Vec<PathBuf> {
+        let mut names: Vec<_> = dir_files(dir, filter)
+            .map(|file| file.unwrap().0.strip_prefix(dir).unwrap().to_path_buf())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let mut stats = DemoStats {
+            total_parse_nanos: 500_000_000,
+            ..DemoStats::new_single(3_000_000, true)
+        };
+        stats.add(&DemoStats::new_single(1_000_000, false));
+        assert_eq!(
+            stats.summary_line(),
+            "4.000 MB in 0.500 s, 8.0 MB/s, 4.0 files/s, 50.0% parsed (1/2)"
+        );
+
+        // Nothing was attempted, so there's no rate to divide by.
+        assert_eq!(
+            DemoStats::new().summary_line(),
+            "0.000 MB in 0.000 s, 0.0 MB/s, 0.0 files/s, 0.0% parsed (0/0)"
+        );
+        // Files were, but no time was measured.
+        assert_eq!(
+            DemoStats::new_single(1_000_000, true).summary_line(),
+            "1.000 MB in 0.000 s, 0.0 MB/s, 0.0 files/s, 100.0% parsed (1/1)"
+        );
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let dir = test_dir("max-file-size");
+        fs::write(dir.join("small.js"), "a;").unwrap();
+        fs::write(dir.join("large.js"), "a + b;").unwrap();
+        let options = DemoOptions {
+            quiet: true,
+            max_file_size: 5,
+            ..DemoOptions::default()
+        };
+        let filter = FileFilter::default();
+        let parse =
+            |name: &str| parse_file_or_dir(&dir.join(name), options, &filter, Some(1)).unwrap();
+        let small = parse("small.js");
+        let large = parse("large.js");
+        let all = parse(".");
+        // The file isn't read, so it doesn't matter that it isn't there.
+        let missing = parse_file(&mut Bump::new(), &dir.join("missing.js"), 6, options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((small.skipped_too_large, small.files_parsed), (0, 1));
+        // Files that are too large aren't counted as attempted.
+        assert_eq!((large.skipped_too_large, large.files_attempted), (1, 0));
+        assert_eq!((all.skipped_too_large, all.files_parsed), (1, 1));
+        assert_eq!((missing.skipped_too_large, missing.files_attempted), (1, 0));
+        assert!(all.summary_line().ends_with("; skipped 1 too large"));
+    }
+
+    #[test]
+    fn test_error_summary() {
+        let options = DemoOptions {
+            quiet: true,
+            ..DemoOptions::default()
+        };
+        let parse = |name: &str, source: &str| {
+            let size_bytes = source.len() as u64;
+            let contents = Ok(source.as_bytes().to_vec());
+            let path = Path::new(name);
+            parse_contents(&mut Bump::new(), path, size_bytes, contents, false, options).unwrap()
+        };
+        let summary = |stats: &DemoStats| {
+            let mut out = Vec::new();
+            stats.write_error_summary(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let mut stats = parse("a.js", "a;");
+        assert_eq!(summary(&stats), "no errors\n");
+
+        // The counts from each file are added up, and the sample file of
+        // each kind is the first one by path that has it. Kinds with the
+        // same count are in the order of `ParseErrorKind`.
+        stats.add(&parse("d.js", "export default 1;"));
+        stats.add(&parse("c.js", "let a; let a;"));
+        stats.add(&parse("b.js", "const b = 1; var b;"));
+        stats.add(&parse("e.js", "x = (1 +"));
+        assert_eq!(
+            summary(&stats),
+            "top 3 of 3 kinds of error, 4 errors in all:\n\
+             \x20      2  50.0%  DuplicateBinding           e.g. b.js\n\
+             \x20      1  25.0%  UnexpectedEnd              e.g. e.js\n\
+             \x20      1  25.0%  ModuleDeclarationInScript  e.g. d.js\n"
+        );
+
+        // Only the most common kinds are listed.
+        let mut stats = DemoStats::new();
+        for (count, kind) in ParseErrorKind::ALL.iter().enumerate() {
+            stats.add_errors(*kind, count + 1, Path::new("a.js"));
+        }
+        let summary = summary(&stats);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), ERROR_SUMMARY_LENGTH + 1);
+        assert!(lines[0].starts_with(&format!(
+            "top {} of {} kinds of error",
+            ERROR_SUMMARY_LENGTH,
+            ParseErrorKind::ALL.len()
+        )));
+        let last = ParseErrorKind::ALL.last().unwrap().to_str();
+        assert!(lines[1].contains(&format!("  {} ", last)));
+    }
+
+    #[test]
+    fn test_parallel_stats() {
+        let dir = test_dir("parallel");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for i in 0..20 {
+            let source = if i % 3 == 0 { "a +;" } else { "f(a, [b]);" };
+            fs::write(dir.join(format!("{}.js", i)), source).unwrap();
+            fs::write(dir.join(format!("sub/{}.js", i)), source.repeat(i)).unwrap();
+        }
+        let options = DemoOptions {
+            quiet: true,
+            ..DemoOptions::default()
+        };
+        let filter = FileFilter::default();
+        let stats = |jobs| parse_file_or_dir(&dir, options, &filter, jobs).unwrap();
+        let sequential = stats(Some(1));
+        let parallel = stats(Some(4));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+        assert_eq!(parallel.error_histogram(), sequential.error_histogram());
+        assert_eq!(sequential.files_attempted, 40);
+    }
+
+    #[test]
+    fn test_handle_script_to() {
+        let handle = |source: &str, what| {
+            let allocator = &Bump::new();
+            let script = parse_script(allocator, source).unwrap();
+            let mut out = Vec::new();
+            let options = &EmitOptions::default();
+            let evaluator = &Evaluator::new(None);
+            handle_script_to(
+                script.unbox(),
+                options,
+                evaluator,
+                &mut out,
+                what,
+                AstFormat::Compact,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        // What the script prints comes before its value.
+        let source = "print('a', 1); print(); 2";
+        assert_eq!(handle(source, OutputSelect::EVAL), "a 1\n\n2\n");
+        assert_eq!(handle(source, OutputSelect::NONE), "");
+
+        let ast = handle(source, OutputSelect::AST);
+        assert!(ast.starts_with("Script {"));
+        assert_eq!(ast.lines().count(), 1);
+
+        // Each part after the first follows a blank line.
+        let all = handle(source, OutputSelect::ALL);
+        assert!(all.starts_with(&ast));
+        assert!(all.ends_with("\n\na 1\n\n2\n"));
+        let dis = handle(source, OutputSelect::DIS | OutputSelect::EVAL);
+        assert!(all.contains(&dis));
+        assert!(dis.ends_with("\n\na 1\n\n2\n"));
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        // `*` and `?` match within one component.
+        assert!(glob_matches("*.js", "a.js"));
+        assert!(glob_matches("*.js", ".js"));
+        assert!(!glob_matches("*.js", "src/a.js"));
+        assert!(glob_matches("a*b*.js", "axxbyy.js"));
+        assert!(glob_matches("?.js", "a.js"));
+        assert!(!glob_matches("?.js", "ab.js"));
+        assert!(!glob_matches("?.js", ".js"));
+        assert!(glob_matches("src/*/?.js", "src/lib/a.js"));
+        assert!(!glob_matches("src/*/?.js", "src/a.js"));
+
+        // `**` matches any number of components, including none.
+        assert!(glob_matches("src/**/*.js", "src/a.js"));
+        assert!(glob_matches("src/**/*.js", "src/lib/util/a.js"));
+        assert!(!glob_matches("src/**/*.js", "test/a.js"));
+        assert!(glob_matches("**", "a/b/c.js"));
+        assert!(glob_matches("**/test/*.js", "test/a.js"));
+        assert!(glob_matches("**/test/*.js", "a/b/test/a.js"));
+        assert!(!glob_matches("**/test/*.js", "a/test/b/a.js"));
+
+        // Other characters match only themselves.
+        assert!(glob_matches("src/a.js", "src/a.js"));
+        assert!(!glob_matches("src/a.js", "src/a.jsx"));
+        assert!(!glob_matches("src/a.js", "src/b.js"));
+    }
+
+    #[test]
+    fn test_includes_file() {
+        let mut filter = FileFilter::default();
+        assert!(filter.includes_file(Path::new("a.js")));
+        assert!(filter.includes_file(Path::new("lib/a.mjs")));
+        assert!(filter.includes_file(Path::new("a.cjs")));
+        assert!(!filter.includes_file(Path::new("README.md")));
+        assert!(!filter.includes_file(Path::new("js")));
+        assert!(!filter.includes_file(Path::new("a.js.map")));
+
+        filter.extensions = vec!["jsx".to_string()];
+        assert!(filter.includes_file(Path::new("a.jsx")));
+        assert!(!filter.includes_file(Path::new("a.js")));
+
+        // The extension is checked as well as the glob.
+        filter.extensions = vec!["js".to_string()];
+        filter.glob = Some("src/**/*".to_string());
+        assert!(filter.includes_file(Path::new("src/lib/a.js")));
+        assert!(!filter.includes_file(Path::new("src/lib/a.ts")));
+        assert!(!filter.includes_file(Path::new("test/a.js")));
+    }
+
+    #[test]
+    fn test_hidden_files() {
+        let dir = test_dir("hidden");
+        fs::create_dir_all(dir.join(".cache")).unwrap();
+        fs::write(dir.join("a.js"), "a;").unwrap();
+        fs::write(dir.join(".hidden.js"), "b;").unwrap();
+        fs::write(dir.join(".cache/c.js"), "c;").unwrap();
+
+        let mut filter = FileFilter::default();
+        let skipped = files(&dir, &filter);
+        filter.hidden = true;
+        let included = files(&dir, &filter);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(skipped, [PathBuf::from("a.js")]);
+        assert_eq!(
+            included,
+            [
+                PathBuf::from(".cache/c.js"),
+                PathBuf::from(".hidden.js"),
+                PathBuf::from("a.js")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let dir = test_dir("gitignore");
+        fs::create_dir_all(dir.join("build")).unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".gitignore"), "build/\n*.min.js\n").unwrap();
+        fs::write(dir.join("a.js"), "a;").unwrap();
+        fs::write(dir.join("a.min.js"), "a;").unwrap();
+        fs::write(dir.join("build/b.js"), "b;").unwrap();
+        fs::write(dir.join("src/.gitignore"), "c.js\n!d.min.js\n").unwrap();
+        fs::write(dir.join("src/c.js"), "c;").unwrap();
+        fs::write(dir.join("src/d.min.js"), "d;").unwrap();
+        fs::write(dir.join("src/e.js"), "e;").unwrap();
+
+        let mut filter = FileFilter::default();
+        let all = files(&dir, &filter);
+        filter.gitignore = true;
+        let not_ignored = files(&dir, &filter);
+        // The `.gitignore` files of the directories around the one parsed
+        // apply too.
+        let in_src = files(&dir.join("src"), &filter);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(all.len(), 6);
+        // A `.gitignore` in a subdirectory adds to the rules of the ones
+        // above it, and can make exceptions to them.
+        assert_eq!(
+            not_ignored,
+            [
+                PathBuf::from("a.js"),
+                PathBuf::from("src/d.min.js"),
+                PathBuf::from("src/e.js")
+            ]
+        );
+        assert_eq!(in_src, [PathBuf::from("d.min.js"), PathBuf::from("e.js")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = test_dir("symlinks");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.js"), "a;").unwrap();
+        fs::write(dir.join("sub/b.js"), "b;").unwrap();
+        symlink("sub/b.js", dir.join("link.js")).unwrap();
+        // A loop, back to the directory the link is in.
+        symlink("..", dir.join("sub/loop")).unwrap();
+
+        let mut filter = FileFilter::default();
+        let not_followed = files(&dir, &filter);
+        filter.follow_symlinks = true;
+        let followed = files(&dir, &filter);
+
+        // A link to nowhere can't be followed, and is counted as an error
+        // instead of ending the walk.
+        symlink("missing.js", dir.join("broken.js")).unwrap();
+        let options = DemoOptions {
+            quiet: true,
+            ..DemoOptions::default()
+        };
+        let stats = parse_dir(&mut Bump::new(), &dir, options, &filter, Some(1)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            not_followed,
+            [PathBuf::from("a.js"), PathBuf::from("sub/b.js")]
+        );
+        assert_eq!(
+            followed,
+            [
+                PathBuf::from("a.js"),
+                PathBuf::from("link.js"),
+                PathBuf::from("sub/b.js")
+            ]
+        );
+        assert_eq!((stats.files_parsed, stats.io_errors), (3, 1));
+        assert!(stats.summary_line().ends_with("; skipped 1 unreadable"));
+    }
+
+    #[test]
+    fn test_skip_non_js() {
+        let options = DemoOptions {
+            quiet: true,
+            skip_non_js: true,
+            ..DemoOptions::default()
+        };
+        let parse = |source: &str, options: DemoOptions| {
+            let size_bytes = source.len() as u64;
+            let contents = Ok(source.as_bytes().to_vec());
+            let path = Path::new("file.js");
+            parse_contents(&mut Bump::new(), path, size_bytes, contents, false, options).unwrap()
+        };
+        let without_skipping = DemoOptions {
+            skip_non_js: false,
+            ..options
+        };
+
+        let json = "{\"name\": \"jsparagus\", \"version\": [1, 2]}\n";
+        let bash = "#!/bin/bash\necho \"$1\"\n";
+        for source in [json, bash] {
+            let stats = parse(source, options);
+            assert_eq!((stats.skipped_non_js, stats.files_attempted), (1, 0));
+
+            let stats = parse(source, without_skipping);
+            assert_eq!(stats.skipped_non_js, 0);
+            assert_eq!((stats.files_attempted, stats.files_parsed), (1, 0));
+        }
+
+        for source in [
+            "#!/usr/bin/env -S node --harmony\nf();\n",
+            "{ f(); }",
+            "[1, 2]",
+            "{}",
+            " { }\n",
+        ] {
+            let stats = parse(source, options);
+            assert_eq!(stats.skipped_non_js, 0);
+            assert_eq!((stats.files_attempted, stats.files_parsed), (1, 1));
+        }
+
+        let mut stats = parse(json, options);
+        stats.add(&parse(bash, options));
+        assert!(stats.summary_line().ends_with("; skipped 2 not JavaScript"));
+    }
+
+    #[test]
+    fn test_node_stats() {
+        let options = DemoOptions {
+            quiet: true,
+            node_stats: true,
+            ..DemoOptions::default()
+        };
+        let parse = |source: &str| {
+            let size_bytes = source.len() as u64;
+            let contents = Ok(source.as_bytes().to_vec());
+            let path = Path::new("file.js");
+            parse_contents(&mut Bump::new(), path, size_bytes, contents, false, options).unwrap()
+        };
+
+        let mut stats = parse("if (a) { f(a.b, 1); }");
+        stats.add(&parse("var [b] = c; class C { m() {} }"));
+        stats.add(&parse("syntax error"));
+        let counts: Vec<(&str, usize)> = stats.node_counts.into_iter().collect();
+        // Declarations, patterns and class elements count too, as do the
+        // nodes that are only parts of others, like `Arguments`.
+        assert_eq!(
+            counts,
+            [
+                ("Arguments", 1),
+                ("ArrayBinding", 1),
+                ("BindingIdentifier", 2),
+                ("Block", 1),
+                ("BlockStatement", 1),
+                ("CallExpression", 1),
+                ("ClassDeclaration", 1),
+                ("ExpressionStatement", 1),
+                ("FormalParameters", 1),
+                ("FunctionBody", 1),
+                ("Identifier", 6),
+                ("IdentifierExpression", 4),
+                ("IdentifierName", 1),
+                ("IfStatement", 1),
+                ("LiteralNumericExpression", 1),
+                ("Method", 1),
+                ("MethodDefinition", 1),
+                ("Script", 2),
+                ("StaticMemberExpression", 1),
+                ("StaticPropertyName", 1),
+                ("Var", 1),
+                ("VariableDeclaration", 1),
+                ("VariableDeclarationStatement", 1),
+                ("VariableDeclarator", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! The parse, emit and evaluate pipeline the `parser` binary prints the
+//! stages of, for other programs to run scripts with.
+
+use bumpalo::Bump;
+use emitter::EmitError;
+use interpreter::{EvalError, Realm, Value};
+use parser::ParseErrorKind;
+use std::error::Error;
+use std::fmt;
+
+/// The stage of `compile_and_run` that failed.
+#[derive(Clone, Debug)]
+pub enum PipelineError {
+    /// The script didn't parse. The error borrows the allocator the script
+    /// was parsed in, so only its kind and message are kept.
+    Parse(ParseErrorKind, String),
+    Emit(EmitError),
+    Eval(EvalError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Parse(_, message) => write!(f, "parse error: {}", message),
+            PipelineError::Emit(err) => write!(f, "emit error: {}", err),
+            PipelineError::Eval(err) => write!(f, "eval error: {}", err),
+        }
+    }
+}
+
+impl Error for PipelineError {}
+
+/// Parse `source` as a script, compile it and run it in a new realm, and
+/// return its completion value, as `handle_script_to` prints it with the
+/// default options.
+pub fn compile_and_run(source: &str) -> Result<Value, PipelineError> {
+    let allocator = &Bump::new();
+    let script = parser::parse_script(allocator, source)
+        .map_err(|err| PipelineError::Parse(err.kind(), err.message()))?;
+    let emit_result = emitter::emit(&mut script.unbox().into()).map_err(PipelineError::Emit)?;
+    Realm::new()
+        .evaluate(&emit_result)
+        .map_err(PipelineError::Eval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_and_run() {
+        assert_eq!(compile_and_run("1 + 2").unwrap(), Value::Number(3.0));
+
+        match compile_and_run("x = ;") {
+            Err(PipelineError::Parse(kind, _)) => assert_eq!(kind, ParseErrorKind::SyntaxError),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+        assert!(matches!(
+            compile_and_run("throw 1;"),
+            Err(PipelineError::Eval(EvalError::Thrown(Value::Number(_))))
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 mod demo;
 
+use demo::{AstFormat, DemoOptions, FileFilter, OutputFormat, OutputSelect};
+use emitter::EmitOptions;
 use std::env;
 
 // jemalloc is temporarily disabled due to a known upstream bug (macOS crashes
@@ -10,18 +12,203 @@ use std::env;
 // #[global_allocator]
 // static ALLOC: Jemalloc = Jemalloc;
 
+const USAGE: &str =
+    "usage: parser [[--fold-constants] [--dead-code-elimination] [--strict] [--max-steps N] [--show ast,emit,dis,eval] [--ast-format pretty|compact|none] [--module | --emit-sourcemap FILE | --opcode-stats FILE | --compile OUT.jsbc FILE] | [--max-steps N] --run FILE.jsbc | [--recover] [--error-summary] [--max-file-size BYTES] [--skip-non-js] [--node-stats] [--quiet] [--progress] [--jobs N] [--format text|json] [--ext EXT,...] [--glob PATTERN] [--exclude DIR,...] [--gitignore] [--hidden] [--follow-symlinks] FILE/DIR/- | --tokens FILE | --emit-ast-json FILE]";
+
+/// Parse the arguments for parsing a file or directory and run it.
+fn parse_files(args: &[String]) {
+    let mut options = DemoOptions::default();
+    let mut filter = FileFilter::default();
+    let mut jobs = None;
+    let mut filename = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--recover" => options.recover = true,
+            "--quiet" => options.quiet = true,
+            "--progress" => options.progress = true,
+            "--error-summary" => options.error_summary = true,
+            "--skip-non-js" => options.skip_non_js = true,
+            "--node-stats" => options.node_stats = true,
+            "--gitignore" => filter.gitignore = true,
+            "--hidden" => filter.hidden = true,
+            "--follow-symlinks" => filter.follow_symlinks = true,
+            "--max-file-size" => match iter.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.max_file_size = n,
+                None => return eprintln!("{}", USAGE),
+            },
+            "--jobs" => match iter.next().and_then(|n| n.parse().ok()) {
+                Some(n) if n > 0 => jobs = Some(n),
+                _ => return eprintln!("{}", USAGE),
+            },
+            "--format" => match iter.next().map(String::as_str) {
+                Some("text") => options.format = OutputFormat::Text,
+                Some("json") => options.format = OutputFormat::Json,
+                _ => return eprintln!("{}", USAGE),
+            },
+            "--ext" => match iter.next() {
+                Some(extensions) => {
+                    filter.extensions = split_list(extensions)
+                        .map(|extension| extension.trim_start_matches('.').to_string())
+                        .collect()
+                }
+                None => return eprintln!("{}", USAGE),
+            },
+            "--glob" => match iter.next() {
+                Some(glob) => filter.glob = Some(glob.clone()),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--exclude" => match iter.next() {
+                Some(names) => filter.exclude = split_list(names).map(str::to_string).collect(),
+                None => return eprintln!("{}", USAGE),
+            },
+            _ if filename.is_none() => filename = Some(arg),
+            _ => return eprintln!("{}", USAGE),
+        }
+    }
+
+    match filename {
+        Some(filename) if filename == "-" => {
+            demo::print_stats(demo::parse_stdin(false, options), options)
+        }
+        Some(filename) => demo::print_stats(
+            demo::parse_file_or_dir(filename, options, &filter, jobs),
+            options,
+        ),
+        None => eprintln!("{}", USAGE),
+    }
+}
+
+/// The items of a comma-separated list given as an argument. An empty
+/// argument is an empty list.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').filter(|item| !item.is_empty())
+}
+
+/// Remove the emitter flags from `args` and return the options they set.
+fn take_emit_options(args: &mut Vec<String>) -> EmitOptions<'static> {
+    let mut options = EmitOptions::default();
+    args.retain(|arg| match arg.as_str() {
+        "--fold-constants" => {
+            options.fold_constants = true;
+            false
+        }
+        "--dead-code-elimination" => {
+            options.dead_code_elimination = true;
+            false
+        }
+        "--strict" => {
+            options.strict = true;
+            false
+        }
+        _ => true,
+    });
+    options
+}
+
+/// The number of instructions scripts are stopped after without
+/// `--max-steps`, so that one that never ends doesn't hang the demo.
+const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// Remove `--max-steps N` from `args` and return N, or `DEFAULT_MAX_STEPS`
+/// if it's not there. N is 0 for no limit, which is None. Err if N is
+/// missing or not a number.
+fn take_max_steps(args: &mut Vec<String>) -> Result<Option<u64>, ()> {
+    match args.iter().position(|arg| arg == "--max-steps") {
+        None => Ok(Some(DEFAULT_MAX_STEPS)),
+        Some(i) => {
+            let max_steps = args.get(i + 1).and_then(|n| n.parse().ok()).ok_or(())?;
+            args.drain(i..i + 2);
+            Ok(Some(max_steps).filter(|&n| n > 0))
+        }
+    }
+}
+
+/// Remove `--show LIST` from `args` and return the parts it selects, or
+/// all of them if it's not there. Err if LIST is missing or names something
+/// else.
+fn take_show(args: &mut Vec<String>) -> Result<OutputSelect, ()> {
+    match args.iter().position(|arg| arg == "--show") {
+        None => Ok(OutputSelect::ALL),
+        Some(i) => {
+            let list = args.get(i + 1).ok_or(())?;
+            let show = split_list(list).try_fold(OutputSelect::NONE, |show, name| {
+                OutputSelect::from_name(name)
+                    .map(|part| show | part)
+                    .ok_or(())
+            })?;
+            args.drain(i..i + 2);
+            Ok(show)
+        }
+    }
+}
+
+/// Remove `--ast-format FORMAT` from `args` and return the format, or the
+/// pretty one if it's not there. Err if FORMAT is missing or unknown.
+fn take_ast_format(args: &mut Vec<String>) -> Result<AstFormat, ()> {
+    match args.iter().position(|arg| arg == "--ast-format") {
+        None => Ok(AstFormat::default()),
+        Some(i) => {
+            let format = args
+                .get(i + 1)
+                .and_then(|name| AstFormat::from_name(name))
+                .ok_or(())?;
+            args.drain(i..i + 2);
+            Ok(format)
+        }
+    }
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let emit_options = take_emit_options(&mut args);
+    let max_steps = match take_max_steps(&mut args) {
+        Ok(max_steps) => max_steps,
+        Err(()) => return eprintln!("{}", USAGE),
+    };
+    let show = match take_show(&mut args) {
+        Ok(show) => show,
+        Err(()) => return eprintln!("{}", USAGE),
+    };
+    let ast_format = match take_ast_format(&mut args) {
+        Ok(ast_format) => ast_format,
+        Err(()) => return eprintln!("{}", USAGE),
+    };
     match args.len() {
-        1 => demo::read_print_loop(),
-        2 => match demo::parse_file_or_dir(&args[1]) {
-            Ok(stats) => {
-                println!("{:#?}", stats);
+        1 => demo::read_print_loop(false, &emit_options, max_steps, show, ast_format),
+        2 if args[1] == "--module" => {
+            demo::read_print_loop(true, &emit_options, max_steps, show, ast_format)
+        }
+        3 if args[1] == "--tokens" => {
+            if let Err(err) = demo::print_tokens(&args[2]) {
+                eprintln!("{}", err);
+            }
+        }
+        3 if args[1] == "--emit-ast-json" => {
+            if let Err(err) = demo::print_ast_json(&args[2]) {
+                eprintln!("{}", err);
+            }
+        }
+        3 if args[1] == "--emit-sourcemap" => {
+            if let Err(err) = demo::print_source_map(&args[2], &emit_options) {
+                eprintln!("{}", err);
+            }
+        }
+        3 if args[1] == "--opcode-stats" => {
+            if let Err(err) = demo::print_opcode_stats(&args[2], &emit_options) {
+                eprintln!("{}", err);
+            }
+        }
+        4 if args[1] == "--compile" => {
+            if let Err(err) = demo::compile_file(&args[3], &args[2], &emit_options) {
+                eprintln!("{}", err);
             }
-            Err(err) => {
+        }
+        3 if args[1] == "--run" => {
+            if let Err(err) = demo::run_bytecode_file(&args[2], max_steps) {
                 eprintln!("{}", err);
             }
-        },
-        _ => eprintln!("usage: parser [FILE/DIR]"),
+        }
+        _ => parse_files(&args[1..]),
     }
 }
@@ -2,15 +2,28 @@
 //!
 //! Converts AST nodes to bytecode.
 
-use super::emitter::{BytecodeOffset, EmitError, EmitResult, InstructionWriter};
+use super::emitter::{
+    u24, AsyncFunctionResolveKind, BindingKind, BindingNote, BindingSlot, BytecodeOffset,
+    CheckIsObjectKind, EmitError, EmitOptions, EmitResult, FunctionCode, InstructionWriter,
+    ScopeKind, ScopeNote, SymbolCode, TryNoteKind,
+};
 use super::opcode::Opcode;
+use super::source_map::{LineNotes, SourceMap};
+use ast::arena;
+use ast::source_location_accessor::SourceLocationAccessor;
 use ast::types::*;
+use ast::visit::{
+    walk_expression, walk_getter, walk_method, walk_parameter, walk_setter, walk_statement, Visitor,
+};
+use ast::{FunctionFlags, SourceLocation};
+use std::collections::HashSet;
 
 /// Emit a program, converting the AST directly to bytecode.
-pub fn emit_program(ast: &Program) -> Result<EmitResult, EmitError> {
-    let mut emitter = AstEmitter {
-        emit: InstructionWriter::new(),
-    };
+///
+/// Only scripts are emitted. A module is `NotImplemented`, and so is
+/// top-level `await`, which only modules can use; the parser accepts it.
+pub fn emit_program(ast: &Program, options: &EmitOptions) -> Result<EmitResult, EmitError> {
+    let mut emitter = AstEmitter::new(options.strict, None);
 
     match ast {
         Program::Script(script) => emitter.emit_script(script)?,
@@ -19,104 +32,1101 @@ pub fn emit_program(ast: &Program) -> Result<EmitResult, EmitError> {
         }
     }
 
-    Ok(emitter.emit.into_emit_result())
+    let source_map = if options.source_map || options.line_notes {
+        Some(SourceMap::new(
+            options.source_name,
+            options.source,
+            emitter.emit.positions(),
+        ))
+    } else {
+        None
+    };
+    let mut result = emitter.emit.into_emit_result();
+    if options.line_notes {
+        result.line_notes = source_map.as_ref().map(LineNotes::new);
+    }
+    if options.source_map {
+        result.source_map = source_map;
+    }
+    Ok(result)
 }
 
 struct AstEmitter {
     emit: InstructionWriter,
+
+    /// True if the code is strict mode code, because of `EmitOptions` or the
+    /// `strict` of the script or function body.
+    strict: bool,
+
+    /// For each optional chain being emitted, innermost last, the jumps
+    /// taken when it short-circuits, to be patched to its end.
+    optional_chain_jumps: Vec<Vec<BytecodeOffset>>,
+
+    /// The statements being emitted that `break` and `continue` jump out
+    /// of, innermost last.
+    controls: Vec<Control>,
+
+    /// The function being emitted, or None for the top level of the script.
+    function: Option<FunctionInfo>,
+
+    /// The index of the innermost scope being emitted in
+    /// `EmitResult::scopes`, if any.
+    scope: Option<usize>,
+
+    /// The scopes the code being emitted is in, innermost last, to look up
+    /// the names it refers to. Those of the enclosing functions only have
+    /// their bindings in environments.
+    names: Vec<NameScope>,
+
+    /// The names that the functions in the script or function being emitted
+    /// refer to, which are kept in environments if it binds them, for the
+    /// functions to find them when they're called. See `captured_names`.
+    captured: HashSet<String>,
+
+    /// The number of local variable slots used so far.
+    num_locals: u24,
+}
+
+struct FunctionInfo {
+    /// The names of the parameters, in the order of their argument slots.
+    /// A parameter that's a pattern has no name, and the names it binds are
+    /// local variables.
+    params: Vec<Option<String>>,
+
+    /// The kind of function, as the parser flagged its node. An async
+    /// function runs as a generator that `await` suspends, and settles the
+    /// promise the call returned when it's done.
+    flags: FunctionFlags,
+}
+
+/// The local variable holding the generator object, in generators and async
+/// functions.
+const GENERATOR_LOCAL: u24 = 0;
+
+/// The bindings of a scope being emitted, with their slots.
+#[derive(Clone)]
+struct NameScope {
+    bindings: Vec<BindingNote>,
+
+    /// True if some of the bindings are in an environment, which the code in
+    /// the scope runs with, and which `GetAliasedVar` counts as a hop.
+    has_environment: bool,
+}
+
+enum Control {
+    Loop(LoopControl),
+    Switch(SwitchControl),
+    /// A `try` block with a `finally` block, or the `catch` block between
+    /// them, whose `finally` block runs before jumping out of it.
+    Finally(FinallyControl),
+    /// A labelled statement, which `break` with its label jumps to the end
+    /// of. `continue` with its label goes on to the loop it labels.
+    Label(LabelControl),
+    /// The body of a `with` statement, whose object is taken off the scope
+    /// chain before jumping out of it.
+    With,
+    /// A scope with an environment, which is popped before jumping out of
+    /// it.
+    Environment,
+}
+
+/// Where the `break` and `continue` statements of a loop jump to.
+struct LoopControl {
+    /// The stack depth in the loop body.
+    depth: usize,
+
+    /// The `break` jumps, to be patched to the end of the loop.
+    breaks: Vec<BytecodeOffset>,
+
+    /// The `continue` jumps, to be patched to the end of the loop body,
+    /// where the next iteration starts: the update of a `for` loop, or the
+    /// test of a `do-while` loop.
+    continues: Vec<BytecodeOffset>,
+
+    /// True for a `for-of` loop, whose iterator is closed when returning out
+    /// of it.
+    closes_iterator: bool,
+}
+
+/// Where the `break` statements of a `switch` statement jump to.
+struct SwitchControl {
+    /// The stack depth in the case clauses.
+    depth: usize,
+
+    /// The `break` jumps, to be patched to the end of the statement.
+    breaks: Vec<BytecodeOffset>,
+}
+
+struct LabelControl {
+    name: String,
+
+    /// The stack depth in the labelled statement.
+    depth: usize,
+
+    /// The `break` jumps, to be patched to the end of the statement.
+    breaks: Vec<BytecodeOffset>,
+}
+
+struct FinallyControl {
+    /// The stack depth in the `try` block.
+    depth: usize,
+
+    /// The `Gosub` jumps to the `finally` block, to be patched to it.
+    gosubs: Vec<BytecodeOffset>,
+}
+
+#[derive(Clone, Copy)]
+enum ForInOrOf {
+    In,
+    Of,
 }
 
 impl AstEmitter {
-    fn emit_script(&mut self, ast: &Script) -> Result<(), EmitError> {
-        for statement in &ast.statements {
-            self.emit_statement(statement)?;
+    fn new(strict: bool, function: Option<FunctionInfo>) -> Self {
+        let num_locals = match function {
+            Some(FunctionInfo { flags, .. }) if flags.is_generator || flags.is_async => {
+                GENERATOR_LOCAL + 1
+            }
+            _ => 0,
+        };
+        AstEmitter {
+            emit: InstructionWriter::new(),
+            strict,
+            optional_chain_jumps: Vec::new(),
+            controls: Vec::new(),
+            function,
+            scope: None,
+            names: Vec::new(),
+            captured: HashSet::new(),
+            num_locals,
         }
+    }
+
+    // An emitter for a function made in the code being emitted, which can
+    // refer to the bindings in its environments.
+    fn new_nested(&self, strict: bool, function: FunctionInfo) -> Self {
+        let mut emitter = AstEmitter::new(strict, Some(function));
+        emitter.names = self
+            .names
+            .iter()
+            .map(|scope| NameScope {
+                bindings: scope
+                    .bindings
+                    .iter()
+                    .filter(|binding| matches!(binding.slot, BindingSlot::Environment(_)))
+                    .cloned()
+                    .collect(),
+                has_environment: scope.has_environment,
+            })
+            .collect();
+        emitter
+    }
+
+    fn emit_script(&mut self, ast: &Script) -> Result<(), EmitError> {
+        self.strict |= ast.strict;
+        self.captured = captured_names(&[], &ast.statements, None);
+        let mut bindings = Vec::new();
+        add_var_bindings(&ast.statements, &mut bindings);
+        add_lexical_bindings(&ast.statements, &mut bindings);
+        self.enter_scope(ScopeKind::Script, bindings)?;
+        self.emit_statements(&ast.statements)?;
         self.emit.ret_rval();
 
         Ok(())
     }
 
+    fn emit_block(&mut self, block: &Block) -> Result<(), EmitError> {
+        let mut bindings = Vec::new();
+        add_lexical_bindings(&block.statements, &mut bindings);
+        let enclosing = self.enter_scope(ScopeKind::Block, bindings)?;
+        let result = self.emit_statements(&block.statements);
+        self.leave_scope(enclosing);
+        result
+    }
+
+    // Add a scope binding `bindings` to the scope notes, as the innermost
+    // scope, and return the index of the one it's in, for `leave_scope` to
+    // restore when it ends. Scopes other than scripts and functions are only
+    // added if they bind names.
+    //
+    // The bindings of a script are global variables. Elsewhere, they're
+    // local variables, or, if a function may refer to them, in the
+    // environment the scope pushes. A function pushes it with `PushVarEnv`
+    // and keeps it until it returns; other scopes push it with
+    // `PushLexicalEnv` and pop it when they end.
+    fn enter_scope(
+        &mut self,
+        kind: ScopeKind,
+        mut bindings: Vec<BindingNote>,
+    ) -> Result<Option<usize>, EmitError> {
+        let enclosing = self.scope;
+        if kind != ScopeKind::Script {
+            self.assign_slots(&mut bindings)?;
+        }
+        let has_environment = bindings
+            .iter()
+            .any(|binding| matches!(binding.slot, BindingSlot::Environment(_)));
+        if has_environment {
+            // `GetAliasedVar` takes the number of hops as a u8.
+            let depth = self
+                .names
+                .iter()
+                .filter(|scope| scope.has_environment)
+                .count();
+            if depth >= u8::MAX as usize {
+                return Err(EmitError::NotImplemented("too many nested scopes"));
+            }
+        }
+        self.names.push(NameScope {
+            bindings: bindings.clone(),
+            has_environment,
+        });
+        if bindings.is_empty() && !matches!(kind, ScopeKind::Script | ScopeKind::Function) {
+            return Ok(enclosing);
+        }
+        let index = self.emit.add_scope(ScopeNote {
+            kind,
+            enclosing,
+            bindings,
+        });
+        self.scope = Some(index);
+        if has_environment {
+            if kind == ScopeKind::Function {
+                self.emit.push_var_env(index as u32);
+            } else {
+                self.emit.push_lexical_env(index as u32);
+                self.controls.push(Control::Environment);
+            }
+        }
+        Ok(enclosing)
+    }
+
+    // End the innermost scope, popping its environment if it has one.
+    fn leave_scope(&mut self, enclosing: Option<usize>) {
+        let scope = self.names.pop().expect("unbalanced scopes");
+        if scope.has_environment {
+            match self.controls.pop() {
+                Some(Control::Environment) => {}
+                _ => panic!("unbalanced controls"),
+            }
+            self.emit.pop_lexical_env();
+        }
+        self.scope = enclosing;
+    }
+
+    // Give the bindings of a scope other than the script's their slots:
+    // parameters keep their argument slots and internal bindings their
+    // local ones, unless they're captured, and the others, which
+    // `add_var_bindings` and the like make global, get new local slots.
+    // Captured bindings get environment slots instead.
+    fn assign_slots(&mut self, bindings: &mut [BindingNote]) -> Result<(), EmitError> {
+        let mut num_environment_slots = 0;
+        for binding in bindings.iter_mut() {
+            if binding.kind != BindingKind::Internal && self.captured.contains(&binding.name) {
+                binding.slot = BindingSlot::Environment(num_environment_slots);
+                num_environment_slots += 1;
+            } else if binding.slot == BindingSlot::Global {
+                // `GetLocal` takes a u24.
+                if self.num_locals >= 1 << 24 {
+                    return Err(EmitError::NotImplemented("too many local variables"));
+                }
+                binding.slot = BindingSlot::Local(self.num_locals);
+                self.num_locals += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // Where the binding `name` refers to is kept, with the number of
+    // environments to go out of to get to it if it's in one, or None for a
+    // global variable.
+    fn lookup(&self, name: &str) -> Option<(u8, BindingSlot)> {
+        let mut hops = 0;
+        for scope in self.names.iter().rev() {
+            let binding = scope
+                .bindings
+                .iter()
+                .find(|binding| binding.name == name && binding.kind != BindingKind::Internal);
+            match binding.map(|binding| binding.slot) {
+                Some(BindingSlot::Global) => return None,
+                Some(slot) => return Some((hops, slot)),
+                None => {}
+            }
+            if scope.has_environment {
+                hops += 1;
+            }
+        }
+        None
+    }
+
+    // The bindings of the scope of the function being emitted, with the
+    // parameters `params` and the body `statements`.
+    fn function_bindings(
+        &self,
+        params: &[Parameter],
+        statements: &[Statement],
+    ) -> Vec<BindingNote> {
+        let mut bindings: Vec<BindingNote> = Vec::new();
+        let info = self
+            .function
+            .as_ref()
+            .expect("the emitter is for a function");
+        for (index, name) in info.params.iter().enumerate() {
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+            // With duplicate parameters, the last one wins.
+            let slot = BindingSlot::Argument(index as u16);
+            match bindings.iter_mut().find(|binding| binding.name == *name) {
+                Some(binding) => binding.slot = slot,
+                None => bindings.push(BindingNote {
+                    name: name.clone(),
+                    kind: BindingKind::Parameter,
+                    slot,
+                }),
+            }
+        }
+        let mut bound_names = Vec::new();
+        for param in params {
+            if let pattern @ Binding::BindingPattern(_) = parameter_binding(param) {
+                add_bound_names(pattern, &mut bound_names);
+            }
+        }
+        for binding in global_bindings(bound_names, BindingKind::Parameter) {
+            add_binding(&mut bindings, binding);
+        }
+        if info.flags.is_generator || info.flags.is_async {
+            bindings.push(BindingNote {
+                name: ".generator".to_string(),
+                kind: BindingKind::Internal,
+                slot: BindingSlot::Local(GENERATOR_LOCAL),
+            });
+        }
+        add_var_bindings(statements, &mut bindings);
+        add_lexical_bindings(statements, &mut bindings);
+        bindings
+    }
+
+    // The function declarations of a block or function body are defined
+    // before its other statements run, so they can be called before them.
+    fn emit_statements(&mut self, statements: &[Statement]) -> Result<(), EmitError> {
+        for statement in statements {
+            if let Statement::FunctionDeclaration(function) = statement {
+                self.emit_function_declaration(function)?;
+            }
+        }
+        for statement in statements {
+            if !matches!(statement, Statement::FunctionDeclaration(_)) {
+                self.emit_statement(statement)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_function_declaration(&mut self, function: &Function) -> Result<(), EmitError> {
+        let name = match &function.name {
+            Some(BindingIdentifier { name, .. }) => name.value,
+            None => return Err(EmitError::NotImplemented("TODO: export default function")),
+        };
+        self.emit_assign_name_with(name, |this| this.emit_lambda(function))?;
+        self.emit.pop();
+        Ok(())
+    }
+
+    // Compile the function separately and push it.
+    fn emit_lambda(&mut self, function: &Function) -> Result<(), EmitError> {
+        self.check_not_in_with()?;
+        let flags = function.flags;
+        if flags.is_async && flags.is_generator {
+            return Err(EmitError::NotImplemented("TODO: async generators"));
+        }
+        let params = parameter_items(&function.params)?;
+        let mut emitter = self.new_function(self.strict, params, flags)?;
+        emitter.emit_function_body(params, &function.body)?;
+        let name = function.name.as_ref().map(|name| name.name.value);
+        let is_constructor = !flags.is_generator && !flags.is_async;
+        let index = self.add_function_code(name, emitter, is_constructor, false);
+        self.emit.lambda(index);
+        Ok(())
+    }
+
+    // Compile the arrow function separately and push it, as for a function.
+    fn emit_arrow(
+        &mut self,
+        flags: FunctionFlags,
+        params: &FormalParameters,
+        body: &ArrowExpressionBody,
+        strict: bool,
+    ) -> Result<(), EmitError> {
+        self.check_not_in_with()?;
+        // They're those of the code the arrow function is made in, which
+        // calling it doesn't pass it.
+        if uses_enclosing_this(params, body) {
+            return Err(EmitError::NotImplemented(
+                "TODO: this, new.target and super in arrow functions",
+            ));
+        }
+        let params = parameter_items(params)?;
+        let mut emitter = self.new_function(strict, params, flags)?;
+        match body {
+            ArrowExpressionBody::FunctionBody(body) => emitter.emit_function_body(params, body)?,
+            ArrowExpressionBody::Expression(expression) => {
+                emitter.emit_function_code(params, &[], Some(expression))?
+            }
+        }
+        let index = self.add_function_code(None, emitter, false, false);
+        self.emit.lambda(index);
+        Ok(())
+    }
+
+    // An emitter for the body of a function with the parameters `params`,
+    // made in the code being emitted.
+    fn new_function(
+        &self,
+        strict: bool,
+        params: &[Parameter],
+        flags: FunctionFlags,
+    ) -> Result<Self, EmitError> {
+        let names: Vec<Option<String>> = params
+            .iter()
+            .map(|param| match parameter_binding(param) {
+                Binding::BindingIdentifier(BindingIdentifier { name, .. }) => {
+                    Some(name.value.to_owned())
+                }
+                _ => None,
+            })
+            .collect();
+        // `GetArg` takes a u16.
+        if names.len() > u16::MAX as usize {
+            return Err(EmitError::NotImplemented("too many parameters"));
+        }
+        Ok(self.new_nested(
+            strict,
+            FunctionInfo {
+                params: names,
+                flags,
+            },
+        ))
+    }
+
+    // Add the function whose body `emitter` emitted to the functions that
+    // `Lambda` can create, returning its index. `new` can call it if it
+    // `is_constructor`; a class constructor is instead made into a class.
+    fn add_function_code(
+        &mut self,
+        name: Option<&str>,
+        emitter: AstEmitter,
+        is_constructor: bool,
+        is_class_constructor: bool,
+    ) -> u32 {
+        let info = emitter
+            .function
+            .as_ref()
+            .expect("the emitter is for a function");
+        let num_params = info.params.len() as u32;
+        let is_generator = info.flags.is_generator;
+        let body = emitter.emit.into_emit_result();
+        self.emit.add_function(FunctionCode {
+            name: name.map(str::to_owned),
+            num_params,
+            frame_slot_count: body.frame_slot_count(),
+            is_generator,
+            is_constructor,
+            is_class_constructor,
+            body,
+        })
+    }
+
+    // The parameters with default values or patterns are set first. Then a
+    // generator creates its generator object, which the call of the
+    // generator function returns when the `InitialYield` suspends it. Each
+    // `next` call then runs it to the next `yield`.
+    fn emit_function_body(
+        &mut self,
+        params: &[Parameter],
+        body: &FunctionBody,
+    ) -> Result<(), EmitError> {
+        if body.is_lazy {
+            return Err(EmitError::NotImplemented(
+                "TODO: lazy function bodies (see parser::force_function)",
+            ));
+        }
+        self.strict |= body.strict;
+        self.emit_function_code(params, &body.statements, None)
+    }
+
+    // Emit a function body with `statements`, or the concise body of an
+    // arrow function, `result`, which is what it returns.
+    fn emit_function_code(
+        &mut self,
+        params: &[Parameter],
+        statements: &[Statement],
+        result: Option<&Expression>,
+    ) -> Result<(), EmitError> {
+        self.captured = captured_names(params, statements, result);
+        let bindings = self.function_bindings(params, statements);
+        self.enter_scope(ScopeKind::Function, bindings)?;
+        self.emit_captured_arguments();
+        if self.is_async() {
+            return self.emit_async_function_body(params, statements, result);
+        }
+        self.emit_parameters(params)?;
+
+        let is_generator = self.is_generator();
+        if is_generator {
+            self.emit.generator();
+            self.emit.set_local(GENERATOR_LOCAL);
+            self.emit.pop();
+            self.emit_yield_op(true);
+            self.emit.pop();
+        }
+
+        self.emit_statements(statements)?;
+        if let Some(result) = result {
+            self.emit_expression(result)?;
+            self.emit.set_rval();
+        }
+
+        if is_generator {
+            self.emit.new_init(0);
+            self.emit.undefined();
+            self.emit_finish_iterator_result(true);
+            self.emit.set_rval();
+            self.emit.get_local(GENERATOR_LOCAL);
+            self.emit.final_yield_rval();
+        } else {
+            self.emit.ret_rval();
+        }
+        Ok(())
+    }
+
+    // An async function creates its generator object first, then runs its
+    // parameters and body in a `try` block, so that what they throw rejects
+    // the promise instead of being thrown to the caller. The call returns
+    // the promise when the function first suspends at an `await`, or
+    // finishes.
+    fn emit_async_function_body(
+        &mut self,
+        params: &[Parameter],
+        statements: &[Statement],
+        result: Option<&Expression>,
+    ) -> Result<(), EmitError> {
+        self.emit.generator();
+        self.emit.set_local(GENERATOR_LOCAL);
+        self.emit.pop();
+
+        let depth = self.emit.stack_depth();
+        let offset_try = self.emit.bytecode_offset();
+        self.emit.try_(0);
+        let start = self.emit.bytecode_offset();
+        self.emit_parameters(params)?;
+        self.emit_statements(statements)?;
+        match result {
+            Some(result) => self.emit_expression(result)?,
+            None => self.emit.undefined(),
+        }
+        self.emit.set_rval();
+        self.emit_async_return(AsyncFunctionResolveKind::Fulfill);
+        self.emit.patch_jump_target(vec![offset_try]);
+
+        self.emit.add_try_note(TryNoteKind::Catch, depth, &start);
+        self.emit.set_stack_depth(depth);
+        self.emit.jump_target();
+        self.emit.exception();
+        self.emit.set_rval();
+        self.emit_async_return(AsyncFunctionResolveKind::Reject);
+        Ok(())
+    }
+
+    // Settle the async function's promise with the return value, and return
+    // the promise.
+    fn emit_async_return(&mut self, kind: AsyncFunctionResolveKind) {
+        self.emit.get_rval();
+        self.emit.get_local(GENERATOR_LOCAL);
+        self.emit.async_resolve(kind);
+        self.emit.set_rval();
+        self.emit.get_local(GENERATOR_LOCAL);
+        self.emit.final_yield_rval();
+    }
+
+    // Copy the arguments of the parameters in the function's environment
+    // there, from their argument slots.
+    fn emit_captured_arguments(&mut self) {
+        let info = self
+            .function
+            .as_ref()
+            .expect("the emitter is for a function");
+        let mut copies = Vec::new();
+        for (index, name) in info.params.iter().enumerate() {
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+            // With duplicate parameters, the last one wins.
+            if info.params[index + 1..].contains(&Some(name.clone())) {
+                continue;
+            }
+            if let Some((0, BindingSlot::Environment(slot))) = self.lookup(name) {
+                copies.push((index as u16, slot));
+            }
+        }
+        for (index, slot) in copies {
+            self.emit.get_arg(index);
+            self.emit.set_aliased_var(0, slot);
+            self.emit.pop();
+        }
+    }
+
+    // Set the parameters with default values or patterns. The others are
+    // read from their argument slots, or their copies in the environment.
+    fn emit_parameters(&mut self, params: &[Parameter]) -> Result<(), EmitError> {
+        for (index, param) in params.iter().enumerate() {
+            if let Parameter::Binding(Binding::BindingIdentifier(_)) = param {
+                continue;
+            }
+            self.emit.get_arg(index as u16);
+            self.emit_parameter(param)?;
+            self.emit.pop();
+        }
+        Ok(())
+    }
+
+    fn is_generator(&self) -> bool {
+        self.function
+            .as_ref()
+            .is_some_and(|info| info.flags.is_generator)
+    }
+
+    fn is_async(&self) -> bool {
+        self.function
+            .as_ref()
+            .is_some_and(|info| info.flags.is_async)
+    }
+
+    fn emit_class_declaration(&mut self, class: &ClassDeclaration) -> Result<(), EmitError> {
+        let name = class.name.name.value;
+        // TODO: classes can be used before their declaration runs.
+        self.emit_assign_name_with(name, |this| {
+            this.emit_class(Some(name), class.super_.as_deref(), &class.elements)
+        })?;
+        self.emit.pop();
+        Ok(())
+    }
+
+    // Push the class, which is made from its constructor, with the other
+    // methods on its `prototype` object and the static ones on itself. The
+    // static fields and blocks then run with the class as `this`.
+    //
+    // The private names the class declares are bound in a scope around its
+    // body, each to a new private name every time the class is made, so the
+    // objects of other classes, and of other evaluations of this one, don't
+    // have its private fields. The functions of the class refer to them, so
+    // they're in its environment.
+    //
+    // TODO: the class's name in its body, as for named function expressions.
+    fn emit_class(
+        &mut self,
+        name: Option<&str>,
+        heritage: Option<&Expression>,
+        elements: &[arena::Box<ClassElement>],
+    ) -> Result<(), EmitError> {
+        self.check_not_in_with()?;
+        if heritage.is_some() {
+            return Err(EmitError::NotImplemented("TODO: extends"));
+        }
+        let private_names: Vec<&str> = elements
+            .iter()
+            .filter_map(|element| match &**element {
+                ClassElement::FieldDefinition {
+                    name: ClassElementName::PrivateFieldName(PrivateIdentifier { value, .. }),
+                    ..
+                } => Some(*value),
+                _ => None,
+            })
+            .collect();
+        if private_names.is_empty() {
+            return self.emit_class_body(name, elements);
+        }
+        let mut bindings = Vec::new();
+        for private_name in &private_names {
+            self.captured.insert(private_name.to_string());
+            bindings.push(BindingNote {
+                name: private_name.to_string(),
+                kind: BindingKind::Const,
+                slot: BindingSlot::Global,
+            });
+        }
+        let enclosing = self.enter_scope(ScopeKind::Block, bindings)?;
+        for private_name in private_names {
+            self.emit.new_private_name(private_name);
+            self.emit_assign_name(private_name);
+            self.emit.pop();
+        }
+        let result = self.emit_class_body(name, elements);
+        self.leave_scope(enclosing);
+        result
+    }
+
+    // Push the private name `name`, which the private field instructions
+    // take on top of the stack, from the scope of the class declaring it.
+    fn emit_private_name(&mut self, name: &str) {
+        match self.lookup(name) {
+            Some((hops, slot)) => self.emit_get_slot(hops, slot),
+            // The parser checks that a class around it declares it.
+            None => panic!("undeclared private name {}", name),
+        }
+    }
+
+    fn emit_class_body(
+        &mut self,
+        name: Option<&str>,
+        elements: &[arena::Box<ClassElement>],
+    ) -> Result<(), EmitError> {
+        let mut constructor = None;
+        let mut fields = Vec::new();
+        let mut static_initializers = Vec::new();
+        for element in elements {
+            match &**element {
+                ClassElement::MethodDefinition {
+                    is_static: false,
+                    method: MethodDefinition::Method(method),
+                    ..
+                } if is_constructor(method) => constructor = Some(method),
+                ClassElement::MethodDefinition { .. } => {}
+                ClassElement::FieldDefinition {
+                    is_static: false, ..
+                } => fields.push(&**element),
+                ClassElement::FieldDefinition { .. } | ClassElement::StaticBlock { .. } => {
+                    static_initializers.push(&**element)
+                }
+            }
+        }
+        self.emit_class_constructor(name, constructor, &fields)?;
+
+        self.emit.new_init(0);
+        self.emit.dup_at(1);
+        self.emit.init_hidden_prop("constructor");
+        for element in elements {
+            match &**element {
+                ClassElement::MethodDefinition {
+                    is_static: false,
+                    method: MethodDefinition::Method(method),
+                    ..
+                } if is_constructor(method) => {}
+                ClassElement::MethodDefinition {
+                    is_static: false,
+                    method,
+                    ..
+                } => self.emit_method(method, true)?,
+                _ => {}
+            }
+        }
+        self.emit.init_locked_prop("prototype");
+
+        for element in elements {
+            if let ClassElement::MethodDefinition {
+                is_static: true,
+                method,
+                ..
+            } = &**element
+            {
+                self.emit_method(method, true)?;
+            }
+        }
+
+        if !static_initializers.is_empty() {
+            let index = self.emit_initializer(&static_initializers)?;
+            self.emit.lambda(index);
+            self.emit.dup_at(1);
+            self.emit.call(0);
+            self.emit.pop();
+        }
+        Ok(())
+    }
+
+    // Push the class made from the constructor, which is empty if there's
+    // none. Before its body, it defines the fields on the new object.
+    fn emit_class_constructor(
+        &mut self,
+        name: Option<&str>,
+        constructor: Option<&Method>,
+        fields: &[&ClassElement],
+    ) -> Result<(), EmitError> {
+        let params = match constructor {
+            Some(method) => parameter_items(&method.params)?,
+            None => &[],
+        };
+        // Class bodies are strict mode code.
+        let mut emitter = match constructor {
+            Some(method) => self.new_function(true, params, method.flags)?,
+            None => self.new_nested(
+                true,
+                FunctionInfo {
+                    params: Vec::new(),
+                    flags: FunctionFlags::default(),
+                },
+            ),
+        };
+        if !fields.is_empty() {
+            // The fields are initialized by a function of their own, as the
+            // constructor's parameters aren't in scope in their initializers.
+            let index = emitter.emit_initializer(fields)?;
+            emitter.emit.lambda(index);
+            emitter.emit.function_this();
+            emitter.emit.call(0);
+            emitter.emit.pop();
+        }
+        match constructor {
+            Some(method) => emitter.emit_function_body(params, &method.body)?,
+            None => emitter.emit.ret_rval(),
+        }
+        let index = self.add_function_code(name, emitter, false, true);
+        self.emit.lambda(index);
+        Ok(())
+    }
+
+    // Define the method, getter or setter on the object on the stack. The
+    // methods of classes aren't enumerable, and are strict mode code.
+    fn emit_method(&mut self, method: &MethodDefinition, in_class: bool) -> Result<(), EmitError> {
+        self.check_not_in_with()?;
+        let (property_name, prefix) = match method {
+            MethodDefinition::Method(method) => (&method.name, ""),
+            MethodDefinition::Getter(getter) => (&getter.property_name, "get "),
+            MethodDefinition::Setter(setter) => (&setter.property_name, "set "),
+        };
+        let name = match property_name {
+            PropertyName::StaticPropertyName(StaticPropertyName { value, .. }) => Some(*value),
+            PropertyName::ComputedPropertyName(ComputedPropertyName { expression, .. }) => {
+                self.emit_expression(expression)?;
+                None
+            }
+        };
+
+        let strict = in_class || self.strict;
+        let emitter = match method {
+            MethodDefinition::Method(method) => {
+                if method.flags.is_async && method.flags.is_generator {
+                    return Err(EmitError::NotImplemented("TODO: async generators"));
+                }
+                let params = parameter_items(&method.params)?;
+                let mut emitter = self.new_function(strict, params, method.flags)?;
+                emitter.emit_function_body(params, &method.body)?;
+                emitter
+            }
+            MethodDefinition::Getter(getter) => {
+                let mut emitter = self.new_function(strict, &[], getter.flags)?;
+                emitter.emit_function_body(&[], &getter.body)?;
+                emitter
+            }
+            MethodDefinition::Setter(setter) => {
+                let params = std::slice::from_ref(&setter.param);
+                let mut emitter = self.new_function(strict, params, setter.flags)?;
+                emitter.emit_function_body(params, &setter.body)?;
+                emitter
+            }
+        };
+        // TODO: the names of the functions with computed names, which are
+        // only known when running.
+        let function_name = name.map(|name| format!("{}{}", prefix, name));
+        let index = self.add_function_code(function_name.as_deref(), emitter, false, false);
+        self.emit.lambda(index);
+
+        match (method, name, in_class) {
+            (MethodDefinition::Method(_), Some(name), false) => self.emit.init_prop(name),
+            (MethodDefinition::Method(_), Some(name), true) => self.emit.init_hidden_prop(name),
+            (MethodDefinition::Method(_), None, false) => self.emit.init_elem(),
+            (MethodDefinition::Method(_), None, true) => self.emit.init_hidden_elem(),
+            (MethodDefinition::Getter(_), Some(name), false) => self.emit.init_prop_getter(name),
+            (MethodDefinition::Getter(_), Some(name), true) => {
+                self.emit.init_hidden_prop_getter(name)
+            }
+            (MethodDefinition::Getter(_), None, false) => self.emit.init_elem_getter(),
+            (MethodDefinition::Getter(_), None, true) => self.emit.init_hidden_elem_getter(),
+            (MethodDefinition::Setter(_), Some(name), false) => self.emit.init_prop_setter(name),
+            (MethodDefinition::Setter(_), Some(name), true) => {
+                self.emit.init_hidden_prop_setter(name)
+            }
+            (MethodDefinition::Setter(_), None, false) => self.emit.init_elem_setter(),
+            (MethodDefinition::Setter(_), None, true) => self.emit.init_hidden_elem_setter(),
+        }
+        Ok(())
+    }
+
+    // Compile the field definitions and static blocks to a function that
+    // runs them in order, defining the fields on `this`, and add it to the
+    // functions, returning its index.
+    fn emit_initializer(&mut self, elements: &[&ClassElement]) -> Result<u32, EmitError> {
+        let mut emitter = self.new_nested(
+            true,
+            FunctionInfo {
+                params: Vec::new(),
+                flags: FunctionFlags::default(),
+            },
+        );
+        for element in elements {
+            match element {
+                ClassElement::FieldDefinition { name, init, .. } => {
+                    emitter.emit.function_this();
+                    match init {
+                        Some(init) => emitter.emit_expression(init)?,
+                        None => emitter.emit.undefined(),
+                    }
+                    match name {
+                        ClassElementName::StaticPropertyName(StaticPropertyName {
+                            value, ..
+                        }) => emitter.emit.init_prop(value),
+                        ClassElementName::PrivateFieldName(PrivateIdentifier { value, .. }) => {
+                            emitter.emit_private_name(value);
+                            emitter.emit.init_private_field();
+                        }
+                        ClassElementName::ComputedPropertyName(_) => {
+                            return Err(EmitError::NotImplemented("TODO: computed property names"));
+                        }
+                    }
+                    emitter.emit.pop();
+                }
+                ClassElement::StaticBlock { statements, .. } => {
+                    emitter.emit_statements(statements)?;
+                }
+                ClassElement::MethodDefinition { .. } => {
+                    panic!("methods are defined by emit_method");
+                }
+            }
+        }
+        emitter.emit.ret_rval();
+        Ok(self.add_function_code(None, emitter, false, false))
+    }
+
     fn emit_statement(&mut self, ast: &Statement) -> Result<(), EmitError> {
+        self.emit.record_position(ast.get_loc().start);
         match ast {
-            Statement::ClassDeclaration(_) => {
-                return Err(EmitError::NotImplemented("TODO: ClassDeclaration"));
+            Statement::ClassDeclaration(class) => {
+                self.emit_class_declaration(class)?;
             }
-            Statement::BlockStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: BlockStatement"));
+            Statement::BlockStatement { block, .. } => {
+                self.emit_block(block)?;
             }
-            Statement::BreakStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: BreakStatement"));
+            Statement::BreakStatement { label, .. } => {
+                self.emit_break_or_continue(true, label.as_ref())?;
             }
-            Statement::ContinueStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ContinueStatement"));
+            Statement::ContinueStatement { label, .. } => {
+                self.emit_break_or_continue(false, label.as_ref())?;
             }
             Statement::DebuggerStatement { .. } => {
                 return Err(EmitError::NotImplemented("TODO: DebuggerStatement"));
             }
-            Statement::DoWhileStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: DoWhileStatement"));
+            Statement::DoWhileStatement { block, test, .. } => {
+                self.emit_do_while(block, test)?;
             }
             Statement::EmptyStatement { .. } => (),
             Statement::ExpressionStatement(ast) => {
                 self.emit_expression(ast)?;
-                self.emit.set_rval();
+                // Only scripts have a completion value. Functions return
+                // undefined unless they return another value.
+                if self.function.is_some() {
+                    self.emit.pop();
+                } else {
+                    self.emit.set_rval();
+                }
             }
-            Statement::ForInStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ForInStatement"));
+            Statement::ForInStatement {
+                left, right, block, ..
+            } => {
+                self.emit_for_in_or_of(ForInOrOf::In, left, right, block)?;
             }
-            Statement::ForOfStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ForOfStatement"));
+            Statement::ForOfStatement {
+                left, right, block, ..
+            } => {
+                self.emit_for_in_or_of(ForInOrOf::Of, left, right, block)?;
             }
-            Statement::ForStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ForStatement"));
+            Statement::ForStatement {
+                init,
+                test,
+                update,
+                block,
+                ..
+            } => {
+                self.emit_for(init.as_ref(), test.as_deref(), update.as_deref(), block)?;
             }
             Statement::IfStatement(if_statement) => {
                 self.emit_if(if_statement)?;
             }
-            Statement::LabeledStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: LabeledStatement"));
+            Statement::LabeledStatement { label, body, .. } => {
+                self.emit_labeled_statement(label, body)?;
             }
-            Statement::ReturnStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ReturnStatement"));
+            Statement::ReturnStatement { expression, .. } => {
+                self.emit_return(expression.as_deref())?;
             }
-            Statement::SwitchStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: SwitchStatement"));
+            Statement::SwitchStatement {
+                discriminant,
+                cases,
+                ..
+            } => {
+                self.emit_switch(discriminant, cases, None, &[])?;
             }
-            Statement::SwitchStatementWithDefault { .. } => {
-                return Err(EmitError::NotImplemented(
-                    "TODO: SwitchStatementWithDefault",
-                ));
+            Statement::SwitchStatementWithDefault {
+                discriminant,
+                pre_default_cases,
+                default_case,
+                post_default_cases,
+                ..
+            } => {
+                self.emit_switch(
+                    discriminant,
+                    pre_default_cases,
+                    Some(default_case),
+                    post_default_cases,
+                )?;
+            }
+            Statement::ThrowStatement { expression, .. } => {
+                self.emit_expression(expression)?;
+                self.emit.throw();
             }
-            Statement::ThrowStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ThrowStatement"));
+            Statement::TryCatchStatement {
+                body, catch_clause, ..
+            } => {
+                self.emit_try(body, Some(catch_clause), None)?;
             }
-            Statement::TryCatchStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: TryCatchStatement"));
+            Statement::TryFinallyStatement {
+                body,
+                catch_clause,
+                finalizer,
+                ..
+            } => {
+                self.emit_try(body, catch_clause.as_ref(), Some(finalizer))?;
             }
-            Statement::TryFinallyStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: TryFinallyStatement"));
+            Statement::VariableDeclarationStatement(ast) => {
+                self.emit_variable_declaration(ast)?;
             }
-            Statement::VariableDeclarationStatement(_ast) => {
-                return Err(EmitError::NotImplemented(
-                    "TODO: VariableDeclarationStatement",
-                ));
+            Statement::WhileStatement { test, block, .. } => {
+                self.emit_for(None, Some(test), None, block)?;
             }
-            Statement::WhileStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: WhileStatement"));
+            Statement::WithStatement { object, body, .. } => {
+                self.emit_with_statement(object, body)?;
             }
-            Statement::WithStatement { .. } => {
-                return Err(EmitError::NotImplemented("TODO: WithStatement"));
+            // Only when it's the body of an `if` statement or a label, as
+            // `emit_statements` defines the others.
+            Statement::FunctionDeclaration(function) => {
+                self.emit_function_declaration(function)?;
             }
-            Statement::FunctionDeclaration(_) => {
-                return Err(EmitError::NotImplemented("TODO: FunctionDeclaration"));
+            Statement::ErrorStatement { .. } => {
+                return Err(EmitError::NotImplemented("ErrorStatement"));
             }
         };
 
         Ok(())
     }
 
+    // TODO: substitute the global object for an undefined `this` in
+    // sloppy mode functions.
     fn emit_this(&mut self) -> Result<(), EmitError> {
-        Err(EmitError::NotImplemented("TODO: this"))
+        if self.function.is_some() {
+            self.emit.function_this();
+        } else {
+            self.emit.global_this();
+        }
+        Ok(())
     }
 
     fn emit_if(&mut self, if_statement: &IfStatement) -> Result<(), EmitError> {
@@ -148,231 +1158,1791 @@ impl AstEmitter {
         Ok(())
     }
 
-    fn emit_expression(&mut self, ast: &Expression) -> Result<(), EmitError> {
-        match ast {
-            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
-                ComputedMemberExpression {
-                    object: ExpressionOrSuper::Expression(object),
-                    expression,
-                    ..
-                },
-            )) => {
-                self.emit_expression(object)?;
-                self.emit_expression(expression)?;
-                self.emit.get_elem();
-            }
+    // `for (init; test; update) block`, and `while (test) block`, which is
+    // the same loop without `init` and `update`. The `let` bindings of
+    // `init` are copied to a new environment for each iteration, before
+    // `update`, if functions refer to them.
+    fn emit_for(
+        &mut self,
+        init: Option<&VariableDeclarationOrExpression>,
+        test: Option<&Expression>,
+        update: Option<&Expression>,
+        block: &Statement,
+    ) -> Result<(), EmitError> {
+        // The completion value of a loop whose body doesn't produce one.
+        self.emit.undefined();
+        self.emit.set_rval();
 
-            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
-                ComputedMemberExpression {
-                    object: ExpressionOrSuper::Super { .. },
-                    expression,
-                    ..
-                },
-            )) => {
-                self.emit_this()?;
+        let (bindings, is_let) = match init {
+            Some(VariableDeclarationOrExpression::VariableDeclaration(declaration)) => (
+                lexical_declaration_bindings(declaration),
+                matches!(declaration.kind, VariableDeclarationKind::Let { .. }),
+            ),
+            _ => (Vec::new(), false),
+        };
+        let enclosing = self.enter_scope(ScopeKind::Block, bindings)?;
+        let freshens = is_let && self.names.last().is_some_and(|scope| scope.has_environment);
+        let result = self.emit_for_in_scope(init, test, update, block, freshens);
+        self.leave_scope(enclosing);
+        result
+    }
+
+    fn emit_for_in_scope(
+        &mut self,
+        init: Option<&VariableDeclarationOrExpression>,
+        test: Option<&Expression>,
+        update: Option<&Expression>,
+        block: &Statement,
+        freshens: bool,
+    ) -> Result<(), EmitError> {
+        match init {
+            Some(VariableDeclarationOrExpression::VariableDeclaration(declaration)) => {
+                self.emit_variable_declaration(declaration)?;
+            }
+            Some(VariableDeclarationOrExpression::Expression(expression)) => {
                 self.emit_expression(expression)?;
-                self.emit.callee();
-                self.emit.super_base();
-                self.emit.get_elem_super();
+                self.emit.pop();
             }
+            None => {}
+        }
+        if freshens {
+            self.emit.freshen_lexical_env();
+        }
 
-            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
-                StaticMemberExpression {
-                    object: ExpressionOrSuper::Expression(object),
-                    property,
-                    ..
-                },
-            )) => {
-                self.emit_expression(object)?;
-                self.emit.get_prop(&property.value);
+        let depth_body = self.emit.stack_depth();
+        let head = self.emit_loop_head();
+        let offset_done = match test {
+            Some(test) => {
+                self.emit_expression(test)?;
+                let offset_done = self.emit.bytecode_offset();
+                self.emit.if_eq(0);
+                self.emit.jump_target();
+                Some(offset_done)
             }
+            None => None,
+        };
 
-            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
-                StaticMemberExpression {
-                    object: ExpressionOrSuper::Super { .. },
-                    property,
-                    ..
-                },
-            )) => {
-                self.emit_this()?;
-                self.emit.callee();
-                self.emit.super_base();
-                self.emit.get_prop_super(&property.value);
-            }
+        let control = self.emit_loop_body(depth_body, block)?;
+        self.emit_continue_target(control.continues, depth_body);
+        if freshens {
+            self.emit.freshen_lexical_env();
+        }
+        if let Some(update) = update {
+            self.emit_expression(update)?;
+            self.emit.pop();
+        }
+        self.emit_goto_back(head);
 
-            Expression::MemberExpression(MemberExpression::PrivateFieldExpression(
-                PrivateFieldExpression { .. },
-            )) => {
-                return Err(EmitError::NotImplemented("PrivateFieldExpression"));
-            }
+        let mut ends = control.breaks;
+        ends.extend(offset_done);
+        if !ends.is_empty() {
+            self.emit.set_stack_depth(depth_body);
+            self.emit_jump_target(ends);
+        }
+        Ok(())
+    }
 
-            Expression::ClassExpression(_) => {
-                return Err(EmitError::NotImplemented("TODO: ClassExpression"));
-            }
+    fn emit_do_while(&mut self, block: &Statement, test: &Expression) -> Result<(), EmitError> {
+        self.emit.undefined();
+        self.emit.set_rval();
 
-            Expression::LiteralBooleanExpression { value, .. } => {
-                self.emit.emit_boolean(*value);
-            }
+        let depth_body = self.emit.stack_depth();
+        let head = self.emit_loop_head();
+        let control = self.emit_loop_body(depth_body, block)?;
+        self.emit_continue_target(control.continues, depth_body);
+        self.emit_expression(test)?;
+        let offset = self.emit.bytecode_offset().offset;
+        self.emit.if_ne(head as i32 - offset as i32);
 
-            Expression::LiteralInfinityExpression { .. } => {
-                self.emit.double(std::f64::INFINITY);
-            }
+        if !control.breaks.is_empty() {
+            self.emit.set_stack_depth(depth_body);
+            self.emit_jump_target(control.breaks);
+        }
+        Ok(())
+    }
 
-            Expression::LiteralNullExpression { .. } => {
-                self.emit.null();
-            }
+    // Emit the body of a loop that isn't a `for-in` or `for-of` loop,
+    // returning the jumps the `break` and `continue` statements in it made.
+    fn emit_loop_body(
+        &mut self,
+        depth_body: usize,
+        block: &Statement,
+    ) -> Result<LoopControl, EmitError> {
+        self.controls.push(Control::Loop(LoopControl {
+            depth: depth_body,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            closes_iterator: false,
+        }));
+        let result = self.emit_statement(block);
+        let control = match self.controls.pop() {
+            Some(Control::Loop(control)) => control,
+            _ => panic!("unbalanced controls"),
+        };
+        result?;
+        Ok(control)
+    }
 
-            Expression::LiteralNumericExpression { value, .. } => {
-                self.emit_numeric_expression(*value);
-            }
+    // `for (x in o)` visits the enumerable property names of `o`, with
+    // `Iter`, `MoreIter` and `EndIter`. `for (x of o)` uses the iterator
+    // protocol: it calls `o[Symbol.iterator]()`, then the iterator's `next`
+    // method until the result's `done` is true, and on `break` calls the
+    // iterator's `return` method if it has one.
+    fn emit_for_in_or_of(
+        &mut self,
+        kind: ForInOrOf,
+        left: &VariableDeclarationOrAssignmentTarget,
+        right: &Expression,
+        block: &Statement,
+    ) -> Result<(), EmitError> {
+        // The completion value of a loop whose body doesn't produce one.
+        self.emit.undefined();
+        self.emit.set_rval();
 
-            Expression::LiteralRegExpExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: LiteralRegExpExpression"));
-            }
+        self.emit_expression(right)?;
+        match kind {
+            ForInOrOf::In => self.emit.iter(),
+            ForInOrOf::Of => self.emit_get_iterator(),
+        }
 
-            Expression::LiteralStringExpression { value, .. } => {
-                self.emit.string(value);
+        // In the loop body, the stack holds the for-in iterator, or `next` and
+        // the for-of iterator.
+        let depth_body = self.emit.stack_depth();
+        let head = self.emit_loop_head();
+        let offset_done = match kind {
+            ForInOrOf::In => {
+                self.emit.more_iter();
+                self.emit.is_no_iter();
+                let offset_done = self.emit.bytecode_offset();
+                self.emit.if_ne(0);
+                offset_done
             }
-
-            Expression::ArrayExpression(ast) => {
-                self.emit_array_expression(ast)?;
+            ForInOrOf::Of => {
+                self.emit.dup2();
+                self.emit.call_iter();
+                self.emit.check_is_obj(CheckIsObjectKind::IteratorNext);
+                self.emit.dup();
+                self.emit.get_prop("done");
+                let offset_done = self.emit.bytecode_offset();
+                self.emit.if_ne(0);
+                self.emit.get_prop("value");
+                offset_done
             }
+        };
+        self.controls.push(Control::Loop(LoopControl {
+            depth: depth_body,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            closes_iterator: matches!(kind, ForInOrOf::Of),
+        }));
 
-            Expression::ArrowExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ArrowExpression"));
+        // The `let` and `const` bindings are made anew for each iteration, in
+        // a scope around the body.
+        let bindings = match left {
+            VariableDeclarationOrAssignmentTarget::VariableDeclaration(declaration) => {
+                lexical_declaration_bindings(declaration)
             }
+            VariableDeclarationOrAssignmentTarget::AssignmentTarget(_) => Vec::new(),
+        };
+        let result = self
+            .enter_scope(ScopeKind::Block, bindings)
+            .and_then(|enclosing| {
+                let result = self.emit_for_in_or_of_assignment(left).and_then(|()| {
+                    self.emit.pop();
+                    self.emit_statement(block)
+                });
+                self.leave_scope(enclosing);
+                result
+            });
+        let control = match self.controls.pop() {
+            Some(Control::Loop(control)) => control,
+            _ => panic!("unbalanced controls"),
+        };
+        result?;
+        self.emit_continue_target(control.continues, depth_body);
+        self.emit_goto_back(head);
 
-            Expression::AssignmentExpression {
-                binding,
-                expression,
-                ..
-            } => {
-                self.emit_assignment_expression(binding, expression)?;
+        match kind {
+            ForInOrOf::In => {
+                // `EndIter` takes the iterator and the value from `MoreIter`,
+                // so push a value in its place when breaking out.
+                if !control.breaks.is_empty() {
+                    self.emit.set_stack_depth(depth_body);
+                    self.emit_jump_target(control.breaks);
+                    self.emit.undefined();
+                }
+                self.emit.set_stack_depth(depth_body + 1);
+                self.emit_jump_target(vec![offset_done]);
+                self.emit.end_iter();
             }
-
-            Expression::BinaryExpression {
-                operator,
-                left,
-                right,
-                ..
-            } => {
-                self.emit_binary_expression(operator, left, right)?;
+            ForInOrOf::Of => {
+                // When done, drop the `next` result. When breaking out, close
+                // the iterator first.
+                self.emit.set_stack_depth(depth_body + 1);
+                self.emit_jump_target(vec![offset_done]);
+                self.emit.pop();
+                if !control.breaks.is_empty() {
+                    let offset_end = self.emit.bytecode_offset();
+                    self.emit.goto(0);
+                    self.emit.set_stack_depth(depth_body);
+                    self.emit_jump_target(control.breaks);
+                    self.emit_iterator_close();
+                    self.emit_jump_target(vec![offset_end]);
+                }
+                self.emit.pop();
+                self.emit.pop();
             }
+        }
+        Ok(())
+    }
 
-            Expression::CallExpression(CallExpression {
-                callee, arguments, ..
-            }) => {
-                self.emit_call_expression(callee, arguments)?;
-            }
+    // Replace the iterable on the stack with its iterator, under the
+    // iterator's `next` method to call it with the iterator as `this`.
+    fn emit_get_iterator(&mut self) {
+        self.emit.dup();
+        self.emit.symbol(SymbolCode::Iterator);
+        self.emit.call_elem();
+        self.emit.swap();
+        self.emit.call_iter();
+        self.emit.check_is_obj(CheckIsObjectKind::GetIterator);
+        self.emit.dup();
+        self.emit.get_prop("next");
+        self.emit.swap();
+    }
 
-            Expression::CompoundAssignmentExpression { .. } => {
-                return Err(EmitError::NotImplemented(
-                    "TODO: CompoundAssignmentExpression",
-                ));
-            }
+    // Emit a `LoopHead`, returning its offset.
+    fn emit_loop_head(&mut self) -> usize {
+        let head = self.emit.bytecode_offset().offset;
+        let enclosing_loops = self
+            .controls
+            .iter()
+            .filter(|control| matches!(control, Control::Loop(_)))
+            .count();
+        let loop_depth = (enclosing_loops + 1).min(u8::MAX as usize);
+        self.emit.loop_head(loop_depth as u8);
+        head
+    }
 
-            Expression::ConditionalExpression {
-                test,
-                consequent,
-                alternate,
-                ..
-            } => {
-                self.emit_conditional_expression(test, consequent, alternate)?;
-            }
+    // Patch the `continue` jumps of a loop to here, where the stack depth is
+    // `depth_body`.
+    fn emit_continue_target(&mut self, continues: Vec<BytecodeOffset>, depth_body: usize) {
+        if !continues.is_empty() {
+            self.emit.set_stack_depth(depth_body);
+            self.emit_jump_target(continues);
+        }
+    }
 
-            Expression::FunctionExpression(_) => {
-                return Err(EmitError::NotImplemented("TODO: FunctionExpression"));
+    // TODO: variables are global variables for now, like functions, unless
+    // they're parameters of the function they're in. `var` declarations
+    // should be hoisted, and `let` and `const` ones scoped to their block.
+    fn emit_variable_declaration(&mut self, ast: &VariableDeclaration) -> Result<(), EmitError> {
+        for declarator in &ast.declarators {
+            match &declarator.init {
+                Some(init) => self.emit_expression(init)?,
+                // `var x;` leaves `x` as it was, but `let x;` sets it to
+                // undefined. Patterns always have an initializer.
+                None if matches!(ast.kind, VariableDeclarationKind::Var { .. }) => continue,
+                None => self.emit.undefined(),
             }
+            self.emit_binding(&declarator.binding)?;
+            self.emit.pop();
+        }
+        Ok(())
+    }
 
-            Expression::IdentifierExpression(ast) => {
-                self.emit_identifier_expression(ast);
+    // Assign the value on the stack to the target of a `for-in` or `for-of`
+    // loop, leaving the value on the stack.
+    fn emit_for_in_or_of_assignment(
+        &mut self,
+        left: &VariableDeclarationOrAssignmentTarget,
+    ) -> Result<(), EmitError> {
+        match left {
+            VariableDeclarationOrAssignmentTarget::VariableDeclaration(declaration) => {
+                match &declaration.declarators[..] {
+                    [VariableDeclarator {
+                        binding,
+                        init: None,
+                        ..
+                    }] => self.emit_binding(binding),
+                    _ => Err(EmitError::NotImplemented(
+                        "TODO: initializer in for-in or for-of",
+                    )),
+                }
             }
-
-            Expression::NewExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: NewExpression"));
+            VariableDeclarationOrAssignmentTarget::AssignmentTarget(target) => {
+                self.emit_assign_to_target(target)
             }
+        }
+    }
 
-            Expression::NewTargetExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: NewTargetExpression"));
+    // Assign the value on the stack to `target`, destructuring it if it's a
+    // pattern, and leave the value on the stack.
+    //
+    // The value is evaluated before the object of a member target, which is
+    // the other way around in the spec.
+    fn emit_assign_to_target(&mut self, target: &AssignmentTarget) -> Result<(), EmitError> {
+        match target {
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
+                    name,
+                    ..
+                }),
+            ) => {
+                self.emit_assign_name(name.value);
             }
-
-            Expression::ObjectExpression(ast) => {
-                self.emit_object_expression(ast)?;
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::StaticMemberAssignmentTarget(
+                        StaticMemberAssignmentTarget {
+                            object: ExpressionOrSuper::Expression(object),
+                            property,
+                            ..
+                        },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit.swap();
+                if self.strict {
+                    self.emit.strict_set_prop(property.value);
+                } else {
+                    self.emit.set_prop(property.value);
+                }
             }
-
-            Expression::UnaryExpression {
-                operator, operand, ..
-            } => {
-                let opcode = match operator {
-                    UnaryOperator::Plus { .. } => Opcode::Pos,
-                    UnaryOperator::Minus { .. } => Opcode::Neg,
-                    UnaryOperator::LogicalNot { .. } => Opcode::Not,
-                    UnaryOperator::BitwiseNot { .. } => Opcode::BitNot,
-                    UnaryOperator::Void { .. } => Opcode::Void,
-                    UnaryOperator::Typeof { .. } => {
-                        return Err(EmitError::NotImplemented("TODO: Typeof"));
-                    }
-                    UnaryOperator::Delete { .. } => {
-                        return Err(EmitError::NotImplemented("TODO: Delete"));
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::ComputedMemberAssignmentTarget(
+                        ComputedMemberAssignmentTarget {
+                            object: ExpressionOrSuper::Expression(object),
+                            expression: key,
+                            ..
+                        },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(key)?;
+                self.emit.pick(2);
+                if self.strict {
+                    self.emit.strict_set_elem();
+                } else {
+                    self.emit.set_elem();
+                }
+            }
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::PrivateFieldAssignmentTarget(
+                        PrivateFieldAssignmentTarget { object, field, .. },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit.swap();
+                self.emit_private_name(field.value);
+                self.emit.set_private_field();
+            }
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(_),
+            ) => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: super property as an assignment target",
+                ));
+            }
+            AssignmentTarget::AssignmentTargetPattern(
+                AssignmentTargetPattern::ObjectAssignmentTarget(ObjectAssignmentTarget {
+                    properties,
+                    rest,
+                    ..
+                }),
+            ) => {
+                let excluded: Vec<&str> = properties
+                    .iter()
+                    .filter_map(|property| match property {
+                        AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
+                            AssignmentTargetPropertyIdentifier { binding, .. },
+                        ) => Some(binding.name.value),
+                        AssignmentTargetProperty::AssignmentTargetPropertyProperty(
+                            AssignmentTargetPropertyProperty { name, .. },
+                        ) => static_property_name(name),
+                    })
+                    .collect();
+                let has_rest = rest.is_some();
+                self.emit_object_pattern_start(if has_rest { Some(&excluded) } else { None });
+                for property in properties.iter() {
+                    match property {
+                        AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
+                            AssignmentTargetPropertyIdentifier { binding, init, .. },
+                        ) => {
+                            self.emit.dup();
+                            self.emit.get_prop(binding.name.value);
+                            if let Some(init) = init {
+                                self.emit_default(init)?;
+                            }
+                            self.emit_assign_name(binding.name.value);
+                        }
+                        AssignmentTargetProperty::AssignmentTargetPropertyProperty(
+                            AssignmentTargetPropertyProperty { name, binding, .. },
+                        ) => {
+                            self.emit_object_pattern_property(name, has_rest)?;
+                            self.emit_assign_to_target_maybe_default(binding)?;
+                        }
                     }
-                };
-                self.emit_expression(operand)?;
-                self.emit.emit_unary_op(opcode);
+                    self.emit.pop();
+                }
+                if let Some(rest) = rest {
+                    self.emit_object_pattern_rest();
+                    self.emit_assign_to_target(rest)?;
+                    self.emit.pop();
+                }
             }
-
-            Expression::TemplateExpression(_) => {
-                return Err(EmitError::NotImplemented("TODO: TemplateExpression"));
+            AssignmentTarget::AssignmentTargetPattern(
+                AssignmentTargetPattern::ArrayAssignmentTarget(ArrayAssignmentTarget {
+                    elements,
+                    rest,
+                    ..
+                }),
+            ) => {
+                self.emit_array_pattern_start();
+                for element in elements.iter() {
+                    self.emit_array_pattern_step();
+                    if let Some(element) = element {
+                        self.emit_assign_to_target_maybe_default(element)?;
+                    }
+                    self.emit.pop();
+                }
+                if let Some(rest) = rest {
+                    self.emit_array_pattern_rest();
+                    self.emit_assign_to_target(rest)?;
+                    self.emit.pop();
+                }
+                self.emit_array_pattern_end();
             }
+        }
+        Ok(())
+    }
 
-            Expression::ThisExpression { .. } => {
-                self.emit_this()?;
+    fn emit_assign_to_target_maybe_default(
+        &mut self,
+        target: &AssignmentTargetMaybeDefault,
+    ) -> Result<(), EmitError> {
+        match target {
+            AssignmentTargetMaybeDefault::AssignmentTarget(target) => {
+                self.emit_assign_to_target(target)
             }
-
-            Expression::UpdateExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: UpdateExpression"));
+            AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(
+                AssignmentTargetWithDefault { binding, init, .. },
+            ) => {
+                self.emit_default(init)?;
+                self.emit_assign_to_target(binding)
             }
+        }
+    }
 
-            Expression::YieldExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: YieldExpression"));
+    // Assign the value on the stack to `binding`, destructuring it if it's a
+    // pattern, and leave the value on the stack.
+    fn emit_binding(&mut self, binding: &Binding) -> Result<(), EmitError> {
+        match binding {
+            Binding::BindingIdentifier(BindingIdentifier { name, .. }) => {
+                self.emit_assign_name(name.value);
             }
-
-            Expression::YieldGeneratorExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: YieldGeneratorExpression"));
+            Binding::BindingPattern(BindingPattern::ObjectBinding(ObjectBinding {
+                properties,
+                rest,
+                ..
+            })) => {
+                let excluded: Vec<&str> = properties
+                    .iter()
+                    .filter_map(|property| match property {
+                        BindingProperty::BindingPropertyIdentifier(BindingPropertyIdentifier {
+                            binding,
+                            ..
+                        }) => Some(binding.name.value),
+                        BindingProperty::BindingPropertyProperty(BindingPropertyProperty {
+                            name,
+                            ..
+                        }) => static_property_name(name),
+                    })
+                    .collect();
+                let has_rest = rest.is_some();
+                self.emit_object_pattern_start(if has_rest { Some(&excluded) } else { None });
+                for property in properties.iter() {
+                    match property {
+                        BindingProperty::BindingPropertyIdentifier(BindingPropertyIdentifier {
+                            binding,
+                            init,
+                            ..
+                        }) => {
+                            self.emit.dup();
+                            self.emit.get_prop(binding.name.value);
+                            if let Some(init) = init {
+                                self.emit_default(init)?;
+                            }
+                            self.emit_assign_name(binding.name.value);
+                        }
+                        BindingProperty::BindingPropertyProperty(BindingPropertyProperty {
+                            name,
+                            binding,
+                            ..
+                        }) => {
+                            self.emit_object_pattern_property(name, has_rest)?;
+                            self.emit_parameter(binding)?;
+                        }
+                    }
+                    self.emit.pop();
+                }
+                if let Some(rest) = rest {
+                    self.emit_object_pattern_rest();
+                    self.emit_assign_name(rest.name.value);
+                    self.emit.pop();
+                }
             }
-
-            Expression::AwaitExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: AwaitExpression"));
+            Binding::BindingPattern(BindingPattern::ArrayBinding(ArrayBinding {
+                elements,
+                rest,
+                ..
+            })) => {
+                self.emit_array_pattern_start();
+                for element in elements.iter() {
+                    self.emit_array_pattern_step();
+                    if let Some(element) = element {
+                        self.emit_parameter(element)?;
+                    }
+                    self.emit.pop();
+                }
+                if let Some(rest) = rest {
+                    self.emit_array_pattern_rest();
+                    self.emit_binding(rest)?;
+                    self.emit.pop();
+                }
+                self.emit_array_pattern_end();
             }
+        }
+        Ok(())
+    }
 
-            Expression::ImportCallExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ImportCallExpression"));
+    // Assign the value on the stack to a parameter, or an element of a
+    // pattern, using its default value instead if the value is undefined.
+    fn emit_parameter(&mut self, parameter: &Parameter) -> Result<(), EmitError> {
+        match parameter {
+            Parameter::Binding(binding) => self.emit_binding(binding),
+            Parameter::BindingWithDefault(BindingWithDefault { binding, init, .. }) => {
+                self.emit_default(init)?;
+                self.emit_binding(binding)
             }
         }
+    }
 
+    // Replace the value on the stack with that of `init` if it's undefined.
+    // Otherwise `init` isn't evaluated.
+    fn emit_default(&mut self, init: &Expression) -> Result<(), EmitError> {
+        self.emit.dup();
+        self.emit.undefined();
+        self.emit.emit_binary_op(Opcode::StrictEq);
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.if_eq(0);
+        self.emit.jump_target();
+        self.emit.pop();
+        self.emit_expression(init)?;
+        self.emit_jump_target(vec![offset_end]);
         Ok(())
     }
 
-    fn emit_binary_expression(
+    // Start destructuring the object on the stack, which can't be null or
+    // undefined. For a pattern with a rest property, `excluded` is the names
+    // of the other properties known before running, and an object with
+    // those properties is pushed under the value: the rest property copies
+    // the properties it doesn't have.
+    fn emit_object_pattern_start(&mut self, excluded: Option<&[&str]>) {
+        self.emit.check_obj_coercible();
+        if let Some(names) = excluded {
+            self.emit.new_init(0);
+            for name in names {
+                self.emit.emit_boolean(true);
+                self.emit.init_prop(name);
+            }
+            self.emit.swap();
+        }
+    }
+
+    // Push the property `name` of the object on the stack. With a rest
+    // property, a computed name is added to the excluded names under it.
+    fn emit_object_pattern_property(
         &mut self,
-        operator: &BinaryOperator,
-        left: &Expression,
-        right: &Expression,
+        name: &PropertyName,
+        has_rest: bool,
     ) -> Result<(), EmitError> {
-        let opcode = match operator {
-            BinaryOperator::Equals { .. } => Opcode::Eq,
-            BinaryOperator::NotEquals { .. } => Opcode::Ne,
-            BinaryOperator::StrictEquals { .. } => Opcode::StrictEq,
-            BinaryOperator::StrictNotEquals { .. } => Opcode::StrictNe,
-            BinaryOperator::LessThan { .. } => Opcode::Lt,
-            BinaryOperator::LessThanOrEqual { .. } => Opcode::Le,
-            BinaryOperator::GreaterThan { .. } => Opcode::Gt,
-            BinaryOperator::GreaterThanOrEqual { .. } => Opcode::Ge,
-            BinaryOperator::In { .. } => Opcode::In,
-            BinaryOperator::Instanceof { .. } => Opcode::Instanceof,
-            BinaryOperator::LeftShift { .. } => Opcode::Lsh,
-            BinaryOperator::RightShift { .. } => Opcode::Rsh,
-            BinaryOperator::RightShiftExt { .. } => Opcode::Ursh,
-            BinaryOperator::Add { .. } => Opcode::Add,
+        self.emit.dup();
+        match name {
+            PropertyName::StaticPropertyName(StaticPropertyName { value, .. }) => {
+                self.emit.get_prop(value);
+            }
+            PropertyName::ComputedPropertyName(ComputedPropertyName { expression, .. }) => {
+                self.emit_expression(expression)?;
+                self.emit.to_id();
+                if has_rest {
+                    // [excluded, value, value, key]
+                    self.emit.dup();
+                    self.emit.dup_at(4);
+                    self.emit.swap();
+                    self.emit.emit_boolean(true);
+                    self.emit.init_elem();
+                    self.emit.pop();
+                }
+                self.emit.get_elem();
+            }
+        }
+        Ok(())
+    }
+
+    // Replace the excluded names and the object under them with the object
+    // and a copy of its other properties, for the rest property.
+    fn emit_object_pattern_rest(&mut self) {
+        // [excluded, value]
+        self.emit.swap();
+        self.emit.new_init(0);
+        self.emit.dup_at(2);
+        self.emit.pick(2);
+        // [value, rest, value, excluded]
+        self.emit.copy_data_properties();
+    }
+
+    // Start destructuring the iterable on the stack, pushing its iterator,
+    // under `next`, and whether the iterator is done.
+    fn emit_array_pattern_start(&mut self) {
+        self.emit.dup();
+        self.emit_get_iterator();
+        self.emit.emit_boolean(false);
+    }
+
+    // Push the next value of the iterator, or undefined once it's done, and
+    // update whether it's done, under the value.
+    fn emit_array_pattern_step(&mut self) {
+        // [next, iterator, done]
+        self.emit.dup();
+        let offset_done = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit.pop();
+        self.emit.dup2();
+        self.emit.call_iter();
+        self.emit.check_is_obj(CheckIsObjectKind::IteratorNext);
+        self.emit.dup();
+        self.emit.get_prop("done");
+        self.emit.dup();
+        let offset_now_done = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit.swap();
+        self.emit.get_prop("value");
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.goto(0);
+
+        // Replace the `next` result with its `done`.
+        self.emit_jump_target(vec![offset_now_done]);
+        self.emit.swap();
+        self.emit.pop();
+        self.emit_jump_target(vec![offset_done]);
+        self.emit.undefined();
+
+        self.emit_jump_target(vec![offset_end]);
+    }
+
+    // Replace whether the iterator is done with true, and an array of its
+    // remaining values, for a rest element.
+    fn emit_array_pattern_rest(&mut self) {
+        self.emit.new_array(0);
+        self.emit.int8(0);
+        self.emit.pick(2);
+        let offset_done = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit_append_iterator_values();
+        self.emit_jump_target(vec![offset_done]);
+        self.emit.pop();
+        self.emit.emit_boolean(true);
+        self.emit.swap();
+    }
+
+    // Append the remaining values of the iterator to the array, with the
+    // index after the array's last element on top of it:
+    // [next, iterator, array, index]. The index is updated.
+    fn emit_append_iterator_values(&mut self) {
+        let depth_loop = self.emit.stack_depth();
+        let head = self.emit_loop_head();
+        self.emit.dup_at(3);
+        self.emit.dup_at(3);
+        self.emit.call_iter();
+        self.emit.check_is_obj(CheckIsObjectKind::IteratorNext);
+        self.emit.dup();
+        self.emit.get_prop("done");
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit.get_prop("value");
+        self.emit.init_elem_inc();
+        self.emit_goto_back(head);
+
+        // Drop the `next` result.
+        self.emit.set_stack_depth(depth_loop + 1);
+        self.emit_jump_target(vec![offset_end]);
+        self.emit.pop();
+    }
+
+    // Append the values of the iterable `...expression` spreads to the array
+    // under the index on the stack, updating the index.
+    fn emit_spread_element(&mut self, expression: &Expression) -> Result<(), EmitError> {
+        self.emit_expression(expression)?;
+        self.emit_get_iterator();
+        // [array, index, next, iterator] -> [next, iterator, array, index]
+        self.emit.pick(3);
+        self.emit.pick(3);
+        self.emit_append_iterator_values();
+        // Drop `next` and the iterator.
+        self.emit.pick(3);
+        self.emit.pop();
+        self.emit.pick(2);
+        self.emit.pop();
+        Ok(())
+    }
+
+    // Close the iterator if it isn't done, and drop it, leaving the value
+    // that was destructured.
+    // TODO: close it when assigning to an element throws, too.
+    fn emit_array_pattern_end(&mut self) {
+        let offset_done = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit_iterator_close();
+        self.emit_jump_target(vec![offset_done]);
+        self.emit.pop();
+        self.emit.pop();
+    }
+
+    // Call the `return` method of the iterator on the stack, under `next`, if
+    // it has one, and check that it returns an object.
+    fn emit_iterator_close(&mut self) {
+        self.emit.dup();
+        self.emit.dup();
+        self.emit.get_prop("return");
+        let offset_call = self.emit.bytecode_offset();
+        self.emit.coalesce(0);
+
+        // Without a `return` method, drop it and the copy of the iterator.
+        let depth_call = self.emit.stack_depth();
+        self.emit.jump_target();
+        self.emit.pop();
+        self.emit.pop();
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.goto(0);
+
+        self.emit.set_stack_depth(depth_call);
+        self.emit_jump_target(vec![offset_call]);
+        self.emit.swap();
+        self.emit.call_iter();
+        self.emit.check_is_obj(CheckIsObjectKind::IteratorReturn);
+        self.emit.pop();
+
+        self.emit_jump_target(vec![offset_end]);
+    }
+
+    // Compare the discriminant to each case in order, then jump to the first
+    // one that matches or to the default clause. The clauses' statements
+    // follow in source order, so each falls through to the next.
+    fn emit_switch<'alloc>(
+        &mut self,
+        discriminant: &Expression,
+        pre_default_cases: &[SwitchCase<'alloc>],
+        default_case: Option<&SwitchDefault<'alloc>>,
+        post_default_cases: &[SwitchCase<'alloc>],
+    ) -> Result<(), EmitError> {
+        self.emit.undefined();
+        self.emit.set_rval();
+
+        let depth = self.emit.stack_depth();
+        self.emit_expression(discriminant)?;
+
+        // The lexical declarations in the case clauses all share one block
+        // scope, which the tests are in too.
+        let mut bindings = Vec::new();
+        for case in pre_default_cases.iter().chain(post_default_cases) {
+            add_lexical_bindings(&case.consequent, &mut bindings);
+        }
+        if let Some(default_case) = default_case {
+            add_lexical_bindings(&default_case.consequent, &mut bindings);
+        }
+        let enclosing = self.enter_scope(ScopeKind::Block, bindings)?;
+        let result =
+            self.emit_switch_cases(depth, pre_default_cases, default_case, post_default_cases);
+        self.leave_scope(enclosing);
+        result
+    }
+
+    // Jump to the clause whose test matches the discriminant on the stack,
+    // or the default clause, then run the clauses from there.
+    fn emit_switch_cases<'alloc>(
+        &mut self,
+        depth: usize,
+        pre_default_cases: &[SwitchCase<'alloc>],
+        default_case: Option<&SwitchDefault<'alloc>>,
+        post_default_cases: &[SwitchCase<'alloc>],
+    ) -> Result<(), EmitError> {
+        let mut case_jumps = Vec::new();
+        for case in pre_default_cases.iter().chain(post_default_cases) {
+            self.emit.dup();
+            self.emit_expression(&case.test)?;
+            self.emit.emit_binary_op(Opcode::StrictEq);
+            case_jumps.push(self.emit.bytecode_offset());
+            self.emit.case(0);
+        }
+        let offset_default = self.emit.bytecode_offset();
+        self.emit.default(0);
+
+        self.controls.push(Control::Switch(SwitchControl {
+            depth,
+            breaks: Vec::new(),
+        }));
+        let result = self.emit_switch_clauses(
+            case_jumps,
+            pre_default_cases,
+            default_case,
+            post_default_cases,
+            offset_default,
+        );
+        let mut control = match self.controls.pop() {
+            Some(Control::Switch(control)) => control,
+            _ => panic!("unbalanced controls"),
+        };
+        if let Some(offset_default) = result? {
+            control.breaks.push(offset_default);
+        }
+
+        self.emit.set_stack_depth(depth);
+        self.emit_jump_target(control.breaks);
+        Ok(())
+    }
+
+    // Emit the clauses of a `switch` statement, returning the `Default` jump
+    // to the end if there's no default clause.
+    fn emit_switch_clauses<'alloc>(
+        &mut self,
+        case_jumps: Vec<BytecodeOffset>,
+        pre_default_cases: &[SwitchCase<'alloc>],
+        default_case: Option<&SwitchDefault<'alloc>>,
+        post_default_cases: &[SwitchCase<'alloc>],
+        offset_default: BytecodeOffset,
+    ) -> Result<Option<BytecodeOffset>, EmitError> {
+        let depth = self.emit.stack_depth();
+        let mut case_jumps = case_jumps.into_iter();
+        for case in pre_default_cases {
+            self.emit_switch_clause(case_jumps.next().unwrap(), &case.consequent, depth)?;
+        }
+        let offset_default = match default_case {
+            Some(default_case) => {
+                self.emit_switch_clause(offset_default, &default_case.consequent, depth)?;
+                None
+            }
+            None => Some(offset_default),
+        };
+        for case in post_default_cases {
+            self.emit_switch_clause(case_jumps.next().unwrap(), &case.consequent, depth)?;
+        }
+        Ok(offset_default)
+    }
+
+    fn emit_switch_clause(
+        &mut self,
+        jump: BytecodeOffset,
+        consequent: &[Statement],
+        depth: usize,
+    ) -> Result<(), EmitError> {
+        self.emit.set_stack_depth(depth);
+        self.emit_jump_target(vec![jump]);
+        for statement in consequent {
+            self.emit_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn emit_labeled_statement(&mut self, label: &Label, body: &Statement) -> Result<(), EmitError> {
+        let depth = self.emit.stack_depth();
+        self.controls.push(Control::Label(LabelControl {
+            name: label.value.to_string(),
+            depth,
+            breaks: Vec::new(),
+        }));
+        let result = self.emit_statement(body);
+        let control = match self.controls.pop() {
+            Some(Control::Label(control)) => control,
+            _ => panic!("unbalanced controls"),
+        };
+        result?;
+
+        if !control.breaks.is_empty() {
+            self.emit.set_stack_depth(depth);
+            self.emit_jump_target(control.breaks);
+        }
+        Ok(())
+    }
+
+    // Jump out of the innermost loop or `switch` for `break`, or on to the
+    // next iteration of the innermost loop for `continue`, running the
+    // `finally` blocks on the way. With a label, `break` jumps out of the
+    // statement with the label instead, and `continue` on to the next
+    // iteration of the loop with it, closing the iterators of the `for-of`
+    // loops left on the way.
+    fn emit_break_or_continue(
+        &mut self,
+        is_break: bool,
+        label: Option<&Label>,
+    ) -> Result<(), EmitError> {
+        let name = label.map(|label| label.value);
+        let depth = self.emit.stack_depth();
+        let mut index = self.controls.len();
+        loop {
+            if index == 0 {
+                return Err(EmitError::NotImplemented(match (is_break, name) {
+                    (true, None) => "break outside of a loop",
+                    (false, None) => "continue outside of a loop",
+                    (_, Some(_)) => "jump to an undefined label",
+                }));
+            }
+            index -= 1;
+            match &self.controls[index] {
+                Control::Finally(control) => {
+                    let finally_depth = control.depth;
+                    self.emit_pop_to(finally_depth);
+                    self.emit_gosub(index);
+                }
+                Control::Label(control) if is_break && Some(&*control.name) == name => {
+                    let label_depth = control.depth;
+                    self.emit_pop_to(label_depth);
+                    let offset = self.emit.bytecode_offset();
+                    if let Control::Label(control) = &mut self.controls[index] {
+                        control.breaks.push(offset);
+                    }
+                    self.emit.goto(0);
+                    break;
+                }
+                Control::Label(_) => {}
+                Control::Loop(_)
+                    if name.is_some() && (is_break || !self.has_label(index, name)) =>
+                {
+                    self.emit_leave_loop(index);
+                }
+                Control::Loop(control) => {
+                    let loop_depth = control.depth;
+                    self.emit_pop_to(loop_depth);
+                    let offset = self.emit.bytecode_offset();
+                    if let Control::Loop(control) = &mut self.controls[index] {
+                        if is_break {
+                            control.breaks.push(offset);
+                        } else {
+                            control.continues.push(offset);
+                        }
+                    }
+                    self.emit.goto(0);
+                    break;
+                }
+                Control::Switch(control) if is_break && name.is_none() => {
+                    let switch_depth = control.depth;
+                    self.emit_pop_to(switch_depth);
+                    let offset = self.emit.bytecode_offset();
+                    if let Control::Switch(control) = &mut self.controls[index] {
+                        control.breaks.push(offset);
+                    }
+                    self.emit.goto(0);
+                    break;
+                }
+                // `continue` goes on to the loop around the `switch`.
+                Control::Switch(_) => {}
+                Control::With => self.emit.leave_with(),
+                Control::Environment => self.emit.pop_lexical_env(),
+            }
+        }
+        self.emit.set_stack_depth(depth);
+        Ok(())
+    }
+
+    // True if the loop `self.controls[index]` has the label `name`, which is
+    // then on one of the labelled statements right around it.
+    fn has_label(&self, index: usize, name: Option<&str>) -> bool {
+        self.controls[..index]
+            .iter()
+            .rev()
+            .map_while(|control| match control {
+                Control::Label(control) => Some(&*control.name),
+                _ => None,
+            })
+            .any(|label| Some(label) == name)
+    }
+
+    // Close the iterator of the loop `self.controls[index]` if it's a
+    // `for-of` loop, before jumping out of it other than by `break`.
+    fn emit_leave_loop(&mut self, index: usize) {
+        if let Control::Loop(LoopControl {
+            depth: loop_depth,
+            closes_iterator: true,
+            ..
+        }) = &self.controls[index]
+        {
+            let loop_depth = *loop_depth;
+            self.emit_pop_to(loop_depth);
+            self.emit_iterator_close();
+        }
+    }
+
+    // Return the value of `expression`, or undefined, running the `finally`
+    // blocks and closing the iterators of the `for-of` loops on the way. A
+    // generator's last result is the returned value with `done` true, and an
+    // async function settles its promise with the value.
+    fn emit_return(&mut self, expression: Option<&Expression>) -> Result<(), EmitError> {
+        if self.function.is_none() {
+            return Err(EmitError::NotImplemented("return outside of a function"));
+        }
+        let is_generator = self.is_generator();
+        let depth = self.emit.stack_depth();
+        if is_generator {
+            self.emit.new_init(0);
+        }
+        match expression {
+            Some(expression) => self.emit_expression(expression)?,
+            None => self.emit.undefined(),
+        }
+        if is_generator {
+            self.emit_finish_iterator_result(true);
+        }
+        self.emit.set_rval();
+
+        for index in (0..self.controls.len()).rev() {
+            match &self.controls[index] {
+                Control::Finally(control) => {
+                    let finally_depth = control.depth;
+                    self.emit_pop_to(finally_depth);
+                    self.emit_gosub(index);
+                }
+                Control::Loop(_) => self.emit_leave_loop(index),
+                Control::With => self.emit.leave_with(),
+                Control::Environment => self.emit.pop_lexical_env(),
+                Control::Switch(_) | Control::Label(_) => {}
+            }
+        }
+
+        if self.is_async() {
+            self.emit_async_return(AsyncFunctionResolveKind::Fulfill);
+        } else if is_generator {
+            self.emit.get_local(GENERATOR_LOCAL);
+            self.emit.final_yield_rval();
+        } else {
+            self.emit.ret_rval();
+        }
+        self.emit.set_stack_depth(depth);
+        Ok(())
+    }
+
+    fn emit_pop_to(&mut self, depth: usize) {
+        while self.emit.stack_depth() > depth {
+            self.emit.pop();
+        }
+    }
+
+    // Run the `finally` block of `self.controls[index]` and come back here.
+    fn emit_gosub(&mut self, index: usize) {
+        let resume_index = self.emit.new_resume_index();
+        self.emit.emit_boolean(false);
+        self.emit.resume_index(resume_index);
+        let offset = self.emit.bytecode_offset();
+        match &mut self.controls[index] {
+            Control::Finally(control) => control.gosubs.push(offset),
+            _ => panic!("gosub to a loop, switch, label or with"),
+        }
+        self.emit.gosub(0);
+        self.emit.set_resume_offset(resume_index);
+        self.emit.jump_target();
+    }
+
+    fn emit_try(
+        &mut self,
+        body: &Block,
+        catch_clause: Option<&CatchClause>,
+        finalizer: Option<&Block>,
+    ) -> Result<(), EmitError> {
+        self.emit.undefined();
+        self.emit.set_rval();
+
+        let depth = self.emit.stack_depth();
+        let offset_try = self.emit.bytecode_offset();
+        self.emit.try_(0);
+        let start = self.emit.bytecode_offset();
+        if finalizer.is_some() {
+            self.controls.push(Control::Finally(FinallyControl {
+                depth,
+                gosubs: Vec::new(),
+            }));
+        }
+        let result = self.emit_try_and_catch(
+            body,
+            catch_clause,
+            finalizer.is_some(),
+            offset_try,
+            depth,
+            &start,
+        );
+
+        let ends = if let Some(finalizer) = finalizer {
+            let control = match self.controls.pop() {
+                Some(Control::Finally(control)) => control,
+                _ => panic!("unbalanced controls"),
+            };
+            let ends = result?;
+
+            // Entered by `Gosub`, or by an exception, with whether it was
+            // thrown and the exception or the resume index on the stack.
+            self.emit.add_try_note(TryNoteKind::Finally, depth, &start);
+            self.emit.set_stack_depth(depth);
+            self.emit_jump_target(control.gosubs);
+            self.emit.finally();
+            // The completion value of the `finally` block is dropped.
+            self.emit.get_rval();
+            self.emit_block(finalizer)?;
+            self.emit.set_rval();
+            self.emit.retsub();
+            ends
+        } else {
+            result?
+        };
+
+        self.emit.set_stack_depth(depth);
+        self.emit_jump_target(ends);
+        Ok(())
+    }
+
+    // Emit the `try` block and the `catch` block, returning the jumps from
+    // their ends to the end of the statement.
+    fn emit_try_and_catch(
+        &mut self,
+        body: &Block,
+        catch_clause: Option<&CatchClause>,
+        has_finally: bool,
+        offset_try: BytecodeOffset,
+        depth: usize,
+        start: &BytecodeOffset,
+    ) -> Result<Vec<BytecodeOffset>, EmitError> {
+        let mut ends = Vec::new();
+        self.emit_try_block(body, has_finally)?;
+        // The `Try` points at the jump over the handlers.
+        self.emit.patch_jump_target(vec![offset_try]);
+        ends.push(self.emit.bytecode_offset());
+        self.emit.goto(0);
+
+        if let Some(catch_clause) = catch_clause {
+            self.emit.add_try_note(TryNoteKind::Catch, depth, start);
+            self.emit.set_stack_depth(depth);
+            self.emit.jump_target();
+            self.emit_catch(catch_clause, has_finally)?;
+            ends.push(self.emit.bytecode_offset());
+            self.emit.goto(0);
+        }
+        Ok(ends)
+    }
+
+    // Emit the `try` or `catch` block, then run the `finally` block if there
+    // is one.
+    fn emit_try_block(&mut self, body: &Block, has_finally: bool) -> Result<(), EmitError> {
+        self.emit_block(body)?;
+        if has_finally {
+            self.emit_gosub(self.controls.len() - 1);
+        }
+        Ok(())
+    }
+
+    fn emit_catch(
+        &mut self,
+        catch_clause: &CatchClause,
+        has_finally: bool,
+    ) -> Result<(), EmitError> {
+        self.emit.exception();
+        let mut names = Vec::new();
+        if let Some(binding) = catch_clause.binding.as_deref() {
+            add_bound_names(binding, &mut names);
+        }
+        let bindings = global_bindings(names, BindingKind::CatchParameter);
+        let enclosing = self.enter_scope(ScopeKind::Catch, bindings)?;
+        let result = match catch_clause.binding.as_deref() {
+            Some(binding) => self.emit_binding(binding),
+            None => Ok(()),
+        }
+        .and_then(|()| {
+            self.emit.pop();
+            self.emit_try_block(&catch_clause.body, has_finally)
+        });
+        self.leave_scope(enclosing);
+        result
+    }
+
+    // A jump back to the `LoopHead` at offset `head`.
+    fn emit_goto_back(&mut self, head: usize) {
+        let offset = self.emit.bytecode_offset().offset;
+        self.emit.goto(head as i32 - offset as i32);
+    }
+
+    // Push the value of the binding that `lookup` found.
+    fn emit_get_slot(&mut self, hops: u8, slot: BindingSlot) {
+        match slot {
+            BindingSlot::Argument(index) => self.emit.get_arg(index),
+            BindingSlot::Local(index) => self.emit.get_local(index),
+            BindingSlot::Environment(index) => self.emit.get_aliased_var(hops, index),
+            BindingSlot::Global => panic!("global variables aren't in slots"),
+        }
+    }
+
+    // Assign the value on the stack to the binding that `lookup` found,
+    // leaving the value on the stack.
+    fn emit_set_slot(&mut self, hops: u8, slot: BindingSlot) {
+        match slot {
+            BindingSlot::Argument(index) => self.emit.set_arg(index),
+            BindingSlot::Local(index) => self.emit.set_local(index),
+            BindingSlot::Environment(index) => self.emit.set_aliased_var(hops, index),
+            BindingSlot::Global => panic!("global variables aren't in slots"),
+        }
+    }
+
+    // Assign the value on the stack to the variable `name`, leaving the
+    // value on the stack.
+    fn emit_assign_name(&mut self, name: &str) {
+        match self.lookup(name) {
+            Some((hops, slot)) => self.emit_set_slot(hops, slot),
+            None => {
+                self.emit_bind_name(name);
+                self.emit.swap();
+                self.emit_set_name(name);
+            }
+        }
+    }
+
+    // Assign the value that `emit_value` pushes to the variable `name`,
+    // leaving the value on the stack. The global object is pushed first for
+    // a global variable, so the value needn't be swapped under it.
+    fn emit_assign_name_with(
+        &mut self,
+        name: &str,
+        emit_value: impl FnOnce(&mut Self) -> Result<(), EmitError>,
+    ) -> Result<(), EmitError> {
+        match self.lookup(name) {
+            Some((hops, slot)) => {
+                emit_value(self)?;
+                self.emit_set_slot(hops, slot);
+            }
+            None => {
+                self.emit_bind_name(name);
+                emit_value(self)?;
+                self.emit_set_name(name);
+            }
+        }
+        Ok(())
+    }
+
+    // True if the code being emitted is in a `with` statement, in this
+    // function. Global variables are then looked up in the `with` objects
+    // before the global object, with the `*Name` instructions instead of the
+    // `*GName` ones.
+    fn in_with(&self) -> bool {
+        self.controls
+            .iter()
+            .any(|control| matches!(control, Control::With))
+    }
+
+    // Functions only see the global object and their own parameters, not the
+    // `with` objects of the code they're made in.
+    fn check_not_in_with(&self) -> Result<(), EmitError> {
+        if self.in_with() {
+            return Err(EmitError::NotImplemented(
+                "TODO: functions and classes in with statements",
+            ));
+        }
+        Ok(())
+    }
+
+    // Push the object the variable `name` is a property of.
+    fn emit_bind_name(&mut self, name: &str) {
+        if self.in_with() {
+            self.emit.bind_name(name);
+        } else {
+            self.emit.bind_g_name(name);
+        }
+    }
+
+    fn emit_get_name(&mut self, name: &str) {
+        if self.in_with() {
+            self.emit.get_name(name);
+        } else {
+            self.emit.get_g_name(name);
+        }
+    }
+
+    // Assign the value on the stack to the variable `name`, with the object
+    // from `emit_bind_name` under it.
+    fn emit_set_name(&mut self, name: &str) {
+        match (self.in_with(), self.strict) {
+            (true, true) => self.emit.strict_set_name(name),
+            (true, false) => self.emit.set_name(name),
+            (false, true) => self.emit.strict_set_g_name(name),
+            (false, false) => self.emit.set_g_name(name),
+        }
+    }
+
+    // The object is put on the scope chain for the body, and taken off
+    // after it, or when jumping out of it.
+    fn emit_with_statement(
+        &mut self,
+        object: &Expression,
+        body: &Statement,
+    ) -> Result<(), EmitError> {
+        // The object would have to be looked in before the bindings outside
+        // the statement, which aren't properties.
+        let has_bindings = self.names.iter().any(|scope| {
+            scope.bindings.iter().any(|binding| {
+                binding.kind != BindingKind::Internal && binding.slot != BindingSlot::Global
+            })
+        });
+        if has_bindings {
+            return Err(EmitError::NotImplemented(
+                "TODO: with statements in the scope of local variables",
+            ));
+        }
+        self.emit_expression(object)?;
+        // There are no scopes in the GC things, so its index isn't used.
+        self.emit.enter_with(0);
+        self.controls.push(Control::With);
+        let result = self.emit_statement(body);
+        self.controls.pop();
+        result?;
+        self.emit.leave_with();
+        Ok(())
+    }
+
+    fn emit_expression(&mut self, ast: &Expression) -> Result<(), EmitError> {
+        match ast {
+            Expression::MemberExpression(ast) => {
+                self.emit_member_expression(ast, false)?;
+            }
+
+            Expression::OptionalChain { expression, .. } => {
+                self.emit_optional_chain(expression)?;
+            }
+
+            Expression::OptionalMember(ast) => {
+                self.emit_member_expression(ast, true)?;
+            }
+
+            Expression::ClassExpression(class) => {
+                let name = class.name.as_ref().map(|name| name.name.value);
+                self.emit_class(name, class.super_.as_deref(), &class.elements)?;
+            }
+
+            Expression::LiteralBigIntExpression { value, .. } => {
+                self.emit.big_int(value);
+            }
+
+            Expression::LiteralBooleanExpression { value, .. } => {
+                self.emit.emit_boolean(*value);
+            }
+
+            Expression::LiteralInfinityExpression { .. } => {
+                self.emit.double(std::f64::INFINITY);
+            }
+
+            Expression::LiteralNullExpression { .. } => {
+                self.emit.null();
+            }
+
+            Expression::LiteralNumericExpression { value, .. } => {
+                self.emit_numeric_expression(*value);
+            }
+
+            Expression::LiteralRegExpExpression { .. } => {
+                return Err(EmitError::NotImplemented("TODO: LiteralRegExpExpression"));
+            }
+
+            Expression::LiteralStringExpression { value, .. } => {
+                self.emit.string(value);
+            }
+
+            Expression::ArrayExpression(ast) => {
+                self.emit_array_expression(ast)?;
+            }
+
+            Expression::ArrowExpression {
+                params,
+                body,
+                strict,
+                flags,
+                ..
+            } => {
+                self.emit_arrow(*flags, params, body, *strict)?;
+            }
+
+            Expression::AssignmentExpression {
+                binding,
+                expression,
+                ..
+            } => {
+                self.emit_assignment_expression(binding, expression)?;
+            }
+
+            Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                self.emit_binary_expression(operator, left, right)?;
+            }
+
+            Expression::CallExpression(CallExpression {
+                callee,
+                arguments,
+                loc,
+            }) => {
+                self.emit_call_expression(callee, arguments, loc, false)?;
+            }
+
+            Expression::OptionalCall(CallExpression {
+                callee,
+                arguments,
+                loc,
+            }) => {
+                self.emit_call_expression(callee, arguments, loc, true)?;
+            }
+
+            Expression::CompoundAssignmentExpression {
+                operator,
+                binding,
+                expression,
+                ..
+            } => {
+                self.emit_compound_assignment_expression(operator, binding, expression)?;
+            }
+
+            Expression::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+                ..
+            } => {
+                self.emit_conditional_expression(test, consequent, alternate)?;
+            }
+
+            // TODO: the name of a named function expression, which refers to
+            // the function in its body.
+            Expression::FunctionExpression(function) => {
+                self.emit_lambda(function)?;
+            }
+
+            Expression::IdentifierExpression(ast) => {
+                self.emit_identifier_expression(ast);
+            }
+
+            Expression::NewExpression {
+                callee,
+                arguments,
+                loc,
+            } => {
+                self.emit_new_expression(callee, arguments, loc)?;
+            }
+
+            Expression::NewTargetExpression { .. } => {
+                self.emit.new_target();
+            }
+
+            Expression::ImportMetaExpression { .. } => {
+                self.emit.import_meta();
+            }
+
+            Expression::ObjectExpression(ast) => {
+                self.emit_object_expression(ast)?;
+            }
+
+            Expression::UnaryExpression {
+                operator: UnaryOperator::Typeof { .. },
+                operand,
+                ..
+            } => {
+                self.emit_expression(operand)?;
+                // `Typeof` after getting a name tells the interpreter that a
+                // name that isn't declared is undefined, not a ReferenceError.
+                if let Expression::IdentifierExpression(_) = &**operand {
+                    self.emit.typeof_();
+                } else {
+                    self.emit.typeof_expr();
+                }
+            }
+
+            Expression::UnaryExpression {
+                operator: UnaryOperator::Delete { .. },
+                operand,
+                ..
+            } => {
+                self.emit_delete(operand)?;
+            }
+
+            Expression::UnaryExpression {
+                operator, operand, ..
+            } => {
+                let opcode = match operator {
+                    UnaryOperator::Plus { .. } => Opcode::Pos,
+                    UnaryOperator::Minus { .. } => Opcode::Neg,
+                    UnaryOperator::LogicalNot { .. } => Opcode::Not,
+                    UnaryOperator::BitwiseNot { .. } => Opcode::BitNot,
+                    UnaryOperator::Void { .. } => Opcode::Void,
+                    UnaryOperator::Typeof { .. } | UnaryOperator::Delete { .. } => {
+                        unreachable!("handled above")
+                    }
+                };
+                self.emit_expression(operand)?;
+                self.emit.emit_unary_op(opcode);
+            }
+
+            Expression::TemplateExpression(ast) => {
+                self.emit_template_expression(ast)?;
+            }
+
+            Expression::ThisExpression { .. } => {
+                self.emit_this()?;
+            }
+
+            Expression::UpdateExpression {
+                is_prefix,
+                operator,
+                operand,
+                ..
+            } => {
+                self.emit_update_expression(*is_prefix, operator, operand)?;
+            }
+
+            Expression::YieldExpression { expression, .. } => {
+                self.emit_yield(expression.as_deref())?;
+            }
+
+            Expression::YieldGeneratorExpression { expression, .. } => {
+                self.emit_yield_star(expression)?;
+            }
+
+            Expression::AwaitExpression { expression, .. } => {
+                self.emit_await(expression)?;
+            }
+
+            // The host loads the module, given the specifier and the options.
+            Expression::ImportCallExpression {
+                argument, options, ..
+            } => {
+                self.emit_expression(argument)?;
+                match options {
+                    Some(options) => self.emit_expression(options)?,
+                    None => self.emit.undefined(),
+                }
+                self.emit.dynamic_import();
+            }
+        }
+
+        Ok(())
+    }
+
+    // `delete` removes a property, giving false if it can't be removed. Any
+    // other operand is evaluated, and the result is true.
+    fn emit_delete(&mut self, operand: &Expression) -> Result<(), EmitError> {
+        match operand {
+            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    property,
+                    ..
+                },
+            )) => {
+                self.emit_expression(object)?;
+                if self.strict {
+                    self.emit.strict_del_prop(property.value);
+                } else {
+                    self.emit.del_prop(property.value);
+                }
+            }
+
+            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    expression,
+                    ..
+                },
+            )) => {
+                self.emit_expression(object)?;
+                self.emit_expression(expression)?;
+                if self.strict {
+                    self.emit.strict_del_elem();
+                } else {
+                    self.emit.del_elem();
+                }
+            }
+
+            Expression::MemberExpression(_) => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: delete of a super property or private field",
+                ));
+            }
+
+            Expression::OptionalChain { .. } => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: delete of an optional chain",
+                ));
+            }
+
+            // Declared variables can't be deleted, but the globals made by
+            // assigning to them and the properties of `with` objects can.
+            // TODO: the globals declared by other scripts.
+            Expression::IdentifierExpression(ast) => {
+                if self.strict {
+                    return Err(EmitError::NotImplemented(
+                        "TODO: the SyntaxError of deleting a variable in strict mode code",
+                    ));
+                }
+                let name = &ast.name.value;
+                let declared = self
+                    .names
+                    .iter()
+                    .any(|scope| scope.bindings.iter().any(|binding| binding.name == *name));
+                if declared && !self.in_with() {
+                    self.emit.emit_boolean(false);
+                } else {
+                    self.emit.del_name(name);
+                }
+            }
+
+            _ => {
+                self.emit_expression(operand)?;
+                self.emit.pop();
+                self.emit.emit_boolean(true);
+            }
+        }
+        Ok(())
+    }
+
+    /// `optional` is true for a link of an optional chain that starts with
+    /// `?.`, such as `b` in `a?.b`.
+    fn emit_member_expression(
+        &mut self,
+        ast: &MemberExpression,
+        optional: bool,
+    ) -> Result<(), EmitError> {
+        match ast {
+            MemberExpression::ComputedMemberExpression(ComputedMemberExpression {
+                object: ExpressionOrSuper::Expression(object),
+                expression,
+                ..
+            }) => {
+                self.emit_expression(object)?;
+                if optional {
+                    self.emit_optional_chain_check();
+                }
+                self.emit_expression(expression)?;
+                self.emit.get_elem();
+            }
+
+            MemberExpression::ComputedMemberExpression(ComputedMemberExpression {
+                object: ExpressionOrSuper::Super { .. },
+                expression,
+                ..
+            }) => {
+                self.emit_this()?;
+                self.emit_expression(expression)?;
+                self.emit.callee();
+                self.emit.super_base();
+                self.emit.get_elem_super();
+            }
+
+            MemberExpression::StaticMemberExpression(StaticMemberExpression {
+                object: ExpressionOrSuper::Expression(object),
+                property,
+                ..
+            }) => {
+                self.emit_expression(object)?;
+                if optional {
+                    self.emit_optional_chain_check();
+                }
+                self.emit.get_prop(&property.value);
+            }
+
+            MemberExpression::StaticMemberExpression(StaticMemberExpression {
+                object: ExpressionOrSuper::Super { .. },
+                property,
+                ..
+            }) => {
+                self.emit_this()?;
+                self.emit.callee();
+                self.emit.super_base();
+                self.emit.get_prop_super(&property.value);
+            }
+
+            MemberExpression::PrivateFieldExpression(PrivateFieldExpression {
+                object,
+                field,
+                ..
+            }) => {
+                self.emit_expression(object)?;
+                if optional {
+                    self.emit_optional_chain_check();
+                }
+                self.emit_private_name(field.value);
+                self.emit.get_private_field();
+            }
+        }
+
+        Ok(())
+    }
+
+    // An optional chain evaluates to `undefined` as soon as a `?.` finds
+    // null or undefined on its left, skipping the rest of the chain.
+    fn emit_optional_chain(&mut self, expression: &Expression) -> Result<(), EmitError> {
+        self.optional_chain_jumps.push(Vec::new());
+        let result = self.emit_expression(expression);
+        let jumplist = self.optional_chain_jumps.pop().unwrap();
+        result?;
+        self.emit_jump_target(jumplist);
+        Ok(())
+    }
+
+    // With the value on the left of a `?.` on the stack, short-circuit the
+    // innermost optional chain if it's null or undefined.
+    fn emit_optional_chain_check(&mut self) {
+        // `Coalesce` jumps if the value is neither null nor undefined.
+        let offset_continue = self.emit.bytecode_offset();
+        self.emit.coalesce(0);
+        self.emit.jump_target();
+        self.emit.pop();
+        self.emit.undefined();
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.goto(0);
+        self.optional_chain_jumps
+            .last_mut()
+            .expect("optional member or call outside of an optional chain")
+            .push(offset_end);
+
+        self.emit_jump_target(vec![offset_continue]);
+    }
+
+    fn emit_binary_expression(
+        &mut self,
+        operator: &BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<(), EmitError> {
+        let opcode = match operator {
+            BinaryOperator::Equals { .. } => Opcode::Eq,
+            BinaryOperator::NotEquals { .. } => Opcode::Ne,
+            BinaryOperator::StrictEquals { .. } => Opcode::StrictEq,
+            BinaryOperator::StrictNotEquals { .. } => Opcode::StrictNe,
+            BinaryOperator::LessThan { .. } => Opcode::Lt,
+            BinaryOperator::LessThanOrEqual { .. } => Opcode::Le,
+            BinaryOperator::GreaterThan { .. } => Opcode::Gt,
+            BinaryOperator::GreaterThanOrEqual { .. } => Opcode::Ge,
+            BinaryOperator::In { .. } => Opcode::In,
+            BinaryOperator::Instanceof { .. } => Opcode::Instanceof,
+            BinaryOperator::LeftShift { .. } => Opcode::Lsh,
+            BinaryOperator::RightShift { .. } => Opcode::Rsh,
+            BinaryOperator::RightShiftExt { .. } => Opcode::Ursh,
+            BinaryOperator::Add { .. } => Opcode::Add,
             BinaryOperator::Sub { .. } => Opcode::Sub,
             BinaryOperator::Mul { .. } => Opcode::Mul,
             BinaryOperator::Div { .. } => Opcode::Div,
@@ -382,250 +2952,1182 @@ impl AstEmitter {
             BinaryOperator::BitwiseXor { .. } => Opcode::BitXor,
             BinaryOperator::BitwiseAnd { .. } => Opcode::BitAnd,
 
-            BinaryOperator::Coalesce { .. }
-            | BinaryOperator::LogicalOr { .. }
-            | BinaryOperator::LogicalAnd { .. } => {
-                self.emit_short_circuit(operator, left, right)?;
-                return Ok(());
+            BinaryOperator::Coalesce { .. }
+            | BinaryOperator::LogicalOr { .. }
+            | BinaryOperator::LogicalAnd { .. } => {
+                self.emit_short_circuit(operator, left, right)?;
+                return Ok(());
+            }
+
+            BinaryOperator::Comma { .. } => {
+                self.emit_expression(left)?;
+                self.emit.pop();
+                self.emit_expression(right)?;
+                return Ok(());
+            }
+        };
+
+        self.emit_expression(left)?;
+        self.emit_expression(right)?;
+        self.emit.emit_binary_op(opcode);
+        Ok(())
+    }
+
+    fn emit_short_circuit(
+        &mut self,
+        operator: &BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(left)?;
+        let mut jumplist: Vec<BytecodeOffset> = Vec::with_capacity(1);
+        self.emit_jump(operator, &mut jumplist)?;
+        self.emit.jump_target();
+        self.emit.pop();
+        self.emit_expression(right)?;
+        self.emit_jump_target(jumplist);
+        return Ok(());
+    }
+
+    fn emit_jump(
+        &mut self,
+        operator: &BinaryOperator,
+        jumplist: &mut Vec<BytecodeOffset>,
+    ) -> Result<(), EmitError> {
+        let offset: BytecodeOffset = self.emit.bytecode_offset();
+        jumplist.push(offset);
+
+        // in the c++ bytecode emitter, the jumplist is emitted
+        // and four bytes are used in order to save memory. We are not using that
+        // here, so instead we are using a placeholder offset set to 0, which will
+        // be updated later in patch_jump_target.
+        let placeholder_offset: i32 = 0;
+        match operator {
+            BinaryOperator::Coalesce { .. } => {
+                self.emit.coalesce(placeholder_offset);
+            }
+            BinaryOperator::LogicalOr { .. } => {
+                self.emit.or(placeholder_offset);
+            }
+            BinaryOperator::LogicalAnd { .. } => {
+                self.emit.and(placeholder_offset);
+            }
+            _ => panic!("unrecognized operator used in jump"),
+        }
+
+        return Ok(());
+    }
+
+    fn emit_jump_target(&mut self, jumplist: Vec<BytecodeOffset>) {
+        self.emit.patch_jump_target(jumplist);
+        self.emit.jump_target();
+    }
+
+    fn emit_numeric_expression(&mut self, value: f64) {
+        // -0 is an integer, but Int8 and Int32 can't represent it.
+        let is_negative_zero = value == 0.0 && value.is_sign_negative();
+        if value.is_finite() && value.fract() == 0.0 && !is_negative_zero {
+            if i8::min_value() as f64 <= value && value <= i8::max_value() as f64 {
+                self.emit.int8(value as i8);
+                return;
+            }
+            if i32::min_value() as f64 <= value && value <= i32::max_value() as f64 {
+                self.emit.int32(value as i32);
+                return;
+            }
+        }
+        self.emit.double(value);
+    }
+
+    fn emit_object_expression(&mut self, object: &ObjectExpression) -> Result<(), EmitError> {
+        self.emit.new_init(0);
+
+        for property in object.properties.iter() {
+            self.emit_object_property(property)?;
+        }
+
+        Ok(())
+    }
+
+    fn emit_object_property(&mut self, property: &ObjectProperty) -> Result<(), EmitError> {
+        match property {
+            ObjectProperty::NamedObjectProperty(NamedObjectProperty::DataProperty(
+                DataProperty {
+                    property_name,
+                    expression,
+                    ..
+                },
+            )) => {
+                match property_name {
+                    PropertyName::StaticPropertyName(StaticPropertyName { value, .. }) => {
+                        self.emit_expression(expression)?;
+                        self.emit.init_prop(value);
+                    }
+                    // The key is evaluated before the value.
+                    PropertyName::ComputedPropertyName(ComputedPropertyName {
+                        expression: key,
+                        ..
+                    }) => {
+                        self.emit_expression(key)?;
+                        self.emit_expression(expression)?;
+                        self.emit.init_elem();
+                    }
+                }
+            }
+            ObjectProperty::ShorthandProperty(ShorthandProperty { name, .. }) => {
+                self.emit_identifier_expression(name);
+                self.emit.init_prop(name.name.value);
+            }
+            ObjectProperty::NamedObjectProperty(NamedObjectProperty::MethodDefinition(method)) => {
+                self.emit_method(method, false)?;
+            }
+            // Copy the own properties, overriding those defined before.
+            ObjectProperty::SpreadProperty(expression) => {
+                self.emit_expression(expression)?;
+                self.emit.undefined();
+                self.emit.copy_data_properties();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_array_expression(&mut self, array: &ArrayExpression) -> Result<(), EmitError> {
+        // TODO: Initialze to correct length where possible.
+        self.emit.new_array(0);
+        let has_spread = array
+            .elements
+            .iter()
+            .any(|element| matches!(element, ArrayExpressionElement::SpreadElement(_)));
+        if has_spread {
+            return self.emit_spread_array_elements(&array.elements);
+        }
+
+        for (index, element) in array.elements.iter().enumerate() {
+            match element {
+                ArrayExpressionElement::Expression(expr) => {
+                    self.emit_expression(&expr)?;
+                    self.emit.init_elem_array(index as u32);
+                }
+                ArrayExpressionElement::Elision { .. } => {
+                    self.emit.hole();
+                    self.emit.init_elem_array(index as u32);
+                }
+                ArrayExpressionElement::SpreadElement(_) => {
+                    panic!("spread elements are emitted by emit_spread_array_elements")
+                }
             }
+        }
 
-            BinaryOperator::Comma { .. } => {
-                self.emit_expression(left)?;
-                self.emit.pop();
-                self.emit_expression(right)?;
-                return Ok(());
+        Ok(())
+    }
+
+    // After a spread element, the indices of the elements aren't known
+    // before running, so the array is built with the next index on top of
+    // it.
+    fn emit_spread_array_elements(
+        &mut self,
+        elements: &[ArrayExpressionElement],
+    ) -> Result<(), EmitError> {
+        self.emit.int8(0);
+        for element in elements {
+            match element {
+                ArrayExpressionElement::Expression(expr) => {
+                    self.emit_expression(expr)?;
+                    self.emit.init_elem_inc();
+                }
+                ArrayExpressionElement::Elision { .. } => {
+                    self.emit.hole();
+                    self.emit.init_elem_inc();
+                }
+                ArrayExpressionElement::SpreadElement(expr) => self.emit_spread_element(expr)?,
             }
-        };
+        }
+        self.emit.pop();
+        Ok(())
+    }
+
+    fn emit_conditional_expression(
+        &mut self,
+        test: &Expression,
+        consequent: &Expression,
+        alternate: &Expression,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(test)?;
+
+        let offset_else = self.emit.bytecode_offset();
+        self.emit.if_eq(0);
+
+        // Then branch
+        self.emit.jump_target();
+        self.emit_expression(consequent)?;
+
+        let offset_final = self.emit.bytecode_offset();
+        self.emit.goto(0);
+
+        // Else branch
+        self.emit_jump_target(vec![offset_else]);
+        self.emit_expression(alternate)?;
+
+        // Merge point
+        self.emit_jump_target(vec![offset_final]);
 
-        self.emit_expression(left)?;
-        self.emit_expression(right)?;
-        self.emit.emit_binary_op(opcode);
         Ok(())
     }
 
-    fn emit_short_circuit(
+    fn emit_assignment_expression(
         &mut self,
-        operator: &BinaryOperator,
-        left: &Expression,
-        right: &Expression,
+        binding: &AssignmentTarget,
+        expression: &Expression,
     ) -> Result<(), EmitError> {
-        self.emit_expression(left)?;
-        let mut jumplist: Vec<BytecodeOffset> = Vec::with_capacity(1);
-        self.emit_jump(operator, &mut jumplist)?;
+        match binding {
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
+                    name,
+                    ..
+                }),
+            ) => {
+                return self
+                    .emit_assign_name_with(name.value, |this| this.emit_expression(expression));
+            }
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::StaticMemberAssignmentTarget(
+                        StaticMemberAssignmentTarget {
+                            object: ExpressionOrSuper::Expression(object),
+                            property,
+                            ..
+                        },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(expression)?;
+                if self.strict {
+                    self.emit.strict_set_prop(property.value);
+                } else {
+                    self.emit.set_prop(property.value);
+                }
+                return Ok(());
+            }
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::ComputedMemberAssignmentTarget(
+                        ComputedMemberAssignmentTarget {
+                            object: ExpressionOrSuper::Expression(object),
+                            expression: key,
+                            ..
+                        },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(key)?;
+                self.emit_expression(expression)?;
+                if self.strict {
+                    self.emit.strict_set_elem();
+                } else {
+                    self.emit.set_elem();
+                }
+                return Ok(());
+            }
+            AssignmentTarget::SimpleAssignmentTarget(
+                SimpleAssignmentTarget::MemberAssignmentTarget(
+                    MemberAssignmentTarget::PrivateFieldAssignmentTarget(
+                        PrivateFieldAssignmentTarget { object, field, .. },
+                    ),
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(expression)?;
+                self.emit_private_name(field.value);
+                self.emit.set_private_field();
+                return Ok(());
+            }
+            AssignmentTarget::AssignmentTargetPattern(_) => {
+                self.emit_expression(expression)?;
+                return self.emit_assign_to_target(binding);
+            }
+            _ => {}
+        }
+
+        return Err(EmitError::NotImplemented("TODO: AssignmentExpression"));
+    }
+
+    fn emit_compound_assignment_expression(
+        &mut self,
+        operator: &CompoundAssignmentOperator,
+        binding: &SimpleAssignmentTarget,
+        expression: &Expression,
+    ) -> Result<(), EmitError> {
+        match operator {
+            CompoundAssignmentOperator::LogicalOr { .. }
+            | CompoundAssignmentOperator::LogicalAnd { .. }
+            | CompoundAssignmentOperator::Coalesce { .. } => {
+                self.emit_logical_assignment(operator, binding, expression)
+            }
+            _ => Err(EmitError::NotImplemented(
+                "TODO: CompoundAssignmentExpression",
+            )),
+        }
+    }
+
+    // `x ||= y`, `x &&= y` and `x ??= y` assign only if `x` doesn't decide
+    // the result, as with `x || (x = y)`, except that the target's object and
+    // key are evaluated once.
+    fn emit_logical_assignment(
+        &mut self,
+        operator: &CompoundAssignmentOperator,
+        binding: &SimpleAssignmentTarget,
+        expression: &Expression,
+    ) -> Result<(), EmitError> {
+        // Keep a copy of the reference for the assignment below.
+        let reference_len = self.emit_get_target(binding)?;
+
+        // Jump over the assignment if the current value is the result.
+        let depth_skip = self.emit.stack_depth();
+        let offset_skip = self.emit.bytecode_offset();
+        match operator {
+            CompoundAssignmentOperator::LogicalOr { .. } => self.emit.or(0),
+            CompoundAssignmentOperator::LogicalAnd { .. } => self.emit.and(0),
+            CompoundAssignmentOperator::Coalesce { .. } => self.emit.coalesce(0),
+            _ => panic!("not a logical assignment operator"),
+        }
+
         self.emit.jump_target();
         self.emit.pop();
-        self.emit_expression(right)?;
-        self.emit_jump_target(jumplist);
-        return Ok(());
+        self.emit_expression(expression)?;
+        self.emit_set_target(binding);
+        let offset_end = self.emit.bytecode_offset();
+        self.emit.goto(0);
+
+        // Without assigning, drop the reference under the value.
+        self.emit.set_stack_depth(depth_skip);
+        self.emit_jump_target(vec![offset_skip]);
+        for _ in 0..reference_len {
+            self.emit.swap();
+            self.emit.pop();
+        }
+
+        self.emit_jump_target(vec![offset_end]);
+        Ok(())
     }
 
-    fn emit_jump(
+    // Push the reference that `binding` is, such as the object and key of
+    // `o[k]`, then the current value of the target, leaving the reference
+    // for `emit_set_target` to assign to. Returns the number of values in
+    // the reference.
+    fn emit_get_target(&mut self, binding: &SimpleAssignmentTarget) -> Result<usize, EmitError> {
+        let reference_len = match binding {
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
+                name,
+                ..
+            }) => match self.lookup(name.value) {
+                Some((hops, slot)) => {
+                    self.emit_get_slot(hops, slot);
+                    0
+                }
+                None => {
+                    self.emit_bind_name(name.value);
+                    self.emit_get_name(name.value);
+                    1
+                }
+            },
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::StaticMemberAssignmentTarget(
+                    StaticMemberAssignmentTarget {
+                        object: ExpressionOrSuper::Expression(object),
+                        property,
+                        ..
+                    },
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit.dup();
+                self.emit.get_prop(property.value);
+                1
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::ComputedMemberAssignmentTarget(
+                    ComputedMemberAssignmentTarget {
+                        object: ExpressionOrSuper::Expression(object),
+                        expression: key,
+                        ..
+                    },
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(key)?;
+                self.emit.dup2();
+                self.emit.get_elem();
+                2
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::PrivateFieldAssignmentTarget(
+                    PrivateFieldAssignmentTarget { object, field, .. },
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit.dup();
+                self.emit_private_name(field.value);
+                self.emit.get_private_field();
+                1
+            }
+            _ => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: assignment to super property",
+                ));
+            }
+        };
+        Ok(reference_len)
+    }
+
+    // Assign the value on the stack, over the reference from
+    // `emit_get_target`, to `binding`, leaving the value on the stack.
+    fn emit_set_target(&mut self, binding: &SimpleAssignmentTarget) {
+        match binding {
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
+                name,
+                ..
+            }) => match self.lookup(name.value) {
+                Some((hops, slot)) => self.emit_set_slot(hops, slot),
+                None => self.emit_set_name(name.value),
+            },
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::StaticMemberAssignmentTarget(
+                    StaticMemberAssignmentTarget { property, .. },
+                ),
+            ) => {
+                if self.strict {
+                    self.emit.strict_set_prop(property.value);
+                } else {
+                    self.emit.set_prop(property.value);
+                }
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::ComputedMemberAssignmentTarget(_),
+            ) => {
+                if self.strict {
+                    self.emit.strict_set_elem();
+                } else {
+                    self.emit.set_elem();
+                }
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::PrivateFieldAssignmentTarget(
+                    PrivateFieldAssignmentTarget { field, .. },
+                ),
+            ) => {
+                self.emit_private_name(field.value);
+                self.emit.set_private_field();
+            }
+        }
+    }
+
+    // `x++` pushes the old value of `x`, converted to a number, and `++x`
+    // the new one. The old value is put under the reference to `x`, to be
+    // left after the assignment.
+    fn emit_update_expression(
         &mut self,
-        operator: &BinaryOperator,
-        jumplist: &mut Vec<BytecodeOffset>,
+        is_prefix: bool,
+        operator: &UpdateOperator,
+        operand: &SimpleAssignmentTarget,
     ) -> Result<(), EmitError> {
-        let offset: BytecodeOffset = self.emit.bytecode_offset();
-        jumplist.push(offset);
-
-        // in the c++ bytecode emitter, the jumplist is emitted
-        // and four bytes are used in order to save memory. We are not using that
-        // here, so instead we are using a placeholder offset set to 0, which will
-        // be updated later in patch_jump_target.
-        let placeholder_offset: i32 = 0;
+        let reference_len = self.emit_get_target(operand)?;
+        self.emit.to_numeric();
+        if !is_prefix {
+            self.emit.dup();
+            self.emit.unpick(reference_len as u8 + 1);
+        }
         match operator {
-            BinaryOperator::Coalesce { .. } => {
-                self.emit.coalesce(placeholder_offset);
+            UpdateOperator::Increment { .. } => self.emit.inc(),
+            UpdateOperator::Decrement { .. } => self.emit.dec(),
+        }
+        self.emit_set_target(operand);
+        if !is_prefix {
+            self.emit.pop();
+        }
+        Ok(())
+    }
+
+    fn emit_identifier_expression(&mut self, ast: &IdentifierExpression) {
+        let name = &ast.name.value;
+        match self.lookup(name) {
+            Some((hops, slot)) => self.emit_get_slot(hops, slot),
+            None => self.emit_get_name(name),
+        }
+    }
+
+    fn emit_call_expression(
+        &mut self,
+        callee: &ExpressionOrSuper,
+        arguments: &Arguments,
+        loc: &SourceLocation,
+        optional: bool,
+    ) -> Result<(), EmitError> {
+        // Don't do super handling in an emit_expresion_or_super because the bytecode heavily
+        // depends on how you're using the super
+        match callee {
+            ExpressionOrSuper::Expression(expr) => self.emit_callee_and_this(expr, optional)?,
+            _ => {
+                return Err(EmitError::NotImplemented("TODO: Super"));
+            }
+        }
+
+        if has_spread(arguments) {
+            self.emit_spread_arguments(arguments)?;
+            self.emit.record_position(loc.start);
+            self.emit.spread_call();
+        } else {
+            self.emit_arguments(arguments)?;
+            self.emit.record_position(loc.start);
+            self.emit.call(arguments.args.len() as u16);
+        }
+
+        Ok(())
+    }
+
+    // The callee is also `new.target`. `IsConstructing` stands for the `this`
+    // value the call creates.
+    fn emit_new_expression(
+        &mut self,
+        callee: &Expression,
+        arguments: &Arguments,
+        loc: &SourceLocation,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(callee)?;
+        self.emit.is_constructing();
+        if has_spread(arguments) {
+            self.emit_spread_arguments(arguments)?;
+            self.emit.dup_at(2);
+            self.emit.record_position(loc.start);
+            self.emit.spread_new();
+        } else {
+            self.emit_arguments(arguments)?;
+            let argc = arguments.args.len();
+            self.emit.dup_at(argc as u32 + 1);
+            self.emit.record_position(loc.start);
+            self.emit.new_(argc as u16);
+        }
+        Ok(())
+    }
+
+    // Push the function to call and its `this` value.
+    fn emit_callee_and_this(
+        &mut self,
+        callee: &Expression,
+        optional: bool,
+    ) -> Result<(), EmitError> {
+        match callee {
+            Expression::IdentifierExpression(IdentifierExpression { name, .. }) => {
+                self.emit_expression(callee)?;
+                if optional {
+                    self.emit_optional_chain_check();
+                }
+                if self.lookup(name.value).is_some() {
+                    self.emit.undefined();
+                } else if self.in_with() {
+                    self.emit.implicit_this(name.value);
+                } else {
+                    self.emit.g_implicit_this(name.value);
+                }
+            }
+            // A method call, with the object as `this`.
+            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    property,
+                    ..
+                },
+            )) if !optional => {
+                self.emit_expression(object)?;
+                self.emit.dup();
+                self.emit.call_prop(property.value);
+                self.emit.swap();
+            }
+            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    expression,
+                    ..
+                },
+            )) if !optional => {
+                self.emit_expression(object)?;
+                self.emit.dup();
+                self.emit_expression(expression)?;
+                self.emit.call_elem();
+                self.emit.swap();
             }
-            BinaryOperator::LogicalOr { .. } => {
-                self.emit.or(placeholder_offset);
+            Expression::MemberExpression(_) | Expression::OptionalMember(_) => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: optional chains or super in method calls",
+                ));
             }
-            BinaryOperator::LogicalAnd { .. } => {
-                self.emit.and(placeholder_offset);
+            _ => {
+                self.emit_expression(callee)?;
+                if optional {
+                    self.emit_optional_chain_check();
+                }
+                self.emit.undefined();
             }
-            _ => panic!("unrecognized operator used in jump"),
         }
 
-        return Ok(());
+        Ok(())
     }
 
-    fn emit_jump_target(&mut self, jumplist: Vec<BytecodeOffset>) {
-        self.emit.patch_jump_target(jumplist);
-        self.emit.jump_target();
+    // `yield value` suspends the generator, making `value` the result of the
+    // `next` call, and evaluates to the value the next `next` call passes.
+    fn emit_yield(&mut self, value: Option<&Expression>) -> Result<(), EmitError> {
+        if !self.is_generator() {
+            return Err(EmitError::NotImplemented("yield outside of a generator"));
+        }
+        self.emit.new_init(0);
+        match value {
+            Some(value) => self.emit_expression(value)?,
+            None => self.emit.undefined(),
+        }
+        self.emit_finish_iterator_result(false);
+        self.emit_yield_op(false);
+        Ok(())
     }
 
-    fn emit_numeric_expression(&mut self, value: f64) {
-        if value.is_finite() && value.fract() == 0.0 {
-            if i8::min_value() as f64 <= value && value <= i8::max_value() as f64 {
-                self.emit.int8(value as i8);
-                return;
-            }
-            if i32::min_value() as f64 <= value && value <= i32::max_value() as f64 {
-                self.emit.int32(value as i32);
-                return;
-            }
+    // `await value` suspends the async function until the promise `value`
+    // is settled, and evaluates to its value, or throws its reason. A value
+    // that isn't a promise is the result as it is, after suspending.
+    // The parser only allows `await` in async functions and at the top level
+    // of modules, which aren't emitted.
+    fn emit_await(&mut self, value: &Expression) -> Result<(), EmitError> {
+        if !self.is_async() {
+            return Err(EmitError::NotImplemented(
+                "await outside of an async function",
+            ));
         }
-        self.emit.double(value);
+        self.emit_expression(value)?;
+        self.emit.get_local(GENERATOR_LOCAL);
+        self.emit.async_await();
+        self.emit.get_local(GENERATOR_LOCAL);
+        let resume_index = self.emit.new_resume_index();
+        self.emit.await_(resume_index);
+        self.emit.set_resume_offset(resume_index);
+        self.emit.after_yield();
+        self.emit.check_resume_kind();
+        Ok(())
     }
 
-    fn emit_object_expression(&mut self, object: &ObjectExpression) -> Result<(), EmitError> {
-        self.emit.new_init(0);
-
-        for property in object.properties.iter() {
-            self.emit_object_property(property)?;
+    // `yield* iterable` yields the results of the iterable's iterator as they
+    // are, passing it the values `next` is called with, and evaluates to the
+    // `value` of its last result.
+    //
+    // TODO: pass on the generator's `throw` and `return` calls to the
+    // iterator.
+    fn emit_yield_star(&mut self, iterable: &Expression) -> Result<(), EmitError> {
+        if !self.is_generator() {
+            return Err(EmitError::NotImplemented("yield outside of a generator"));
         }
+        self.emit_expression(iterable)?;
+        self.emit_get_iterator();
+        // The value to pass to `next`, undefined the first time.
+        self.emit.undefined();
 
+        let depth = self.emit.stack_depth();
+        let head = self.emit_loop_head();
+        // Call `next` with the iterator as `this`, keeping both.
+        self.emit.pick(2);
+        self.emit.pick(2);
+        self.emit.dup2();
+        self.emit.pick(4);
+        self.emit.call(1);
+        self.emit.check_is_obj(CheckIsObjectKind::IteratorNext);
+        self.emit.dup();
+        self.emit.get_prop("done");
+        let offset_done = self.emit.bytecode_offset();
+        self.emit.if_ne(0);
+        self.emit_yield_op(false);
+        self.emit_goto_back(head);
+
+        self.emit.set_stack_depth(depth);
+        self.emit_jump_target(vec![offset_done]);
+        self.emit.get_prop("value");
+        for _ in 0..2 {
+            self.emit.swap();
+            self.emit.pop();
+        }
         Ok(())
     }
 
-    fn emit_object_property(&mut self, property: &ObjectProperty) -> Result<(), EmitError> {
-        match property {
-            ObjectProperty::NamedObjectProperty(NamedObjectProperty::DataProperty(
-                DataProperty {
-                    property_name,
-                    expression,
-                    ..
-                },
-            )) => {
-                self.emit_expression(expression)?;
+    // Finish the iterator result object under the value on the stack, as
+    // `{ value, done }`.
+    fn emit_finish_iterator_result(&mut self, done: bool) {
+        self.emit.init_prop("value");
+        self.emit.emit_boolean(done);
+        self.emit.init_prop("done");
+    }
 
-                match property_name {
-                    PropertyName::StaticPropertyName(StaticPropertyName { value, .. }) => {
-                        self.emit.init_prop(value);
-                    }
-                    PropertyName::ComputedPropertyName(ComputedPropertyName { .. }) => {
-                        return Err(EmitError::NotImplemented("TODO: computed property"))
-                    }
-                }
-            }
-            _ => return Err(EmitError::NotImplemented("TODO: non data property")),
+    // Suspend the generator with the value on the stack, or for the initial
+    // yield without one. When resumed, the value passed to `next` is on the
+    // stack.
+    fn emit_yield_op(&mut self, initial: bool) {
+        self.emit.get_local(GENERATOR_LOCAL);
+        let resume_index = self.emit.new_resume_index();
+        if initial {
+            self.emit.initial_yield(resume_index);
+        } else {
+            self.emit.yield_(resume_index);
         }
-
-        Ok(())
+        self.emit.set_resume_offset(resume_index);
+        self.emit.after_yield();
+        self.emit.check_resume_kind();
     }
 
-    fn emit_array_expression(&mut self, array: &ArrayExpression) -> Result<(), EmitError> {
-        // TODO: Initialze to correct length where possible.
-        self.emit.new_array(0);
+    fn emit_template_expression(&mut self, ast: &TemplateExpression) -> Result<(), EmitError> {
+        if let Some(tag) = &ast.tag {
+            return self.emit_tagged_template(tag, ast);
+        }
 
-        for (index, element) in array.elements.iter().enumerate() {
+        // `a${b}c` is `"a" + ToString(b) + "c"`. The first string is always
+        // emitted, even if empty, so that the result is a string.
+        for (i, element) in ast.elements.iter().enumerate() {
             match element {
-                ArrayExpressionElement::Expression(expr) => {
-                    self.emit_expression(&expr)?;
-                    self.emit.init_elem_array(index as u32);
+                TemplateExpressionElement::TemplateElement(element) => {
+                    let value = element
+                        .cooked_value
+                        .expect("untagged template with an invalid escape sequence");
+                    if i == 0 || !value.is_empty() {
+                        self.emit.string(value);
+                        if i > 0 {
+                            self.emit.emit_binary_op(Opcode::Add);
+                        }
+                    }
                 }
-                ArrayExpressionElement::Elision { .. } => {
-                    self.emit.hole();
-                    self.emit.init_elem_array(index as u32);
+                TemplateExpressionElement::Expression(expression) => {
+                    self.emit_expression(expression)?;
+                    self.emit.to_string();
+                    self.emit.emit_binary_op(Opcode::Add);
                 }
-                _ => return Err(EmitError::NotImplemented("TODO: Array Element")),
             }
         }
 
         Ok(())
     }
 
-    fn emit_conditional_expression(
+    // ``tag`a${b}c` `` is `tag(strings, b)`, where `strings` is the array of
+    // the cooked strings, with the array of the raw strings as its `raw`
+    // property.
+    //
+    // TODO: Pass the same frozen array each time the template is evaluated,
+    // as CallSiteObj does.
+    fn emit_tagged_template(
         &mut self,
-        test: &Expression,
-        consequent: &Expression,
-        alternate: &Expression,
+        tag: &Expression,
+        ast: &TemplateExpression,
     ) -> Result<(), EmitError> {
-        self.emit_expression(test)?;
+        self.emit_callee_and_this(tag, false)?;
 
-        let offset_else = self.emit.bytecode_offset();
-        self.emit.if_eq(0);
+        let strings = ast.elements.iter().filter_map(|element| match element {
+            TemplateExpressionElement::TemplateElement(element) => Some(element),
+            TemplateExpressionElement::Expression(_) => None,
+        });
+        self.emit.new_array(0);
+        for (index, element) in strings.clone().enumerate() {
+            match element.cooked_value {
+                Some(value) => self.emit.string(value),
+                None => self.emit.undefined(),
+            }
+            self.emit.init_elem_array(index as u32);
+        }
+        self.emit.new_array(0);
+        for (index, element) in strings.enumerate() {
+            self.emit.string(element.raw_value);
+            self.emit.init_elem_array(index as u32);
+        }
+        self.emit.init_prop("raw");
 
-        // Then branch
-        self.emit.jump_target();
-        self.emit_expression(consequent)?;
+        let mut argc = 1;
+        for element in ast.elements.iter() {
+            if let TemplateExpressionElement::Expression(expression) = element {
+                self.emit_expression(expression)?;
+                argc += 1;
+            }
+        }
+        self.emit.record_position(ast.loc.start);
+        self.emit.call(argc);
+        Ok(())
+    }
 
-        let offset_final = self.emit.bytecode_offset();
-        self.emit.goto(0);
+    fn emit_arguments(&mut self, ast: &Arguments) -> Result<(), EmitError> {
+        for argument in &ast.args {
+            self.emit_argument(argument)?;
+        }
 
-        // Else branch
-        self.emit_jump_target(vec![offset_else]);
-        self.emit_expression(alternate)?;
+        Ok(())
+    }
 
-        // Merge point
-        self.emit_jump_target(vec![offset_final]);
+    fn emit_argument(&mut self, ast: &Argument) -> Result<(), EmitError> {
+        match ast {
+            Argument::Expression(ast) => self.emit_expression(ast)?,
+            Argument::SpreadElement(_) => {
+                panic!("spread arguments are emitted by emit_spread_arguments")
+            }
+        }
 
         Ok(())
     }
 
-    fn emit_assignment_expression(
-        &mut self,
-        binding: &AssignmentTarget,
-        expression: &Expression,
-    ) -> Result<(), EmitError> {
-        match binding {
-            AssignmentTarget::SimpleAssignmentTarget(
-                SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
-                    name,
-                    ..
-                }),
-            ) => {
-                self.emit.bind_g_name(name.value);
-                self.emit_expression(expression)?;
-                self.emit.set_g_name(name.value);
-                return Ok(());
+    // Push an array of the arguments, for `SpreadCall` or `SpreadNew`.
+    fn emit_spread_arguments(&mut self, ast: &Arguments) -> Result<(), EmitError> {
+        self.emit.new_array(0);
+        self.emit.int8(0);
+        for argument in &ast.args {
+            match argument {
+                Argument::Expression(ast) => {
+                    self.emit_expression(ast)?;
+                    self.emit.init_elem_inc();
+                }
+                Argument::SpreadElement(ast) => self.emit_spread_element(ast)?,
+            }
+        }
+        self.emit.pop();
+        Ok(())
+    }
+}
+
+// Whether the arguments of a call include a spread element, as in
+// `f(...args)`.
+fn has_spread(arguments: &Arguments) -> bool {
+    arguments
+        .args
+        .iter()
+        .any(|argument| matches!(argument, Argument::SpreadElement(_)))
+}
+
+// The binding of a parameter, without its default value.
+fn parameter_binding<'a, 'alloc>(parameter: &'a Parameter<'alloc>) -> &'a Binding<'alloc> {
+    match parameter {
+        Parameter::Binding(binding) => binding,
+        Parameter::BindingWithDefault(BindingWithDefault { binding, .. }) => binding,
+    }
+}
+
+// Add the names `binding` binds to `names`, in source order.
+fn add_bound_names(binding: &Binding, names: &mut Vec<String>) {
+    match binding {
+        Binding::BindingIdentifier(BindingIdentifier { name, .. }) => {
+            names.push(name.value.to_owned());
+        }
+        Binding::BindingPattern(BindingPattern::ObjectBinding(ObjectBinding {
+            properties,
+            rest,
+            ..
+        })) => {
+            for property in properties.iter() {
+                match property {
+                    BindingProperty::BindingPropertyIdentifier(BindingPropertyIdentifier {
+                        binding,
+                        ..
+                    }) => names.push(binding.name.value.to_owned()),
+                    BindingProperty::BindingPropertyProperty(BindingPropertyProperty {
+                        binding,
+                        ..
+                    }) => add_bound_names(parameter_binding(binding), names),
+                }
+            }
+            if let Some(rest) = rest {
+                names.push(rest.name.value.to_owned());
+            }
+        }
+        Binding::BindingPattern(BindingPattern::ArrayBinding(ArrayBinding {
+            elements,
+            rest,
+            ..
+        })) => {
+            for element in elements.iter().flatten() {
+                add_bound_names(parameter_binding(element), names);
+            }
+            if let Some(rest) = rest {
+                add_bound_names(rest, names);
             }
-            _ => {}
         }
+    }
+}
 
-        return Err(EmitError::NotImplemented("TODO: AssignmentExpression"));
+// Bindings of `kind` for `names`, in the global object.
+fn global_bindings(names: Vec<String>, kind: BindingKind) -> Vec<BindingNote> {
+    names
+        .into_iter()
+        .map(|name| BindingNote {
+            name,
+            kind,
+            slot: BindingSlot::Global,
+        })
+        .collect()
+}
+
+// Add a binding to `bindings`, unless it has one with the same name, as a
+// `var` declaration of a parameter's name declares nothing new.
+fn add_binding(bindings: &mut Vec<BindingNote>, binding: BindingNote) {
+    if !bindings.iter().any(|other| other.name == binding.name) {
+        bindings.push(binding);
     }
+}
 
-    fn emit_identifier_expression(&mut self, ast: &IdentifierExpression) {
-        let name = &ast.name.value;
-        self.emit.get_g_name(name);
+// Add the bindings of the `var` declarations in `statements`, including
+// those in blocks and other statements, but not in functions.
+fn add_var_bindings(statements: &[Statement], bindings: &mut Vec<BindingNote>) {
+    let mut names = VarNames { names: Vec::new() };
+    for statement in statements {
+        names.visit_statement(statement);
     }
+    for binding in global_bindings(names.names, BindingKind::Var) {
+        add_binding(bindings, binding);
+    }
+}
 
-    fn emit_call_expression(
+// Collects the names declared with `var`, skipping the expressions and
+// declarations that can't contain such declarations, or have their own.
+struct VarNames {
+    names: Vec<String>,
+}
+
+impl<'alloc> Visitor<'alloc> for VarNames {
+    fn visit_variable_declaration(&mut self, ast: &VariableDeclaration<'alloc>) {
+        if let VariableDeclarationKind::Var { .. } = ast.kind {
+            for declarator in ast.declarators.iter() {
+                add_bound_names(&declarator.binding, &mut self.names);
+            }
+        }
+    }
+
+    fn visit_expression(&mut self, _ast: &Expression<'alloc>) {}
+
+    fn visit_function(&mut self, _ast: &Function<'alloc>) {}
+
+    fn visit_class_declaration(&mut self, _ast: &ClassDeclaration<'alloc>) {}
+}
+
+// The names that appear in the functions, classes and methods in the
+// parameters `params` and the body `statements`, at any depth. It's a guess
+// at the names the functions refer to, which is sure to include them.
+fn captured_names(
+    params: &[Parameter],
+    statements: &[Statement],
+    result: Option<&Expression>,
+) -> HashSet<String> {
+    let mut names = CapturedNames {
+        names: HashSet::new(),
+        depth: 0,
+    };
+    for param in params {
+        walk_parameter(&mut names, param);
+    }
+    for statement in statements {
+        walk_statement(&mut names, statement);
+    }
+    if let Some(result) = result {
+        names.visit_expression(result);
+    }
+    names.names
+}
+
+struct CapturedNames {
+    names: HashSet<String>,
+
+    /// How many functions, classes and methods the visitor is in.
+    depth: usize,
+}
+
+impl CapturedNames {
+    fn nested(&mut self, walk: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        walk(self);
+        self.depth -= 1;
+    }
+
+    // The elements of a class are compiled to functions, but its heritage is
+    // evaluated where the class is.
+    fn visit_class(
         &mut self,
-        callee: &ExpressionOrSuper,
-        arguments: &Arguments,
-    ) -> Result<(), EmitError> {
-        // Don't do super handling in an emit_expresion_or_super because the bytecode heavily
-        // depends on how you're using the super
-        match callee {
-            ExpressionOrSuper::Expression(expr) => match &**expr {
-                Expression::IdentifierExpression(IdentifierExpression { name, .. }) => {
-                    self.emit_expression(expr)?;
-                    self.emit.g_implicit_this(name.value);
-                }
-                _ => {
-                    return Err(EmitError::NotImplemented(
-                        "TODO: Call (only global functions are supported)",
-                    ));
-                }
-            },
-            _ => {
-                return Err(EmitError::NotImplemented("TODO: Super"));
+        heritage: Option<&Expression>,
+        elements: &[arena::Box<ClassElement>],
+    ) {
+        if let Some(heritage) = heritage {
+            self.visit_expression(heritage);
+        }
+        self.nested(|visitor| {
+            for element in elements {
+                visitor.visit_class_element(element);
             }
+        });
+    }
+}
+
+impl<'alloc> Visitor<'alloc> for CapturedNames {
+    fn visit_identifier(&mut self, ast: &Identifier<'alloc>) {
+        if self.depth > 0 {
+            self.names.insert(ast.value.to_string());
         }
+    }
 
-        self.emit_arguments(arguments)?;
-        self.emit.call(arguments.args.len() as u16);
+    // The name of a function or class declaration is bound outside it.
+    fn visit_function(&mut self, ast: &Function<'alloc>) {
+        if let Some(name) = &ast.name {
+            self.visit_binding_identifier(name);
+        }
+        self.nested(|visitor| {
+            visitor.visit_formal_parameters(&ast.params);
+            visitor.visit_function_body(&ast.body);
+        });
+    }
 
-        Ok(())
+    fn visit_expression(&mut self, ast: &Expression<'alloc>) {
+        match ast {
+            Expression::ArrowExpression { .. } => {
+                self.nested(|visitor| walk_expression(visitor, ast))
+            }
+            _ => walk_expression(self, ast),
+        }
     }
 
-    fn emit_arguments(&mut self, ast: &Arguments) -> Result<(), EmitError> {
-        for argument in &ast.args {
-            self.emit_argument(argument)?;
+    fn visit_class_declaration(&mut self, ast: &ClassDeclaration<'alloc>) {
+        self.visit_binding_identifier(&ast.name);
+        self.visit_class(ast.super_.as_deref(), &ast.elements);
+    }
+
+    fn visit_class_expression(&mut self, ast: &ClassExpression<'alloc>) {
+        if let Some(name) = &ast.name {
+            self.visit_binding_identifier(name);
         }
+        self.visit_class(ast.super_.as_deref(), &ast.elements);
+    }
 
-        Ok(())
+    fn visit_method(&mut self, ast: &Method<'alloc>) {
+        self.nested(|visitor| walk_method(visitor, ast));
     }
 
-    fn emit_argument(&mut self, ast: &Argument) -> Result<(), EmitError> {
+    fn visit_getter(&mut self, ast: &Getter<'alloc>) {
+        self.nested(|visitor| walk_getter(visitor, ast));
+    }
+
+    fn visit_setter(&mut self, ast: &Setter<'alloc>) {
+        self.nested(|visitor| walk_setter(visitor, ast));
+    }
+}
+
+// True if `this`, `new.target` or `super` in the parameters or body of an
+// arrow function are those of the code around it, as they are unless
+// they're in a function, method or class element of their own.
+fn uses_enclosing_this(params: &FormalParameters, body: &ArrowExpressionBody) -> bool {
+    let mut finder = FindEnclosingThis { found: false };
+    finder.visit_formal_parameters(params);
+    finder.visit_arrow_expression_body(body);
+    finder.found
+}
+
+struct FindEnclosingThis {
+    found: bool,
+}
+
+impl<'alloc> Visitor<'alloc> for FindEnclosingThis {
+    fn visit_expression(&mut self, ast: &Expression<'alloc>) {
         match ast {
-            Argument::Expression(ast) => self.emit_expression(ast)?,
-            Argument::SpreadElement(_) => {
-                return Err(EmitError::NotImplemented("TODO: SpreadElement"));
+            Expression::ThisExpression { .. } | Expression::NewTargetExpression { .. } => {
+                self.found = true;
             }
+            _ => walk_expression(self, ast),
         }
+    }
 
-        Ok(())
+    fn visit_expression_or_super(&mut self, ast: &ExpressionOrSuper<'alloc>) {
+        match ast {
+            ExpressionOrSuper::Super { .. } => self.found = true,
+            ExpressionOrSuper::Expression(expression) => self.visit_expression(expression),
+        }
+    }
+
+    fn visit_function(&mut self, _ast: &Function<'alloc>) {}
+
+    // Computed names are evaluated in the code around the method or field.
+    fn visit_method(&mut self, ast: &Method<'alloc>) {
+        self.visit_property_name(&ast.name);
+    }
+
+    fn visit_getter(&mut self, ast: &Getter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+    }
+
+    fn visit_setter(&mut self, ast: &Setter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+    }
+
+    fn visit_class_element(&mut self, ast: &ClassElement<'alloc>) {
+        match ast {
+            ClassElement::MethodDefinition { method, .. } => self.visit_method_definition(method),
+            ClassElement::FieldDefinition { name, .. } => self.visit_class_element_name(name),
+            ClassElement::StaticBlock { .. } => {}
+        }
+    }
+}
+
+// Add the bindings of the `let`, `const`, class and function declarations
+// directly in `statements`.
+fn add_lexical_bindings(statements: &[Statement], bindings: &mut Vec<BindingNote>) {
+    for statement in statements {
+        let names = match statement {
+            Statement::VariableDeclarationStatement(declaration) => {
+                lexical_declaration_bindings(declaration)
+            }
+            Statement::FunctionDeclaration(Function {
+                name: Some(name), ..
+            }) => global_bindings(vec![name.name.value.to_owned()], BindingKind::Function),
+            Statement::ClassDeclaration(ClassDeclaration { name, .. }) => {
+                global_bindings(vec![name.name.value.to_owned()], BindingKind::Class)
+            }
+            _ => continue,
+        };
+        for binding in names {
+            add_binding(bindings, binding);
+        }
+    }
+}
+
+// The bindings of a `let` or `const` declaration, or none for `var`.
+fn lexical_declaration_bindings(declaration: &VariableDeclaration) -> Vec<BindingNote> {
+    let kind = match declaration.kind {
+        VariableDeclarationKind::Var { .. } => return Vec::new(),
+        VariableDeclarationKind::Let { .. } => BindingKind::Let,
+        VariableDeclarationKind::Const { .. } => BindingKind::Const,
+    };
+    let mut names = Vec::new();
+    for declarator in declaration.declarators.iter() {
+        add_bound_names(&declarator.binding, &mut names);
     }
+    global_bindings(names, kind)
+}
+
+// The name of a property that isn't computed.
+fn static_property_name<'alloc>(name: &PropertyName<'alloc>) -> Option<&'alloc str> {
+    match name {
+        PropertyName::StaticPropertyName(StaticPropertyName { value, .. }) => Some(value),
+        PropertyName::ComputedPropertyName(_) => None,
+    }
+}
+
+// The parameters of a function, which can't have a rest parameter yet.
+fn parameter_items<'a, 'alloc>(
+    params: &'a FormalParameters<'alloc>,
+) -> Result<&'a [Parameter<'alloc>], EmitError> {
+    if params.rest.is_some() {
+        return Err(EmitError::NotImplemented("TODO: rest parameters"));
+    }
+    Ok(&params.items)
+}
+
+// A method of a class named `constructor`, which is the class itself.
+fn is_constructor(method: &Method) -> bool {
+    matches!(
+        method.name,
+        PropertyName::StaticPropertyName(StaticPropertyName {
+            value: "constructor",
+            ..
+        })
+    )
 }
@@ -1,32 +1,159 @@
+use crate::emitter::EmitResult;
 use crate::opcode::Opcode;
-use std::convert::TryFrom;
-use std::fmt::Write;
+use crate::source_map::LineNotes;
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Write};
 
-/// Return a string form of the given bytecode.
+/// Return a string form of the given bytecode. See `dis_to`.
 pub fn dis(bc: &[u8]) -> String {
-    let mut result = String::new();
-    let mut iter = bc.iter();
-    loop {
-        let len = match iter.next() {
-            Some(byte) => match Opcode::try_from(*byte) {
-                Ok(op) => {
-                    write!(&mut result, "{:?}", op).unwrap();
-                    op.instruction_length()
-                }
-                Err(()) => {
-                    write!(&mut result, "{}", byte).unwrap();
-                    1
+    let mut result = Vec::new();
+    dis_to(bc, &mut result).unwrap();
+    String::from_utf8(result).unwrap()
+}
+
+/// Write a string form of the given bytecode to `out`, one instruction per
+/// line, each starting with its offset in the bytecode.
+///
+/// Each instruction a jump goes to is preceded by a label line, such as
+/// `L0:`, numbered in the order of the bytecode. The operand of the jump is
+/// shown as the label, with the offset it's at, as in `Goto L0 (00012)`.
+pub fn dis_to(bc: &[u8], out: &mut impl Write) -> io::Result<()> {
+    write_instructions(bc, None, out)
+}
+
+/// Return a string form of the given bytecode, with the source line it
+/// comes from in the margin. See `dis_with_lines_to`.
+pub fn dis_with_lines(bc: &[u8], line_notes: &LineNotes) -> String {
+    let mut result = Vec::new();
+    dis_with_lines_to(bc, line_notes, &mut result).unwrap();
+    String::from_utf8(result).unwrap()
+}
+
+/// Write a string form of the given bytecode to `out`, like `dis_to`, with
+/// the 1-based source line in the margin of each instruction that starts a
+/// new line.
+pub fn dis_with_lines_to(
+    bc: &[u8],
+    line_notes: &LineNotes,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    write_instructions(bc, Some(line_notes), out)
+}
+
+fn write_instructions(
+    bc: &[u8],
+    line_notes: Option<&LineNotes>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let labels = jump_labels(bc);
+    let mut notes = line_notes.map(|notes| notes.iter().peekable());
+    let mut line = None;
+    let mut iter = bc.iter().enumerate();
+    while let Some((pc, byte)) = iter.next() {
+        if let Some(label) = labels.get(&pc) {
+            writeln!(out, "L{}:", label)?;
+        }
+        if let Some(notes) = &mut notes {
+            while let Some((_, note_line)) = notes.next_if(|&(offset, _)| offset <= pc) {
+                line = Some(note_line);
+            }
+            match line.take() {
+                Some(line) => write!(out, "{:>4} ", line + 1)?,
+                None => write!(out, "     ")?,
+            }
+        }
+        write!(out, "{:05} ", pc)?;
+        let len = match Opcode::try_from(*byte) {
+            Ok(op) => {
+                write!(out, "{}", op.name())?;
+                if let Some(target) = jump_target(bc, pc, op) {
+                    writeln!(out, " L{} ({:05})", labels[&target], target)?;
+                    for _ in 1..op.instruction_length() {
+                        iter.next();
+                    }
+                    continue;
                 }
-            },
-            None => break,
+                op.instruction_length()
+            }
+            Err(()) => {
+                write!(out, "{}", byte)?;
+                1
+            }
         };
 
         for _ in 1..len {
-            write!(&mut result, " {}", iter.next().unwrap()).unwrap();
+            match iter.next() {
+                Some((_, operand)) => write!(out, " {}", operand)?,
+                None => break,
+            }
         }
 
-        writeln!(&mut result).unwrap();
+        writeln!(out)?;
+    }
+    // A jump to the end of the bytecode.
+    if let Some(label) = labels.get(&bc.len()) {
+        writeln!(out, "L{}:", label)?;
+    }
+
+    Ok(())
+}
+
+/// The offset the jump at `pc` goes to, if `op` is a jump and its operand
+/// isn't cut off.
+fn jump_target(bc: &[u8], pc: usize, op: Opcode) -> Option<usize> {
+    if !op.is_jump() {
+        return None;
+    }
+    let operand: [u8; 4] = bc.get(pc + 1..pc + 5)?.try_into().ok()?;
+    let target = pc as i64 + i64::from(i32::from_le_bytes(operand));
+    usize::try_from(target).ok()
+}
+
+/// The number of the label of each offset that a jump in the bytecode goes
+/// to, numbered from 0 in order of offset, for a first pass over it.
+fn jump_labels(bc: &[u8]) -> BTreeMap<usize, usize> {
+    let mut targets = BTreeMap::new();
+    let mut pc = 0;
+    while pc < bc.len() {
+        let len = match Opcode::try_from(bc[pc]) {
+            Ok(op) => {
+                if let Some(target) = jump_target(bc, pc, op) {
+                    targets.insert(target, 0);
+                }
+                op.instruction_length()
+            }
+            Err(()) => 1,
+        };
+        pc += len;
     }
+    for (number, label) in targets.values_mut().enumerate() {
+        *label = number;
+    }
+    targets
+}
+
+/// Count the instructions in the bytecode of `result`, and of the functions
+/// in it, by the name of their opcode. Bytes that aren't an opcode are
+/// counted as `"(invalid)"`, one instruction each, as `dis` shows them.
+pub fn opcode_histogram(result: &EmitResult) -> BTreeMap<&'static str, usize> {
+    let mut histogram = BTreeMap::new();
+    add_opcodes(result, &mut histogram);
+    histogram
+}
 
-    result
+fn add_opcodes(result: &EmitResult, histogram: &mut BTreeMap<&'static str, usize>) {
+    let bc = &result.bytecode;
+    let mut pc = 0;
+    while pc < bc.len() {
+        let (name, len) = match Opcode::try_from(bc[pc]) {
+            Ok(op) => (op.name(), op.instruction_length()),
+            Err(()) => ("(invalid)", 1),
+        };
+        *histogram.entry(name).or_insert(0) += 1;
+        pc += len;
+    }
+    for function in &result.functions {
+        add_opcodes(&function.body, histogram);
+    }
 }
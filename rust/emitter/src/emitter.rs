@@ -6,14 +6,19 @@
 #![allow(dead_code)]
 
 use super::opcode::Opcode;
+use super::source_map::{LineNotes, SourceMap};
 use byteorder::{ByteOrder, LittleEndian};
 use std::convert::TryInto;
 use std::fmt;
+use std::rc::Rc;
 
+/// How a generator is resumed: by its `next`, `throw` or `return` method.
+/// Pushed on the stack for `CheckResumeKind` when the generator resumes.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ResumeKind {
     Normal = 0,
     Throw = 1,
+    Return = 2,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -22,6 +27,232 @@ pub enum AsyncFunctionResolveKind {
     Reject = 1,
 }
 
+/// The well-known symbols, in the order of `JS::SymbolCode`. The operand of
+/// `Symbol` instructions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SymbolCode {
+    IsConcatSpreadable = 0,
+    Iterator = 1,
+    Match = 2,
+    MatchAll = 3,
+    Replace = 4,
+    Search = 5,
+    Species = 6,
+    HasInstance = 7,
+    Split = 8,
+    ToPrimitive = 9,
+    ToStringTag = 10,
+    Unscopables = 11,
+    AsyncIterator = 12,
+}
+
+impl SymbolCode {
+    const ALL: [SymbolCode; 13] = [
+        SymbolCode::IsConcatSpreadable,
+        SymbolCode::Iterator,
+        SymbolCode::Match,
+        SymbolCode::MatchAll,
+        SymbolCode::Replace,
+        SymbolCode::Search,
+        SymbolCode::Species,
+        SymbolCode::HasInstance,
+        SymbolCode::Split,
+        SymbolCode::ToPrimitive,
+        SymbolCode::ToStringTag,
+        SymbolCode::Unscopables,
+        SymbolCode::AsyncIterator,
+    ];
+
+    /// The symbol of a `Symbol` instruction's operand.
+    pub fn from_u8(code: u8) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+
+    /// The name of the symbol as a property of `Symbol`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolCode::IsConcatSpreadable => "isConcatSpreadable",
+            SymbolCode::Iterator => "iterator",
+            SymbolCode::Match => "match",
+            SymbolCode::MatchAll => "matchAll",
+            SymbolCode::Replace => "replace",
+            SymbolCode::Search => "search",
+            SymbolCode::Species => "species",
+            SymbolCode::HasInstance => "hasInstance",
+            SymbolCode::Split => "split",
+            SymbolCode::ToPrimitive => "toPrimitive",
+            SymbolCode::ToStringTag => "toStringTag",
+            SymbolCode::Unscopables => "unscopables",
+            SymbolCode::AsyncIterator => "asyncIterator",
+        }
+    }
+}
+
+/// What the value checked by a `CheckIsObj` instruction is, which decides the
+/// error message when it isn't an object.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckIsObjectKind {
+    IteratorNext = 0,
+    IteratorReturn = 1,
+    IteratorThrow = 2,
+    GetIterator = 3,
+    GetAsyncIterator = 4,
+}
+
+/// What a `TryNote` handles exceptions for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TryNoteKind {
+    /// A `try` block followed by a `catch` block.
+    Catch = 0,
+    /// A `try` block, and its `catch` block if any, followed by a `finally`
+    /// block.
+    Finally = 1,
+}
+
+impl TryNoteKind {
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            0 => Some(TryNoteKind::Catch),
+            1 => Some(TryNoteKind::Finally),
+            _ => None,
+        }
+    }
+}
+
+/// A range of bytecode whose exceptions are handled by the code right after
+/// it, as with `JSTryNote` in SpiderMonkey.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryNote {
+    pub kind: TryNoteKind,
+
+    /// The stack depth at the start of the range, to unwind the stack to
+    /// before running the handler.
+    pub stack_depth: usize,
+
+    /// The bytecode offset of the start of the range.
+    pub start: usize,
+
+    /// The length of the range in bytes. The handler starts at `start +
+    /// length`.
+    pub length: usize,
+}
+
+/// What a `ScopeNote` is the scope of.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScopeKind {
+    /// The top level of a script.
+    Script = 0,
+    /// The body of a function, with its parameters.
+    Function = 1,
+    /// A block, the case clauses of a `switch` statement or the head of a
+    /// `for-in` or `for-of` loop, with the `let`, `const`, class and function
+    /// declarations directly in it.
+    Block = 2,
+    /// A catch clause, with its parameter. Its block is another scope.
+    Catch = 3,
+}
+
+impl ScopeKind {
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            0 => Some(ScopeKind::Script),
+            1 => Some(ScopeKind::Function),
+            2 => Some(ScopeKind::Block),
+            3 => Some(ScopeKind::Catch),
+            _ => None,
+        }
+    }
+}
+
+/// How a `BindingNote` is declared.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingKind {
+    Var = 0,
+    Let = 1,
+    Const = 2,
+    /// A parameter, or a name in a parameter's pattern.
+    Parameter = 3,
+    Function = 4,
+    Class = 5,
+    CatchParameter = 6,
+    /// A slot the emitted code uses for itself, which code can't name, like
+    /// `.generator` for the generator object of a generator or async
+    /// function.
+    Internal = 7,
+}
+
+impl BindingKind {
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            0 => Some(BindingKind::Var),
+            1 => Some(BindingKind::Let),
+            2 => Some(BindingKind::Const),
+            3 => Some(BindingKind::Parameter),
+            4 => Some(BindingKind::Function),
+            5 => Some(BindingKind::Class),
+            6 => Some(BindingKind::CatchParameter),
+            7 => Some(BindingKind::Internal),
+            _ => None,
+        }
+    }
+}
+
+/// Where the value of a `BindingNote` is kept.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingSlot {
+    /// An argument slot of the frame, read with `GetArg`.
+    Argument(u16),
+    /// A local variable slot of the frame, read with `GetLocal`.
+    Local(u24),
+    /// A slot of the environment the scope creates when it's entered, read
+    /// with `GetAliasedVar`, for a binding that a function made in its scope
+    /// may refer to.
+    Environment(u24),
+    /// A property of the global object, read with `GetGName`, as the
+    /// bindings of the top level of a script are.
+    Global,
+}
+
+/// A name bound in a `ScopeNote`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindingNote {
+    pub name: String,
+    pub kind: BindingKind,
+    pub slot: BindingSlot,
+}
+
+/// A scope of the code and the names it binds, as with `js::Scope` in
+/// SpiderMonkey, for debuggers and tools, and for `PushVarEnv` and
+/// `PushLexicalEnv` to make environments for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeNote {
+    pub kind: ScopeKind,
+
+    /// The index of the scope this one is in, or None for the outermost
+    /// one, which is the script or function.
+    pub enclosing: Option<usize>,
+
+    /// The bindings, with the parameters first, then the bindings declared
+    /// with `var`, anywhere in a script or function, then the others.
+    pub bindings: Vec<BindingNote>,
+}
+
+impl ScopeNote {
+    /// The number of slots the scope's environment has, which are the
+    /// `BindingSlot::Environment` slots of its bindings. A scope without
+    /// any doesn't make an environment.
+    pub fn environment_slot_count(&self) -> u32 {
+        self.bindings
+            .iter()
+            .filter_map(|binding| match binding.slot {
+                BindingSlot::Environment(slot) => Some(slot + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
 #[allow(non_camel_case_types)]
 pub type u24 = u32;
 
@@ -35,6 +266,7 @@ pub struct BytecodeOffset {
 pub struct InstructionWriter {
     bytecode: Vec<u8>,
     strings: Vec<String>,
+    bigints: Vec<String>,
 
     /// Stack depth after the instructions emitted so far.
     stack_depth: usize,
@@ -47,16 +279,130 @@ pub struct InstructionWriter {
 
     /// Number of instructions in this script that have JOF_TYPESET.
     num_type_sets: usize,
+
+    /// `(bytecode offset, source offset)` pairs, in bytecode order, for the
+    /// source map. See `record_position`.
+    positions: Vec<(usize, usize)>,
+
+    try_notes: Vec<TryNote>,
+    resume_offsets: Vec<usize>,
+    functions: Vec<Rc<FunctionCode>>,
+    scopes: Vec<ScopeNote>,
 }
 
 /// The output of bytecode-compiling a script or module.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct EmitResult {
     pub bytecode: Vec<u8>,
     pub strings: Vec<String>,
+
+    /// The values of the BigInt literals, as decimal digits, indexed by the
+    /// operand of `BigInt` instructions.
+    pub bigints: Vec<String>,
+
     pub maximum_stack_depth: u32,
     pub num_ic_entries: u32,
     pub num_type_sets: u32,
+
+    /// Where exceptions are caught, with inner `try` statements before the
+    /// ones they are nested in.
+    pub try_notes: Vec<TryNote>,
+
+    /// The bytecode offsets where execution resumes, indexed by the operand
+    /// of `ResumeIndex`, `InitialYield` and `Yield` instructions.
+    pub resume_offsets: Vec<usize>,
+
+    /// The functions defined in the code, indexed by the operand of `Lambda`
+    /// instructions.
+    pub functions: Vec<Rc<FunctionCode>>,
+
+    /// The scopes of the code, in the order they start in. Blocks, catch
+    /// clauses and loop heads that bind no names aren't scopes.
+    pub scopes: Vec<ScopeNote>,
+
+    /// Where in the source each part of the bytecode comes from, if
+    /// `EmitOptions::source_map` was set.
+    pub source_map: Option<SourceMap>,
+
+    /// The source line of each part of the bytecode, if
+    /// `EmitOptions::line_notes` was set.
+    pub line_notes: Option<LineNotes>,
+}
+
+impl EmitResult {
+    /// The number of local variable slots the frame running the code needs,
+    /// which are the `BindingSlot::Local` slots of `scopes`.
+    pub fn frame_slot_count(&self) -> u32 {
+        self.scopes
+            .iter()
+            .flat_map(|scope| &scope.bindings)
+            .filter_map(|binding| match binding.slot {
+                BindingSlot::Local(slot) => Some(slot + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A function compiled separately from the code around it, to run when
+/// the function is called.
+#[derive(Debug, PartialEq)]
+pub struct FunctionCode {
+    /// The name, if the function has one.
+    pub name: Option<String>,
+
+    /// The number of parameters, read with `GetArg`.
+    pub num_params: u32,
+
+    /// The number of local variable slots a call needs: the body's
+    /// `frame_slot_count`, computed once when the function is emitted.
+    pub frame_slot_count: u32,
+
+    /// True for a `function*`, whose body starts with `InitialYield`.
+    pub is_generator: bool,
+
+    /// True for a function that `new` can call: a `function` that isn't a
+    /// generator or async. `Lambda` gives it a `prototype`. Arrow
+    /// functions and methods aren't constructors.
+    pub is_constructor: bool,
+
+    /// True for the constructor of a class. `Lambda` makes a class from it,
+    /// which can only be called with `new`.
+    pub is_class_constructor: bool,
+
+    /// The function body.
+    pub body: EmitResult,
+}
+
+/// Options for bytecode-compilation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmitOptions<'a> {
+    /// Map bytecode offsets back to positions in `source`; see
+    /// `EmitResult::source_map`.
+    pub source_map: bool,
+
+    /// Record the source line of the bytecode; see `EmitResult::line_notes`.
+    pub line_notes: bool,
+
+    /// The text the program was parsed from. Only used for the source map
+    /// and line notes.
+    pub source: &'a str,
+
+    /// The name the source map refers to `source` by.
+    pub source_name: &'a str,
+
+    /// Evaluate constant expressions at compile time; see
+    /// `optimize::fold_constants`.
+    pub fold_constants: bool,
+
+    /// Leave out the statements that can't run because of a jump before them;
+    /// see `optimize::eliminate_dead_code`.
+    pub dead_code_elimination: bool,
+
+    /// Emit bytecode for strict mode code, as if the program started with a
+    /// "use strict" directive.
+    pub strict: bool,
 }
 
 /// The error of bytecode-compilation.
@@ -78,23 +424,51 @@ impl InstructionWriter {
         Self {
             bytecode: Vec::new(),
             strings: Vec::new(),
+            bigints: Vec::new(),
             stack_depth: 0,
             maximum_stack_depth: 0,
             num_ic_entries: 0,
             num_type_sets: 0,
+            positions: Vec::new(),
+            try_notes: Vec::new(),
+            resume_offsets: Vec::new(),
+            functions: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Record that the next instruction comes from `source_offset` in the
+    /// source, until another position is recorded.
+    pub fn record_position(&mut self, source_offset: usize) {
+        let bytecode_offset = self.bytecode.len();
+        match self.positions.last_mut() {
+            Some(last) if last.1 == source_offset => {}
+            Some(last) if last.0 == bytecode_offset => last.1 = source_offset,
+            _ => self.positions.push((bytecode_offset, source_offset)),
         }
     }
 
+    pub fn positions(&self) -> &[(usize, usize)] {
+        &self.positions
+    }
+
     pub fn into_emit_result(self) -> EmitResult {
         EmitResult {
             bytecode: self.bytecode,
             strings: self.strings,
+            bigints: self.bigints,
 
             // These values probably can't be out of range for u32, as we would
             // have hit other limits first. Release-assert anyway.
             maximum_stack_depth: self.maximum_stack_depth.try_into().unwrap(),
             num_ic_entries: self.num_ic_entries.try_into().unwrap(),
             num_type_sets: self.num_type_sets.try_into().unwrap(),
+            try_notes: self.try_notes,
+            resume_offsets: self.resume_offsets,
+            functions: self.functions,
+            scopes: self.scopes,
+            source_map: None,
+            line_notes: None,
         }
     }
 
@@ -115,7 +489,13 @@ impl InstructionWriter {
     fn emit_argc_op(&mut self, opcode: Opcode, argc: u16) {
         assert!(opcode.has_argc());
         assert_eq!(opcode.nuses(), -1);
-        self.emit_op_common(opcode, argc as usize);
+        // The callee, `this` and the arguments, and `new.target` for
+        // constructor calls.
+        let nuses = match opcode {
+            Opcode::New | Opcode::SuperCall => argc as usize + 3,
+            _ => argc as usize + 2,
+        };
+        self.emit_op_common(opcode, nuses);
         self.write_u16(argc);
     }
 
@@ -264,8 +644,17 @@ impl InstructionWriter {
             .extend_from_slice(&value.to_bits().to_le_bytes());
     }
 
-    pub fn big_int(&mut self, const_index: u32) {
-        self.emit_u32(Opcode::BigInt, const_index);
+    /// `value` is the decimal digits of the BigInt, as in
+    /// `Expression::LiteralBigIntExpression`.
+    pub fn big_int(&mut self, value: &str) {
+        let index = match self.bigints.iter().position(|bigint| bigint == value) {
+            Some(index) => index,
+            None => {
+                self.bigints.push(value.to_string());
+                self.bigints.len() - 1
+            }
+        };
+        self.emit_u32(Opcode::BigInt, index as u32);
     }
 
     pub fn string(&mut self, value: &str) {
@@ -273,8 +662,8 @@ impl InstructionWriter {
         self.emit_atom(value);
     }
 
-    pub fn symbol(&mut self, symbol: u8) {
-        self.emit_u8(Opcode::Symbol, symbol);
+    pub fn symbol(&mut self, symbol: SymbolCode) {
+        self.emit_u8(Opcode::Symbol, symbol as u8);
     }
 
     pub fn emit_unary_op(&mut self, opcode: Opcode) {
@@ -365,6 +754,26 @@ impl InstructionWriter {
         self.emit_with_name_index(Opcode::InitLockedProp, name);
     }
 
+    pub fn new_private_name(&mut self, name: &str) {
+        self.emit_with_name_index(Opcode::NewPrivateName, name);
+    }
+
+    pub fn init_private_field(&mut self) {
+        self.emit1(Opcode::InitPrivateField);
+    }
+
+    pub fn get_private_field(&mut self) {
+        self.emit1(Opcode::GetPrivateField);
+    }
+
+    pub fn set_private_field(&mut self) {
+        self.emit1(Opcode::SetPrivateField);
+    }
+
+    pub fn copy_data_properties(&mut self) {
+        self.emit1(Opcode::CopyDataProperties);
+    }
+
     pub fn init_elem(&mut self) {
         self.emit1(Opcode::InitElem);
     }
@@ -509,9 +918,8 @@ impl InstructionWriter {
         self.emit1(Opcode::EndIter);
     }
 
-    // TODO - stronger typing of this parameter
-    pub fn check_is_obj(&mut self, kind: u8) {
-        self.emit_u8(Opcode::CheckIsObj, kind);
+    pub fn check_is_obj(&mut self, kind: CheckIsObjectKind) {
+        self.emit_u8(Opcode::CheckIsObj, kind as u8);
     }
 
     pub fn check_is_callable(&mut self, kind: u8) {
@@ -696,6 +1104,10 @@ impl InstructionWriter {
         self.emit_u24(Opcode::Yield, resume_index);
     }
 
+    pub fn check_resume_kind(&mut self) {
+        self.emit1(Opcode::CheckResumeKind);
+    }
+
     pub fn is_gen_closing(&mut self) {
         self.emit1(Opcode::IsGenClosing);
     }
@@ -730,6 +1142,53 @@ impl InstructionWriter {
         }
     }
 
+    pub fn stack_depth(&self) -> usize {
+        self.stack_depth
+    }
+
+    /// Set the stack depth at a jump target that's only reached by jumping,
+    /// where it can differ from that after the instruction before it.
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack_depth = depth;
+    }
+
+    /// Handle the exceptions thrown by the instructions from `start` up to
+    /// the next one emitted, with the handler emitted next.
+    pub fn add_try_note(&mut self, kind: TryNoteKind, stack_depth: usize, start: &BytecodeOffset) {
+        let length = self.bytecode.len() - start.offset;
+        self.try_notes.push(TryNote {
+            kind,
+            stack_depth,
+            start: start.offset,
+            length,
+        });
+    }
+
+    /// Allocate a resume index, for the operand of a `ResumeIndex`,
+    /// `InitialYield` or `Yield` instruction. Its offset is set by
+    /// `set_resume_offset`.
+    pub fn new_resume_index(&mut self) -> u24 {
+        self.resume_offsets.push(0);
+        (self.resume_offsets.len() - 1).try_into().unwrap()
+    }
+
+    /// Resume at the next instruction emitted for `resume_index`.
+    pub fn set_resume_offset(&mut self, resume_index: u24) {
+        self.resume_offsets[resume_index as usize] = self.bytecode.len();
+    }
+
+    /// Add a function, returning its index for the operand of `Lambda`.
+    pub fn add_function(&mut self, function: FunctionCode) -> u32 {
+        self.functions.push(Rc::new(function));
+        (self.functions.len() - 1).try_into().unwrap()
+    }
+
+    /// Add a scope to `EmitResult::scopes`, returning its index.
+    pub fn add_scope(&mut self, scope: ScopeNote) -> usize {
+        self.scopes.push(scope);
+        self.scopes.len() - 1
+    }
+
     pub fn patch_jump_target(&mut self, jumplist: Vec<BytecodeOffset>) {
         let target = self.bytecode_offset();
         for jump in jumplist {
@@ -739,8 +1198,11 @@ impl InstructionWriter {
         }
     }
 
-    pub fn loop_head(&mut self) {
+    /// `depth` is how deeply the loop is nested, 1 for a loop that isn't in
+    /// another one.
+    pub fn loop_head(&mut self, depth: u8) {
         self.emit_op(Opcode::LoopHead);
+        self.bytecode.push(depth);
     }
 
     pub fn goto(&mut self, offset: i32) {
@@ -825,8 +1287,9 @@ impl InstructionWriter {
         self.emit_u24(Opcode::ThrowSetConst, local_no);
     }
 
-    pub fn try_(&mut self) {
-        self.emit1(Opcode::Try);
+    /// `offset` is the offset of the `Goto` at the end of the `try` block.
+    pub fn try_(&mut self, offset: i32) {
+        self.emit_with_offset(Opcode::Try, offset);
     }
 
     pub fn try_destructuring(&mut self) {
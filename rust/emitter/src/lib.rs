@@ -3,22 +3,51 @@ mod dis;
 mod emitter;
 mod lower;
 pub mod opcode;
+pub mod optimize;
+mod serialize;
+mod source_map;
 
-pub use crate::emitter::{EmitError, EmitResult};
-pub use dis::dis;
+pub use crate::emitter::{
+    AsyncFunctionResolveKind, BindingKind, BindingNote, BindingSlot, CheckIsObjectKind, EmitError,
+    EmitOptions, EmitResult, FunctionCode, ResumeKind, ScopeKind, ScopeNote, SymbolCode, TryNote,
+    TryNoteKind,
+};
+pub use crate::serialize::{deserialize, serialize, DecodeError, FORMAT_VERSION};
+pub use crate::source_map::{source_map_json, LineNotes, Mapping, SourceMap};
+pub use dis::{dis, dis_to, dis_with_lines, dis_with_lines_to, opcode_histogram};
 
+/// Compile a program with the default options. See `emit_with_options`.
 pub fn emit(ast: &mut ast::types::Program) -> Result<EmitResult, EmitError> {
+    emit_with_options(ast, &EmitOptions::default())
+}
+
+pub fn emit_with_options(
+    ast: &mut ast::types::Program,
+    options: &EmitOptions,
+) -> Result<EmitResult, EmitError> {
     //lower::run(ast);
-    ast_emitter::emit_program(ast)
+    if options.fold_constants {
+        optimize::fold_constants(ast);
+    }
+    if options.dead_code_elimination {
+        optimize::eliminate_dead_code(ast);
+    }
+    ast_emitter::emit_program(ast, options)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::emit;
+    use super::{
+        deserialize, dis_with_lines, emit, emit_with_options, opcode_histogram, serialize,
+        source_map_json, BindingKind, BindingNote, BindingSlot, DecodeError, EmitError,
+        EmitOptions, Mapping, ScopeKind, ScopeNote, SymbolCode, TryNote, TryNoteKind,
+        FORMAT_VERSION,
+    };
     use crate::dis::*;
     use crate::opcode::*;
     use bumpalo::Bump;
-    use parser::parse_script;
+    use parser::{parse_module, parse_script};
+    use std::convert::{TryFrom, TryInto};
 
     fn bytecode(source: &str) -> Vec<u8> {
         let alloc = &Bump::new();
@@ -71,6 +100,214 @@ mod tests {
         )
     }
 
+    // The offset and opcode of each instruction in `bc`.
+    fn instructions(bc: &[u8]) -> Vec<(usize, Opcode)> {
+        let mut offsets = vec![];
+        let mut offset = 0;
+        while offset < bc.len() {
+            let op = Opcode::try_from(bc[offset]).unwrap();
+            offsets.push((offset, op));
+            offset += op.instruction_length();
+        }
+        offsets
+    }
+
+    fn jump_target(bc: &[u8], offset: usize) -> usize {
+        let jump = i32::from_le_bytes(bc[offset + 1..offset + 5].try_into().unwrap());
+        (offset as i32 + jump) as usize
+    }
+
+    #[test]
+    fn optional_chain() {
+        // Both `?.` jump to the end of the chain, after `GetProp "c"`.
+        let bc = bytecode("a?.b.c?.d");
+        let offsets = instructions(&bc);
+        let ops: Vec<Opcode> = offsets.iter().map(|&(_, op)| op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::GetGName,
+                Opcode::Coalesce,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::Undefined,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::GetProp,
+                Opcode::GetProp,
+                Opcode::Coalesce,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::Undefined,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::GetProp,
+                Opcode::JumpTarget,
+                Opcode::SetRval,
+                Opcode::RetRval,
+            ]
+        );
+        let end = offsets[16].0;
+        for &(offset, _) in offsets.iter().filter(|&&(_, op)| op == Opcode::Goto) {
+            assert_eq!(jump_target(&bc, offset), end);
+        }
+    }
+
+    #[test]
+    fn logical_assignment() {
+        // `o` and `k` are evaluated once, and `SetElem` is skipped with the
+        // right-hand side when `o[k]` isn't null or undefined.
+        let bc = bytecode("o[k] ??= v");
+        let offsets = instructions(&bc);
+        let ops: Vec<Opcode> = offsets.iter().map(|&(_, op)| op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::GetGName,
+                Opcode::GetGName,
+                Opcode::Dup2,
+                Opcode::GetElem,
+                Opcode::Coalesce,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::GetGName,
+                Opcode::SetElem,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::Swap,
+                Opcode::Pop,
+                Opcode::Swap,
+                Opcode::Pop,
+                Opcode::JumpTarget,
+                Opcode::SetRval,
+                Opcode::RetRval,
+            ]
+        );
+        assert_eq!(jump_target(&bc, offsets[4].0), offsets[10].0);
+        assert_eq!(jump_target(&bc, offsets[9].0), offsets[15].0);
+    }
+
+    #[test]
+    fn postfix_increment() {
+        // The old value is kept under `o` and `k` until `SetElem` is done.
+        let bc = bytecode("o[k]++");
+        let ops: Vec<Opcode> = instructions(&bc).iter().map(|&(_, op)| op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::GetGName,
+                Opcode::GetGName,
+                Opcode::Dup2,
+                Opcode::GetElem,
+                Opcode::ToNumeric,
+                Opcode::Dup,
+                Opcode::Unpick,
+                Opcode::Inc,
+                Opcode::SetElem,
+                Opcode::Pop,
+                Opcode::SetRval,
+                Opcode::RetRval,
+            ]
+        );
+    }
+
+    #[test]
+    fn for_continue() {
+        // `continue` jumps to the update, and `IfEq` on the test to the end
+        // of the loop.
+        let bc = bytecode("for (; a; b) continue;");
+        let offsets = instructions(&bc);
+        let ops: Vec<Opcode> = offsets.iter().map(|&(_, op)| op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::Undefined,
+                Opcode::SetRval,
+                Opcode::LoopHead,
+                Opcode::GetGName,
+                Opcode::IfEq,
+                Opcode::JumpTarget,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::GetGName,
+                Opcode::Pop,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::RetRval,
+            ]
+        );
+        assert_eq!(jump_target(&bc, offsets[4].0), offsets[11].0);
+        assert_eq!(jump_target(&bc, offsets[6].0), offsets[7].0);
+        assert_eq!(jump_target(&bc, offsets[10].0), offsets[2].0);
+    }
+
+    #[test]
+    fn for_of_break() {
+        // `break` jumps to the code that calls the iterator's `return`
+        // method, and `IfNe` on the `done` property to the end of the loop.
+        let bc = bytecode("for (x of a) break;");
+        let offsets = instructions(&bc);
+        let ops: Vec<Opcode> = offsets.iter().map(|&(_, op)| op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Opcode::Undefined,
+                Opcode::SetRval,
+                Opcode::GetGName,
+                Opcode::Dup,
+                Opcode::Symbol,
+                Opcode::CallElem,
+                Opcode::Swap,
+                Opcode::CallIter,
+                Opcode::CheckIsObj,
+                Opcode::Dup,
+                Opcode::GetProp,
+                Opcode::Swap,
+                Opcode::LoopHead,
+                Opcode::Dup2,
+                Opcode::CallIter,
+                Opcode::CheckIsObj,
+                Opcode::Dup,
+                Opcode::GetProp,
+                Opcode::IfNe,
+                Opcode::GetProp,
+                Opcode::BindGName,
+                Opcode::Swap,
+                Opcode::SetGName,
+                Opcode::Pop,
+                Opcode::Goto,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::Dup,
+                Opcode::Dup,
+                Opcode::GetProp,
+                Opcode::Coalesce,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::Pop,
+                Opcode::Goto,
+                Opcode::JumpTarget,
+                Opcode::Swap,
+                Opcode::CallIter,
+                Opcode::CheckIsObj,
+                Opcode::Pop,
+                Opcode::JumpTarget,
+                Opcode::JumpTarget,
+                Opcode::Pop,
+                Opcode::Pop,
+                Opcode::RetRval,
+            ]
+        );
+        assert_eq!(bc[offsets[4].0 + 1], SymbolCode::Iterator as u8);
+        assert_eq!(jump_target(&bc, offsets[18].0), offsets[26].0);
+        assert_eq!(jump_target(&bc, offsets[24].0), offsets[29].0);
+        assert_eq!(jump_target(&bc, offsets[25].0), offsets[12].0);
+        assert_eq!(jump_target(&bc, offsets[28].0), offsets[44].0);
+    }
+
     #[test]
     fn literals() {
         assert_eq!(
@@ -98,4 +335,639 @@ mod tests {
         //    ]
         //);
     }
+
+    #[test]
+    fn try_notes() {
+        let alloc = &Bump::new();
+        let source = "try { a; } catch (e) { b; } finally { c; }";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+        let offsets = instructions(&result.bytecode);
+
+        // Both notes cover the try block, from the instruction after `Try`,
+        // and the finally one covers the catch block too. Their handlers
+        // follow them.
+        let try_offset = offsets
+            .iter()
+            .find(|&&(_, op)| op == Opcode::Try)
+            .unwrap()
+            .0;
+        let handler = |note: &TryNote| {
+            let handler = note.start + note.length;
+            let handler_offsets = offsets.iter().skip_while(|&&(offset, _)| offset < handler);
+            handler_offsets
+                .take(2)
+                .map(|&(_, op)| op)
+                .collect::<Vec<_>>()
+        };
+        let catch = &result.try_notes[0];
+        let finally = &result.try_notes[1];
+        assert_eq!(catch.kind, TryNoteKind::Catch);
+        assert_eq!(finally.kind, TryNoteKind::Finally);
+        assert_eq!(catch.start, try_offset + 5);
+        assert_eq!(finally.start, catch.start);
+        assert_eq!(handler(catch), [Opcode::JumpTarget, Opcode::Exception]);
+        assert_eq!(handler(finally), [Opcode::JumpTarget, Opcode::Finally]);
+
+        // `Try` points at the jump over the handlers, at the end of the try
+        // block.
+        assert_eq!(
+            result.bytecode[jump_target(&result.bytecode, try_offset)],
+            Opcode::Goto as u8
+        );
+
+        // Each gosub resumes after itself.
+        assert_eq!(result.resume_offsets.len(), 2);
+        for &resume_offset in &result.resume_offsets {
+            assert_eq!(result.bytecode[resume_offset], Opcode::JumpTarget as u8);
+            let gosub_offset = resume_offset - Opcode::Gosub.instruction_length();
+            assert_eq!(result.bytecode[gosub_offset], Opcode::Gosub as u8);
+        }
+    }
+
+    #[test]
+    fn opcode_counts() {
+        let alloc = &Bump::new();
+        let source = "function f(x) { return x + 1; }\nf(2 + 2);";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+
+        // Operands aren't counted as instructions, and the function's
+        // bytecode is counted with the script's.
+        let histogram = opcode_histogram(&result);
+        let counts: Vec<_> = histogram.iter().map(|(&name, &n)| (name, n)).collect();
+        assert_eq!(
+            counts,
+            [
+                ("Add", 2),
+                ("BindGName", 1),
+                ("Call", 1),
+                ("GImplicitThis", 1),
+                ("GetArg", 1),
+                ("GetGName", 1),
+                ("Int8", 3),
+                ("Lambda", 1),
+                ("Pop", 1),
+                ("RetRval", 3),
+                ("SetGName", 1),
+                ("SetRval", 2),
+            ]
+        );
+        assert_eq!(
+            histogram.values().sum::<usize>(),
+            instructions(&result.bytecode).len()
+                + instructions(&result.functions[0].body.bytecode).len()
+        );
+    }
+
+    #[test]
+    fn dis_offsets() {
+        assert_eq!(
+            dis(&bytecode("2 + 2")),
+            "00000 Int8 2\n\
+             00002 Int8 2\n\
+             00004 Add\n\
+             00005 SetRval\n\
+             00006 RetRval\n"
+        );
+    }
+
+    #[test]
+    fn dis_jump_labels() {
+        // The `IfEq` goes to the `else` branch, and the `Goto` after the
+        // `if` branch past it.
+        assert_eq!(
+            dis(&bytecode("if (a) b; else c;")),
+            "00000 GetGName 0 0 0 0\n\
+             00005 IfEq L0 (00026)\n\
+             00010 JumpTarget 2 0 0 0\n\
+             00015 GetGName 1 0 0 0\n\
+             00020 SetRval\n\
+             00021 Goto L1 (00037)\n\
+             L0:\n\
+             00026 JumpTarget 3 0 0 0\n\
+             00031 GetGName 2 0 0 0\n\
+             00036 SetRval\n\
+             L1:\n\
+             00037 JumpTarget 4 0 0 0\n\
+             00042 RetRval\n"
+        );
+
+        // A jump cut off by the end of the bytecode shows its bytes.
+        assert_eq!(dis(&[Opcode::Goto as u8, 5, 0]), "00000 Goto 5 0\n");
+    }
+
+    #[test]
+    fn source_map() {
+        let alloc = &Bump::new();
+        let source = "f();\n  x;\r\n\u{e9}; 1 + g(2)";
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let options = EmitOptions {
+            source_map: true,
+            source,
+            source_name: "a.js",
+            ..EmitOptions::default()
+        };
+        let result = emit_with_options(
+            &mut ast::types::Program::Script(parse_result.unbox()),
+            &options,
+        )
+        .expect("Should work!");
+        let mapping = |bytecode_offset, line, column| Mapping {
+            bytecode_offset,
+            line,
+            column,
+        };
+        // `x` is not mapped to its own offset: mappings are only made at
+        // statements and calls.
+        assert_eq!(
+            result.source_map.as_ref().unwrap().mappings,
+            vec![
+                mapping(0, 0, 0),
+                mapping(14, 1, 2),
+                mapping(20, 2, 0),
+                mapping(26, 2, 3),
+                mapping(40, 2, 7),
+            ]
+        );
+        assert_eq!(
+            source_map_json(&result).unwrap(),
+            r#"{"mappings":"AAAA,cACE,MACF,MAAG,cAAI","names":[],"sources":["a.js"],"version":3}"#
+        );
+
+        let parse_result = parse_script(alloc, "f()").expect("Failed to parse");
+        let result = emit(&mut ast::types::Program::Script(parse_result.unbox())).unwrap();
+        assert_eq!(source_map_json(&result), None);
+    }
+
+    #[test]
+    fn line_notes() {
+        let alloc = &Bump::new();
+        let source = "a = 1;\nf(a, 2);\nb;";
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let options = EmitOptions {
+            line_notes: true,
+            source,
+            ..EmitOptions::default()
+        };
+        let result = emit_with_options(
+            &mut ast::types::Program::Script(parse_result.unbox()),
+            &options,
+        )
+        .expect("Should work!");
+        assert_eq!(result.source_map, None);
+
+        // Each statement starts a line.
+        let line_notes = result.line_notes.as_ref().unwrap();
+        assert_eq!(
+            line_notes.iter().collect::<Vec<_>>(),
+            vec![(0, 0), (13, 1), (34, 2)]
+        );
+        let dis = dis_with_lines(&result.bytecode, line_notes);
+        assert_eq!(
+            dis.lines().collect::<Vec<_>>(),
+            [
+                "   1 00000 BindGName 0 0 0 0",
+                "     00005 Int8 1",
+                "     00007 SetGName 0 0 0 0",
+                "     00012 SetRval",
+                "   2 00013 GetGName 1 0 0 0",
+                "     00018 GImplicitThis 1 0 0 0",
+                "     00023 GetGName 0 0 0 0",
+                "     00028 Int8 2",
+                "     00030 Call 2 0",
+                "     00033 SetRval",
+                "   3 00034 GetGName 2 0 0 0",
+                "     00039 SetRval",
+                "     00040 RetRval",
+            ]
+        );
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+    }
+
+    fn fold(source: &str) -> String {
+        let alloc = &Bump::new();
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let mut program = ast::types::Program::Script(parse_result.unbox());
+        crate::optimize::fold_constants(&mut program);
+        ast::codegen::generate(&program)
+    }
+
+    #[test]
+    fn fold_constants() {
+        assert_eq!(fold("x = 2 * 60 * 60 * 1000;"), "x = 7200000;\n");
+        assert_eq!(fold("'a' + 'b' + true + null;"), "(\"abtruenull\");\n");
+        assert_eq!(fold("1 / 0; -1 / 0;"), "2e308;\n-2e308;\n");
+        assert_eq!(fold("0.1 + 0.2;"), "0.30000000000000004;\n");
+        assert_eq!(fold("0 / 0; 1 ** NaN;"), "NaN;\n1 ** NaN;\n");
+        assert_eq!(fold("1 ** (1 / 0);"), "NaN;\n");
+        assert_eq!(fold("x = -0; x = 0 * -1;"), "x = -0;\nx = -0;\n");
+        assert_eq!(
+            fold("~5; -1 >>> 0; 1 << 31; -8 >> 1;"),
+            "-6;\n4294967295;\n-2147483648;\n-4;\n"
+        );
+        assert_eq!(
+            fold("1 < 2; 'b' < 'a'; 0 / 0 >= 0;"),
+            "true;\nfalse;\nfalse;\n"
+        );
+        assert_eq!(
+            fold("1 == true; null == 0; 1 === 1.0; '1' == 1;"),
+            "true;\nfalse;\ntrue;\n\"1\" == 1;\n"
+        );
+        assert_eq!(
+            fold("typeof null; !''; void 0;"),
+            "(\"object\");\ntrue;\nvoid 0;\n"
+        );
+        assert_eq!(
+            fold("x = 1 ? a : b; x = 0 && f(); x = null ?? f();"),
+            "x = a;\nx = 0;\nx = f();\n"
+        );
+
+        // Operands that aren't constant, or conversions that aren't folded.
+        assert_eq!(fold("x = a * 2 * 3;"), "x = a * 2 * 3;\n");
+        assert_eq!(fold("x = 2 * 3 * a;"), "x = 6 * a;\n");
+        assert_eq!(fold("x = f() && 1;"), "x = f() && 1;\n");
+        assert_eq!(
+            fold("x = 'a' + 1; x = +'1'; x = 'a' in b;"),
+            "x = \"a\" + 1;\nx = +\"1\";\nx = \"a\" in b;\n"
+        );
+        assert_eq!(
+            fold("x = (function () {}, a); x = (a.b, c);"),
+            "x = a;\nx = (a.b, c);\n"
+        );
+        assert_eq!(
+            fold("function f() { return 1 + 1; }"),
+            "function f() {\n  return 2;\n}\n"
+        );
+
+        // Callees, tags and `delete` operands that would become references
+        // stay in a comma expression, so that `this` and direct `eval` don't
+        // change.
+        assert_eq!(
+            fold("(0, o.m)(); (0, eval)(x); (true && o.m)(); (null ?? o.m)();"),
+            "(0, o.m)();\n(0, eval)(x);\n(0, o.m)();\n(0, o.m)();\n"
+        );
+        assert_eq!(
+            fold("(1 ? o.m : f)(); (0 || o[k])``; delete (0, x); delete (0 && o.m);"),
+            "(0, o.m)();\n(0, o[k])``;\ndelete (0, x);\ndelete 0;\n"
+        );
+        assert_eq!(
+            fold("(function () {}, o.m)(); (a => a, eval)(x); (1, 2, o.m)``;"),
+            "(0, o.m)();\n(0, eval)(x);\n(0, o.m)``;\n"
+        );
+        assert_eq!(
+            fold("(0, f())(); (0, o.m)?.(); f((0, o.m));"),
+            "f()();\n(0, o.m)?.();\nf(o.m);\n"
+        );
+    }
+
+    fn eliminate(source: &str) -> String {
+        let alloc = &Bump::new();
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let mut program = ast::types::Program::Script(parse_result.unbox());
+        crate::optimize::eliminate_dead_code(&mut program);
+        ast::codegen::generate(&program)
+    }
+
+    #[test]
+    fn eliminate_dead_code() {
+        assert_eq!(
+            eliminate("function f() { return 1; g(); }"),
+            "function f() {\n  return 1;\n}\n"
+        );
+        assert_eq!(
+            eliminate("for (;;) { if (a) { continue; b; } c; break; d; }"),
+            "for (;;) {\n  if (a) {\n    continue;\n  }\n  c;\n  break;\n}\n"
+        );
+        assert_eq!(
+            eliminate("switch (a) { case 1: throw b; c; case 2: d; }"),
+            "switch (a) {\n  case 1:\n    throw b;\n  case 2:\n    d;\n}\n"
+        );
+        // The statements after a block that always jumps don't run either.
+        assert_eq!(
+            eliminate("function f() { { return; a; } b; }"),
+            "function f() {\n  {\n    return;\n  }\n}\n"
+        );
+
+        // Only jumps that always happen count.
+        let source =
+            "function f() {\n  if (a) return;\n  try {\n    return;\n  } finally {}\n  b;\n}\n";
+        assert_eq!(eliminate(source), source);
+
+        // Declarations are kept, and so are statements with `var`
+        // declarations in them, but not those in nested functions.
+        assert_eq!(
+            eliminate(
+                "function f() { return g; function g() {} var a = 1; if (b) { var c; } \
+                 const d = 1; class E {} if (b) { (function () { var e; }); } }"
+            ),
+            "function f() {\n  return g;\n  function g() {}\n  var a = 1;\n  if (b) {\n    var c;\n  }\n  const d = 1;\n  class E {}\n}\n"
+        );
+
+        // Statements that do nothing are removed from functions, but not
+        // from the directive prologue, or from scripts, whose value is the
+        // value of their last expression statement.
+        assert_eq!(
+            eliminate("function f() { 'use strict'; 1 + 2; a.b; { [1]; } } 1 + 2;"),
+            "function f() {\n  (\"use strict\");\n  a.b;\n  {}\n}\n1 + 2;\n"
+        );
+        assert_eq!(
+            eliminate("class C { static { 'a'; f(); } }"),
+            "class C {\n  static {\n    f();\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn dead_code_elimination_option() {
+        let alloc = &Bump::new();
+        let source = "function f(){ return 1; console.log(2); }";
+        let emit_body = |options: &EmitOptions| {
+            let parse_result = parse_script(alloc, source).expect("Failed to parse");
+            let mut program = ast::types::Program::Script(parse_result.unbox());
+            let result = emit_with_options(&mut program, options).expect("Should work!");
+            let body = &result.functions[0].body;
+            let ops: Vec<Opcode> = instructions(&body.bytecode)
+                .into_iter()
+                .map(|(_, op)| op)
+                .collect();
+            ops
+        };
+        let options = EmitOptions {
+            dead_code_elimination: true,
+            ..EmitOptions::default()
+        };
+        assert_eq!(
+            emit_body(&options),
+            [
+                Opcode::Int8,
+                Opcode::SetRval,
+                Opcode::RetRval,
+                Opcode::RetRval
+            ]
+        );
+        assert!(emit_body(&EmitOptions::default()).contains(&Opcode::CallProp));
+    }
+
+    fn bytecode_with_options(source: &str, options: &EmitOptions) -> Vec<u8> {
+        let alloc = &Bump::new();
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let mut program = ast::types::Program::Script(parse_result.unbox());
+        emit_with_options(&mut program, options)
+            .expect("Should work!")
+            .bytecode
+    }
+
+    #[test]
+    fn fold_constants_option() {
+        let options = EmitOptions {
+            fold_constants: true,
+            ..EmitOptions::default()
+        };
+        let bc = bytecode_with_options("-0 * 1", &options);
+        assert_eq!(bc[0], Opcode::Double as u8);
+        assert_eq!(bc[1..9], (-0.0f64).to_bits().to_le_bytes());
+    }
+
+    #[test]
+    fn strict_option() {
+        let options = EmitOptions {
+            strict: true,
+            ..EmitOptions::default()
+        };
+        let bc = bytecode_with_options("x = 1", &options);
+        assert_eq!(bc[7], Opcode::StrictSetGName as u8);
+        assert_eq!(bytecode("x = 1")[7], Opcode::SetGName as u8);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let alloc = &Bump::new();
+        let source = "x = 'hello';\nprint(x, 'world');";
+        let parse_result = parse_script(alloc, source).expect("Failed to parse");
+        let options = EmitOptions {
+            source_map: true,
+            source,
+            source_name: "a.js",
+            ..EmitOptions::default()
+        };
+        let mut program = ast::types::Program::Script(parse_result.unbox());
+        let result = emit_with_options(&mut program, &options).expect("Should work!");
+        assert!(!result.strings.is_empty());
+
+        let data = serialize(&result);
+        assert_eq!(deserialize(&data), Ok(result));
+
+        let mut program = ast::types::Program::Script(parse_script(alloc, "1").unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+
+        let mut program =
+            ast::types::Program::Script(parse_script(alloc, "0xFFn").unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+
+        let source = "try { throw 1; } catch (e) {} finally {}";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+        assert_eq!(result.try_notes.len(), 2);
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+
+        let source = "function* g(a, b) { yield function () {}; }";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+        assert_eq!(result.functions[0].body.functions.len(), 1);
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+    }
+
+    #[test]
+    fn generator() {
+        let alloc = &Bump::new();
+        let source = "function* g(x) { yield x; }";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+
+        // The declaration is defined with the compiled function.
+        let ops: Vec<Opcode> = instructions(&result.bytecode)
+            .into_iter()
+            .map(|(_, op)| op)
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                Opcode::BindGName,
+                Opcode::Lambda,
+                Opcode::SetGName,
+                Opcode::Pop,
+                Opcode::RetRval
+            ]
+        );
+
+        let function = &result.functions[0];
+        assert_eq!(function.name.as_deref(), Some("g"));
+        assert_eq!(function.num_params, 1);
+        assert_eq!(function.body.frame_slot_count(), 1);
+        assert_eq!(function.frame_slot_count, 1);
+        assert!(function.is_generator);
+        assert!(!function.is_constructor);
+
+        // The body starts with the initial yield, and each yield resumes at
+        // the `AfterYield` after it.
+        let body = &function.body;
+        let ops: Vec<Opcode> = instructions(&body.bytecode)
+            .into_iter()
+            .map(|(_, op)| op)
+            .collect();
+        assert_eq!(
+            ops[..8],
+            [
+                Opcode::Generator,
+                Opcode::SetLocal,
+                Opcode::Pop,
+                Opcode::GetLocal,
+                Opcode::InitialYield,
+                Opcode::AfterYield,
+                Opcode::CheckResumeKind,
+                Opcode::Pop,
+            ]
+        );
+        assert_eq!(ops.last(), Some(&Opcode::FinalYieldRval));
+        assert_eq!(body.resume_offsets.len(), 2);
+        for &resume_offset in &body.resume_offsets {
+            assert_eq!(body.bytecode[resume_offset], Opcode::AfterYield as u8);
+        }
+    }
+
+    #[test]
+    fn scopes() {
+        let alloc = &Bump::new();
+        let source = "function* f(a, [b]) { var c; { const d = 1; { var h; class e {} } } }";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+
+        let binding = |name: &str, kind, slot| BindingNote {
+            name: name.to_string(),
+            kind,
+            slot,
+        };
+        assert_eq!(
+            result.scopes,
+            [ScopeNote {
+                kind: ScopeKind::Script,
+                enclosing: None,
+                bindings: vec![binding("f", BindingKind::Function, BindingSlot::Global)],
+            }]
+        );
+        assert_eq!(result.frame_slot_count(), 0);
+
+        // The destructured parameter is a local, like the variables; the
+        // generator is in local 0.
+        let body = &result.functions[0].body;
+        assert_eq!(
+            body.scopes,
+            [
+                ScopeNote {
+                    kind: ScopeKind::Function,
+                    enclosing: None,
+                    bindings: vec![
+                        binding("a", BindingKind::Parameter, BindingSlot::Argument(0)),
+                        binding("b", BindingKind::Parameter, BindingSlot::Local(1)),
+                        binding(".generator", BindingKind::Internal, BindingSlot::Local(0)),
+                        binding("c", BindingKind::Var, BindingSlot::Local(2)),
+                        binding("h", BindingKind::Var, BindingSlot::Local(3)),
+                    ],
+                },
+                ScopeNote {
+                    kind: ScopeKind::Block,
+                    enclosing: Some(0),
+                    bindings: vec![binding("d", BindingKind::Const, BindingSlot::Local(4))],
+                },
+                ScopeNote {
+                    kind: ScopeKind::Block,
+                    enclosing: Some(1),
+                    bindings: vec![binding("e", BindingKind::Class, BindingSlot::Local(5))],
+                },
+            ]
+        );
+        assert_eq!(body.frame_slot_count(), 6);
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+    }
+
+    #[test]
+    fn captured_scopes() {
+        let alloc = &Bump::new();
+        let source = "function f(a, b) { var c; function g() { return a + c; } return g; }";
+        let mut program = ast::types::Program::Script(parse_script(alloc, source).unwrap().unbox());
+        let result = emit(&mut program).unwrap();
+
+        let binding = |name: &str, kind, slot| BindingNote {
+            name: name.to_string(),
+            kind,
+            slot,
+        };
+        // `g` refers to `a` and `c`, so they're kept in the function's
+        // environment; `f` itself is a global.
+        let body = &result.functions[0].body;
+        assert_eq!(
+            body.scopes,
+            [ScopeNote {
+                kind: ScopeKind::Function,
+                enclosing: None,
+                bindings: vec![
+                    binding("a", BindingKind::Parameter, BindingSlot::Environment(0)),
+                    binding("b", BindingKind::Parameter, BindingSlot::Argument(1)),
+                    binding("c", BindingKind::Var, BindingSlot::Environment(1)),
+                    binding("g", BindingKind::Function, BindingSlot::Local(0)),
+                ],
+            }]
+        );
+        assert_eq!(body.scopes[0].environment_slot_count(), 2);
+        assert_eq!(body.frame_slot_count(), 1);
+        assert_eq!(deserialize(&serialize(&result)), Ok(result));
+    }
+
+    #[test]
+    fn big_int() {
+        let alloc = &Bump::new();
+        let parse_result = parse_script(alloc, "1n; 0xFF_FFn; 65535n").expect("Failed to parse");
+        let result = emit(&mut ast::types::Program::Script(parse_result.unbox())).unwrap();
+        assert_eq!(result.bigints, vec!["1", "65535"]);
+        assert_eq!(result.bytecode[..5], [Opcode::BigInt as u8, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let alloc = &Bump::new();
+        let mut program = ast::types::Program::Script(parse_script(alloc, "f(1)").unwrap().unbox());
+        let data = serialize(&emit(&mut program).unwrap());
+
+        assert_eq!(deserialize(b"\x7fELF"), Err(DecodeError::NotBytecode));
+        assert_eq!(deserialize(&data[..3]), Err(DecodeError::NotBytecode));
+
+        let mut other_version = data.clone();
+        other_version[4] += 1;
+        assert_eq!(
+            deserialize(&other_version),
+            Err(DecodeError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+
+        for len in 8..data.len() {
+            assert_eq!(deserialize(&data[..len]), Err(DecodeError::UnexpectedEnd));
+        }
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert_eq!(deserialize(&trailing), Err(DecodeError::TrailingData));
+    }
+
+    #[test]
+    fn top_level_await() {
+        // Modules aren't emitted, so top-level `await` only parses.
+        let alloc = &Bump::new();
+        let module = parse_module(alloc, "await 1;").expect("Failed to parse");
+        let mut program = ast::types::Program::Module(module.unbox());
+        assert!(matches!(
+            emit(&mut program),
+            Err(EmitError::NotImplemented(_))
+        ));
+    }
 }
@@ -54,7 +54,7 @@ macro_rules! using_opcode_database {
                 (ToString, to_string, NULL, 1, 1, 1, JOF_BYTE),
                 (GlobalThis, global_this, NULL, 1, 0, 1, JOF_BYTE),
                 (NewTarget, new_target, NULL, 1, 0, 1, JOF_BYTE),
-                (DynamicImport, dynamic_import, NULL, 1, 1, 1, JOF_BYTE),
+                (DynamicImport, dynamic_import, NULL, 1, 2, 1, JOF_BYTE),
                 (ImportMeta, import_meta, NULL, 1, 0, 1, JOF_BYTE),
                 (NewInit, new_init, NULL, 5, 0, 1, JOF_UINT32|JOF_IC),
                 (NewObject, new_object, NULL, 5, 0, 1, JOF_OBJECT|JOF_IC),
@@ -244,6 +244,18 @@ macro_rules! using_opcode_database {
                 (InstrumentationCallback, instrumentation_callback, NULL, 1, 0, 1, JOF_BYTE),
                 (InstrumentationScriptId, instrumentation_script_id, NULL, 1, 0, 1, JOF_BYTE),
                 (Debugger, debugger, NULL, 1, 0, 0, JOF_BYTE),
+                // Not SpiderMonkey's: a new private name, with the name as
+                // its description, which a class binds for each private
+                // name it declares, and the private fields keyed by the
+                // private name on top of the stack.
+                (NewPrivateName, new_private_name, NULL, 5, 0, 1, JOF_ATOM),
+                (InitPrivateField, init_private_field, NULL, 1, 3, 1, JOF_BYTE|JOF_PROPINIT),
+                (GetPrivateField, get_private_field, NULL, 1, 2, 1, JOF_BYTE),
+                (SetPrivateField, set_private_field, NULL, 1, 3, 1, JOF_BYTE|JOF_PROPSET),
+                // Not SpiderMonkey's, which self-hosts this: copy the own
+                // properties of an object to the target under it, except
+                // those the object on top has, for object rest and spread.
+                (CopyDataProperties, copy_data_properties, NULL, 1, 3, 1, JOF_BYTE),
             ]
         }
     }
@@ -473,6 +485,24 @@ macro_rules! define_table {
 using_opcode_database!(define_table!());
 
 impl Opcode {
+    /// The name of this opcode, as `dis` shows it.
+    pub fn name(self) -> &'static str {
+        macro_rules! select_name {
+            ( [ $(
+                (
+                    $op:ident , $op_snake:ident , $str:expr ,
+                    $length:expr , $nuses:expr , $ndefs:expr , $format:expr
+                ) ,
+            )* ] ) => {
+                match self {
+                    $( Opcode::$op => stringify!($op) , )*
+                }
+            }
+        }
+
+        using_opcode_database!(select_name!())
+    }
+
     /// Length of this instruction, in bytes.
     pub fn instruction_length(self) -> usize {
         macro_rules! select_length {
@@ -555,6 +585,12 @@ impl Opcode {
         }
     }
 
+    /// Whether the operand of this instruction is an `i32` jump offset,
+    /// relative to the start of the instruction.
+    pub fn is_jump(self) -> bool {
+        self.format_bits() & JOF_TYPEMASK == JOF_JUMP
+    }
+
     pub fn has_argc(self) -> bool {
         self.format_bits() & JOF_TYPEMASK == JOF_ARGC
     }
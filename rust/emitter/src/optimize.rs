@@ -0,0 +1,549 @@
+//! Optimization passes over the AST, run before emitting bytecode.
+
+use ast::analysis::is_pure;
+use ast::arena;
+use ast::source_location_accessor::SourceLocationAccessor;
+use ast::types::*;
+use ast::visit::{
+    walk_mut_block, walk_mut_class_element, walk_mut_expression, walk_mut_function_body,
+    walk_mut_script, walk_mut_switch_case, walk_mut_switch_default, Pass, Visitor,
+};
+use ast::SourceLocation;
+use bumpalo::Bump;
+use std::cmp::Ordering;
+use std::mem;
+
+/// Replace expressions whose value is known at compile time by that value,
+/// so that `2 * 60 * 60 * 1000` is emitted as a single number.
+///
+/// Only expressions whose operands are number, string, boolean or null
+/// literals are folded, or already folded constants, and only when computing
+/// them has no side effects and gives the same result as at run time. Other
+/// expressions, such as conversions from strings to numbers or from numbers to
+/// strings, are left to the interpreter. The exception is the comma operator,
+/// whose left operand is dropped if it's pure, as `is_pure` says, unless
+/// that would turn the callee in `(0, o.m)()` into a method call.
+pub fn fold_constants(program: &mut Program) {
+    // Folded strings need to live as long as the AST. Get the allocator it
+    // was parsed with from one of its arena vectors.
+    let allocator = match program {
+        Program::Script(script) => script.statements.bump(),
+        Program::Module(module) => module.items.bump(),
+    };
+    ConstantFolder { allocator }.visit_program(program);
+}
+
+struct ConstantFolder<'alloc> {
+    allocator: &'alloc Bump,
+}
+
+impl<'alloc> Pass<'alloc> for ConstantFolder<'alloc> {
+    fn visit_expression(&mut self, ast: &mut Expression<'alloc>) {
+        // Fold the operands first, so that `1 + 2 + 3` is folded to `3 + 3`
+        // and then `6`.
+        match ast {
+            Expression::UnaryExpression {
+                operator: UnaryOperator::Delete { .. },
+                operand,
+                ..
+            } => self.visit_reference(operand),
+            _ => walk_mut_expression(self, ast),
+        }
+        if let Some(folded) = self.fold(ast) {
+            *ast = folded;
+        }
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut CallExpression<'alloc>) {
+        if let ExpressionOrSuper::Expression(callee) = &mut ast.callee {
+            self.visit_reference(callee);
+        }
+        self.visit_arguments(&mut ast.arguments);
+    }
+
+    fn visit_template_expression(&mut self, ast: &mut TemplateExpression<'alloc>) {
+        if let Some(tag) = &mut ast.tag {
+            self.visit_reference(tag);
+        }
+        for element in &mut ast.elements {
+            self.visit_template_expression_element(element);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Constant<'alloc> {
+    Number(f64),
+    String(&'alloc str),
+    Boolean(bool),
+    Null,
+}
+
+impl<'alloc> Constant<'alloc> {
+    fn from_expression(ast: &Expression<'alloc>) -> Option<Self> {
+        match ast {
+            Expression::LiteralNumericExpression { value, .. } => Some(Constant::Number(*value)),
+            Expression::LiteralInfinityExpression { .. } => Some(Constant::Number(f64::INFINITY)),
+            Expression::LiteralStringExpression { value, .. } => Some(Constant::String(value)),
+            Expression::LiteralBooleanExpression { value, .. } => Some(Constant::Boolean(*value)),
+            Expression::LiteralNullExpression { .. } => Some(Constant::Null),
+            _ => None,
+        }
+    }
+
+    fn into_expression(self, loc: SourceLocation) -> Expression<'alloc> {
+        match self {
+            Constant::Number(value) => Expression::LiteralNumericExpression { value, loc },
+            Constant::String(value) => Expression::LiteralStringExpression { value, loc },
+            Constant::Boolean(value) => Expression::LiteralBooleanExpression { value, loc },
+            Constant::Null => Expression::LiteralNullExpression { loc },
+        }
+    }
+
+    /// ToNumber, or None for strings, whose conversion isn't folded.
+    fn to_number(self) -> Option<f64> {
+        match self {
+            Constant::Number(value) => Some(value),
+            Constant::String(_) => None,
+            Constant::Boolean(value) => Some(if value { 1.0 } else { 0.0 }),
+            Constant::Null => Some(0.0),
+        }
+    }
+
+    /// ToString, or None for numbers, whose conversion isn't folded.
+    fn to_str(self) -> Option<&'alloc str> {
+        match self {
+            Constant::Number(_) => None,
+            Constant::String(value) => Some(value),
+            Constant::Boolean(value) => Some(if value { "true" } else { "false" }),
+            Constant::Null => Some("null"),
+        }
+    }
+
+    /// ToBoolean.
+    fn is_truthy(self) -> bool {
+        match self {
+            Constant::Number(value) => !(value == 0.0 || value.is_nan()),
+            Constant::String(value) => !value.is_empty(),
+            Constant::Boolean(value) => value,
+            Constant::Null => false,
+        }
+    }
+
+    fn type_of(self) -> &'static str {
+        match self {
+            Constant::Number(_) => "number",
+            Constant::String(_) => "string",
+            Constant::Boolean(_) => "boolean",
+            Constant::Null => "object",
+        }
+    }
+}
+
+/// ToInt32.
+fn to_int32(value: f64) -> i32 {
+    to_uint32(value) as i32
+}
+
+/// ToUint32.
+fn to_uint32(value: f64) -> u32 {
+    if !value.is_finite() {
+        return 0;
+    }
+    const TWO_32: f64 = 4294967296.0;
+    let value = value.trunc() % TWO_32;
+    (if value < 0.0 { value + TWO_32 } else { value }) as u32
+}
+
+/// The `**` operator, which differs from `powf` when the base is 1 or -1.
+fn exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        return f64::NAN;
+    }
+    base.powf(exponent)
+}
+
+/// IsStrictlyEqual.
+fn strict_equals(left: Constant, right: Constant) -> bool {
+    match (left, right) {
+        (Constant::Number(left), Constant::Number(right)) => left == right,
+        (Constant::String(left), Constant::String(right)) => left == right,
+        (Constant::Boolean(left), Constant::Boolean(right)) => left == right,
+        (Constant::Null, Constant::Null) => true,
+        _ => false,
+    }
+}
+
+/// IsLooselyEqual, or None if it would convert a string to a number.
+fn loose_equals(left: Constant, right: Constant) -> Option<bool> {
+    match (left, right) {
+        (Constant::Null, Constant::Null) => Some(true),
+        // null is only loosely equal to null and undefined.
+        (Constant::Null, _) | (_, Constant::Null) => Some(false),
+        (Constant::String(_), Constant::String(_)) => Some(strict_equals(left, right)),
+        (Constant::String(_), _) | (_, Constant::String(_)) => None,
+        _ => Some(left.to_number()? == right.to_number()?),
+    }
+}
+
+/// Compare two values as the relational operators do, or None if that would
+/// convert a string to a number. Strings are compared by UTF-16 code units.
+/// The comparison is unordered if either side is NaN.
+fn compare(left: Constant, right: Constant) -> Option<Option<Ordering>> {
+    match (left, right) {
+        (Constant::String(left), Constant::String(right)) => {
+            Some(Some(left.encode_utf16().cmp(right.encode_utf16())))
+        }
+        _ => Some(left.to_number()?.partial_cmp(&right.to_number()?)),
+    }
+}
+
+impl<'alloc> ConstantFolder<'alloc> {
+    /// The folded form of `ast`, assuming its operands are already folded,
+    /// or None if it can't be folded.
+    fn fold(&self, ast: &mut Expression<'alloc>) -> Option<Expression<'alloc>> {
+        match ast {
+            Expression::UnaryExpression {
+                operator,
+                operand,
+                loc,
+            } => {
+                let operand = Constant::from_expression(operand)?;
+                let result = match operator {
+                    UnaryOperator::Plus { .. } => Constant::Number(operand.to_number()?),
+                    UnaryOperator::Minus { .. } => Constant::Number(-operand.to_number()?),
+                    UnaryOperator::LogicalNot { .. } => Constant::Boolean(!operand.is_truthy()),
+                    UnaryOperator::BitwiseNot { .. } => {
+                        Constant::Number(!to_int32(operand.to_number()?) as f64)
+                    }
+                    UnaryOperator::Typeof { .. } => Constant::String(operand.type_of()),
+                    // There is no literal for `undefined`, and deleting a
+                    // constant is pointless.
+                    UnaryOperator::Void { .. } | UnaryOperator::Delete { .. } => return None,
+                };
+                Some(result.into_expression(*loc))
+            }
+
+            Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                loc,
+            } => {
+                if let BinaryOperator::Comma { .. } = operator {
+                    if is_pure(left) {
+                        return Some(take_expression(right));
+                    }
+                }
+                let left_constant = Constant::from_expression(left)?;
+
+                // These only evaluate `right` depending on `left`, so only
+                // `left` needs to be constant.
+                let take_left = match operator {
+                    BinaryOperator::LogicalAnd { .. } => Some(!left_constant.is_truthy()),
+                    BinaryOperator::LogicalOr { .. } => Some(left_constant.is_truthy()),
+                    BinaryOperator::Coalesce { .. } => Some(left_constant != Constant::Null),
+                    _ => None,
+                };
+                if let Some(take_left) = take_left {
+                    return Some(if take_left {
+                        left_constant.into_expression(*loc)
+                    } else {
+                        take_expression(right)
+                    });
+                }
+
+                let right = Constant::from_expression(right)?;
+                let left = left_constant;
+                let number = |f: fn(f64, f64) -> f64| -> Option<Constant<'alloc>> {
+                    Some(Constant::Number(f(left.to_number()?, right.to_number()?)))
+                };
+                let int32 = |f: fn(i32, u32) -> i32| -> Option<Constant<'alloc>> {
+                    let left = to_int32(left.to_number()?);
+                    let right = to_uint32(right.to_number()?);
+                    Some(Constant::Number(f(left, right) as f64))
+                };
+                let ordering = |f: fn(Ordering) -> bool| -> Option<Constant<'alloc>> {
+                    Some(Constant::Boolean(compare(left, right)?.is_some_and(f)))
+                };
+                let result = match operator {
+                    BinaryOperator::Add { .. } => match (left, right) {
+                        (Constant::String(_), _) | (_, Constant::String(_)) => {
+                            let mut result = String::from(left.to_str()?);
+                            result.push_str(right.to_str()?);
+                            Constant::String(self.allocator.alloc_str(&result))
+                        }
+                        _ => number(|left, right| left + right)?,
+                    },
+                    BinaryOperator::Sub { .. } => number(|left, right| left - right)?,
+                    BinaryOperator::Mul { .. } => number(|left, right| left * right)?,
+                    BinaryOperator::Div { .. } => number(|left, right| left / right)?,
+                    BinaryOperator::Mod { .. } => number(|left, right| left % right)?,
+                    BinaryOperator::Pow { .. } => number(exponentiate)?,
+
+                    BinaryOperator::BitwiseAnd { .. } => int32(|left, right| left & right as i32)?,
+                    BinaryOperator::BitwiseOr { .. } => int32(|left, right| left | right as i32)?,
+                    BinaryOperator::BitwiseXor { .. } => int32(|left, right| left ^ right as i32)?,
+                    BinaryOperator::LeftShift { .. } => {
+                        int32(|left, right| left.wrapping_shl(right & 31))?
+                    }
+                    BinaryOperator::RightShift { .. } => int32(|left, right| left >> (right & 31))?,
+                    BinaryOperator::RightShiftExt { .. } => {
+                        let left = to_uint32(left.to_number()?);
+                        let right = to_uint32(right.to_number()?);
+                        Constant::Number((left >> (right & 31)) as f64)
+                    }
+
+                    BinaryOperator::StrictEquals { .. } => {
+                        Constant::Boolean(strict_equals(left, right))
+                    }
+                    BinaryOperator::StrictNotEquals { .. } => {
+                        Constant::Boolean(!strict_equals(left, right))
+                    }
+                    BinaryOperator::Equals { .. } => Constant::Boolean(loose_equals(left, right)?),
+                    BinaryOperator::NotEquals { .. } => {
+                        Constant::Boolean(!loose_equals(left, right)?)
+                    }
+                    BinaryOperator::LessThan { .. } => ordering(|o| o == Ordering::Less)?,
+                    BinaryOperator::LessThanOrEqual { .. } => ordering(|o| o != Ordering::Greater)?,
+                    BinaryOperator::GreaterThan { .. } => ordering(|o| o == Ordering::Greater)?,
+                    BinaryOperator::GreaterThanOrEqual { .. } => ordering(|o| o != Ordering::Less)?,
+
+                    // These throw a TypeError when `right` isn't an object.
+                    BinaryOperator::In { .. } | BinaryOperator::Instanceof { .. } => return None,
+
+                    BinaryOperator::LogicalAnd { .. }
+                    | BinaryOperator::LogicalOr { .. }
+                    | BinaryOperator::Coalesce { .. }
+                    | BinaryOperator::Comma { .. } => unreachable!(),
+                };
+                Some(result.into_expression(*loc))
+            }
+
+            Expression::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+                ..
+            } => {
+                if Constant::from_expression(test)?.is_truthy() {
+                    Some(take_expression(consequent))
+                } else {
+                    Some(take_expression(alternate))
+                }
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Fold a callee, a tag or the operand of `delete`, whose meaning changes
+    /// when it's a reference: `(0, o.m)()` calls `o.m` with `this` undefined,
+    /// `(0, eval)(x)` is an indirect eval and `delete (0, x)` doesn't delete
+    /// `x`. So when folding it gives a reference, it's kept in a comma
+    /// expression.
+    fn visit_reference(&mut self, ast: &mut Expression<'alloc>) {
+        walk_mut_expression(self, ast);
+        let loc = ast.get_loc();
+        if let Some(folded) = self.fold(ast) {
+            *ast = if is_reference(&folded) {
+                Expression::BinaryExpression {
+                    operator: BinaryOperator::Comma { loc },
+                    left: arena::alloc(self.allocator, Constant::Number(0.0).into_expression(loc)),
+                    right: arena::alloc(self.allocator, folded),
+                    loc,
+                }
+            } else {
+                folded
+            };
+        }
+    }
+}
+
+/// Whether `ast` is evaluated to a reference, rather than a value, when it's
+/// called or deleted.
+fn is_reference(ast: &Expression) -> bool {
+    matches!(
+        ast,
+        Expression::MemberExpression(_)
+            | Expression::OptionalChain { .. }
+            | Expression::IdentifierExpression(_)
+    )
+}
+
+/// Move an expression out of the AST, which is about to be replaced anyway.
+fn take_expression<'alloc>(ast: &mut Expression<'alloc>) -> Expression<'alloc> {
+    let placeholder = Expression::LiteralNullExpression {
+        loc: SourceLocation::new(0, 0),
+    };
+    mem::replace(ast, placeholder)
+}
+
+/// Remove the statements that can't run because they come after a `return`,
+/// `throw`, `break` or `continue` statement in the same block, such as the
+/// call in `return 1; f();`.
+///
+/// Statements after an `if` statement, a loop or a `try` statement are kept,
+/// even if all of its branches return. So are the declarations after the
+/// unconditional jump: a `var` or function declaration defines its name in
+/// the whole function, and functions called before the jump can see the
+/// names of `let`, `const` and class declarations.
+///
+/// In functions and class static blocks, expression statements whose
+/// expression is pure, as `is_pure` says, are removed too, apart from the
+/// directive prologue. Elsewhere they give the completion value of the
+/// script, which `eval` returns.
+pub fn eliminate_dead_code(program: &mut Program) {
+    DeadCodeEliminator { in_function: false }.visit_program(program);
+}
+
+struct DeadCodeEliminator {
+    /// Whether the statements being visited are in a function or a class
+    /// static block, where values of statements aren't used.
+    in_function: bool,
+}
+
+impl DeadCodeEliminator {
+    fn remove_dead_statements(&self, statements: &mut arena::Vec<Statement>) {
+        remove_dead_statements(statements);
+        if self.in_function {
+            remove_pure_statements(statements, false);
+        }
+    }
+
+    fn function_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        let in_function = mem::replace(&mut self.in_function, true);
+        f(self);
+        self.in_function = in_function;
+    }
+}
+
+impl<'alloc> Pass<'alloc> for DeadCodeEliminator {
+    fn visit_script(&mut self, ast: &mut Script<'alloc>) {
+        self.remove_dead_statements(&mut ast.statements);
+        walk_mut_script(self, ast);
+    }
+
+    fn visit_block(&mut self, ast: &mut Block<'alloc>) {
+        self.remove_dead_statements(&mut ast.statements);
+        walk_mut_block(self, ast);
+    }
+
+    fn visit_function_body(&mut self, ast: &mut FunctionBody<'alloc>) {
+        self.function_scope(|eliminator| {
+            remove_dead_statements(&mut ast.statements);
+            remove_pure_statements(&mut ast.statements, true);
+            walk_mut_function_body(eliminator, ast);
+        });
+    }
+
+    fn visit_switch_case(&mut self, ast: &mut SwitchCase<'alloc>) {
+        self.remove_dead_statements(&mut ast.consequent);
+        walk_mut_switch_case(self, ast);
+    }
+
+    fn visit_switch_default(&mut self, ast: &mut SwitchDefault<'alloc>) {
+        self.remove_dead_statements(&mut ast.consequent);
+        walk_mut_switch_default(self, ast);
+    }
+
+    fn visit_class_element(&mut self, ast: &mut ClassElement<'alloc>) {
+        if let ClassElement::StaticBlock { statements, .. } = ast {
+            self.function_scope(|eliminator| {
+                eliminator.remove_dead_statements(statements);
+                for statement in statements.iter_mut() {
+                    eliminator.visit_statement(statement);
+                }
+            });
+        } else {
+            walk_mut_class_element(self, ast);
+        }
+    }
+}
+
+fn remove_dead_statements(statements: &mut arena::Vec<Statement>) {
+    let end = match statements.iter().position(is_unconditional_jump) {
+        Some(index) => index + 1,
+        None => return,
+    };
+    let mut index = 0;
+    statements.retain(|statement| {
+        index += 1;
+        index <= end || declares_names(statement)
+    });
+}
+
+/// Remove the expression statements that do nothing, such as `1 + 2;`. If
+/// the statements are a function body, the string literals at the start
+/// are kept, as they may be directives.
+fn remove_pure_statements(statements: &mut arena::Vec<Statement>, has_prologue: bool) {
+    let mut in_prologue = has_prologue;
+    statements.retain(|statement| {
+        let expression = match statement {
+            Statement::ExpressionStatement(expression) => expression,
+            _ => {
+                in_prologue = false;
+                return true;
+            }
+        };
+        if in_prologue {
+            if let Expression::LiteralStringExpression { .. } = **expression {
+                return true;
+            }
+            in_prologue = false;
+        }
+        !is_pure(expression)
+    });
+}
+
+/// True if the statements after `ast` never run. A block is a jump if a
+/// statement in it is: the statements after that one in the block don't run
+/// either, so whatever it jumps to is after the block.
+fn is_unconditional_jump(ast: &Statement) -> bool {
+    match ast {
+        Statement::ReturnStatement { .. }
+        | Statement::ThrowStatement { .. }
+        | Statement::BreakStatement { .. }
+        | Statement::ContinueStatement { .. } => true,
+        Statement::BlockStatement { block, .. } => {
+            block.statements.iter().any(is_unconditional_jump)
+        }
+        _ => false,
+    }
+}
+
+/// True if `ast` declares names that code outside it can see, even without
+/// running it.
+fn declares_names(ast: &Statement) -> bool {
+    match ast {
+        Statement::VariableDeclarationStatement(_)
+        | Statement::FunctionDeclaration(_)
+        | Statement::ClassDeclaration(_) => true,
+        _ => {
+            let mut finder = VarFinder { found: false };
+            finder.visit_statement(ast);
+            finder.found
+        }
+    }
+}
+
+/// Looks for `var` declarations, which are in the scope of the function
+/// around them, not of the block they're in.
+struct VarFinder {
+    found: bool,
+}
+
+impl<'alloc> Visitor<'alloc> for VarFinder {
+    fn visit_variable_declaration(&mut self, ast: &VariableDeclaration<'alloc>) {
+        if let VariableDeclarationKind::Var { .. } = ast.kind {
+            self.found = true;
+        }
+    }
+
+    // The declarations in a function are in its own scope.
+    fn visit_function(&mut self, _ast: &Function<'alloc>) {}
+
+    fn visit_arrow_expression_body(&mut self, _ast: &ArrowExpressionBody<'alloc>) {}
+
+    fn visit_class_element(&mut self, _ast: &ClassElement<'alloc>) {}
+}
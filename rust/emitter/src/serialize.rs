@@ -0,0 +1,386 @@
+//! Saving compiled bytecode, to run it later without parsing the source again.
+//!
+//! The format is a header, the magic bytes `JSBC` followed by the format
+//! version, then every field of `EmitResult` in order. Integers are
+//! little-endian u32s, and strings and lists are prefixed with their length.
+//! Each function's body is an `EmitResult` in the same format, without the
+//! header.
+
+use crate::emitter::{
+    BindingKind, BindingNote, BindingSlot, EmitResult, FunctionCode, ScopeKind, ScopeNote, TryNote,
+    TryNoteKind,
+};
+use crate::source_map::{LineNotes, Mapping, SourceMap};
+use byteorder::{ByteOrder, LittleEndian};
+use std::convert::TryInto;
+use std::fmt;
+use std::rc::Rc;
+
+const MAGIC: &[u8; 4] = b"JSBC";
+
+/// The version of the format. Increase it when changing the format or the
+/// meaning of the bytecode, such as the opcode numbers.
+pub const FORMAT_VERSION: u32 = 12;
+
+/// The error of deserializing bytecode.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// The data doesn't start with the magic bytes.
+    NotBytecode,
+    /// The data was written in another version of the format.
+    UnsupportedVersion(u32),
+    /// The data ends in the middle of a field.
+    UnexpectedEnd,
+    /// A string isn't valid UTF-8.
+    InvalidString,
+    /// A try note's kind isn't a `TryNoteKind`.
+    UnknownTryNoteKind(u32),
+    /// A scope note's kind isn't a `ScopeKind`, or one of its bindings has
+    /// a kind or slot that isn't a `BindingKind` or `BindingSlot`.
+    InvalidScopeNote,
+    /// There's more data after the last field.
+    TrailingData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NotBytecode => write!(f, "not a bytecode file"),
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported bytecode version {} (expected {})",
+                version, FORMAT_VERSION
+            ),
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of bytecode file"),
+            DecodeError::InvalidString => write!(f, "invalid UTF-8 string in bytecode file"),
+            DecodeError::UnknownTryNoteKind(kind) => {
+                write!(f, "unknown try note kind {} in bytecode file", kind)
+            }
+            DecodeError::InvalidScopeNote => write!(f, "invalid scope note in bytecode file"),
+            DecodeError::TrailingData => write!(f, "unexpected data at end of bytecode file"),
+        }
+    }
+}
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn write_u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Lengths and offsets can't be out of range for u32 for the same reason
+    // as in `InstructionWriter::into_emit_result`.
+    fn write_usize(&mut self, value: usize) {
+        self.write_u32(value.try_into().unwrap());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_usize(bytes.len());
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn write_emit_result(&mut self, emit_result: &EmitResult) {
+        self.write_bytes(&emit_result.bytecode);
+        self.write_usize(emit_result.strings.len());
+        for string in &emit_result.strings {
+            self.write_bytes(string.as_bytes());
+        }
+        self.write_usize(emit_result.bigints.len());
+        for bigint in &emit_result.bigints {
+            self.write_bytes(bigint.as_bytes());
+        }
+        self.write_u32(emit_result.maximum_stack_depth);
+        self.write_u32(emit_result.num_ic_entries);
+        self.write_u32(emit_result.num_type_sets);
+        self.write_usize(emit_result.try_notes.len());
+        for try_note in &emit_result.try_notes {
+            self.write_u32(try_note.kind as u32);
+            self.write_usize(try_note.stack_depth);
+            self.write_usize(try_note.start);
+            self.write_usize(try_note.length);
+        }
+        self.write_usize(emit_result.resume_offsets.len());
+        for &offset in &emit_result.resume_offsets {
+            self.write_usize(offset);
+        }
+        self.write_usize(emit_result.functions.len());
+        for function in &emit_result.functions {
+            match &function.name {
+                None => self.out.push(0),
+                Some(name) => {
+                    self.out.push(1);
+                    self.write_bytes(name.as_bytes());
+                }
+            }
+            self.write_u32(function.num_params);
+            self.write_u32(function.frame_slot_count);
+            self.out.push(function.is_generator as u8);
+            self.out.push(function.is_constructor as u8);
+            self.out.push(function.is_class_constructor as u8);
+            self.write_emit_result(&function.body);
+        }
+        self.write_usize(emit_result.scopes.len());
+        for scope in &emit_result.scopes {
+            self.write_u32(scope.kind as u32);
+            match scope.enclosing {
+                None => self.out.push(0),
+                Some(enclosing) => {
+                    self.out.push(1);
+                    self.write_usize(enclosing);
+                }
+            }
+            self.write_usize(scope.bindings.len());
+            for binding in &scope.bindings {
+                self.write_bytes(binding.name.as_bytes());
+                self.write_u32(binding.kind as u32);
+                match binding.slot {
+                    BindingSlot::Argument(index) => {
+                        self.out.push(0);
+                        self.write_u32(index.into());
+                    }
+                    BindingSlot::Local(index) => {
+                        self.out.push(1);
+                        self.write_u32(index);
+                    }
+                    BindingSlot::Global => self.out.push(2),
+                    BindingSlot::Environment(index) => {
+                        self.out.push(3);
+                        self.write_u32(index);
+                    }
+                }
+            }
+        }
+
+        match &emit_result.source_map {
+            None => self.out.push(0),
+            Some(source_map) => {
+                self.out.push(1);
+                self.write_bytes(source_map.source_name.as_bytes());
+                self.write_usize(source_map.mappings.len());
+                for mapping in &source_map.mappings {
+                    self.write_usize(mapping.bytecode_offset);
+                    self.write_usize(mapping.line);
+                    self.write_usize(mapping.column);
+                }
+            }
+        }
+
+        match &emit_result.line_notes {
+            None => self.out.push(0),
+            Some(line_notes) => {
+                self.out.push(1);
+                self.write_bytes(&line_notes.data);
+            }
+        }
+    }
+}
+
+/// Serialize an `EmitResult`. See `deserialize`.
+pub fn serialize(emit_result: &EmitResult) -> Vec<u8> {
+    let mut writer = Writer { out: Vec::new() };
+    writer.out.extend_from_slice(MAGIC);
+    writer.write_u32(FORMAT_VERSION);
+    writer.write_emit_result(emit_result);
+    writer.out
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (slice, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(LittleEndian::read_u32(self.read_slice(4)?))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, DecodeError> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.read_usize()?;
+        self.read_slice(len)
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidString)
+    }
+
+    // A count of items that are at least `min_item_size` bytes each. Checking
+    // it against the remaining data keeps a corrupt count from making us
+    // allocate a huge Vec.
+    fn read_count(&mut self, min_item_size: usize) -> Result<usize, DecodeError> {
+        let count = self.read_usize()?;
+        if count.saturating_mul(min_item_size) > self.data.len() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        Ok(count)
+    }
+
+    fn read_scope_note(&mut self) -> Result<ScopeNote, DecodeError> {
+        let kind = ScopeKind::from_u32(self.read_u32()?).ok_or(DecodeError::InvalidScopeNote)?;
+        let enclosing = match self.read_u8()? {
+            0 => None,
+            _ => Some(self.read_usize()?),
+        };
+        let num_bindings = self.read_count(9)?;
+        let mut bindings = Vec::with_capacity(num_bindings);
+        for _ in 0..num_bindings {
+            let name = self.read_string()?;
+            let kind =
+                BindingKind::from_u32(self.read_u32()?).ok_or(DecodeError::InvalidScopeNote)?;
+            let slot = match self.read_u8()? {
+                0 => BindingSlot::Argument(
+                    self.read_u32()?
+                        .try_into()
+                        .map_err(|_| DecodeError::InvalidScopeNote)?,
+                ),
+                1 => BindingSlot::Local(self.read_u32()?),
+                2 => BindingSlot::Global,
+                3 => BindingSlot::Environment(self.read_u32()?),
+                _ => return Err(DecodeError::InvalidScopeNote),
+            };
+            bindings.push(BindingNote { name, kind, slot });
+        }
+        Ok(ScopeNote {
+            kind,
+            enclosing,
+            bindings,
+        })
+    }
+
+    fn read_emit_result(&mut self) -> Result<EmitResult, DecodeError> {
+        let bytecode = self.read_bytes()?.to_vec();
+        let num_strings = self.read_count(4)?;
+        let mut strings = Vec::with_capacity(num_strings);
+        for _ in 0..num_strings {
+            strings.push(self.read_string()?);
+        }
+        let num_bigints = self.read_count(4)?;
+        let mut bigints = Vec::with_capacity(num_bigints);
+        for _ in 0..num_bigints {
+            bigints.push(self.read_string()?);
+        }
+        let maximum_stack_depth = self.read_u32()?;
+        let num_ic_entries = self.read_u32()?;
+        let num_type_sets = self.read_u32()?;
+        let num_try_notes = self.read_count(16)?;
+        let mut try_notes = Vec::with_capacity(num_try_notes);
+        for _ in 0..num_try_notes {
+            let kind = self.read_u32()?;
+            try_notes.push(TryNote {
+                kind: TryNoteKind::from_u32(kind).ok_or(DecodeError::UnknownTryNoteKind(kind))?,
+                stack_depth: self.read_usize()?,
+                start: self.read_usize()?,
+                length: self.read_usize()?,
+            });
+        }
+        let num_resume_offsets = self.read_count(4)?;
+        let mut resume_offsets = Vec::with_capacity(num_resume_offsets);
+        for _ in 0..num_resume_offsets {
+            resume_offsets.push(self.read_usize()?);
+        }
+        let num_functions = self.read_count(7)?;
+        let mut functions = Vec::with_capacity(num_functions);
+        for _ in 0..num_functions {
+            let name = match self.read_u8()? {
+                0 => None,
+                _ => Some(self.read_string()?),
+            };
+            functions.push(Rc::new(FunctionCode {
+                name,
+                num_params: self.read_u32()?,
+                frame_slot_count: self.read_u32()?,
+                is_generator: self.read_u8()? != 0,
+                is_constructor: self.read_u8()? != 0,
+                is_class_constructor: self.read_u8()? != 0,
+                body: self.read_emit_result()?,
+            }));
+        }
+        let num_scopes = self.read_count(9)?;
+        let mut scopes = Vec::with_capacity(num_scopes);
+        for _ in 0..num_scopes {
+            scopes.push(self.read_scope_note()?);
+        }
+
+        let source_map = match self.read_u8()? {
+            0 => None,
+            _ => {
+                let source_name = self.read_string()?;
+                let num_mappings = self.read_count(12)?;
+                let mut mappings = Vec::with_capacity(num_mappings);
+                for _ in 0..num_mappings {
+                    mappings.push(Mapping {
+                        bytecode_offset: self.read_usize()?,
+                        line: self.read_usize()?,
+                        column: self.read_usize()?,
+                    });
+                }
+                Some(SourceMap {
+                    source_name,
+                    mappings,
+                })
+            }
+        };
+
+        let line_notes = match self.read_u8()? {
+            0 => None,
+            _ => Some(LineNotes {
+                data: self.read_bytes()?.to_vec(),
+            }),
+        };
+
+        Ok(EmitResult {
+            bytecode,
+            strings,
+            bigints,
+            maximum_stack_depth,
+            num_ic_entries,
+            num_type_sets,
+            try_notes,
+            resume_offsets,
+            functions,
+            scopes,
+            source_map,
+            line_notes,
+        })
+    }
+}
+
+/// Deserialize an `EmitResult` written by `serialize`.
+///
+/// The bytecode itself is not checked: like bytecode just emitted, it's
+/// assumed to be valid.
+pub fn deserialize(data: &[u8]) -> Result<EmitResult, DecodeError> {
+    let mut reader = Reader { data };
+    if reader.read_slice(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(DecodeError::NotBytecode);
+    }
+    let version = reader.read_u32()?;
+    if version != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    let emit_result = reader.read_emit_result()?;
+    if !reader.data.is_empty() {
+        return Err(DecodeError::TrailingData);
+    }
+    Ok(emit_result)
+}
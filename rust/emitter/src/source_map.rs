@@ -0,0 +1,231 @@
+//! Mapping bytecode offsets back to source positions.
+
+use crate::emitter::EmitResult;
+
+/// A bytecode offset and the source position of the code it was emitted for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub bytecode_offset: usize,
+
+    /// 0-based line number in the source.
+    pub line: usize,
+
+    /// 0-based column, in UTF-16 code units as in JavaScript strings.
+    pub column: usize,
+}
+
+/// Where in the source each part of the bytecode comes from.
+///
+/// Positions are recorded at the start of each statement and at each call,
+/// so a mapping applies to the instructions from its `bytecode_offset` up to
+/// the next mapping's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The name of the source, such as its file name.
+    pub source_name: String,
+
+    /// Mappings in increasing bytecode offset order.
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// `positions` are `(bytecode offset, byte offset in source)` pairs, in
+    /// bytecode order.
+    pub(crate) fn new(source_name: &str, source: &str, positions: &[(usize, usize)]) -> Self {
+        let mut mappings = Vec::with_capacity(positions.len());
+        let mut sorted_offsets: Vec<usize> = positions.iter().map(|&(_, offset)| offset).collect();
+        sorted_offsets.sort_unstable();
+        sorted_offsets.dedup();
+
+        // Find the line and column of every offset in one pass.
+        let mut line_and_column = Vec::with_capacity(sorted_offsets.len());
+        let (mut line, mut column) = (0, 0);
+        let mut next = sorted_offsets.iter().peekable();
+        let mut chars = source.char_indices().peekable();
+        while let Some(&&offset) = next.peek() {
+            match chars.peek() {
+                Some(&(i, c)) if i < offset => {
+                    chars.next();
+                    match c {
+                        '\r' => {
+                            if let Some(&(_, '\n')) = chars.peek() {
+                                chars.next();
+                            }
+                            line += 1;
+                            column = 0;
+                        }
+                        '\n' | '\u{2028}' | '\u{2029}' => {
+                            line += 1;
+                            column = 0;
+                        }
+                        c => column += c.len_utf16(),
+                    }
+                }
+                _ => {
+                    line_and_column.push((offset, line, column));
+                    next.next();
+                }
+            }
+        }
+
+        for &(bytecode_offset, source_offset) in positions {
+            let index = line_and_column
+                .binary_search_by_key(&source_offset, |&(offset, _, _)| offset)
+                .unwrap();
+            let (_, line, column) = line_and_column[index];
+            mappings.push(Mapping {
+                bytecode_offset,
+                line,
+                column,
+            });
+        }
+
+        SourceMap {
+            source_name: source_name.to_string(),
+            mappings,
+        }
+    }
+
+    /// Serialize as Source Map Revision 3 JSON.
+    ///
+    /// The bytecode is treated as a single line of generated code whose
+    /// columns are bytecode offsets, as in WebAssembly source maps.
+    pub fn to_json(&self) -> String {
+        let mut mappings = String::new();
+        let mut previous = Mapping {
+            bytecode_offset: 0,
+            line: 0,
+            column: 0,
+        };
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            if i > 0 {
+                mappings.push(',');
+            }
+            write_vlq(
+                &mut mappings,
+                mapping.bytecode_offset,
+                previous.bytecode_offset,
+            );
+            // Index of the source in "sources", always the only one.
+            write_vlq(&mut mappings, 0, 0);
+            write_vlq(&mut mappings, mapping.line, previous.line);
+            write_vlq(&mut mappings, mapping.column, previous.column);
+            previous = *mapping;
+        }
+
+        serde_json::json!({
+            "version": 3,
+            "sources": [self.source_name],
+            "names": [],
+            "mappings": mappings,
+        })
+        .to_string()
+    }
+}
+
+/// The source line each part of the bytecode comes from, for people reading
+/// it; see `dis_with_lines`.
+///
+/// Like SpiderMonkey's source notes, each note is stored as the change in
+/// bytecode offset and in line from the note before it, or from offset 0 and
+/// line 0 for the first one, so most notes take two bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineNotes {
+    // Each note is the bytecode offset delta as an unsigned LEB128, followed
+    // by the line delta as a signed LEB128.
+    pub(crate) data: Vec<u8>,
+}
+
+impl LineNotes {
+    /// A note for each mapping of `source_map` that's on another line than
+    /// the one before it.
+    pub(crate) fn new(source_map: &SourceMap) -> Self {
+        let mut data = Vec::new();
+        let (mut bytecode_offset, mut line) = (0, 0);
+        for mapping in &source_map.mappings {
+            if mapping.line == line && !data.is_empty() {
+                continue;
+            }
+            write_leb128(
+                &mut data,
+                (mapping.bytecode_offset - bytecode_offset) as u64,
+            );
+            let delta = mapping.line as i64 - line as i64;
+            // Zigzag encoding: the sign is the least significant bit.
+            write_leb128(&mut data, ((delta << 1) ^ (delta >> 63)) as u64);
+            bytecode_offset = mapping.bytecode_offset;
+            line = mapping.line;
+        }
+        LineNotes { data }
+    }
+
+    /// The notes, as `(bytecode offset, 0-based line)` pairs in increasing
+    /// bytecode offset order. Each applies to the instructions from its
+    /// offset up to the next note's.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut data = self.data.iter().copied();
+        let (mut bytecode_offset, mut line) = (0usize, 0i64);
+        std::iter::from_fn(move || {
+            let offset_delta = read_leb128(&mut data)?;
+            let line_delta = read_leb128(&mut data)?;
+            bytecode_offset += offset_delta as usize;
+            line += (line_delta >> 1) as i64 ^ -((line_delta & 1) as i64);
+            Some((bytecode_offset, line as usize))
+        })
+    }
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+// None at the end of the data, or if it ends in the middle of a number.
+fn read_leb128(data: &mut impl Iterator<Item = u8>) -> Option<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data.next()?;
+        value |= ((byte & 0x7f) as u64).checked_shl(shift).unwrap_or(0);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Append `value - previous` to `out` as a Base64 VLQ.
+fn write_vlq(out: &mut String, value: usize, previous: usize) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    // The sign is the least significant bit.
+    let mut vlq = if value >= previous {
+        (value - previous) << 1
+    } else {
+        ((previous - value) << 1) | 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// The source map of `emit_result` as Source Map Revision 3 JSON, or None if
+/// it wasn't emitted with one. See `EmitOptions::source_map`.
+pub fn source_map_json(emit_result: &EmitResult) -> Option<String> {
+    emit_result.source_map.as_ref().map(SourceMap::to_json)
+}
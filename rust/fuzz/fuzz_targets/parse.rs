@@ -0,0 +1,18 @@
+//! Arbitrary text must always come back from `parse_script` as `Ok` or
+//! `Err`, never as a panic or a stack overflow.
+//!
+//! ```text
+//! cargo +nightly fuzz run parse
+//! ```
+
+#![no_main]
+
+use bumpalo::Bump;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        let allocator = &Bump::new();
+        let _ = parser::parse_script(allocator, source);
+    }
+});
@@ -0,0 +1,46 @@
+//! The sources in `parser/tests/verdicts.txt` must keep the verdicts they're
+//! listed with, `ok` or the kind of their error, with arbitrary text after
+//! them in a line comment, which can't change whether they parse.
+//!
+//! ```text
+//! cargo +nightly fuzz run verdicts
+//! ```
+
+#![no_main]
+
+use bumpalo::Bump;
+use libfuzzer_sys::fuzz_target;
+
+const VERDICTS: &str = include_str!("../../parser/tests/verdicts.txt");
+
+/// See `verdict` in `parser/tests/verdicts.rs`.
+fn verdict(source: &str) -> &'static str {
+    let allocator = &Bump::new();
+    match parser::parse_script(allocator, source) {
+        Ok(_) => "ok",
+        Err(err) => err.kind().to_str(),
+    }
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fuzz_target!(|data: &[u8]| {
+    let comment = match std::str::from_utf8(data) {
+        Ok(text) => text.replace(is_line_terminator, ""),
+        Err(_) => return,
+    };
+    for line in VERDICTS.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (expected, source) = line.split_once(' ').unwrap_or((line, ""));
+        // In these the comment would be part of the token that isn't closed.
+        if expected.starts_with("Unterminated") {
+            continue;
+        }
+        let source = format!("{}\n//{}", source, comment);
+        assert_eq!(verdict(&source), expected, "{:?}", source);
+    }
+});
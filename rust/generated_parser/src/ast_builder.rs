@@ -1,9 +1,22 @@
 use crate::declaration_kind::DeclarationKind;
 use crate::early_errors::*;
 use crate::error::{ParseError, Result};
-use crate::Token;
-use ast::{arena, source_location_accessor::SourceLocationAccessor, types::*, SourceLocation};
+use crate::parser_options::{EcmaVersion, ParserOptions};
+use crate::{TerminalId, Token};
+use ast::{
+    arena,
+    source_location_accessor::SourceLocationAccessor,
+    types::*,
+    visit::{
+        walk_expression, walk_mut_assignment_target_identifier, walk_mut_binding_identifier,
+        walk_mut_expression, walk_mut_formal_parameters, walk_mut_function_body,
+        walk_mut_statement, Pass, Visitor,
+    },
+    FunctionFlags, SourceLocation,
+};
 use bumpalo::{vec, Bump};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 
 // The kind of BindingIdentifier found while parsing.
 //
@@ -47,6 +60,56 @@ enum BindingKind {
 
     // BindingIdentifier is the name of ClassDeclaration.
     Class,
+
+    // BindingIdentifier is inside ImportDeclaration.
+    Import,
+}
+
+// How `await` is used at an offset noted in `AstBuilder::awaits`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum AwaitUse {
+    // An AwaitExpression.
+    Expression,
+
+    // The identifier `await`, not in any function that isn't async. It's
+    // reserved in async functions, class static blocks and modules.
+    Identifier,
+
+    // The identifier `await`, in a function that isn't async, where it's
+    // only reserved in modules.
+    IdentifierInFunction,
+}
+
+// Whether a jump noted in `AstBuilder::jumps` is a BreakStatement or a
+// ContinueStatement.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum JumpKind {
+    Break,
+    Continue,
+}
+
+// A jump noted in `AstBuilder::jumps`: its kind, its label and the offset of
+// the label, if any, and the offset of its keyword.
+type Jump<'alloc> = (JumpKind, Option<(&'alloc str, usize)>, usize);
+
+// What the function being reduced allows `await` to be.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum AwaitScope {
+    // An async function, method or arrow function, whose body can have
+    // AwaitExpressions, and in which `await` is reserved.
+    Async,
+
+    // Any other function or method, in which `await` is an identifier.
+    Function,
+
+    // A class static block, which can't have AwaitExpressions, and in which
+    // `await` is reserved.
+    StaticBlock,
+
+    // An arrow function that isn't async. Its body can't have
+    // AwaitExpressions, but `await` is still reserved in it if it's in an
+    // async function.
+    Arrow,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -57,6 +120,16 @@ struct BindingInfo<'alloc> {
     kind: BindingKind,
 }
 
+/// The bindings declared at the top level of a script, with their offsets
+/// in the source, as kept by `AstBuilder::keep_script_bindings`.
+///
+/// Parsing part of a script again needs the bindings of the rest of it, to
+/// detect Early Errors between them. See `AstBuilder::add_script_bindings`.
+#[derive(Debug, Default)]
+pub struct ScriptBindings<'alloc> {
+    bindings: Vec<BindingInfo<'alloc>>,
+}
+
 pub struct AstBuilder<'alloc> {
     pub allocator: &'alloc Bump,
 
@@ -82,13 +155,574 @@ pub struct AstBuilder<'alloc> {
     //        related methods should be removed and each declaration should be
     //        fed directly to EarlyErrorsContext.
     bindings: Vec<BindingInfo<'alloc>>,
+
+    // The names exported by ExportDeclarations, and the local bindings
+    // referred by `export { ... };`, with their offsets in the source.
+    //
+    // They're fed to ModuleEarlyErrorsContext when the parser reaches the
+    // end of the module.
+    exported_names: Vec<(&'alloc str, usize)>,
+    exported_bindings: Vec<(&'alloc str, usize)>,
+
+    // The labels of the LabelledStatements parsed so far in the current
+    // function, script or module, with their offsets in the source.
+    //
+    // Labels are noted when their LabelledStatement is reduced, after the
+    // statements in its body, so the labels after the offset of a label are
+    // those nested in its body. When the parser reaches the end of a
+    // function, the labels inside it are removed.
+    labels: Vec<(&'alloc str, usize)>,
+
+    // The BreakStatements and ContinueStatements parsed so far whose target
+    // hasn't been parsed yet.
+    //
+    // The statement a jump targets is reduced after it: an unlabelled jump
+    // targets the innermost loop around it, or `switch` for `break`, and a
+    // labelled one the LabelledStatement with its label. Each of them takes
+    // the jumps at/after its start that it's the target of. Any left at the
+    // start of a function when the parser reaches its end, or at the end of
+    // the script or module, have no target.
+    jumps: Vec<Jump<'alloc>>,
+
+    // The initializers of the CoverInitializedNames parsed so far, as in
+    // `{ a = 1 }`, with the offsets of their names.
+    //
+    // An ObjectProperty can't hold one, so it's kept here until its object
+    // literal is refined into a pattern, which takes it back. Any left when
+    // the parser reaches the end of the script or module were in object
+    // literals that stayed expressions.
+    cover_initializers: RefCell<Vec<(usize, arena::Box<'alloc, Expression<'alloc>>)>>,
+
+    // The uses of `await` parsed so far that haven't been checked yet, with
+    // their offsets in the source.
+    //
+    // Whether an AwaitExpression is allowed, and whether `await` is an
+    // identifier, depend on the function it's in, which is reduced after
+    // it. So they're noted here, and checked when the parser reaches the
+    // end of the function, or of the script or module. As with the labels,
+    // the ones at/after the start of a function are the ones in it.
+    awaits: RefCell<Vec<(usize, AwaitUse)>>,
+
+    // The offsets of the `new.target` expressions parsed so far that aren't
+    // known to be in a function yet.
+    //
+    // Arrow functions use the `new.target` of the code around them, so only
+    // other functions, class fields and static blocks remove the ones in
+    // them when they're reduced. Any left at the end of the script or module
+    // are errors.
+    new_targets: RefCell<Vec<usize>>,
+
+    // The offsets of the Arguments starting on a new line, as in `f\n(a)`.
+    // The `async` of an async arrow function can't be followed by one, as
+    // in `async\n(a) => a`.
+    new_line_arguments: RefCell<Vec<usize>>,
+
+    // The offset of the first `import.meta` expression parsed so far, which
+    // is an error unless the code is a module.
+    first_import_meta: Cell<Option<usize>>,
+
+    // The private names used in member expressions parsed so far, as in
+    // `this.#x`, that aren't known to be declared yet, with their offsets.
+    //
+    // The class that declares a name is reduced after the uses in its body,
+    // so each class removes the ones in its body it declares. Any left at
+    // the end of the script or module aren't declared by any class around
+    // them.
+    private_references: RefCell<Vec<(&'alloc str, usize)>>,
+
+    // The offsets of the string literals parsed so far in parentheses, which
+    // can't be directives.
+    //
+    // The parentheses aren't in the AST, so `("use strict");` would look like
+    // a "use strict" directive. When the parser reaches the end of a
+    // function or script, the ones in it are removed.
+    parenthesized_strings: Vec<usize>,
+
+    // The offsets of the "use strict" directives of the function bodies
+    // parsed so far, whose functions haven't been reduced yet.
+    //
+    // The parameters and name of a function come before the directive that
+    // makes them strict mode code, so the function checks them once it's
+    // reduced, and removes the directive of its body.
+    use_strict_directives: Vec<usize>,
+
+    // The offsets of the expressions parsed so far in parentheses.
+    //
+    // `((a)) => a` and `((a, b)) => a` aren't arrow functions, but the
+    // parentheses around `(a)` aren't in the AST, so it looks like `(a)`
+    // when it's refined into parameters, which are checked against these.
+    // They're removed when the parser reaches the end of the script or
+    // module.
+    parenthesized_expressions: HashSet<usize>,
+
+    // The offsets of the spread elements and properties parsed so far that
+    // are followed by the trailing comma of their literal, as in `[...a,]`.
+    //
+    // The comma isn't in the AST, and the literal is fine as an expression,
+    // but can't be refined into a pattern, whose rest element must be last.
+    // They're removed when the parser reaches the end of the script or
+    // module.
+    spreads_before_trailing_comma: RefCell<Vec<usize>>,
+
+    // The offsets of the legacy octal literals and escape sequences shifted
+    // so far, with the end offset and the terminal of the token they're in.
+    //
+    // The lexer only knows whether strict mode is forced, so in other code
+    // they're allowed, and checked here when code turns out to be strict:
+    // at the end of a function or script with a "use strict" directive, of
+    // a class, or of a module. They're removed when the parser reaches the
+    // end of the script or module.
+    legacy_octals: Vec<(usize, usize, TerminalId)>,
+
+    // A copy of the bindings at the top level of the script, made when the
+    // parser reaches its end, if `keep_script_bindings` was called.
+    script_bindings: Option<Vec<BindingInfo<'alloc>>>,
+
+    options: ParserOptions,
+}
+
+/// The digits of a NumericLiteral token, without numeric separators or the
+/// radix prefix, and the radix.
+fn numeric_literal_digits(s: &str) -> (String, u32) {
+    let digits: String = s.chars().filter(|&c| c != '_').collect();
+    let prefix = digits.get(..2).map(str::to_ascii_lowercase);
+    match prefix.as_deref() {
+        Some("0x") => (digits[2..].to_string(), 16),
+        Some("0o") => (digits[2..].to_string(), 8),
+        Some("0b") => (digits[2..].to_string(), 2),
+        // A LegacyOctalIntegerLiteral, unlike a NonOctalDecimalIntegerLiteral
+        // such as `089`.
+        _ if digits.len() > 1
+            && digits.starts_with('0')
+            && digits.chars().all(|c| c.is_digit(8)) =>
+        {
+            (digits[1..].to_string(), 8)
+        }
+        _ => (digits, 10),
+    }
+}
+
+/// Convert the digits of an integer in `radix` to decimal, without any limit
+/// on its size.
+fn to_decimal(digits: &str, radix: u32) -> String {
+    const LIMB: u64 = 1_000_000_000;
+
+    // Little-endian base 10^9 digits.
+    let mut limbs: Vec<u64> = std::vec![0];
+    for c in digits.chars() {
+        let mut carry = u64::from(c.to_digit(radix).unwrap());
+        for limb in limbs.iter_mut() {
+            let value = *limb * u64::from(radix) + carry;
+            *limb = value % LIMB;
+            carry = value / LIMB;
+        }
+        if carry > 0 {
+            limbs.push(carry);
+        }
+    }
+
+    let mut decimal = limbs.last().unwrap().to_string();
+    for limb in limbs.iter().rev().skip(1) {
+        decimal.push_str(&format!("{:09}", limb));
+    }
+    decimal
+}
+
+/// True if `body`, the LabelledItem of a LabelledStatement, is an
+/// IterationStatement, possibly with more labels, so `continue` can target
+/// the label.
+fn labels_iteration(body: &Statement) -> bool {
+    match body {
+        Statement::DoWhileStatement { .. }
+        | Statement::WhileStatement { .. }
+        | Statement::ForStatement { .. }
+        | Statement::ForInStatement { .. }
+        | Statement::ForOfStatement { .. } => true,
+        Statement::LabeledStatement { body, .. } => labels_iteration(body),
+        _ => false,
+    }
+}
+
+/// Makes the functions in code that turned out to be strict mode code strict
+/// too. Functions are reduced before the directive prologue or the class
+/// around them, so they don't know yet.
+///
+/// The first early error that the code has only as strict mode code is
+/// noted, as the AstBuilder finds those only when strict mode is forced,
+/// and takes other code to be sloppy mode code while it's reduced. These
+/// are a WithStatement, a `delete` of a name, a binding of or assignment to
+/// `eval` or `arguments`, a name that's reserved in strict mode code, such
+/// as `static`, two simple parameters with the same name, and a
+/// FunctionDeclaration as the body of an IfStatement.
+#[derive(Default)]
+struct MarkStrict<'alloc> {
+    error: Option<ParseError<'alloc>>,
+}
+
+impl<'alloc> MarkStrict<'alloc> {
+    fn note(&mut self, error: ParseError<'alloc>) {
+        self.error.get_or_insert(error);
+    }
+
+    fn check(&mut self) -> Result<'alloc, ()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+// The names that aren't reserved words, but can't be identifiers in strict
+// mode code.
+fn is_strict_mode_reserved_word(name: &str) -> bool {
+    matches!(
+        name,
+        "implements"
+            | "interface"
+            | "let"
+            | "package"
+            | "private"
+            | "protected"
+            | "public"
+            | "static"
+            | "yield"
+    )
+}
+
+impl<'alloc> Pass<'alloc> for MarkStrict<'alloc> {
+    fn visit_function_body(&mut self, ast: &mut FunctionBody<'alloc>) {
+        // The functions in a strict one were marked with it, and its code
+        // was checked.
+        if !ast.strict {
+            ast.strict = true;
+            walk_mut_function_body(self, ast);
+        }
+    }
+
+    fn visit_statement(&mut self, ast: &mut Statement<'alloc>) {
+        match ast {
+            Statement::WithStatement { loc, .. } => {
+                self.note(ParseError::WithInStrictMode(loc.start));
+            }
+            // The block that `make_block_stmt_from_function_decl` puts a
+            // FunctionDeclaration in has the same location, unlike one that
+            // was in the source, which has braces around it.
+            Statement::BlockStatement { block, loc } => match block.statements.first() {
+                Some(Statement::FunctionDeclaration(function)) if function.loc == *loc => {
+                    self.note(ParseError::FunctionDeclInSingleStatement);
+                }
+                _ => {}
+            },
+            Statement::LabeledStatement { label, body, .. } => {
+                if let Statement::FunctionDeclaration(_) = **body {
+                    self.note(ParseError::LabelledFunctionDeclInStrictMode(
+                        label.loc.start,
+                    ));
+                }
+            }
+            _ => {}
+        }
+        walk_mut_statement(self, ast);
+    }
+
+    fn visit_expression(&mut self, ast: &mut Expression<'alloc>) {
+        match ast {
+            Expression::UnaryExpression {
+                operator: UnaryOperator::Delete { loc },
+                operand,
+                ..
+            } => {
+                if let Expression::IdentifierExpression(_) = **operand {
+                    self.note(ParseError::DeleteOfIdentifierInStrictMode(loc.start));
+                }
+            }
+            // An arrow function with an expression body has no FunctionBody
+            // to mark.
+            Expression::ArrowExpression { strict, .. } => *strict = true,
+            _ => {}
+        }
+        walk_mut_expression(self, ast);
+    }
+
+    fn visit_binding_identifier(&mut self, ast: &mut BindingIdentifier<'alloc>) {
+        if ast.name.value == "eval" || ast.name.value == "arguments" {
+            self.note(ParseError::InvalidIdentifier(ast.name.value, ast.loc.start));
+        }
+        walk_mut_binding_identifier(self, ast);
+    }
+
+    fn visit_assignment_target_identifier(&mut self, ast: &mut AssignmentTargetIdentifier<'alloc>) {
+        if ast.name.value == "eval" || ast.name.value == "arguments" {
+            self.note(ParseError::InvalidAssignmentTarget);
+        }
+        walk_mut_assignment_target_identifier(self, ast);
+    }
+
+    fn visit_identifier(&mut self, ast: &mut Identifier<'alloc>) {
+        if is_strict_mode_reserved_word(ast.value) {
+            self.note(ParseError::InvalidIdentifier(ast.value, ast.loc.start));
+        }
+    }
+
+    fn visit_label(&mut self, ast: &mut Label<'alloc>) {
+        if is_strict_mode_reserved_word(ast.value) {
+            self.note(ParseError::InvalidIdentifier(ast.value, ast.loc.start));
+        }
+    }
+
+    fn visit_formal_parameters(&mut self, ast: &mut FormalParameters<'alloc>) {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-function-definitions-static-semantics-early-errors
+        //
+        // FormalParameters : FormalParameterList
+        //
+        // * If the source text matched by FormalParameters is strict mode
+        //   code, the Early Error rules for UniqueFormalParameters :
+        //   FormalParameters are applied.
+        //
+        // Only simple parameters can have the same name in sloppy mode code,
+        // so only they are compared.
+        let mut names: Vec<(&'alloc str, usize)> = Vec::new();
+        for item in ast.items.iter() {
+            if let Parameter::Binding(Binding::BindingIdentifier(binding)) = item {
+                let name = binding.name.value;
+                if let Some((_, first)) = names.iter().find(|(other, _)| *other == name) {
+                    self.note(ParseError::DuplicateBinding(
+                        name,
+                        DeclarationKind::FormalParameter,
+                        *first,
+                        DeclarationKind::FormalParameter,
+                        binding.loc.start,
+                    ));
+                }
+                names.push((name, binding.loc.start));
+            }
+        }
+        walk_mut_formal_parameters(self, ast);
+    }
+}
+
+/// Finds the first YieldExpression in the parameters of a generator, or of
+/// an arrow function in it, which are early errors.
+///
+/// The parser doesn't know yet whether it's in a generator when it reduces
+/// an arrow function, so the generators look at theirs. Arrow function
+/// bodies aren't searched, as they have no [Yield] parameter, and neither
+/// are other functions, which are checked when they're reduced if they're
+/// generators.
+#[derive(Default)]
+struct FindYield {
+    in_parameters: bool,
+    offset: Option<usize>,
+}
+
+impl FindYield {
+    fn check<'alloc>(&self) -> Result<'alloc, ()> {
+        match self.offset {
+            Some(offset) => Err(ParseError::YieldInParameters(offset)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'alloc> Visitor<'alloc> for FindYield {
+    fn visit_expression(&mut self, ast: &Expression<'alloc>) {
+        match ast {
+            Expression::YieldExpression { loc, .. }
+            | Expression::YieldGeneratorExpression { loc, .. }
+                if self.in_parameters =>
+            {
+                self.offset.get_or_insert(loc.start);
+            }
+            Expression::ArrowExpression { params, .. } => {
+                let in_parameters = self.in_parameters;
+                self.in_parameters = true;
+                self.visit_formal_parameters(params);
+                self.in_parameters = in_parameters;
+            }
+            _ => walk_expression(self, ast),
+        }
+    }
+
+    fn visit_function(&mut self, _ast: &Function<'alloc>) {}
+
+    fn visit_method(&mut self, ast: &Method<'alloc>) {
+        self.visit_property_name(&ast.name);
+    }
+
+    fn visit_getter(&mut self, ast: &Getter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+    }
+
+    fn visit_setter(&mut self, ast: &Setter<'alloc>) {
+        self.visit_property_name(&ast.property_name);
+    }
+}
+
+// Declare the names bound by a parameter, in source order.
+//
+// Static Semantics: BoundNames
+// https://tc39.es/ecma262/#sec-static-semantics-boundnames
+fn declare_bound_names_of_parameter<'alloc, T>(
+    context: &mut T,
+    parameter: &Parameter<'alloc>,
+) -> Result<'alloc, ()>
+where
+    T: ParameterEarlyErrorsContext<'alloc>,
+{
+    match parameter {
+        Parameter::Binding(binding) => declare_bound_names_of_binding(context, binding),
+        Parameter::BindingWithDefault(BindingWithDefault { binding, .. }) => {
+            declare_bound_names_of_binding(context, binding)
+        }
+    }
+}
+
+fn declare_bound_names_of_binding<'alloc, T>(
+    context: &mut T,
+    binding: &Binding<'alloc>,
+) -> Result<'alloc, ()>
+where
+    T: ParameterEarlyErrorsContext<'alloc>,
+{
+    match binding {
+        Binding::BindingIdentifier(BindingIdentifier { name, loc }) => {
+            context.declare(name.value, loc.start)?;
+        }
+        Binding::BindingPattern(BindingPattern::ArrayBinding(ArrayBinding {
+            elements,
+            rest,
+            ..
+        })) => {
+            for element in elements.iter().flatten() {
+                declare_bound_names_of_parameter(context, element)?;
+            }
+            if let Some(rest) = rest {
+                declare_bound_names_of_binding(context, rest)?;
+            }
+        }
+        Binding::BindingPattern(BindingPattern::ObjectBinding(ObjectBinding {
+            properties,
+            rest,
+            ..
+        })) => {
+            for property in properties.iter() {
+                match property {
+                    BindingProperty::BindingPropertyIdentifier(BindingPropertyIdentifier {
+                        binding: BindingIdentifier { name, loc },
+                        ..
+                    }) => context.declare(name.value, loc.start)?,
+                    BindingProperty::BindingPropertyProperty(BindingPropertyProperty {
+                        binding,
+                        ..
+                    }) => declare_bound_names_of_parameter(context, binding)?,
+                }
+            }
+            if let Some(rest) = rest {
+                context.declare(rest.name.value, rest.loc.start)?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 impl<'alloc> AstBuilder<'alloc> {
     pub fn new(allocator: &'alloc Bump) -> Self {
+        Self::with_options(allocator, ParserOptions::default())
+    }
+
+    pub fn with_options(allocator: &'alloc Bump, options: ParserOptions) -> Self {
         Self {
             allocator,
             bindings: Vec::new(),
+            exported_names: Vec::new(),
+            exported_bindings: Vec::new(),
+            labels: Vec::new(),
+            jumps: Vec::new(),
+            cover_initializers: RefCell::new(Vec::new()),
+            awaits: RefCell::new(Vec::new()),
+            new_targets: RefCell::new(Vec::new()),
+            new_line_arguments: RefCell::new(Vec::new()),
+            first_import_meta: Cell::new(None),
+            private_references: RefCell::new(Vec::new()),
+            parenthesized_strings: Vec::new(),
+            use_strict_directives: Vec::new(),
+            parenthesized_expressions: HashSet::new(),
+            spreads_before_trailing_comma: RefCell::new(Vec::new()),
+            legacy_octals: Vec::new(),
+            script_bindings: None,
+            options,
+        }
+    }
+
+    pub fn options(&self) -> ParserOptions {
+        self.options
+    }
+
+    /// Keep the bindings declared at the top level of the script being
+    /// parsed, for `take_script_bindings`.
+    pub fn keep_script_bindings(&mut self) {
+        self.script_bindings.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the uses of private names parsed so far that no class around
+    /// them declares, with their offsets, for code parsed without the
+    /// classes around it, which may declare them.
+    pub fn take_undeclared_private_names(&mut self) -> Vec<(&'alloc str, usize)> {
+        std::mem::take(self.private_references.get_mut())
+    }
+
+    /// The bindings declared at the top level of the script parsed, if
+    /// `keep_script_bindings` was called.
+    pub fn take_script_bindings(&mut self) -> Option<ScriptBindings<'alloc>> {
+        self.script_bindings
+            .take()
+            .map(|bindings| ScriptBindings { bindings })
+    }
+
+    /// Note the bindings in `bindings` at offsets from `start` to `end`, as
+    /// if the top-level statements declaring them had just been parsed, after
+    /// being moved to `new_start` in the source.
+    pub fn add_script_bindings(
+        &mut self,
+        bindings: &ScriptBindings<'alloc>,
+        start: usize,
+        end: usize,
+        new_start: usize,
+    ) {
+        for info in &bindings.bindings {
+            if start <= info.offset && info.offset < end {
+                self.bindings.push(BindingInfo {
+                    offset: info.offset - start + new_start,
+                    ..*info
+                });
+            }
+        }
+    }
+
+    /// Note `token`, which the parser is shifting, if it's in legacy octal
+    /// syntax. See `Token::legacy_octal`.
+    pub fn note_legacy_octal(&mut self, token: &Token<'alloc>) {
+        if let Some(offset) = token.legacy_octal {
+            self.legacy_octals
+                .push((offset, token.loc.end, token.terminal_id));
+        }
+    }
+
+    // Check that there's no legacy octal syntax from `start` to `end`, which
+    // is strict mode code.
+    fn check_legacy_octals(&self, start: usize, end: usize) -> Result<'alloc, ()> {
+        match self
+            .legacy_octals
+            .iter()
+            .find(|(offset, _, _)| start <= *offset && *offset < end)
+        {
+            Some((offset, literal_end, TerminalId::NumericLiteral)) => {
+                Err(ParseError::OctalLiteralInStrictMode(*offset, *literal_end))
+            }
+            Some((offset, _, _)) => Err(ParseError::OctalEscapeInStrictMode(*offset)),
+            None => Ok(()),
         }
     }
 
@@ -159,6 +793,7 @@ impl<'alloc> AstBuilder<'alloc> {
         Ok(self.alloc(BindingIdentifier {
             name: Identifier {
                 value: "yield",
+                atom: token.atom,
                 loc,
             },
             loc,
@@ -175,6 +810,7 @@ impl<'alloc> AstBuilder<'alloc> {
         Ok(self.alloc(BindingIdentifier {
             name: Identifier {
                 value: "await",
+                atom: token.atom,
                 loc,
             },
             loc,
@@ -220,20 +856,22 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         token: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
-        let pattern = token.value.unwrap();
-        let global: bool = false;
-        let ignore_case: bool = false;
-        let multi_line: bool = false;
-        let sticky: bool = false;
-        let unicode: bool = false;
+        // The lexer has checked the flags, so they're letters from `dgimsuy`,
+        // after the last `/`.
+        let source = token.value.unwrap();
+        let body_end = source.rfind('/').unwrap();
+        let pattern = &source[1..body_end];
+        let flags = &source[body_end + 1..];
         let loc = token.loc;
         self.alloc(Expression::LiteralRegExpExpression {
             pattern,
-            global,
-            ignore_case,
-            multi_line,
-            sticky,
-            unicode,
+            global: flags.contains('g'),
+            ignore_case: flags.contains('i'),
+            multi_line: flags.contains('m'),
+            dot_all: flags.contains('s'),
+            sticky: flags.contains('y'),
+            unicode: flags.contains('u'),
+            has_indices: flags.contains('d'),
             loc,
         })
     }
@@ -242,24 +880,42 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn untagged_template_expr(
         &self,
         template_literal: arena::Box<'alloc, TemplateExpression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
-        self.alloc(Expression::TemplateExpression(template_literal.unbox()))
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        // Only tagged templates can have invalid escape sequences, whose
+        // cooked value is undefined.
+        for element in template_literal.elements.iter() {
+            if let TemplateExpressionElement::TemplateElement(TemplateElement {
+                cooked_value: None,
+                loc,
+                ..
+            }) = element
+            {
+                return Err(ParseError::InvalidEscapeSequence(loc.start));
+            }
+        }
+        Ok(self.alloc(Expression::TemplateExpression(template_literal.unbox())))
     }
 
     // PrimaryExpression : CoverParenthesizedExpressionAndArrowParameterList
     pub fn uncover_parenthesized_expression(
-        &self,
+        &mut self,
         parenthesized: arena::Box<'alloc, CoverParenthesized<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         match parenthesized.unbox() {
             CoverParenthesized::Expression { expression, .. } => {
+                if let Expression::LiteralStringExpression { loc, .. } = &*expression {
+                    self.parenthesized_strings.push(loc.start);
+                }
+                self.parenthesized_expressions
+                    .insert(expression.get_loc().start);
                 // TODO - does this need to rewalk the expression to look for
                 // invalid ObjectPattern or ArrayPattern syntax?
                 Ok(expression)
             }
-            CoverParenthesized::Parameters(_parameters) => Err(ParseError::NotImplemented(
-                "parenthesized expression with `...` should be a syntax error",
-            )),
+            // `(a,)`, `(...a)` or `()` without `=>` after it.
+            CoverParenthesized::Parameters(parameters) => {
+                Err(ParseError::ExpectedArrow(parameters.loc.end))
+            }
         }
     }
 
@@ -367,6 +1023,8 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         target: AssignmentTarget<'alloc>,
     ) -> Result<'alloc, Binding<'alloc>> {
+        // ([(a)] = dv) => {}
+        self.check_unparenthesized(target.get_loc().start)?;
         match target {
             // (a = dv) => {}
             AssignmentTarget::SimpleAssignmentTarget(
@@ -468,18 +1126,14 @@ impl<'alloc> AstBuilder<'alloc> {
 
             ObjectProperty::ShorthandProperty(ShorthandProperty {
                 name: IdentifierExpression { name, loc },
-                ..
-            }) => {
-                // TODO - CoverInitializedName can't be represented in an
-                // ObjectProperty, but we need it here.
-                Ok(BindingProperty::BindingPropertyIdentifier(
-                    BindingPropertyIdentifier {
-                        binding: BindingIdentifier { name, loc },
-                        init: None,
-                        loc,
-                    },
-                ))
-            }
+                loc: property_loc,
+            }) => Ok(BindingProperty::BindingPropertyIdentifier(
+                BindingPropertyIdentifier {
+                    binding: BindingIdentifier { name, loc },
+                    init: self.take_cover_initializer(loc.start),
+                    loc: property_loc,
+                },
+            )),
 
             ObjectProperty::SpreadProperty(_expression) => {
                 Err(ParseError::ObjectPatternWithNonFinalRest)
@@ -503,19 +1157,40 @@ impl<'alloc> AstBuilder<'alloc> {
         })
     }
 
+    /// Whether the spread element or property is followed by the trailing
+    /// comma of its literal; see `spreads_before_trailing_comma`.
+    fn is_spread_before_trailing_comma(&self, expression: &Expression<'alloc>) -> bool {
+        self.spreads_before_trailing_comma
+            .borrow()
+            .contains(&expression.get_loc().start)
+    }
+
+    /// Note the spread element or property that ends an array or object
+    /// literal with a trailing comma.
+    fn note_spread_before_trailing_comma(&self, expression: &Expression<'alloc>) {
+        self.spreads_before_trailing_comma
+            .borrow_mut()
+            .push(expression.get_loc().start);
+    }
+
     fn pop_trailing_spread_property(
         &self,
         properties: &mut arena::Vec<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>>,
-    ) -> Option<arena::Box<'alloc, Expression<'alloc>>> {
+    ) -> Result<'alloc, Option<arena::Box<'alloc, Expression<'alloc>>>> {
         // Check whether we want to pop a PropertyDefinition
         match properties.last().map(|boxed| &**boxed) {
-            Some(ObjectProperty::SpreadProperty(_)) => {}
-            _ => return None,
+            Some(ObjectProperty::SpreadProperty(expression)) => {
+                // ({...a,}) => {}
+                if self.is_spread_before_trailing_comma(expression) {
+                    return Err(ParseError::ObjectPatternWithNonFinalRest);
+                }
+            }
+            _ => return Ok(None),
         }
 
         // We do.
         match properties.pop().unwrap().unbox() {
-            ObjectProperty::SpreadProperty(expression) => Some(expression),
+            ObjectProperty::SpreadProperty(expression) => Ok(Some(expression)),
             _ => panic!("bug"), // can't happen: we just checked this above
         }
     }
@@ -527,7 +1202,7 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> Result<'alloc, ObjectBinding<'alloc>> {
         let mut properties = object.properties;
         let loc = object.loc;
-        let rest = self.pop_trailing_spread_property(&mut properties);
+        let rest = self.pop_trailing_spread_property(&mut properties)?;
         Ok(ObjectBinding {
             properties: self.collect_vec_from_results(
                 properties
@@ -558,31 +1233,45 @@ impl<'alloc> AstBuilder<'alloc> {
     fn pop_trailing_spread_element(
         &self,
         elements: &mut arena::Vec<'alloc, ArrayExpressionElement<'alloc>>,
-    ) -> Option<arena::Box<'alloc, Expression<'alloc>>> {
+    ) -> Result<'alloc, Option<arena::Box<'alloc, Expression<'alloc>>>> {
         // Check whether we want to pop an element.
         match elements.last() {
-            Some(ArrayExpressionElement::SpreadElement(_)) => {}
-            _ => return None,
+            Some(ArrayExpressionElement::SpreadElement(expression)) => {
+                // ([...a,]) => {}
+                if self.is_spread_before_trailing_comma(expression) {
+                    return Err(ParseError::ArrayPatternWithNonFinalRest);
+                }
+            }
+            _ => return Ok(None),
         }
 
         // We do.
         match elements.pop() {
-            Some(ArrayExpressionElement::SpreadElement(expression)) => Some(expression),
+            Some(ArrayExpressionElement::SpreadElement(expression)) => Ok(Some(expression)),
             _ => panic!("bug"), // can't happen: we just checked this above
         }
     }
 
+    /// Parameters can't be in parentheses of their own, as in `((a)) => a`.
+    fn check_unparenthesized(&self, offset: usize) -> Result<'alloc, ()> {
+        if self.parenthesized_expressions.contains(&offset) {
+            return Err(ParseError::InvalidParameter);
+        }
+        Ok(())
+    }
+
     fn expression_to_binding_no_default(
         &self,
         expression: Expression<'alloc>,
     ) -> Result<'alloc, Binding<'alloc>> {
+        self.check_unparenthesized(expression.get_loc().start)?;
         match expression {
             Expression::IdentifierExpression(IdentifierExpression { name, loc }) => {
                 Ok(Binding::BindingIdentifier(BindingIdentifier { name, loc }))
             }
 
             Expression::ArrayExpression(ArrayExpression { mut elements, loc }) => {
-                let rest = self.pop_trailing_spread_element(&mut elements);
+                let rest = self.pop_trailing_spread_element(&mut elements)?;
                 let elements = self.array_elements_to_parameters(elements)?;
                 let rest = rest
                     .map(|expr| match self.expression_to_parameter(expr.unbox())? {
@@ -735,12 +1424,26 @@ impl<'alloc> AstBuilder<'alloc> {
 
     fn numeric_literal_value(token: arena::Box<'alloc, Token<'alloc>>) -> Result<'alloc, f64> {
         let s = token.unbox().value.unwrap();
-
-        // BUG: Not all syntax is supported yet.
-        s.parse::<f64>()
+        let (digits, radix) = numeric_literal_digits(s);
+        let decimal = if radix == 10 {
+            digits
+        } else {
+            // Parsing the exact decimal value rounds correctly, even for
+            // integers that don't fit in an f64.
+            to_decimal(&digits, radix)
+        };
+        decimal
+            .parse::<f64>()
             .map_err(|_| ParseError::NotImplemented("Cannot parse numeric literal"))
     }
 
+    /// The value of a BigInt literal token, which doesn't include the `n`
+    /// suffix, as decimal digits.
+    fn bigint_literal_value(&self, token: arena::Box<'alloc, Token<'alloc>>) -> &'alloc str {
+        let (digits, radix) = numeric_literal_digits(token.unbox().value.unwrap());
+        self.alloc_str(&to_decimal(&digits, radix))
+    }
+
     // Literal : NumericLiteral
     pub fn numeric_literal(
         &self,
@@ -760,9 +1463,13 @@ impl<'alloc> AstBuilder<'alloc> {
     //   * NonDecimalIntegerLiteralBigIntLiteralSuffix
     pub fn bigint_literal(
         &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
+        token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
-        Err(ParseError::NotImplemented("BigInt"))
+        let loc = token.loc;
+        Ok(self.alloc(Expression::LiteralBigIntExpression {
+            value: self.bigint_literal_value(token),
+            loc,
+        }))
     }
 
     // Literal : StringLiteral
@@ -771,12 +1478,6 @@ impl<'alloc> AstBuilder<'alloc> {
         token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let loc = token.loc;
-        // Hack: Prevent emission for scripts with "use strict"
-        // directive.
-        if token.value == Some("use strict") {
-            return Err(ParseError::NotImplemented("use strict directive"));
-        }
-
         Ok(self.alloc(Expression::LiteralStringExpression {
             value: token.value.unwrap(),
             loc,
@@ -821,8 +1522,15 @@ impl<'alloc> AstBuilder<'alloc> {
         elision: Option<arena::Box<'alloc, ArrayExpression<'alloc>>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
-        if let Some(mut more) = elision {
-            self.append(&mut array.elements, &mut more.elements);
+        match elision {
+            Some(mut more) => self.append(&mut array.elements, &mut more.elements),
+            None => {
+                if let Some(ArrayExpressionElement::SpreadElement(expression)) =
+                    array.elements.last()
+                {
+                    self.note_spread_before_trailing_comma(expression);
+                }
+            }
         }
         array.loc.set_range(open_token.loc, close_token.loc);
         self.alloc(Expression::ArrayExpression(array.unbox()))
@@ -949,7 +1657,6 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // ObjectLiteral : `{` PropertyDefinitionList `}`
-    // ObjectLiteral : `{` PropertyDefinitionList `,` `}`
     pub fn object_literal(
         &self,
         open_token: arena::Box<'alloc, Token<'alloc>>,
@@ -960,6 +1667,21 @@ impl<'alloc> AstBuilder<'alloc> {
         self.alloc(Expression::ObjectExpression(object.unbox()))
     }
 
+    // ObjectLiteral : `{` PropertyDefinitionList `,` `}`
+    pub fn object_literal_with_trailing_comma(
+        &self,
+        open_token: arena::Box<'alloc, Token<'alloc>>,
+        object: arena::Box<'alloc, ObjectExpression<'alloc>>,
+        close_token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        if let Some(ObjectProperty::SpreadProperty(expression)) =
+            object.properties.last().map(|boxed| &**boxed)
+        {
+            self.note_spread_before_trailing_comma(expression);
+        }
+        self.object_literal(open_token, object, close_token)
+    }
+
     // PropertyDefinitionList : PropertyDefinition
     pub fn property_definition_list_single(
         &self,
@@ -1028,8 +1750,14 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn property_definition_spread(
         &self,
         spread: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> arena::Box<'alloc, ObjectProperty<'alloc>> {
-        self.alloc(ObjectProperty::SpreadProperty(spread))
+    ) -> Result<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>> {
+        // The `...` token isn't passed in, so report the operand's location.
+        self.options.check_target(
+            "object spread properties",
+            EcmaVersion::Es2018,
+            spread.get_loc().start,
+        )?;
+        Ok(self.alloc(ObjectProperty::SpreadProperty(spread)))
     }
 
     // LiteralPropertyName : IdentifierName
@@ -1077,9 +1805,17 @@ impl<'alloc> AstBuilder<'alloc> {
     //   * NonDecimalIntegerLiteralBigIntLiteralSuffix
     pub fn property_name_bigint(
         &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
+        token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, PropertyName<'alloc>>> {
-        Err(ParseError::NotImplemented("BigInt"))
+        let loc = token.loc;
+        // The property name is the BigInt converted to a string, as for
+        // `({ 0x10n: x })[16]`.
+        Ok(
+            self.alloc(PropertyName::StaticPropertyName(StaticPropertyName {
+                value: self.bigint_literal_value(token),
+                loc,
+            })),
+        )
     }
 
     // ComputedPropertyName : `[` AssignmentExpression `]`
@@ -1098,15 +1834,64 @@ impl<'alloc> AstBuilder<'alloc> {
     // CoverInitializedName : IdentifierReference Initializer
     pub fn cover_initialized_name(
         &self,
-        _name: arena::Box<'alloc, Identifier<'alloc>>,
-        _initializer: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>> {
-        // Awkward. This needs to be stored somehow until we reach an enclosing
-        // context where it can be reinterpreted as a default value in an
-        // object destructuring assignment pattern.
-        Err(ParseError::NotImplemented(
-            "default initializers in object patterns",
-        ))
+        name: arena::Box<'alloc, Identifier<'alloc>>,
+        initializer: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> arena::Box<'alloc, ObjectProperty<'alloc>> {
+        // This is only valid in an object literal that's refined into a
+        // pattern, where the initializer is a default value. Until then, the
+        // property is shorthand, and the initializer is kept aside.
+        let name_loc = name.loc;
+        let initializer_loc = initializer.get_loc();
+        self.cover_initializers
+            .borrow_mut()
+            .push((name_loc.start, initializer));
+        self.alloc(ObjectProperty::ShorthandProperty(ShorthandProperty {
+            name: IdentifierExpression {
+                name: name.unbox(),
+                loc: name_loc,
+            },
+            loc: SourceLocation::from_parts(name_loc, initializer_loc),
+        }))
+    }
+
+    /// Take back the initializer `cover_initialized_name` kept for the
+    /// shorthand property named at `offset`, if it had one.
+    fn take_cover_initializer(
+        &self,
+        offset: usize,
+    ) -> Option<arena::Box<'alloc, Expression<'alloc>>> {
+        let mut initializers = self.cover_initializers.borrow_mut();
+        let index = initializers.iter().rposition(|(o, _)| *o == offset)?;
+        Some(initializers.remove(index).1)
+    }
+
+    /// Early Error for a CoverInitializedName in an object literal that
+    /// wasn't refined into a pattern:
+    ///
+    /// > PropertyDefinition : CoverInitializedName
+    /// >
+    /// > - It is a Syntax Error if any source text is matched by this
+    /// >   production.
+    fn check_cover_initializers(&self) -> Result<'alloc, ()> {
+        match self.cover_initializers.borrow().first() {
+            Some((_, initializer)) => Err(ParseError::InitializerInObjectLiteral(
+                initializer.get_loc().start,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // The TemplateElement for a NoSubstitutionTemplate, TemplateHead,
+    // TemplateMiddle or TemplateTail token.
+    fn template_element(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> TemplateExpressionElement<'alloc> {
+        TemplateExpressionElement::TemplateElement(TemplateElement {
+            raw_value: token.raw.unwrap(),
+            cooked_value: token.value,
+            loc: token.loc,
+        })
     }
 
     // TemplateLiteral : NoSubstitutionTemplate
@@ -1117,12 +1902,7 @@ impl<'alloc> AstBuilder<'alloc> {
         let loc = token.loc;
         self.alloc(TemplateExpression {
             tag: None,
-            elements: self.new_vec_single(TemplateExpressionElement::TemplateElement(
-                TemplateElement {
-                    raw_value: token.value.unwrap(),
-                    loc,
-                },
-            )),
+            elements: self.new_vec_single(self.template_element(token)),
             loc,
         })
     }
@@ -1130,40 +1910,65 @@ impl<'alloc> AstBuilder<'alloc> {
     // SubstitutionTemplate : TemplateHead Expression TemplateSpans
     pub fn substitution_template(
         &self,
-        _head: arena::Box<'alloc, Token<'alloc>>,
-        _expression: arena::Box<'alloc, Expression<'alloc>>,
-        _spans: arena::Box<'alloc, Void>,
-    ) -> Result<'alloc, arena::Box<'alloc, TemplateExpression<'alloc>>> {
-        Err(ParseError::NotImplemented("template strings"))
+        head: arena::Box<'alloc, Token<'alloc>>,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+        mut spans: arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>>,
+    ) -> arena::Box<'alloc, TemplateExpression<'alloc>> {
+        let head_loc = head.loc;
+        let tail_loc = spans.last().unwrap().get_loc();
+        let mut elements = self.new_vec();
+        self.push(&mut elements, self.template_element(head));
+        self.push(
+            &mut elements,
+            TemplateExpressionElement::Expression(expression),
+        );
+        self.append(&mut elements, &mut spans);
+        self.alloc(TemplateExpression {
+            tag: None,
+            elements,
+            loc: SourceLocation::from_parts(head_loc, tail_loc),
+        })
     }
 
     // TemplateSpans : TemplateTail
     // TemplateSpans : TemplateMiddleList TemplateTail
     pub fn template_spans(
         &self,
-        _middle_list: Option<arena::Box<'alloc, Void>>,
-        _tail: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("template strings"))
+        middle_list: Option<
+            arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>>,
+        >,
+        tail: arena::Box<'alloc, Token<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>> {
+        let mut spans = middle_list.unwrap_or_else(|| self.alloc(self.new_vec()));
+        self.push(&mut spans, self.template_element(tail));
+        spans
     }
 
     // TemplateMiddleList : TemplateMiddle Expression
     pub fn template_middle_list_single(
         &self,
-        _middle: arena::Box<'alloc, Token<'alloc>>,
-        _expression: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("template strings"))
+        middle: arena::Box<'alloc, Token<'alloc>>,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>> {
+        let mut list = self.new_vec();
+        self.push(&mut list, self.template_element(middle));
+        self.push(&mut list, TemplateExpressionElement::Expression(expression));
+        self.alloc(list)
     }
 
     // TemplateMiddleList : TemplateMiddleList TemplateMiddle Expression
     pub fn template_middle_list_append(
         &self,
-        _middle_list: arena::Box<'alloc, Void>,
-        _middle: arena::Box<'alloc, Token<'alloc>>,
-        _expression: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("template strings"))
+        mut middle_list: arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>>,
+        middle: arena::Box<'alloc, Token<'alloc>>,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, TemplateExpressionElement<'alloc>>> {
+        self.push(&mut middle_list, self.template_element(middle));
+        self.push(
+            &mut middle_list,
+            TemplateExpressionElement::Expression(expression),
+        );
+        middle_list
     }
 
     // MemberExpression : MemberExpression `[` Expression `]`
@@ -1187,6 +1992,7 @@ impl<'alloc> AstBuilder<'alloc> {
     fn identifier(&self, token: arena::Box<'alloc, Token<'alloc>>) -> Identifier<'alloc> {
         Identifier {
             value: token.value.unwrap(),
+            atom: token.atom,
             loc: token.loc,
         }
     }
@@ -1194,6 +2000,7 @@ impl<'alloc> AstBuilder<'alloc> {
     fn identifier_name(&self, token: arena::Box<'alloc, Token<'alloc>>) -> IdentifierName<'alloc> {
         IdentifierName {
             value: token.value.unwrap(),
+            atom: token.atom,
             loc: token.loc,
         }
     }
@@ -1233,6 +2040,7 @@ impl<'alloc> AstBuilder<'alloc> {
         tag: arena::Box<'alloc, Expression<'alloc>>,
         mut template_literal: arena::Box<'alloc, TemplateExpression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        template_literal.loc = SourceLocation::from_parts(tag.get_loc(), template_literal.loc);
         template_literal.tag = Some(tag);
         self.alloc(Expression::TemplateExpression(template_literal.unbox()))
     }
@@ -1258,6 +2066,7 @@ impl<'alloc> AstBuilder<'alloc> {
         object: arena::Box<'alloc, Expression<'alloc>>,
         private_identifier: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.note_private_reference(&private_identifier);
         let object_loc = object.get_loc();
         let field_loc = private_identifier.loc;
         self.alloc(Expression::MemberExpression(
@@ -1269,6 +2078,107 @@ impl<'alloc> AstBuilder<'alloc> {
         ))
     }
 
+    // Unlike in the specification, an OptionalChain in our grammar includes
+    // the expression it starts from, so that every link is built with its
+    // object. The productions for tagged templates, which are early errors,
+    // are left out.
+    //
+    // OptionalExpression : OptionalChain
+    pub fn optional_chain(
+        &self,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        let loc = expression.get_loc();
+        self.alloc(Expression::OptionalChain { expression, loc })
+    }
+
+    // OptionalChain : OptionalChainBase `?.` Arguments
+    pub fn optional_call_expr(
+        &self,
+        callee: arena::Box<'alloc, Expression<'alloc>>,
+        optional_chain_token: arena::Box<'alloc, Token<'alloc>>,
+        arguments: arena::Box<'alloc, Arguments<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.check_optional_chain_target(&optional_chain_token)?;
+        let callee_loc = callee.get_loc();
+        let arguments_loc = arguments.loc;
+        Ok(self.alloc(Expression::OptionalCall(CallExpression {
+            callee: ExpressionOrSuper::Expression(callee),
+            arguments: arguments.unbox(),
+            loc: SourceLocation::from_parts(callee_loc, arguments_loc),
+        })))
+    }
+
+    // OptionalChain : OptionalChainBase `?.` `[` Expression `]`
+    pub fn optional_computed_member_expr(
+        &self,
+        object: arena::Box<'alloc, Expression<'alloc>>,
+        optional_chain_token: arena::Box<'alloc, Token<'alloc>>,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+        close_token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.check_optional_chain_target(&optional_chain_token)?;
+        let object_loc = object.get_loc();
+        Ok(self.alloc(Expression::OptionalMember(
+            MemberExpression::ComputedMemberExpression(ComputedMemberExpression {
+                object: ExpressionOrSuper::Expression(object),
+                expression,
+                loc: SourceLocation::from_parts(object_loc, close_token.loc),
+            }),
+        )))
+    }
+
+    // OptionalChain : OptionalChainBase `?.` IdentifierName
+    pub fn optional_static_member_expr(
+        &self,
+        object: arena::Box<'alloc, Expression<'alloc>>,
+        optional_chain_token: arena::Box<'alloc, Token<'alloc>>,
+        identifier_token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.check_optional_chain_target(&optional_chain_token)?;
+        let object_loc = object.get_loc();
+        let identifier_token_loc = identifier_token.loc;
+        Ok(self.alloc(Expression::OptionalMember(
+            MemberExpression::StaticMemberExpression(StaticMemberExpression {
+                object: ExpressionOrSuper::Expression(object),
+                property: self.identifier_name(identifier_token),
+                loc: SourceLocation::from_parts(object_loc, identifier_token_loc),
+            }),
+        )))
+    }
+
+    // OptionalChain : OptionalChainBase `?.` PrivateIdentifier
+    pub fn optional_private_field_expr(
+        &self,
+        object: arena::Box<'alloc, Expression<'alloc>>,
+        optional_chain_token: arena::Box<'alloc, Token<'alloc>>,
+        private_identifier: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.check_optional_chain_target(&optional_chain_token)?;
+        self.note_private_reference(&private_identifier);
+        let object_loc = object.get_loc();
+        let field_loc = private_identifier.loc;
+        Ok(self.alloc(Expression::OptionalMember(
+            MemberExpression::PrivateFieldExpression(PrivateFieldExpression {
+                object,
+                field: self.private_identifier(private_identifier),
+                loc: SourceLocation::from_parts(object_loc, field_loc),
+            }),
+        )))
+    }
+
+    fn note_private_reference(&self, private_identifier: &Token<'alloc>) {
+        self.private_references.borrow_mut().push((
+            private_identifier.value.unwrap(),
+            private_identifier.loc.start,
+        ));
+    }
+
+    fn check_optional_chain_target(&self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.options
+            .check_target("optional chaining", EcmaVersion::Es2020, token.loc.start)
+    }
+
     // SuperProperty : `super` `[` Expression `]`
     pub fn super_property_computed(
         &self,
@@ -1309,11 +2219,28 @@ impl<'alloc> AstBuilder<'alloc> {
         new_token: arena::Box<'alloc, Token<'alloc>>,
         target_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.new_targets.borrow_mut().push(new_token.loc.start);
         return self.alloc(Expression::NewTargetExpression {
             loc: SourceLocation::from_parts(new_token.loc, target_token.loc),
         });
     }
 
+    // ImportMeta : `import` `.` `meta`
+    pub fn import_meta_expr(
+        &self,
+        import_token: arena::Box<'alloc, Token<'alloc>>,
+        meta_token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.options
+            .check_target("import.meta", EcmaVersion::Es2020, import_token.loc.start)?;
+        if self.first_import_meta.get().is_none() {
+            self.first_import_meta.set(Some(import_token.loc.start));
+        }
+        Ok(self.alloc(Expression::ImportMetaExpression {
+            loc: SourceLocation::from_parts(import_token.loc, meta_token.loc),
+        }))
+    }
+
     // NewExpression : `new` NewExpression
     pub fn new_expr_without_arguments(
         &self,
@@ -1338,14 +2265,18 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         callee: arena::Box<'alloc, Expression<'alloc>>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let callee_loc = callee.get_loc();
+        // `import.meta` is an ordinary object, never a function.
+        if let Expression::ImportMetaExpression { .. } = *callee {
+            return Err(ParseError::ImportMetaCall(callee_loc.start));
+        }
         let arguments_loc = arguments.loc;
-        self.alloc(Expression::CallExpression(CallExpression {
+        Ok(self.alloc(Expression::CallExpression(CallExpression {
             callee: ExpressionOrSuper::Expression(callee),
             arguments: arguments.unbox(),
             loc: SourceLocation::from_parts(callee_loc, arguments_loc),
-        }))
+        })))
     }
 
     // SuperCall : `super` Arguments
@@ -1364,16 +2295,21 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // ImportCall : `import` `(` AssignmentExpression `)`
+    // ImportCall : `import` `(` AssignmentExpression `,` AssignmentExpression `)`
     pub fn import_call(
         &self,
         import_token: arena::Box<'alloc, Token<'alloc>>,
         argument: arena::Box<'alloc, Expression<'alloc>>,
+        options: Option<arena::Box<'alloc, Expression<'alloc>>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
-        self.alloc(Expression::ImportCallExpression {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.options
+            .check_target("import()", EcmaVersion::Es2020, import_token.loc.start)?;
+        Ok(self.alloc(Expression::ImportCallExpression {
             argument,
+            options,
             loc: SourceLocation::from_parts(import_token.loc, close_token.loc),
-        })
+        }))
     }
 
     // Arguments : `(` `)`
@@ -1382,6 +2318,11 @@ impl<'alloc> AstBuilder<'alloc> {
         open_token: arena::Box<'alloc, Token<'alloc>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Arguments<'alloc>> {
+        if open_token.is_on_new_line {
+            self.new_line_arguments
+                .borrow_mut()
+                .push(open_token.loc.start);
+        }
         self.alloc(Arguments {
             args: self.new_vec(),
             loc: SourceLocation::from_parts(open_token.loc, close_token.loc),
@@ -1418,6 +2359,11 @@ impl<'alloc> AstBuilder<'alloc> {
         mut arguments: arena::Box<'alloc, Arguments<'alloc>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> arena::Box<'alloc, Arguments<'alloc>> {
+        if open_token.is_on_new_line {
+            self.new_line_arguments
+                .borrow_mut()
+                .push(open_token.loc.start);
+        }
         arguments.loc.set_range(open_token.loc, close_token.loc);
         arguments
     }
@@ -1521,15 +2467,34 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         operator_token: arena::Box<'alloc, Token<'alloc>>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-delete-operator-static-semantics-early-errors
+        //
+        // UnaryExpression : `delete` UnaryExpression
+        //
+        // * It is a Syntax Error if the UnaryExpression is contained in
+        //   strict mode code and the derived UnaryExpression is
+        //   PrimaryExpression : IdentifierReference.
+        //
+        // The same goes for an IdentifierReference in parentheses, which
+        // aren't in the AST.
+        if self.is_strict() {
+            if let Expression::IdentifierExpression(_) = *operand {
+                return Err(ParseError::DeleteOfIdentifierInStrictMode(
+                    operator_token.loc.start,
+                ));
+            }
+        }
+
         let operand_loc = operand.get_loc();
-        self.alloc(Expression::UnaryExpression {
+        Ok(self.alloc(Expression::UnaryExpression {
             operator: UnaryOperator::Delete {
                 loc: operator_token.loc,
             },
             operand,
             loc: SourceLocation::from_parts(operator_token.loc, operand_loc),
-        })
+        }))
     }
 
     // UnaryExpression : `void` UnaryExpression
@@ -1688,14 +2653,24 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn mod_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
         BinaryOperator::Mod { loc: token.loc }
     }
-    pub fn pow_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
-        BinaryOperator::Pow { loc: token.loc }
+    pub fn pow_op(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, BinaryOperator> {
+        self.options
+            .check_target("the ** operator", EcmaVersion::Es2016, token.loc.start)?;
+        Ok(BinaryOperator::Pow { loc: token.loc })
     }
     pub fn comma_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
         BinaryOperator::Comma { loc: token.loc }
     }
-    pub fn coalesce_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
-        BinaryOperator::Coalesce { loc: token.loc }
+    pub fn coalesce_op(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, BinaryOperator> {
+        self.options
+            .check_target("the ?? operator", EcmaVersion::Es2020, token.loc.start)?;
+        Ok(BinaryOperator::Coalesce { loc: token.loc })
     }
     pub fn logical_or_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
         BinaryOperator::LogicalOr { loc: token.loc }
@@ -1790,12 +2765,12 @@ impl<'alloc> AstBuilder<'alloc> {
         mut elements: arena::Vec<'alloc, ArrayExpressionElement<'alloc>>,
         loc: SourceLocation,
     ) -> Result<'alloc, ArrayAssignmentTarget<'alloc>> {
-        let spread = self.pop_trailing_spread_element(&mut elements);
+        let spread = self.pop_trailing_spread_element(&mut elements)?;
         let elements =
             self.collect_vec_from_results(elements.into_iter().map(|element| match element {
-                ArrayExpressionElement::SpreadElement(_) => Err(ParseError::NotImplemented(
-                    "rest destructuring in array pattern",
-                )),
+                ArrayExpressionElement::SpreadElement(_) => {
+                    Err(ParseError::ArrayPatternWithNonFinalRest)
+                }
                 ArrayExpressionElement::Expression(expression) => Ok(Some(
                     self.expression_to_assignment_target_maybe_default(expression)?,
                 )),
@@ -1836,17 +2811,14 @@ impl<'alloc> AstBuilder<'alloc> {
 
             ObjectProperty::ShorthandProperty(ShorthandProperty {
                 name: IdentifierExpression { name, loc },
-                ..
-            }) => {
-                // TODO - support CoverInitializedName
-                AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
-                    AssignmentTargetPropertyIdentifier {
-                        binding: AssignmentTargetIdentifier { name, loc },
-                        init: None,
-                        loc,
-                    },
-                )
-            }
+                loc: property_loc,
+            }) => AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
+                AssignmentTargetPropertyIdentifier {
+                    binding: AssignmentTargetIdentifier { name, loc },
+                    init: self.take_cover_initializer(loc.start),
+                    loc: property_loc,
+                },
+            ),
 
             ObjectProperty::SpreadProperty(_expression) => {
                 return Err(ParseError::ObjectPatternWithNonFinalRest)
@@ -1860,7 +2832,7 @@ impl<'alloc> AstBuilder<'alloc> {
         mut properties: arena::Vec<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>>,
         loc: SourceLocation,
     ) -> Result<'alloc, ObjectAssignmentTarget<'alloc>> {
-        let spread = self.pop_trailing_spread_property(&mut properties);
+        let spread = self.pop_trailing_spread_property(&mut properties)?;
         let properties = self.collect_vec_from_results(
             properties
                 .into_iter()
@@ -1940,7 +2912,7 @@ impl<'alloc> AstBuilder<'alloc> {
                 //    code and StringValue of Identifier is "eval" or
                 //    "arguments", return invalid.
                 if name.value == "arguments" || name.value == "eval" {
-                    if self.is_strict()? {
+                    if self.is_strict() {
                         return Err(ParseError::InvalidAssignmentTarget);
                     }
                 }
@@ -1999,6 +2971,13 @@ impl<'alloc> AstBuilder<'alloc> {
                     },
                 ),
             ),
+            Expression::MemberExpression(MemberExpression::PrivateFieldExpression(
+                PrivateFieldExpression { object, field, loc },
+            )) => SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::PrivateFieldAssignmentTarget(
+                    PrivateFieldAssignmentTarget { object, field, loc },
+                ),
+            ),
 
             // Static Semantics: AssignmentTargetType
             // https://tc39.es/ecma262/#sec-static-semantics-static-semantics-assignmenttargettype
@@ -2069,8 +3048,10 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn pow_assign_op(
         &self,
         token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
-        CompoundAssignmentOperator::Pow { loc: token.loc }
+    ) -> Result<'alloc, CompoundAssignmentOperator> {
+        self.options
+            .check_target("the **= operator", EcmaVersion::Es2016, token.loc.start)?;
+        Ok(CompoundAssignmentOperator::Pow { loc: token.loc })
     }
     pub fn left_shift_assign_op(
         &self,
@@ -2108,6 +3089,30 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::And { loc: token.loc }
     }
+    pub fn logical_or_assign_op(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, CompoundAssignmentOperator> {
+        self.options
+            .check_target("logical assignment", EcmaVersion::Es2021, token.loc.start)?;
+        Ok(CompoundAssignmentOperator::LogicalOr { loc: token.loc })
+    }
+    pub fn logical_and_assign_op(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, CompoundAssignmentOperator> {
+        self.options
+            .check_target("logical assignment", EcmaVersion::Es2021, token.loc.start)?;
+        Ok(CompoundAssignmentOperator::LogicalAnd { loc: token.loc })
+    }
+    pub fn coalesce_assign_op(
+        &self,
+        token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, CompoundAssignmentOperator> {
+        self.options
+            .check_target("logical assignment", EcmaVersion::Es2021, token.loc.start)?;
+        Ok(CompoundAssignmentOperator::Coalesce { loc: token.loc })
+    }
 
     pub fn box_assign_op(
         &self,
@@ -2216,6 +3221,7 @@ impl<'alloc> AstBuilder<'alloc> {
         };
 
         self.mark_binding_kind(kind.get_loc().start, None, binding_kind);
+        self.check_let_not_bound(kind.get_loc().start, usize::MAX)?;
 
         // 13.3.1.1 Static Semantics: Early Errors
         if let VariableDeclarationKind::Const { .. } = *kind {
@@ -2254,6 +3260,7 @@ impl<'alloc> AstBuilder<'alloc> {
             _ => panic!("unexpected VariableDeclarationKind"),
         };
         self.mark_binding_kind(kind.get_loc().start, None, binding_kind);
+        self.check_let_not_bound(kind.get_loc().start, usize::MAX)?;
 
         // 13.3.1.1 Static Semantics: Early Errors
         if let VariableDeclarationKind::Const { .. } = *kind {
@@ -2370,14 +3377,23 @@ impl<'alloc> AstBuilder<'alloc> {
         properties: arena::Box<'alloc, arena::Vec<'alloc, BindingProperty<'alloc>>>,
         rest: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Binding<'alloc>> {
-        self.alloc(Binding::BindingPattern(BindingPattern::ObjectBinding(
-            ObjectBinding {
-                properties: properties.unbox(),
-                rest,
-                loc: SourceLocation::from_parts(open_token.loc, close_token.loc),
-            },
-        )))
+    ) -> Result<'alloc, arena::Box<'alloc, Binding<'alloc>>> {
+        if let Some(rest) = &rest {
+            self.options.check_target(
+                "object rest properties",
+                EcmaVersion::Es2018,
+                rest.loc.start,
+            )?;
+        }
+        Ok(
+            self.alloc(Binding::BindingPattern(BindingPattern::ObjectBinding(
+                ObjectBinding {
+                    properties: properties.unbox(),
+                    rest,
+                    loc: SourceLocation::from_parts(open_token.loc, close_token.loc),
+                },
+            ))),
+        )
     }
 
     pub fn binding_element_list_empty(
@@ -2468,10 +3484,13 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> arena::Box<'alloc, BindingProperty<'alloc>> {
         // Previous parsing interpreted this as a Parameter. We need to take
         // all the pieces out of that box and put them in a new box.
-        let (binding, init) = match binding.unbox() {
-            Parameter::Binding(binding) => (binding, None),
-            Parameter::BindingWithDefault(BindingWithDefault { binding, init, .. }) => {
-                (binding, Some(init.unbox()))
+        let (binding, init, loc) = match binding.unbox() {
+            Parameter::Binding(binding) => {
+                let loc = binding.get_loc();
+                (binding, None, loc)
+            }
+            Parameter::BindingWithDefault(BindingWithDefault { binding, init, loc }) => {
+                (binding, Some(init.unbox()), loc)
             }
         };
 
@@ -2484,8 +3503,6 @@ impl<'alloc> AstBuilder<'alloc> {
             }
         };
 
-        let loc = binding.loc;
-
         self.alloc(BindingProperty::BindingPropertyIdentifier(
             BindingPropertyIdentifier {
                 binding,
@@ -2620,7 +3637,7 @@ impl<'alloc> AstBuilder<'alloc> {
         // https://tc39.es/ecma262/#sec-functiondeclarations-in-ifstatement-statement-clauses
         //
         // This production only applies when parsing non-strict code.
-        if self.is_strict()? {
+        if self.is_strict() {
             return Err(ParseError::FunctionDeclInSingleStatement);
         }
 
@@ -2641,21 +3658,125 @@ impl<'alloc> AstBuilder<'alloc> {
         }))
     }
 
-    fn is_strict(&self) -> Result<'alloc, bool> {
-        Err(ParseError::NotImplemented(
-            "strict-mode-only early error is not yet supported",
-        ))
+    // True if the function body or script with `statements` is strict mode
+    // code: if strict mode is forced, or they start with a directive
+    // prologue with a "use strict" directive. If so, the functions in them
+    // are made strict too, and a WithStatement in them is an error.
+    //
+    // The directive prologue is the ExpressionStatements at the start that
+    // are only a string literal, not in parentheses. A "use strict"
+    // directive has no escapes or line continuations, so its token is
+    // exactly `"use strict"` or `'use strict'`. Its offset is noted for the
+    // function around it, which checks its parameters against it.
+    fn directive_prologue_strict(
+        &mut self,
+        statements: &mut arena::Vec<'alloc, Statement<'alloc>>,
+    ) -> Result<'alloc, bool> {
+        let start = match statements.first() {
+            Some(statement) => statement.get_loc().start,
+            None => return Ok(self.options.strict),
+        };
+        let mut use_strict = false;
+        for statement in statements.iter() {
+            match statement {
+                Statement::ExpressionStatement(expression) => match &**expression {
+                    Expression::LiteralStringExpression { value, loc }
+                        if !self.parenthesized_strings.contains(&loc.start) =>
+                    {
+                        if *value == "use strict" && loc.end - loc.start == "'use strict'".len() {
+                            if !use_strict {
+                                self.use_strict_directives.push(loc.start);
+                            }
+                            use_strict = true;
+                        }
+                    }
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+        self.parenthesized_strings.retain(|&offset| offset < start);
+
+        if self.options.strict {
+            return Ok(true);
+        }
+        if use_strict {
+            let end = statements.last().unwrap().get_loc().end;
+            self.check_legacy_octals(start, end)?;
+            let mut pass = MarkStrict::default();
+            for statement in statements.iter_mut() {
+                pass.visit_statement(statement);
+            }
+            pass.check()?;
+        }
+        Ok(use_strict)
+    }
+
+    // True if the code being reduced is known to be strict mode code, which
+    // is only if strict mode is forced. Otherwise, a "use strict" directive,
+    // or a class or module around it, can still make it strict, and the
+    // early errors it has as strict mode code are then found by
+    // `MarkStrict`.
+    fn is_strict(&self) -> bool {
+        self.options.strict
+    }
+
+    // Removes the "use strict" directive of `body` from the ones noted by
+    // `directive_prologue_strict`, and returns its offset, if it has one.
+    fn take_use_strict_directive(&mut self, body: &FunctionBody<'alloc>) -> Option<usize> {
+        let start = body.statements.first()?.get_loc().start;
+        let directive = self
+            .use_strict_directives
+            .iter()
+            .copied()
+            .find(|&offset| offset >= start);
+        self.use_strict_directives.retain(|&offset| offset < start);
+        directive
+    }
+
+    // Check the name and parameters of a function whose body has a "use
+    // strict" directive, which makes them strict mode code too.
+    fn check_use_strict_function(
+        &mut self,
+        body: &FunctionBody<'alloc>,
+        name: Option<&mut BindingIdentifier<'alloc>>,
+        params: &mut FormalParameters<'alloc>,
+    ) -> Result<'alloc, ()> {
+        let offset = match self.take_use_strict_directive(body) {
+            Some(offset) => offset,
+            None => return Ok(()),
+        };
+
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-function-definitions-static-semantics-early-errors
+        //
+        // * It is a Syntax Error if FunctionBodyContainsUseStrict of
+        //   FunctionBody is true and IsSimpleParameterList of
+        //   FormalParameters is false.
+        if !params.is_simple() {
+            return Err(ParseError::UseStrictWithNonSimpleParameters(offset));
+        }
+        if !self.options.strict {
+            let mut pass = MarkStrict::default();
+            if let Some(name) = name {
+                pass.visit_binding_identifier(name);
+            }
+            pass.visit_formal_parameters(params);
+            pass.check()?;
+        }
+        Ok(())
     }
 
     // IterationStatement : `do` Statement `while` `(` Expression `)` `;`
     pub fn do_while_statement(
-        &self,
+        &mut self,
         do_token: arena::Box<'alloc, Token<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
         test: arena::Box<'alloc, Expression<'alloc>>,
         close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         self.check_single_statement(&stmt)?;
+        self.take_loop_jumps(do_token.loc.start);
 
         Ok(self.alloc(Statement::DoWhileStatement {
             block: stmt,
@@ -2666,12 +3787,13 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // IterationStatement : `while` `(` Expression `)` Statement
     pub fn while_statement(
-        &self,
+        &mut self,
         while_token: arena::Box<'alloc, Token<'alloc>>,
         test: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         self.check_single_statement(&stmt)?;
+        self.take_loop_jumps(while_token.loc.start);
 
         let stmt_loc = stmt.get_loc();
         Ok(self.alloc(Statement::WhileStatement {
@@ -2684,7 +3806,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` [lookahead != 'let'] Expression? `;` Expression? `;` Expression? `)` Statement
     // IterationStatement : `for` `(` `var` VariableDeclarationList `;` Expression? `;` Expression? `)` Statement
     pub fn for_statement(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         init: Option<VariableDeclarationOrExpression<'alloc>>,
         test: Option<arena::Box<'alloc, Expression<'alloc>>>,
@@ -2710,13 +3832,15 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_statement_common(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         init: Option<VariableDeclarationOrExpression<'alloc>>,
         test: Option<arena::Box<'alloc, Expression<'alloc>>>,
         update: Option<arena::Box<'alloc, Expression<'alloc>>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
+        self.take_loop_jumps(for_token.loc.start);
+
         let stmt_loc = stmt.get_loc();
         Ok(self.alloc(Statement::ForStatement {
             init,
@@ -2769,7 +3893,7 @@ impl<'alloc> AstBuilder<'alloc> {
     //
     // IterationStatement :  `for` `(` `var` BindingIdentifier Initializer `in` Expression `)` Statement
     pub fn for_in_statement(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
@@ -2793,12 +3917,14 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_in_statement_common(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
+        self.take_loop_jumps(for_token.loc.start);
+
         let stmt_loc = stmt.get_loc();
         Ok(self.alloc(Statement::ForInStatement {
             left,
@@ -2853,7 +3979,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` [lookahead != 'let'] LeftHandSideExpression `of` AssignmentExpression `)` Statement
     // IterationStatement : `for` `(` `var` ForBinding `of` AssignmentExpression `)` Statement
     pub fn for_of_statement(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
@@ -2877,12 +4003,14 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_of_statement_common(
-        &self,
+        &mut self,
         for_token: arena::Box<'alloc, Token<'alloc>>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
+        self.take_loop_jumps(for_token.loc.start);
+
         let stmt_loc = stmt.get_loc();
         Ok(self.alloc(Statement::ForOfStatement {
             left,
@@ -2973,11 +4101,16 @@ impl<'alloc> AstBuilder<'alloc> {
     // ContinueStatement : `continue` `;`
     // ContinueStatement : `continue` LabelIdentifier `;`
     pub fn continue_statement(
-        &self,
+        &mut self,
         continue_token: arena::Box<'alloc, Token<'alloc>>,
         label: Option<arena::Box<'alloc, Label<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let continue_loc = continue_token.loc;
+        self.jumps.push((
+            JumpKind::Continue,
+            label.as_ref().map(|label| (label.value, label.loc.start)),
+            continue_loc.start,
+        ));
         let loc = match label {
             Some(ref label) => SourceLocation::from_parts(continue_loc, label.loc),
             None => continue_loc,
@@ -2991,11 +4124,16 @@ impl<'alloc> AstBuilder<'alloc> {
     // BreakStatement : `break` `;`
     // BreakStatement : `break` LabelIdentifier `;`
     pub fn break_statement(
-        &self,
+        &mut self,
         break_token: arena::Box<'alloc, Token<'alloc>>,
         label: Option<arena::Box<'alloc, Label<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let break_loc = break_token.loc;
+        self.jumps.push((
+            JumpKind::Break,
+            label.as_ref().map(|label| (label.value, label.loc.start)),
+            break_loc.start,
+        ));
         let loc = match label {
             Some(ref label) => SourceLocation::from_parts(break_loc, label.loc),
             None => break_loc,
@@ -3027,18 +4165,27 @@ impl<'alloc> AstBuilder<'alloc> {
         with_token: arena::Box<'alloc, Token<'alloc>>,
         object: arena::Box<'alloc, Expression<'alloc>>,
         body: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, Statement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-with-statement-static-semantics-early-errors
+        //
+        // * It is a Syntax Error if the code that matches this production is
+        //   contained in strict mode code.
+        if self.options.strict {
+            return Err(ParseError::WithInStrictMode(with_token.loc.start));
+        }
+
         let body_loc = body.get_loc();
-        self.alloc(Statement::WithStatement {
+        Ok(self.alloc(Statement::WithStatement {
             object,
             body,
             loc: SourceLocation::from_parts(with_token.loc, body_loc),
-        })
+        }))
     }
 
     // SwitchStatement : `switch` `(` Expression `)` CaseBlock
     pub fn switch_statement(
-        &self,
+        &mut self,
         switch_token: arena::Box<'alloc, Token<'alloc>>,
         discriminant_expr: arena::Box<'alloc, Expression<'alloc>>,
         mut cases: arena::Box<'alloc, Statement<'alloc>>,
@@ -3062,6 +4209,11 @@ impl<'alloc> AstBuilder<'alloc> {
                 panic!("invalid argument: argument 2 must be a SwitchStatement");
             }
         }
+        // The unlabelled `break`s in the case clauses target it.
+        let start = switch_token.loc.start;
+        self.jumps.retain(|&(kind, label, offset)| {
+            offset < start || label.is_some() || kind != JumpKind::Break
+        });
         cases
     }
 
@@ -3197,17 +4349,37 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // LabelledStatement : LabelIdentifier `:` LabelledItem
     pub fn labelled_statement(
-        &self,
+        &mut self,
         label: arena::Box<'alloc, Label<'alloc>>,
         body: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, Statement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         let label_loc = label.loc;
         let body_loc = body.get_loc();
-        self.alloc(Statement::LabeledStatement {
+
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-labelled-statements-static-semantics-early-errors
+        //
+        // LabelledItem : FunctionDeclaration
+        //
+        // * It is a Syntax Error if any source text is matched by this
+        //   production.
+        //
+        // Annex B allows it in sloppy mode code; see `MarkStrict` for code
+        // that turns out to be strict.
+        if self.is_strict() {
+            if let Statement::FunctionDeclaration(_) = *body {
+                return Err(ParseError::LabelledFunctionDeclInStrictMode(
+                    label_loc.start,
+                ));
+            }
+        }
+        self.declare_label(label.value, label_loc.start)?;
+        self.take_labelled_jumps(label.value, label_loc.start, labels_iteration(&body))?;
+        Ok(self.alloc(Statement::LabeledStatement {
             label: label.unbox(),
             body,
             loc: SourceLocation::from_parts(label_loc, body_loc),
-        })
+        }))
     }
 
     // ThrowStatement : `throw` Expression `;`
@@ -3293,6 +4465,11 @@ impl<'alloc> AstBuilder<'alloc> {
         let catch_loc = catch_token.loc;
         let body_loc = body.loc;
 
+        self.options.check_target(
+            "catch without a binding",
+            EcmaVersion::Es2019,
+            catch_loc.start,
+        )?;
         self.check_catch_no_param_bindings(catch_loc.start)?;
 
         Ok(self.alloc(CatchClause {
@@ -3325,11 +4502,24 @@ impl<'alloc> AstBuilder<'alloc> {
         self.alloc(Statement::FunctionDeclaration(f))
     }
 
-    pub fn function_expr(&mut self, f: Function<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
+    pub fn function_expr(
+        &mut self,
+        f: Function<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        // The name of an async function or async generator expression is
+        // bound in it, so it's reserved as in the body.
+        //
+        // AsyncFunctionExpression : `async` `function` BindingIdentifier[~Yield, +Await] ...
+        if let Some(name) = &f.name {
+            if f.is_async && name.name.value == "await" {
+                return Err(ParseError::InvalidIdentifier("await", name.loc.start));
+            }
+        }
+
         let index = self.find_first_binding(f.loc.start);
         self.pop_bindings_from(index);
 
-        self.alloc(Expression::FunctionExpression(f))
+        Ok(self.alloc(Expression::FunctionExpression(f)))
     }
 
     // FunctionDeclaration : `function` BindingIdentifier `(` FormalParameters `)` `{` FunctionBody `}`
@@ -3338,7 +4528,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn function(
         &mut self,
         function_token: arena::Box<'alloc, Token<'alloc>>,
-        name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        mut name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         param_open_token: arena::Box<'alloc, Token<'alloc>>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
         param_close_token: arena::Box<'alloc, Token<'alloc>>,
@@ -3350,8 +4540,15 @@ impl<'alloc> AstBuilder<'alloc> {
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        let is_simple = Self::is_params_simple(&params);
+        let is_simple = params.is_simple();
         self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, name.as_deref_mut(), &mut params)?;
+        self.check_awaits(
+            AwaitScope::Function,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3360,6 +4557,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.map(|b| b.unbox()),
             is_async: false,
             is_generator: false,
+            flags: FunctionFlags::function(false, false, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(function_token.loc, body_close_loc),
@@ -3372,7 +4570,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn async_function(
         &mut self,
         async_token: arena::Box<'alloc, Token<'alloc>>,
-        name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        mut name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         param_open_token: arena::Box<'alloc, Token<'alloc>>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
         param_close_token: arena::Box<'alloc, Token<'alloc>>,
@@ -3380,12 +4578,24 @@ impl<'alloc> AstBuilder<'alloc> {
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, Function<'alloc>> {
+        self.options.check_target(
+            "async functions",
+            EcmaVersion::Es2017,
+            async_token.loc.start,
+        )?;
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        let is_simple = Self::is_params_simple(&params);
+        let is_simple = params.is_simple();
         self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, name.as_deref_mut(), &mut params)?;
+        self.check_awaits(
+            AwaitScope::Async,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3394,6 +4604,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.map(|b| b.unbox()),
             is_async: true,
             is_generator: false,
+            flags: FunctionFlags::function(false, true, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
@@ -3406,7 +4617,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn generator(
         &mut self,
         function_token: arena::Box<'alloc, Token<'alloc>>,
-        name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        mut name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         param_open_token: arena::Box<'alloc, Token<'alloc>>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
         param_close_token: arena::Box<'alloc, Token<'alloc>>,
@@ -3418,8 +4629,16 @@ impl<'alloc> AstBuilder<'alloc> {
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        let is_simple = Self::is_params_simple(&params);
+        let is_simple = params.is_simple();
         self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, name.as_deref_mut(), &mut params)?;
+        self.check_awaits(
+            AwaitScope::Function,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
+        Self::check_yields(&params, &body)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3428,6 +4647,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.map(|b| b.unbox()),
             is_async: false,
             is_generator: true,
+            flags: FunctionFlags::function(true, false, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(function_token.loc, body_close_loc),
@@ -3440,7 +4660,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn async_generator(
         &mut self,
         async_token: arena::Box<'alloc, Token<'alloc>>,
-        name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        mut name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         param_open_token: arena::Box<'alloc, Token<'alloc>>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
         param_close_token: arena::Box<'alloc, Token<'alloc>>,
@@ -3448,12 +4668,25 @@ impl<'alloc> AstBuilder<'alloc> {
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, Function<'alloc>> {
+        self.options.check_target(
+            "async generators",
+            EcmaVersion::Es2018,
+            async_token.loc.start,
+        )?;
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        let is_simple = Self::is_params_simple(&params);
+        let is_simple = params.is_simple();
         self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, name.as_deref_mut(), &mut params)?;
+        self.check_awaits(
+            AwaitScope::Async,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
+        Self::check_yields(&params, &body)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3462,6 +4695,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.map(|b| b.unbox()),
             is_async: true,
             is_generator: true,
+            flags: FunctionFlags::function(true, true, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
@@ -3522,16 +4756,21 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // FunctionBody : FunctionStatementList
     pub fn function_body(
-        &self,
-        statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
-    ) -> arena::Box<'alloc, FunctionBody<'alloc>> {
-        // TODO: Directives
-        self.alloc(FunctionBody {
+        &mut self,
+        mut statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
+    ) -> Result<'alloc, arena::Box<'alloc, FunctionBody<'alloc>>> {
+        let strict = self.directive_prologue_strict(&mut statements)?;
+        // The directives are left in the statements, as the statements of
+        // the directive prologue are still run.
+        Ok(self.alloc(FunctionBody {
             directives: self.new_vec(),
             statements: statements.unbox(),
+            is_lazy: false,
+            // This is also set once the enclosing code turns out to be strict.
+            strict,
             // This will be overwritten once the enclosing function gets parsed.
             loc: SourceLocation::default(),
-        })
+        }))
     }
 
     // FunctionStatementList : StatementList?
@@ -3548,22 +4787,40 @@ impl<'alloc> AstBuilder<'alloc> {
     // ArrowFunction : ArrowParameters `=>` ConciseBody
     pub fn arrow_function(
         &mut self,
-        params: arena::Box<'alloc, FormalParameters<'alloc>>,
+        mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
         body: arena::Box<'alloc, ArrowExpressionBody<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_arrow_function_bindings(&params)?;
+        if let ArrowExpressionBody::FunctionBody(body) = &*body {
+            self.check_use_strict_function(body, None, &mut params)?;
+        }
 
         let params_loc = params.loc;
         let body_loc = body.get_loc();
+        self.check_awaits(AwaitScope::Arrow, params_loc.start, body_loc.start)?;
 
+        let strict = self.arrow_body_strict(&body);
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: false,
+            flags: FunctionFlags::arrow(false, &params),
             params: params.unbox(),
             body: body.unbox(),
+            strict,
             loc: SourceLocation::from_parts(params_loc, body_loc),
         }))
     }
 
+    // True if the code of an arrow function with the body `body` is known to
+    // be strict mode code, as for a FunctionBody: if its body has a "use
+    // strict" directive or strict mode is forced. Otherwise `MarkStrict`
+    // marks it once the code around it turns out to be strict.
+    fn arrow_body_strict(&self, body: &ArrowExpressionBody<'alloc>) -> bool {
+        match body {
+            ArrowExpressionBody::FunctionBody(body) => body.strict,
+            ArrowExpressionBody::Expression(_) => self.is_strict(),
+        }
+    }
+
     // ArrowParameters : BindingIdentifier
     pub fn arrow_parameters_bare(
         &mut self,
@@ -3631,6 +4888,13 @@ impl<'alloc> AstBuilder<'alloc> {
         let body_close_loc = body_close_token.loc;
 
         self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, None, &mut params)?;
+        self.check_awaits(
+            AwaitScope::Function,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3639,6 +4903,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.unbox(),
             is_async: false,
             is_generator: false,
+            flags: FunctionFlags::method(false, false, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(name_loc, body_close_loc),
@@ -3647,20 +4912,32 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // MethodDefinition : `get` PropertyName `(` `)` `{` FunctionBody `}`
     pub fn getter(
-        &self,
+        &mut self,
         get_token: arena::Box<'alloc, Token<'alloc>>,
         name: arena::Box<'alloc, PropertyName<'alloc>>,
         body_open_token: arena::Box<'alloc, Token<'alloc>>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, MethodDefinition<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let body_close_loc = body_close_token.loc;
+        // A getter has no parameters to check the bindings of, but its body
+        // is still a function body.
+        self.take_use_strict_directive(&body);
+        self.pop_labels_from(body_open_token.loc.start);
+        self.check_jumps_from(body_open_token.loc.start)?;
+        self.check_awaits(
+            AwaitScope::Function,
+            body_open_token.loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(body_open_token.loc.start);
         body.loc.set_range(body_open_token.loc, body_close_loc);
-        self.alloc(MethodDefinition::Getter(Getter {
+        Ok(self.alloc(MethodDefinition::Getter(Getter {
             property_name: name.unbox(),
             body: body.unbox(),
+            flags: FunctionFlags::getter(),
             loc: SourceLocation::from_parts(get_token.loc, body_close_loc),
-        }))
+        })))
     }
 
     // MethodDefinition : `set` PropertyName `(` PropertySetParameterList `)` `{` FunctionBody `}`
@@ -3682,11 +4959,32 @@ impl<'alloc> AstBuilder<'alloc> {
         // A setter only has one parameter, but it can be a destructuring
         // pattern, so it is still possible to flunk this check.
         self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        if let Some(offset) = self.take_use_strict_directive(&body) {
+            // As for the FormalParameters of a function, it's an error for
+            // a PropertySetParameterList that isn't simple.
+            match &mut *parameter {
+                Parameter::Binding(Binding::BindingIdentifier(binding)) => {
+                    if !self.options.strict {
+                        let mut pass = MarkStrict::default();
+                        pass.visit_binding_identifier(binding);
+                        pass.check()?;
+                    }
+                }
+                _ => return Err(ParseError::UseStrictWithNonSimpleParameters(offset)),
+            }
+        }
+        self.check_awaits(
+            AwaitScope::Function,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
 
         parameter.set_loc(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
         Ok(self.alloc(MethodDefinition::Setter(Setter {
             property_name: name.unbox(),
+            flags: FunctionFlags::setter(&parameter),
             param: parameter.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(set_token.loc, body_close_loc),
@@ -3710,6 +5008,14 @@ impl<'alloc> AstBuilder<'alloc> {
         let body_close_loc = body_close_token.loc;
 
         self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, None, &mut params)?;
+        self.check_awaits(
+            AwaitScope::Function,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
+        Self::check_yields(&params, &body)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3718,6 +5024,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.unbox(),
             is_async: false,
             is_generator: true,
+            flags: FunctionFlags::method(true, false, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(generator_token.loc, body_close_loc),
@@ -3769,11 +5076,24 @@ impl<'alloc> AstBuilder<'alloc> {
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
+        self.options.check_target(
+            "async generator methods",
+            EcmaVersion::Es2018,
+            async_token.loc.start,
+        )?;
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
         self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, None, &mut params)?;
+        self.check_awaits(
+            AwaitScope::Async,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
+        Self::check_yields(&params, &body)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3782,6 +5102,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.unbox(),
             is_async: true,
             is_generator: true,
+            flags: FunctionFlags::method(true, true, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
@@ -3809,6 +5130,7 @@ impl<'alloc> AstBuilder<'alloc> {
                     BindingIdentifier {
                         name: Identifier {
                             value: "default",
+                            atom: None,
                             loc,
                         },
                         loc,
@@ -3840,23 +5162,45 @@ impl<'alloc> AstBuilder<'alloc> {
     // ClassTail : ClassHeritage? `{` ClassBody? `}`
     pub fn class_tail(
         &self,
-        heritage: Option<arena::Box<'alloc, Expression<'alloc>>>,
+        mut heritage: Option<arena::Box<'alloc, Expression<'alloc>>>,
+        body_open_token: arena::Box<'alloc, Token<'alloc>>,
         body: Option<
             arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>>,
         >,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, ClassExpression<'alloc>> {
-        self.alloc(ClassExpression {
+    ) -> Result<'alloc, arena::Box<'alloc, ClassExpression<'alloc>>> {
+        let mut elements = match body {
+            None => self.new_vec(),
+            Some(boxed) => boxed.unbox(),
+        };
+        self.take_private_references(&elements, body_open_token.loc.start)?;
+        // All parts of a class are strict mode code.
+        if !self.options.strict {
+            let start = match (&heritage, elements.first()) {
+                (Some(heritage), _) => Some(heritage.get_loc().start),
+                (None, Some(element)) => Some(element.get_loc().start),
+                (None, None) => None,
+            };
+            if let Some(start) = start {
+                self.check_legacy_octals(start, body_close_token.loc.end)?;
+            }
+            let mut pass = MarkStrict::default();
+            if let Some(heritage) = &mut heritage {
+                pass.visit_expression(heritage);
+            }
+            for element in elements.iter_mut() {
+                pass.visit_class_element(element);
+            }
+            pass.check()?;
+        }
+        Ok(self.alloc(ClassExpression {
             name: None,
             super_: heritage,
-            elements: match body {
-                None => self.new_vec(),
-                Some(boxed) => boxed.unbox(),
-            },
+            elements,
             // `start` of this will be overwritten once the enclosing class
             // gets parsed.
             loc: body_close_token.loc,
-        })
+        }))
     }
 
     // ClassElementList : ClassElementList ClassElement
@@ -3877,17 +5221,24 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         name: arena::Box<'alloc, ClassElementName<'alloc>>,
         init: Option<arena::Box<'alloc, Expression<'alloc>>>,
-    ) -> arena::Box<'alloc, ClassElement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElement<'alloc>>> {
         let name_loc = name.get_loc();
+        self.options
+            .check_target("class fields", EcmaVersion::Es2022, name_loc.start)?;
+        // Like a function body, an initializer is evaluated with a
+        // `new.target`, which is undefined. A computed name isn't: it's
+        // evaluated with the class.
+        self.pop_new_targets_from(name_loc.end);
         let loc = match &init {
             None => name_loc,
             Some(expr) => SourceLocation::from_parts(name_loc, expr.get_loc()),
         };
-        self.alloc(ClassElement::FieldDefinition {
+        Ok(self.alloc(ClassElement::FieldDefinition {
+            is_static: false,
             name: name.unbox(),
             init,
             loc,
-        })
+        }))
     }
 
     // ClassElementName : PropertyName
@@ -3905,10 +5256,15 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn class_element_name_private(
         &self,
         private_identifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, ClassElementName<'alloc>> {
-        self.alloc(ClassElementName::PrivateFieldName(
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElementName<'alloc>>> {
+        self.options.check_target(
+            "private class members",
+            EcmaVersion::Es2022,
+            private_identifier.loc.start,
+        )?;
+        Ok(self.alloc(ClassElementName::PrivateFieldName(
             self.private_identifier(private_identifier),
-        ))
+        )))
     }
 
     // ClassElement : MethodDefinition
@@ -3948,6 +5304,47 @@ impl<'alloc> AstBuilder<'alloc> {
         )
     }
 
+    // ClassElement : `static` FieldDefinition `;`
+    pub fn class_element_static_field(
+        &self,
+        static_token: arena::Box<'alloc, Token<'alloc>>,
+        mut field: arena::Box<'alloc, ClassElement<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>> {
+        if let ClassElement::FieldDefinition { is_static, loc, .. } = &mut *field {
+            *is_static = true;
+            loc.start = static_token.loc.start;
+        }
+        self.class_element_to_vec(field)
+    }
+
+    // ClassStaticBlock : `static` `{` ClassStaticBlockStatementList `}`
+    pub fn class_static_block(
+        &mut self,
+        static_token: arena::Box<'alloc, Token<'alloc>>,
+        statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
+        close_token: arena::Box<'alloc, Token<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElement<'alloc>>> {
+        self.options.check_target(
+            "class static blocks",
+            EcmaVersion::Es2022,
+            static_token.loc.start,
+        )?;
+        // Like a function body, a static block can't use the labels around
+        // it.
+        self.pop_labels_from(static_token.loc.start);
+        self.check_jumps_from(static_token.loc.start)?;
+        self.check_awaits(
+            AwaitScope::StaticBlock,
+            static_token.loc.start,
+            static_token.loc.start,
+        )?;
+        self.pop_new_targets_from(static_token.loc.start);
+        Ok(self.alloc(ClassElement::StaticBlock {
+            statements: statements.unbox(),
+            loc: SourceLocation::from_parts(static_token.loc, close_token.loc),
+        }))
+    }
+
     // ClassElement : `;`
     pub fn class_element_empty(
         &self,
@@ -3967,11 +5364,20 @@ impl<'alloc> AstBuilder<'alloc> {
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
         body_close_token: arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
+        self.options
+            .check_target("async methods", EcmaVersion::Es2017, async_token.loc.start)?;
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
         self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_use_strict_function(&body, None, &mut params)?;
+        self.check_awaits(
+            AwaitScope::Async,
+            param_open_loc.start,
+            body_open_token.loc.start,
+        )?;
+        self.pop_new_targets_from(param_open_loc.start);
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
@@ -3980,6 +5386,7 @@ impl<'alloc> AstBuilder<'alloc> {
             name: name.unbox(),
             is_async: true,
             is_generator: false,
+            flags: FunctionFlags::method(false, true, &params),
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
@@ -3992,6 +5399,9 @@ impl<'alloc> AstBuilder<'alloc> {
         await_token: arena::Box<'alloc, Token<'alloc>>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.awaits
+            .borrow_mut()
+            .push((await_token.loc.start, AwaitUse::Expression));
         let operand_loc = operand.get_loc();
         self.alloc(Expression::AwaitExpression {
             expression: operand,
@@ -4007,15 +5417,27 @@ impl<'alloc> AstBuilder<'alloc> {
         identifier: arena::Box<'alloc, BindingIdentifier<'alloc>>,
         body: arena::Box<'alloc, ArrowExpressionBody<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
-        let params = self.arrow_parameters_bare(identifier);
+        self.options.check_target(
+            "async arrow functions",
+            EcmaVersion::Es2017,
+            async_token.loc.start,
+        )?;
+        let mut params = self.arrow_parameters_bare(identifier);
 
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_arrow_function_bindings(&params)?;
+        if let ArrowExpressionBody::FunctionBody(body) = &*body {
+            self.check_use_strict_function(body, None, &mut params)?;
+        }
 
         let body_loc = body.get_loc();
+        self.check_awaits(AwaitScope::Async, async_token.loc.start, body_loc.start)?;
+        let strict = self.arrow_body_strict(&body);
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: true,
+            flags: FunctionFlags::arrow(true, &params),
             params: params.unbox(),
             body: body.unbox(),
+            strict,
             loc: SourceLocation::from_parts(async_token.loc, body_loc),
         }))
     }
@@ -4025,15 +5447,24 @@ impl<'alloc> AstBuilder<'alloc> {
         params: arena::Box<'alloc, Expression<'alloc>>,
         body: arena::Box<'alloc, ArrowExpressionBody<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
-        let (params, call_loc) = self.async_arrow_parameters(params)?;
+        let (mut params, call_loc) = self.async_arrow_parameters(params)?;
+        self.options
+            .check_target("async arrow functions", EcmaVersion::Es2017, call_loc.start)?;
 
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_arrow_function_bindings(&params)?;
+        if let ArrowExpressionBody::FunctionBody(body) = &*body {
+            self.check_use_strict_function(body, None, &mut params)?;
+        }
 
         let body_loc = body.get_loc();
+        self.check_awaits(AwaitScope::Async, call_loc.start, body_loc.start)?;
+        let strict = self.arrow_body_strict(&body);
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: true,
+            flags: FunctionFlags::arrow(true, &params),
             params: params.unbox(),
             body: body.unbox(),
+            strict,
             loc: SourceLocation::from_parts(call_loc, body_loc),
         }))
     }
@@ -4059,6 +5490,15 @@ impl<'alloc> AstBuilder<'alloc> {
                                 // `foo(a, b) => {}`
                                 return Err(ParseError::ArrowHeadInvalid);
                             }
+                            // AsyncArrowHead : `async` [no LineTerminator here] ArrowFormalParameters
+                            if self
+                                .new_line_arguments
+                                .borrow()
+                                .contains(&arguments.loc.start)
+                            {
+                                // `async\n(a) => {}`
+                                return Err(ParseError::ArrowHeadInvalid);
+                            }
                         }
                         _ => {
                             // `obj.async() => {}`
@@ -4088,13 +5528,30 @@ impl<'alloc> AstBuilder<'alloc> {
         &mut self,
         script: Option<arena::Box<'alloc, Script<'alloc>>>,
     ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+        self.check_cover_initializers()?;
+        self.check_script_awaits()?;
+        self.check_new_targets()?;
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+        //
+        // * It is a Syntax Error if StatementList Contains ImportMeta.
+        if let Some(offset) = self.first_import_meta.take() {
+            return Err(ParseError::ImportMetaOutsideModule(offset));
+        }
+        self.check_private_references()?;
+        self.check_jumps_from(0)?;
         self.check_script_bindings()?;
+        self.legacy_octals.clear();
+        self.parenthesized_expressions.clear();
+        self.spreads_before_trailing_comma.get_mut().clear();
 
         Ok(match script {
             Some(script) => script,
             None => self.alloc(Script {
                 directives: self.new_vec(),
                 statements: self.new_vec(),
+                hashbang: None,
+                strict: self.options.strict,
                 loc: SourceLocation::default(),
             }),
         })
@@ -4102,9 +5559,9 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // ScriptBody : StatementList
     pub fn script_body(
-        &self,
-        statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
-    ) -> arena::Box<'alloc, Script<'alloc>> {
+        &mut self,
+        mut statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
         let loc = if statements.is_empty() {
             SourceLocation::default()
         } else {
@@ -4114,50 +5571,152 @@ impl<'alloc> AstBuilder<'alloc> {
             )
         };
 
-        // TODO: directives
-        self.alloc(Script {
+        let strict = self.directive_prologue_strict(&mut statements)?;
+        self.use_strict_directives.clear();
+        // As in a FunctionBody, the directives are left in the statements.
+        Ok(self.alloc(Script {
             directives: self.new_vec(),
             statements: statements.unbox(),
+            hashbang: None,
+            strict,
             loc,
-        })
+        }))
     }
 
     // Module : ModuleBody?
     pub fn module(
         &mut self,
-        body: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
-    ) -> Result<'alloc, arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>> {
+        body: Option<arena::Box<'alloc, arena::Vec<'alloc, ModuleItems<'alloc>>>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
+        self.check_cover_initializers()?;
+        self.check_module_awaits()?;
+        self.check_new_targets()?;
+        self.first_import_meta.set(None);
+        self.check_private_references()?;
+        self.check_jumps_from(0)?;
         self.check_module_bindings()?;
 
-        Ok(body.unwrap_or_else(|| self.alloc(self.new_vec())))
+        let mut items = match body {
+            Some(items) => items.unbox(),
+            None => self.new_vec(),
+        };
+        // Module code is always strict mode code.
+        if !self.options.strict {
+            self.check_legacy_octals(0, usize::MAX)?;
+            let mut pass = MarkStrict::default();
+            for item in items.iter_mut() {
+                pass.visit_module_items(item);
+            }
+            pass.check()?;
+        }
+        self.legacy_octals.clear();
+        self.parenthesized_strings.clear();
+        self.parenthesized_expressions.clear();
+        self.spreads_before_trailing_comma.get_mut().clear();
+        let loc = if items.is_empty() {
+            SourceLocation::default()
+        } else {
+            SourceLocation::from_parts(
+                items.first().unwrap().get_loc(),
+                items.last().unwrap().get_loc(),
+            )
+        };
+
+        Ok(self.alloc(Module {
+            directives: self.new_vec(),
+            items,
+            hashbang: None,
+            strict: true,
+            loc,
+        }))
     }
 
     // ModuleItemList : ModuleItem
     pub fn module_item_list_single(
         &self,
-        item: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>> {
+        item: arena::Box<'alloc, ModuleItems<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ModuleItems<'alloc>>> {
         self.alloc(self.new_vec_single(item.unbox()))
     }
 
     // ModuleItemList : ModuleItemList ModuleItem
     pub fn module_item_list_append(
         &self,
-        mut list: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
-        item: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>> {
+        mut list: arena::Box<'alloc, arena::Vec<'alloc, ModuleItems<'alloc>>>,
+        item: arena::Box<'alloc, ModuleItems<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ModuleItems<'alloc>>> {
         self.push(&mut list, item.unbox());
         list
     }
 
+    // ModuleItem : ImportDeclaration
+    pub fn module_item_import(
+        &self,
+        declaration: arena::Box<'alloc, ImportDeclaration<'alloc>>,
+    ) -> arena::Box<'alloc, ModuleItems<'alloc>> {
+        self.alloc(ModuleItems::ImportDeclaration(declaration.unbox()))
+    }
+
+    // ModuleItem : ExportDeclaration
+    pub fn module_item_export(
+        &self,
+        declaration: arena::Box<'alloc, ExportDeclaration<'alloc>>,
+    ) -> arena::Box<'alloc, ModuleItems<'alloc>> {
+        self.alloc(ModuleItems::ExportDeclaration(declaration.unbox()))
+    }
+
+    // ModuleItem : StatementListItem
+    pub fn module_item_statement(
+        &self,
+        statement: arena::Box<'alloc, Statement<'alloc>>,
+    ) -> arena::Box<'alloc, ModuleItems<'alloc>> {
+        self.alloc(ModuleItems::Statement(statement))
+    }
+
     // ImportDeclaration : `import` ImportClause FromClause `;`
+    // ImportDeclaration : `import` ImportClause FromClause WithClause `;`
     // ImportDeclaration : `import` ModuleSpecifier `;`
+    // ImportDeclaration : `import` ModuleSpecifier WithClause `;`
     pub fn import_declaration(
-        &self,
-        _import_clause: Option<arena::Box<'alloc, Void>>,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
+        &mut self,
+        import_token: arena::Box<'alloc, Token<'alloc>>,
+        import_clause: Option<arena::Box<'alloc, ImportDeclaration<'alloc>>>,
+        module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        attributes: Option<arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>>,
+    ) -> arena::Box<'alloc, ImportDeclaration<'alloc>> {
+        let import_loc = import_token.loc;
+        let specifier_loc = module_specifier.loc;
+        let specifier = module_specifier.value.unwrap();
+        let attributes = self.import_attributes(attributes);
+
+        self.mark_binding_kind(import_loc.start, None, BindingKind::Import);
+
+        let mut declaration = match import_clause {
+            Some(clause) => clause.unbox(),
+            None => ImportDeclaration::Import(Import {
+                module_specifier: specifier,
+                default_binding: None,
+                named_imports: self.new_vec(),
+                attributes: self.new_vec(),
+                loc: SourceLocation::default(),
+            }),
+        };
+
+        // ImportClause doesn't know the module specifier or the attributes,
+        // fill them here.
+        match &mut declaration {
+            ImportDeclaration::Import(import) => {
+                import.module_specifier = specifier;
+                import.attributes = attributes;
+            }
+            ImportDeclaration::ImportNamespace(import) => {
+                import.module_specifier = specifier;
+                import.attributes = attributes;
+            }
+        }
+        declaration.set_loc(import_loc, specifier_loc);
+
+        self.alloc(declaration)
     }
 
     // ImportClause : ImportedDefaultBinding
@@ -4167,156 +5726,343 @@ impl<'alloc> AstBuilder<'alloc> {
     // ImportClause : ImportedDefaultBinding `,` NamedImports
     pub fn import_clause(
         &self,
-        _default_binding: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        _name_space_import: Option<arena::Box<'alloc, Void>>,
-        _named_imports: Option<arena::Box<'alloc, Void>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
-    }
-
-    // NameSpaceImport : `*` `as` ImportedBinding
-    pub fn name_space_import(
-        &self,
-        _name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
+        default_binding: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        name_space_import: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
+        named_imports: Option<arena::Box<'alloc, arena::Vec<'alloc, ImportSpecifier<'alloc>>>>,
+    ) -> arena::Box<'alloc, ImportDeclaration<'alloc>> {
+        // `module_specifier`, `attributes` and `loc` are updated by
+        // `import_declaration`.
+        let default_binding = default_binding.map(|binding| binding.unbox());
+        self.alloc(match name_space_import {
+            Some(namespace_binding) => ImportDeclaration::ImportNamespace(ImportNamespace {
+                module_specifier: "",
+                default_binding,
+                namespace_binding: namespace_binding.unbox(),
+                attributes: self.new_vec(),
+                loc: SourceLocation::default(),
+            }),
+            None => ImportDeclaration::Import(Import {
+                module_specifier: "",
+                default_binding,
+                named_imports: match named_imports {
+                    Some(named_imports) => named_imports.unbox(),
+                    None => self.new_vec(),
+                },
+                attributes: self.new_vec(),
+                loc: SourceLocation::default(),
+            }),
+        })
     }
 
     // NamedImports : `{` `}`
-    pub fn imports_list_empty(&self) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
+    pub fn imports_list_empty(
+        &self,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ImportSpecifier<'alloc>>> {
+        self.alloc(self.new_vec())
     }
 
     // ImportsList : ImportSpecifier
     // ImportsList : ImportsList `,` ImportSpecifier
     pub fn imports_list_append(
         &self,
-        _list: arena::Box<'alloc, Void>,
-        _item: arena::Box<'alloc, Void>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
+        mut list: arena::Box<'alloc, arena::Vec<'alloc, ImportSpecifier<'alloc>>>,
+        item: arena::Box<'alloc, ImportSpecifier<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ImportSpecifier<'alloc>>> {
+        self.push(&mut list, item.unbox());
+        list
+    }
+
+    // WithClause : `with` `{` `}`
+    // WithClause : `assert` `{` `}`
+    pub fn import_attributes_empty(
+        &self,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>> {
+        self.alloc(self.new_vec())
+    }
+
+    // WithEntries : AttributeKey `:` StringLiteral
+    // WithEntries : WithEntries `,` AttributeKey `:` StringLiteral
+    pub fn import_attributes_append(
+        &self,
+        mut list: arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>,
+        attribute: arena::Box<'alloc, ImportAttribute<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>> {
+        // It is a Syntax Error if WithClauseToAttributes of WithClause has
+        // two different entries a and b such that a.[[Key]] is b.[[Key]].
+        if let Some(previous) = list.iter().find(|previous| previous.key == attribute.key) {
+            return Err(ParseError::DuplicateImportAttribute(
+                attribute.key,
+                previous.loc.start,
+                attribute.loc.start,
+            ));
+        }
+        self.push(&mut list, attribute.unbox());
+        Ok(list)
+    }
+
+    // WithEntries : AttributeKey `:` StringLiteral
+    pub fn import_attribute(
+        &self,
+        key: arena::Box<'alloc, Token<'alloc>>,
+        value: arena::Box<'alloc, Token<'alloc>>,
+    ) -> arena::Box<'alloc, ImportAttribute<'alloc>> {
+        let loc = SourceLocation::from_parts(key.loc, value.loc);
+        self.alloc(ImportAttribute {
+            key: key.value.unwrap(),
+            value: value.value.unwrap(),
+            loc,
+        })
+    }
+
+    // The attributes of a declaration without a WithClause are empty.
+    fn import_attributes(
+        &self,
+        attributes: Option<arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>>,
+    ) -> arena::Vec<'alloc, ImportAttribute<'alloc>> {
+        match attributes {
+            Some(attributes) => attributes.unbox(),
+            None => self.new_vec(),
+        }
     }
 
     // ImportSpecifier : ImportedBinding
     pub fn import_specifier(
         &self,
-        _name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
+        name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
+    ) -> arena::Box<'alloc, ImportSpecifier<'alloc>> {
+        let loc = name.loc;
+        self.alloc(ImportSpecifier {
+            name: None,
+            binding: name.unbox(),
+            loc,
+        })
     }
 
     // ImportSpecifier : IdentifierName `as` ImportedBinding
     pub fn import_specifier_renaming(
         &self,
-        _original_name: arena::Box<'alloc, Token<'alloc>>,
-        _local_name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("import"))
-    }
-
-    // ModuleSpecifier : StringLiteral
-    pub fn module_specifier(
-        &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Token<'alloc>>> {
-        Err(ParseError::NotImplemented("import"))
+        original_name: arena::Box<'alloc, Token<'alloc>>,
+        local_name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
+    ) -> arena::Box<'alloc, ImportSpecifier<'alloc>> {
+        let loc = SourceLocation::from_parts(original_name.loc, local_name.loc);
+        self.alloc(ImportSpecifier {
+            name: Some(self.identifier_name(original_name)),
+            binding: local_name.unbox(),
+            loc,
+        })
     }
 
     // ExportDeclaration : `export` `*` FromClause `;`
+    // ExportDeclaration : `export` `*` FromClause WithClause `;`
     pub fn export_all_from(
         &self,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        export_token: arena::Box<'alloc, Token<'alloc>>,
+        module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        attributes: Option<arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        let loc = SourceLocation::from_parts(export_token.loc, module_specifier.loc);
+        self.alloc(ExportDeclaration::ExportAllFrom(ExportAllFrom {
+            module_specifier: module_specifier.value.unwrap(),
+            attributes: self.import_attributes(attributes),
+            loc,
+        }))
     }
 
     // ExportDeclaration : `export` ExportClause FromClause `;`
+    // ExportDeclaration : `export` ExportClause FromClause WithClause `;`
     pub fn export_set_from(
-        &self,
-        _export_clause: arena::Box<'alloc, Void>,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        export_token: arena::Box<'alloc, Token<'alloc>>,
+        export_clause: arena::Box<'alloc, arena::Vec<'alloc, ExportFromSpecifier<'alloc>>>,
+        module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        attributes: Option<arena::Box<'alloc, arena::Vec<'alloc, ImportAttribute<'alloc>>>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        for specifier in export_clause.iter() {
+            let exported_name = specifier.exported_name.as_ref().unwrap_or(&specifier.name);
+            self.add_exported_name(exported_name.value, exported_name.loc.start);
+        }
+
+        let loc = SourceLocation::from_parts(export_token.loc, module_specifier.loc);
+        self.alloc(ExportDeclaration::ExportFrom(ExportFrom {
+            named_exports: export_clause.unbox(),
+            module_specifier: module_specifier.value.unwrap(),
+            attributes: self.import_attributes(attributes),
+            loc,
+        }))
     }
 
     // ExportDeclaration : `export` ExportClause `;`
     pub fn export_set(
-        &self,
-        _export_clause: arena::Box<'alloc, Void>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        export_token: arena::Box<'alloc, Token<'alloc>>,
+        export_clause: arena::Box<'alloc, arena::Vec<'alloc, ExportFromSpecifier<'alloc>>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        let mut named_exports = self.new_vec();
+        let mut end_loc = export_token.loc;
+        for specifier in export_clause.unbox() {
+            let local_name = specifier.name;
+            let exported_name = specifier.exported_name.as_ref().unwrap_or(&local_name);
+            self.add_exported_name(exported_name.value, exported_name.loc.start);
+            self.add_exported_binding(local_name.value, local_name.loc.start);
+
+            end_loc = specifier.loc;
+            self.push(
+                &mut named_exports,
+                ExportLocalSpecifier {
+                    name: IdentifierExpression {
+                        name: Identifier {
+                            value: local_name.value,
+                            atom: local_name.atom,
+                            loc: local_name.loc,
+                        },
+                        loc: local_name.loc,
+                    },
+                    exported_name: specifier.exported_name,
+                    loc: specifier.loc,
+                },
+            );
+        }
+
+        self.alloc(ExportDeclaration::ExportLocals(ExportLocals {
+            named_exports,
+            loc: SourceLocation::from_parts(export_token.loc, end_loc),
+        }))
     }
 
     // ExportDeclaration : `export` VariableStatement
     pub fn export_vars(
-        &self,
-        _statement: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        export_token: arena::Box<'alloc, Token<'alloc>>,
+        statement: arena::Box<'alloc, Statement<'alloc>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        self.export_declaration(export_token, statement)
     }
 
     // ExportDeclaration : `export` Declaration
     pub fn export_declaration(
-        &self,
-        _declaration: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        export_token: arena::Box<'alloc, Token<'alloc>>,
+        declaration: arena::Box<'alloc, Statement<'alloc>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        // All bindings declared by the declaration are exported under the
+        // same name.
+        let index = self.find_first_binding(export_token.loc.start);
+        for i in index..self.bindings.len() {
+            let info = self.bindings[i];
+            self.add_exported_name(info.name, info.offset);
+        }
+
+        self.alloc(ExportDeclaration::Export(match declaration.unbox() {
+            Statement::VariableDeclarationStatement(decl) => Export::VariableDeclaration(decl),
+            Statement::FunctionDeclaration(f) => Export::FunctionDeclaration(f),
+            Statement::ClassDeclaration(class) => Export::ClassDeclaration(class),
+            _ => {
+                // The grammar ensures that the parser always passes a
+                // declaration to this function.
+                panic!("invalid argument");
+            }
+        }))
     }
 
     // ExportDeclaration : `export` `default` HoistableDeclaration
     pub fn export_default_hoistable(
-        &self,
-        _declaration: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        _export_token: arena::Box<'alloc, Token<'alloc>>,
+        default_token: arena::Box<'alloc, Token<'alloc>>,
+        declaration: arena::Box<'alloc, Statement<'alloc>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        self.add_exported_name("default", default_token.loc.start);
+
+        self.alloc(ExportDeclaration::ExportDefault(
+            match declaration.unbox() {
+                Statement::FunctionDeclaration(f) => ExportDefault::FunctionDeclaration(f),
+                _ => {
+                    // The grammar ensures that the parser always passes a
+                    // HoistableDeclaration to this function.
+                    panic!("invalid argument");
+                }
+            },
+        ))
     }
 
     // ExportDeclaration : `export` `default` ClassDeclaration
     pub fn export_default_class(
-        &self,
-        _class_declaration: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        _export_token: arena::Box<'alloc, Token<'alloc>>,
+        default_token: arena::Box<'alloc, Token<'alloc>>,
+        class_declaration: arena::Box<'alloc, Statement<'alloc>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        self.add_exported_name("default", default_token.loc.start);
+
+        self.alloc(ExportDeclaration::ExportDefault(
+            match class_declaration.unbox() {
+                Statement::ClassDeclaration(class) => ExportDefault::ClassDeclaration(class),
+                _ => {
+                    // The grammar ensures that the parser always passes a
+                    // ClassDeclaration to this function.
+                    panic!("invalid argument");
+                }
+            },
+        ))
     }
 
     // ExportDeclaration : `export` `default` [lookahead <! {`function`, `async`, `class`}] AssignmentExpression `;`
     pub fn export_default_value(
-        &self,
-        _expression: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        &mut self,
+        _export_token: arena::Box<'alloc, Token<'alloc>>,
+        default_token: arena::Box<'alloc, Token<'alloc>>,
+        expression: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> arena::Box<'alloc, ExportDeclaration<'alloc>> {
+        self.add_exported_name("default", default_token.loc.start);
+
+        self.alloc(ExportDeclaration::ExportDefault(ExportDefault::Expression(
+            expression,
+        )))
     }
 
     // ExportClause : `{` `}`
-    pub fn exports_list_empty(&self) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+    pub fn exports_list_empty(
+        &self,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ExportFromSpecifier<'alloc>>> {
+        self.alloc(self.new_vec())
     }
 
     // ExportsList : ExportSpecifier
     // ExportsList : ExportsList `,` ExportSpecifier
     pub fn exports_list_append(
         &self,
-        _list: arena::Box<'alloc, Void>,
-        _export_specifier: arena::Box<'alloc, Void>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        mut list: arena::Box<'alloc, arena::Vec<'alloc, ExportFromSpecifier<'alloc>>>,
+        export_specifier: arena::Box<'alloc, ExportFromSpecifier<'alloc>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, ExportFromSpecifier<'alloc>>> {
+        self.push(&mut list, export_specifier.unbox());
+        list
     }
 
     // ExportSpecifier : IdentifierName
     pub fn export_specifier(
         &self,
-        _identifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        identifier: arena::Box<'alloc, Token<'alloc>>,
+    ) -> arena::Box<'alloc, ExportFromSpecifier<'alloc>> {
+        let loc = identifier.loc;
+        self.alloc(ExportFromSpecifier {
+            name: self.identifier_name(identifier),
+            exported_name: None,
+            loc,
+        })
     }
 
     // ExportSpecifier : IdentifierName `as` IdentifierName
     pub fn export_specifier_renaming(
         &self,
-        _local_name: arena::Box<'alloc, Token<'alloc>>,
-        _exported_name: arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, arena::Box<'alloc, Void>> {
-        Err(ParseError::NotImplemented("export"))
+        local_name: arena::Box<'alloc, Token<'alloc>>,
+        exported_name: arena::Box<'alloc, Token<'alloc>>,
+    ) -> arena::Box<'alloc, ExportFromSpecifier<'alloc>> {
+        let loc = SourceLocation::from_parts(local_name.loc, exported_name.loc);
+        self.alloc(ExportFromSpecifier {
+            name: self.identifier_name(local_name),
+            exported_name: Some(self.identifier_name(exported_name)),
+            loc,
+        })
     }
 
     // Check Early Error for BindingIdentifier and note binding info to the
@@ -4325,8 +6071,9 @@ impl<'alloc> AstBuilder<'alloc> {
         &mut self,
         token: &arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, ()> {
-        let context = IdentifierEarlyErrorsContext::new();
+        let context = IdentifierEarlyErrorsContext::new(self.options.strict);
         context.check_binding_identifier(token)?;
+        self.note_await_identifier(token);
 
         let name = token.value.unwrap();
         let offset = token.loc.start;
@@ -4349,14 +6096,219 @@ impl<'alloc> AstBuilder<'alloc> {
         &self,
         token: &arena::Box<'alloc, Token<'alloc>>,
     ) -> Result<'alloc, ()> {
-        let context = IdentifierEarlyErrorsContext::new();
-        context.check_identifier_reference(token)
+        let context = IdentifierEarlyErrorsContext::new(self.options.strict);
+        context.check_identifier_reference(token)?;
+        self.note_await_identifier(token);
+        Ok(())
     }
 
     // Check Early Error for LabelIdentifier.
     fn on_label_identifier(&self, token: &arena::Box<'alloc, Token<'alloc>>) -> Result<'alloc, ()> {
-        let context = IdentifierEarlyErrorsContext::new();
-        context.check_label_identifier(token)
+        let context = IdentifierEarlyErrorsContext::new(self.options.strict);
+        context.check_label_identifier(token)?;
+        self.note_await_identifier(token);
+        Ok(())
+    }
+
+    // Note the identifier `await`, to check it once it's known whether it's
+    // reserved there.
+    fn note_await_identifier(&self, token: &arena::Box<'alloc, Token<'alloc>>) {
+        if token.value == Some("await") {
+            self.awaits
+                .borrow_mut()
+                .push((token.loc.start, AwaitUse::Identifier));
+        }
+    }
+
+    // Check the uses of `await` in a function whose parameters start at
+    // `start` and whose body starts at `body_start`, which are the ones
+    // at/after `start`, and remove those that don't need checking anymore.
+    fn check_awaits(
+        &self,
+        scope: AwaitScope,
+        start: usize,
+        body_start: usize,
+    ) -> Result<'alloc, ()> {
+        let mut awaits = self.awaits.borrow_mut();
+        for (offset, use_) in awaits.iter_mut() {
+            if *offset < start {
+                continue;
+            }
+            match (scope, *use_) {
+                // Static Semantics: Early Errors
+                // https://tc39.es/ecma262/#sec-async-function-definitions-static-semantics-early-errors
+                //
+                // * It is a Syntax Error if FormalParameters Contains
+                //   AwaitExpression is true.
+                (AwaitScope::Async, AwaitUse::Expression) if *offset < body_start => {
+                    return Err(ParseError::AwaitInParameters(*offset));
+                }
+                (AwaitScope::Async, AwaitUse::Expression) => {}
+                // ArrowFunction : ArrowParameters `=>` ConciseBody
+                //
+                // * It is a Syntax Error if ArrowParameters Contains
+                //   AwaitExpression is true.
+                (AwaitScope::Arrow, AwaitUse::Expression) if *offset < body_start => {
+                    return Err(ParseError::AwaitInParameters(*offset));
+                }
+                (_, AwaitUse::Expression) => {
+                    return Err(ParseError::AwaitOutsideAsyncFunction(*offset));
+                }
+                // ClassStaticBlockBody : ClassStaticBlockStatementList
+                //
+                // * It is a Syntax Error if ClassStaticBlockStatementList
+                //   Contains await is true.
+                (AwaitScope::Async, AwaitUse::Identifier)
+                | (AwaitScope::StaticBlock, AwaitUse::Identifier) => {
+                    return Err(ParseError::InvalidIdentifier("await", *offset));
+                }
+                (AwaitScope::Function, AwaitUse::Identifier) => {
+                    *use_ = AwaitUse::IdentifierInFunction;
+                }
+                _ => {}
+            }
+        }
+        if scope == AwaitScope::Async || scope == AwaitScope::StaticBlock {
+            awaits
+                .retain(|&(offset, use_)| offset < start || use_ == AwaitUse::IdentifierInFunction);
+        }
+        Ok(())
+    }
+
+    // Check that there are no YieldExpressions in the parameters of a
+    // generator, or of the arrow functions in it; see `FindYield`.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-generator-function-definitions-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if FormalParameters Contains YieldExpression is
+    //   true.
+    fn check_yields(
+        params: &FormalParameters<'alloc>,
+        body: &FunctionBody<'alloc>,
+    ) -> Result<'alloc, ()> {
+        let mut find_yield = FindYield {
+            in_parameters: true,
+            offset: None,
+        };
+        find_yield.visit_formal_parameters(params);
+        find_yield.in_parameters = false;
+        find_yield.visit_function_body(body);
+        find_yield.check()
+    }
+
+    // Remove the `new.target` expressions at/after `start`, which are in the
+    // function that ends there.
+    fn pop_new_targets_from(&self, start: usize) {
+        self.new_targets
+            .borrow_mut()
+            .retain(|&offset| offset < start);
+    }
+
+    // Check that no `new.target` expression is left at the end of a script or
+    // module, outside any function.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if StatementList Contains NewTarget unless the
+    //   source text containing NewTarget is eval code that is being processed
+    //   by a direct eval.
+    fn check_new_targets(&self) -> Result<'alloc, ()> {
+        let mut new_targets = self.new_targets.borrow_mut();
+        let first = new_targets.first().copied();
+        new_targets.clear();
+        match first {
+            Some(offset) => Err(ParseError::NewTargetOutsideFunction(offset)),
+            None => Ok(()),
+        }
+    }
+
+    // Check that the class with `elements`, whose body starts at `start`,
+    // declares each private name once, and remove the uses of the names it
+    // declares in its body. The ClassHeritage is before the body, so the
+    // uses in it are left for the classes around it.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+    //
+    // ClassBody : ClassElementList
+    //
+    // * It is a Syntax Error if PrivateBoundIdentifiers of ClassElementList
+    //   contains any duplicate entries, unless the name is used once for a
+    //   getter and once for a setter and in no other entries, and the getter
+    //   and setter are either both static or both non-static.
+    //
+    // Only fields can have private names for now, so any duplicate is an
+    // error.
+    fn take_private_references(
+        &self,
+        elements: &[arena::Box<'alloc, ClassElement<'alloc>>],
+        start: usize,
+    ) -> Result<'alloc, ()> {
+        let mut declared: Vec<(&'alloc str, usize)> = Vec::new();
+        for element in elements {
+            if let ClassElement::FieldDefinition {
+                name: ClassElementName::PrivateFieldName(PrivateIdentifier { value, loc, .. }),
+                ..
+            } = &**element
+            {
+                if let Some(&(_, first)) = declared.iter().find(|&&(name, _)| name == *value) {
+                    return Err(ParseError::DuplicatePrivateName(value, first, loc.start));
+                }
+                declared.push((value, loc.start));
+            }
+        }
+        self.private_references
+            .borrow_mut()
+            .retain(|&(name, offset)| {
+                offset < start || !declared.iter().any(|&(declared, _)| declared == name)
+            });
+        Ok(())
+    }
+
+    // Check that no use of a private name is left at the end of a script or
+    // module, outside the classes that declare it.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if AllPrivateIdentifiersValid of StatementList
+    //   with argument « » is false unless the source text containing
+    //   ScriptBody is eval code that is being processed by a direct eval.
+    fn check_private_references(&self) -> Result<'alloc, ()> {
+        let mut references = self.private_references.borrow_mut();
+        let first = references.first().copied();
+        references.clear();
+        match first {
+            Some((name, offset)) => Err(ParseError::UndefinedPrivateName(name, offset)),
+            None => Ok(()),
+        }
+    }
+
+    // Check the uses of `await` left at the end of a script, which aren't in
+    // any async function.
+    fn check_script_awaits(&self) -> Result<'alloc, ()> {
+        for &(offset, use_) in self.awaits.borrow().iter() {
+            if use_ == AwaitUse::Expression {
+                return Err(ParseError::AwaitOutsideAsyncFunction(offset));
+            }
+        }
+        Ok(())
+    }
+
+    // Check the uses of `await` left at the end of a module. AwaitExpressions
+    // are allowed at its top level, but `await` is always reserved in it.
+    fn check_module_awaits(&self) -> Result<'alloc, ()> {
+        for &(offset, use_) in self.awaits.borrow().iter() {
+            if use_ == AwaitUse::Expression {
+                self.options
+                    .check_target("top-level await", EcmaVersion::Es2022, offset)?;
+            } else {
+                return Err(ParseError::InvalidIdentifier("await", offset));
+            }
+        }
+        Ok(())
     }
 
     // Update the binding kind of all names declared in a specific range of the
@@ -4381,6 +6333,150 @@ impl<'alloc> AstBuilder<'alloc> {
         }
     }
 
+    // Check that the names bound between `from` and `to` by a lexical
+    // declaration, the last one noted, aren't `let`.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-let-and-const-declarations-static-semantics-early-errors
+    //
+    // LexicalDeclaration : LetOrConst BindingList `;`
+    //
+    // * It is a Syntax Error if the BoundNames of BindingList contains
+    //   "let".
+    fn check_let_not_bound(&self, from: usize, to: usize) -> Result<'alloc, ()> {
+        let found = self
+            .bindings
+            .iter()
+            .rev()
+            .take_while(|info| info.offset >= from)
+            .filter(|info| info.offset < to && info.name == "let")
+            .last();
+        match found {
+            Some(info) => Err(ParseError::InvalidIdentifier(info.name, info.offset)),
+            None => Ok(()),
+        }
+    }
+
+    // Note a name exported from the module, to detect duplicate exports.
+    fn add_exported_name(&mut self, name: &'alloc str, offset: usize) {
+        self.exported_names.push((name, offset));
+    }
+
+    // Note a local binding referred by `export { ... };`, to detect exports
+    // of undeclared bindings.
+    fn add_exported_binding(&mut self, name: &'alloc str, offset: usize) {
+        self.exported_bindings.push((name, offset));
+    }
+
+    // Note the label of a LabelledStatement, after its body.
+    fn declare_label(&mut self, name: &'alloc str, offset: usize) -> Result<'alloc, ()> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-labelled-statements-static-semantics-early-errors
+        //
+        // LabelledStatement : LabelIdentifier `:` LabelledItem
+        //
+        // * It is a Syntax Error if any source text is matched by this
+        //   production, and the label set of LabelledItem contains the
+        //   StringValue of LabelIdentifier.
+        //
+        // The labels noted after this one are the ones in its body.
+        for &(nested_name, nested_offset) in self.labels.iter().rev() {
+            if nested_offset < offset {
+                break;
+            }
+            if nested_name == name {
+                return Err(ParseError::DuplicateLabel(name, offset, nested_offset));
+            }
+        }
+        self.labels.push((name, offset));
+
+        Ok(())
+    }
+
+    // Remove the labels at/after `offset` source position.
+    //
+    // This should be called when leaving function, since labels can't be
+    // used across function boundaries.
+    fn pop_labels_from(&mut self, offset: usize) {
+        while let Some(&(_, label_offset)) = self.labels.last() {
+            if label_offset < offset {
+                break;
+            }
+            self.labels.pop();
+        }
+    }
+
+    // Remove the unlabelled jumps at/after `start`, which is the start of an
+    // IterationStatement: they're in its body, and it's their target.
+    fn take_loop_jumps(&mut self, start: usize) {
+        self.jumps
+            .retain(|&(_, label, offset)| offset < start || label.is_some());
+    }
+
+    // Remove the jumps at/after `start` that target the LabelledStatement
+    // starting there, with the label `name`. `is_iteration` is true if it
+    // labels an IterationStatement.
+    fn take_labelled_jumps(
+        &mut self,
+        name: &'alloc str,
+        start: usize,
+        is_iteration: bool,
+    ) -> Result<'alloc, ()> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-continue-statement-static-semantics-early-errors
+        //
+        // ContinueStatement : `continue` LabelIdentifier `;`
+        //
+        // * It is a Syntax Error if this ContinueStatement is not nested,
+        //   directly or indirectly (but not crossing function or static
+        //   initialization block boundaries), within an IterationStatement.
+        //
+        // and ContainsUndefinedContinueTarget doesn't allow a label that
+        // isn't in the label set of an IterationStatement around it.
+        let mut index = 0;
+        while index < self.jumps.len() {
+            match self.jumps[index] {
+                (kind, Some((label, label_offset)), offset) if offset >= start && label == name => {
+                    if kind == JumpKind::Continue && !is_iteration {
+                        return Err(ParseError::ContinueToNonLoopLabel(name, label_offset));
+                    }
+                    self.jumps.remove(index);
+                }
+                _ => index += 1,
+            }
+        }
+        Ok(())
+    }
+
+    // Check that no jump at/after `start` is left without a target. This
+    // should be called when leaving a function, since jumps can't cross
+    // function boundaries, and at the end of the script or module.
+    fn check_jumps_from(&mut self, start: usize) -> Result<'alloc, ()> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-break-statement-static-semantics-early-errors
+        //
+        // BreakStatement : `break` `;`
+        //
+        // * It is a Syntax Error if this BreakStatement is not nested,
+        //   directly or indirectly (but not crossing function or static
+        //   initialization block boundaries), within an IterationStatement
+        //   or a SwitchStatement.
+        //
+        // and ContainsUndefinedBreakTarget and
+        // ContainsUndefinedContinueTarget of the function body or the script
+        // or module for the labelled ones.
+        if let Some(&(kind, label, offset)) =
+            self.jumps.iter().find(|&&(_, _, offset)| offset >= start)
+        {
+            return Err(match (kind, label) {
+                (_, Some((name, label_offset))) => ParseError::UndefinedLabel(name, label_offset),
+                (JumpKind::Break, None) => ParseError::BreakOutsideLoop(offset),
+                (JumpKind::Continue, None) => ParseError::ContinueOutsideLoop(offset),
+            });
+        }
+        Ok(())
+    }
+
     // Returns the index of the first binding at/after `offset` source position.
     fn find_first_binding(&mut self, offset: usize) -> usize {
         let mut i = self.bindings.len();
@@ -4523,6 +6619,7 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // Check bindings in lexical for-statement.
     fn check_lexical_for_bindings(&mut self, bindings_loc: &SourceLocation) -> Result<'alloc, ()> {
+        self.check_let_not_bound(bindings_loc.start, bindings_loc.end)?;
         let mut head_context = LexicalForHeadEarlyErrorsContext::new();
 
         let head_index = self.find_first_binding(bindings_loc.start);
@@ -4637,7 +6734,18 @@ impl<'alloc> AstBuilder<'alloc> {
         start_of_param_offset: usize,
         end_of_param_offset: usize,
     ) -> Result<'alloc, ()> {
-        let mut param_context = if is_simple {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-function-definitions-static-semantics-early-errors
+        //
+        // FormalParameters : FormalParameterList
+        //
+        // * If the source text matched by FormalParameters is strict mode
+        //   code, the Early Error rules for UniqueFormalParameters :
+        //   FormalParameters are applied.
+        //
+        // The parameters are checked as non-simple ones, which can't have
+        // duplicate names either.
+        let mut param_context = if is_simple && !self.options.strict {
             FormalParametersEarlyErrorsContext::new_simple()
         } else {
             FormalParametersEarlyErrorsContext::new_non_simple()
@@ -4650,6 +6758,8 @@ impl<'alloc> AstBuilder<'alloc> {
         let mut body_context = FunctionBodyEarlyErrorsContext::new(param_context);
         self.declare_script_or_function(&mut body_context, body_index)?;
         self.pop_bindings_from(param_index);
+        self.pop_labels_from(start_of_param_offset);
+        self.check_jumps_from(start_of_param_offset)?;
 
         Ok(())
     }
@@ -4669,47 +6779,110 @@ impl<'alloc> AstBuilder<'alloc> {
         let mut body_context = UniqueFunctionBodyEarlyErrorsContext::new(param_context);
         self.declare_script_or_function(&mut body_context, body_index)?;
         self.pop_bindings_from(param_index);
+        self.pop_labels_from(start_of_param_offset);
+        self.check_jumps_from(start_of_param_offset)?;
 
         Ok(())
     }
 
-    // Check bindings in Script.
-    fn check_script_bindings(&mut self) -> Result<'alloc, ()> {
-        let mut context = ScriptEarlyErrorsContext::new();
-        self.declare_script_or_function(&mut context, 0)?;
-        self.pop_bindings_from(0);
+    // Check bindings in ArrowFunction and AsyncArrowFunction.
+    //
+    // Parameters written as an expression, as in `(a, b) => a`, are refined
+    // into FormalParameters only when the parser reaches `=>`, so their names
+    // are never noted to the bindings stack. The names of the parameters are
+    // taken from `params` instead.
+    fn check_arrow_function_bindings(
+        &mut self,
+        params: &FormalParameters<'alloc>,
+    ) -> Result<'alloc, ()> {
+        let mut param_context = UniqueFormalParametersEarlyErrorsContext::new();
+        for item in params.items.iter() {
+            declare_bound_names_of_parameter(&mut param_context, item)?;
+        }
+        if let Some(rest) = &params.rest {
+            declare_bound_names_of_binding(&mut param_context, rest)?;
+        }
+
+        let param_index = self.find_first_binding(params.loc.start);
+        let body_index = self.find_first_binding(params.loc.end);
+
+        let mut body_context = UniqueFunctionBodyEarlyErrorsContext::new(param_context);
+        self.declare_script_or_function(&mut body_context, body_index)?;
+        self.pop_bindings_from(param_index);
+        self.pop_labels_from(params.loc.start);
+        self.check_jumps_from(params.loc.start)?;
 
         Ok(())
     }
 
-    // Check bindings in Module.
-    fn check_module_bindings(&mut self) -> Result<'alloc, ()> {
-        let mut context = ModuleEarlyErrorsContext::new();
+    // Check bindings in Script.
+    fn check_script_bindings(&mut self) -> Result<'alloc, ()> {
+        let mut context = ScriptEarlyErrorsContext::new();
         self.declare_script_or_function(&mut context, 0)?;
+        if let Some(kept) = &mut self.script_bindings {
+            kept.clone_from(&self.bindings);
+        }
         self.pop_bindings_from(0);
 
         Ok(())
     }
 
-    // Returns IsSimpleParameterList of `params`.
-    //
-    // NOTE: For Syntax-only parsing (NYI), the stack value for FormalParameters
-    //       should contain this information.
-    fn is_params_simple(params: &FormalParameters<'alloc>) -> bool {
-        for param in params.items.iter() {
-            match param {
-                Parameter::Binding(Binding::BindingIdentifier(_)) => {}
+    // Declare bindings to Module, where function declarations are lexical.
+    fn declare_module(
+        &self,
+        context: &mut ModuleEarlyErrorsContext<'alloc>,
+        index: usize,
+    ) -> Result<'alloc, ()> {
+        for info in self.bindings.iter().skip(index) {
+            match info.kind {
+                BindingKind::Var => {
+                    context.declare_var(info.name, DeclarationKind::Var, info.offset)?;
+                }
+                BindingKind::Function | BindingKind::AsyncOrGenerator => {
+                    context.declare_lex(
+                        info.name,
+                        DeclarationKind::BodyLevelFunction,
+                        info.offset,
+                    )?;
+                }
+                BindingKind::Let => {
+                    context.declare_lex(info.name, DeclarationKind::Let, info.offset)?;
+                }
+                BindingKind::Const => {
+                    context.declare_lex(info.name, DeclarationKind::Const, info.offset)?;
+                }
+                BindingKind::Class => {
+                    context.declare_lex(info.name, DeclarationKind::Class, info.offset)?;
+                }
+                BindingKind::Import => {
+                    context.declare_lex(info.name, DeclarationKind::Import, info.offset)?;
+                }
                 _ => {
-                    return false;
+                    panic!("Unexpected binding found {:?}", info);
                 }
             }
         }
 
-        if params.rest.is_some() {
-            return false;
+        for &(name, offset) in &self.exported_names {
+            context.add_exported_name(name, offset)?;
+        }
+        for &(name, offset) in &self.exported_bindings {
+            context.add_exported_binding(name, offset);
         }
+        context.check_exported_name()?;
+
+        Ok(())
+    }
 
-        true
+    // Check bindings in Module.
+    fn check_module_bindings(&mut self) -> Result<'alloc, ()> {
+        let mut context = ModuleEarlyErrorsContext::new();
+        self.declare_module(&mut context, 0)?;
+        self.pop_bindings_from(0);
+        self.exported_names.clear();
+        self.exported_bindings.clear();
+
+        Ok(())
     }
 
     // Static Semantics: Early Errors
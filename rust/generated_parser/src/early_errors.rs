@@ -51,20 +51,25 @@ pub trait ParameterEarlyErrorsContext<'alloc> {
 
 #[derive(Debug, PartialEq)]
 pub struct IdentifierEarlyErrorsContext<'alloc> {
+    // True if the code is known to be strict mode code, e.g. because strict
+    // mode was forced by ParserOptions.
+    strict: bool,
+
     phantom: PhantomData<&'alloc ()>,
 }
 
 impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
-    pub fn new() -> Self {
+    pub fn new(strict: bool) -> Self {
         Self {
+            strict,
             phantom: PhantomData,
         }
     }
 
+    // Other code is taken to be sloppy mode code here. If it turns out to be
+    // strict, the AstBuilder's `MarkStrict` finds the errors it then has.
     fn is_strict(&self) -> Result<bool, ParseError<'alloc>> {
-        Err(ParseError::NotImplemented(
-            "strict-mode-only early error is not yet supported",
-        ))
+        Ok(self.strict)
     }
 
     // Not used due to NotImplemented before the callsite.
@@ -137,9 +142,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
             //
             // * It is a Syntax Error if this production has an [Await]
             //   parameter.
-            return Err(ParseError::NotImplemented("[Await] parameter"));
-
-            // return self.check_await_common();
+            return self.check_await_common(token);
         }
 
         self.check_identifier(token)
@@ -235,8 +238,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         //
         // * It is a Syntax Error if this production has an [Await] parameter
         //   and StringValue of Identifier is "await".
-        return Err(ParseError::NotImplemented("[Await] parameter"));
-
+        //
         // IdentifierReference : await
         //
         // BindingIdentifier : await
@@ -253,14 +255,10 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         // * It is a Syntax Error if the goal symbol of the syntactic grammar
         //   is Module and the StringValue of IdentifierName is "await".
         //
-        // if self.is_module()? {
-        //     return Err(ParseError::InvalidIdentifier(
-        //         token.value.unwrap().clone(),
-        //         offset,
-        //     ));
-        // }
-        //
-        // Ok(())
+        // Neither the [Await] parameter nor the goal symbol is known yet, so
+        // AstBuilder notes the identifier, and checks it when the function,
+        // script or module it is in is reduced.
+        Ok(())
     }
 
     fn check_identifier(
@@ -1983,7 +1981,6 @@ impl<'alloc> ModuleEarlyErrorsContext<'alloc> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn add_exported_name(
         &mut self,
         name: Name<'alloc>,
@@ -2009,12 +2006,10 @@ impl<'alloc> ModuleEarlyErrorsContext<'alloc> {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn add_exported_binding(&mut self, name: Name<'alloc>, offset: usize) {
         self.exported_bindings_of_item_list.insert(name, offset);
     }
 
-    #[allow(dead_code)]
     pub fn check_exported_name(&self) -> EarlyErrorsResult<'alloc> {
         // Static Semantics: Early Errors
         // https://tc39.es/ecma262/#sec-module-semantics-static-semantics-early-errors
@@ -1,5 +1,6 @@
 use crate::stack_value_generated::AstError;
 use crate::DeclarationKind;
+use crate::EcmaVersion;
 use crate::Token;
 use std::{convert::Infallible, error::Error, fmt, io};
 
@@ -8,12 +9,27 @@ pub enum ParseError<'alloc> {
     IOError(io::Error),
 
     // Lexical errors
-    IllegalCharacter(char),
-    InvalidEscapeSequence,
-    UnterminatedString,
-    UnterminatedRegExp,
-    UnterminatedMultiLineComment,
+    IllegalCharacter(char, usize),
+    InvalidEscapeSequence(usize),
+    /// A string or template literal cut short, from its opening quote or
+    /// backquote, or the `}` before the rest of a template, to the end of
+    /// the line or of the source.
+    UnterminatedString(usize, usize),
+    UnterminatedRegExp(usize),
+    /// A regexp flag other than `d`, `g`, `i`, `m`, `s`, `u` and `y`.
+    InvalidRegExpFlag(char, usize),
+    DuplicateRegExpFlag(char, usize),
+    /// A regexp body that isn't a valid pattern, such as `/(a/`.
+    InvalidRegExpPattern(&'static str, usize),
+    UnterminatedMultiLineComment(usize),
+    /// A legacy octal literal, or a decimal one starting with `0`, in
+    /// strict mode code, from its start to its end.
+    OctalLiteralInStrictMode(usize, usize),
+    OctalEscapeInStrictMode(usize),
     LexerError,
+    /// The source bytes aren't valid in the encoding named, at this byte
+    /// offset. See `parser::decode_source`.
+    InvalidEncoding(&'static str, usize),
 
     // Generic syntax errors
     NotImplemented(&'static str),
@@ -23,6 +39,54 @@ pub enum ParseError<'alloc> {
     InvalidParameter,
     InvalidIdentifier(&'alloc str, usize),
     AstError(String),
+    FeatureNotAvailable(&'static str, EcmaVersion, usize),
+    WithInStrictMode(usize),
+    /// `??` next to `||` or `&&`, as in `a ?? b || c`, which needs
+    /// parentheses around one of them.
+    CoalesceWithLogicalOperator(usize),
+    /// A call of `import.meta` itself, as in `import.meta()`.
+    ImportMetaCall(usize),
+    /// An `import.meta` in a script, as when a module is parsed as a script,
+    /// at the offset of its `import`.
+    ImportMetaOutsideModule(usize),
+    /// An opening parenthesis, bracket, brace or template substitution
+    /// nested deeper than `ParserOptions::max_nesting_depth`, or the token
+    /// before which an expression or statement nested deeper than
+    /// `ParserOptions::max_node_depth` was made.
+    TooDeeplyNested(usize),
+    /// An AwaitExpression outside of async functions and the top level of
+    /// modules, as in `function f() { await x; }`.
+    AwaitOutsideAsyncFunction(usize),
+    /// An AwaitExpression in the parameters of an async function, as in
+    /// `async function f(a = await b) {}`, or of an arrow function.
+    AwaitInParameters(usize),
+    /// A `new.target` outside of functions, as in `x = new.target;` at the
+    /// top level of a script, at the offset of its `new`.
+    NewTargetOutsideFunction(usize),
+    /// A unary expression as the left operand of `**`, as in `-a ** b`,
+    /// which needs parentheses, either `(-a) ** b` or `-(a ** b)`.
+    UnaryBeforeExponentiation(usize),
+    /// Parentheses that can only be the parameters of an arrow function,
+    /// such as `(a,)`, `(...a)` or `()`, not followed by `=>`, at the
+    /// offset after them.
+    ExpectedArrow(usize),
+    /// A YieldExpression in the parameters of a generator, or of an arrow
+    /// function in one, as in `function* g() { (a = yield) => a; }`.
+    YieldInParameters(usize),
+    /// An `import` or `export` declaration, given by its keyword, at the top
+    /// level of a script, as when a module is parsed as a script.
+    ModuleDeclarationInScript(&'static str, usize),
+    /// A `delete` of a name, as in `delete x`, in strict mode code, at the
+    /// offset of the `delete`.
+    DeleteOfIdentifierInStrictMode(usize),
+    /// A "use strict" directive in a function whose parameters aren't
+    /// simple, as in `function f(a = 1) { "use strict"; }`, at the offset of
+    /// the directive.
+    UseStrictWithNonSimpleParameters(usize),
+    /// A FunctionDeclaration as the item of a LabelledStatement, as in
+    /// `a: function f() {}`, in strict mode code, at the offset of the
+    /// label.
+    LabelledFunctionDeclInStrictMode(usize),
 
     // Destructuring errors
     ArrayPatternWithNonFinalRest,
@@ -30,6 +94,9 @@ pub enum ParseError<'alloc> {
     ObjectPatternWithMethod,
     ObjectPatternWithNonFinalRest,
     ObjectBindingPatternWithInvalidRest,
+    /// A default value in an object literal that isn't a pattern, as in
+    /// `({ a = 1 })`, at the offset of the value.
+    InitializerInObjectLiteral(usize),
 
     // 14.8 Async arrow function definitions
     ArrowHeadInvalid,
@@ -37,7 +104,32 @@ pub enum ParseError<'alloc> {
 
     DuplicateBinding(&'alloc str, DeclarationKind, usize, DeclarationKind, usize),
     DuplicateExport(&'alloc str, usize, usize),
+    /// An import attribute key given twice, as in
+    /// `with { type: "json", type: "css" }`.
+    DuplicateImportAttribute(&'alloc str, usize, usize),
     MissingExport(&'alloc str, usize),
+    /// A label used again inside the statement it labels, as in
+    /// `a: { a: ; }`.
+    DuplicateLabel(&'alloc str, usize, usize),
+    /// A `break` or `continue` to a label that isn't on a statement around
+    /// it in the same function, as in `a: ; break a;`, at the offset of the
+    /// label in the jump.
+    UndefinedLabel(&'alloc str, usize),
+    /// A `continue` to the label of a statement that isn't a loop, as in
+    /// `a: { continue a; }`, at the offset of the label in the jump.
+    ContinueToNonLoopLabel(&'alloc str, usize),
+    /// A `break` without a label that isn't in a loop or `switch` in the
+    /// same function.
+    BreakOutsideLoop(usize),
+    /// A `continue` without a label that isn't in a loop in the same
+    /// function.
+    ContinueOutsideLoop(usize),
+    /// A private name declared twice in the same class, as in
+    /// `class C { #a; #a; }`, at the offsets of the two declarations.
+    DuplicatePrivateName(&'alloc str, usize, usize),
+    /// A private name used where no class around it declares it, as in
+    /// `class C { m() { this.#a; } }`, at the offset of the use.
+    UndefinedPrivateName(&'alloc str, usize),
 
     // Annex B. FunctionDeclarations in IfStatement Statement Clauses
     // https://tc39.es/ecma262/#sec-functiondeclarations-in-ifstatement-statement-clauses
@@ -45,16 +137,332 @@ pub enum ParseError<'alloc> {
     LabelledFunctionDeclInSingleStatement,
 }
 
+/// The kind of a `ParseError`, without its details. This is stable across
+/// versions, so it can be used to group errors, e.g. across a corpus, and
+/// tools should match on it rather than on `ParseError::message()`, whose
+/// wording may change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ParseErrorKind {
+    IOError,
+
+    // Lexical errors
+    IllegalCharacter,
+    InvalidEscapeSequence,
+    UnterminatedString,
+    UnterminatedRegExp,
+    InvalidRegExpFlag,
+    DuplicateRegExpFlag,
+    InvalidRegExpPattern,
+    UnterminatedMultiLineComment,
+    OctalLiteralInStrictMode,
+    OctalEscapeInStrictMode,
+    LexerError,
+    InvalidEncoding,
+
+    // Generic syntax errors
+    NotImplemented,
+    SyntaxError,
+    UnexpectedEnd,
+    InvalidAssignmentTarget,
+    InvalidParameter,
+    InvalidIdentifier,
+    AstError,
+    FeatureNotAvailable,
+    WithInStrictMode,
+    CoalesceWithLogicalOperator,
+    ImportMetaCall,
+
+    // Destructuring errors
+    ArrayPatternWithNonFinalRest,
+    ArrayBindingPatternWithInvalidRest,
+    ObjectPatternWithMethod,
+    ObjectPatternWithNonFinalRest,
+    ObjectBindingPatternWithInvalidRest,
+
+    // 14.8 Async arrow function definitions
+    ArrowHeadInvalid,
+    ArrowParametersWithNonFinalRest,
+
+    DuplicateBinding,
+    DuplicateExport,
+    DuplicateImportAttribute,
+    MissingExport,
+
+    // Annex B. FunctionDeclarations in IfStatement Statement Clauses
+    // https://tc39.es/ecma262/#sec-functiondeclarations-in-ifstatement-statement-clauses
+    FunctionDeclInSingleStatement,
+    LabelledFunctionDeclInSingleStatement,
+
+    // Added after the ones above, which keep their order.
+    DuplicateLabel,
+    TooDeeplyNested,
+    InitializerInObjectLiteral,
+    AwaitOutsideAsyncFunction,
+    AwaitInParameters,
+    UnaryBeforeExponentiation,
+    UndefinedLabel,
+    ContinueToNonLoopLabel,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ExpectedArrow,
+    YieldInParameters,
+    NewTargetOutsideFunction,
+    ModuleDeclarationInScript,
+    DeleteOfIdentifierInStrictMode,
+    UseStrictWithNonSimpleParameters,
+    DuplicatePrivateName,
+    UndefinedPrivateName,
+    ImportMetaOutsideModule,
+    LabelledFunctionDeclInStrictMode,
+}
+
+impl ParseErrorKind {
+    /// Every kind, in declaration order.
+    pub const ALL: &'static [ParseErrorKind] = &[
+        Self::IOError,
+        Self::IllegalCharacter,
+        Self::InvalidEscapeSequence,
+        Self::UnterminatedString,
+        Self::UnterminatedRegExp,
+        Self::InvalidRegExpFlag,
+        Self::DuplicateRegExpFlag,
+        Self::InvalidRegExpPattern,
+        Self::UnterminatedMultiLineComment,
+        Self::OctalLiteralInStrictMode,
+        Self::OctalEscapeInStrictMode,
+        Self::LexerError,
+        Self::InvalidEncoding,
+        Self::NotImplemented,
+        Self::SyntaxError,
+        Self::UnexpectedEnd,
+        Self::InvalidAssignmentTarget,
+        Self::InvalidParameter,
+        Self::InvalidIdentifier,
+        Self::AstError,
+        Self::FeatureNotAvailable,
+        Self::WithInStrictMode,
+        Self::CoalesceWithLogicalOperator,
+        Self::ImportMetaCall,
+        Self::ArrayPatternWithNonFinalRest,
+        Self::ArrayBindingPatternWithInvalidRest,
+        Self::ObjectPatternWithMethod,
+        Self::ObjectPatternWithNonFinalRest,
+        Self::ObjectBindingPatternWithInvalidRest,
+        Self::ArrowHeadInvalid,
+        Self::ArrowParametersWithNonFinalRest,
+        Self::DuplicateBinding,
+        Self::DuplicateExport,
+        Self::DuplicateImportAttribute,
+        Self::MissingExport,
+        Self::FunctionDeclInSingleStatement,
+        Self::LabelledFunctionDeclInSingleStatement,
+        Self::DuplicateLabel,
+        Self::TooDeeplyNested,
+        Self::InitializerInObjectLiteral,
+        Self::AwaitOutsideAsyncFunction,
+        Self::AwaitInParameters,
+        Self::UnaryBeforeExponentiation,
+        Self::UndefinedLabel,
+        Self::ContinueToNonLoopLabel,
+        Self::BreakOutsideLoop,
+        Self::ContinueOutsideLoop,
+        Self::ExpectedArrow,
+        Self::YieldInParameters,
+        Self::NewTargetOutsideFunction,
+        Self::ModuleDeclarationInScript,
+        Self::DeleteOfIdentifierInStrictMode,
+        Self::UseStrictWithNonSimpleParameters,
+        Self::DuplicatePrivateName,
+        Self::UndefinedPrivateName,
+        Self::ImportMetaOutsideModule,
+        Self::LabelledFunctionDeclInStrictMode,
+    ];
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::IOError => "IOError",
+            Self::IllegalCharacter => "IllegalCharacter",
+            Self::InvalidEscapeSequence => "InvalidEscapeSequence",
+            Self::UnterminatedString => "UnterminatedString",
+            Self::UnterminatedRegExp => "UnterminatedRegExp",
+            Self::InvalidRegExpFlag => "InvalidRegExpFlag",
+            Self::DuplicateRegExpFlag => "DuplicateRegExpFlag",
+            Self::InvalidRegExpPattern => "InvalidRegExpPattern",
+            Self::UnterminatedMultiLineComment => "UnterminatedMultiLineComment",
+            Self::OctalLiteralInStrictMode => "OctalLiteralInStrictMode",
+            Self::OctalEscapeInStrictMode => "OctalEscapeInStrictMode",
+            Self::LexerError => "LexerError",
+            Self::InvalidEncoding => "InvalidEncoding",
+            Self::NotImplemented => "NotImplemented",
+            Self::SyntaxError => "SyntaxError",
+            Self::UnexpectedEnd => "UnexpectedEnd",
+            Self::InvalidAssignmentTarget => "InvalidAssignmentTarget",
+            Self::InvalidParameter => "InvalidParameter",
+            Self::InvalidIdentifier => "InvalidIdentifier",
+            Self::AstError => "AstError",
+            Self::FeatureNotAvailable => "FeatureNotAvailable",
+            Self::WithInStrictMode => "WithInStrictMode",
+            Self::CoalesceWithLogicalOperator => "CoalesceWithLogicalOperator",
+            Self::ImportMetaCall => "ImportMetaCall",
+            Self::ArrayPatternWithNonFinalRest => "ArrayPatternWithNonFinalRest",
+            Self::ArrayBindingPatternWithInvalidRest => "ArrayBindingPatternWithInvalidRest",
+            Self::ObjectPatternWithMethod => "ObjectPatternWithMethod",
+            Self::ObjectPatternWithNonFinalRest => "ObjectPatternWithNonFinalRest",
+            Self::ObjectBindingPatternWithInvalidRest => "ObjectBindingPatternWithInvalidRest",
+            Self::ArrowHeadInvalid => "ArrowHeadInvalid",
+            Self::ArrowParametersWithNonFinalRest => "ArrowParametersWithNonFinalRest",
+            Self::DuplicateBinding => "DuplicateBinding",
+            Self::DuplicateExport => "DuplicateExport",
+            Self::DuplicateImportAttribute => "DuplicateImportAttribute",
+            Self::MissingExport => "MissingExport",
+            Self::FunctionDeclInSingleStatement => "FunctionDeclInSingleStatement",
+            Self::LabelledFunctionDeclInSingleStatement => "LabelledFunctionDeclInSingleStatement",
+            Self::DuplicateLabel => "DuplicateLabel",
+            Self::TooDeeplyNested => "TooDeeplyNested",
+            Self::InitializerInObjectLiteral => "InitializerInObjectLiteral",
+            Self::AwaitOutsideAsyncFunction => "AwaitOutsideAsyncFunction",
+            Self::AwaitInParameters => "AwaitInParameters",
+            Self::UnaryBeforeExponentiation => "UnaryBeforeExponentiation",
+            Self::UndefinedLabel => "UndefinedLabel",
+            Self::ContinueToNonLoopLabel => "ContinueToNonLoopLabel",
+            Self::BreakOutsideLoop => "BreakOutsideLoop",
+            Self::ContinueOutsideLoop => "ContinueOutsideLoop",
+            Self::ExpectedArrow => "ExpectedArrow",
+            Self::YieldInParameters => "YieldInParameters",
+            Self::NewTargetOutsideFunction => "NewTargetOutsideFunction",
+            Self::ModuleDeclarationInScript => "ModuleDeclarationInScript",
+            Self::DeleteOfIdentifierInStrictMode => "DeleteOfIdentifierInStrictMode",
+            Self::UseStrictWithNonSimpleParameters => "UseStrictWithNonSimpleParameters",
+            Self::DuplicatePrivateName => "DuplicatePrivateName",
+            Self::UndefinedPrivateName => "UndefinedPrivateName",
+            Self::ImportMetaOutsideModule => "ImportMetaOutsideModule",
+            Self::LabelledFunctionDeclInStrictMode => "LabelledFunctionDeclInStrictMode",
+        }
+    }
+}
+
 impl<'alloc> ParseError<'alloc> {
+    pub fn kind(&self) -> ParseErrorKind {
+        match self {
+            ParseError::IOError(..) => ParseErrorKind::IOError,
+            ParseError::IllegalCharacter(..) => ParseErrorKind::IllegalCharacter,
+            ParseError::InvalidEscapeSequence(..) => ParseErrorKind::InvalidEscapeSequence,
+            ParseError::UnterminatedString(..) => ParseErrorKind::UnterminatedString,
+            ParseError::UnterminatedRegExp(..) => ParseErrorKind::UnterminatedRegExp,
+            ParseError::InvalidRegExpFlag(..) => ParseErrorKind::InvalidRegExpFlag,
+            ParseError::DuplicateRegExpFlag(..) => ParseErrorKind::DuplicateRegExpFlag,
+            ParseError::InvalidRegExpPattern(..) => ParseErrorKind::InvalidRegExpPattern,
+            ParseError::UnterminatedMultiLineComment(..) => {
+                ParseErrorKind::UnterminatedMultiLineComment
+            }
+            ParseError::OctalLiteralInStrictMode(..) => ParseErrorKind::OctalLiteralInStrictMode,
+            ParseError::OctalEscapeInStrictMode(..) => ParseErrorKind::OctalEscapeInStrictMode,
+            ParseError::LexerError => ParseErrorKind::LexerError,
+            ParseError::InvalidEncoding(..) => ParseErrorKind::InvalidEncoding,
+            ParseError::NotImplemented(..) => ParseErrorKind::NotImplemented,
+            ParseError::SyntaxError(..) => ParseErrorKind::SyntaxError,
+            ParseError::UnexpectedEnd => ParseErrorKind::UnexpectedEnd,
+            ParseError::InvalidAssignmentTarget => ParseErrorKind::InvalidAssignmentTarget,
+            ParseError::InvalidParameter => ParseErrorKind::InvalidParameter,
+            ParseError::InvalidIdentifier(..) => ParseErrorKind::InvalidIdentifier,
+            ParseError::AstError(..) => ParseErrorKind::AstError,
+            ParseError::FeatureNotAvailable(..) => ParseErrorKind::FeatureNotAvailable,
+            ParseError::WithInStrictMode(..) => ParseErrorKind::WithInStrictMode,
+            ParseError::CoalesceWithLogicalOperator(..) => {
+                ParseErrorKind::CoalesceWithLogicalOperator
+            }
+            ParseError::ImportMetaCall(..) => ParseErrorKind::ImportMetaCall,
+            ParseError::ImportMetaOutsideModule(..) => ParseErrorKind::ImportMetaOutsideModule,
+            ParseError::TooDeeplyNested(..) => ParseErrorKind::TooDeeplyNested,
+            ParseError::AwaitOutsideAsyncFunction(..) => ParseErrorKind::AwaitOutsideAsyncFunction,
+            ParseError::AwaitInParameters(..) => ParseErrorKind::AwaitInParameters,
+            ParseError::UnaryBeforeExponentiation(..) => ParseErrorKind::UnaryBeforeExponentiation,
+            ParseError::ExpectedArrow(..) => ParseErrorKind::ExpectedArrow,
+            ParseError::YieldInParameters(..) => ParseErrorKind::YieldInParameters,
+            ParseError::NewTargetOutsideFunction(..) => ParseErrorKind::NewTargetOutsideFunction,
+            ParseError::ModuleDeclarationInScript(..) => ParseErrorKind::ModuleDeclarationInScript,
+            ParseError::DeleteOfIdentifierInStrictMode(..) => {
+                ParseErrorKind::DeleteOfIdentifierInStrictMode
+            }
+            ParseError::UseStrictWithNonSimpleParameters(..) => {
+                ParseErrorKind::UseStrictWithNonSimpleParameters
+            }
+            ParseError::LabelledFunctionDeclInStrictMode(..) => {
+                ParseErrorKind::LabelledFunctionDeclInStrictMode
+            }
+            ParseError::ArrayPatternWithNonFinalRest => {
+                ParseErrorKind::ArrayPatternWithNonFinalRest
+            }
+            ParseError::ArrayBindingPatternWithInvalidRest => {
+                ParseErrorKind::ArrayBindingPatternWithInvalidRest
+            }
+            ParseError::ObjectPatternWithMethod => ParseErrorKind::ObjectPatternWithMethod,
+            ParseError::ObjectPatternWithNonFinalRest => {
+                ParseErrorKind::ObjectPatternWithNonFinalRest
+            }
+            ParseError::ObjectBindingPatternWithInvalidRest => {
+                ParseErrorKind::ObjectBindingPatternWithInvalidRest
+            }
+            ParseError::InitializerInObjectLiteral(..) => {
+                ParseErrorKind::InitializerInObjectLiteral
+            }
+            ParseError::ArrowHeadInvalid => ParseErrorKind::ArrowHeadInvalid,
+            ParseError::ArrowParametersWithNonFinalRest => {
+                ParseErrorKind::ArrowParametersWithNonFinalRest
+            }
+            ParseError::DuplicateBinding(..) => ParseErrorKind::DuplicateBinding,
+            ParseError::DuplicateExport(..) => ParseErrorKind::DuplicateExport,
+            ParseError::DuplicateImportAttribute(..) => ParseErrorKind::DuplicateImportAttribute,
+            ParseError::MissingExport(..) => ParseErrorKind::MissingExport,
+            ParseError::DuplicateLabel(..) => ParseErrorKind::DuplicateLabel,
+            ParseError::UndefinedLabel(..) => ParseErrorKind::UndefinedLabel,
+            ParseError::ContinueToNonLoopLabel(..) => ParseErrorKind::ContinueToNonLoopLabel,
+            ParseError::BreakOutsideLoop(..) => ParseErrorKind::BreakOutsideLoop,
+            ParseError::ContinueOutsideLoop(..) => ParseErrorKind::ContinueOutsideLoop,
+            ParseError::DuplicatePrivateName(..) => ParseErrorKind::DuplicatePrivateName,
+            ParseError::UndefinedPrivateName(..) => ParseErrorKind::UndefinedPrivateName,
+            ParseError::FunctionDeclInSingleStatement => {
+                ParseErrorKind::FunctionDeclInSingleStatement
+            }
+            ParseError::LabelledFunctionDeclInSingleStatement => {
+                ParseErrorKind::LabelledFunctionDeclInSingleStatement
+            }
+        }
+    }
+
+    /// The stable code of the error: the name of its kind, such as
+    /// `"UnterminatedString"`. This is what JSON output should carry.
+    pub fn code(&self) -> &'static str {
+        self.kind().to_str()
+    }
+
     pub fn message(&self) -> String {
         match self {
             ParseError::IOError(io_error) => format!("{}", io_error),
-            ParseError::IllegalCharacter(c) => format!("illegal character: {:?}", c),
-            ParseError::InvalidEscapeSequence => format!("invalid escape sequence"),
-            ParseError::UnterminatedString => format!("unterminated string literal"),
-            ParseError::UnterminatedRegExp => format!("unterminated regexp literal"),
-            ParseError::UnterminatedMultiLineComment => format!("unterminated multiline comment"),
+            ParseError::IllegalCharacter(c, _) => format!("illegal character: {:?}", c),
+            ParseError::InvalidEscapeSequence(_) => format!("invalid escape sequence"),
+            ParseError::UnterminatedString(..) => format!("unterminated string literal"),
+            ParseError::UnterminatedRegExp(_) => format!("unterminated regexp literal"),
+            ParseError::InvalidRegExpFlag(flag, _) => {
+                format!("invalid regexp flag {:?}", flag)
+            }
+            ParseError::DuplicateRegExpFlag(flag, _) => {
+                format!("regexp flag {:?} is repeated", flag)
+            }
+            ParseError::InvalidRegExpPattern(message, _) => {
+                format!("invalid regexp pattern: {}", message)
+            }
+            ParseError::UnterminatedMultiLineComment(_) => format!("unterminated multiline comment"),
+            ParseError::OctalLiteralInStrictMode(..) => {
+                "octal literals are not allowed in strict mode code".to_string()
+            }
+            ParseError::OctalEscapeInStrictMode(_) => {
+                "octal escape sequences are not allowed in strict mode code".to_string()
+            }
             ParseError::LexerError => format!("lexical error"),
+            ParseError::InvalidEncoding(encoding, offset) => {
+                format!("invalid {} at byte {}", encoding, offset)
+            }
             ParseError::NotImplemented(message) => format!("not implemented: {}", message),
             ParseError::SyntaxError(token) => format!("syntax error on: {:?}", token),
             ParseError::UnexpectedEnd => format!("unexpected end of input"),
@@ -64,6 +472,53 @@ impl<'alloc> ParseError<'alloc> {
                 format!("invalid identifier {}", name)
             }
             ParseError::AstError(ast_error) => format!("{}", ast_error),
+            ParseError::FeatureNotAvailable(feature, target, _) => {
+                format!("{} is not available in {}", feature, target)
+            }
+            ParseError::WithInStrictMode(_) => {
+                "with statements are not allowed in strict mode code".to_string()
+            }
+            ParseError::CoalesceWithLogicalOperator(_) => {
+                "`??` can't be mixed with `||` or `&&` without parentheses".to_string()
+            }
+            ParseError::ImportMetaCall(_) => "`import.meta` is not a function".to_string(),
+            ParseError::ImportMetaOutsideModule(_) => {
+                "`import.meta` is only allowed in modules".to_string()
+            }
+            ParseError::TooDeeplyNested(_) => "too deeply nested".to_string(),
+            ParseError::AwaitOutsideAsyncFunction(_) => {
+                "`await` is only allowed in async functions and at the top level of modules"
+                    .to_string()
+            }
+            ParseError::AwaitInParameters(_) => {
+                "`await` is not allowed in function parameters".to_string()
+            }
+            ParseError::NewTargetOutsideFunction(_) => {
+                "`new.target` is only allowed in functions".to_string()
+            }
+            ParseError::UnaryBeforeExponentiation(_) => {
+                "a unary expression before `**` needs parentheses".to_string()
+            }
+            ParseError::ExpectedArrow(_) => {
+                "expected `=>` after arrow function parameters".to_string()
+            }
+            ParseError::YieldInParameters(_) => {
+                "`yield` is not allowed in the parameters of a generator".to_string()
+            }
+            ParseError::ModuleDeclarationInScript(keyword, _) => format!(
+                "`{}` declarations may only appear in a module; did you mean to parse as a module?",
+                keyword
+            ),
+            ParseError::DeleteOfIdentifierInStrictMode(_) => {
+                "names can't be deleted in strict mode code".to_string()
+            }
+            ParseError::UseStrictWithNonSimpleParameters(_) => {
+                "\"use strict\" is not allowed in functions with default, rest or destructuring parameters"
+                    .to_string()
+            }
+            ParseError::LabelledFunctionDeclInStrictMode(_) => {
+                "functions can't be labelled in strict mode code".to_string()
+            }
             ParseError::ArrayPatternWithNonFinalRest => {
                 format!("array patterns can have a rest element (`...x`) only at the end")
             }
@@ -77,6 +532,10 @@ impl<'alloc> ParseError<'alloc> {
             ParseError::ObjectBindingPatternWithInvalidRest => format!(
                 "the expression after `...` in this object pattern must be a single identifier"
             ),
+            ParseError::InitializerInObjectLiteral(_) => {
+                "default values (`a = 1`) are only allowed in object patterns, not object literals"
+                    .to_string()
+            }
             ParseError::ArrowHeadInvalid => format!(
                 "unexpected `=>` after function call (parentheses around the arrow function may help)"
             ),
@@ -93,10 +552,37 @@ impl<'alloc> ParseError<'alloc> {
                 "duplicate export name '{}'",
                 name,
             ),
+            ParseError::DuplicateImportAttribute(key, _, _) => format!(
+                "duplicate import attribute '{}'",
+                key,
+            ),
             ParseError::MissingExport(name, _) => format!(
                 "local binding for export '{}' not found",
                 name,
             ),
+            ParseError::DuplicateLabel(name, _, _) => format!(
+                "label '{}' is already used by an enclosing statement",
+                name,
+            ),
+            ParseError::UndefinedLabel(name, _) => format!("label '{}' not found", name),
+            ParseError::ContinueToNonLoopLabel(name, _) => format!(
+                "`continue` can only go to the label of a loop, and '{}' isn't one",
+                name,
+            ),
+            ParseError::BreakOutsideLoop(_) => {
+                "`break` is only allowed in a loop or `switch`, or with a label".to_string()
+            }
+            ParseError::ContinueOutsideLoop(_) => {
+                "`continue` is only allowed in a loop".to_string()
+            }
+            ParseError::DuplicatePrivateName(name, _, _) => format!(
+                "private name '{}' is already declared in this class",
+                name,
+            ),
+            ParseError::UndefinedPrivateName(name, _) => format!(
+                "private name '{}' is not declared in an enclosing class",
+                name,
+            ),
             ParseError::FunctionDeclInSingleStatement => format!(
                 "function declarations can't appear in single-statement context"
             ),
@@ -105,6 +591,235 @@ impl<'alloc> ParseError<'alloc> {
             ),
         }
     }
+
+    /// The byte offset in the source where the error was detected, if known.
+    ///
+    /// `UnexpectedEnd` doesn't carry an offset, since it's always the end of
+    /// the source; see `location`. Nor does `InvalidEncoding`, whose offset
+    /// is in the undecoded bytes.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ParseError::IllegalCharacter(_, offset)
+            | ParseError::InvalidEscapeSequence(offset)
+            | ParseError::UnterminatedString(offset, _)
+            | ParseError::UnterminatedRegExp(offset)
+            | ParseError::InvalidRegExpFlag(_, offset)
+            | ParseError::DuplicateRegExpFlag(_, offset)
+            | ParseError::InvalidRegExpPattern(_, offset)
+            | ParseError::UnterminatedMultiLineComment(offset)
+            | ParseError::OctalLiteralInStrictMode(offset, _)
+            | ParseError::OctalEscapeInStrictMode(offset)
+            | ParseError::InvalidIdentifier(_, offset)
+            | ParseError::FeatureNotAvailable(_, _, offset)
+            | ParseError::WithInStrictMode(offset)
+            | ParseError::CoalesceWithLogicalOperator(offset)
+            | ParseError::ImportMetaCall(offset)
+            | ParseError::ImportMetaOutsideModule(offset)
+            | ParseError::TooDeeplyNested(offset)
+            | ParseError::AwaitOutsideAsyncFunction(offset)
+            | ParseError::AwaitInParameters(offset)
+            | ParseError::NewTargetOutsideFunction(offset)
+            | ParseError::UnaryBeforeExponentiation(offset)
+            | ParseError::ExpectedArrow(offset)
+            | ParseError::YieldInParameters(offset)
+            | ParseError::ModuleDeclarationInScript(_, offset)
+            | ParseError::DeleteOfIdentifierInStrictMode(offset)
+            | ParseError::UseStrictWithNonSimpleParameters(offset)
+            | ParseError::LabelledFunctionDeclInStrictMode(offset)
+            | ParseError::InitializerInObjectLiteral(offset)
+            | ParseError::DuplicateBinding(_, _, _, _, offset)
+            | ParseError::DuplicateExport(_, _, offset)
+            | ParseError::DuplicateImportAttribute(_, _, offset)
+            | ParseError::MissingExport(_, offset)
+            | ParseError::DuplicateLabel(_, _, offset)
+            | ParseError::UndefinedLabel(_, offset)
+            | ParseError::ContinueToNonLoopLabel(_, offset)
+            | ParseError::BreakOutsideLoop(offset)
+            | ParseError::ContinueOutsideLoop(offset)
+            | ParseError::DuplicatePrivateName(_, _, offset)
+            | ParseError::UndefinedPrivateName(_, offset) => Some(*offset),
+            ParseError::SyntaxError(token) => Some(token.loc.start),
+            _ => None,
+        }
+    }
+
+    /// The byte range of the source to underline for this error, such as the
+    /// token that was unexpected. `source` must be the source text that was
+    /// parsed: it gives the end of the source, and shows whether a name was
+    /// written with escapes.
+    ///
+    /// For errors detected at a single point, such as most lexical errors,
+    /// the range is empty and starts at `offset`. For `UnexpectedEnd`, it's
+    /// the empty range at the end of the source.
+    pub fn span(&self, source: &str) -> Option<(usize, usize)> {
+        match self {
+            ParseError::SyntaxError(token) => Some((token.loc.start, token.loc.end)),
+            ParseError::UnexpectedEnd => Some((source.len(), source.len())),
+            // The offset is that of the operator token, `??`, `||` or `&&`.
+            ParseError::CoalesceWithLogicalOperator(offset) => Some((*offset, *offset + 2)),
+            // The offset is that of the keyword.
+            ParseError::BreakOutsideLoop(offset) => Some((*offset, *offset + "break".len())),
+            ParseError::ContinueOutsideLoop(offset) => Some((*offset, *offset + "continue".len())),
+            ParseError::WithInStrictMode(offset) => Some((*offset, *offset + "with".len())),
+            ParseError::DeleteOfIdentifierInStrictMode(offset) => {
+                Some((*offset, *offset + "delete".len()))
+            }
+            ParseError::UseStrictWithNonSimpleParameters(offset) => {
+                Some((*offset, *offset + "'use strict'".len()))
+            }
+            // The offset is that of the `**`.
+            ParseError::UnaryBeforeExponentiation(offset) => Some((*offset, *offset + 2)),
+            // The offset is that of the `await` keyword.
+            ParseError::AwaitOutsideAsyncFunction(offset)
+            | ParseError::AwaitInParameters(offset) => Some((*offset, *offset + "await".len())),
+            ParseError::YieldInParameters(offset) => Some((*offset, *offset + "yield".len())),
+            ParseError::NewTargetOutsideFunction(offset) => {
+                Some((*offset, *offset + "new.target".len()))
+            }
+            ParseError::ImportMetaOutsideModule(offset) => {
+                Some((*offset, *offset + "import.meta".len()))
+            }
+            ParseError::ModuleDeclarationInScript(keyword, offset) => {
+                Some((*offset, *offset + keyword.len()))
+            }
+            // The lexer knows where these literals end.
+            ParseError::UnterminatedString(start, end)
+            | ParseError::OctalLiteralInStrictMode(start, end) => Some((*start, *end)),
+            ParseError::InvalidRegExpFlag(flag, offset)
+            | ParseError::DuplicateRegExpFlag(flag, offset) => {
+                Some((*offset, *offset + flag.len_utf8()))
+            }
+            ParseError::InvalidIdentifier(name, offset)
+            | ParseError::DuplicateBinding(name, _, _, _, offset)
+            | ParseError::DuplicateExport(name, _, offset)
+            | ParseError::DuplicateImportAttribute(name, _, offset)
+            | ParseError::MissingExport(name, offset)
+            | ParseError::DuplicateLabel(name, _, offset)
+            | ParseError::UndefinedLabel(name, offset)
+            | ParseError::ContinueToNonLoopLabel(name, offset)
+            | ParseError::DuplicatePrivateName(name, _, offset)
+            | ParseError::UndefinedPrivateName(name, offset) => {
+                // A name written with escapes doesn't match the source. Point
+                // at its start then.
+                let end = *offset + name.len();
+                if source.get(*offset..end) == Some(*name) {
+                    Some((*offset, end))
+                } else {
+                    Some((*offset, *offset))
+                }
+            }
+            _ => self.offset().map(|offset| (offset, offset)),
+        }
+    }
+
+    /// The line and column where the error was detected, computed from
+    /// `source`, which must be the source text that was parsed.
+    pub fn location(&self, source: &str) -> Option<ErrorLocation> {
+        self.location_with(source, &ColumnConfig::default())
+    }
+
+    /// The line and column where the error was detected, like `location`,
+    /// with columns counted as `config` says.
+    pub fn location_with(&self, source: &str, config: &ColumnConfig) -> Option<ErrorLocation> {
+        match self {
+            ParseError::UnexpectedEnd => Some(ErrorLocation::from_offset_with(
+                source,
+                source.len(),
+                config,
+            )),
+            _ => self
+                .offset()
+                .map(|offset| ErrorLocation::from_offset_with(source, offset, config)),
+        }
+    }
+}
+
+/// What a column of an `ErrorLocation` counts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnUnit {
+    /// Bytes of UTF-8, as the offsets do.
+    Utf8Byte,
+
+    /// UTF-16 code units, as in JS strings and the Language Server Protocol,
+    /// so a character outside the Basic Multilingual Plane, such as an emoji,
+    /// is two.
+    Utf16,
+
+    /// Unicode scalar values.
+    CodePoint,
+}
+
+/// How to count the columns of an `ErrorLocation`. The default counts code
+/// points, and a tab as one column, as `ParseError::location` does.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ColumnConfig {
+    /// A tab moves to the next column after a multiple of this many, as in
+    /// a terminal, where it's usually 8. With 1, a tab is one column, like
+    /// any other character. 0 is taken as 1.
+    pub tab_width: u32,
+
+    pub unit: ColumnUnit,
+}
+
+impl Default for ColumnConfig {
+    fn default() -> Self {
+        Self {
+            tab_width: 1,
+            unit: ColumnUnit::CodePoint,
+        }
+    }
+}
+
+/// A position in the source text, for error reporting.
+///
+/// `line` and `column` are 1-based. By default, `column` counts Unicode scalar
+/// values (so a tab is a single column), not bytes; see `ColumnConfig`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ErrorLocation {
+    pub offset: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl ErrorLocation {
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        Self::from_offset_with(source, offset, &ColumnConfig::default())
+    }
+
+    /// The location of `offset` in `source`, with its column counted as
+    /// `config` says.
+    pub fn from_offset_with(source: &str, offset: usize, config: &ColumnConfig) -> Self {
+        let tab_width = config.tab_width.max(1);
+        let mut line = 1;
+        let mut column = 1;
+        let mut chars = source[..offset].chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // CR LF is a single line terminator.
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => {
+                    line += 1;
+                    column = 1;
+                }
+                '\t' => {
+                    column += tab_width - (column - 1) % tab_width;
+                }
+                _ => {
+                    column += match config.unit {
+                        ColumnUnit::Utf8Byte => c.len_utf8(),
+                        ColumnUnit::Utf16 => c.len_utf16(),
+                        ColumnUnit::CodePoint => 1,
+                    } as u32;
+                }
+            }
+        }
+
+        Self {
+            offset,
+            line,
+            column,
+        }
+    }
 }
 
 impl<'alloc> PartialEq for ParseError<'alloc> {
@@ -4,13 +4,15 @@ mod ast_builder;
 mod declaration_kind;
 mod early_errors;
 mod error;
+mod parser_options;
 mod parser_tables_generated;
 mod stack_value_generated;
 mod token;
 
-pub use ast_builder::AstBuilder;
+pub use ast_builder::{AstBuilder, ScriptBindings};
 pub use declaration_kind::DeclarationKind;
-pub use error::{ParseError, Result};
+pub use error::{ColumnConfig, ColumnUnit, ErrorLocation, ParseError, ParseErrorKind, Result};
+pub use parser_options::{EcmaVersion, ParserOptions};
 pub use parser_tables_generated::{
     reduce, ErrorCode, NonterminalId, ParserTables, TerminalId, START_STATE_MODULE,
     START_STATE_SCRIPT, TABLES,
@@ -0,0 +1,98 @@
+use crate::error::{ParseError, Result};
+use std::fmt;
+
+/// An edition of the ECMAScript standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EcmaVersion {
+    Es2015,
+    Es2016,
+    Es2017,
+    Es2018,
+    Es2019,
+    Es2020,
+    Es2021,
+    Es2022,
+}
+
+impl EcmaVersion {
+    pub const LATEST: EcmaVersion = EcmaVersion::Es2022;
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            EcmaVersion::Es2015 => "ES2015",
+            EcmaVersion::Es2016 => "ES2016",
+            EcmaVersion::Es2017 => "ES2017",
+            EcmaVersion::Es2018 => "ES2018",
+            EcmaVersion::Es2019 => "ES2019",
+            EcmaVersion::Es2020 => "ES2020",
+            EcmaVersion::Es2021 => "ES2021",
+            EcmaVersion::Es2022 => "ES2022",
+        }
+    }
+}
+
+impl fmt::Display for EcmaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParserOptions {
+    /// Parse the whole source as strict mode code, as if it started with a
+    /// "use strict" directive.
+    pub strict: bool,
+
+    /// Reject syntax added after this edition.
+    pub target: EcmaVersion,
+
+    /// Accept a `#!` comment at the very start of the source.
+    pub allow_hashbang: bool,
+
+    /// Return `ParseError::TooDeeplyNested` for parentheses, brackets,
+    /// braces and template substitutions nested deeper than this. The parser
+    /// itself doesn't recurse, but code walking the AST, such as the
+    /// emitter, does, and would overflow the stack on deeper input.
+    pub max_nesting_depth: usize,
+
+    /// Return `ParseError::TooDeeplyNested` for expressions and statements
+    /// nested deeper than this, which operators can do without brackets, as
+    /// in `1 + 1 + ...`, `x = x = ...` or `!!!...`. Flat code nests this way
+    /// too: each operand of `a, b, c, ...` and each `else if` is a level, so
+    /// the default leaves room for generated and minified code. Optimized
+    /// builds of the emitter compile that deep an AST in an 8 MiB stack, the
+    /// size of a main thread's; smaller stacks need a lower limit.
+    pub max_node_depth: usize,
+}
+
+impl ParserOptions {
+    /// Return an error if `feature`, which was added in `version`, is not
+    /// available in the target edition. `offset` is where it was found.
+    pub fn check_target<'alloc>(
+        &self,
+        feature: &'static str,
+        version: EcmaVersion,
+        offset: usize,
+    ) -> Result<'alloc, ()> {
+        if version > self.target {
+            return Err(ParseError::FeatureNotAvailable(
+                feature,
+                self.target,
+                offset,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            target: EcmaVersion::LATEST,
+            allow_hashbang: true,
+            max_nesting_depth: 1000,
+            max_node_depth: 10_000,
+        }
+    }
+}
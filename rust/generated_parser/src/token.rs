@@ -1,5 +1,5 @@
 use crate::parser_tables_generated::TerminalId;
-use ast::SourceLocation;
+use ast::{source_atom_set::SourceAtomSetIndex, SourceLocation};
 
 /// An ECMAScript input token. The lexer discards input matching *WhiteSpace*,
 /// *LineTerminator*, and *Comment*. The remaining input elements are called
@@ -38,9 +38,27 @@ pub struct Token<'a> {
     /// *EscapeSequence*s and removing *LineContinuation*s (the SV of the
     /// literal, in standardese).
     ///
-    /// For all other tokens (including template literal parts), the content is
-    /// unspecified for now. TODO.
+    /// For a template literal part, such as *TemplateHead*, the template
+    /// characters after decoding escape sequences (the TV), or None if they
+    /// contain a *NotEscapeSequence*, which only tagged templates allow.
+    ///
+    /// For all other tokens, the content is unspecified for now. TODO.
     pub value: Option<&'a str>,
+
+    /// For a template literal part, the template characters as they appear
+    /// in the source, with line terminators normalized to LF (the TRV).
+    /// None for other tokens.
+    pub raw: Option<&'a str>,
+
+    /// For a *NumericLiteral* or string literal in legacy octal syntax, which
+    /// non-strict code allows, the offset of its leading `0`, or of the `\`
+    /// of its first octal escape sequence. None for other tokens.
+    pub legacy_octal: Option<usize>,
+
+    /// For names, private names and keywords, the handle of the name in the
+    /// lexer's `SourceAtomSet`, if it interns them; see
+    /// `Lexer::collect_atoms`. None for other tokens.
+    pub atom: Option<SourceAtomSetIndex>,
 }
 
 impl Token<'_> {
@@ -50,6 +68,9 @@ impl Token<'_> {
             loc,
             is_on_new_line: false,
             value: None,
+            raw: None,
+            legacy_octal: None,
+            atom: None,
         }
     }
 }
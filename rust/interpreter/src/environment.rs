@@ -0,0 +1,133 @@
+//! The environments that hold the bindings functions refer to, which live
+//! on after the code that made them returns, as long as a function that
+//! refers to them does.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::value::Value;
+
+/// The bindings of a scope that are kept in an environment, as the scope
+/// note of the scope says, for a run of its code: each call of a function
+/// gets a new one, as does each iteration of a loop with `let` bindings
+/// that functions refer to.
+pub struct Environment {
+    slots: RefCell<Vec<Value>>,
+
+    /// The environment of the code the scope is in, or None for the top
+    /// level of the script.
+    enclosing: Option<Rc<Environment>>,
+}
+
+impl Environment {
+    /// An environment with `num_slots` slots, which start out undefined.
+    fn new(num_slots: u32, enclosing: Option<Rc<Environment>>) -> Rc<Self> {
+        Rc::new(Environment {
+            slots: RefCell::new(vec![Value::Undefined; num_slots as usize]),
+            enclosing,
+        })
+    }
+
+    /// The environment `hops` environments out from this one, as
+    /// `GetAliasedVar` counts them.
+    fn at(&self, hops: u8) -> &Environment {
+        let mut env = self;
+        for _ in 0..hops {
+            env = env
+                .enclosing
+                .as_deref()
+                .expect("GetAliasedVar goes out of the outermost environment");
+        }
+        env
+    }
+
+    fn get(&self, hops: u8, slot: u32) -> Value {
+        self.at(hops).slots.borrow()[slot as usize].clone()
+    }
+
+    fn set(&self, hops: u8, slot: u32, value: Value) {
+        self.at(hops).slots.borrow_mut()[slot as usize] = value;
+    }
+}
+
+// The slots aren't shown, as they may hold functions that refer back to the
+// environment.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<environment: {} slots>", self.slots.borrow().len())
+    }
+}
+
+/// The environments a frame's code runs with: the one its function was made
+/// in, and those the code pushed, innermost last, with the offsets of the
+/// `PushVarEnv` and `PushLexicalEnv` instructions that pushed them.
+pub(crate) struct Environments {
+    closure: Option<Rc<Environment>>,
+    pushed: Vec<(Rc<Environment>, usize)>,
+}
+
+impl Environments {
+    /// The environments of a function made in `closure`, or of the script
+    /// if it's None.
+    pub(crate) fn new(closure: Option<Rc<Environment>>) -> Self {
+        Environments {
+            closure,
+            pushed: Vec::new(),
+        }
+    }
+
+    /// The innermost environment, which `GetAliasedVar` counts hops from,
+    /// and which the functions the code makes refer to.
+    pub(crate) fn innermost(&self) -> Option<&Rc<Environment>> {
+        match self.pushed.last() {
+            Some((env, _)) => Some(env),
+            None => self.closure.as_ref(),
+        }
+    }
+
+    /// Push an environment with `num_slots` slots, for the instruction at
+    /// `pc`.
+    pub(crate) fn push(&mut self, num_slots: u32, pc: usize) {
+        let env = Environment::new(num_slots, self.innermost().cloned());
+        self.pushed.push((env, pc));
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.pushed.pop();
+    }
+
+    /// Replace the innermost pushed environment with a copy of it, for the
+    /// next iteration of a loop with `let` bindings, so that the functions
+    /// made in the last one keep their own bindings.
+    pub(crate) fn freshen(&mut self) {
+        let (env, pc) = self
+            .pushed
+            .pop()
+            .expect("FreshenLexicalEnv is only in code with an environment");
+        let copy = Rc::new(Environment {
+            slots: RefCell::new(env.slots.borrow().clone()),
+            enclosing: env.enclosing.clone(),
+        });
+        self.pushed.push((copy, pc));
+    }
+
+    /// Pop the environments pushed from `start` on, as when jumping from
+    /// there to a `catch` or `finally` block.
+    pub(crate) fn pop_from(&mut self, start: usize) {
+        self.pushed.retain(|(_, push_pc)| *push_pc < start);
+    }
+
+    pub(crate) fn get(&self, hops: u8, slot: u32) -> Value {
+        self.innermost_or_panic().get(hops, slot)
+    }
+
+    pub(crate) fn set(&self, hops: u8, slot: u32, value: Value) {
+        self.innermost_or_panic().set(hops, slot, value)
+    }
+
+    fn innermost_or_panic(&self) -> &Environment {
+        self.innermost()
+            .expect("GetAliasedVar is only in code with an environment")
+    }
+}
@@ -1,33 +1,56 @@
 use emitter::opcode::Opcode;
-use emitter::EmitResult;
+use emitter::{
+    AsyncFunctionResolveKind, EmitResult, FunctionCode, ResumeKind, SymbolCode, TryNoteKind,
+};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt;
 use std::rc::Rc;
 
-use crate::object::Object;
-use crate::value::{to_boolean, to_number, JSValue};
+use crate::environment::{Environment, Environments};
+use crate::function::{Function, GeneratorState};
+use crate::object::{Accessor, Object};
+use crate::promise::{self, Job};
+use crate::value::{
+    exponentiate, negate_bigint, step_bigint, strict_equals, to_boolean, to_number, NativeFunction,
+    Value,
+};
 
 /// The error of evaluating JS bytecode.
 #[derive(Clone, Debug)]
 pub enum EvalError {
     NotImplemented(String),
+    /// A JS TypeError was thrown, such as by reading a property of null.
+    TypeError(String),
+    /// A JS value was thrown by a `throw` statement and not caught.
+    Thrown(Value),
     EmptyStack,
+    /// More instructions were executed than allowed; see
+    /// `Realm::evaluate_with_limit`.
+    StepLimitExceeded,
+    /// Calls were nested too deeply.
+    TooMuchRecursion,
 }
 
 impl fmt::Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             EvalError::NotImplemented(message) => write!(f, "not implemented: {}", message),
+            EvalError::TypeError(message) => write!(f, "TypeError: {}", message),
+            EvalError::Thrown(value) => write!(f, "uncaught exception: {}", value.to_js_string()),
             EvalError::EmptyStack => write!(f, "trying to pop from empty stack"),
+            EvalError::StepLimitExceeded => write!(f, "step limit exceeded"),
+            EvalError::TooMuchRecursion => write!(f, "too much recursion"),
         }
     }
 }
 
 trait Helpers {
     fn read_u16(&self, offset: usize) -> u16;
+    fn read_u24(&self, offset: usize) -> u32;
     fn read_i32(&self, offset: usize) -> i32;
     fn read_u32(&self, offset: usize) -> u32;
     fn read_offset(&self, offset: usize) -> isize;
@@ -39,6 +62,11 @@ impl Helpers for EmitResult {
         u16::from_le_bytes(self.bytecode[offset..offset + 2].try_into().unwrap())
     }
 
+    fn read_u24(&self, offset: usize) -> u32 {
+        let bytes = &self.bytecode[offset..offset + 3];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+    }
+
     fn read_i32(&self, offset: usize) -> i32 {
         i32::from_le_bytes(self.bytecode[offset..offset + 4].try_into().unwrap())
     }
@@ -56,221 +84,1778 @@ impl Helpers for EmitResult {
     }
 }
 
-fn print(_this_value: JSValue, args: &[JSValue]) -> JSValue {
-    println!("{:?}", args);
-    JSValue::Undefined
+/// BigInt arithmetic isn't implemented yet, and mixing BigInts with numbers
+/// is a TypeError.
+fn check_not_bigint(value: &Value, op: Opcode) -> Result<(), EvalError> {
+    match value {
+        Value::BigInt(_) => Err(EvalError::NotImplemented(format!("{:?} on a BigInt", op))),
+        _ => Ok(()),
+    }
 }
 
-pub fn evaluate(emit: &EmitResult) -> Result<JSValue, EvalError> {
-    let mut pc = 0;
-    let mut stack = Vec::new();
-    let mut rval = JSValue::Undefined;
+fn get_property(obj: &Value, name: String) -> Result<Value, EvalError> {
+    match obj {
+        Value::Object(obj) => Ok(obj.borrow().get(name)),
+        Value::Function(function) if name == "prototype" => Ok(match &**function {
+            Function::Script(_, _, Some(prototype)) => Value::Object(prototype.clone()),
+            _ => Value::Undefined,
+        }),
+        // TODO: other properties of functions.
+        Value::NativeFunction(_) | Value::Function(_) => Err(EvalError::NotImplemented(
+            "property of a function".to_owned(),
+        )),
+        Value::Null | Value::Undefined => Err(EvalError::TypeError(format!(
+            "can't access property {:?} of {}",
+            name,
+            obj.to_js_string()
+        ))),
+        // TODO: properties of primitives, from their prototypes.
+        _ => Err(EvalError::NotImplemented(
+            "property of a primitive".to_owned(),
+        )),
+    }
+}
 
-    let global = Rc::new(RefCell::new(Object::new()));
-    global
-        .borrow_mut()
-        .set("print".to_owned(), JSValue::NativeFunction(print));
-    global
+/// The property of `obj` whose key is `key`, as with `obj[key]`.
+fn get_element(obj: &Value, key: &Value) -> Result<Value, EvalError> {
+    match (obj, key) {
+        (Value::Object(array), Value::Symbol(SymbolCode::Iterator))
+            if array.borrow().array_length().is_some() =>
+        {
+            Ok(array_values_function(array.clone()))
+        }
+        (Value::Object(generator), Value::Symbol(SymbolCode::Iterator))
+            if generator.borrow().is_generator() =>
+        {
+            Ok(Value::Function(Rc::new(Function::ReturnThis)))
+        }
+        // TODO: Symbol-keyed properties of other objects.
+        (Value::Object(_), Value::Symbol(_)) => Ok(Value::Undefined),
+        _ => get_property(obj, key.to_js_string()),
+    }
+}
+
+/// Define the property of `obj` whose key is `key`, as in an object
+/// literal.
+fn init_element(obj: &Value, key: &Value, value: Value) -> Result<(), EvalError> {
+    match (obj, key) {
+        (_, Value::Symbol(_)) => Err(EvalError::NotImplemented(
+            "defining a Symbol-keyed property".to_owned(),
+        )),
+        (Value::Object(obj), _) => {
+            obj.borrow_mut().set(key.to_js_string(), value);
+            Ok(())
+        }
+        _ => Err(EvalError::NotImplemented("not an object".to_owned())),
+    }
+}
+
+/// Define the own properties of `source` on `target`, except those that
+/// `excluded` has, if it's an object, as `{ ...source }` does.
+fn copy_data_properties(target: &Value, source: &Value, excluded: &Value) -> Result<(), EvalError> {
+    let target = match target {
+        Value::Object(target) => target,
+        _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
+    };
+    let source = match source {
+        Value::Object(source) => source.borrow(),
+        // TODO: the properties of functions, and the indices of strings.
+        Value::NativeFunction(_) | Value::Function(_) | Value::String(_) => {
+            return Err(EvalError::NotImplemented(
+                "copying the properties of a function or string".to_owned(),
+            ))
+        }
+        // Other primitives have no own properties.
+        _ => return Ok(()),
+    };
+    for name in source.property_names() {
+        let is_excluded = match excluded {
+            Value::Object(excluded) => excluded.borrow().has_own(&name),
+            _ => false,
+        };
+        if !is_excluded {
+            // TODO: call the getters, which copies their values.
+            if source.accessor(&name).is_some() {
+                return Err(EvalError::NotImplemented(
+                    "copying an accessor property".to_owned(),
+                ));
+            }
+            let value = source.get(name.clone());
+            target.borrow_mut().set(name, value);
+        }
+    }
+    Ok(())
+}
+
+/// `array[Symbol.iterator]`, which returns an iterator over the elements of
+/// `array`. Native functions don't get a `this` value, so each array gets its
+/// own function.
+fn array_values_function(array: Rc<RefCell<Object>>) -> Value {
+    Value::NativeFunction(Rc::new(move |_args| {
+        let array = array.clone();
+        // The next index, or None once done.
+        let index = Cell::new(Some(0));
+        let next = move |_args: &[Value]| {
+            let length = array.borrow().array_length().unwrap_or(0);
+            match index.get() {
+                Some(i) if i < length => {
+                    index.set(Some(i + 1));
+                    iterator_result(array.borrow().get(i.to_string()), false)
+                }
+                _ => {
+                    index.set(None);
+                    iterator_result(Value::Undefined, true)
+                }
+            }
+        };
+
+        let mut iterator = Object::new();
+        iterator.set("next".to_owned(), Value::NativeFunction(Rc::new(next)));
+        Value::Object(Rc::new(RefCell::new(iterator)))
+    }))
+}
+
+/// The function `Lambda` makes from `code` in `env`. If it's a
+/// constructor, its `prototype` is a new object, whose `constructor` is the
+/// function.
+// TODO: make `constructor` not enumerable, as for classes.
+fn new_function(code: Rc<FunctionCode>, env: Option<Rc<Environment>>) -> Value {
+    if !code.is_constructor {
+        return Value::Function(Rc::new(Function::Script(code, env, None)));
+    }
+    let prototype = Rc::new(RefCell::new(Object::new()));
+    let function = Rc::new(Function::Script(code, env, Some(prototype.clone())));
+    prototype
         .borrow_mut()
-        .set("undefined".to_owned(), JSValue::Undefined);
+        .set("constructor".to_owned(), Value::Function(function.clone()));
+    Value::Function(function)
+}
+
+/// The `{ value, done }` object returned by the `next` method of iterators.
+fn iterator_result(value: Value, done: bool) -> Value {
+    let mut result = Object::new();
+    result.set("value".to_owned(), value);
+    result.set("done".to_owned(), Value::Bool(done));
+    Value::Object(Rc::new(RefCell::new(result)))
+}
+
+fn set_property(obj: &Value, name: String, value: Value) -> Result<(), EvalError> {
+    match obj {
+        Value::Object(obj) => {
+            obj.borrow_mut().set(name, value);
+            Ok(())
+        }
+        Value::Null | Value::Undefined => Err(EvalError::TypeError(format!(
+            "can't assign to property {:?} of {}",
+            name,
+            obj.to_js_string()
+        ))),
+        // TODO: setters of primitives' prototypes, and the TypeError in
+        // strict mode code.
+        _ => Err(EvalError::NotImplemented(
+            "assigning to a property of a primitive".to_owned(),
+        )),
+    }
+}
+
+/// The property `name` of `obj`, if it's an accessor property.
+fn find_accessor(obj: &Value, name: &str) -> Option<Accessor> {
+    match obj {
+        Value::Object(obj) => obj.borrow().accessor(name),
+        _ => None,
+    }
+}
+
+/// Push the property `name` of `obj`, or call its getter with `obj` as
+/// `this` if it's an accessor property, whose result is then pushed.
+fn get_or_call_getter(
+    obj: Value,
+    name: String,
+    stack: &mut Vec<Value>,
+) -> Result<Option<Exit>, EvalError> {
+    match find_accessor(&obj, &name) {
+        Some(Accessor {
+            getter: Some(getter),
+            ..
+        }) => return Ok(Some(Exit::Call(getter, obj, Vec::new()))),
+        // Without a getter, the value is undefined.
+        Some(_) => stack.push(Value::Undefined),
+        None => stack.push(get_property(&obj, name)?),
+    }
+    Ok(None)
+}
+
+/// Assign `value` to the property `name` of `obj`, or call its setter with
+/// `obj` as `this` if it's an accessor property, and push `value`.
+fn set_or_call_setter(
+    obj: Value,
+    name: String,
+    value: Value,
+    stack: &mut Vec<Value>,
+) -> Result<Option<Exit>, EvalError> {
+    match find_accessor(&obj, &name) {
+        Some(Accessor {
+            setter: Some(setter),
+            ..
+        }) => return Ok(Some(Exit::CallSetter(setter, obj, value))),
+        // TODO: the TypeError in strict mode code, without a setter.
+        Some(_) => {}
+        None => set_property(&obj, name, value.clone())?,
+    }
+    stack.push(value);
+    Ok(None)
+}
+
+/// Delete the property of `obj` whose key is `key`, as with `delete
+/// obj[key]`, and return whether it's gone. In strict mode code, a property
+/// that can't be deleted is a TypeError instead.
+fn delete_property(obj: &Value, key: &Value, strict: bool) -> Result<bool, EvalError> {
+    let deleted = match (obj, key) {
+        (Value::Null, _) | (Value::Undefined, _) => {
+            return Err(EvalError::TypeError(format!(
+                "can't delete property {:?} of {}",
+                key.to_js_string(),
+                obj.to_js_string()
+            )))
+        }
+        // TODO: Symbol-keyed properties.
+        (_, Value::Symbol(_)) => true,
+        (Value::Object(obj), _) => obj.borrow_mut().delete(&key.to_js_string()),
+        // The `prototype` of a constructor can't be deleted.
+        (Value::Function(function), _) if key.to_js_string() == "prototype" => {
+            !matches!(&**function, Function::Script(_, _, Some(_)))
+        }
+        // TODO: the other properties of functions, and of strings, like
+        // `length`, which can't be deleted.
+        _ => true,
+    };
+    if !deleted && strict {
+        return Err(EvalError::TypeError(format!(
+            "property {:?} can't be deleted",
+            key.to_js_string()
+        )));
+    }
+    Ok(deleted)
+}
+
+/// The result of `key in obj`.
+fn has_property(key: &Value, obj: &Value) -> Result<bool, EvalError> {
+    match obj {
+        Value::Object(_) if matches!(key, Value::Symbol(_)) => {
+            Ok(!matches!(get_element(obj, key)?, Value::Undefined))
+        }
+        Value::Object(obj) => Ok(obj.borrow().has(&key.to_js_string())),
+        // TODO: properties of functions.
+        Value::NativeFunction(_) | Value::Function(_) => Ok(false),
+        _ => Err(EvalError::TypeError(format!(
+            "can't use 'in' to search for {:?} in {}",
+            key.to_js_string(),
+            obj.to_js_string()
+        ))),
+    }
+}
+
+/// The result of `value instanceof constructor`: whether the `prototype` of
+/// the constructor is on the prototype chain of the value.
+// TODO: `Symbol.hasInstance`.
+fn instance_of(value: &Value, constructor: &Value) -> Result<bool, EvalError> {
+    let prototype = match constructor {
+        Value::Object(class) if class.borrow().class_constructor().is_some() => {
+            class.borrow().get("prototype".to_owned())
+        }
+        Value::Function(_) => get_property(constructor, "prototype".to_owned())?,
+        Value::NativeFunction(_) => {
+            if let Value::Object(_) = value {
+                // TODO: the `prototype` property of native functions.
+                return Err(EvalError::NotImplemented(
+                    "instanceof a native function".to_owned(),
+                ));
+            }
+            return Ok(false);
+        }
+        _ => {
+            return Err(EvalError::TypeError(format!(
+                "the right side of instanceof, {}, is not callable",
+                constructor.to_js_string()
+            )))
+        }
+    };
+    let prototype = match prototype {
+        Value::Object(prototype) => prototype,
+        _ => {
+            return Err(EvalError::TypeError(
+                "the prototype of the right side of instanceof is not an object".to_owned(),
+            ))
+        }
+    };
+    let mut object = match value {
+        Value::Object(object) => object.borrow().prototype().cloned(),
+        _ => return Ok(false),
+    };
+    while let Some(current) = object {
+        if Rc::ptr_eq(&current, &prototype) {
+            return Ok(true);
+        }
+        object = current.borrow().prototype().cloned();
+    }
+    Ok(false)
+}
+
+/// The object of the innermost `with` statement being run that has the
+/// property `name`, if any. Otherwise, the name is a global variable.
+fn find_with_object<'a>(
+    with_objects: &'a [(Rc<RefCell<Object>>, usize)],
+    name: &str,
+) -> Option<&'a Rc<RefCell<Object>>> {
+    with_objects
+        .iter()
+        .rev()
+        .map(|(object, _)| object)
+        .find(|object| object.borrow().has(name))
+}
+
+/// Define the getter or setter of the accessor property `name` of `obj`, as
+/// in an object literal or a class.
+fn init_accessor(
+    obj: &Value,
+    name: String,
+    function: Value,
+    is_getter: bool,
+) -> Result<(), EvalError> {
+    match (obj, function) {
+        (Value::Object(obj), Value::Function(function)) if is_getter => {
+            obj.borrow_mut().set_getter(name, function);
+            Ok(())
+        }
+        (Value::Object(obj), Value::Function(function)) => {
+            obj.borrow_mut().set_setter(name, function);
+            Ok(())
+        }
+        _ => Err(EvalError::NotImplemented("not an object".to_owned())),
+    }
+}
+
+/// How deeply calls can nest.
+const MAX_CALL_DEPTH: usize = 10000;
+
+/// The state of a script or function being run.
+pub(crate) struct Frame {
+    pub(crate) pc: usize,
+    stack: Vec<Value>,
+    rval: Value,
+
+    /// The exception being handled by a `catch` block, until `Exception`
+    /// takes it.
+    exception: Option<Value>,
+
+    /// The `this` value.
+    this: Value,
+
+    /// The arguments, with undefined for the missing ones.
+    args: Vec<Value>,
+
+    /// The `new.target` value: the constructor for a function run by `new`,
+    /// and undefined otherwise.
+    new_target: Value,
+
+    locals: Vec<Value>,
+
+    /// The objects of the `with` statements being run, innermost last, with
+    /// the offsets of their `EnterWith` instructions.
+    with_objects: Vec<(Rc<RefCell<Object>>, usize)>,
+
+    environments: Environments,
+}
+
+impl Frame {
+    fn new(
+        this: Value,
+        args: Vec<Value>,
+        num_locals: usize,
+        closure_environment: Option<Rc<Environment>>,
+    ) -> Self {
+        Frame {
+            pc: 0,
+            stack: Vec::new(),
+            rval: Value::Undefined,
+            exception: None,
+            this,
+            args,
+            new_target: Value::Undefined,
+            locals: vec![Value::Undefined; num_locals],
+            with_objects: Vec::new(),
+            environments: Environments::new(closure_environment),
+        }
+    }
+}
+
+/// Why `step` stopped running a frame.
+enum Exit {
+    Return(Value),
+    /// The generator suspended itself with a `yield`, yielding the value,
+    /// or the async function with an `await`, returning its promise.
+    Yield(Rc<RefCell<Object>>, Value),
+    /// Call the function with the `this` value and the arguments. The frame
+    /// stays at the call instruction until the call returns.
+    Call(Rc<Function>, Value, Vec<Value>),
+    /// Call the setter with the `this` value and the value assigned, like
+    /// `Call`. The result is the value assigned.
+    CallSetter(Rc<Function>, Value, Value),
+    /// Run the constructor, made in the environment, with the new object as
+    /// `this` and the `new.target` value, like `Call`.
+    Construct(
+        Rc<FunctionCode>,
+        Option<Rc<Environment>>,
+        Rc<RefCell<Object>>,
+        Value,
+        Vec<Value>,
+    ),
+}
+
+enum Code<'a> {
+    Script(&'a EmitResult),
+    Function(Rc<FunctionCode>),
+}
+
+impl Code<'_> {
+    fn emit_result(&self) -> &EmitResult {
+        match self {
+            Code::Script(emit) => emit,
+            Code::Function(code) => &code.body,
+        }
+    }
+}
+
+/// A frame on the call stack. Calls of functions that run bytecode don't call
+/// the interpreter recursively, so deep recursion in scripts doesn't
+/// overflow the native stack.
+struct Activation<'a> {
+    code: Code<'a>,
+    frame: Frame,
+
+    /// For a generator resumed by `next`, or an async function resumed by a
+    /// job, the generator object, whose state is updated when the frame
+    /// exits.
+    generator: Option<Rc<RefCell<Object>>>,
+
+    /// For a constructor run by `new`, the new object, which is the result
+    /// unless the constructor returns another object.
+    new_object: Option<Value>,
+
+    /// For a setter called by an assignment, the value assigned, which is
+    /// the result whatever the setter returns.
+    assigned_value: Option<Value>,
+}
+
+/// What `import()` and `import.meta` evaluate to in the code being run.
+pub(crate) struct ModuleHost {
+    /// The host's function that `import()` calls with the specifier and the
+    /// options. See `Realm::register_dynamic_import`.
+    pub(crate) dynamic_import: Option<Rc<NativeFunction>>,
+
+    /// The `import.meta` object.
+    pub(crate) import_meta: Rc<RefCell<Object>>,
+}
+
+/// Run `emit` with `global` as the global object, executing at most
+/// `max_steps` instructions. See `Realm::evaluate_with_limit`.
+///
+/// Then the jobs the script queued run, resuming the async functions whose
+/// `await` is done, and the jobs they queue, until there are none left. The
+/// result is still the completion value of the script.
+pub(crate) fn evaluate(
+    emit: &EmitResult,
+    global: &Rc<RefCell<Object>>,
+    host: &ModuleHost,
+    max_steps: u64,
+) -> Result<Value, EvalError> {
+    let mut steps_left = max_steps;
+    let mut activations = vec![Activation {
+        code: Code::Script(emit),
+        frame: Frame::new(
+            Value::Object(global.clone()),
+            Vec::new(),
+            emit.frame_slot_count() as usize,
+            None,
+        ),
+        generator: None,
+        new_object: None,
+        assigned_value: None,
+    }];
+    let mut jobs = VecDeque::new();
+    let mut completion = None;
 
     loop {
-        let op = match Opcode::try_from(emit.bytecode[pc]) {
-            Ok(op) => op,
-            Err(_) => {
-                return Err(EvalError::NotImplemented(format!(
-                    "{} is not an opcode",
-                    emit.bytecode[pc]
-                )))
+        if steps_left == 0 {
+            return Err(EvalError::StepLimitExceeded);
+        }
+        steps_left -= 1;
+
+        let activation = activations.last_mut().unwrap();
+        let result = step(
+            activation.code.emit_result(),
+            global,
+            host,
+            &mut activation.frame,
+            &mut jobs,
+        );
+        // The value to return to the frame below, if the call is done.
+        let returned = match result {
+            Ok(None) => continue,
+            Ok(Some(Exit::Call(function, this, args))) => {
+                call(&mut activations, &function, this, args)
+            }
+            Ok(Some(Exit::CallSetter(function, this, value))) => {
+                match call(&mut activations, &function, this, vec![value.clone()]) {
+                    Ok(None) => {
+                        activations.last_mut().unwrap().assigned_value = Some(value);
+                        Ok(None)
+                    }
+                    Ok(Some(_)) => Ok(Some(value)),
+                    Err(error) => Err(error),
+                }
+            }
+            Ok(Some(Exit::Construct(code, env, this, new_target, args))) => {
+                let this = Value::Object(this);
+                let constructing = Some((this.clone(), new_target));
+                run_function(&mut activations, &code, env, this, args, constructing)
+            }
+            Ok(Some(Exit::Return(value))) => {
+                let activation = activations.pop().unwrap();
+                if let Some(generator) = activation.generator {
+                    generator
+                        .borrow_mut()
+                        .replace_generator_state(GeneratorState::Completed);
+                }
+                let is_object = matches!(
+                    value,
+                    Value::Object(_) | Value::Function(_) | Value::NativeFunction(_)
+                );
+                match (activation.new_object, activation.assigned_value) {
+                    (Some(new_object), _) if !is_object => Ok(Some(new_object)),
+                    (_, Some(assigned_value)) => Ok(Some(assigned_value)),
+                    _ => Ok(Some(value)),
+                }
+            }
+            Ok(Some(Exit::Yield(generator, value))) => {
+                let activation = activations.pop().unwrap();
+                let code = match activation.code {
+                    Code::Function(code) => code,
+                    Code::Script(_) => {
+                        return Err(EvalError::NotImplemented(
+                            "yield outside of a generator".to_owned(),
+                        ))
+                    }
+                };
+                generator
+                    .borrow_mut()
+                    .replace_generator_state(GeneratorState::Suspended(
+                        code,
+                        Box::new(activation.frame),
+                    ));
+                Ok(Some(value))
             }
+            Err(error) => Err(error),
         };
 
-        match op {
-            Opcode::Int8 => stack.push(JSValue::Number(emit.bytecode[pc + 1] as f64)),
+        match returned {
+            Ok(None) => {}
+            Ok(Some(value)) => match activations.last_mut() {
+                // Go on after the call instruction.
+                Some(caller) => {
+                    let frame = &mut caller.frame;
+                    let op =
+                        Opcode::try_from(caller.code.emit_result().bytecode[frame.pc]).unwrap();
+                    frame.stack.push(value);
+                    frame.pc += op.instruction_length();
+                }
+                // The script, or a job, is done: run the next job.
+                None => {
+                    let completion = completion.get_or_insert(value);
+                    match jobs.pop_front() {
+                        Some(job) => {
+                            let kind = Value::Number(job.kind as u8 as f64);
+                            resume(&mut activations, job.generator, job.value, kind)?;
+                        }
+                        None => return Ok(completion.clone()),
+                    }
+                }
+            },
+            Err(error) => throw(&mut activations, error)?,
+        }
+    }
+}
 
-            Opcode::Add => {
-                let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
-                // TODO: Add is special, i.e. string concat
-                stack.push(JSValue::Number(to_number(&lhs) + to_number(&rhs)))
+/// Start calling `function` from the frame on top of `activations`, giving
+/// the result if it's known without running bytecode.
+fn call(
+    activations: &mut Vec<Activation>,
+    function: &Function,
+    this: Value,
+    args: Vec<Value>,
+) -> Result<Option<Value>, EvalError> {
+    if activations.len() >= MAX_CALL_DEPTH {
+        return Err(EvalError::TooMuchRecursion);
+    }
+    match function {
+        Function::Script(code, env, _) => {
+            run_function(activations, code, env.clone(), this, args, None)
+        }
+        // Run the generator up to its next `yield` or its end, with the
+        // argument as the result of the `yield` it's suspended at.
+        Function::GeneratorNext => {
+            let generator = match this {
+                Value::Object(ref generator) if generator.borrow().is_generator() => {
+                    generator.clone()
+                }
+                _ => {
+                    return Err(EvalError::TypeError(format!(
+                        "{} is not a generator object",
+                        this.to_js_string()
+                    )))
+                }
+            };
+            let state = generator
+                .borrow_mut()
+                .replace_generator_state(GeneratorState::Running);
+            match state {
+                Some(GeneratorState::Suspended(code, frame)) => {
+                    let value = args.into_iter().next().unwrap_or(Value::Undefined);
+                    let kind = Value::Number(ResumeKind::Normal as u8 as f64);
+                    resume_frame(activations, generator, code, *frame, value, kind);
+                    Ok(None)
+                }
+                Some(GeneratorState::Running) => {
+                    Err(EvalError::TypeError("already running generator".to_owned()))
+                }
+                Some(GeneratorState::Completed) | None => {
+                    generator
+                        .borrow_mut()
+                        .replace_generator_state(GeneratorState::Completed);
+                    Ok(Some(iterator_result(Value::Undefined, true)))
+                }
             }
+        }
+        Function::ReturnThis => Ok(Some(this)),
+    }
+}
 
-            Opcode::Sub => {
-                let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
-                stack.push(JSValue::Number(to_number(&lhs) - to_number(&rhs)))
-            }
+/// Resume the suspended async function whose generator object is
+/// `generator`, for a job.
+fn resume(
+    activations: &mut Vec<Activation>,
+    generator: Rc<RefCell<Object>>,
+    value: Value,
+    kind: Value,
+) -> Result<(), EvalError> {
+    let state = generator
+        .borrow_mut()
+        .replace_generator_state(GeneratorState::Running);
+    match state {
+        Some(GeneratorState::Suspended(code, frame)) => {
+            resume_frame(activations, generator, code, *frame, value, kind);
+            Ok(())
+        }
+        _ => Err(EvalError::NotImplemented(
+            "resuming an async function that isn't suspended".to_owned(),
+        )),
+    }
+}
+
+/// Go on running the frame of a generator or async function, with the
+/// value and the resume kind as the result of the `Yield` or `Await` it's
+/// suspended at, as `CheckResumeKind` expects.
+fn resume_frame(
+    activations: &mut Vec<Activation>,
+    generator: Rc<RefCell<Object>>,
+    code: Rc<FunctionCode>,
+    mut frame: Frame,
+    value: Value,
+    kind: Value,
+) {
+    frame.stack.push(value);
+    frame.stack.push(Value::Object(generator.clone()));
+    frame.stack.push(kind);
+    activations.push(Activation {
+        code: Code::Function(code),
+        frame,
+        generator: Some(generator),
+        new_object: None,
+        assigned_value: None,
+    });
+}
+
+/// Start running the function's bytecode, in a frame whose environment is
+/// `env`, that of the code that made the function. For a constructor run by
+/// `new`, `constructing` is the new object and the `new.target` value; see
+/// `Activation::new_object`.
+fn run_function(
+    activations: &mut Vec<Activation>,
+    code: &Rc<FunctionCode>,
+    env: Option<Rc<Environment>>,
+    this: Value,
+    args: Vec<Value>,
+    constructing: Option<(Value, Value)>,
+) -> Result<Option<Value>, EvalError> {
+    if activations.len() >= MAX_CALL_DEPTH {
+        return Err(EvalError::TooMuchRecursion);
+    }
+    // TODO: the `arguments` object, for the arguments without a parameter.
+    let mut args = args;
+    args.resize(code.num_params as usize, Value::Undefined);
+    let mut frame = Frame::new(this, args, code.frame_slot_count as usize, env);
+    let new_object = constructing.map(|(new_object, new_target)| {
+        frame.new_target = new_target;
+        new_object
+    });
+    activations.push(Activation {
+        code: Code::Function(code.clone()),
+        frame,
+        generator: None,
+        new_object,
+        assigned_value: None,
+    });
+    Ok(None)
+}
+
+/// Throw `error` in the frame on top of `activations`, dropping the frames
+/// that don't catch it. A generator that throws is completed.
+fn throw(activations: &mut Vec<Activation>, error: EvalError) -> Result<(), EvalError> {
+    let mut error = error;
+    while let Some(activation) = activations.last_mut() {
+        match unwind(activation.code.emit_result(), &mut activation.frame, error) {
+            Ok(()) => return Ok(()),
+            Err(uncaught) => error = uncaught,
+        }
+        if let Some(generator) = activations.pop().unwrap().generator {
+            generator
+                .borrow_mut()
+                .replace_generator_state(GeneratorState::Completed);
+        }
+    }
+    Err(error)
+}
+
+/// Jump to the handler of the innermost `try` statement whose `try` block,
+/// or `catch` block when there's a `finally` block, threw `error`. Without
+/// one, the error is returned.
+fn unwind(emit: &EmitResult, frame: &mut Frame, error: EvalError) -> Result<(), EvalError> {
+    let exception = match &error {
+        EvalError::Thrown(value) => value.clone(),
+        EvalError::TypeError(message) => type_error_object(message),
+        _ => return Err(error),
+    };
+    let pc = frame.pc;
+    let note = match emit
+        .try_notes
+        .iter()
+        .find(|note| note.start <= pc && pc < note.start + note.length)
+    {
+        Some(note) => note,
+        None => return Err(error),
+    };
+
+    frame.stack.truncate(note.stack_depth);
+    // The `with` statements and the scopes entered in the `try` block are
+    // left.
+    frame
+        .with_objects
+        .retain(|(_, enter_pc)| *enter_pc < note.start);
+    frame.environments.pop_from(note.start);
+    match note.kind {
+        TryNoteKind::Catch => frame.exception = Some(exception),
+        // The `finally` block rethrows it when done.
+        TryNoteKind::Finally => {
+            frame.stack.push(Value::Bool(true));
+            frame.stack.push(exception);
+        }
+    }
+    frame.pc = note.start + note.length;
+    Ok(())
+}
 
-            Opcode::Pos => {
-                let v = stack.pop().ok_or(EvalError::EmptyStack)?;
-                stack.push(JSValue::Number(to_number(&v)));
+/// Pop the arguments of a call: `argc` values, or for `SpreadCall` and
+/// `SpreadNew`, the array of them.
+fn pop_args(
+    emit: &EmitResult,
+    op: Opcode,
+    pc: usize,
+    stack: &mut Vec<Value>,
+) -> Result<Vec<Value>, EvalError> {
+    if let Opcode::SpreadCall | Opcode::SpreadNew = op {
+        return match stack.pop().ok_or(EvalError::EmptyStack)? {
+            Value::Object(array) => {
+                let array = array.borrow();
+                let length = array.array_length().unwrap_or(0);
+                Ok((0..length).map(|i| array.get(i.to_string())).collect())
             }
+            _ => Err(EvalError::NotImplemented("not an array".to_owned())),
+        };
+    }
+    let argc = emit.read_u16(pc + 1) as usize;
+    if stack.len() < argc {
+        return Err(EvalError::EmptyStack);
+    }
+    Ok(stack.split_off(stack.len() - argc))
+}
+
+/// Pop the generator object an instruction of a generator or an async
+/// function takes.
+fn pop_generator(stack: &mut Vec<Value>) -> Result<Rc<RefCell<Object>>, EvalError> {
+    match stack.pop().ok_or(EvalError::EmptyStack)? {
+        Value::Object(generator) => Ok(generator),
+        _ => Err(EvalError::NotImplemented("not a generator".to_owned())),
+    }
+}
+
+/// The private name on top of the stack, which `NewPrivateName` made.
+fn pop_private_name(stack: &mut Vec<Value>) -> Result<Rc<RefCell<Object>>, EvalError> {
+    match stack.pop().ok_or(EvalError::EmptyStack)? {
+        Value::Object(name) if name.borrow().private_name().is_some() => Ok(name),
+        _ => Err(EvalError::NotImplemented("not a private name".to_owned())),
+    }
+}
+
+/// The TypeError of getting or setting a private field that `obj` doesn't
+/// have.
+fn missing_private_field(obj: &Value, name: &Rc<RefCell<Object>>) -> EvalError {
+    EvalError::TypeError(format!(
+        "can't access private field {} of {}",
+        name.borrow().private_name().unwrap_or_default(),
+        obj.to_js_string()
+    ))
+}
+
+/// The value of a TypeError thrown by the interpreter, when it's caught.
+// TODO: TypeError objects, with their prototype.
+pub(crate) fn type_error_object(message: &str) -> Value {
+    let mut error = Object::new();
+    error.set("name".to_owned(), Value::String("TypeError".to_owned()));
+    error.set("message".to_owned(), Value::String(message.to_owned()));
+    Value::Object(Rc::new(RefCell::new(error)))
+}
+
+/// Execute the instruction at `frame.pc`, returning why the frame stops if
+/// it does.
+fn step(
+    emit: &EmitResult,
+    global: &Rc<RefCell<Object>>,
+    host: &ModuleHost,
+    frame: &mut Frame,
+    jobs: &mut VecDeque<Job>,
+) -> Result<Option<Exit>, EvalError> {
+    let pc = frame.pc;
+    let stack = &mut frame.stack;
+
+    let op = match Opcode::try_from(emit.bytecode[pc]) {
+        Ok(op) => op,
+        Err(_) => {
+            return Err(EvalError::NotImplemented(format!(
+                "{} is not an opcode",
+                emit.bytecode[pc]
+            )))
+        }
+    };
 
-            Opcode::Neg => {
-                let v = stack.pop().ok_or(EvalError::EmptyStack)?;
-                stack.push(JSValue::Number(-to_number(&v)));
+    match op {
+        Opcode::Int8 => stack.push(Value::Number(emit.bytecode[pc + 1] as f64)),
+
+        Opcode::Add => {
+            let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            // TODO: ToPrimitive on objects, which can give strings.
+            if let (Value::String(_), _) | (_, Value::String(_)) = (&lhs, &rhs) {
+                stack.push(Value::String(lhs.to_js_string() + &rhs.to_js_string()));
+            } else {
+                check_not_bigint(&lhs, op)?;
+                check_not_bigint(&rhs, op)?;
+                stack.push(Value::Number(to_number(&lhs) + to_number(&rhs)))
             }
+        }
 
-            Opcode::Void => {
-                stack.pop().ok_or(EvalError::EmptyStack)?;
-                stack.push(JSValue::Undefined);
+        Opcode::Sub => {
+            let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            check_not_bigint(&lhs, op)?;
+            check_not_bigint(&rhs, op)?;
+            stack.push(Value::Number(to_number(&lhs) - to_number(&rhs)))
+        }
+
+        Opcode::Pow => {
+            let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            check_not_bigint(&lhs, op)?;
+            check_not_bigint(&rhs, op)?;
+            stack.push(Value::Number(exponentiate(
+                to_number(&lhs),
+                to_number(&rhs),
+            )))
+        }
+
+        Opcode::StrictEq | Opcode::StrictNe => {
+            let rhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let lhs = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let equal = strict_equals(&lhs, &rhs);
+            stack.push(Value::Bool(equal == (op == Opcode::StrictEq)));
+        }
+
+        Opcode::Pos => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            check_not_bigint(&v, op)?;
+            stack.push(Value::Number(to_number(&v)));
+        }
+
+        Opcode::ToNumeric => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(match v {
+                Value::BigInt(_) => v,
+                _ => Value::Number(to_number(&v)),
+            });
+        }
+
+        Opcode::Inc | Opcode::Dec => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(match v {
+                Value::BigInt(ref n) => Value::BigInt(step_bigint(n, op == Opcode::Inc)),
+                _ => {
+                    let delta = if op == Opcode::Inc { 1.0 } else { -1.0 };
+                    Value::Number(to_number(&v) + delta)
+                }
+            });
+        }
+
+        Opcode::Neg => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(match v {
+                Value::BigInt(ref n) => Value::BigInt(negate_bigint(n)),
+                _ => Value::Number(-to_number(&v)),
+            });
+        }
+
+        Opcode::ToString => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(Value::String(v.to_js_string()));
+        }
+
+        Opcode::Void => {
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(Value::Undefined);
+        }
+
+        // Getting a name that isn't declared gives undefined, so `typeof`
+        // of one is "undefined" either way.
+        Opcode::Typeof | Opcode::TypeofExpr => {
+            let v = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(Value::String(v.type_of().to_owned()));
+        }
+
+        Opcode::In => {
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(Value::Bool(has_property(&key, &obj)?));
+        }
+
+        Opcode::Instanceof => {
+            let constructor = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(Value::Bool(instance_of(&value, &constructor)?));
+        }
+
+        Opcode::Dup => {
+            let value = stack.last().ok_or(EvalError::EmptyStack)?.clone();
+            stack.push(value);
+        }
+
+        Opcode::Dup2 => {
+            if stack.len() < 2 {
+                return Err(EvalError::EmptyStack);
             }
+            let values = stack[stack.len() - 2..].to_vec();
+            stack.extend(values);
+        }
 
-            Opcode::Pop => {
-                stack.pop().ok_or(EvalError::EmptyStack)?;
+        Opcode::DupAt => {
+            let n = emit.read_u24(pc + 1) as usize;
+            if stack.len() <= n {
+                return Err(EvalError::EmptyStack);
             }
+            let value = stack[stack.len() - 1 - n].clone();
+            stack.push(value);
+        }
 
-            Opcode::SetRval => {
-                rval = stack.pop().ok_or(EvalError::EmptyStack)?;
+        Opcode::Pick => {
+            let n = emit.bytecode[pc + 1] as usize;
+            if stack.len() <= n {
+                return Err(EvalError::EmptyStack);
             }
+            let value = stack.remove(stack.len() - 1 - n);
+            stack.push(value);
+        }
 
-            Opcode::RetRval => {
-                return Ok(rval);
+        Opcode::Unpick => {
+            let n = emit.bytecode[pc + 1] as usize;
+            if stack.len() <= n {
+                return Err(EvalError::EmptyStack);
             }
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.insert(stack.len() - n, value);
+        }
 
-            Opcode::IfEq => {
-                let b = to_boolean(&stack.pop().ok_or(EvalError::EmptyStack)?);
-                if !b {
-                    let offset = emit.read_offset(pc + 1);
-                    pc = (pc as isize + offset) as usize;
-                    continue;
-                }
+        Opcode::Swap => {
+            let len = stack.len();
+            if len < 2 {
+                return Err(EvalError::EmptyStack);
             }
+            stack.swap(len - 2, len - 1);
+        }
+
+        Opcode::Pop => {
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+        }
+
+        Opcode::SetRval => {
+            frame.rval = stack.pop().ok_or(EvalError::EmptyStack)?;
+        }
 
-            Opcode::Goto => {
+        Opcode::RetRval => {
+            return Ok(Some(Exit::Return(frame.rval.clone())));
+        }
+
+        Opcode::IfEq => {
+            let b = to_boolean(&stack.pop().ok_or(EvalError::EmptyStack)?);
+            if !b {
                 let offset = emit.read_offset(pc + 1);
-                pc = (pc as isize + offset) as usize;
-                continue;
+                frame.pc = (pc as isize + offset) as usize;
+                return Ok(None);
             }
+        }
 
-            Opcode::BindGName => {
-                // TODO: proper binding
-                stack.push(JSValue::Object(global.clone()))
+        Opcode::IfNe => {
+            let b = to_boolean(&stack.pop().ok_or(EvalError::EmptyStack)?);
+            if b {
+                let offset = emit.read_offset(pc + 1);
+                frame.pc = (pc as isize + offset) as usize;
+                return Ok(None);
             }
+        }
+
+        Opcode::Goto => {
+            let offset = emit.read_offset(pc + 1);
+            frame.pc = (pc as isize + offset) as usize;
+            return Ok(None);
+        }
+
+        Opcode::BindGName => {
+            // TODO: proper binding
+            stack.push(Value::Object(global.clone()))
+        }
+
+        Opcode::GetGName => {
+            let atom = emit.read_atom(pc + 1);
+            stack.push(global.borrow().get(atom));
+        }
+
+        // TODO: StrictSetGName should throw a ReferenceError if the name
+        // isn't declared.
+        Opcode::SetGName | Opcode::StrictSetGName => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
 
-            Opcode::GetGName => {
-                let atom = emit.read_atom(pc + 1);
-                stack.push(global.borrow().get(atom));
+            let atom = emit.read_atom(pc + 1);
+            match obj {
+                Value::Object(ref obj) => {
+                    obj.borrow_mut().set(atom, value.clone());
+                }
+                _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
             }
 
-            Opcode::SetGName => {
-                let value = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(value);
+        }
+
+        Opcode::BindName => {
+            let atom = emit.read_atom(pc + 1);
+            let object = find_with_object(&frame.with_objects, &atom).unwrap_or(global);
+            stack.push(Value::Object(object.clone()));
+        }
 
-                let atom = emit.read_atom(pc + 1);
-                match obj {
-                    JSValue::Object(ref obj) => {
-                        obj.borrow_mut().set(atom, value.clone());
+        Opcode::GetName => {
+            let atom = emit.read_atom(pc + 1);
+            match find_with_object(&frame.with_objects, &atom) {
+                Some(object) => {
+                    let object = Value::Object(object.clone());
+                    if let Some(exit) = get_or_call_getter(object, atom, stack)? {
+                        return Ok(Some(exit));
                     }
-                    _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
                 }
-
-                stack.push(value);
+                None => stack.push(global.borrow().get(atom)),
             }
+        }
 
-            Opcode::InitProp => {
-                let value = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+        // Only sloppy mode code can delete a variable.
+        Opcode::DelName => {
+            let atom = emit.read_atom(pc + 1);
+            let object = find_with_object(&frame.with_objects, &atom).unwrap_or(global);
+            let deleted = object.borrow_mut().delete(&atom);
+            stack.push(Value::Bool(deleted));
+        }
 
-                let atom = emit.read_atom(pc + 1);
-                match obj {
-                    JSValue::Object(ref obj) => {
-                        obj.borrow_mut().set(atom, value);
-                    }
-                    _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
+        Opcode::EnterWith => {
+            let object = match stack.pop().ok_or(EvalError::EmptyStack)? {
+                Value::Object(object) => object,
+                value @ Value::Null | value @ Value::Undefined => {
+                    return Err(EvalError::TypeError(format!(
+                        "can't use {} as the object of a with statement",
+                        value.to_js_string()
+                    )))
+                }
+                // TODO: wrapper objects for primitives.
+                _ => {
+                    return Err(EvalError::NotImplemented(
+                        "with statement on a primitive or a function".to_owned(),
+                    ))
                 }
+            };
+            frame.with_objects.push((object, pc));
+        }
+
+        Opcode::LeaveWith => {
+            frame.with_objects.pop();
+        }
+
+        Opcode::GetArg => {
+            let index = emit.read_u16(pc + 1) as usize;
+            stack.push(frame.args[index].clone());
+        }
+
+        Opcode::SetArg => {
+            let index = emit.read_u16(pc + 1) as usize;
+            frame.args[index] = stack.last().ok_or(EvalError::EmptyStack)?.clone();
+        }
+
+        // The environment of a function, or of a scope in it, made from the
+        // scope note.
+        Opcode::PushVarEnv | Opcode::PushLexicalEnv => {
+            let scope = &emit.scopes[emit.read_u32(pc + 1) as usize];
+            frame.environments.push(scope.environment_slot_count(), pc);
+        }
 
-                stack.push(obj);
+        Opcode::PopLexicalEnv => {
+            frame.environments.pop();
+        }
+
+        Opcode::FreshenLexicalEnv => {
+            frame.environments.freshen();
+        }
+
+        Opcode::GetAliasedVar => {
+            let hops = emit.bytecode[pc + 1];
+            let slot = emit.read_u24(pc + 2);
+            stack.push(frame.environments.get(hops, slot));
+        }
+
+        Opcode::SetAliasedVar => {
+            let hops = emit.bytecode[pc + 1];
+            let slot = emit.read_u24(pc + 2);
+            let value = stack.last().ok_or(EvalError::EmptyStack)?.clone();
+            frame.environments.set(hops, slot, value);
+        }
+
+        Opcode::GetLocal => {
+            let index = emit.read_u24(pc + 1) as usize;
+            stack.push(frame.locals[index].clone());
+        }
+
+        Opcode::SetLocal => {
+            let index = emit.read_u24(pc + 1) as usize;
+            frame.locals[index] = stack.last().ok_or(EvalError::EmptyStack)?.clone();
+        }
+
+        Opcode::GetProp | Opcode::CallProp => {
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            if let Some(exit) = get_or_call_getter(obj, emit.read_atom(pc + 1), stack)? {
+                return Ok(Some(exit));
             }
+        }
 
-            Opcode::InitElemArray => {
-                let value = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+        Opcode::DelProp | Opcode::StrictDelProp => {
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let key = Value::String(emit.read_atom(pc + 1));
+            let deleted = delete_property(&obj, &key, op == Opcode::StrictDelProp)?;
+            stack.push(Value::Bool(deleted));
+        }
 
-                let index = emit.read_u32(pc + 1);
-                match obj {
-                    JSValue::Object(ref obj) => {
-                        obj.borrow_mut().set(index.to_string(), value);
-                    }
-                    _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
+        Opcode::DelElem | Opcode::StrictDelElem => {
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let deleted = delete_property(&obj, &key, op == Opcode::StrictDelElem)?;
+            stack.push(Value::Bool(deleted));
+        }
+
+        Opcode::GetElem | Opcode::CallElem => {
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            if let Value::Symbol(_) = key {
+                stack.push(get_element(&obj, &key)?);
+            } else {
+                if let Some(exit) = get_or_call_getter(obj, key.to_js_string(), stack)? {
+                    return Ok(Some(exit));
                 }
+            }
+        }
+
+        // `SetName` assigns to the object from `BindName`.
+        Opcode::SetProp | Opcode::StrictSetProp | Opcode::SetName | Opcode::StrictSetName => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            if let Some(exit) = set_or_call_setter(obj, emit.read_atom(pc + 1), value, stack)? {
+                return Ok(Some(exit));
+            }
+        }
+
+        Opcode::SetElem | Opcode::StrictSetElem => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            if let Value::Symbol(_) = key {
+                return Err(EvalError::NotImplemented(
+                    "assigning to a Symbol-keyed property".to_owned(),
+                ));
+            }
+            if let Some(exit) = set_or_call_setter(obj, key.to_js_string(), value, stack)? {
+                return Ok(Some(exit));
+            }
+        }
+
+        Opcode::InitProp | Opcode::InitHiddenProp | Opcode::InitLockedProp => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
 
-                stack.push(obj);
+            let atom = emit.read_atom(pc + 1);
+            match obj {
+                Value::Object(ref obj) if op == Opcode::InitLockedProp => {
+                    obj.borrow_mut().set_locked(atom, value);
+                }
+                Value::Object(ref obj) => {
+                    obj.borrow_mut().set(atom, value);
+                }
+                _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
             }
 
-            Opcode::GImplicitThis => {
-                // "The result is always `undefined` except when the name refers to a
-                // binding in a non-syntactic `with` environment."
-                stack.push(JSValue::Undefined);
+            stack.push(obj);
+        }
+
+        Opcode::InitElem | Opcode::InitHiddenElem => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            init_element(&obj, &key, value)?;
+            stack.push(obj);
+        }
+
+        Opcode::InitPropGetter
+        | Opcode::InitHiddenPropGetter
+        | Opcode::InitPropSetter
+        | Opcode::InitHiddenPropSetter => {
+            let function = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.last().ok_or(EvalError::EmptyStack)?;
+            let is_getter = matches!(op, Opcode::InitPropGetter | Opcode::InitHiddenPropGetter);
+            init_accessor(obj, emit.read_atom(pc + 1), function, is_getter)?;
+        }
+
+        Opcode::InitElemGetter
+        | Opcode::InitHiddenElemGetter
+        | Opcode::InitElemSetter
+        | Opcode::InitHiddenElemSetter => {
+            let function = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.last().ok_or(EvalError::EmptyStack)?;
+            if let Value::Symbol(_) = key {
+                return Err(EvalError::NotImplemented(
+                    "defining a Symbol-keyed property".to_owned(),
+                ));
             }
+            let is_getter = matches!(op, Opcode::InitElemGetter | Opcode::InitHiddenElemGetter);
+            init_accessor(obj, key.to_js_string(), function, is_getter)?;
+        }
 
-            Opcode::Call => {
-                let argc = emit.read_u16(pc + 1) as usize;
+        Opcode::InitElemInc => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let index = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            init_element(&obj, &index, value)?;
+            stack.push(obj);
+            stack.push(Value::Number(to_number(&index) + 1.0));
+        }
+
+        Opcode::CopyDataProperties => {
+            let excluded = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let source = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let target = stack.last().ok_or(EvalError::EmptyStack)?;
+            copy_data_properties(target, &source, &excluded)?;
+        }
 
-                if stack.len() < argc {
-                    return Err(EvalError::EmptyStack);
+        Opcode::InitElemArray => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+
+            let index = emit.read_u32(pc + 1);
+            match obj {
+                Value::Object(ref obj) => {
+                    obj.borrow_mut().set(index.to_string(), value);
                 }
+                _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
+            }
+
+            stack.push(obj);
+        }
 
-                let args = stack.split_off(stack.len() - argc);
-                let thisv = stack.pop().ok_or(EvalError::EmptyStack)?;
-                let callee = stack.pop().ok_or(EvalError::EmptyStack)?;
+        Opcode::NewPrivateName => {
+            let name = emit.read_atom(pc + 1);
+            stack.push(Value::Object(Rc::new(RefCell::new(
+                Object::new_private_name(name),
+            ))));
+        }
 
-                match callee {
-                    JSValue::NativeFunction(fun) => {
-                        stack.push(fun(thisv, &args));
+        Opcode::InitPrivateField => {
+            let name = pop_private_name(stack)?;
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            match obj {
+                Value::Object(ref obj) => {
+                    let description = name.borrow().private_name().unwrap_or_default().to_owned();
+                    if !obj.borrow_mut().init_private_field(name, value) {
+                        return Err(EvalError::TypeError(format!(
+                            "private field {} is already defined",
+                            description
+                        )));
                     }
-                    _ => return Err(EvalError::NotImplemented("non function callee".to_owned())),
                 }
+                _ => return Err(EvalError::NotImplemented("not an object".to_owned())),
             }
+            stack.push(obj);
+        }
 
-            Opcode::And => {
-                let cond = to_boolean(stack.last().ok_or(EvalError::EmptyStack)?);
-                if !cond {
-                    let offset = emit.read_offset(pc + 1);
-                    pc = (pc as isize + offset) as usize;
-                    continue;
+        Opcode::GetPrivateField => {
+            let name = pop_private_name(stack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let value = match obj {
+                Value::Object(ref obj) => obj.borrow().get_private_field(&name),
+                _ => None,
+            };
+            stack.push(value.ok_or_else(|| missing_private_field(&obj, &name))?);
+        }
+
+        Opcode::SetPrivateField => {
+            let name = pop_private_name(stack)?;
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let defined = match obj {
+                Value::Object(ref obj) => obj.borrow_mut().set_private_field(&name, value.clone()),
+                _ => false,
+            };
+            if !defined {
+                return Err(missing_private_field(&obj, &name));
+            }
+            stack.push(value);
+        }
+
+        Opcode::FunctionThis => stack.push(frame.this.clone()),
+
+        Opcode::NewTarget => stack.push(frame.new_target.clone()),
+
+        Opcode::DynamicImport => {
+            let options = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let specifier = stack.pop().ok_or(EvalError::EmptyStack)?;
+            match &host.dynamic_import {
+                Some(dynamic_import) => stack.push(dynamic_import(&[specifier, options])),
+                None => {
+                    return Err(EvalError::NotImplemented(
+                        "import() without a dynamic import function".to_owned(),
+                    ))
                 }
             }
+        }
+
+        Opcode::ImportMeta => stack.push(Value::Object(host.import_meta.clone())),
+
+        Opcode::GlobalThis => stack.push(Value::Object(global.clone())),
+
+        // A function found in a `with` object is called with it as `this`.
+        Opcode::ImplicitThis => {
+            let atom = emit.read_atom(pc + 1);
+            match find_with_object(&frame.with_objects, &atom) {
+                Some(object) => stack.push(Value::Object(object.clone())),
+                None => stack.push(Value::Undefined),
+            }
+        }
 
-            Opcode::Or => {
-                let cond = to_boolean(stack.last().ok_or(EvalError::EmptyStack)?);
-                if cond {
-                    let offset = emit.read_offset(pc + 1);
-                    pc = (pc as isize + offset) as usize;
-                    continue;
+        Opcode::GImplicitThis => {
+            // "The result is always `undefined` except when the name refers to a
+            // binding in a non-syntactic `with` environment."
+            stack.push(Value::Undefined);
+        }
+
+        Opcode::Call | Opcode::CallIter | Opcode::SpreadCall => {
+            let args = pop_args(emit, op, pc, stack)?;
+            let thisv = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let callee = stack.pop().ok_or(EvalError::EmptyStack)?;
+
+            match callee {
+                // Native functions don't take a `this` value.
+                Value::NativeFunction(fun) => {
+                    stack.push(fun(&args));
+                }
+                Value::Function(function) => {
+                    return Ok(Some(Exit::Call(function, thisv, args)));
+                }
+                Value::Object(ref class) if class.borrow().class_constructor().is_some() => {
+                    return Err(EvalError::TypeError(format!(
+                        "class constructor {} can't be called without new",
+                        class
+                            .borrow()
+                            .class_constructor()
+                            .unwrap()
+                            .name
+                            .as_deref()
+                            .unwrap_or("")
+                    )))
+                }
+                _ => {
+                    return Err(EvalError::TypeError(format!(
+                        "{} is not a function",
+                        callee.to_js_string()
+                    )))
                 }
             }
+        }
+
+        // The `this` value is a placeholder for the object `New` creates.
+        Opcode::IsConstructing => stack.push(Value::Undefined),
+
+        Opcode::New | Opcode::SpreadNew => {
+            let new_target = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let args = pop_args(emit, op, pc, stack)?;
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            let callee = stack.pop().ok_or(EvalError::EmptyStack)?;
 
-            Opcode::Coalesce => {
-                let last = stack.last().ok_or(EvalError::EmptyStack)?;
-                match last {
-                    JSValue::Null | JSValue::Undefined => {}
+            match callee {
+                Value::Object(ref class) if class.borrow().class_constructor().is_some() => {
+                    let class = class.borrow();
+                    let new_object = match class.get("prototype".to_owned()) {
+                        Value::Object(prototype) => Object::with_prototype(prototype),
+                        _ => Object::new(),
+                    };
+                    let code = class.class_constructor().unwrap().clone();
+                    return Ok(Some(Exit::Construct(
+                        code,
+                        class.class_environment().cloned(),
+                        Rc::new(RefCell::new(new_object)),
+                        new_target,
+                        args,
+                    )));
+                }
+                Value::Function(ref function) => match &**function {
+                    Function::Script(code, env, Some(prototype)) => {
+                        return Ok(Some(Exit::Construct(
+                            code.clone(),
+                            env.clone(),
+                            Rc::new(RefCell::new(Object::with_prototype(prototype.clone()))),
+                            new_target,
+                            args,
+                        )));
+                    }
                     _ => {
-                        let offset = emit.read_offset(pc + 1);
-                        pc = (pc as isize + offset) as usize;
-                        continue;
+                        return Err(EvalError::TypeError(format!(
+                            "{} is not a constructor",
+                            callee.to_js_string()
+                        )))
                     }
+                },
+                _ => {
+                    return Err(EvalError::TypeError(format!(
+                        "{} is not a constructor",
+                        callee.to_js_string()
+                    )))
                 }
             }
+        }
 
-            Opcode::JumpTarget => {}
+        Opcode::Lambda => {
+            let index = emit.read_u32(pc + 1) as usize;
+            let code = emit.functions[index].clone();
+            let env = frame.environments.innermost().cloned();
+            if code.is_class_constructor {
+                stack.push(Value::Object(Rc::new(RefCell::new(Object::new_class(
+                    code, env,
+                )))));
+            } else {
+                stack.push(new_function(code, env));
+            }
+        }
 
-            Opcode::NewArray | Opcode::NewInit => {
-                stack.push(JSValue::Object(Rc::new(RefCell::new(Object::new()))));
+        Opcode::CheckObjCoercible => {
+            let value = stack.last().ok_or(EvalError::EmptyStack)?;
+            if let Value::Null | Value::Undefined = value {
+                return Err(EvalError::TypeError(format!(
+                    "can't destructure {}",
+                    value.to_js_string()
+                )));
             }
+        }
 
-            Opcode::String => {
-                stack.push(JSValue::String(emit.read_atom(pc + 1)));
+        // Symbols are property keys already. Other values are converted to
+        // strings.
+        Opcode::ToId => {
+            let key = stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.push(match key {
+                Value::Symbol(_) => key,
+                _ => Value::String(key.to_js_string()),
+            });
+        }
+
+        Opcode::CheckIsObj => match stack.last().ok_or(EvalError::EmptyStack)? {
+            Value::Object(_) | Value::NativeFunction(_) | Value::Function(_) => {}
+            value => {
+                return Err(EvalError::TypeError(format!(
+                    "{} is not an object",
+                    value.to_js_string()
+                )))
             }
+        },
+
+        // The iterator of a for-in loop is a function that returns the
+        // next property name, or undefined after the last one.
+        Opcode::Iter => {
+            let obj = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let names = match obj {
+                Value::Object(ref obj) => obj.borrow().property_names(),
+                Value::Null | Value::Undefined => Vec::new(),
+                // TODO: the properties of primitives, such as the indices
+                // of strings.
+                _ => {
+                    return Err(EvalError::NotImplemented(
+                        "for-in over a primitive".to_owned(),
+                    ))
+                }
+            };
+            let names = RefCell::new(names.into_iter());
+            stack.push(Value::NativeFunction(Rc::new(move |_args| {
+                names
+                    .borrow_mut()
+                    .next()
+                    .map_or(Value::Undefined, Value::String)
+            })));
+        }
+
+        Opcode::MoreIter => {
+            let name = match stack.last().ok_or(EvalError::EmptyStack)? {
+                Value::NativeFunction(next) => next(&[]),
+                _ => return Err(EvalError::NotImplemented("not an iterator".to_owned())),
+            };
+            stack.push(name);
+        }
+
+        Opcode::IsNoIter => {
+            let done = *stack.last().ok_or(EvalError::EmptyStack)? == Value::Undefined;
+            stack.push(Value::Bool(done));
+        }
+
+        Opcode::EndIter => {
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+        }
 
-            Opcode::True => stack.push(JSValue::Boolean(true)),
-            Opcode::False => stack.push(JSValue::Boolean(false)),
-            Opcode::Undefined => stack.push(JSValue::Undefined),
-            Opcode::Null => stack.push(JSValue::Null),
+        Opcode::And => {
+            let cond = to_boolean(stack.last().ok_or(EvalError::EmptyStack)?);
+            if !cond {
+                let offset = emit.read_offset(pc + 1);
+                frame.pc = (pc as isize + offset) as usize;
+                return Ok(None);
+            }
+        }
 
-            _ => return Err(EvalError::NotImplemented(format!("{:?}", op))),
+        Opcode::Or => {
+            let cond = to_boolean(stack.last().ok_or(EvalError::EmptyStack)?);
+            if cond {
+                let offset = emit.read_offset(pc + 1);
+                frame.pc = (pc as isize + offset) as usize;
+                return Ok(None);
+            }
         }
 
-        pc += op.instruction_length();
+        // A case matched: drop the discriminant and jump to its clause.
+        Opcode::Case => {
+            let matched = to_boolean(&stack.pop().ok_or(EvalError::EmptyStack)?);
+            if matched {
+                stack.pop().ok_or(EvalError::EmptyStack)?;
+                let offset = emit.read_offset(pc + 1);
+                frame.pc = (pc as isize + offset) as usize;
+                return Ok(None);
+            }
+        }
+
+        Opcode::Default => {
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            let offset = emit.read_offset(pc + 1);
+            frame.pc = (pc as isize + offset) as usize;
+            return Ok(None);
+        }
+
+        Opcode::Coalesce => {
+            let last = stack.last().ok_or(EvalError::EmptyStack)?;
+            match last {
+                Value::Null | Value::Undefined => {}
+                _ => {
+                    let offset = emit.read_offset(pc + 1);
+                    frame.pc = (pc as isize + offset) as usize;
+                    return Ok(None);
+                }
+            }
+        }
+
+        Opcode::JumpTarget | Opcode::LoopHead | Opcode::Try | Opcode::Finally => {}
+
+        Opcode::Throw => {
+            return Err(EvalError::Thrown(stack.pop().ok_or(EvalError::EmptyStack)?));
+        }
+
+        Opcode::Exception => {
+            stack.push(
+                frame
+                    .exception
+                    .take()
+                    .ok_or_else(|| EvalError::NotImplemented("no exception".to_owned()))?,
+            );
+        }
+
+        Opcode::GetRval => stack.push(frame.rval.clone()),
+
+        Opcode::ResumeIndex => {
+            let resume_index = emit.read_u24(pc + 1);
+            stack.push(Value::Number(resume_index as f64));
+        }
+
+        Opcode::Generator => {
+            let mut generator = Object::new_generator();
+            // TODO: %GeneratorPrototype%, for `return` and `throw`.
+            generator.set(
+                "next".to_owned(),
+                Value::Function(Rc::new(Function::GeneratorNext)),
+            );
+            stack.push(Value::Object(Rc::new(RefCell::new(generator))));
+        }
+
+        // Suspend the generator. With `InitialYield`, the value returned is
+        // the generator object itself, and with `Await`, the promise of the
+        // async function.
+        Opcode::InitialYield | Opcode::Yield | Opcode::Await => {
+            let generator = match stack.pop().ok_or(EvalError::EmptyStack)? {
+                Value::Object(generator) => generator,
+                _ => return Err(EvalError::NotImplemented("not a generator".to_owned())),
+            };
+            let value = if op == Opcode::InitialYield {
+                Value::Object(generator.clone())
+            } else {
+                stack.pop().ok_or(EvalError::EmptyStack)?
+            };
+            frame.pc = emit.resume_offsets[emit.read_u24(pc + 1) as usize];
+            return Ok(Some(Exit::Yield(generator, value)));
+        }
+
+        Opcode::AfterYield => {}
+
+        // A promise awaited by an async function that's rejected throws its
+        // reason at the `await`.
+        Opcode::CheckResumeKind => {
+            let kind = to_number(&stack.pop().ok_or(EvalError::EmptyStack)?);
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            if kind == ResumeKind::Throw as u8 as f64 {
+                let reason = stack.pop().ok_or(EvalError::EmptyStack)?;
+                return Err(EvalError::Thrown(reason));
+            }
+            if kind != ResumeKind::Normal as u8 as f64 {
+                return Err(EvalError::NotImplemented("generator return".to_owned()));
+            }
+        }
+
+        // Queue the job resuming the async function once the value is
+        // settled, and push the function's promise for the `Await`.
+        Opcode::AsyncAwait => {
+            let generator = pop_generator(stack)?;
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            promise::await_value(&generator, value, jobs);
+            stack.push(Value::Object(promise::result_promise(&generator)));
+        }
+
+        Opcode::AsyncResolve => {
+            let generator = pop_generator(stack)?;
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let result = if emit.bytecode[pc + 1] == AsyncFunctionResolveKind::Reject as u8 {
+                Err(value)
+            } else {
+                Ok(value)
+            };
+            let result_promise = promise::result_promise(&generator);
+            promise::resolve(&result_promise, result, jobs);
+            stack.push(Value::Object(result_promise));
+        }
+
+        Opcode::FinalYieldRval => {
+            stack.pop().ok_or(EvalError::EmptyStack)?;
+            return Ok(Some(Exit::Return(frame.rval.clone())));
+        }
+
+        // The `finally` block gets whether an exception was thrown, and the
+        // exception or the resume index, from the stack.
+        Opcode::Gosub => {
+            let offset = emit.read_offset(pc + 1);
+            frame.pc = (pc as isize + offset) as usize;
+            return Ok(None);
+        }
+
+        Opcode::Retsub => {
+            let value = stack.pop().ok_or(EvalError::EmptyStack)?;
+            let throwing = stack.pop().ok_or(EvalError::EmptyStack)?;
+            if to_boolean(&throwing) {
+                return Err(EvalError::Thrown(value));
+            }
+            frame.pc = emit.resume_offsets[to_number(&value) as usize];
+            return Ok(None);
+        }
+
+        Opcode::NewArray => {
+            let length = emit.read_u32(pc + 1);
+            stack.push(Value::Object(Rc::new(RefCell::new(Object::new_array(
+                length,
+            )))));
+        }
+
+        Opcode::NewInit => {
+            stack.push(Value::Object(Rc::new(RefCell::new(Object::new()))));
+        }
+
+        Opcode::String => {
+            stack.push(Value::String(emit.read_atom(pc + 1)));
+        }
+
+        Opcode::Symbol => match SymbolCode::from_u8(emit.bytecode[pc + 1]) {
+            Some(symbol) => stack.push(Value::Symbol(symbol)),
+            None => return Err(EvalError::NotImplemented("unknown symbol".to_owned())),
+        },
+
+        Opcode::BigInt => {
+            let index = emit.read_u32(pc + 1) as usize;
+            stack.push(Value::BigInt(emit.bigints[index].clone()));
+        }
+
+        Opcode::True => stack.push(Value::Bool(true)),
+        Opcode::False => stack.push(Value::Bool(false)),
+        Opcode::Undefined => stack.push(Value::Undefined),
+        Opcode::Null => stack.push(Value::Null),
+
+        _ => return Err(EvalError::NotImplemented(format!("{:?}", op))),
     }
+
+    frame.pc = pc + op.instruction_length();
+    Ok(None)
 }
@@ -0,0 +1,76 @@
+use emitter::FunctionCode;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::evaluate::Frame;
+use crate::object::Object;
+
+/// A function that runs JS code, or a built-in method that needs the
+/// interpreter, such as to get the `this` value or to resume a generator,
+/// which native functions can't.
+pub enum Function {
+    /// A function defined by a script, with the environment of the code it
+    /// was made in, where the names it refers to are kept, and its
+    /// `prototype` property, which the objects `new` makes inherit from, if
+    /// it isn't a generator.
+    // TODO: other properties of functions.
+    Script(
+        Rc<FunctionCode>,
+        Option<Rc<Environment>>,
+        Option<Rc<RefCell<Object>>>,
+    ),
+
+    /// The `next` method of generator objects.
+    GeneratorNext,
+
+    /// The `Symbol.iterator` method of iterators, which returns `this`.
+    ReturnThis,
+}
+
+impl Function {
+    /// The name, or an empty string for an anonymous function.
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Script(code, _, _) => code.name.as_deref().unwrap_or(""),
+            Function::GeneratorNext => "next",
+            Function::ReturnThis => "[Symbol.iterator]",
+        }
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<function: {}>", self.name())
+    }
+}
+
+/// Where a generator is in running its function body.
+pub(crate) enum GeneratorState {
+    /// Stopped at a `yield`, with what it needs to go on.
+    Suspended(Rc<FunctionCode>, Box<Frame>),
+
+    /// Running a `next` call.
+    Running,
+
+    /// Returned or threw.
+    Completed,
+}
+
+// The frame isn't shown, as it holds the generator object itself.
+impl fmt::Debug for GeneratorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorState::Suspended(code, frame) => write!(
+                f,
+                "Suspended({} at {})",
+                code.name.as_deref().unwrap_or("<anonymous>"),
+                frame.pc
+            ),
+            GeneratorState::Running => write!(f, "Running"),
+            GeneratorState::Completed => write!(f, "Completed"),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Converting values to `serde_json` values, like `JSON.stringify`, so that
+//! embedders and tests can look at the result of a script without knowing
+//! how objects are laid out.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::object::Object;
+use crate::value::Value;
+
+/// What `Value::to_json_with` does with NaN and the infinities, which JSON
+/// can't represent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Convert them to null, as `JSON.stringify` does.
+    #[default]
+    Null,
+    /// Fail with `JsonError::NonFinite`.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonError {
+    /// An object contains itself, directly or not.
+    Cyclic,
+    /// A BigInt, which `JSON.stringify` throws a TypeError for.
+    BigInt(String),
+    /// NaN or an infinity, when converting with `NonFinite::Error`.
+    NonFinite(f64),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Cyclic => write!(f, "cyclic object value"),
+            JsonError::BigInt(n) => write!(f, "BigInt value can't be serialized: {}n", n),
+            JsonError::NonFinite(n) => write!(f, "number can't be serialized: {}", n),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Value {
+    /// The value as JSON, following `JSON.stringify`: NaN and the infinities
+    /// become null, objects have their own properties, arrays have their
+    /// elements with holes as null, and properties whose value is undefined,
+    /// a function or a symbol are left out, or are null in arrays. Getters
+    /// aren't called, so accessor properties are left out too.
+    ///
+    /// Undefined, functions and symbols other than in objects are null. The
+    /// properties of a `serde_json` object are sorted by name, unless its
+    /// `preserve_order` feature is enabled.
+    pub fn to_json(&self) -> Result<serde_json::Value, JsonError> {
+        self.to_json_with(NonFinite::default())
+    }
+
+    /// Like `to_json`, but `non_finite` says what to do with NaN and the
+    /// infinities.
+    pub fn to_json_with(&self, non_finite: NonFinite) -> Result<serde_json::Value, JsonError> {
+        let mut converter = Converter {
+            non_finite,
+            stack: Vec::new(),
+        };
+        Ok(converter.convert(self)?.unwrap_or(serde_json::Value::Null))
+    }
+}
+
+struct Converter {
+    non_finite: NonFinite,
+    /// The objects being converted, to detect cycles. Objects that are in
+    /// several places but don't contain themselves are fine.
+    stack: Vec<Rc<RefCell<Object>>>,
+}
+
+impl Converter {
+    /// The JSON value, or None if `JSON.stringify` leaves it out.
+    fn convert(&mut self, value: &Value) -> Result<Option<serde_json::Value>, JsonError> {
+        Ok(Some(match value {
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Number(n) => self.number(*n)?,
+            Value::BigInt(n) => return Err(JsonError::BigInt(n.clone())),
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::Null => serde_json::Value::Null,
+            Value::Object(object) if object.borrow().class_constructor().is_none() => {
+                self.object(object)?
+            }
+            Value::Object(_)
+            | Value::Symbol(_)
+            | Value::NativeFunction(_)
+            | Value::Function(_)
+            | Value::Undefined => return Ok(None),
+        }))
+    }
+
+    fn number(&self, n: f64) -> Result<serde_json::Value, JsonError> {
+        if !n.is_finite() {
+            return match self.non_finite {
+                NonFinite::Null => Ok(serde_json::Value::Null),
+                NonFinite::Error => Err(JsonError::NonFinite(n)),
+            };
+        }
+        // Integers without a fraction, as `JSON.stringify` writes them, and
+        // -0 as 0.
+        const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+        if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER {
+            return Ok(serde_json::Value::from(n as i64));
+        }
+        Ok(serde_json::Value::from(n))
+    }
+
+    fn object(&mut self, object: &Rc<RefCell<Object>>) -> Result<serde_json::Value, JsonError> {
+        if self.stack.iter().any(|outer| Rc::ptr_eq(outer, object)) {
+            return Err(JsonError::Cyclic);
+        }
+        self.stack.push(object.clone());
+        let result = self.properties(&object.borrow());
+        self.stack.pop();
+        result
+    }
+
+    fn properties(&mut self, object: &Object) -> Result<serde_json::Value, JsonError> {
+        if let Some(length) = object.array_length() {
+            let mut elements = Vec::with_capacity(length as usize);
+            for index in 0..length {
+                let element = self.convert(&object.get(index.to_string()))?;
+                elements.push(element.unwrap_or(serde_json::Value::Null));
+            }
+            return Ok(serde_json::Value::Array(elements));
+        }
+
+        let mut properties = serde_json::Map::new();
+        for name in object.property_names() {
+            if let Some(value) = self.convert(&object.get(name.clone()))? {
+                properties.insert(name, value);
+            }
+        }
+        Ok(serde_json::Value::Object(properties))
+    }
+}
@@ -1,9 +1,18 @@
+mod environment;
 mod evaluate;
+mod function;
+mod json;
 mod object;
+mod promise;
+mod realm;
 mod value;
 
 #[cfg(test)]
 mod tests;
 
-pub use evaluate::{evaluate, EvalError};
-pub use value::JSValue;
+pub use evaluate::EvalError;
+pub use function::Function;
+pub use json::{JsonError, NonFinite};
+pub use object::Object;
+pub use realm::Realm;
+pub use value::{NativeFunction, Value};
@@ -1,27 +1,354 @@
-use std::collections::HashMap;
+use emitter::FunctionCode;
 
-use crate::value::JSValue;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
-#[derive(Debug)]
+use crate::environment::Environment;
+use crate::function::{Function, GeneratorState};
+use crate::promise::PromiseState;
+use crate::value::Value;
+
+/// The functions of an accessor property, defined with `get` and `set` in
+/// object literals and classes. Getting the property calls the getter, and
+/// assigning to it calls the setter.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Accessor {
+    pub(crate) getter: Option<Rc<Function>>,
+    pub(crate) setter: Option<Rc<Function>>,
+}
+
+#[derive(Debug, Default)]
 pub struct Object {
-    properties: HashMap<String, JSValue>,
+    properties: HashMap<String, Value>,
+
+    /// The accessor properties, which aren't in `properties`.
+    accessors: HashMap<String, Accessor>,
+
+    /// The names of `properties` and `accessors`, in the order they were
+    /// added.
+    names: Vec<String>,
+
+    /// The names of the properties that can't be deleted, such as the
+    /// `prototype` of a class.
+    // TODO: non-writable properties.
+    locked: HashSet<String>,
+
+    /// For arrays, the length: one more than the largest index.
+    // TODO: the `length` property.
+    array_length: Option<u32>,
+
+    /// For generator objects, the state of the generator.
+    generator: Option<GeneratorState>,
+
+    /// For the generator objects of async functions, the promise the call
+    /// returned, once it's needed.
+    async_promise: Option<Rc<RefCell<Object>>>,
+
+    /// For promises, the state of the promise.
+    promise: Option<PromiseState>,
+
+    /// The object whose properties this one inherits, if any.
+    prototype: Option<Rc<RefCell<Object>>>,
+
+    /// The private fields, keyed by the private names of the classes that
+    /// declare them; see `new_private_name`.
+    private_fields: Vec<(Rc<RefCell<Object>>, Value)>,
+
+    /// For private names, the name, such as `#x`.
+    private_name: Option<String>,
+
+    /// For classes, the constructor, which `new` runs, with the environment
+    /// of the code the class was made in.
+    class_constructor: Option<(Rc<FunctionCode>, Option<Rc<Environment>>)>,
 }
 
 impl Object {
     pub fn new() -> Self {
         Object {
             properties: HashMap::new(),
+            accessors: HashMap::new(),
+            names: Vec::new(),
+            locked: HashSet::new(),
+            array_length: None,
+            generator: None,
+            async_promise: None,
+            promise: None,
+            prototype: None,
+            private_fields: Vec::new(),
+            private_name: None,
+            class_constructor: None,
+        }
+    }
+
+    /// An object that inherits the properties of `prototype`.
+    pub fn with_prototype(prototype: Rc<RefCell<Object>>) -> Self {
+        Object {
+            prototype: Some(prototype),
+            ..Object::new()
+        }
+    }
+
+    /// An array of `length` holes.
+    pub fn new_array(length: u32) -> Self {
+        Object {
+            array_length: Some(length),
+            ..Object::new()
+        }
+    }
+
+    /// A generator object, which isn't running its function yet.
+    pub(crate) fn new_generator() -> Self {
+        Object {
+            generator: Some(GeneratorState::Running),
+            ..Object::new()
+        }
+    }
+
+    /// A promise that isn't settled yet.
+    pub(crate) fn new_promise() -> Self {
+        Object {
+            promise: Some(PromiseState::Pending(Vec::new())),
+            ..Object::new()
+        }
+    }
+
+    /// A class, whose properties are its static methods and its
+    /// `prototype`.
+    pub(crate) fn new_class(
+        constructor: Rc<FunctionCode>,
+        environment: Option<Rc<Environment>>,
+    ) -> Self {
+        Object {
+            class_constructor: Some((constructor, environment)),
+            ..Object::new()
+        }
+    }
+
+    /// A private name, such as `#x`, which a class binds for each private
+    /// name it declares every time it's made. Scripts can't get it, only
+    /// use it to key private fields, so a private field of one class isn't
+    /// a private field of another with the same name.
+    pub(crate) fn new_private_name(name: String) -> Self {
+        Object {
+            private_name: Some(name),
+            ..Object::new()
         }
     }
 
-    pub fn set(&mut self, name: String, value: JSValue) {
+    /// The name, if this is a private name.
+    pub(crate) fn private_name(&self) -> Option<&str> {
+        self.private_name.as_deref()
+    }
+
+    /// The constructor, if this is a class.
+    pub(crate) fn class_constructor(&self) -> Option<&Rc<FunctionCode>> {
+        self.class_constructor.as_ref().map(|(code, _)| code)
+    }
+
+    /// The environment the class was made in, if this is a class made in a
+    /// function or scope with one.
+    pub(crate) fn class_environment(&self) -> Option<&Rc<Environment>> {
+        self.class_constructor.as_ref()?.1.as_ref()
+    }
+
+    pub fn is_generator(&self) -> bool {
+        self.generator.is_some()
+    }
+
+    /// Replace the state of the generator, returning the old one, or None if
+    /// this isn't a generator object.
+    pub(crate) fn replace_generator_state(
+        &mut self,
+        state: GeneratorState,
+    ) -> Option<GeneratorState> {
+        self.generator
+            .as_mut()
+            .map(|old| std::mem::replace(old, state))
+    }
+
+    /// The promise an async function returned, if this is its generator
+    /// object.
+    pub(crate) fn async_promise_mut(&mut self) -> &mut Option<Rc<RefCell<Object>>> {
+        &mut self.async_promise
+    }
+
+    /// The state, if this is a promise.
+    pub(crate) fn promise_state(&self) -> Option<&PromiseState> {
+        self.promise.as_ref()
+    }
+
+    pub(crate) fn promise_state_mut(&mut self) -> Option<&mut PromiseState> {
+        self.promise.as_mut()
+    }
+
+    pub fn is_promise(&self) -> bool {
+        self.promise.is_some()
+    }
+
+    /// The length, if this is an array.
+    pub fn array_length(&self) -> Option<u32> {
+        self.array_length
+    }
+
+    /// Define the data property, replacing the property with the name, if
+    /// any. Assigning to an accessor property calls its setter instead,
+    /// which only the interpreter can do.
+    pub fn set(&mut self, name: String, value: Value) {
+        if let (Some(length), Some(index)) = (self.array_length, array_index(&name)) {
+            if index >= length {
+                self.array_length = Some(index + 1);
+            }
+        }
+        if !self.has_own(&name) {
+            self.names.push(name.clone());
+        }
+        self.accessors.remove(&name);
         self.properties.insert(name, value);
     }
 
-    pub fn get(&self, name: String) -> JSValue {
-        self.properties
-            .get(&name)
-            .unwrap_or(&JSValue::Undefined)
-            .clone()
+    /// Define the data property, like `set`, and make it one that can't be
+    /// deleted.
+    pub(crate) fn set_locked(&mut self, name: String, value: Value) {
+        self.locked.insert(name.clone());
+        self.set(name, value);
+    }
+
+    /// Delete the own property, returning false if it can't be deleted. It's
+    /// true if the object doesn't have the property.
+    pub fn delete(&mut self, name: &str) -> bool {
+        if self.locked.contains(name) {
+            return false;
+        }
+        if self.has_own(name) {
+            self.properties.remove(name);
+            self.accessors.remove(name);
+            self.names.retain(|n| n != name);
+        }
+        true
+    }
+
+    /// The value of the property, which can be inherited from the
+    /// prototype. It's undefined for an accessor property, whose value is
+    /// what its getter returns; see `accessor`.
+    pub fn get(&self, name: String) -> Value {
+        if self.accessors.contains_key(&name) {
+            return Value::Undefined;
+        }
+        match (self.properties.get(&name), &self.prototype) {
+            (Some(value), _) => value.clone(),
+            (None, Some(prototype)) => prototype.borrow().get(name),
+            (None, None) => Value::Undefined,
+        }
+    }
+
+    /// The object whose properties this one inherits, if any.
+    pub(crate) fn prototype(&self) -> Option<&Rc<RefCell<Object>>> {
+        self.prototype.as_ref()
+    }
+
+    /// Whether the object has the property itself, not inherited.
+    pub fn has_own(&self, name: &str) -> bool {
+        self.properties.contains_key(name) || self.accessors.contains_key(name)
+    }
+
+    /// Whether the object has the property, itself or from its prototype.
+    pub fn has(&self, name: &str) -> bool {
+        self.has_own(name)
+            || match &self.prototype {
+                Some(prototype) => prototype.borrow().has(name),
+                None => false,
+            }
+    }
+
+    /// The property, if it's an accessor property, which can be inherited
+    /// from the prototype.
+    pub(crate) fn accessor(&self, name: &str) -> Option<Accessor> {
+        if let Some(accessor) = self.accessors.get(name) {
+            return Some(accessor.clone());
+        }
+        match (self.properties.contains_key(name), &self.prototype) {
+            (false, Some(prototype)) => prototype.borrow().accessor(name),
+            _ => None,
+        }
+    }
+
+    /// Define the getter of the accessor property, keeping its setter if
+    /// it's already one, as when an object literal has both.
+    pub(crate) fn set_getter(&mut self, name: String, getter: Rc<Function>) {
+        self.own_accessor(name).getter = Some(getter);
+    }
+
+    /// Define the setter of the accessor property, like `set_getter`.
+    pub(crate) fn set_setter(&mut self, name: String, setter: Rc<Function>) {
+        self.own_accessor(name).setter = Some(setter);
+    }
+
+    // The accessor property, replacing the data property with the name, if
+    // any.
+    fn own_accessor(&mut self, name: String) -> &mut Accessor {
+        if !self.has_own(&name) {
+            self.names.push(name.clone());
+        }
+        self.properties.remove(&name);
+        self.accessors.entry(name).or_default()
+    }
+
+    // The private field with the private name `name`, if it's defined.
+    fn private_field_mut(&mut self, name: &Rc<RefCell<Object>>) -> Option<&mut Value> {
+        self.private_fields
+            .iter_mut()
+            .find(|(key, _)| Rc::ptr_eq(key, name))
+            .map(|(_, value)| value)
+    }
+
+    /// The value of the private field with the private name `name`, or None
+    /// if it isn't defined.
+    pub(crate) fn get_private_field(&self, name: &Rc<RefCell<Object>>) -> Option<Value> {
+        self.private_fields
+            .iter()
+            .find(|(key, _)| Rc::ptr_eq(key, name))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Set the private field, returning false if it isn't defined.
+    pub(crate) fn set_private_field(&mut self, name: &Rc<RefCell<Object>>, value: Value) -> bool {
+        match self.private_field_mut(name) {
+            Some(field) => {
+                *field = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Define the private field, returning false if it's already defined.
+    pub(crate) fn init_private_field(&mut self, name: Rc<RefCell<Object>>, value: Value) -> bool {
+        if self.private_field_mut(&name).is_some() {
+            return false;
+        }
+        self.private_fields.push((name, value));
+        true
+    }
+
+    /// The names of the properties in the order `for-in` visits them: array
+    /// indices in increasing order, then the other names in the order they
+    /// were added.
+    pub fn property_names(&self) -> Vec<String> {
+        let (mut indices, others): (Vec<&String>, Vec<&String>) = self
+            .names
+            .iter()
+            .partition(|name| array_index(name).is_some());
+        indices.sort_by_key(|name| array_index(name));
+        indices.into_iter().chain(others).cloned().collect()
+    }
+}
+
+/// The array index `name` is the canonical string of, such as 3 for "3" but
+/// not for "03". The largest u32 isn't an array index.
+fn array_index(name: &str) -> Option<u32> {
+    match name.parse::<u32>() {
+        Ok(index) if index != u32::MAX && index.to_string() == name => Some(index),
+        _ => None,
     }
 }
@@ -0,0 +1,149 @@
+use emitter::ResumeKind;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::evaluate::type_error_object;
+use crate::object::Object;
+use crate::value::Value;
+
+/// The state of a promise, such as the one an async function returns.
+///
+/// Only async functions make promises, and only `await` and their `return`
+/// look at them: there are no `then` methods, and other objects, even ones
+/// with a `then` method, aren't promises.
+pub(crate) enum PromiseState {
+    /// Not settled yet, with what to do when it is.
+    Pending(Vec<Reaction>),
+    Fulfilled(Value),
+    Rejected(Value),
+}
+
+/// What a pending promise does when it's settled.
+pub(crate) enum Reaction {
+    /// Resume the async function, whose generator object this is, at the
+    /// `await` it's suspended at.
+    Resume(Rc<RefCell<Object>>),
+
+    /// Settle the other promise the same way, as it was resolved with this
+    /// one.
+    Resolve(Rc<RefCell<Object>>),
+}
+
+// The reactions aren't shown, as they can hold the promise itself.
+impl fmt::Debug for PromiseState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromiseState::Pending(reactions) => write!(f, "Pending({})", reactions.len()),
+            PromiseState::Fulfilled(value) => write!(f, "Fulfilled({:?})", value),
+            PromiseState::Rejected(reason) => write!(f, "Rejected({:?})", reason),
+        }
+    }
+}
+
+/// Resuming an async function at an `await`, with the value or the reason
+/// of the promise it awaited. Jobs run in the order they're queued, after the
+/// script, each one until the function suspends again or finishes.
+pub(crate) struct Job {
+    pub(crate) generator: Rc<RefCell<Object>>,
+    pub(crate) value: Value,
+    pub(crate) kind: ResumeKind,
+}
+
+/// The promise the call of the async function returned, whose generator
+/// object `generator` is.
+pub(crate) fn result_promise(generator: &Rc<RefCell<Object>>) -> Rc<RefCell<Object>> {
+    let mut generator = generator.borrow_mut();
+    generator
+        .async_promise_mut()
+        .get_or_insert_with(|| Rc::new(RefCell::new(Object::new_promise())))
+        .clone()
+}
+
+/// The state of `value`, if it's a settled promise, as the value or the
+/// reason. None if it's pending.
+fn settled_result(value: &Value) -> Option<Result<Value, Value>> {
+    let promise = match value {
+        Value::Object(promise) => promise,
+        _ => return Some(Ok(value.clone())),
+    };
+    match promise.borrow().promise_state() {
+        None => Some(Ok(value.clone())),
+        Some(PromiseState::Pending(_)) => None,
+        Some(PromiseState::Fulfilled(value)) => Some(Ok(value.clone())),
+        Some(PromiseState::Rejected(reason)) => Some(Err(reason.clone())),
+    }
+}
+
+/// Queue the job resuming `generator` with `result`.
+fn queue_resume(
+    generator: Rc<RefCell<Object>>,
+    result: Result<Value, Value>,
+    jobs: &mut VecDeque<Job>,
+) {
+    let (value, kind) = match result {
+        Ok(value) => (value, ResumeKind::Normal),
+        Err(reason) => (reason, ResumeKind::Throw),
+    };
+    jobs.push_back(Job {
+        generator,
+        value,
+        kind,
+    });
+}
+
+/// Suspend the async function whose generator object is `generator` until
+/// `value` is settled, if it's a promise. Otherwise, or if it's already
+/// settled, it's resumed by the next job queued.
+pub(crate) fn await_value(generator: &Rc<RefCell<Object>>, value: Value, jobs: &mut VecDeque<Job>) {
+    match settled_result(&value) {
+        Some(result) => queue_resume(generator.clone(), result, jobs),
+        None => add_reaction(&value, Reaction::Resume(generator.clone())),
+    }
+}
+
+/// Settle `promise` with `result`, or when `result` is a promise that's
+/// pending, once it's settled. A promise is only settled once.
+pub(crate) fn resolve(
+    promise: &Rc<RefCell<Object>>,
+    result: Result<Value, Value>,
+    jobs: &mut VecDeque<Job>,
+) {
+    let result = match result {
+        Ok(Value::Object(value)) if Rc::ptr_eq(&value, promise) => {
+            Err(type_error_object("a promise can't be resolved with itself"))
+        }
+        Ok(value) => match settled_result(&value) {
+            Some(result) => result,
+            None => return add_reaction(&value, Reaction::Resolve(promise.clone())),
+        },
+        Err(reason) => Err(reason),
+    };
+    let state = match &result {
+        Ok(value) => PromiseState::Fulfilled(value.clone()),
+        Err(reason) => PromiseState::Rejected(reason.clone()),
+    };
+    let reactions = match promise.borrow_mut().promise_state_mut() {
+        Some(old @ PromiseState::Pending(_)) => match std::mem::replace(old, state) {
+            PromiseState::Pending(reactions) => reactions,
+            _ => unreachable!(),
+        },
+        _ => return,
+    };
+    for reaction in reactions {
+        match reaction {
+            Reaction::Resume(generator) => queue_resume(generator, result.clone(), jobs),
+            Reaction::Resolve(other) => resolve(&other, result.clone(), jobs),
+        }
+    }
+}
+
+fn add_reaction(promise: &Value, reaction: Reaction) {
+    if let Value::Object(promise) = promise {
+        if let Some(PromiseState::Pending(reactions)) = promise.borrow_mut().promise_state_mut() {
+            reactions.push(reaction);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use emitter::EmitResult;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::evaluate::{evaluate, EvalError, ModuleHost};
+use crate::object::Object;
+use crate::value::{NativeFunction, Value};
+
+/// The global environment scripts are evaluated in, including the functions
+/// the host provides to them.
+///
+/// Globals are kept between calls to `evaluate`, so a script can use the
+/// globals set by the scripts evaluated before it in the same realm.
+pub struct Realm {
+    global: Rc<RefCell<Object>>,
+    dynamic_import: Option<Rc<NativeFunction>>,
+}
+
+impl Realm {
+    pub fn new() -> Self {
+        let global = Rc::new(RefCell::new(Object::new()));
+        global
+            .borrow_mut()
+            .set("undefined".to_owned(), Value::Undefined);
+        Realm {
+            global,
+            dynamic_import: None,
+        }
+    }
+
+    /// Make `fun` callable from scripts as the global function `name`. It is
+    /// called with the arguments of the call, and its result is the value of
+    /// the call expression.
+    pub fn register_fn(&mut self, name: &str, fun: Box<NativeFunction>) {
+        self.global
+            .borrow_mut()
+            .set(name.to_owned(), Value::NativeFunction(Rc::from(fun)));
+    }
+
+    /// Make `fun` the function that `import(specifier, options)` calls to
+    /// load a module, with the specifier and the options, or undefined
+    /// without them. Its result is the value of the `import()` expression.
+    /// Without one, `import()` fails with `EvalError::NotImplemented`.
+    pub fn register_dynamic_import(&mut self, fun: Box<NativeFunction>) {
+        self.dynamic_import = Some(Rc::from(fun));
+    }
+
+    /// Run the bytecode in `emit` and return its completion value.
+    pub fn evaluate(&self, emit: &EmitResult) -> Result<Value, EvalError> {
+        self.evaluate_with_limit(emit, u64::MAX)
+    }
+
+    /// Like `evaluate`, but fail with `EvalError::StepLimitExceeded` instead
+    /// of executing more than `max_steps` instructions, so that a script
+    /// that loops forever can't hang the host.
+    pub fn evaluate_with_limit(
+        &self,
+        emit: &EmitResult,
+        max_steps: u64,
+    ) -> Result<Value, EvalError> {
+        // Each evaluation has its own `import.meta` object.
+        let host = ModuleHost {
+            dynamic_import: self.dynamic_import.clone(),
+            import_meta: Rc::new(RefCell::new(Object::new())),
+        };
+        evaluate(emit, &self.global, &host, max_steps)
+    }
+}
+
+impl Default for Realm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,59 +1,99 @@
 use bumpalo::Bump;
-use emitter::emit;
-use parser::parse_script;
+use emitter::{emit, EmitResult};
+use parser::{parse_script, ParseError};
 
-use crate::{evaluate, EvalError, JSValue};
+use std::cell::RefCell;
+use std::rc::Rc;
 
-fn try_evaluate(source: &str) -> Result<JSValue, EvalError> {
+use crate::{EvalError, JsonError, NonFinite, Realm, Value};
+
+fn try_evaluate_in(realm: &Realm, source: &str) -> Result<Value, EvalError> {
+    realm.evaluate(&emit_script(source))
+}
+
+fn emit_script(source: &str) -> EmitResult {
     let alloc = &Bump::new();
     let parse_result = parse_script(alloc, source).expect("Failed to parse");
     let emit_result =
         emit(&mut ast::types::Program::Script(parse_result.unbox())).expect("Should work!");
-    evaluate(&emit_result)
+    emit_result
+}
+
+fn try_evaluate(source: &str) -> Result<Value, EvalError> {
+    let mut realm = Realm::new();
+    realm.register_fn("print", Box::new(|_args| Value::Undefined));
+    try_evaluate_in(&realm, source)
 }
 
 #[test]
 fn test_arithmetic() {
     match try_evaluate("1 + 1") {
-        Ok(JSValue::Number(n)) if n == 2.0 => (),
+        Ok(Value::Number(n)) if n == 2.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("3 - 2") {
-        Ok(JSValue::Number(n)) if n == 1.0 => (),
+        Ok(Value::Number(n)) if n == 1.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("-2") {
-        Ok(JSValue::Number(n)) if n == -2.0 => (),
+        Ok(Value::Number(n)) if n == -2.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("+2") {
-        Ok(JSValue::Number(n)) if n == 2.0 => (),
+        Ok(Value::Number(n)) if n == 2.0 => (),
         _ => panic!("wrong result"),
     }
 }
 
+#[test]
+fn test_exponentiation() {
+    // `**` is right-associative.
+    assert_eq!(try_evaluate("2 ** 3 ** 2").unwrap(), Value::Number(512.0));
+    assert_eq!(try_evaluate("(2 ** 3) ** 2").unwrap(), Value::Number(64.0));
+    assert_eq!(try_evaluate("(-2) ** 2").unwrap(), Value::Number(4.0));
+    assert_eq!(try_evaluate("2 ** -1").unwrap(), Value::Number(0.5));
+
+    // A unary expression can't be the left operand without parentheses.
+    assert!(parse_script(&Bump::new(), "-2 ** 2").is_err());
+
+    match try_evaluate("(-0) ** -1") {
+        Ok(Value::Number(n)) if n == f64::NEG_INFINITY => (),
+        other => panic!("wrong result: {:?}", other),
+    }
+    match try_evaluate("(-0) ** 3") {
+        Ok(Value::Number(n)) if n == 0.0 && n.is_sign_negative() => (),
+        other => panic!("wrong result: {:?}", other),
+    }
+    // NaN to the power of 0 is 1, but 1 to the power of NaN is NaN.
+    assert_eq!(try_evaluate("undefined ** 0").unwrap(), Value::Number(1.0));
+    match try_evaluate("1 ** undefined") {
+        Ok(Value::Number(n)) if n.is_nan() => (),
+        other => panic!("wrong result: {:?}", other),
+    }
+}
+
 #[test]
 fn test_ternary_conditional() {
     match try_evaluate("true ? 1 : 37") {
-        Ok(JSValue::Number(n)) if n == 1.0 => (),
+        Ok(Value::Number(n)) if n == 1.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("false ? 1 : 37") {
-        Ok(JSValue::Number(n)) if n == 37.0 => (),
+        Ok(Value::Number(n)) if n == 37.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("1 ? true : false") {
-        Ok(JSValue::Boolean(true)) => (),
+        Ok(Value::Bool(true)) => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("0 ? true : false") {
-        Ok(JSValue::Boolean(false)) => (),
+        Ok(Value::Bool(false)) => (),
         _ => panic!("wrong result"),
     }
 }
@@ -61,17 +101,17 @@ fn test_ternary_conditional() {
 #[test]
 fn test_gname() {
     match try_evaluate("undefined") {
-        Ok(JSValue::Undefined) => (),
+        Ok(Value::Undefined) => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("a = true; a") {
-        Ok(JSValue::Boolean(true)) => (),
+        Ok(Value::Bool(true)) => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("a = true; a = false; a") {
-        Ok(JSValue::Boolean(false)) => (),
+        Ok(Value::Bool(false)) => (),
         _ => panic!("wrong result"),
     }
 }
@@ -79,22 +119,22 @@ fn test_gname() {
 #[test]
 fn test_if() {
     match try_evaluate("if (true) 1; else 2;") {
-        Ok(JSValue::Number(n)) if n == 1.0 => (),
+        Ok(Value::Number(n)) if n == 1.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("if (false) 1; else 2;") {
-        Ok(JSValue::Number(n)) if n == 2.0 => (),
+        Ok(Value::Number(n)) if n == 2.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("if (true) 1;") {
-        Ok(JSValue::Number(n)) if n == 1.0 => (),
+        Ok(Value::Number(n)) if n == 1.0 => (),
         _ => panic!("wrong result"),
     }
 
     match try_evaluate("if (false) 1;") {
-        Ok(JSValue::Undefined) => (),
+        Ok(Value::Undefined) => (),
         _ => panic!("wrong result"),
     }
 }
@@ -102,7 +142,1790 @@ fn test_if() {
 #[test]
 fn test_call() {
     match try_evaluate("print(1)") {
-        Ok(JSValue::Undefined) => (),
+        Ok(Value::Undefined) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_register_fn() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut realm = Realm::new();
+    let recorded = calls.clone();
+    realm.register_fn(
+        "record",
+        Box::new(move |args| {
+            recorded.borrow_mut().push(format!("{:?}", args));
+            Value::Number(args.len() as f64)
+        }),
+    );
+
+    match try_evaluate_in(&realm, "record(1, 'two', true)") {
+        Ok(Value::Number(n)) if n == 3.0 => (),
+        _ => panic!("wrong result"),
+    }
+    assert_eq!(*calls.borrow(), vec![r#"[1, "two", true]"#]);
+
+    // Globals set by one script are seen by the next one in the same realm.
+    match try_evaluate_in(&realm, "a = record(); a") {
+        Ok(Value::Number(n)) if n == 0.0 => (),
+        _ => panic!("wrong result"),
+    }
+    match try_evaluate_in(&realm, "a") {
+        Ok(Value::Number(n)) if n == 0.0 => (),
+        _ => panic!("wrong result"),
+    }
+
+    match try_evaluate_in(&Realm::new(), "record(1)") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_variable_declarations() {
+    assert_eq!(
+        try_evaluate("var a = 1, b; const c = a + 1; c").unwrap(),
+        Value::Number(2.0)
+    );
+    assert_eq!(
+        try_evaluate("let a = 1, b; a = a + 1; '' + a + b").unwrap(),
+        Value::String("2undefined".to_string())
+    );
+    assert_eq!(try_evaluate("var a = 1; a").unwrap(), Value::Number(1.0));
+    // A declaration has no completion value.
+    assert_eq!(try_evaluate("1; var a = 2;").unwrap(), Value::Number(1.0));
+    assert_eq!(
+        try_evaluate("function f(x) { var y = x + 1; return y; } f(1)").unwrap(),
+        Value::Number(2.0)
+    );
+
+    // Variables declared by one script are seen by the next one in the same
+    // realm, and `var` without an initializer doesn't change them.
+    let realm = Realm::new();
+    try_evaluate_in(&realm, "var a = 1; const b = 2;").unwrap();
+    assert_eq!(
+        try_evaluate_in(&realm, "var a; a + b").unwrap(),
+        Value::Number(3.0)
+    );
+    assert_eq!(
+        try_evaluate_in(&realm, "const b = 3; b").unwrap(),
+        Value::Number(3.0)
+    );
+}
+
+#[test]
+fn test_value() {
+    assert_eq!(try_evaluate("40 + 2").unwrap().as_f64(), Some(42.0));
+    assert_eq!(try_evaluate("'a'").unwrap().as_f64(), None);
+    assert_eq!(try_evaluate("'a'").unwrap().as_str(), Some("a"));
+    assert_eq!(try_evaluate("true").unwrap().as_bool(), Some(true));
+    assert_eq!(try_evaluate("null").unwrap(), Value::Null);
+    assert_eq!(try_evaluate("-'x'").unwrap(), Value::Number(f64::NAN));
+
+    // Objects are only equal to themselves.
+    let object = try_evaluate("({})").unwrap();
+    assert_eq!(object, object.clone());
+    assert_ne!(object, try_evaluate("({})").unwrap());
+
+    let to_string = |value: f64| Value::Number(value).to_js_string();
+    assert_eq!(to_string(0.0), "0");
+    assert_eq!(to_string(-0.0), "0");
+    assert_eq!(to_string(-42.0), "-42");
+    assert_eq!(to_string(1.5), "1.5");
+    assert_eq!(to_string(0.000001), "0.000001");
+    assert_eq!(to_string(0.0000001), "1e-7");
+    assert_eq!(to_string(1e21), "1e+21");
+    assert_eq!(to_string(123456789012345680000.0), "123456789012345680000");
+    assert_eq!(to_string(1.25e-10), "1.25e-10");
+    assert_eq!(to_string(f64::NAN), "NaN");
+    assert_eq!(to_string(f64::NEG_INFINITY), "-Infinity");
+
+    assert_eq!(Value::Bool(false).to_js_string(), "false");
+    assert_eq!(Value::Undefined.to_js_string(), "undefined");
+    assert_eq!(object.to_js_string(), "[object Object]");
+}
+
+#[test]
+fn test_to_json() {
+    let to_json = |source: &str| try_evaluate(source).unwrap().to_json();
+
+    // Properties that are undefined or functions are left out.
+    assert_eq!(
+        to_json("({ a: 1, b: 'two', c: [true, null], d: { e: -0 }, f: undefined, g() {} })"),
+        Ok(serde_json::json!({ "a": 1, "b": "two", "c": [true, null], "d": { "e": 0 } }))
+    );
+
+    // Holes and undefined elements are null.
+    assert_eq!(
+        to_json("a = [2 ** -1, 'x', undefined, {}]; a[5] = 7; a"),
+        Ok(serde_json::json!([0.5, "x", null, {}, null, 7]))
+    );
+
+    assert_eq!(
+        to_json("({ x: -'x', y: (2 ** 100) ** 100 })"),
+        Ok(serde_json::json!({ "x": null, "y": null }))
+    );
+    assert_eq!(
+        try_evaluate("({ x: -'x' })")
+            .unwrap()
+            .to_json_with(NonFinite::Error)
+            .map_err(|err| err.to_string()),
+        Err("number can't be serialized: NaN".to_string())
+    );
+
+    assert_eq!(to_json("undefined"), Ok(serde_json::Value::Null));
+    assert_eq!(to_json("[1n]"), Err(JsonError::BigInt("1".to_string())));
+
+    // An object can be in several places, but not in itself.
+    assert_eq!(
+        to_json("o = {}; [o, { o }]"),
+        Ok(serde_json::json!([{}, { "o": {} }]))
+    );
+    assert_eq!(
+        to_json("o = { p: [] }; o.p[0] = o; o"),
+        Err(JsonError::Cyclic)
+    );
+}
+
+#[test]
+fn test_step_limit() {
+    let realm = Realm::new();
+    let emit_result = emit_script("1 + 1");
+    match realm.evaluate_with_limit(&emit_result, 0) {
+        Err(EvalError::StepLimitExceeded) => (),
+        _ => panic!("wrong result"),
+    }
+
+    // Find the number of steps the script takes: one less isn't enough.
+    let steps = (1..100)
+        .find(|&steps| realm.evaluate_with_limit(&emit_result, steps).is_ok())
+        .expect("should finish");
+    match realm.evaluate_with_limit(&emit_result, steps - 1) {
+        Err(EvalError::StepLimitExceeded) => (),
+        _ => panic!("wrong result"),
+    }
+    assert_eq!(
+        realm
+            .evaluate_with_limit(&emit_result, steps)
+            .unwrap()
+            .as_f64(),
+        Some(2.0)
+    );
+}
+
+#[test]
+fn test_big_int() {
+    assert_eq!(
+        try_evaluate("0xFF_FFn").unwrap(),
+        Value::BigInt("65535".to_string())
+    );
+    assert_eq!(
+        try_evaluate("-1_000n").unwrap(),
+        Value::BigInt("-1000".to_string())
+    );
+    assert_eq!(try_evaluate("-0n").unwrap(), Value::BigInt("0".to_string()));
+    assert_eq!(try_evaluate("1_0").unwrap().as_f64(), Some(10.0));
+    assert_eq!(try_evaluate("0xFFn").unwrap().to_js_string(), "255");
+
+    match try_evaluate("1n + 1") {
+        Err(EvalError::NotImplemented(_)) => (),
+        _ => panic!("wrong result"),
+    }
+
+    // `++` and `--` step by 1n.
+    let bigint = |source| try_evaluate(source).unwrap().to_js_string();
+    assert_eq!(bigint("var b = 1n; b++; b"), "2");
+    assert_eq!(bigint("var b = 1n; b++"), "1");
+    assert_eq!(bigint("var b = 1n; --b"), "0");
+    assert_eq!(bigint("var b = 0n; --b"), "-1");
+    assert_eq!(bigint("var b = -1n; ++b"), "0");
+    assert_eq!(bigint("var b = -1n; --b"), "-2");
+    assert_eq!(bigint("var b = 999n; ++b"), "1000");
+    assert_eq!(bigint("var b = 1000n; --b"), "999");
+    assert_eq!(bigint("var b = -1000n; ++b"), "-999");
+    assert_eq!(bigint("var b = -999n; --b"), "-1000");
+    assert_eq!(
+        bigint("var b = 18446744073709551615n; ++b"),
+        "18446744073709551616"
+    );
+    assert_eq!(
+        try_evaluate("var b = 1n; b++; typeof b").unwrap(),
+        Value::String("bigint".to_string())
+    );
+}
+
+#[test]
+fn test_legacy_octal() {
+    assert_eq!(try_evaluate("017 + 089").unwrap(), Value::Number(104.0));
+    assert_eq!(
+        try_evaluate(r#""\123\08""#).unwrap(),
+        Value::String("S\u{0}8".to_string())
+    );
+}
+
+#[test]
+fn test_coalesce() {
+    assert_eq!(try_evaluate("a = 0; a ?? 1").unwrap(), Value::Number(0.0));
+    assert_eq!(
+        try_evaluate("a = ''; a ?? 1").unwrap(),
+        Value::String("".to_string())
+    );
+    assert_eq!(
+        try_evaluate("a = null; a ?? 1").unwrap(),
+        Value::Number(1.0)
+    );
+    assert_eq!(
+        try_evaluate("a = undefined; a ?? 1").unwrap(),
+        Value::Number(1.0)
+    );
+    assert_eq!(
+        try_evaluate("a = null; b = null; a ?? b ?? 2").unwrap(),
+        Value::Number(2.0)
+    );
+    assert_eq!(
+        try_evaluate("a = 0; (a || null) ?? 3").unwrap(),
+        Value::Number(3.0)
+    );
+
+    // The right side isn't evaluated when the left side isn't nullish.
+    assert_eq!(
+        try_evaluate("a = 1; 0 ?? (a = 2); a").unwrap(),
+        Value::Number(1.0)
+    );
+}
+
+#[test]
+fn test_logical_assignment() {
+    // `record(label, value)` records `label` and returns `value`.
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut realm = Realm::new();
+    let recorded = calls.clone();
+    realm.register_fn(
+        "record",
+        Box::new(move |args| {
+            recorded.borrow_mut().push(args[0].to_js_string());
+            args[1].clone()
+        }),
+    );
+    let evaluate = |source| {
+        calls.borrow_mut().clear();
+        let result = try_evaluate_in(&realm, source).unwrap();
+        (result, calls.borrow().clone())
+    };
+
+    assert_eq!(evaluate("a = 0; a ||= 2; a"), (Value::Number(2.0), vec![]));
+    assert_eq!(evaluate("a = 1; a &&= 2; a"), (Value::Number(2.0), vec![]));
+    assert_eq!(
+        evaluate("a = null; a ??= 3; a"),
+        (Value::Number(3.0), vec![])
+    );
+    assert_eq!(
+        evaluate("a = undefined; a ??= record('rhs', 4)"),
+        (Value::Number(4.0), vec!["rhs".to_string()])
+    );
+
+    // When the target decides the result, the right-hand side isn't
+    // evaluated and nothing is assigned.
+    assert_eq!(
+        evaluate("a = 1; a ||= record('rhs', 2)"),
+        (Value::Number(1.0), vec![])
+    );
+    assert_eq!(
+        evaluate("a = ''; a &&= record('rhs', 2)"),
+        (Value::String("".to_string()), vec![])
+    );
+    assert_eq!(
+        evaluate("a = 0; a ??= record('rhs', 2)"),
+        (Value::Number(0.0), vec![])
+    );
+    assert_eq!(evaluate("a"), (Value::Number(0.0), vec![]));
+
+    // The object and key of a member target are evaluated once.
+    assert_eq!(
+        evaluate("o = {p: 0}; record('obj', o).p ??= record('rhs', 1)"),
+        (Value::Number(0.0), vec!["obj".to_string()])
+    );
+    assert_eq!(
+        evaluate("o = {p: null}; record('obj', o).p ??= record('rhs', 1); o.p"),
+        (
+            Value::Number(1.0),
+            vec!["obj".to_string(), "rhs".to_string()]
+        )
+    );
+    assert_eq!(
+        evaluate("o = {p: 1}; record('obj', o)[record('key', 'p')] ||= record('rhs', 2)"),
+        (
+            Value::Number(1.0),
+            vec!["obj".to_string(), "key".to_string()]
+        )
+    );
+    assert_eq!(
+        evaluate("o = {p: 1}; record('obj', o)[record('key', 'p')] &&= record('rhs', 2); o.p"),
+        (
+            Value::Number(2.0),
+            vec!["obj".to_string(), "key".to_string(), "rhs".to_string()]
+        )
+    );
+    assert_eq!(
+        evaluate("o = {}; o.p ||= 'x'; o['p'] ??= 'y'; o.p"),
+        (Value::String("x".to_string()), vec![])
+    );
+
+    match try_evaluate("a = null; a.p ??= 1") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_template_literals() {
+    let string = |s: &str| Value::String(s.to_string());
+    assert_eq!(try_evaluate("`abc`").unwrap(), string("abc"));
+    assert_eq!(try_evaluate("``").unwrap(), string(""));
+    assert_eq!(
+        try_evaluate("a = 'x'; `${a}${1 + 1} and ${null}\\t${`!`}`").unwrap(),
+        string("x2 and null\t!")
+    );
+    assert_eq!(
+        try_evaluate("`\\x41\\u{42}\\\n${true}\r\n`").unwrap(),
+        string("ABtrue\n")
+    );
+
+    // `tag` describes its arguments as "cooked|raw|substitutions".
+    let mut realm = Realm::new();
+    realm.register_fn(
+        "tag",
+        Box::new(|args| {
+            let strings = match &args[0] {
+                Value::Object(strings) => strings.borrow(),
+                _ => panic!("not an object"),
+            };
+            let raw = match strings.get("raw".to_string()) {
+                Value::Object(raw) => raw,
+                _ => panic!("not an object"),
+            };
+            let raw = raw.borrow();
+            let indices = 0..args.len();
+            let cooked: Vec<String> = indices
+                .clone()
+                .map(|i| strings.get(i.to_string()).to_js_string())
+                .collect();
+            let raw: Vec<String> = indices
+                .map(|i| raw.get(i.to_string()).to_js_string())
+                .collect();
+            let substitutions: Vec<String> = args[1..].iter().map(Value::to_js_string).collect();
+            Value::String(format!(
+                "{}|{}|{}",
+                cooked.join(","),
+                raw.join(","),
+                substitutions.join(",")
+            ))
+        }),
+    );
+    let evaluate = |source| try_evaluate_in(&realm, source).unwrap();
+    assert_eq!(
+        evaluate("tag`a${1}\\x41${'b'}`"),
+        string("a,A,|a,\\x41,|1,b")
+    );
+    // Invalid escape sequences leave the cooked string undefined.
+    assert_eq!(
+        evaluate("tag`\\xg${1}\\unicode`"),
+        string("undefined,undefined|\\xg,\\unicode|1")
+    );
+}
+
+#[test]
+fn test_loops() {
+    assert_eq!(
+        try_evaluate("s = ''; for (var i = 0; i !== 3; i++) s = s + i; s").unwrap(),
+        Value::String("012".to_string())
+    );
+    assert_eq!(
+        try_evaluate("s = ''; i = 3; while (i) { s = s + i; i--; } s").unwrap(),
+        Value::String("321".to_string())
+    );
+    // The body of a `do-while` loop runs before the test.
+    assert_eq!(
+        try_evaluate("s = ''; do s = s + 'a'; while (s !== 'aaa'); s").unwrap(),
+        Value::String("aaa".to_string())
+    );
+    assert_eq!(
+        try_evaluate("i = 0; do i++; while (false); i").unwrap(),
+        Value::Number(1.0)
+    );
+    // A loop with no test runs until `break`.
+    assert_eq!(
+        try_evaluate("i = 0; for (;;) { if (i === 2) break; i++; } i").unwrap(),
+        Value::Number(2.0)
+    );
+
+    // `continue` goes on to the update of a `for` loop and the test of a
+    // `do-while` loop.
+    assert_eq!(
+        try_evaluate("s = ''; for (i = 0; i !== 4; i++) { if (i === 1) continue; s = s + i; } s")
+            .unwrap(),
+        Value::String("023".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; i = 0; do { i++; if (i === 2) continue; s = s + i; } while (i !== 3); s"
+        )
+        .unwrap(),
+        Value::String("13".to_string())
+    );
+
+    // The completion value is the value of the last statement the body ran.
+    assert_eq!(try_evaluate("1; while (false);").unwrap(), Value::Undefined);
+    assert_eq!(
+        try_evaluate("for (i = 0; i !== 2; i++) i;").unwrap(),
+        Value::Number(1.0)
+    );
+
+    // Functions made in the body of a `for` loop keep the `let` bindings of
+    // their iteration.
+    assert_eq!(
+        try_evaluate(
+            "var fs = [];
+             for (let i = 0; i !== 3; i++) { fs[i] = function () { return i; }; }
+             '' + fs[0]() + fs[1]() + fs[2]()"
+        )
+        .unwrap(),
+        Value::String("012".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "var fs = [];
+             for (var i = 0; i !== 3; i++) { fs[i] = function () { return i; }; }
+             '' + fs[0]() + fs[1]() + fs[2]()"
+        )
+        .unwrap(),
+        Value::String("333".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "var fs = [];
+             for (let i = 0; i !== 3; i++) { fs[i] = () => i++; if (i === 1) continue; }
+             '' + fs[0]() + fs[0]() + fs[2]()"
+        )
+        .unwrap(),
+        Value::String("012".to_string())
+    );
+}
+
+#[test]
+fn test_for_of() {
+    assert_eq!(
+        try_evaluate("sum = 0; for (const x of [1, 2, 3, 4]) { sum = sum + x; } sum").unwrap(),
+        Value::Number(10.0)
+    );
+    assert_eq!(
+        try_evaluate("s = ''; for (var x of ['a', 'b']) s = s + x; s + x").unwrap(),
+        Value::String("abb".to_string())
+    );
+    assert_eq!(
+        try_evaluate("o = {}; for (o.p of [1, 2]); for (o['q'] of [3]); o.p + o.q").unwrap(),
+        Value::Number(5.0)
+    );
+
+    // `x - 2` is false only for 2.
+    assert_eq!(
+        try_evaluate("sum = 0; for (x of [1, 2, 3]) { if (x - 2) continue; sum = sum + x; } sum")
+            .unwrap(),
+        Value::Number(2.0)
+    );
+    assert_eq!(
+        try_evaluate("sum = 0; for (x of [1, 2, 3]) { sum = sum + x; if (x - 1) break; } sum")
+            .unwrap(),
+        Value::Number(3.0)
+    );
+    // `break` and `continue` apply to the innermost loop.
+    assert_eq!(
+        try_evaluate(
+            "s = ''; for (x of ['a', 'b']) { for (y of [1, 2]) { s = s + x + y; break; } continue; s = 'no'; } s"
+        )
+        .unwrap(),
+        Value::String("a1b1".to_string())
+    );
+
+    // The completion value is undefined if the body doesn't run.
+    assert_eq!(
+        try_evaluate("1; for (x of []) 2;").unwrap(),
+        Value::Undefined
+    );
+    assert_eq!(
+        try_evaluate("1; for (x of [1]) 2;").unwrap(),
+        Value::Number(2.0)
+    );
+
+    match try_evaluate("for (x of {}) ;") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+    match try_evaluate("for (x of null) ;") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_for_in() {
+    // Array indices first, then the other names in the order they were added.
+    assert_eq!(
+        try_evaluate("s = ''; for (k in {b: 1, '2': 1, a: 1, '0': 1, '01': 1}) s = s + k + ','; s")
+            .unwrap(),
+        Value::String("0,2,b,a,01,".to_string())
+    );
+    assert_eq!(
+        try_evaluate("o = {}; for (o.k in ['x', 'y']); o.k").unwrap(),
+        Value::String("1".to_string())
+    );
+    assert_eq!(
+        try_evaluate("s = ''; for (const k in {a: 1, b: 2, c: 3}) { s = s + k; if (s) break; } s")
+            .unwrap(),
+        Value::String("a".to_string())
+    );
+    assert_eq!(
+        try_evaluate("n = 0; for (k in null) n = 1; for (k in undefined) n = 2; n").unwrap(),
+        Value::Number(0.0)
+    );
+}
+
+#[test]
+fn test_destructuring() {
+    let string = |s: &str| Value::String(s.to_string());
+    assert_eq!(
+        try_evaluate(
+            "const { a = 1, ...rest } = { b: 2, c: 3 }; '' + a + rest.b + rest.c + rest.a"
+        )
+        .unwrap(),
+        string("123undefined")
+    );
+    assert_eq!(
+        try_evaluate(
+            "[x, , y = 2, ...z] = [1, 9, undefined, 4, 5]; '' + x + y + z[0] + z[1] + z[2]"
+        )
+        .unwrap(),
+        string("1245undefined")
+    );
+    assert_eq!(
+        try_evaluate(
+            "k = 'p';
+             ({ [k]: v, [k + 'q']: w = 3, ...r } = { p: 1, pq: undefined, s: 2 });
+             '' + v + w + r.s + r.p + r.pq"
+        )
+        .unwrap(),
+        string("132undefinedundefined")
+    );
+    assert_eq!(
+        try_evaluate("o = {}; [o.a, o['b'], ...o.c] = [1, 2, 3]; '' + o.a + o.b + o.c[0]").unwrap(),
+        string("123")
+    );
+
+    // Default values are only evaluated for undefined values.
+    assert_eq!(
+        try_evaluate(
+            "n = 0;
+             function f() { n = n + 1; return 'default'; }
+             var { a = f(), b: { c = f() } } = { a: 'given', b: { c: null } };
+             [d = f(), e = f()] = [0, false];
+             ({ g = f() } = { g: '' });
+             '' + n + a + c + d + e + g"
+        )
+        .unwrap(),
+        string("0givennull0false")
+    );
+    assert_eq!(
+        try_evaluate(
+            "n = 0;
+             function f() { n = n + 1; return n; }
+             var { a = f() } = {};
+             [b = f(), c = f()] = [undefined];
+             ({ d = f() } = { d: undefined });
+             '' + n + a + b + c + d"
+        )
+        .unwrap(),
+        string("41234")
+    );
+
+    // Parameters.
+    assert_eq!(
+        try_evaluate(
+            "n = 0;
+             function f() { n = n + 1; return 9; }
+             function g({ a, b: [c, d = f()] }, e = a + c) { return '' + a + c + d + e; }
+             g({ a: 1, b: [2] }) + g({ a: 1, b: [2, 5] }, 0) + n"
+        )
+        .unwrap(),
+        string("129312501")
+    );
+    assert_eq!(
+        try_evaluate("function* g([a, b] = [1, 2]) { yield a + b; } g().next().value").unwrap(),
+        Value::Number(3.0)
+    );
+
+    // The value of an assignment is the value destructured.
+    assert_eq!(
+        try_evaluate("o = { a: 1 }; ({ a } = o) === o").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; for (const [k, { v }] of [['a', { v: 1 }], ['b', {}]]) s = s + k + v; s"
+        )
+        .unwrap(),
+        string("a1bundefined")
+    );
+    assert_eq!(
+        try_evaluate("try { throw { m: 'x' }; } catch ({ m, n = 'y' }) { m + n }").unwrap(),
+        string("xy")
+    );
+    assert_eq!(
+        try_evaluate(
+            "function* g() { yield 1; yield 2; yield 3; } [p, ...q] = g(); p + q[0] + q[1]"
+        )
+        .unwrap(),
+        Value::Number(6.0)
+    );
+
+    assert!(matches!(
+        try_evaluate("const { a } = null;"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert!(matches!(
+        try_evaluate("[] = {};"),
+        Err(EvalError::TypeError(_))
+    ));
+}
+
+#[test]
+fn test_spread() {
+    let mut realm = Realm::new();
+    realm.register_fn(
+        "count",
+        Box::new(|args: &[Value]| Value::Number(args.len() as f64)),
+    );
+    assert_eq!(
+        try_evaluate_in(&realm, "count(...[1, 2, 3])").unwrap(),
+        Value::Number(3.0)
+    );
+    assert_eq!(
+        try_evaluate_in(&realm, "count(0, ...[], ...[1, 2], 3)").unwrap(),
+        Value::Number(4.0)
+    );
+    assert_eq!(
+        try_evaluate_in(
+            &realm,
+            "function f(a, b, c) { return '' + a + b + c; }
+             o = { x: 'x', m: function (a, b) { return this.x + a + b; } };
+             class P { constructor(a, b) { this.s = a + b; } }
+             f(...['a', 'b'], 'c') + o.m(...[1, 2]) + new P(...[3], 4).s"
+        )
+        .unwrap(),
+        Value::String("abcx127".to_string())
+    );
+
+    // Elements after a spread element follow the spread values.
+    assert_eq!(
+        try_evaluate(
+            "a = [1, 2]; b = [3];
+             function* g() { yield 4; yield 5; }
+             c = [0, ...a, ...b, ...g(), 6];
+             s = ''; for (x of c) s = s + x; s"
+        )
+        .unwrap(),
+        Value::String("0123456".to_string())
+    );
+
+    // Later properties override earlier ones, whether spread or not.
+    assert_eq!(
+        try_evaluate(
+            "o = { a: 1, b: 2 };
+             p = { a: 0, ...o, b: 3, ...null, ...{ c: 4 } };
+             '' + p.a + p.b + p.c"
+        )
+        .unwrap(),
+        Value::String("134".to_string())
+    );
+}
+
+#[test]
+fn test_object_literals() {
+    // Shorthand properties, methods and computed keys, which are evaluated
+    // in order with the values.
+    assert_eq!(
+        try_evaluate(
+            "a = 1; k = 'b';
+             o = { a, [k]: 2, ['c' + a]: 3, m() { return this.a + this.b; }, [k + 'm']() { return 4; } };
+             '' + o.a + o.b + o.c1 + o.m() + o.bm()"
+        )
+        .unwrap(),
+        Value::String("12334".to_string())
+    );
+
+    // A getter runs when the property is read, with the object as `this`, and
+    // a setter receives the value assigned, which is the assignment's result.
+    assert_eq!(
+        try_evaluate(
+            "log = '';
+             o = {
+               v: 1,
+               get x() { log = log + 'get;'; return this.v; },
+               set x(value) { log = log + 'set ' + value + ';'; this.v = value + value; return 'ignored'; },
+             };
+             r = (o.x = 5);
+             log + r + ',' + o.x + ',' + o['x'] + ',' + log"
+        )
+        .unwrap(),
+        Value::String("set 5;5,10,10,set 5;get;get;".to_string())
+    );
+
+    // A getter and a setter with the same name are one property, and
+    // computed names work for them too. A getter alone ignores assignments.
+    assert_eq!(
+        try_evaluate(
+            "k = 'y';
+             o = { set [k](v) { this.saved = v; }, get [k]() { return 'got ' + this.saved; } };
+             o.y = 'a';
+             p = { get z() { return 'z'; } };
+             p.z = 1;
+             o.y + ',' + p.z"
+        )
+        .unwrap(),
+        Value::String("got a,z".to_string())
+    );
+
+    // Accessors are inherited from the prototype, as in classes, where
+    // `this` is the instance.
+    assert_eq!(
+        try_evaluate(
+            "class C {
+               constructor() { this.n = 1; }
+               get double() { return this.n + this.n; }
+               set double(v) { this.n = v - 1; }
+               static get kind() { return 'C'; }
+             }
+             c = new C();
+             c.double = 10;
+             '' + c.n + c.double + C.kind"
+        )
+        .unwrap(),
+        Value::String("918C".to_string())
+    );
+
+    // A data property defined later replaces an accessor, and the other way
+    // around.
+    assert_eq!(
+        try_evaluate(
+            "o = { get a() { return 1; }, a: 2, b: 3, get b() { return 4; } }; '' + o.a + o.b"
+        )
+        .unwrap(),
+        Value::String("24".to_string())
+    );
+}
+
+#[test]
+fn test_with() {
+    // Names the object has are its properties, and the others are still
+    // global variables.
+    assert_eq!(
+        try_evaluate(
+            "o = { a: 1, b: 2 }; c = 3;
+             with (o) { a = a + c; d = b; }
+             '' + o.a + ',' + o.d + ',' + d"
+        )
+        .unwrap(),
+        Value::String("4,undefined,2".to_string())
+    );
+
+    // Inner `with` objects come first, and a function found in one is called
+    // with it as `this`.
+    assert_eq!(
+        try_evaluate(
+            "o = { v: 'o', m() { return this.v; } }; p = { v: 'p' };
+             with (o) with (p) r = m() + v;
+             r"
+        )
+        .unwrap(),
+        Value::String("op".to_string())
+    );
+
+    // The object is taken off the scope chain when jumping or throwing out
+    // of the body.
+    assert_eq!(
+        try_evaluate(
+            "o = { x: 'o' }; x = 'g'; r = '';
+             for (i of [1, 2]) { with (o) { r = r + x; continue; } }
+             a: with (o) { r = r + x; break a; }
+             try { with (o) { r = r + x; throw 1; } } catch (e) { r = r + x; }
+             r + x"
+        )
+        .unwrap(),
+        Value::String("oooogg".to_string())
+    );
+
+    match try_evaluate("with (null) {}") {
+        Err(EvalError::TypeError(_)) => (),
+        other => panic!("expected a TypeError, got {:?}", other),
+    }
+
+    // It's not allowed in strict mode code.
+    assert!(parse_script(&Bump::new(), "'use strict'; with (o) x;").is_err());
+}
+
+#[test]
+fn test_optional_chaining() {
+    assert_eq!(try_evaluate("null?.x.y").unwrap(), Value::Undefined);
+    assert_eq!(try_evaluate("undefined?.[0]").unwrap(), Value::Undefined);
+    assert_eq!(
+        try_evaluate("a = {b: {c: null}}; a?.b.c?.d").unwrap(),
+        Value::Undefined
+    );
+    assert_eq!(
+        try_evaluate("a = {b: {c: {d: 1}}}; a?.b.c?.d").unwrap(),
+        Value::Number(1.0)
+    );
+    assert_eq!(
+        try_evaluate("a = {b: 'x'}; a?.['b']").unwrap(),
+        Value::String("x".to_string())
+    );
+
+    // The rest of the chain isn't evaluated.
+    assert_eq!(
+        try_evaluate("a = 1; null?.[a = 2]; a").unwrap(),
+        Value::Number(1.0)
+    );
+
+    // Calls.
+    assert_eq!(try_evaluate("f?.(1)").unwrap(), Value::Undefined);
+    let mut realm = Realm::new();
+    realm.register_fn("f", Box::new(|_args| Value::Number(42.0)));
+    assert_eq!(
+        try_evaluate_in(&realm, "f?.(1)").unwrap(),
+        Value::Number(42.0)
+    );
+
+    // Without `?.`, or outside the parentheses, reading null's properties
+    // throws.
+    match try_evaluate("null.x") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+    match try_evaluate("(null?.x).y") {
+        Err(EvalError::TypeError(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_try_catch_finally() {
+    assert_eq!(
+        try_evaluate("try { throw 'oops'; } catch (e) { e + '!' }").unwrap(),
+        Value::String("oops!".to_string())
+    );
+    assert_eq!(
+        try_evaluate("s = 'a'; try { s = s + 'b'; } catch (e) { s = s + 'c'; } s").unwrap(),
+        Value::String("ab".to_string())
+    );
+    assert_eq!(
+        try_evaluate("try { throw 1; } catch { 2 }").unwrap(),
+        Value::Number(2.0)
+    );
+
+    // Errors thrown by the interpreter are caught too.
+    assert_eq!(
+        try_evaluate("try { null.x; } catch (e) { e.name }").unwrap(),
+        Value::String("TypeError".to_string())
+    );
+
+    // The finally block runs whether or not anything was thrown, without
+    // changing the completion value.
+    assert_eq!(
+        try_evaluate("s = ''; try { s = s + 'a'; } finally { s = s + 'f'; } s").unwrap(),
+        Value::String("af".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; try { throw 'x'; } catch (e) { s = s + e; } finally { s = s + 'f'; } s"
+        )
+        .unwrap(),
+        Value::String("xf".to_string())
+    );
+    assert_eq!(
+        try_evaluate("try { 1 } finally { 2 }").unwrap(),
+        Value::Number(1.0)
+    );
+
+    // Including when breaking or continuing out of the try block.
+    assert_eq!(
+        try_evaluate(
+            "s = ''; for (x of [1, 2, 3]) { try { s = s + x; if (x - 2) continue; break; } \
+             finally { s = s + 'f'; } } s"
+        )
+        .unwrap(),
+        Value::String("1f2f".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; for (x of [1]) { try { try { break; } finally { s = s + 'a'; } } \
+             finally { s = s + 'b'; } } s"
+        )
+        .unwrap(),
+        Value::String("ab".to_string())
+    );
+
+    // Rethrowing, and throwing past a finally block.
+    assert_eq!(
+        try_evaluate("try { try { throw 'x'; } catch (e) { throw e + 'y'; } } catch (e) { e }")
+            .unwrap(),
+        Value::String("xy".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; try { try { throw 'x'; } finally { s = s + 'f'; } } catch (e) { s + e }"
+        )
+        .unwrap(),
+        Value::String("fx".to_string())
+    );
+    match try_evaluate("try { throw 'x'; } catch (e) { throw e; }") {
+        Err(EvalError::Thrown(value)) => assert_eq!(value, Value::String("x".to_string())),
+        _ => panic!("wrong result"),
+    }
+    match try_evaluate("throw 1") {
+        Err(EvalError::Thrown(value)) => assert_eq!(value, Value::Number(1.0)),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_switch() {
+    // Falling through to the next clause until a break.
+    assert_eq!(
+        try_evaluate("s = ''; switch (2) { case 1: s = s + 'a'; case 2: s = s + 'b'; case 3: s = s + 'c'; break; case 4: s = s + 'd'; } s")
+            .unwrap(),
+        Value::String("bc".to_string())
+    );
+
+    // The default clause in the middle is taken only if no case matches,
+    // after testing the cases after it, and falls through too.
+    let source = "s = ''; switch (x) { case 1: s = s + 'a'; default: s = s + 'd'; case 2: s = s + 'b'; break; case 3: s = s + 'c'; } s";
+    assert_eq!(
+        try_evaluate(&format!("x = 3; {}", source)).unwrap(),
+        Value::String("c".to_string())
+    );
+    assert_eq!(
+        try_evaluate(&format!("x = 5; {}", source)).unwrap(),
+        Value::String("db".to_string())
+    );
+    assert_eq!(
+        try_evaluate(&format!("x = 1; {}", source)).unwrap(),
+        Value::String("adb".to_string())
+    );
+
+    // Cases are compared with ===, evaluating the tests in order until one
+    // matches, and the discriminant only once.
+    assert_eq!(
+        try_evaluate("switch ('1') { case 1: 'number'; break; case '1': 'string'; }").unwrap(),
+        Value::String("string".to_string())
+    );
+    assert_eq!(
+        try_evaluate("n = 0; switch (n = n + 1) { case (n = n + 10, 1): case (n = n + 100): } n")
+            .unwrap(),
+        Value::Number(11.0)
+    );
+
+    // Empty switches, and ones without a matching case.
+    assert_eq!(
+        try_evaluate("n = 0; switch (n = 1) {} n").unwrap(),
+        Value::Number(1.0)
+    );
+    assert_eq!(try_evaluate("1; switch (0) {}").unwrap(), Value::Undefined);
+    assert_eq!(
+        try_evaluate("1; switch (0) { case 1: 2; }").unwrap(),
+        Value::Undefined
+    );
+
+    // `continue` goes to the loop around the switch.
+    assert_eq!(
+        try_evaluate("s = ''; for (x of [1, 2]) { switch (x) { case 1: continue; } s = s + x; } s")
+            .unwrap(),
+        Value::String("2".to_string())
+    );
+}
+
+#[test]
+fn test_labels() {
+    // `break` and `continue` with a label apply to the loop with it, not the
+    // innermost one.
+    assert_eq!(
+        try_evaluate(
+            "s = ''; outer: for (x of [1, 2, 3]) { inner: for (y of ['a', 'b']) { \
+             s = s + x + y; if (x - 1) break outer; continue outer; } s = 'no'; } s"
+        )
+        .unwrap(),
+        Value::String("1a2a".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; outer: for (x of [1, 2]) { inner: for (y of ['a', 'b']) { \
+             if (x - 1) continue inner; s = s + x + y; } s = s + ';'; } s"
+        )
+        .unwrap(),
+        Value::String("1a1b;;".to_string())
+    );
+    // A loop can have more than one label, and `switch` doesn't take a
+    // labelled `break`.
+    assert_eq!(
+        try_evaluate(
+            "s = ''; a: b: for (x of [1, 2, 3]) { switch (x) { case 2: continue a; case 3: break b; } \
+             s = s + x; } s"
+        )
+        .unwrap(),
+        Value::String("1".to_string())
+    );
+
+    // The same in `for`, `while` and `do-while` loops.
+    assert_eq!(
+        try_evaluate(
+            "s = ''; outer: for (x = 1; x !== 4; x++) { for (y = 0; y !== 2; y++) { \
+             s = s + x + y; if (x === 2) break outer; continue outer; } s = 'no'; } s"
+        )
+        .unwrap(),
+        Value::String("1020".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; x = 0; outer: while (x !== 3) { x++; y = 0; while (true) { \
+             y++; s = s + x + y; if (x === 2) break outer; continue outer; } s = 'no'; } s"
+        )
+        .unwrap(),
+        Value::String("1121".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; x = 0; outer: do { x++; inner: do { \
+             s = s + x; if (x === 2) break outer; continue outer; } while (true); s = 'no'; \
+             } while (x !== 3); s"
+        )
+        .unwrap(),
+        Value::String("12".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; a: b: for (x = 0; x !== 3; x++) { y = 0; do { y++; \
+             if (y === 2) continue b; if (x === 2) break a; s = s + x + y; } while (true); } s"
+        )
+        .unwrap(),
+        Value::String("0111".to_string())
+    );
+
+    // `break` can leave a labelled block, running the `finally` blocks on
+    // the way.
+    assert_eq!(
+        try_evaluate("s = 'a'; block: { s = s + 'b'; if (s) break block; s = 'no'; } s + 'c'")
+            .unwrap(),
+        Value::String("abc".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "s = ''; block: { for (x of [1, 2]) { try { break block; } finally { s = s + x; } } \
+             s = 'no'; } s"
+        )
+        .unwrap(),
+        Value::String("1".to_string())
+    );
+    assert_eq!(
+        try_evaluate("function f() { a: { b: { break a; } return 'no'; } return 'yes'; } f()")
+            .unwrap(),
+        Value::String("yes".to_string())
+    );
+}
+
+#[test]
+fn test_functions() {
+    // Declarations are defined before the statements before them run.
+    assert_eq!(
+        try_evaluate("r = add('a', 'b'); function add(x, y) { return x + y; } r").unwrap(),
+        Value::String("ab".to_string())
+    );
+
+    // Missing arguments are undefined, and parameters can be assigned.
+    assert_eq!(
+        try_evaluate("function f(x, y) { x = x + y; return x; } f(1)").unwrap(),
+        Value::Number(f64::NAN)
+    );
+    assert_eq!(
+        try_evaluate("function f() { 1; } f()").unwrap(),
+        Value::Undefined
+    );
+    assert_eq!(
+        try_evaluate("f = function (x) { return x; }; f(3)").unwrap(),
+        Value::Number(3.0)
+    );
+
+    // Method calls, and returning out of a for-of loop closes its iterator
+    // and runs the finally blocks.
+    assert_eq!(
+        try_evaluate(
+            "log = '';
+             o = { m: function (x) { return x + '!'; } };
+             function f(p) {
+                 try {
+                     for (x of [1, 2]) { return o.m(p + x); }
+                 } finally {
+                     log = log + 'finally';
+                 }
+             }
+             f(1) + o['m'](log)"
+        )
+        .unwrap(),
+        Value::String("2!finally!".to_string())
+    );
+
+    assert!(matches!(
+        try_evaluate("x = 1; x()"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert!(matches!(
+        try_evaluate("function f() { f(); } f()"),
+        Err(EvalError::TooMuchRecursion)
+    ));
+}
+
+#[test]
+fn test_generators() {
+    let realm = Realm::new();
+    try_evaluate_in(&realm, "function* g() { yield 1; yield 2; } it = g();").unwrap();
+    let next = |realm: &Realm| {
+        let result = try_evaluate_in(realm, "r = it.next(); [r.value, r.done]").unwrap();
+        match result {
+            Value::Object(array) => {
+                let array = array.borrow();
+                (array.get("0".to_string()), array.get("1".to_string()))
+            }
+            other => panic!("not an array: {:?}", other),
+        }
+    };
+    assert_eq!(next(&realm), (Value::Number(1.0), Value::Bool(false)));
+    assert_eq!(next(&realm), (Value::Number(2.0), Value::Bool(false)));
+    assert_eq!(next(&realm), (Value::Undefined, Value::Bool(true)));
+    assert_eq!(next(&realm), (Value::Undefined, Value::Bool(true)));
+
+    // The body starts running at the first `next` call, and each `yield`
+    // evaluates to the value passed to the next one. The return value comes
+    // with `done` true.
+    assert_eq!(
+        try_evaluate(
+            "log = '';
+             function* g(x) { log = log + 'start'; y = yield x; return y + '!'; }
+             it = g('a');
+             log = log + ',';
+             a = it.next('ignored');
+             b = it.next('b');
+             log + ',' + a.value + a.done + ',' + b.value + b.done"
+        )
+        .unwrap(),
+        Value::String(",start,afalse,b!true".to_string())
+    );
+
+    // Generators are iterable, and `yield*` yields the values of another
+    // iterable.
+    assert_eq!(
+        try_evaluate(
+            "function* g() { yield 1; r = yield* [2, 3]; yield* inner(); }
+             function* inner() { yield 4; }
+             s = ''; for (x of g()) s = s + x; s + r"
+        )
+        .unwrap(),
+        Value::String("1234undefined".to_string())
+    );
+
+    // Exceptions thrown in the body are thrown by `next`, and complete the
+    // generator.
+    assert_eq!(
+        try_evaluate(
+            "function* g() { throw 'oops'; }
+             it = g();
+             try { it.next(); } catch (e) { s = e; }
+             s + it.next().done"
+        )
+        .unwrap(),
+        Value::String("oopstrue".to_string())
+    );
+}
+
+#[test]
+fn test_async_functions() {
+    // The jobs resuming async functions run after the script, so the results
+    // are seen by the next one.
+    let realm = Realm::new();
+    try_evaluate_in(
+        &realm,
+        "async function f() { return await 41 + 1; }
+         async function g() { await 0; return f(); }
+         async function main() { result = await f(); adopted = await g(); }
+         log = 'sync';
+         main();
+         log = log + ',after main';",
+    )
+    .unwrap();
+    assert_eq!(
+        try_evaluate_in(&realm, "log + ',' + result + ',' + adopted").unwrap(),
+        Value::String("sync,after main,42,42".to_string())
+    );
+
+    // What an async function throws rejects its promise, and the `await`
+    // throws it.
+    assert_eq!(
+        try_evaluate_in(
+            &realm,
+            "async function fail() { await 1; throw 'oops'; }
+             async function catcher() {
+               try { await fail(); } catch (e) { caught = e; }
+               finally { done = true; }
+             }
+             catcher();
+             0",
+        )
+        .unwrap(),
+        Value::Number(0.0)
+    );
+    assert_eq!(
+        try_evaluate_in(&realm, "caught + done").unwrap(),
+        Value::String("oopstrue".to_string())
+    );
+
+    // Jobs run in the order they're queued.
+    assert_eq!(
+        try_evaluate_in(
+            &realm,
+            "order = '';
+             async function a() { order = order + 'a1'; await 0; order = order + 'a2'; }
+             async function b() { order = order + 'b1'; await 0; order = order + 'b2'; }
+             a(); b();
+             order",
+        )
+        .unwrap(),
+        Value::String("a1b1".to_string())
+    );
+    assert_eq!(
+        try_evaluate_in(&realm, "order").unwrap(),
+        Value::String("a1b1a2b2".to_string())
+    );
+}
+
+#[test]
+fn test_classes() {
+    // Methods are shared by the instances through the prototype, and the
+    // fields are defined in order before the constructor body runs.
+    assert_eq!(
+        try_evaluate(
+            "class Point {
+               x = 1;
+               y = this.x + 1;
+               constructor(x) { this.sum = this.x + this.y; this.x = x; }
+               get_x() { return this.x; }
+               static origin() { return new Point(0); }
+             }
+             p = new Point(5);
+             '' + p.get_x() + p.sum + Point.origin().get_x() + (p.get_x === Point.origin().get_x)"
+        )
+        .unwrap(),
+        Value::String("530true".to_string())
+    );
+
+    // The static fields and blocks run with the class as `this`, and class
+    // expressions can be anonymous.
+    assert_eq!(
+        try_evaluate(
+            "C = class { static a = 1; static { this.b = this.a + 1; } m() { return this; } };
+             c = new C();
+             '' + C.a + C.b + (c.m() === c) + (C.prototype.constructor === C)"
+        )
+        .unwrap(),
+        Value::String("12truetrue".to_string())
+    );
+
+    // A constructor can return another object instead of `this`.
+    assert_eq!(
+        try_evaluate("class A { constructor() { return { k: 1 }; } } new A().k").unwrap(),
+        Value::Number(1.0)
+    );
+
+    assert!(matches!(
+        try_evaluate("class A {} A()"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert!(matches!(
+        try_evaluate("x = 1; new x()"),
+        Err(EvalError::TypeError(_))
+    ));
+}
+
+#[test]
+fn test_private_fields() {
+    assert_eq!(
+        try_evaluate(
+            "class Counter {
+               #count = 0;
+               static #created = 0;
+               constructor() { Counter.#created ||= 1; }
+               increment() { this.#count = this.#count + 1; return this.#count; }
+               static created() { return Counter.#created; }
+             }
+             c = new Counter();
+             c.increment();
+             '' + c.increment() + Counter.created() + c.count"
+        )
+        .unwrap(),
+        Value::String("21undefined".to_string())
+    );
+
+    // Getting or setting a private field of an object without it throws a
+    // TypeError, which can be caught.
+    assert!(matches!(
+        try_evaluate("class A { #x = 1; static get(o) { return o.#x; } } A.get({})"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert!(matches!(
+        try_evaluate("class A { #x; static set(o) { o.#x = 1; } } A.set(new A()); A.set({})"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert_eq!(
+        try_evaluate(
+            "class A { #x; static get(o) { return o.#x; } }
+             try { A.get(null); } catch (e) { s = e.name; }
+             s"
+        )
+        .unwrap(),
+        Value::String("TypeError".to_string())
+    );
+
+    // Private names with the same name in other classes, or in other
+    // evaluations of the same class, are different.
+    assert!(matches!(
+        try_evaluate(
+            "class A { #x; static g(o) { return o.#x; } }
+             class B { #x; }
+             A.g(new B())"
+        ),
+        Err(EvalError::TypeError(_))
+    ));
+    assert_eq!(
+        try_evaluate(
+            "function make() { return class { #x = 1; static g(o) { return o.#x; } }; }
+             var C = make(), D = make();
+             var s = '' + C.g(new C());
+             try { D.g(new C()); } catch (e) { s = s + e.name; }
+             s"
+        )
+        .unwrap(),
+        Value::String("1TypeError".to_string())
+    );
+
+    // A class in a class sees the private names of the outer one, unless it
+    // declares the same name.
+    assert_eq!(
+        try_evaluate(
+            "class A {
+               #x = 1;
+               m() {
+                 class B { #y = 2; static g(o) { return o.#x; } }
+                 return B.g(this);
+               }
+             }
+             new A().m()"
+        )
+        .unwrap(),
+        Value::Number(1.0)
+    );
+    assert!(matches!(
+        try_evaluate(
+            "class A {
+               #x = 1;
+               m() {
+                 class B { #x = 2; static g(o) { return o.#x; } }
+                 return B.g(this);
+               }
+             }
+             new A().m()"
+        ),
+        Err(EvalError::TypeError(_))
+    ));
+}
+
+#[test]
+fn test_dynamic_import() {
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    let mut realm = Realm::new();
+    let recorded = requests.clone();
+    realm.register_dynamic_import(Box::new(move |args| {
+        recorded.borrow_mut().push(format!("{:?}", args));
+        Value::String("namespace".to_string())
+    }));
+
+    assert_eq!(
+        try_evaluate_in(&realm, "import('a') + import('b', 1)").unwrap(),
+        Value::String("namespacenamespace".to_string())
+    );
+    assert_eq!(
+        *requests.borrow(),
+        vec![r#"["a", undefined]"#, r#"["b", 1]"#]
+    );
+
+    match try_evaluate("import('a')") {
+        Err(EvalError::NotImplemented(_)) => (),
         _ => panic!("wrong result"),
     }
 }
+
+#[test]
+fn test_import_meta() {
+    // `import.meta` is only allowed in modules, which aren't emitted yet, so
+    // a script with one is a syntax error.
+    let allocator = &Bump::new();
+    assert_eq!(
+        parse_script(allocator, "import.meta.x = 1; m = import.meta;").unwrap_err(),
+        ParseError::ImportMetaOutsideModule(0)
+    );
+}
+
+#[test]
+fn test_new_target() {
+    // It's undefined in a plain call, and the function called with `new`.
+    assert_eq!(
+        try_evaluate("function F() { return new.target; } F()").unwrap(),
+        Value::Undefined
+    );
+    assert_eq!(
+        try_evaluate("function F() { return new.target; } new F() === F").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        try_evaluate(
+            "class C { constructor() { this.t = new.target; } }
+             new C().t === C"
+        )
+        .unwrap(),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn test_closures() {
+    // Each call of `counter` makes a new `n`, which the function it returns
+    // goes on updating after it returns.
+    assert_eq!(
+        try_evaluate(
+            "function counter() {
+               var n = 0;
+               return function () { n = n + 1; return n; };
+             }
+             var a = counter(), b = counter();
+             a(); a(); b();
+             '' + a() + b()"
+        )
+        .unwrap(),
+        Value::String("32".to_string())
+    );
+
+    // A function can update its enclosing function's parameters, too.
+    assert_eq!(
+        try_evaluate(
+            "function f(x) { function g() { x = x + 1; } g(); g(); return x; }
+             f(5)"
+        )
+        .unwrap(),
+        Value::Number(7.0)
+    );
+
+    // Lexical loop variables are new for each iteration, but a `var` is
+    // shared by all of them.
+    assert_eq!(
+        try_evaluate(
+            "var fs = [], k = 0;
+             for (let i of [0, 1, 2]) { fs[k] = function () { return i; }; k = k + 1; }
+             '' + fs[0]() + fs[1]() + fs[2]()"
+        )
+        .unwrap(),
+        Value::String("012".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "var fs = [], k = 0;
+             for (var i of [0, 1, 2]) { fs[k] = function () { return i; }; k = k + 1; }
+             '' + fs[0]() + fs[1]() + fs[2]()"
+        )
+        .unwrap(),
+        Value::String("222".to_string())
+    );
+
+    // Bindings of blocks, including one that's left by a throw.
+    assert_eq!(
+        try_evaluate(
+            "function f() {
+               var g;
+               { const c = 2; g = function () { return c; }; }
+               try { const d = 1; throw function () { return d + g(); }; }
+               catch (h) { return h(); }
+             }
+             f()"
+        )
+        .unwrap(),
+        Value::Number(3.0)
+    );
+}
+
+#[test]
+fn test_typeof() {
+    // A name that isn't declared isn't a ReferenceError here.
+    assert_eq!(
+        try_evaluate("typeof undeclared === 'undefined'").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        try_evaluate(
+            "function f() {}
+             class C {}
+             typeof 1 + ' ' + typeof 'a' + ' ' + typeof true + ' ' + typeof 1n + ' ' +
+             typeof null + ' ' + typeof {} + ' ' + typeof f + ' ' + typeof C + ' ' +
+             typeof void f() + ' ' + typeof typeof f"
+        )
+        .unwrap(),
+        Value::String(
+            "number string boolean bigint object object function function undefined string"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_void() {
+    // The operand is evaluated for its side effects.
+    assert_eq!(
+        try_evaluate("var x = 1; var y = void (x = 2); '' + y + x").unwrap(),
+        Value::String("undefined2".to_string())
+    );
+}
+
+#[test]
+fn test_delete() {
+    assert_eq!(
+        try_evaluate(
+            "var o = { a: 1, b: 2 };
+             '' + delete o.a + delete o['b'] + delete o.c + ('a' in o) + ('b' in o)"
+        )
+        .unwrap(),
+        Value::String("truetruetruefalsefalse".to_string())
+    );
+
+    // Deleting an own property uncovers the inherited one.
+    assert_eq!(
+        try_evaluate(
+            "class C { m() {} }
+             var c = new C();
+             c.m = 2;
+             '' + delete c.m + typeof c.m + delete c.m + typeof c.m"
+        )
+        .unwrap(),
+        Value::String("truefunctiontruefunction".to_string())
+    );
+
+    // The `prototype` of a class can't be deleted, which is a TypeError in
+    // strict mode code.
+    assert_eq!(
+        try_evaluate("class C {} '' + delete C.prototype + typeof C.prototype").unwrap(),
+        Value::String("falseobject".to_string())
+    );
+    assert!(matches!(
+        try_evaluate("'use strict'; class C {} delete C.prototype"),
+        Err(EvalError::TypeError(_))
+    ));
+    assert!(matches!(
+        try_evaluate("var o = null; delete o.x"),
+        Err(EvalError::TypeError(_))
+    ));
+
+    // Declared variables can't be deleted, but assigned globals can.
+    assert_eq!(
+        try_evaluate(
+            "var x = 1; y = 2;
+             '' + delete x + delete y + typeof x + typeof y + delete 1"
+        )
+        .unwrap(),
+        Value::String("falsetruenumberundefinedtrue".to_string())
+    );
+}
+
+#[test]
+fn test_in_and_instanceof() {
+    assert_eq!(
+        try_evaluate(
+            "var obj = { x: 1 };
+             class A {}
+             var a = new A();
+             '' + ('x' in obj) + ('y' in obj) + ('x' in a) + (0 in [1]) + (1 in [1])"
+        )
+        .unwrap(),
+        Value::String("truefalsefalsetruefalse".to_string())
+    );
+    assert!(matches!(
+        try_evaluate("'x' in 'xyz'"),
+        Err(EvalError::TypeError(_))
+    ));
+
+    // `extends` isn't implemented yet, so the prototype chains are short.
+    assert_eq!(
+        try_evaluate(
+            "class A {}
+             class B {}
+             var a = new A();
+             '' + (a instanceof A) + (a instanceof B) + ({} instanceof A) + (1 instanceof A)"
+        )
+        .unwrap(),
+        Value::String("truefalsefalsefalse".to_string())
+    );
+    assert!(matches!(
+        try_evaluate("({}) instanceof {}"),
+        Err(EvalError::TypeError(_))
+    ));
+
+    // Objects made with `new` from a function inherit from its `prototype`.
+    assert_eq!(
+        try_evaluate("function F(){}; new F() instanceof F").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        try_evaluate(
+            "function F() {}
+             function G() {}
+             '' + (new F() instanceof G) + ({} instanceof F) + (1 instanceof F)"
+        )
+        .unwrap(),
+        Value::String("falsefalsefalse".to_string())
+    );
+    assert_eq!(
+        try_evaluate(
+            "function F(x) { this.x = x; }
+             F.prototype.y = 2;
+             var f = new F(1);
+             '' + f.x + f.y"
+        )
+        .unwrap(),
+        Value::String("12".to_string())
+    );
+
+    // The prototype's `constructor` is the function, and the `prototype`
+    // can't be deleted.
+    assert_eq!(
+        try_evaluate("function F() {} F.prototype.constructor === F").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        try_evaluate("function F() {} '' + delete F.prototype + typeof F.prototype").unwrap(),
+        Value::String("falseobject".to_string())
+    );
+    assert!(matches!(
+        try_evaluate("'use strict'; function F() {} delete F.prototype"),
+        Err(EvalError::TypeError(_))
+    ));
+
+    // Only functions that can be constructors have a `prototype`.
+    assert_eq!(
+        try_evaluate(
+            "async function g() {}
+             '' + typeof (() => 1).prototype + typeof g.prototype
+                + typeof ({ m() {} }).m.prototype + delete (() => 1).prototype"
+        )
+        .unwrap(),
+        Value::String("undefinedundefinedundefinedtrue".to_string())
+    );
+    for source in [
+        "var a = () => 1; new a()",
+        "new (async () => 1)()",
+        "async function g() {} new g()",
+        "function* g() {} new g()",
+        "var o = { m() {} }; new o.m()",
+    ] {
+        assert!(
+            matches!(try_evaluate(source), Err(EvalError::TypeError(_))),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_arrow_functions() {
+    assert_eq!(
+        try_evaluate("var a = (x, y) => { return x - y; }; a(3, 4)").unwrap(),
+        Value::Number(-1.0)
+    );
+    assert_eq!(
+        try_evaluate("var a = ([x], { y = 2 }) => x + y; a([1], {})").unwrap(),
+        Value::Number(3.0)
+    );
+    assert_eq!(
+        try_evaluate("(a => b => a + b)(1)(2)").unwrap(),
+        Value::Number(3.0)
+    );
+    assert_eq!(try_evaluate("(() => {})()").unwrap(), Value::Undefined);
+    assert_eq!(
+        try_evaluate("var f = async () => 1; typeof f").unwrap(),
+        Value::String("function".to_string())
+    );
+}
+
+#[test]
+fn test_update_expressions() {
+    assert_eq!(
+        try_evaluate("var i = 1; var r = i++; '' + r + i").unwrap(),
+        Value::String("12".to_string())
+    );
+    assert_eq!(
+        try_evaluate("var i = 1; var r = --i; '' + r + i").unwrap(),
+        Value::String("00".to_string())
+    );
+    // The old value is converted to a number.
+    assert_eq!(
+        try_evaluate("var s = '5'; var r = s++; typeof r + r + s").unwrap(),
+        Value::String("number56".to_string())
+    );
+    assert!(matches!(
+        try_evaluate("var u; u++").unwrap(),
+        Value::Number(n) if n.is_nan()
+    ));
+    assert_eq!(
+        try_evaluate("var o = {a: [5]}; var r = o.a[0]++; '' + r + o.a[0]").unwrap(),
+        Value::String("56".to_string())
+    );
+    assert_eq!(
+        try_evaluate("var k = 'p', o = {p: 2}; o[k]++ + o[k]").unwrap(),
+        Value::Number(5.0)
+    );
+    assert_eq!(
+        try_evaluate("class C { #x = 1; m() { this.#x++; return ++this.#x; } } new C().m()")
+            .unwrap(),
+        Value::Number(3.0)
+    );
+}
+
+#[test]
+fn test_arrow_closures() {
+    assert_eq!(
+        try_evaluate("function make(){ let n=0; return ()=>++n; } var f = make(); f(); f()")
+            .unwrap(),
+        Value::Number(2.0)
+    );
+    // Each call of `make` has its own `n`.
+    assert_eq!(
+        try_evaluate(
+            "function make(){ let n=0; return ()=>++n; }
+             var a = make(), b = make();
+             a(); a(); b();
+             '' + a() + b()"
+        )
+        .unwrap(),
+        Value::String("32".to_string())
+    );
+}
@@ -1,60 +1,304 @@
+use emitter::SymbolCode;
+
 use std::cell::RefCell;
 use std::f64;
 use std::fmt;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use crate::function::Function;
 use crate::object::Object;
 
+/// A function provided by the host; see `Realm::register_fn`.
+pub type NativeFunction = dyn Fn(&[Value]) -> Value;
+
+/// A JS value, as produced by evaluating a script.
 #[derive(Clone)]
-pub enum JSValue {
-    Boolean(bool),
+pub enum Value {
+    Bool(bool),
     Number(f64),
+    /// A BigInt, as decimal digits with a `-` sign if it's negative.
+    BigInt(String),
     String(String),
+    /// A well-known symbol, such as `Symbol.iterator`. Scripts can't create
+    /// other symbols yet.
+    Symbol(SymbolCode),
     Object(Rc<RefCell<Object>>),
-    NativeFunction(fn(JSValue, &[JSValue]) -> JSValue),
+    NativeFunction(Rc<NativeFunction>),
+    Function(Rc<Function>),
     Undefined,
     Null,
 }
 
-impl fmt::Debug for JSValue {
+impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Boolean(b) => write!(f, "{}", b),
+            Self::Bool(b) => write!(f, "{}", b),
             Self::Number(n) => write!(f, "{}", n),
+            Self::BigInt(ref n) => write!(f, "{}n", n),
             Self::String(ref s) => write!(f, "{:?}", s),
-            Self::Object(ref o) => write!(f, "{:?}", o),
-            Self::NativeFunction(ref n) => write!(f, "<native-function: {:p}>", n),
+            Self::Symbol(symbol) => write!(f, "Symbol(Symbol.{})", symbol.name()),
+            // Not the properties of classes, as their prototype refers back
+            // to them.
+            Self::Object(ref o) => match o.borrow().class_constructor() {
+                Some(code) => write!(f, "<class: {}>", code.name.as_deref().unwrap_or("")),
+                None => write!(f, "{:?}", o),
+            },
+            Self::NativeFunction(ref n) => write!(f, "<native-function: {:p}>", Rc::as_ptr(n)),
+            Self::Function(ref function) => write!(f, "{:?}", function),
             Self::Undefined => write!(f, "undefined"),
             Self::Null => write!(f, "null"),
         }
     }
 }
 
-pub fn to_number(v: &JSValue) -> f64 {
+/// Objects and functions are equal only if they're the same object, as with
+/// the `===` operator, but NaN is equal to itself.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Self::BigInt(a), Self::BigInt(b)) | (Self::String(a), Self::String(b)) => a == b,
+            (Self::Symbol(a), Self::Symbol(b)) => a == b,
+            (Self::Object(a), Self::Object(b)) => Rc::ptr_eq(a, b),
+            (Self::NativeFunction(a), Self::NativeFunction(b)) => {
+                Rc::as_ptr(a) as *const u8 == Rc::as_ptr(b) as *const u8
+            }
+            (Self::Function(a), Self::Function(b)) => Rc::ptr_eq(a, b),
+            (Self::Undefined, Self::Undefined) | (Self::Null, Self::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /// The number, if this is a number. Unlike `to_number`, other values
+    /// are not converted.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The boolean, if this is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The string `typeof` gives for the value.
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::BigInt(_) => "bigint",
+            Self::String(_) => "string",
+            Self::Symbol(_) => "symbol",
+            Self::Object(o) if o.borrow().class_constructor().is_some() => "function",
+            Self::Object(_) | Self::Null => "object",
+            Self::NativeFunction(_) | Self::Function(_) => "function",
+            Self::Undefined => "undefined",
+        }
+    }
+
+    /// ToString, as used by `String(value)`, except that objects are not
+    /// asked for their `toString` method.
+    pub fn to_js_string(&self) -> String {
+        match self {
+            Self::Bool(b) => b.to_string(),
+            Self::Number(n) => number_to_string(*n),
+            Self::BigInt(n) | Self::String(n) => n.clone(),
+            // TODO: implicit conversions of symbols, such as `'' + symbol`,
+            // should throw a TypeError.
+            Self::Symbol(symbol) => format!("Symbol(Symbol.{})", symbol.name()),
+            Self::Object(o) => match o.borrow().class_constructor() {
+                Some(code) => format!("class {} {{ [code] }}", code.name.as_deref().unwrap_or("")),
+                None => "[object Object]".to_owned(),
+            },
+            Self::NativeFunction(_) => "function () { [native code] }".to_owned(),
+            // TODO: the source text of functions defined by scripts.
+            Self::Function(function) => format!("function {}() {{ [code] }}", function.name()),
+            Self::Undefined => "undefined".to_owned(),
+            Self::Null => "null".to_owned(),
+        }
+    }
+}
+
+/// Number::toString, which uses the shortest digits that round-trip like
+/// Rust does, but switches to exponent notation differently.
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_owned();
+    }
+    if n == 0.0 {
+        return "0".to_owned();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_owned();
+    }
+
+    // `{:e}` gives the digits and exponent, like "-1.2345e3". In the terms of
+    // the spec, the value is `0.digits * 10^point`.
+    let formatted = format!("{:e}", n.abs());
+    let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let point = exponent[1..].parse::<i32>().unwrap() + 1;
+    let num_digits = digits.len() as i32;
+
+    let mut out = String::new();
+    if n < 0.0 {
+        out.push('-');
+    }
+    if num_digits <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend((num_digits..point).map(|_| '0'));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend((point..0).map(|_| '0'));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if num_digits > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if point > 0 { '+' } else { '-' });
+        out.push_str(&(point - 1).abs().to_string());
+    }
+    out
+}
+
+/// The negation of a BigInt, in the representation of `Value::BigInt`.
+pub fn negate_bigint(n: &str) -> String {
+    match n.strip_prefix('-') {
+        Some(positive) => positive.to_owned(),
+        None if n == "0" => n.to_owned(),
+        None => format!("-{}", n),
+    }
+}
+
+/// The BigInt `n + 1`, or `n - 1` if `increment` is false, for `++` and
+/// `--`, in the representation of `Value::BigInt`.
+pub fn step_bigint(n: &str, increment: bool) -> String {
+    let (negative, magnitude) = match n.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, n),
+    };
+    if magnitude == "0" {
+        if increment {
+            "1".to_owned()
+        } else {
+            "-1".to_owned()
+        }
+    } else if negative == increment {
+        // Towards zero. The magnitude is at least 1.
+        let mut digits = magnitude.as_bytes().to_vec();
+        let mut i = digits.len();
+        loop {
+            i -= 1;
+            if digits[i] == b'0' {
+                digits[i] = b'9';
+            } else {
+                digits[i] -= 1;
+                break;
+            }
+        }
+        let digits = String::from_utf8(digits).unwrap();
+        let digits = match digits.trim_start_matches('0') {
+            "" => "0",
+            trimmed => trimmed,
+        };
+        if negative {
+            negate_bigint(digits)
+        } else {
+            digits.to_owned()
+        }
+    } else {
+        // Away from zero.
+        let mut digits = magnitude.as_bytes().to_vec();
+        let mut i = digits.len();
+        loop {
+            if i == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+            i -= 1;
+            if digits[i] == b'9' {
+                digits[i] = b'0';
+            } else {
+                digits[i] += 1;
+                break;
+            }
+        }
+        let digits = String::from_utf8(digits).unwrap();
+        if negative {
+            format!("-{}", digits)
+        } else {
+            digits
+        }
+    }
+}
+
+pub fn to_number(v: &Value) -> f64 {
     match v {
-        JSValue::Boolean(true) => 1.0,
-        JSValue::Boolean(false) => 0.0,
-        JSValue::Number(n) => *n,
-        JSValue::String(ref s) => f64::from_str(s).unwrap_or(f64::NAN),
-        JSValue::Object(_) | JSValue::NativeFunction(_) => f64::NAN, // ToDo: valueOf
-        JSValue::Undefined => f64::NAN,
-        JSValue::Null => 0.0,
+        Value::Bool(true) => 1.0,
+        Value::Bool(false) => 0.0,
+        Value::Number(n) => *n,
+        Value::String(ref s) => f64::from_str(s).unwrap_or(f64::NAN),
+        Value::Object(_) | Value::NativeFunction(_) | Value::Function(_) => f64::NAN, // ToDo: valueOf
+        Value::BigInt(_) | Value::Symbol(_) => f64::NAN, // ToDo: TypeError
+        Value::Undefined => f64::NAN,
+        Value::Null => 0.0,
+    }
+}
+
+/// The `**` operator on numbers, which differs from `powf` when the base is
+/// 1 or -1: the result is NaN if the exponent is NaN or infinite.
+pub fn exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        return f64::NAN;
     }
+    base.powf(exponent)
 }
 
-pub fn to_boolean(v: &JSValue) -> bool {
+pub fn to_boolean(v: &Value) -> bool {
     match v {
-        JSValue::Null | JSValue::Undefined => false,
-        JSValue::Boolean(b) => *b,
-        JSValue::Number(n) => {
+        Value::Null | Value::Undefined => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => {
             if *n == 0.0 || n.is_nan() {
                 false
             } else {
                 true
             }
         }
-        JSValue::String(ref s) => !s.is_empty(),
-        JSValue::Object(_) | JSValue::NativeFunction(_) => true,
+        Value::String(ref s) => !s.is_empty(),
+        Value::BigInt(ref n) => n != "0",
+        Value::Symbol(_) | Value::Object(_) | Value::NativeFunction(_) | Value::Function(_) => true,
+    }
+}
+
+/// The `===` operator: like `==` on `Value`, but NaN isn't equal to itself.
+pub fn strict_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b,
+        _ => a == b,
     }
 }
@@ -0,0 +1,265 @@
+/*!
+ * A small DOM utility library, in the style of jQuery, for benchmarking the
+ * parser. It isn't meant to run.
+ */
+(function (global, factory) {
+    "use strict";
+    if (typeof module === "object" && typeof module.exports === "object") {
+        module.exports = factory(global, true);
+    } else {
+        factory(global);
+    }
+})(typeof window !== "undefined" ? window : this, function (window, noGlobal) {
+    "use strict";
+
+    var arr = [];
+    var slice = arr.slice;
+    var push = arr.push;
+    var indexOf = arr.indexOf;
+    var class2type = {};
+    var toString = class2type.toString;
+    var hasOwn = class2type.hasOwnProperty;
+    var document = window.document;
+    var version = "1.0.0";
+    var rtrim = /^[\s\uFEFF\xA0]+|[\s\uFEFF\xA0]+$/g;
+    var rsingleTag = /^<([a-z][^\/\0>:\x20\t\r\n\f]*)[\x20\t\r\n\f]*\/?>(?:<\/\1>|)$/i;
+    var rnothtmlwhite = /[^\x20\t\r\n\f]+/g;
+
+    function isFunction(obj) {
+        return typeof obj === "function" && typeof obj.nodeType !== "number";
+    }
+
+    function isWindow(obj) {
+        return obj != null && obj === obj.window;
+    }
+
+    function toType(obj) {
+        if (obj == null) {
+            return obj + "";
+        }
+        return typeof obj === "object" || typeof obj === "function"
+            ? class2type[toString.call(obj)] || "object"
+            : typeof obj;
+    }
+
+    function isArrayLike(obj) {
+        var length = !!obj && "length" in obj && obj.length,
+            type = toType(obj);
+        if (isFunction(obj) || isWindow(obj)) {
+            return false;
+        }
+        return type === "array" || length === 0 ||
+            typeof length === "number" && length > 0 && (length - 1) in obj;
+    }
+
+    var lib = function (selector, context) {
+        return new lib.fn.init(selector, context);
+    };
+
+    lib.fn = lib.prototype = {
+        version: version,
+        constructor: lib,
+        length: 0,
+
+        toArray: function () {
+            return slice.call(this);
+        },
+
+        get: function (num) {
+            if (num == null) {
+                return slice.call(this);
+            }
+            return num < 0 ? this[num + this.length] : this[num];
+        },
+
+        pushStack: function (elems) {
+            var ret = lib.merge(this.constructor(), elems);
+            ret.prevObject = this;
+            return ret;
+        },
+
+        each: function (callback) {
+            return lib.each(this, callback);
+        },
+
+        map: function (callback) {
+            return this.pushStack(lib.map(this, function (elem, i) {
+                return callback.call(elem, i, elem);
+            }));
+        },
+
+        eq: function (i) {
+            var len = this.length,
+                j = +i + (i < 0 ? len : 0);
+            return this.pushStack(j >= 0 && j < len ? [this[j]] : []);
+        },
+
+        end: function () {
+            return this.prevObject || this.constructor();
+        }
+    };
+
+    lib.extend = lib.fn.extend = function () {
+        var options, name, src, copy, copyIsArray, clone,
+            target = arguments[0] || {},
+            i = 1,
+            length = arguments.length,
+            deep = false;
+
+        if (typeof target === "boolean") {
+            deep = target;
+            target = arguments[i] || {};
+            i++;
+        }
+        if (typeof target !== "object" && !isFunction(target)) {
+            target = {};
+        }
+        if (i === length) {
+            target = this;
+            i--;
+        }
+        for (; i < length; i++) {
+            if ((options = arguments[i]) != null) {
+                for (name in options) {
+                    copy = options[name];
+                    if (name === "__proto__" || target === copy) {
+                        continue;
+                    }
+                    if (deep && copy && (lib.isPlainObject(copy) ||
+                        (copyIsArray = Array.isArray(copy)))) {
+                        src = target[name];
+                        if (copyIsArray && !Array.isArray(src)) {
+                            clone = [];
+                        } else if (!copyIsArray && !lib.isPlainObject(src)) {
+                            clone = {};
+                        } else {
+                            clone = src;
+                        }
+                        copyIsArray = false;
+                        target[name] = lib.extend(deep, clone, copy);
+                    } else if (copy !== undefined) {
+                        target[name] = copy;
+                    }
+                }
+            }
+        }
+        return target;
+    };
+
+    lib.extend({
+        expando: "lib" + (version + Math.random()).replace(/\D/g, ""),
+        isReady: true,
+
+        error: function (msg) {
+            throw new Error(msg);
+        },
+
+        isPlainObject: function (obj) {
+            var proto, Ctor;
+            if (!obj || toString.call(obj) !== "[object Object]") {
+                return false;
+            }
+            proto = Object.getPrototypeOf(obj);
+            if (!proto) {
+                return true;
+            }
+            Ctor = hasOwn.call(proto, "constructor") && proto.constructor;
+            return typeof Ctor === "function" && Ctor.toString() === Object.toString();
+        },
+
+        each: function (obj, callback) {
+            var length, i = 0;
+            if (isArrayLike(obj)) {
+                length = obj.length;
+                for (; i < length; i++) {
+                    if (callback.call(obj[i], i, obj[i]) === false) {
+                        break;
+                    }
+                }
+            } else {
+                for (i in obj) {
+                    if (callback.call(obj[i], i, obj[i]) === false) {
+                        break;
+                    }
+                }
+            }
+            return obj;
+        },
+
+        trim: function (text) {
+            return text == null ? "" : (text + "").replace(rtrim, "");
+        },
+
+        merge: function (first, second) {
+            var len = +second.length,
+                j = 0,
+                i = first.length;
+            for (; j < len; j++) {
+                first[i++] = second[j];
+            }
+            first.length = i;
+            return first;
+        },
+
+        map: function (elems, callback, arg) {
+            var length, value,
+                i = 0,
+                ret = [];
+            if (isArrayLike(elems)) {
+                length = elems.length;
+                for (; i < length; i++) {
+                    value = callback(elems[i], i, arg);
+                    if (value != null) {
+                        ret.push(value);
+                    }
+                }
+            } else {
+                for (i in elems) {
+                    value = callback(elems[i], i, arg);
+                    if (value != null) {
+                        ret.push(value);
+                    }
+                }
+            }
+            return [].concat.apply([], ret);
+        },
+
+        classes: function (value) {
+            return (value.match(rnothtmlwhite) || []).join(" ");
+        }
+    });
+
+    lib.each("Boolean Number String Function Array Date RegExp Object Error Symbol".split(" "),
+        function (_i, name) {
+            class2type["[object " + name + "]"] = name.toLowerCase();
+        });
+
+    var init = lib.fn.init = function (selector, context) {
+        var match;
+        if (!selector) {
+            return this;
+        }
+        if (typeof selector === "string") {
+            match = rsingleTag.exec(selector);
+            if (match) {
+                this[0] = (context || document).createElement(match[1]);
+                this.length = 1;
+                return this;
+            }
+            return lib(context || document).find(selector);
+        } else if (selector.nodeType) {
+            this[0] = selector;
+            this.length = 1;
+            return this;
+        } else if (isFunction(selector)) {
+            return selector(lib);
+        }
+        return lib.merge(this, selector);
+    };
+    init.prototype = lib.fn;
+
+    if (!noGlobal) {
+        window.lib = window.$ = lib;
+    }
+    return lib;
+});
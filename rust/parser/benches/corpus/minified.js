@@ -0,0 +1 @@
+!function(e,t){"object"==typeof exports&&"undefined"!=typeof module?module.exports=t():"function"==typeof define&&define.amd?define(t):(e=e||self).Store=t()}(this,function(){"use strict";var e=Object.prototype.hasOwnProperty,t=Array.isArray,n=function(e){return null!==e&&"object"==typeof e},r=function(e,t){for(var n in e)if(Object.prototype.hasOwnProperty.call(e,n)&&!1===t(e[n],n))return!1;return!0},o=function(e){return"[object Object]"===Object.prototype.toString.call(e)},i=/^(?:0|[1-9]\d*)$/,a=/[A-Z]/g,u=function(e){return e.replace(a,function(e){return"-"+e.toLowerCase()})},c=function(e,t){var n=Object.create(null),r=e.split(",");for(var o=0;o<r.length;o++)n[r[o]]=!0;return t?function(e){return n[e.toLowerCase()]}:function(e){return n[e]}},s=c("slot,component",!0),f=function(e){var t=Object.create(null);return function(n){var r=t[n];return r||(t[n]=e(n))}},l=f(function(e){return e.replace(/-(\w)/g,function(e,t){return t?t.toUpperCase():""})}),p=function(e,t,n){return n=n||0,e.length-n>=t?e.slice(n,n+t):e.slice(n)},d=function(){function e(e,t){void 0===t&&(t={}),this.state=e,this.getters={},this.mutations=t.mutations||{},this.actions=t.actions||{},this.subscribers=[],this.committing=!1,this.strict=!!t.strict,this._modules=[],this._watchers=[]}return e.prototype.commit=function(e,t,n){var r=this,o="object"==typeof e?[e.type,e]:[e,t],i=o[0],a=o[1],u=this.mutations[i];if(!u)return void("production"!==process.env.NODE_ENV&&console.error("[store] unknown mutation type: "+i));this._withCommit(function(){u.forEach(function(e){e(r.state,a)})}),this.subscribers.slice().forEach(function(e){return e({type:i,payload:a},r.state)}),n&&n.silent&&console.warn("[store] mutation type: "+i+". Silent option has been removed.")},e.prototype.dispatch=function(e,t){var n=this,r="object"==typeof e?[e.type,e]:[e,t],o=r[0],i=r[1],a=this.actions[o];if(a){try{this._actionSubscribers.slice().filter(function(e){return e.before}).forEach(function(e){return e.before({type:o,payload:i},n.state)})}catch(e){console.warn("[store] error in before action subscribers: "),console.error(e)}var u=a.length>1?Promise.all(a.map(function(e){return e(i)})):a[0](i);return new Promise(function(e,t){u.then(function(t){try{n._actionSubscribers.filter(function(e){return e.after}).forEach(function(e){return e.after({type:o,payload:i},n.state)})}catch(e){console.warn("[store] error in after action subscribers: ")}e(t)},function(e){t(e)})})}"production"!==process.env.NODE_ENV&&console.error("[store] unknown action type: "+o)},e.prototype.subscribe=function(e,t){return h(e,this.subscribers,t)},e.prototype.watch=function(e,t,n){var r=this;return"production"!==process.env.NODE_ENV&&v("function"==typeof e,"store.watch only accepts a function."),this._watcherVM.$watch(function(){return e(r.state,r.getters)},t,n)},e.prototype.replaceState=function(e){var t=this;this._withCommit(function(){t._vm._data.$$state=e})},e.prototype._withCommit=function(e){var t=this.committing;this.committing=!0,e(),this.committing=t},Object.defineProperties(e.prototype,{size:{get:function(){return this._modules.length},configurable:!0}}),e}();function h(e,t,n){return t.indexOf(e)<0&&(n&&n.prepend?t.unshift(e):t.push(e)),function(){var n=t.indexOf(e);n>-1&&t.splice(n,1)}}function v(e,t){if(!e)throw new Error("[store] "+t)}function m(e,t){return t.reduce(function(e,t){return e[t]},e)}function y(e,t,n){var r;return n=n||[],null==e||"object"!=typeof e?e:(r=n.filter(function(t){return t.original===e})[0])?r.copy:(r=Array.isArray(e)?[]:{},n.push({original:e,copy:r}),Object.keys(e).forEach(function(o){r[o]=y(e[o],t,n)}),r)}var g=function(e,t){return function(n){for(var r=[],o=arguments.length-1;o-->0;)r[o]=arguments[o+1];return n?e.apply(void 0,[n].concat(r)):t.apply(void 0,r)}},b={Store:d,version:"3.1.2",install:function(e){if(b._installed&&e===b._installed)return void("production"!==process.env.NODE_ENV&&console.error("[store] already installed."));b._installed=e},mapState:g(function(e,t){var n={};return w(t).forEach(function(t){var r=t.key,o=t.val;n[r]=function(){var t=this.$store.state,n=this.$store.getters;if(e){var r=m(this.$store._modules,e.split("/"));if(!r)return;t=r.context.state,n=r.context.getters}return"function"==typeof o?o.call(this,t,n):t[o]}}),n},function(e){return e}),helpers:{hasOwn:e,isArray:t,isObject:n,forEach:r,isPlainObject:o,isIndex:i,hyphenate:u,makeMap:c,isBuiltInTag:s,cached:f,camelize:l,take:p,deepCopy:y}};function w(e){return Array.isArray(e)?e.map(function(e){return{key:e,val:e}}):Object.keys(e).map(function(t){return{key:t,val:e[t]}})}return b});
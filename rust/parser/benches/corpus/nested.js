@@ -0,0 +1,87 @@
+// Deeply nested expressions, statements and functions, to exercise the
+// parser's stack.
+var config = {
+    a: { b: { c: { d: { e: { f: { g: { h: { i: { j: { k: { l: [1, [2, [3, [4, [5, [6, [7, [8]]]]]]]] } } } } } } } } } } },
+    m: [[[[[[[[[[[[[[[[1, 2], 3], 4], 5], 6], 7], 8], 9], 10], 11], 12], 13], 14], 15], 16], 17]
+};
+
+var total = ((((((((((((((((((((1 + 2) * 3) - 4) / 5) + 6) * 7) - 8) / 9) + 10) * 11) - 12) / 13) + 14) * 15) - 16) / 17) + 18) * 19) - 20) / 21);
+
+var pick = a ? b ? c ? d ? e ? f ? g ? h ? i ? j ? 1 : 2 : 3 : 4 : 5 : 6 : 7 : 8 : 9 : 10 : 11;
+
+var chain = first.second.third(1).fourth[2].fifth(function () { return this; }).sixth.seventh(3)[4].eighth;
+
+function outer(a) {
+    return function (b) {
+        return function (c) {
+            return function (d) {
+                return function (e) {
+                    return function (f) {
+                        return function (g) {
+                            return function (h) {
+                                return (i) => (j) => (k) => (l) => a + b + c + d + e + f + g + h + i + j + k + l;
+                            };
+                        };
+                    };
+                };
+            };
+        };
+    };
+}
+
+function walk(tree, visit) {
+    if (tree) {
+        if (tree.left) {
+            if (tree.left.left) {
+                if (tree.left.left.left) {
+                    if (tree.left.left.left.left) {
+                        for (var i = 0; i < tree.size; i++) {
+                            while (visit(tree, i)) {
+                                do {
+                                    try {
+                                        switch (tree.kind) {
+                                            case 1: {
+                                                label: {
+                                                    if (i % 2) {
+                                                        break label;
+                                                    } else {
+                                                        with (tree) {
+                                                            visit(left, right);
+                                                        }
+                                                    }
+                                                }
+                                                break;
+                                            }
+                                            default:
+                                                throw new Error("unknown kind " + tree.kind);
+                                        }
+                                    } catch (e) {
+                                        visit(e);
+                                    } finally {
+                                        i++;
+                                    }
+                                } while (i < tree.size);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        walk(tree.left, visit);
+        walk(tree.right, visit);
+    }
+}
+
+var calls = f(g(h(i(j(k(l(m(n(o(p(q(r(s(t(u(v(w(x(y(z(0)))))))))))))))))))));
+
+var objects = [
+    { name: "a", children: [{ name: "b", children: [{ name: "c", children: [{ name: "d", children: [] }] }] }] },
+    { name: "e", children: [{ name: "f", children: [{ name: "g", children: [{ name: "h", children: [] }] }] }] },
+    { name: "i", children: [{ name: "j", children: [{ name: "k", children: [{ name: "l", children: [] }] }] }] }
+];
+
+var template = `a${`b${`c${`d${`e${`f${x}`}`}`}`}`}`;
+
+var bits = a & (b | (c ^ (d & (e | (f ^ (g & (h | (i ^ (j << (k >> (l >>> 1)))))))))));
+
+var logic = a && (b || (c && (d || (e && (f || (g && (h || (i && (j || k)))))))));
@@ -0,0 +1,80 @@
+//! Parser throughput over the scripts in `benches/corpus`, each one and all
+//! of them together, and with lazy parsing, each one. Run with
+//! `cargo bench -p parser`.
+//!
+//! The files are read once, before anything is timed, so only parsing is
+//! measured.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::fs;
+use std::path::Path;
+
+/// The `.js` files in the corpus, sorted by name, with their source.
+fn read_corpus() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/corpus");
+    let mut files: Vec<(String, String)> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("reading {}: {}", dir.display(), err))
+        .map(|entry| entry.expect("reading corpus entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let source = fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err));
+            (name, source)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let corpus = read_corpus();
+    let mut allocator = bumpalo::Bump::new();
+
+    // A file that doesn't parse would only time how soon the error is found.
+    for (name, source) in &corpus {
+        assert!(
+            parser::bench_parse_script(&mut allocator, source),
+            "{} doesn't parse",
+            name
+        );
+    }
+
+    let mut group = c.benchmark_group("parse");
+    for (name, source) in &corpus {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(name.as_str(), |b| {
+            b.iter(|| parser::bench_parse_script(&mut allocator, source))
+        });
+    }
+
+    let total: usize = corpus.iter().map(|(_, source)| source.len()).sum();
+    group.throughput(Throughput::Bytes(total as u64));
+    group.bench_function("all", |b| {
+        b.iter(|| {
+            corpus
+                .iter()
+                .all(|(_, source)| parser::bench_parse_script(&mut allocator, source))
+        })
+    });
+    group.finish();
+}
+
+/// `parse_script_lazy`, which skips the bodies of plain functions, and then
+/// parses each one without keeping its nodes.
+fn bench_parse_lazy(c: &mut Criterion) {
+    let corpus = read_corpus();
+    let mut allocator = bumpalo::Bump::new();
+
+    let mut group = c.benchmark_group("parse_lazy");
+    for (name, source) in &corpus {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(name.as_str(), |b| {
+            b.iter(|| parser::bench_parse_script_lazy(&mut allocator, source))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_parse_lazy);
+criterion_main!(benches);
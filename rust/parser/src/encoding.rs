@@ -0,0 +1,63 @@
+//! Decoding source files to text.
+
+use ast::arena;
+use generated_parser::{ParseError, Result};
+use std::char;
+use std::str;
+
+/// Decode the bytes of a source file, allocating the text in `allocator`.
+///
+/// A UTF-16 source must start with a byte order mark telling whether it's
+/// little- or big-endian. Anything else is decoded as UTF-8; a UTF-8 byte
+/// order mark is kept, since the lexer skips it, so offsets in the AST are
+/// offsets in `bytes`. For UTF-16, offsets are in the decoded UTF-8 text.
+///
+/// Returns `ParseError::InvalidEncoding` with the offset of the first byte
+/// that can't be decoded.
+pub fn decode_source<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    bytes: &[u8],
+) -> Result<'alloc, &'alloc str> {
+    let decoded = match bytes {
+        [0xff, 0xfe, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xfe, 0xff, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        _ => {
+            return match str::from_utf8(bytes) {
+                Ok(text) => Ok(arena::alloc_str(allocator, text)),
+                Err(err) => Err(ParseError::InvalidEncoding("UTF-8", err.valid_up_to())),
+            }
+        }
+    };
+    let text = decoded.map_err(|offset| ParseError::InvalidEncoding("UTF-16", offset))?;
+    Ok(arena::alloc_str(allocator, &text))
+}
+
+/// Decode UTF-16 code units, each read from two bytes with `from_bytes`. The
+/// error is the offset of the invalid unit in the source, counting the byte
+/// order mark before `bytes`.
+fn decode_utf16(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+) -> std::result::Result<String, usize> {
+    const BOM_LENGTH: usize = 2;
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        // An odd number of bytes.
+        return Err(BOM_LENGTH + bytes.len() - 1);
+    }
+    let units = pairs.map(|pair| from_bytes([pair[0], pair[1]]));
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut index = 0;
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                text.push(c);
+                index += c.len_utf16();
+            }
+            // An unpaired surrogate.
+            Err(_) => return Err(BOM_LENGTH + 2 * index),
+        }
+    }
+    Ok(text)
+}
@@ -0,0 +1,273 @@
+//! Parsing a script again after an edit, reusing the parts of the previous
+//! AST that the edit can't have changed.
+//!
+//! The top-level statements before the edit are kept, except the last of
+//! them: where a statement ends can depend on the token after it, as in
+//! `a \n (b)`, so that one is parsed again. Parsing starts with the parser in
+//! the state it was in after the kept statements, and goes on through the
+//! edit. After the edit, once the parser is at the start of a top-level
+//! statement, at the same place in the source as one of the old statements,
+//! the rest of the source is the same as before and would be parsed the same
+//! way: the old statements from there on are moved to their new place in the
+//! source and reused instead.
+//!
+//! The bindings at the top level of the old script are kept with it, so that
+//! Early Errors between reused statements and parsed ones, such as
+//! `const a = 1; const a = 2;`, are found as in a full parse. So are the
+//! names of the old script, which the reused statements use, with the names
+//! of the new statements added to them.
+//!
+//! If the edit makes the script strict mode code, or stops it from being,
+//! nothing is reused: the script is parsed again.
+
+use crate::lexer::{hashbang_comment, Lexer};
+use crate::parser::Parser;
+use crate::ParseResult;
+use ast::{arena, source_atom_set::SourceAtomSet, types::Script, visit::Pass, SourceLocation};
+use generated_parser::{
+    AstBuilder, ParserOptions, Result, ScriptBindings, TerminalId, START_STATE_SCRIPT,
+};
+
+/// A change to a source: the text from `start` to `old_end` in the old source
+/// was replaced by the text from `start` to `new_end` in the new source.
+/// Offsets are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl TextEdit {
+    // Where `offset`, in the old source after the edit, is in the new source.
+    fn moved(&self, offset: usize) -> usize {
+        offset - self.old_end + self.new_end
+    }
+}
+
+/// What `reparse_script` needs to know about a script, besides its AST and
+/// comments.
+#[derive(Debug)]
+pub(crate) struct ReparseInfo<'alloc> {
+    bindings: ScriptBindings<'alloc>,
+
+    /// Where the first token of each top-level statement starts, and where
+    /// its last token ends. This isn't the `loc` of the statement, which
+    /// leaves out parentheses and semicolons.
+    extents: Vec<(usize, usize)>,
+}
+
+/// Moves the nodes after an edit to their place in the new source.
+struct MoveLocations {
+    edit: TextEdit,
+}
+
+impl<'alloc> Pass<'alloc> for MoveLocations {
+    fn visit_source_location(&mut self, loc: &mut SourceLocation) {
+        // Some nodes are left with the default location by the parser, which
+        // isn't anywhere in the source.
+        if *loc != SourceLocation::default() {
+            *loc = SourceLocation::new(self.edit.moved(loc.start), self.edit.moved(loc.end));
+        }
+    }
+}
+
+/// Parse `source`, which is the source of `previous` after `edit`, with the
+/// `options` `previous` was parsed with. Top-level statements outside the
+/// edit are reused from `previous` where that can't change the result, which
+/// is the same as `parse_script_with_comments(allocator, source, options)`.
+///
+/// `previous` must come from `parse_script_with_comments` or
+/// `reparse_script`.
+pub fn reparse_script<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    previous: ParseResult<'alloc, Script<'alloc>>,
+    source: &'alloc str,
+    edit: TextEdit,
+    options: ParserOptions,
+) -> Result<'alloc, ParseResult<'alloc, Script<'alloc>>> {
+    debug_assert!(edit.start <= edit.old_end && edit.old_end <= previous.loc.end);
+    debug_assert!(edit.start <= edit.new_end && edit.new_end <= source.len());
+    debug_assert_eq!(previous.loc.end - edit.old_end, source.len() - edit.new_end);
+    parse_script(allocator, source, options, Some((previous, edit)))
+}
+
+/// Parse a script, with its comments, reusing what `reparse_script` would of
+/// `previous`, if there is one.
+pub(crate) fn parse_script<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    options: ParserOptions,
+    previous: Option<(ParseResult<'alloc, Script<'alloc>>, TextEdit)>,
+) -> Result<'alloc, ParseResult<'alloc, Script<'alloc>>> {
+    let mut handler = AstBuilder::with_options(allocator, options);
+    handler.keep_script_bindings();
+
+    // Where parsing starts, and what comes before it: the statements kept,
+    // and the comments among them.
+    let mut start = 0;
+    let mut kept = arena::Vec::new_in(allocator);
+    let mut extents = Vec::new();
+    let mut comments = Vec::new();
+    let mut atoms = SourceAtomSet::new();
+
+    // The statements after the edit, which can be reused if the parser gets
+    // to the start of one of them, and the rest of the old script to go with
+    // them.
+    let mut reusable = arena::Vec::new_in(allocator);
+    let mut reusable_extents = Vec::new();
+    let mut old = None;
+    let mut old_strict = None;
+
+    if let Some((previous, edit)) = previous {
+        let info = previous
+            .reparse
+            .expect("only scripts from parse_script_with_comments can be reparsed");
+        old_strict = Some(previous.ast.strict);
+        let mut statements = previous.ast.unbox().statements;
+        let mut old_extents = info.extents;
+
+        // The statements that end before the edit, with at least one
+        // unchanged character after them for the lexer to look at, are kept,
+        // except the last one, which is parsed again from `start`.
+        let before = old_extents
+            .iter()
+            .take_while(|(_, end)| *end < edit.start)
+            .count();
+        if before > 1 {
+            start = old_extents[before - 1].0;
+        }
+        let after = old_extents
+            .iter()
+            .position(|(first, _)| *first >= edit.old_end)
+            .unwrap_or(old_extents.len());
+
+        reusable = statements.split_off(after);
+        reusable_extents = old_extents.split_off(after);
+        kept = statements;
+        kept.truncate(before.saturating_sub(1));
+        extents = old_extents;
+        extents.truncate(kept.len());
+        comments = previous
+            .comments
+            .iter()
+            .filter(|comment| comment.loc.end <= start)
+            .cloned()
+            .collect();
+        atoms = previous.atoms;
+        handler.add_script_bindings(&info.bindings, 0, start, 0);
+        old = Some((edit, previous.comments, info.bindings));
+    }
+
+    let mut parser =
+        Parser::after_statements(handler, START_STATE_SCRIPT, arena::alloc(allocator, kept));
+    let mut tokens = if start == 0 {
+        Lexer::with_options(allocator, source.chars(), options)
+    } else {
+        Lexer::with_offset_and_options(allocator, source[start..].chars(), start, options)
+    };
+    tokens.collect_comments();
+    tokens.collect_atoms(atoms);
+
+    // Where the statement being parsed starts, and where the last token
+    // written to the parser ends.
+    let mut statement_start = None;
+    let mut prev_end = start;
+    // The index in `reusable` of the next old statement the parser could get
+    // to the start of, and of the one it got to.
+    let mut next = 0;
+    let mut reused = None;
+    loop {
+        let t = tokens.next(&parser)?;
+        if t.terminal_id == TerminalId::End {
+            break;
+        }
+
+        let action = parser.reduce_before(&t)?;
+        if parser.statements_mut().is_some() {
+            // `t` starts a top-level statement, after the one before it.
+            if let Some(first) = statement_start.take() {
+                extents.push((first, prev_end));
+            }
+            if let Some((edit, _, _)) = &old {
+                while next < reusable_extents.len()
+                    && edit.moved(reusable_extents[next].0) < t.loc.start
+                {
+                    next += 1;
+                }
+                if next < reusable_extents.len()
+                    && edit.moved(reusable_extents[next].0) == t.loc.start
+                {
+                    reused = Some(next);
+                    break;
+                }
+            }
+            statement_start = Some(t.loc.start);
+        }
+        parser.shift(&t, action);
+        prev_end = t.loc.end;
+    }
+    comments.extend(tokens.take_comments());
+    let atoms = tokens.take_atoms();
+
+    let result = match (reused, old) {
+        (Some(index), Some((edit, old_comments, old_bindings))) => {
+            let old_start = reusable_extents[index].0;
+            let mut pass = MoveLocations { edit };
+            let statements = parser.statements_mut().unwrap();
+            for mut statement in reusable.split_off(index) {
+                pass.visit_statement(&mut statement);
+                statements.push(statement);
+            }
+            extents.extend(
+                reusable_extents[index..]
+                    .iter()
+                    .map(|&(first, end)| (edit.moved(first), edit.moved(end))),
+            );
+            parser.handler_mut().add_script_bindings(
+                &old_bindings,
+                old_start,
+                usize::MAX,
+                edit.moved(old_start),
+            );
+            comments.extend(
+                old_comments
+                    .iter()
+                    .filter(|comment| comment.loc.start >= old_start)
+                    .map(|comment| {
+                        let mut comment = *comment;
+                        pass.visit_source_location(&mut comment.loc);
+                        comment
+                    }),
+            );
+            parser.close(source.len())?
+        }
+        _ => {
+            if let Some(first) = statement_start {
+                extents.push((first, prev_end));
+            }
+            parser.close(tokens.offset())?
+        }
+    };
+
+    let mut script: arena::Box<'alloc, Script<'alloc>> = result.to_ast()?;
+    // The statements kept and reused were parsed as strict mode code or
+    // not, and the edit changed which the script is.
+    if old_strict.is_some_and(|strict| strict != script.strict) {
+        return parse_script(allocator, source, options, None);
+    }
+    if options.allow_hashbang {
+        script.hashbang = hashbang_comment(source);
+    }
+    let bindings = parser
+        .handler_mut()
+        .take_script_bindings()
+        .unwrap_or_default();
+    Ok(ParseResult {
+        ast: script,
+        comments,
+        atoms,
+        loc: SourceLocation::new(0, source.len()),
+        reparse: Some(ReparseInfo { bindings, extents }),
+    })
+}
@@ -0,0 +1,306 @@
+//! A parser for JSON text, as `JSON.parse` accepts it.
+
+use generated_parser::{ParseError, ParserOptions, Result};
+use std::char;
+use std::collections::HashMap;
+
+/// A JSON value. See `parse_json`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// The members of an object, in the order their names first appear.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// The value of the member of an object, or None if this isn't an
+    /// object or it has no member with the name.
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+/// Parse JSON text: only the JSON grammar, without the comments, trailing
+/// commas, single-quoted strings, `+` signs, hexadecimal numbers and
+/// unquoted names JavaScript has. As with `JSON.parse`, a name given twice
+/// in an object keeps the last value.
+///
+/// Errors are at the offset of the first character that can't be part of
+/// the text, as `ParseError::IllegalCharacter`, or `UnexpectedEnd` if the
+/// text stops early. Unlike `JSON.parse`, which makes strings of UTF-16
+/// code units, an escaped surrogate that isn't part of a pair is an
+/// `InvalidEscapeSequence`.
+pub fn parse_json(source: &str) -> Result<'static, JsonValue> {
+    let mut parser = JsonParser {
+        source,
+        offset: 0,
+        depth: 0,
+        max_depth: ParserOptions::default().max_nesting_depth,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(ParseError::IllegalCharacter(c, parser.offset)),
+    }
+}
+
+struct JsonParser<'a> {
+    source: &'a str,
+    offset: usize,
+    /// How many arrays and objects the parser is in, which is limited like
+    /// the brackets and braces in scripts, so deeply nested text can't
+    /// overflow the stack.
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    /// The next character, which is an error if it isn't `expected`.
+    fn expect(&mut self, expected: char) -> Result<'static, ()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.offset += c.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// The error for the character at the offset.
+    fn unexpected(&self) -> ParseError<'static> {
+        match self.peek() {
+            Some(c) => ParseError::IllegalCharacter(c, self.offset),
+            None => ParseError::UnexpectedEnd,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<'static, JsonValue> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('-') | Some('0'..='9') => self.number(),
+            Some('t') => self.literal("true", JsonValue::Bool(true)),
+            Some('f') => self.literal("false", JsonValue::Bool(false)),
+            Some('n') => self.literal("null", JsonValue::Null),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<'static, JsonValue>,
+    ) -> Result<'static, JsonValue> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::TooDeeplyNested(self.offset));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<'static, JsonValue> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<'static, JsonValue> {
+        self.expect('{')?;
+        let mut members: Vec<(String, JsonValue)> = Vec::new();
+        // The index in `members` of each name, so that finding a name given
+        // before doesn't take longer the more members there are.
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.unexpected());
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            match indices.get(&name) {
+                Some(&index) => members[index].1 = value,
+                None => {
+                    indices.insert(name.clone(), members.len());
+                    members.push((name, value));
+                }
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some('}') => {
+                    self.offset += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<'static, JsonValue> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(JsonValue::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some(']') => {
+                    self.offset += 1;
+                    return Ok(JsonValue::Array(elements));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn digits(&mut self) -> Result<'static, ()> {
+        if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return Err(self.unexpected());
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<'static, JsonValue> {
+        let start = self.offset;
+        if self.peek() == Some('-') {
+            self.offset += 1;
+        }
+        if self.peek() == Some('0') {
+            // No leading zeros, and no `0x`, `0o` or `0b` prefixes, which are
+            // caught after the number.
+            self.offset += 1;
+            if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(self.unexpected());
+            }
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some('.') {
+            self.offset += 1;
+            self.digits()?;
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.offset += 1;
+            if let Some('+') | Some('-') = self.peek() {
+                self.offset += 1;
+            }
+            self.digits()?;
+        }
+        // Rust's parsing of decimal numbers rounds like the spec's.
+        let number = self.source[start..self.offset].parse().unwrap();
+        Ok(JsonValue::Number(number))
+    }
+
+    fn string(&mut self) -> Result<'static, String> {
+        let start = self.offset;
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(ParseError::UnterminatedString(start, self.offset)),
+            };
+            match c {
+                '"' => {
+                    self.offset += 1;
+                    return Ok(text);
+                }
+                '\\' => text.push(self.escape_sequence()?),
+                // Control characters must be escaped, including line
+                // terminators; U+2028 and U+2029 needn't be.
+                '\u{0}'..='\u{1f}' => return Err(ParseError::IllegalCharacter(c, self.offset)),
+                _ => {
+                    text.push(c);
+                    self.offset += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn escape_sequence(&mut self) -> Result<'static, char> {
+        let start = self.offset;
+        self.offset += 1;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(ParseError::UnterminatedString(start, start)),
+        };
+        self.offset += c.len_utf8();
+        Ok(match c {
+            '"' | '\\' | '/' => c,
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let unit = self.hex_4_digits()?;
+                if (0xd800..0xdc00).contains(&unit) && self.source[self.offset..].starts_with("\\u")
+                {
+                    let low_start = self.offset;
+                    self.offset += 2;
+                    let low = self.hex_4_digits()?;
+                    if (0xdc00..0xe000).contains(&low) {
+                        let value = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00);
+                        return Ok(char::from_u32(value).unwrap());
+                    }
+                    self.offset = low_start;
+                }
+                char::from_u32(unit).ok_or(ParseError::InvalidEscapeSequence(start))?
+            }
+            _ => return Err(ParseError::InvalidEscapeSequence(start)),
+        })
+    }
+
+    fn hex_4_digits(&mut self) -> Result<'static, u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => digit,
+                None => return Err(self.unexpected()),
+            };
+            value = value * 16 + digit;
+            self.offset += 1;
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,393 @@
+//! Parsing a script without the bodies of its functions, which are parsed
+//! later, when they're needed.
+//!
+//! The bodies skipped are those of plain functions, declarations and
+//! expressions, which make up most of the code in a typical bundle. Methods,
+//! getters, setters, arrow functions and generators are parsed as usual.
+//!
+//! A skipped body is first only scanned for its tokens, to find the `}`
+//! ending it. Without the parser, the scan can't always tell a `/` that
+//! divides from one that starts a regular expression, and guesses from the
+//! token before it. When the guess is wrong the scan fails or the script
+//! doesn't parse, and the script is parsed again without skipping anything.
+//!
+//! Once the rest of the script is parsed, so that it's known which bodies
+//! are strict mode code and which private names the classes around them
+//! declare, each skipped body is parsed, into an arena of its own that's
+//! emptied after each one, for its errors. So the script is parsed just as
+//! long as with `parse_script`, but only the nodes outside the bodies are
+//! kept.
+
+use crate::lexer::{hashbang_comment, Lexer, ParserState};
+use crate::parse_script;
+use crate::parser::Parser;
+use ast::{
+    arena,
+    types::{
+        ClassDeclaration, ClassElement, ClassElementName, ClassExpression, Expression, Function,
+        FunctionBody, PrivateIdentifier, Script, Statement,
+    },
+    visit::{walk_function, Visitor},
+    SourceLocation,
+};
+use generated_parser::{
+    AstBuilder, ParseError, ParserOptions, Result, TerminalId, Token, START_STATE_SCRIPT,
+};
+
+/// Parse a script, like `parse_script`, except that the bodies of its plain
+/// functions are skipped: each one is left empty, with `is_lazy` set and the
+/// `loc` of the whole body. `force_function` parses it. Until then, the
+/// body's `strict` only says whether the code around the function is strict,
+/// as its own directive prologue is skipped too.
+///
+/// A script that doesn't parse fails as it does with `parse_script`, even if
+/// the error is in a skipped body.
+pub fn parse_script_lazy<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    if let Ok(script) = parse_skipping_bodies(allocator, source) {
+        let mut check = CheckSkippedBodies {
+            source,
+            allocator: bumpalo::Bump::new(),
+            private_names: Vec::new(),
+            ok: true,
+        };
+        check.visit_script(&script);
+        if check.ok {
+            return Ok(script);
+        }
+    }
+    // The scan may have guessed wrong, so the script may parse. If not, the
+    // errors are reported as usual, in source order.
+    parse_script(allocator, source)
+}
+
+fn parse_skipping_bodies<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    let options = ParserOptions::default();
+    let mut tokens = Lexer::with_options(allocator, source.chars(), options);
+    let mut parser = Parser::new(
+        AstBuilder::with_options(allocator, options),
+        START_STATE_SCRIPT,
+    );
+    let mut heads = FunctionHeads::default();
+    loop {
+        let t = tokens.next(&parser)?;
+        if t.terminal_id == TerminalId::End {
+            break;
+        }
+        let starts_body = t.terminal_id == TerminalId::OpenBrace && heads.after_params;
+        parser.write_token(&t)?;
+        heads.note(&t, &parser);
+        if starts_body {
+            let close = skip_function_body(&mut tokens)?;
+            // An empty body is made with the function, after the `}`, so the
+            // parser is given a statement to make the body from, before it.
+            let empty = SourceLocation::new(close.loc.start, close.loc.start);
+            parser.write_token(&Token::basic_token(TerminalId::Semicolon, empty))?;
+            let action = parser.reduce_before(&close)?;
+            match parser.function_body_mut() {
+                Some(body) => {
+                    body.statements.clear();
+                    body.is_lazy = true;
+                }
+                // The `{` wasn't the start of a function body after all.
+                None => return Err(ParseError::SyntaxError(close)),
+            }
+            parser.shift(&close, action);
+            heads.note(&close, &parser);
+        }
+    }
+    let mut script: arena::Box<'alloc, Script<'alloc>> = parser.close(tokens.offset())?.to_ast()?;
+    if options.allow_hashbang {
+        script.hashbang = hashbang_comment(source);
+    }
+    Ok(script)
+}
+
+/// Parse the body of `function`, if `parse_script_lazy` skipped it, making
+/// the function what `parse_script` would have made it. `source` is the
+/// source of the script the function is in. Functions in the body are
+/// parsed too. The names in the body aren't interned, so they have no
+/// `atom`. The uses of private names in the body were checked by
+/// `parse_script_lazy`, with the classes around the function.
+pub fn force_function<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    function: &mut Function<'alloc>,
+) -> Result<'alloc, ()> {
+    if !function.body.is_lazy {
+        return Ok(());
+    }
+    let (body, _) = parse_function(allocator, source, function.loc, function.body.strict)?;
+    function.body = body;
+    Ok(())
+}
+
+/// Parse the function at `loc` in `source`, as a function expression, in
+/// parentheses, at its place in the source, in strict mode code if `strict`.
+/// Returns its body, and the uses of private names in it, which the classes
+/// around it have to declare.
+fn parse_function<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    loc: SourceLocation,
+    strict: bool,
+) -> Result<'alloc, (FunctionBody<'alloc>, Vec<(&'alloc str, usize)>)> {
+    let options = ParserOptions {
+        strict,
+        ..ParserOptions::default()
+    };
+    let mut parser = Parser::new(
+        AstBuilder::with_options(allocator, options),
+        START_STATE_SCRIPT,
+    );
+    let mut tokens = Lexer::with_offset_and_options(
+        allocator,
+        source[loc.start..loc.end].chars(),
+        loc.start,
+        options,
+    );
+    parser.write_token(&Token::basic_token(
+        TerminalId::OpenParenthesis,
+        SourceLocation::new(loc.start, loc.start),
+    ))?;
+    loop {
+        let t = tokens.next(&parser)?;
+        if t.terminal_id == TerminalId::End {
+            break;
+        }
+        parser.write_token(&t)?;
+    }
+    parser.write_token(&Token::basic_token(
+        TerminalId::CloseParenthesis,
+        SourceLocation::new(loc.end, loc.end),
+    ))?;
+    // The classes around the function aren't parsed, so the private names
+    // used in it are checked by the caller.
+    let private_names = parser.handler_mut().take_undeclared_private_names();
+    let script: arena::Box<'alloc, Script<'alloc>> = parser.close(loc.end)?.to_ast()?;
+    let mut statements = script.unbox().statements;
+    match (statements.pop(), statements.is_empty()) {
+        (Some(Statement::ExpressionStatement(expression)), true) => match expression.unbox() {
+            Expression::FunctionExpression(parsed) if parsed.loc == loc => {
+                Ok((parsed.body, private_names))
+            }
+            other => Err(ParseError::AstError(format!(
+                "expected the function at {}..{}, got {:?}",
+                loc.start, loc.end, other
+            ))),
+        },
+        _ => Err(ParseError::AstError(format!(
+            "expected the function at {}..{}, got more than one statement",
+            loc.start, loc.end
+        ))),
+    }
+}
+
+/// Parses the bodies skipped in a script, for their errors. `ok` is false
+/// once one of them doesn't parse, or uses a private name that the classes
+/// around it don't declare.
+struct CheckSkippedBodies<'alloc> {
+    source: &'alloc str,
+
+    /// The arena the bodies are parsed into, emptied before each one.
+    allocator: bumpalo::Bump,
+
+    /// The private names declared by the classes around the code visited.
+    private_names: Vec<&'alloc str>,
+
+    ok: bool,
+}
+
+impl<'alloc> CheckSkippedBodies<'alloc> {
+    /// Visit the elements of a class, whose private names they can use.
+    fn visit_class_elements(&mut self, elements: &[arena::Box<'alloc, ClassElement<'alloc>>]) {
+        let outer = self.private_names.len();
+        for element in elements {
+            if let ClassElement::FieldDefinition {
+                name: ClassElementName::PrivateFieldName(PrivateIdentifier { value, .. }),
+                ..
+            } = &**element
+            {
+                self.private_names.push(value);
+            }
+        }
+        for element in elements {
+            self.visit_class_element(element);
+        }
+        self.private_names.truncate(outer);
+    }
+}
+
+impl<'alloc> Visitor<'alloc> for CheckSkippedBodies<'alloc> {
+    fn visit_function(&mut self, ast: &Function<'alloc>) {
+        if !self.ok {
+            return;
+        }
+        // The functions in the parameters of a skipped body are parsed with
+        // it.
+        if !ast.body.is_lazy {
+            walk_function(self, ast);
+            return;
+        }
+        self.allocator.reset();
+        self.ok = match parse_function(&self.allocator, self.source, ast.loc, ast.body.strict) {
+            Ok((_, used)) => used
+                .iter()
+                .all(|(name, _)| self.private_names.contains(name)),
+            Err(_) => false,
+        };
+    }
+
+    // A class's own private names can't be used in its heritage.
+    fn visit_class_declaration(&mut self, ast: &ClassDeclaration<'alloc>) {
+        if let Some(super_) = &ast.super_ {
+            self.visit_expression(super_);
+        }
+        self.visit_class_elements(&ast.elements);
+    }
+
+    fn visit_class_expression(&mut self, ast: &ClassExpression<'alloc>) {
+        if let Some(super_) = &ast.super_ {
+            self.visit_expression(super_);
+        }
+        self.visit_class_elements(&ast.elements);
+    }
+}
+
+/// Tells the `{` starting the body of a plain function from the tokens
+/// before it: `function`, maybe a name, and the parameters in parentheses.
+#[derive(Default)]
+struct FunctionHeads {
+    /// Whether the last token is `function`, or the name after it, so that
+    /// a `(` would start the parameters.
+    before_params: bool,
+    named: bool,
+
+    /// For each `(` not closed yet, whether it starts the parameters of a
+    /// function.
+    parens: Vec<bool>,
+
+    /// Whether the last token is the `)` after the parameters of a function.
+    after_params: bool,
+}
+
+impl FunctionHeads {
+    /// Note `t`, which `parser` has just shifted.
+    fn note(&mut self, t: &Token, parser: &Parser) {
+        let before_params = std::mem::take(&mut self.before_params);
+        self.after_params = false;
+        match t.terminal_id {
+            // A `function` that's a property name, as in `{ function() {} }`,
+            // can't have a `*` after it.
+            TerminalId::Function => {
+                self.before_params = parser.can_accept_terminal(TerminalId::Star);
+                self.named = false;
+            }
+            TerminalId::OpenParenthesis => self.parens.push(before_params),
+            TerminalId::CloseParenthesis => {
+                self.after_params = self.parens.pop().unwrap_or(false);
+            }
+            // Generators aren't skipped.
+            TerminalId::Star => {}
+            _ if before_params && !self.named => {
+                self.before_params = true;
+                self.named = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Skip the tokens of a function body, after its `{`, and return the `}`
+/// ending it.
+fn skip_function_body<'alloc>(tokens: &mut Lexer<'alloc>) -> Result<'alloc, Token<'alloc>> {
+    let mut state = SkipState {
+        open: Vec::new(),
+        divide: false,
+    };
+    let mut after_dot = false;
+    loop {
+        let t = tokens.next(&state)?;
+        match t.terminal_id {
+            TerminalId::End => return Err(ParseError::UnexpectedEnd),
+            TerminalId::OpenBrace => state.open.push(false),
+            TerminalId::TemplateHead => state.open.push(true),
+            TerminalId::TemplateTail => {
+                state.open.pop();
+            }
+            TerminalId::CloseBrace if state.open.pop().is_none() => return Ok(t),
+            _ => {}
+        }
+        state.divide = after_dot || ends_operand(t.terminal_id);
+        after_dot = matches!(t.terminal_id, TerminalId::Dot | TerminalId::OptionalChain);
+    }
+}
+
+/// What the lexer needs to know about the body being skipped.
+struct SkipState {
+    /// For each `{` and `${` not closed yet, whether it's a `${` in a
+    /// template.
+    open: Vec<bool>,
+
+    /// Whether a `/` would divide, going by the token before it.
+    divide: bool,
+}
+
+impl ParserState for SkipState {
+    fn can_accept_terminal(&self, t: TerminalId) -> bool {
+        match t {
+            TerminalId::Divide => self.divide,
+            TerminalId::TemplateMiddle => self.open.last() == Some(&true),
+            _ => false,
+        }
+    }
+}
+
+/// Whether a token can be the end of an operand, so that a `/` after it
+/// would divide. After `)`, this is wrong for `if (a) /b/.test(c)`, and
+/// after `}`, for `({} / a)`.
+fn ends_operand(t: TerminalId) -> bool {
+    matches!(
+        t,
+        TerminalId::Name
+            | TerminalId::NameWithEscape
+            | TerminalId::Let
+            | TerminalId::Static
+            | TerminalId::Implements
+            | TerminalId::Interface
+            | TerminalId::Package
+            | TerminalId::Private
+            | TerminalId::Protected
+            | TerminalId::Public
+            | TerminalId::As
+            | TerminalId::Assert
+            | TerminalId::Async
+            | TerminalId::From
+            | TerminalId::Get
+            | TerminalId::Meta
+            | TerminalId::Of
+            | TerminalId::Set
+            | TerminalId::Target
+            | TerminalId::Yield
+            | TerminalId::Await
+            | TerminalId::This
+            | TerminalId::Super
+            | TerminalId::RegularExpressionLiteral
+            | TerminalId::NullLiteral
+            | TerminalId::BooleanLiteral
+            | TerminalId::NumericLiteral
+            | TerminalId::BigIntLiteral
+            | TerminalId::StringLiteral
+            | TerminalId::NoSubstitutionTemplate
+            | TerminalId::TemplateTail
+            | TerminalId::CloseParenthesis
+            | TerminalId::CloseBracket
+            | TerminalId::Increment
+            | TerminalId::Decrement
+    )
+}
@@ -1,13 +1,30 @@
 //! JavaScript lexer.
 
-use crate::parser::Parser;
-use ast::SourceLocation;
+use ast::{
+    source_atom_set::{SourceAtomSet, SourceAtomSetIndex},
+    SourceLocation,
+};
 use bumpalo::{collections::String, Bump};
-use generated_parser::{ParseError, Result, TerminalId, Token};
+use generated_parser::{EcmaVersion, ParseError, ParserOptions, Result, TerminalId, Token};
 use std::convert::TryFrom;
 use std::str::Chars;
 use unic_ucd_ident::{is_id_continue, is_id_start};
 
+/// The parser state the lexer depends on. Some tokens, like `/` and the start
+/// of a RegularExpressionLiteral, can only be told apart by knowing what the
+/// parser expects next.
+pub trait ParserState {
+    fn can_accept_terminal(&self, t: TerminalId) -> bool;
+
+    /// Whether `await` is reserved where the next token goes, in an async
+    /// function, a class static block or a module, so that it can start an
+    /// AwaitExpression. Elsewhere it's an identifier. States that can't tell
+    /// say it is reserved, leaving it to `Lexer::await_can_be_keyword`.
+    fn await_is_reserved(&self) -> bool {
+        true
+    }
+}
+
 pub struct Lexer<'alloc> {
     allocator: &'alloc Bump,
 
@@ -20,6 +37,33 @@ pub struct Lexer<'alloc> {
     /// True if the current position is before the first
     /// token of a line (or on a line with no tokens).
     is_on_new_line: bool,
+
+    /// Where a *HashbangComment* can start: the start of the source, after
+    /// the byte order mark if any. None if the lexer starts in the middle of
+    /// the source.
+    hashbang_start: Option<usize>,
+
+    /// The raw text of the template literal part just scanned, for the
+    /// `raw` field of its token. See `template_part`.
+    template_raw: Option<&'alloc str>,
+
+    /// Where the literal just scanned uses legacy octal syntax, for the
+    /// `legacy_octal` field of its token.
+    legacy_octal: Option<usize>,
+
+    /// The comments skipped so far, if they're being collected. See
+    /// `collect_comments`.
+    comments: Option<Vec<Comment>>,
+
+    /// The names scanned so far, if they're being interned. See
+    /// `collect_atoms`.
+    atoms: Option<SourceAtomSet<'alloc>>,
+
+    /// The handle of the name just scanned, for the `atom` field of its
+    /// token.
+    atom: Option<SourceAtomSetIndex>,
+
+    options: ParserOptions,
 }
 
 impl<'alloc> Lexer<'alloc> {
@@ -27,6 +71,17 @@ impl<'alloc> Lexer<'alloc> {
         Self::with_offset(allocator, chars, 0)
     }
 
+    pub fn with_options(
+        allocator: &'alloc Bump,
+        chars: Chars<'alloc>,
+        options: ParserOptions,
+    ) -> Lexer<'alloc> {
+        Lexer {
+            options,
+            ..Self::new(allocator, chars)
+        }
+    }
+
     /// Create a lexer for a part of a JS script or module. `offset` is the
     /// total length of all previous parts, in bytes; source locations for
     /// tokens created by the new lexer start counting from this number.
@@ -36,11 +91,81 @@ impl<'alloc> Lexer<'alloc> {
         offset: usize,
     ) -> Lexer<'alloc> {
         let source_length = offset + chars.as_str().len();
-        Lexer {
+        let mut lexer = Lexer {
             allocator,
             source_length,
             chars,
             is_on_new_line: true,
+            hashbang_start: None,
+            template_raw: None,
+            legacy_octal: None,
+            comments: None,
+            atoms: None,
+            atom: None,
+            options: ParserOptions::default(),
+        };
+        if offset == 0 {
+            // A byte order mark at the start of the source, as some editors
+            // save, is not part of the source text. It comes before the
+            // hashbang. (Elsewhere it's whitespace; see `ZWNBSP`.)
+            if lexer.peek() == Some(ZWNBSP) {
+                lexer.chars.next();
+            }
+            lexer.hashbang_start = Some(lexer.offset());
+        }
+        lexer
+    }
+
+    /// Create a lexer for a part of a JS script or module, as `with_offset`
+    /// does, with `options`.
+    pub fn with_offset_and_options(
+        allocator: &'alloc Bump,
+        chars: Chars<'alloc>,
+        offset: usize,
+        options: ParserOptions,
+    ) -> Lexer<'alloc> {
+        Lexer {
+            options,
+            ..Self::with_offset(allocator, chars, offset)
+        }
+    }
+
+    /// Keep the comments skipped from now on, for `take_comments`.
+    pub fn collect_comments(&mut self) {
+        self.comments.get_or_insert_with(Vec::new);
+    }
+
+    /// The comments skipped so far, in source order, if `collect_comments`
+    /// was called.
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        self.comments.take().unwrap_or_default()
+    }
+
+    /// Intern the names scanned from now on in `atoms`, for `take_atoms`.
+    /// Names already in it keep their handles. Until this is called, names
+    /// aren't interned, and tokens have no `atom`.
+    pub fn collect_atoms(&mut self, atoms: SourceAtomSet<'alloc>) {
+        self.atoms = Some(atoms);
+    }
+
+    /// The names scanned since `collect_atoms` was called, including those
+    /// in the set given to it, or an empty set if it wasn't. Each of those
+    /// tokens' value is the string stored in the set for its name, and its
+    /// `atom` the name's handle.
+    pub fn take_atoms(&mut self) -> SourceAtomSet<'alloc> {
+        self.atoms.take().unwrap_or_default()
+    }
+
+    /// The string to use for `name` in its token: the one stored for it in
+    /// the set, if names are interned. Its handle is kept for the token.
+    fn intern(&mut self, name: &'alloc str) -> &'alloc str {
+        match &mut self.atoms {
+            Some(atoms) => {
+                let (atom, name) = atoms.insert(name);
+                self.atom = Some(atom);
+                name
+            }
+            None => name,
         }
     }
 
@@ -56,8 +181,15 @@ impl<'alloc> Lexer<'alloc> {
         self.chars.as_str().chars().next()
     }
 
-    pub fn next<'parser>(&mut self, parser: &Parser<'parser>) -> Result<'alloc, Token<'alloc>> {
-        let (loc, value, terminal_id) = self.advance_impl(parser)?;
+    pub fn next(&mut self, parser: &impl ParserState) -> Result<'alloc, Token<'alloc>> {
+        let (loc, value, mut terminal_id) = self.advance_impl(parser)?;
+        match terminal_id {
+            TerminalId::Async if !self.async_is_keyword(parser) => terminal_id = TerminalId::Name,
+            TerminalId::Await if !parser.await_is_reserved() || !self.await_can_be_keyword() => {
+                terminal_id = TerminalId::Name
+            }
+            _ => {}
+        }
         let is_on_new_line = self.is_on_new_line;
         self.is_on_new_line = false;
         Ok(Token {
@@ -65,12 +197,94 @@ impl<'alloc> Lexer<'alloc> {
             loc,
             is_on_new_line,
             value,
+            raw: self.template_raw.take(),
+            legacy_octal: self.legacy_octal.take(),
+            atom: self.atom.take(),
         })
     }
 
+    /// Whether the `async` just scanned is the keyword, starting an async
+    /// function, method or arrow function, rather than a name.
+    ///
+    /// The keyword has another token after it on the same line. Where a
+    /// statement can start, that token has to be `function`: the grammar
+    /// can only look one token ahead, so it doesn't let an expression
+    /// statement start with `async`, leaving `async function` to the
+    /// declaration. So there, `async(x)` and `async () => {}` are parsed
+    /// with `async` as a name, which works for them, but `async x => {}`
+    /// can't be parsed.
+    fn async_is_keyword(&self, parser: &impl ParserState) -> bool {
+        match self.source_after_spaces() {
+            (_, true) => false,
+            // Where both of these are allowed, a statement starts. (A class
+            // element can be `;`, and where an expression starts, `{` and
+            // keywords such as `var` can be property names.)
+            (rest, false)
+                if parser.can_accept_terminal(TerminalId::Semicolon)
+                    && parser.can_accept_terminal(TerminalId::LogicalNot) =>
+            {
+                match rest.strip_prefix("function") {
+                    Some(after) => !after.starts_with(is_identifier_part),
+                    None => false,
+                }
+            }
+            (_, false) => true,
+        }
+    }
+
+    /// Whether the `await` just scanned, where it's reserved, can be the
+    /// keyword, starting an AwaitExpression, because the source after it
+    /// can start its operand.
+    ///
+    /// The grammar doesn't know where `await` is reserved, so where the
+    /// parser says it isn't, it's always a name, as in `await(x)` or
+    /// `await - 1` in a script. Where it's reserved but can't be the
+    /// keyword, as in `await = 1`, `await;` or `{ await }`, it's a name too,
+    /// and AstBuilder reports it.
+    fn await_can_be_keyword(&self) -> bool {
+        let (rest, _) = self.source_after_spaces();
+        let mut chars = rest.chars();
+        match chars.next() {
+            None => false,
+            Some(
+                ';' | ')' | ']' | '}' | ',' | ':' | '=' | '?' | '*' | '%' | '&' | '|' | '^' | '<'
+                | '>',
+            ) => false,
+            Some('!') => chars.next() != Some('='),
+            Some('.') => matches!(chars.next(), Some('0'..='9')),
+            Some(_) => true,
+        }
+    }
+
+    /// The source after the whitespace, line terminators and comments that
+    /// come next, without scanning it, and whether there's a line
+    /// terminator among them.
+    fn source_after_spaces(&self) -> (&'alloc str, bool) {
+        let mut rest = self.chars.as_str();
+        let mut new_line = false;
+        loop {
+            let trimmed = rest.trim_start_matches(is_whitespace);
+            let after_lines = trimmed.trim_start_matches(is_line_terminator);
+            new_line |= after_lines.len() < trimmed.len();
+            rest = after_lines;
+            if after_lines.len() < trimmed.len() {
+                continue;
+            }
+            if let Some(comment) = rest.strip_prefix("//") {
+                rest = comment.trim_start_matches(|c| !is_line_terminator(c));
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/").unwrap_or(comment.len());
+                new_line |= comment[..end].contains(is_line_terminator);
+                rest = comment.get(end + 2..).unwrap_or("");
+            } else {
+                return (rest, new_line);
+            }
+        }
+    }
+
     fn unexpected_err(&mut self) -> ParseError<'alloc> {
         if let Some(ch) = self.peek() {
-            ParseError::IllegalCharacter(ch)
+            ParseError::IllegalCharacter(ch, self.offset())
         } else {
             ParseError::UnexpectedEnd
         }
@@ -110,6 +324,14 @@ const SP: char = '\u{20}';
 /// U+00A0 NON-BREAKING SPACE, abbreviated <NBSP>.
 const NBSP: char = '\u{a0}';
 
+fn is_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        TAB | VT | FF | SP | NBSP | ZWNBSP | '\u{1680}' | '\u{2000}'
+            ..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+    )
+}
+
 // ----------------------------------------------------------------------------
 // 11.3 Line Terminators
 
@@ -125,6 +347,10 @@ const LS: char = '\u{2028}';
 /// U+2029 PARAGRAPH SEPARATOR, abbreviated <PS>.
 const PS: char = '\u{2029}';
 
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, LF | CR | LS | PS)
+}
+
 // ----------------------------------------------------------------------------
 // 11.4 Comments
 //
@@ -132,7 +358,50 @@ const PS: char = '\u{2029}';
 //     MultiLineComment
 //     SingleLineComment
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentKind {
+    /// A *SingleLineComment*, `// ...`, or one of the HTML-like comments of
+    /// B.1.3, `<!-- ...` and `--> ...`.
+    Line,
+
+    /// A *MultiLineComment*, `/* ... */`.
+    Block,
+}
+
+/// A comment, as collected by `Lexer::collect_comments`. The hashbang isn't
+/// one; see `hashbang_comment`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+
+    /// The comment with its delimiters, but not the line terminator after a
+    /// line comment.
+    pub loc: SourceLocation,
+
+    /// True if no token comes before the comment on its line.
+    pub own_line: bool,
+}
+
+impl Comment {
+    /// The text of the comment in `source`, the source it was found in.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.loc.start..self.loc.end]
+    }
+}
+
 impl<'alloc> Lexer<'alloc> {
+    /// Note a comment from `start` to `end`, if comments are being
+    /// collected. `own_line` is `is_on_new_line` before the comment.
+    fn add_comment(&mut self, kind: CommentKind, start: usize, end: usize, own_line: bool) {
+        if let Some(comments) = &mut self.comments {
+            comments.push(Comment {
+                kind,
+                loc: SourceLocation::new(start, end),
+                own_line,
+            });
+        }
+    }
+
     /// Skip a *MultiLineComment*.
     ///
     /// ```text
@@ -160,11 +429,15 @@ impl<'alloc> Lexer<'alloc> {
     /// use `is_on_new_line` for that.)
     ///
     fn skip_multi_line_comment(&mut self, builder: &mut AutoCow<'alloc>) -> Result<'alloc, ()> {
+        // The opening `/*` has already been consumed.
+        let start = self.offset() - 2;
+        let own_line = self.is_on_new_line;
         while let Some(ch) = self.chars.next() {
             match ch {
                 '*' if self.peek() == Some('/') => {
                     self.chars.next();
                     *builder = AutoCow::new(&self);
+                    self.add_comment(CommentKind::Block, start, self.offset(), own_line);
                     return Ok(());
                 }
                 CR | LF | PS | LS => {
@@ -173,11 +446,12 @@ impl<'alloc> Lexer<'alloc> {
                 _ => {}
             }
         }
-        Err(ParseError::UnterminatedMultiLineComment)
+        Err(ParseError::UnterminatedMultiLineComment(start))
     }
 
     /// Skip a *SingleLineComment* and the following *LineTerminatorSequence*,
-    /// if any.
+    /// if any, and return the offset of the end of the comment, before the
+    /// line terminator.
     ///
     /// ```text
     /// SingleLineComment ::
@@ -189,18 +463,45 @@ impl<'alloc> Lexer<'alloc> {
     /// SingleLineCommentChar ::
     ///     SourceCharacter but not LineTerminator
     /// ```
-    fn skip_single_line_comment(&mut self, builder: &mut AutoCow<'alloc>) {
+    fn skip_single_line_comment(&mut self, builder: &mut AutoCow<'alloc>) -> usize {
+        let mut end = self.offset();
         while let Some(ch) = self.chars.next() {
             match ch {
                 CR | LF | LS | PS => break,
-                _ => continue,
+                _ => end = self.offset(),
             }
         }
         *builder = AutoCow::new(&self);
         self.is_on_new_line = true;
+        end
+    }
+
+    /// Skip a line comment that started at `start`, as
+    /// `skip_single_line_comment` does, and note it.
+    fn skip_line_comment(&mut self, builder: &mut AutoCow<'alloc>, start: usize) {
+        let own_line = self.is_on_new_line;
+        let end = self.skip_single_line_comment(builder);
+        self.add_comment(CommentKind::Line, start, end, own_line);
     }
 }
 
+/// The text of the *HashbangComment* at the start of `source`, after the
+/// byte order mark if any, without the `#!`, if there is one. The lexer skips
+/// it like a comment when `ParserOptions::allow_hashbang` is set.
+///
+/// ```text
+/// HashbangComment ::
+///     `#!` SingleLineCommentChars?
+/// ```
+pub fn hashbang_comment(source: &str) -> Option<&str> {
+    let source = source.strip_prefix(ZWNBSP).unwrap_or(source);
+    let text = source.strip_prefix("#!")?;
+    let end = text
+        .find(|c| matches!(c, CR | LF | LS | PS))
+        .unwrap_or(text.len());
+    Some(&text[..end])
+}
+
 // ----------------------------------------------------------------------------
 // 11.6 Names and Keywords
 
@@ -273,7 +574,7 @@ impl<'alloc> Lexer<'alloc> {
 
                     let value = self.unicode_escape_sequence_after_backslash()?;
                     if !is_identifier_part(value) {
-                        return Err(ParseError::InvalidEscapeSequence);
+                        return Err(ParseError::InvalidEscapeSequence(self.offset()));
                     }
 
                     builder.push_different(value);
@@ -305,7 +606,7 @@ impl<'alloc> Lexer<'alloc> {
 
                         let value = self.unicode_escape_sequence_after_backslash()?;
                         if !is_identifier_start(value) {
-                            return Err(ParseError::IllegalCharacter(value));
+                            return Err(ParseError::IllegalCharacter(value, self.offset()));
                         }
                         builder.push_different(value);
                     }
@@ -315,7 +616,10 @@ impl<'alloc> Lexer<'alloc> {
                     }
 
                     other => {
-                        return Err(ParseError::IllegalCharacter(other));
+                        return Err(ParseError::IllegalCharacter(
+                            other,
+                            self.offset() - other.len_utf8(),
+                        ));
                     }
                 }
                 self.identifier_name_tail(builder)
@@ -368,18 +672,9 @@ impl<'alloc> Lexer<'alloc> {
         } else {
             match &text as &str {
                 "as" => TerminalId::As,
-                "async" => {
-                    //TerminalId::Async
-                    return Err(ParseError::NotImplemented(
-                        "async cannot be handled in parser due to multiple lookahead",
-                    ));
-                }
-                "await" => {
-                    //TerminalId::Await
-                    return Err(ParseError::NotImplemented(
-                        "await cannot be handled in parser",
-                    ));
-                }
+                "assert" => TerminalId::Assert,
+                "async" => TerminalId::Async,
+                "await" => TerminalId::Await,
                 "break" => TerminalId::Break,
                 "case" => TerminalId::Case,
                 "catch" => TerminalId::Catch,
@@ -404,12 +699,8 @@ impl<'alloc> Lexer<'alloc> {
                 "in" => TerminalId::In,
                 "instanceof" => TerminalId::Instanceof,
                 "interface" => TerminalId::Interface,
-                "let" => {
-                    //TerminalId::Let,
-                    return Err(ParseError::NotImplemented(
-                        "let cannot be handled in parser due to multiple lookahead",
-                    ));
-                }
+                "let" if self.options.strict || self.let_starts_declaration() => TerminalId::Let,
+                "meta" => TerminalId::Meta,
                 "new" => TerminalId::New,
                 "of" => TerminalId::Of,
                 "package" => TerminalId::Package,
@@ -430,21 +721,91 @@ impl<'alloc> Lexer<'alloc> {
                 "void" => TerminalId::Void,
                 "while" => TerminalId::While,
                 "with" => TerminalId::With,
-                "yield" => {
-                    //TerminalId::Yield
-                    return Err(ParseError::NotImplemented(
-                        "yield cannot be handled in parser",
-                    ));
-                }
+                "yield" => TerminalId::Yield,
                 "null" => TerminalId::NullLiteral,
                 "true" | "false" => TerminalId::BooleanLiteral,
                 _ => TerminalId::Name,
             }
         };
 
+        let text = self.intern(text);
         Ok((SourceLocation::new(start, self.offset()), Some(text), id))
     }
 
+    /// True if the `let` just scanned can start a LexicalDeclaration, because
+    /// the next token, even on another line, is `[`, `{` or an identifier.
+    ///
+    /// The simplified grammar has `[lookahead != let]` before every
+    /// ExpressionStatement, so a `let` that isn't a declaration, as in
+    /// `let = 1;` or `let.length;` in sloppy mode code, has to be a
+    /// `TerminalId::Name`. In strict mode code, it's always the keyword.
+    fn let_starts_declaration(&self) -> bool {
+        let mut rest = self.chars.as_str();
+        loop {
+            rest = rest.trim_start_matches(|c| is_whitespace(c) || is_line_terminator(c));
+            if let Some(comment) = rest.strip_prefix("//") {
+                rest = comment
+                    .find(is_line_terminator)
+                    .map_or("", |end| &comment[end..]);
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => rest = &comment[end + 2..],
+                    None => return false,
+                }
+            } else {
+                break;
+            }
+        }
+        match rest.chars().next() {
+            Some('[') | Some('{') | Some('\\') => true,
+            Some(c) if is_identifier_start(c) => {
+                let end = rest.find(|c| !is_identifier_part(c)).unwrap_or(rest.len());
+                // A reserved word other than `let`, `yield` and `await` can't
+                // be a binding, as in `let in a`.
+                !matches!(
+                    &rest[..end],
+                    "break"
+                        | "case"
+                        | "catch"
+                        | "class"
+                        | "const"
+                        | "continue"
+                        | "debugger"
+                        | "default"
+                        | "delete"
+                        | "do"
+                        | "else"
+                        | "enum"
+                        | "export"
+                        | "extends"
+                        | "false"
+                        | "finally"
+                        | "for"
+                        | "function"
+                        | "if"
+                        | "import"
+                        | "in"
+                        | "instanceof"
+                        | "new"
+                        | "null"
+                        | "return"
+                        | "super"
+                        | "switch"
+                        | "this"
+                        | "throw"
+                        | "true"
+                        | "try"
+                        | "typeof"
+                        | "var"
+                        | "void"
+                        | "while"
+                        | "with"
+                )
+            }
+            _ => false,
+        }
+    }
+
     /// ```text
     /// PrivateIdentifier::
     ///     `#` IdentifierName
@@ -455,6 +816,7 @@ impl<'alloc> Lexer<'alloc> {
         builder: AutoCow<'alloc>,
     ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
         let name = self.identifier_name(builder)?;
+        let name = self.intern(name);
         Ok((
             SourceLocation::new(start, self.offset()),
             Some(name),
@@ -462,6 +824,14 @@ impl<'alloc> Lexer<'alloc> {
         ))
     }
 
+    /// Scan a UnicodeEscapeSequence in an *IdentifierName*, after the `\`,
+    /// and return the character it stands for, which the caller checks is an
+    /// *IdentifierStart* or *IdentifierPart*.
+    ///
+    /// Unlike in a string, an escaped surrogate is always an error, even if
+    /// it's half of a pair: `\uD835\uDC9C` isn't an identifier, though
+    /// `\u{1D49C}` is.
+    ///
     /// ```text
     /// UnicodeEscapeSequence::
     ///     `u` Hex4Digits
@@ -471,13 +841,21 @@ impl<'alloc> Lexer<'alloc> {
         match self.chars.next() {
             Some('u') => {}
             _ => {
-                return Err(ParseError::InvalidEscapeSequence);
+                return Err(ParseError::InvalidEscapeSequence(self.offset()));
             }
         }
-        self.unicode_escape_sequence_after_backslash_and_u()
+        let value = self.unicode_escape_value_after_backslash_and_u()?;
+        char::try_from(value).map_err(|_| ParseError::InvalidEscapeSequence(self.offset()))
     }
 
     fn unicode_escape_sequence_after_backslash_and_u(&mut self) -> Result<'alloc, char> {
+        let value = self.unicode_escape_value_after_backslash_and_u()?;
+        self.code_point_to_char(value)
+    }
+
+    /// The number a UnicodeEscapeSequence after the `\u` stands for, which
+    /// can be a surrogate.
+    fn unicode_escape_value_after_backslash_and_u(&mut self) -> Result<'alloc, u32> {
         let value = match self.peek() {
             Some('{') => {
                 self.chars.next();
@@ -486,7 +864,7 @@ impl<'alloc> Lexer<'alloc> {
                 match self.chars.next() {
                     Some('}') => {}
                     _ => {
-                        return Err(ParseError::InvalidEscapeSequence);
+                        return Err(ParseError::InvalidEscapeSequence(self.offset()));
                     }
                 }
                 value
@@ -532,11 +910,18 @@ impl<'alloc> Lexer<'alloc> {
         Ok(true)
     }
 
+    fn numeric_literal_separator(&mut self) -> Result<'alloc, ()> {
+        self.options
+            .check_target("numeric separators", EcmaVersion::Es2021, self.offset())?;
+        self.chars.next();
+        Ok(())
+    }
+
     fn decimal_digits_after_first_digit(&mut self) -> Result<'alloc, ()> {
         while let Some(next) = self.peek() {
             match next {
                 '_' => {
-                    self.chars.next();
+                    self.numeric_literal_separator()?;
 
                     if let Some('0'..='9') = self.peek() {
                         self.chars.next();
@@ -588,21 +973,24 @@ impl<'alloc> Lexer<'alloc> {
     /// ```
     fn hex_digit(&mut self) -> Result<'alloc, u32> {
         match self.chars.next() {
-            None => Err(ParseError::InvalidEscapeSequence),
+            None => Err(ParseError::InvalidEscapeSequence(self.offset())),
             Some(c @ '0'..='9') => Ok(c as u32 - '0' as u32),
             Some(c @ 'a'..='f') => Ok(10 + (c as u32 - 'a' as u32)),
             Some(c @ 'A'..='F') => Ok(10 + (c as u32 - 'A' as u32)),
-            Some(other) => Err(ParseError::IllegalCharacter(other)),
+            Some(other) => Err(ParseError::IllegalCharacter(
+                other,
+                self.offset() - other.len_utf8(),
+            )),
         }
     }
 
-    fn code_point_to_char(value: u32) -> Result<'alloc, char> {
+    fn code_point_to_char(&self, value: u32) -> Result<'alloc, char> {
         if 0xd800 <= value && value <= 0xdfff {
             Err(ParseError::NotImplemented(
                 "unicode escape sequences (surrogates)",
             ))
         } else {
-            char::try_from(value).map_err(|_| ParseError::InvalidEscapeSequence)
+            char::try_from(value).map_err(|_| ParseError::InvalidEscapeSequence(self.offset()))
         }
     }
 
@@ -610,12 +998,12 @@ impl<'alloc> Lexer<'alloc> {
     /// Hex4Digits ::
     ///     HexDigit HexDigit HexDigit HexDigit
     /// ```
-    fn hex_4_digits(&mut self) -> Result<'alloc, char> {
+    fn hex_4_digits(&mut self) -> Result<'alloc, u32> {
         let mut value = 0;
         for _ in 0..4 {
             value = (value << 4) | self.hex_digit()?;
         }
-        Self::code_point_to_char(value)
+        Ok(value)
     }
 
     /// ```text
@@ -626,13 +1014,13 @@ impl<'alloc> Lexer<'alloc> {
     ///    HexDigit
     ///    HexDigits HexDigit
     /// ```
-    fn code_point(&mut self) -> Result<'alloc, char> {
+    fn code_point(&mut self) -> Result<'alloc, u32> {
         let mut value = self.hex_digit()?;
 
         loop {
             let next = match self.peek() {
                 None => {
-                    return Err(ParseError::InvalidEscapeSequence);
+                    return Err(ParseError::InvalidEscapeSequence(self.offset()));
                 }
                 Some(c @ '0'..='9') => c as u32 - '0' as u32,
                 Some(c @ 'a'..='f') => 10 + (c as u32 - 'a' as u32),
@@ -642,11 +1030,11 @@ impl<'alloc> Lexer<'alloc> {
             self.chars.next();
             value = (value << 4) | next;
             if value > 0x10FFFF {
-                return Err(ParseError::InvalidEscapeSequence);
+                return Err(ParseError::InvalidEscapeSequence(self.offset()));
             }
         }
 
-        Self::code_point_to_char(value)
+        Ok(value)
     }
 
     /// Scan a NumericLiteral (defined in 11.8.3, extended by B.1.1) after
@@ -695,7 +1083,7 @@ impl<'alloc> Lexer<'alloc> {
                 while let Some(next) = self.peek() {
                     match next {
                         '_' => {
-                            self.chars.next();
+                            self.numeric_literal_separator()?;
 
                             if let Some('0'..='1') = self.peek() {
                                 self.chars.next();
@@ -740,7 +1128,7 @@ impl<'alloc> Lexer<'alloc> {
                 while let Some(next) = self.peek() {
                     match next {
                         '_' => {
-                            self.chars.next();
+                            self.numeric_literal_separator()?;
 
                             if let Some('0'..='7') = self.peek() {
                                 self.chars.next();
@@ -784,7 +1172,7 @@ impl<'alloc> Lexer<'alloc> {
                 while let Some(next) = self.peek() {
                     match next {
                         '_' => {
-                            self.chars.next();
+                            self.numeric_literal_separator()?;
 
                             if let Some('0'..='9') | Some('a'..='f') | Some('A'..='F') = self.peek()
                             {
@@ -845,15 +1233,23 @@ impl<'alloc> Lexer<'alloc> {
                 //     `8` `9`
                 //
 
-                // TODO: implement `strict_mode` check
-                // let strict_mode = true;
-                // if !strict_mode {
-                //     // TODO: Distinguish between Octal and NonOctalDecimal.
-                //     // TODO: Support NonOctalDecimal followed by a decimal
-                //     //       point and/or ExponentPart.
-                //     self.decimal_digits()?;
-                // }
-                return Err(ParseError::NotImplemented("LegacyOctalIntegerLiteral"));
+                // The offset of the leading `0`. In strict mode code, the
+                // error is reported once the whole literal is scanned.
+                let start = self.offset() - 1;
+                self.legacy_octal = Some(start);
+                let mut octal = true;
+                while let Some(next @ '0'..='9') = self.peek() {
+                    octal &= next < '8';
+                    self.chars.next();
+                }
+                if !octal {
+                    // A NonOctalDecimalIntegerLiteral can't be a BigInt
+                    // either, as in `08n`.
+                    if let Some('n') = self.peek() {
+                        return Err(ParseError::IllegalCharacter('n', self.offset()));
+                    }
+                    return self.decimal_literal_after_digits();
+                }
             }
 
             _ => {}
@@ -915,7 +1311,7 @@ impl<'alloc> Lexer<'alloc> {
         // DecimalDigit. (11.8.3)
         if let Some(ch) = self.peek() {
             if is_identifier_start(ch) || ch.is_digit(10) {
-                return Err(ParseError::IllegalCharacter(ch));
+                return Err(ParseError::IllegalCharacter(ch, self.offset()));
             }
         }
 
@@ -947,7 +1343,8 @@ impl<'alloc> Lexer<'alloc> {
     ///     `'` `"` `\` `b` `f` `n` `r` `t` `v`
     ///
     /// LegacyOctalEscapeSequence ::
-    ///     OctalDigit [lookahead ∉ OctalDigit]
+    ///     `0` [lookahead ∈ { `8`, `9` }]
+    ///     NonZeroOctalDigit [lookahead ∉ OctalDigit]
     ///     ZeroToThree OctalDigit [lookahead ∉ OctalDigit]
     ///     FourToSeven OctalDigit
     ///     ZeroToThree OctalDigit OctalDigit
@@ -958,10 +1355,35 @@ impl<'alloc> Lexer<'alloc> {
     /// FourToSeven :: one of
     ///     `4` `5` `6` `7`
     /// ```
+    // Called after the first digit of a LegacyOctalEscapeSequence, `first`.
+    // Returns the character it stands for.
+    fn legacy_octal_escape_sequence(&mut self, first: char) -> Result<'alloc, char> {
+        // The offset of the `\`.
+        let start = self.offset() - 2;
+        if self.options.strict {
+            return Err(ParseError::OctalEscapeInStrictMode(start));
+        }
+        self.legacy_octal.get_or_insert(start);
+
+        let mut value = first.to_digit(8).unwrap();
+        let max_digits = if first <= '3' { 3 } else { 2 };
+        for _ in 1..max_digits {
+            match self.peek().and_then(|c| c.to_digit(8)) {
+                Some(digit) => {
+                    self.chars.next();
+                    value = value * 8 + digit;
+                }
+                None => break,
+            }
+        }
+        Ok(char::from_u32(value).unwrap())
+    }
+
     fn escape_sequence(&mut self, text: &mut String<'alloc>) -> Result<'alloc, ()> {
         match self.chars.next() {
             None => {
-                return Err(ParseError::UnterminatedString);
+                let offset = self.offset();
+                return Err(ParseError::UnterminatedString(offset, offset));
             }
             Some(c) => match c {
                 LF | LS | PS => {
@@ -1015,7 +1437,7 @@ impl<'alloc> Lexer<'alloc> {
                     value = (value << 4) | self.hex_digit()?;
                     match char::try_from(value) {
                         Err(_) => {
-                            return Err(ParseError::InvalidEscapeSequence);
+                            return Err(ParseError::InvalidEscapeSequence(self.offset()));
                         }
                         Ok(c) => {
                             text.push(c);
@@ -1035,28 +1457,18 @@ impl<'alloc> Lexer<'alloc> {
                     //     EscapeSequence ::
                     //         `0` [lookahead <! DecimalDigit]
                     //
-                    // In non-strict StringLiterals, `\0` begins a
-                    // LegacyOctalEscapeSequence which may contain more digits.
-                    match self.peek() {
-                        Some('0'..='7') => {
-                            return Err(ParseError::NotImplemented(
-                                "legacy octal escape sequence in string",
-                            ));
-                        }
-                        Some('8'..='9') => {
-                            return Err(ParseError::NotImplemented(
-                                "digit immediately following \\0 escape sequence",
-                            ));
-                        }
-                        _ => {}
+                    // In non-strict StringLiterals, `\0` followed by a digit is
+                    // a LegacyOctalEscapeSequence, which may contain more
+                    // digits: `"\01"` is `"\x01"`, and `"\08"` is `"\x008"`.
+                    if let Some('0'..='9') = self.peek() {
+                        text.push(self.legacy_octal_escape_sequence(c)?);
+                    } else {
+                        text.push('\0');
                     }
-                    text.push('\0');
                 }
 
                 '1'..='7' => {
-                    return Err(ParseError::NotImplemented(
-                        "legacy octal escape sequence in string",
-                    ));
+                    text.push(self.legacy_octal_escape_sequence(c)?);
                 }
 
                 other => {
@@ -1106,8 +1518,11 @@ impl<'alloc> Lexer<'alloc> {
         let mut builder = AutoCow::new(&self);
         loop {
             match self.chars.next() {
-                None | Some('\r') | Some('\n') => {
-                    return Err(ParseError::UnterminatedString);
+                None => {
+                    return Err(ParseError::UnterminatedString(offset, self.offset()));
+                }
+                Some('\r') | Some('\n') => {
+                    return Err(ParseError::UnterminatedString(offset, self.offset() - 1));
                 }
 
                 Some(c @ '"') | Some(c @ '\'') => {
@@ -1151,7 +1566,9 @@ impl<'alloc> Lexer<'alloc> {
     ) -> Result<'alloc, ()> {
         text.push('\\');
         match self.chars.next() {
-            None | Some(CR) | Some(LF) | Some(LS) | Some(PS) => Err(ParseError::UnterminatedRegExp),
+            None | Some(CR) | Some(LF) | Some(LS) | Some(PS) => {
+                Err(ParseError::UnterminatedRegExp(self.offset()))
+            }
             Some(c) => {
                 text.push(c);
                 Ok(())
@@ -1162,14 +1579,13 @@ impl<'alloc> Lexer<'alloc> {
     // See 12.2.8 and 11.8.5 sections.
     fn regular_expression_literal(
         &mut self,
+        start: usize,
         builder: &mut AutoCow<'alloc>,
     ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
-        let offset = self.offset();
-
         loop {
             match self.chars.next() {
                 None | Some(CR) | Some(LF) | Some(LS) | Some(PS) => {
-                    return Err(ParseError::UnterminatedRegExp);
+                    return Err(ParseError::UnterminatedRegExp(start));
                 }
                 Some('/') => {
                     break;
@@ -1180,7 +1596,7 @@ impl<'alloc> Lexer<'alloc> {
                     loop {
                         match self.chars.next() {
                             None | Some(CR) | Some(LF) | Some(LS) | Some(PS) => {
-                                return Err(ParseError::UnterminatedRegExp);
+                                return Err(ParseError::UnterminatedRegExp(start));
                             }
                             Some(']') => {
                                 break;
@@ -1206,13 +1622,22 @@ impl<'alloc> Lexer<'alloc> {
             }
         }
         builder.push_matching('/');
-        let mut flag_text = AutoCow::new(&self);
+        let mut flags = 0u32;
         while let Some(ch) = self.peek() {
             match ch {
                 '$' | '_' | 'a'..='z' | 'A'..='Z' | '0'..='9' => {
+                    // 12.2.8.2.2 Check that only dgimsuy flags are mentioned
+                    // at most once.
+                    let mask = match "dgimsuy".find(ch) {
+                        Some(index) => 1 << index,
+                        None => return Err(ParseError::InvalidRegExpFlag(ch, self.offset())),
+                    };
+                    if flags & mask != 0 {
+                        return Err(ParseError::DuplicateRegExpFlag(ch, self.offset()));
+                    }
+                    flags |= mask;
                     self.chars.next();
                     builder.push_matching(ch);
-                    flag_text.push_matching(ch);
                 }
                 _ => break,
             }
@@ -1221,37 +1646,13 @@ impl<'alloc> Lexer<'alloc> {
         // 12.2.8.2.1 Assert literal is a RegularExpressionLiteral.
         let literal = builder.finish(&self);
 
-        // 12.2.8.2.2 Check that only gimsuy flags are mentioned at most once.
-        let gimsuy_mask: u32 = ['g', 'i', 'm', 's', 'u', 'y']
-            .into_iter()
-            .map(|x| 1 << ((*x as u8) - ('a' as u8)))
-            .sum();
-        let mut flag_text_set: u32 = 0;
-        for ch in flag_text.finish(&self).chars() {
-            if !ch.is_ascii_lowercase() {
-                return Err(ParseError::NotImplemented(
-                    "Unexpected flag in regular expression literal",
-                ));
-            }
-            let ch_mask = 1 << ((ch as u8) - ('a' as u8));
-            if ch_mask & gimsuy_mask == 0 {
-                return Err(ParseError::NotImplemented(
-                    "Unexpected flag in regular expression literal",
-                ));
-            }
-            if flag_text_set & ch_mask != 0 {
-                return Err(ParseError::NotImplemented(
-                    "Flag is mentioned twice in regular expression literal",
-                ));
-            }
-            flag_text_set |= ch_mask;
-        }
-
-        // TODO: 12.2.8.2.4 and 12.2.8.2.5 Check that the body matches the
-        // grammar defined in 21.2.1.
+        // 12.2.8.2.4 and 12.2.8.2.5 Check that the body matches the grammar
+        // defined in 21.2.1. Only the basic structure is checked for now.
+        let body_end = literal.rfind('/').unwrap();
+        check_regexp_pattern(&literal[1..body_end], start + 1)?;
 
         Ok((
-            SourceLocation::new(offset, self.offset()),
+            SourceLocation::new(start, self.offset()),
             Some(literal),
             TerminalId::RegularExpressionLiteral,
         ))
@@ -1288,6 +1689,8 @@ impl<'alloc> Lexer<'alloc> {
         tail: TerminalId,
     ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
         let mut builder = AutoCow::new(&self);
+        let raw_start = self.chars.as_str();
+        let mut is_cooked = true;
         while let Some(ch) = self.chars.next() {
             // TemplateCharacter ::
             //   `$` [lookahead != `{` ]
@@ -1315,35 +1718,73 @@ impl<'alloc> Lexer<'alloc> {
             //
             // CodePoint ::
             //   HexDigits [> but only if MV of |HexDigits| ≤ 0x10FFFF ]
-            if ch == '$' && self.peek() == Some('{') {
-                self.chars.next();
-                return Ok((
-                    SourceLocation::new(start, self.offset()),
-                    Some(builder.finish_without_push(&self)),
-                    subst,
-                ));
-            }
-            if ch == '`' {
+            if (ch == '$' && self.peek() == Some('{')) || ch == '`' {
+                let value = builder.finish_without_push(&self);
+                let raw = &raw_start[..raw_start.len() - self.chars.as_str().len() - 1];
+                let terminal_id = if ch == '$' {
+                    self.chars.next();
+                    subst
+                } else {
+                    tail
+                };
+                self.template_raw = Some(if raw.contains(CR) {
+                    let raw = raw.replace("\r\n", "\n").replace(CR, "\n");
+                    self.allocator.alloc_str(&raw)
+                } else {
+                    raw
+                });
                 return Ok((
                     SourceLocation::new(start, self.offset()),
-                    Some(builder.finish_without_push(&self)),
-                    tail,
+                    if is_cooked { Some(value) } else { None },
+                    terminal_id,
                 ));
             }
-            // TODO: Support escape sequences.
             if ch == '\\' {
                 let text = builder.get_mut_string_without_current_ascii_char(&self);
-                self.escape_sequence(text)?;
+                if !self.template_escape_sequence(text)? {
+                    is_cooked = false;
+                }
+            } else if ch == CR {
+                // LineTerminatorSequence: <CR><LF> and <CR> are both <LF>
+                // in the TV.
+                builder.force_allocation_without_current_ascii_char(&self);
+                if self.peek() == Some(LF) {
+                    self.chars.next();
+                }
+                builder.push_different(LF);
             } else {
                 builder.push_matching(ch);
             }
         }
-        Err(ParseError::UnterminatedString)
+        Err(ParseError::UnterminatedString(start, self.offset()))
     }
 
-    fn advance_impl<'parser>(
+    /// Scan an escape sequence in a template literal, having already consumed
+    /// the backslash, and push its value to `text`. Return false if it's a
+    /// *NotEscapeSequence* instead, whose characters are then scanned again
+    /// as template characters.
+    fn template_escape_sequence(&mut self, text: &mut String<'alloc>) -> Result<'alloc, bool> {
+        let rest = self.chars.clone();
+        let mut digits = rest.clone();
+        match (digits.next(), digits.next()) {
+            (Some('1'..='9'), _) | (Some('0'), Some('0'..='9')) => return Ok(false),
+            _ => {}
+        }
+        match self.escape_sequence(text) {
+            Ok(()) => Ok(true),
+            // `x` or `u` without the hex digits of a valid escape sequence.
+            Err(ParseError::InvalidEscapeSequence(_)) | Err(ParseError::IllegalCharacter(..)) => {
+                self.chars = rest;
+                self.chars.next();
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn advance_impl(
         &mut self,
-        parser: &Parser<'parser>,
+        parser: &impl ParserState,
     ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
         let mut builder = AutoCow::new(&self);
         let mut start = self.offset();
@@ -1397,6 +1838,13 @@ impl<'alloc> Lexer<'alloc> {
                 '0' => {
                     match self.numeric_literal_starting_with_zero()? {
                         NumericType::Normal => {
+                            if let (true, Some(start)) = (self.options.strict, self.legacy_octal)
+                            {
+                                return Err(ParseError::OctalLiteralInStrictMode(
+                                    start,
+                                    self.offset(),
+                                ));
+                            }
                             return Ok((
                                 SourceLocation::new(start, self.offset()),
                                 Some(builder.finish(&self)),
@@ -1404,6 +1852,8 @@ impl<'alloc> Lexer<'alloc> {
                             ));
                         }
                         NumericType::BigInt => {
+                            self.options
+                                .check_target("BigInt literals", EcmaVersion::Es2020, start)?;
                             return Ok((
                                 SourceLocation::new(start, self.offset()),
                                 Some(builder.finish_without_push(&self)),
@@ -1423,6 +1873,8 @@ impl<'alloc> Lexer<'alloc> {
                             ));
                         }
                         NumericType::BigInt => {
+                            self.options
+                                .check_target("BigInt literals", EcmaVersion::Es2020, start)?;
                             return Ok((
                                 SourceLocation::new(start, self.offset()),
                                 Some(builder.finish_without_push(&self)),
@@ -1465,6 +1917,10 @@ impl<'alloc> Lexer<'alloc> {
                 '&' => match self.peek() {
                     Some('&') => {
                         self.chars.next();
+                        if self.peek() == Some('=') {
+                            self.chars.next();
+                            return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalAndAssign));
+                        }
                         return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalAnd));
                     }
                     Some('=') => {
@@ -1511,7 +1967,8 @@ impl<'alloc> Lexer<'alloc> {
                             Some('>') if self.is_on_new_line => {
                                 // B.1.3 SingleLineHTMLCloseComment
                                 // TODO: Limit this to Script (not Module).
-                                self.skip_single_line_comment(&mut builder);
+                                self.skip_line_comment(&mut builder, start);
+                                start = self.offset();
                                 continue;
                             }
                             _ => return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::Decrement)),
@@ -1532,7 +1989,7 @@ impl<'alloc> Lexer<'alloc> {
                                 self.chars.next();
                                 return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::Ellipsis));
                             }
-                            _ => return Err(ParseError::IllegalCharacter('.')),
+                            _ => return Err(ParseError::IllegalCharacter('.', self.offset() - 1)),
                         }
                     }
                     Some('0'..='9') => {
@@ -1544,7 +2001,7 @@ impl<'alloc> Lexer<'alloc> {
                         // DecimalDigit. (11.8.3)
                         if let Some(ch) = self.peek() {
                             if is_identifier_start(ch) || ch.is_digit(10) {
-                                return Err(ParseError::IllegalCharacter(ch));
+                                return Err(ParseError::IllegalCharacter(ch, self.offset()));
                             }
                         }
 
@@ -1557,7 +2014,7 @@ impl<'alloc> Lexer<'alloc> {
                     Some('/') => {
                         // SingleLineComment :: `//` SingleLineCommentChars?
                         self.chars.next();
-                        self.skip_single_line_comment(&mut builder);
+                        self.skip_line_comment(&mut builder, start);
                         start = self.offset();
                         continue;
                     }
@@ -1578,7 +2035,7 @@ impl<'alloc> Lexer<'alloc> {
                             }
                         }
                         builder.push_matching('/');
-                        return self.regular_expression_literal(&mut builder);
+                        return self.regular_expression_literal(start, &mut builder);
                     }
                 },
 
@@ -1611,7 +2068,7 @@ impl<'alloc> Lexer<'alloc> {
                         // because `x<!--` has a comment but `x<!-y` does not.
                         //
                         // TODO: Limit this to Script (not Module).
-                        self.skip_single_line_comment(&mut builder);
+                        self.skip_line_comment(&mut builder, start);
                         start = self.offset();
                         continue;
                     }
@@ -1675,6 +2132,10 @@ impl<'alloc> Lexer<'alloc> {
                 '|' => match self.peek() {
                     Some('|') => {
                         self.chars.next();
+                        if self.peek() == Some('=') {
+                            self.chars.next();
+                            return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalOrAssign));
+                        }
                         return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalOr));
                     }
                     Some('=') => {
@@ -1687,8 +2148,20 @@ impl<'alloc> Lexer<'alloc> {
                 '?' => match self.peek() {
                     Some('?') => {
                         self.chars.next();
+                        if self.peek() == Some('=') {
+                            self.chars.next();
+                            return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::CoalesceAssign));
+                        }
                         return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::Coalesce));
                     }
+                    // OptionalChainingPunctuator ::
+                    //     `?.` [lookahead <! DecimalDigit]
+                    //
+                    // so that `a?.5:b` is a conditional expression.
+                    Some('.') if !matches!(self.chars.as_str().chars().nth(1), Some('0'..='9')) => {
+                        self.chars.next();
+                        return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::OptionalChain));
+                    }
                     _ => return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::QuestionMark)),
                 }
 
@@ -1713,7 +2186,7 @@ impl<'alloc> Lexer<'alloc> {
 
                     let value = self.unicode_escape_sequence_after_backslash()?;
                     if !is_identifier_start(value) {
-                        return Err(ParseError::IllegalCharacter(value));
+                        return Err(ParseError::IllegalCharacter(value, self.offset()));
                     }
                     builder.push_different(value);
 
@@ -1721,7 +2194,7 @@ impl<'alloc> Lexer<'alloc> {
                 }
 
                 '#' => {
-                    if start == 0 {
+                    if Some(start) == self.hashbang_start && self.options.allow_hashbang {
                         // https://tc39.es/proposal-hashbang/out.html
                         // HashbangComment ::
                         //     `#!` SingleLineCommentChars?
@@ -1742,7 +2215,7 @@ impl<'alloc> Lexer<'alloc> {
                 }
 
                 other => {
-                    return Err(ParseError::IllegalCharacter(other));
+                    return Err(ParseError::IllegalCharacter(other, start));
                 }
             }
         }
@@ -1824,3 +2297,72 @@ impl<'alloc> AutoCow<'alloc> {
         }
     }
 }
+
+/// Check that a regexp body, which starts at byte `offset` in the source,
+/// has balanced groups and no quantifier with nothing to repeat, as in
+/// `/(a/`, `/a)/` or `/a|*b/`.
+///
+/// The lexer has already made sure every `\` escapes a character and every
+/// class is closed, so those are only skipped here.
+fn check_regexp_pattern<'alloc>(pattern: &str, offset: usize) -> Result<'alloc, ()> {
+    // The offsets of the `(` of the groups we're in.
+    let mut groups = vec![];
+    // True at the start of an alternative, where there's nothing to repeat.
+    let mut at_term_start = true;
+    let mut chars = pattern.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' => {
+                if chars.next().is_none() {
+                    return Err(ParseError::InvalidRegExpPattern(
+                        "\\ at end of pattern",
+                        offset + i,
+                    ));
+                }
+                at_term_start = false;
+            }
+            '[' => {
+                let mut escaped = false;
+                for (_, ch) in &mut chars {
+                    match ch {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        ']' => break,
+                        _ => {}
+                    }
+                }
+                at_term_start = false;
+            }
+            '(' => {
+                groups.push(offset + i);
+                // Skip the `?` of `(?:`, `(?=` and so on, which isn't a
+                // quantifier.
+                if chars.as_str().starts_with('?') {
+                    chars.next();
+                }
+                at_term_start = true;
+            }
+            ')' => {
+                if groups.pop().is_none() {
+                    return Err(ParseError::InvalidRegExpPattern(
+                        "unmatched ')'",
+                        offset + i,
+                    ));
+                }
+                at_term_start = false;
+            }
+            '|' => at_term_start = true,
+            '*' | '+' | '?' if at_term_start => {
+                return Err(ParseError::InvalidRegExpPattern(
+                    "nothing to repeat",
+                    offset + i,
+                ));
+            }
+            _ => at_term_start = false,
+        }
+    }
+    match groups.pop() {
+        Some(group_start) => Err(ParseError::InvalidRegExpPattern("missing ')'", group_start)),
+        None => Ok(()),
+    }
+}
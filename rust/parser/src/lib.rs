@@ -1,8 +1,15 @@
-#![cfg_attr(feature = "unstable", feature(test))]
-
+mod encoding;
+mod incremental;
+mod json;
+mod lazy;
 mod lexer;
+mod metrics;
+mod owned;
 mod parser;
+mod recovery;
 mod simulator;
+mod stream;
+mod tokenizer;
 
 #[cfg(test)]
 mod tests;
@@ -10,54 +17,259 @@ mod tests;
 use crate::parser::Parser;
 use ast::{
     arena,
+    source_atom_set::SourceAtomSet,
+    source_location::SourceLocation,
     types::{Module, Script},
 };
 use bumpalo;
-use generated_parser::{
-    AstBuilder, StackValue, TerminalId, START_STATE_MODULE, START_STATE_SCRIPT, TABLES,
+pub use encoding::decode_source;
+use generated_parser::{AstBuilder, StackValue, START_STATE_MODULE, START_STATE_SCRIPT, TABLES};
+pub use generated_parser::{
+    ColumnConfig, ColumnUnit, EcmaVersion, ErrorLocation, ParseError, ParseErrorKind,
+    ParserOptions, Result, TerminalId, Token,
 };
-pub use generated_parser::{ParseError, Result};
-use lexer::Lexer;
+pub use incremental::{reparse_script, TextEdit};
+pub use json::{parse_json, JsonValue};
+pub use lazy::{force_function, parse_script_lazy};
+use lexer::{hashbang_comment, Lexer};
+pub use lexer::{Comment, CommentKind};
+pub use metrics::ParseMetrics;
+pub use owned::{parse_owned, OwnedParse, OwnedParseError};
+pub use recovery::{parse_script_recovering, parse_script_recovering_with_options};
 use std::io::{self, Write};
+pub use stream::ScriptStream;
+pub use tokenizer::{tokenize, Tokens};
 
+/// Parse a script.
+///
+/// There's no way to only check whether a script parses, without making its
+/// AST. Early Errors are found by the AstBuilder while it makes the nodes,
+/// and the parser's tables alone accept scripts such as
+/// `const a = 1; const a = 2;`. To check a script, parse it and drop the
+/// result, as `bench_parse_script` does, emptying the arena it reuses.
 pub fn parse_script<'alloc>(
     allocator: &'alloc bumpalo::Bump,
     source: &'alloc str,
 ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
-    Ok(parse(allocator, source, START_STATE_SCRIPT)?.to_ast()?)
+    parse_script_with_options(allocator, source, ParserOptions::default())
+}
+
+/// Parse a script from the bytes of a source file, decoded as
+/// `decode_source` does.
+pub fn parse_script_bytes<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    bytes: &[u8],
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    parse_script(allocator, decode_source(allocator, bytes)?)
+}
+
+pub fn parse_script_with_options<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    options: ParserOptions,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    script(allocator, source, options, None)
+}
+
+/// Parse a script, like `parse_script`, and count the work it took, for
+/// benchmarks. The counts are the same each time the same source is parsed
+/// with a new allocator. If parsing fails, they're up to the error, and no
+/// nodes are counted.
+pub fn parse_script_instrumented<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+) -> (
+    Result<'alloc, arena::Box<'alloc, Script<'alloc>>>,
+    ParseMetrics,
+) {
+    let mut metrics = ParseMetrics::default();
+    let bytes_before = metrics::arena_bytes(allocator);
+    let result = script(
+        allocator,
+        source,
+        ParserOptions::default(),
+        Some(&mut metrics.tokens),
+    );
+    if let Ok(script) = &result {
+        metrics.nodes = metrics::count_nodes(script);
+    }
+    metrics.bytes = metrics::arena_bytes(allocator) - bytes_before;
+    (result, metrics)
+}
+
+/// Parse `source` as a script, for benchmarks: `allocator` is emptied first,
+/// so each call starts from the same state and takes the same memory, and the
+/// AST is dropped. Returns whether `source` is a script.
+pub fn bench_parse_script(allocator: &mut bumpalo::Bump, source: &str) -> bool {
+    allocator.reset();
+    parse_script(allocator, source).is_ok()
+}
+
+/// Like `bench_parse_script`, with `parse_script_lazy`.
+pub fn bench_parse_script_lazy(allocator: &mut bumpalo::Bump, source: &str) -> bool {
+    allocator.reset();
+    parse_script_lazy(allocator, source).is_ok()
 }
 
 pub fn parse_module<'alloc>(
     allocator: &'alloc bumpalo::Bump,
     source: &'alloc str,
 ) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
-    Ok(parse(allocator, source, START_STATE_MODULE)?.to_ast()?)
+    module(allocator, source, None, None)
+}
+
+/// An AST, with the comments in its source, which aren't part of it.
+#[derive(Debug)]
+pub struct ParseResult<'alloc, T> {
+    pub ast: arena::Box<'alloc, T>,
+
+    /// The comments, in source order. The hashbang isn't one; it's in the
+    /// AST.
+    pub comments: Vec<Comment>,
+
+    /// The names in the source: the identifiers, including private names
+    /// and property names, and the keywords. Each name in the AST is the
+    /// string stored for it here, and `atoms.index_of(name)` is its handle,
+    /// the `atom` of each `Identifier` and `IdentifierName` for it.
+    /// After `reparse_script`, names that were only in the old source can
+    /// be left in it.
+    pub atoms: SourceAtomSet<'alloc>,
+
+    /// The whole source. The `loc` of the AST only goes from its first
+    /// statement to its last, leaving out the comments before and after.
+    pub loc: SourceLocation,
+
+    // What `reparse_script` needs, for scripts from
+    // `parse_script_with_comments` and `reparse_script`.
+    reparse: Option<incremental::ReparseInfo<'alloc>>,
+}
+
+/// Parse a script, like `parse_script_with_options`, and collect its
+/// comments, for tools such as formatters that keep them.
+///
+/// The comments aren't attached to AST nodes, since which node a comment is
+/// about is up to the tool.
+pub fn parse_script_with_comments<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    options: ParserOptions,
+) -> Result<'alloc, ParseResult<'alloc, Script<'alloc>>> {
+    TABLES.check();
+    incremental::parse_script(allocator, source, options, None)
+}
+
+/// Parse a module, like `parse_module`, and collect its comments. See
+/// `parse_script_with_comments`.
+pub fn parse_module_with_comments<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+) -> Result<'alloc, ParseResult<'alloc, Module<'alloc>>> {
+    let mut comments = Vec::new();
+    let mut atoms = SourceAtomSet::new();
+    let ast = module(allocator, source, Some(&mut comments), Some(&mut atoms))?;
+    Ok(ParseResult {
+        ast,
+        comments,
+        atoms,
+        loc: SourceLocation::new(0, source.len()),
+        reparse: None,
+    })
+}
+
+fn script<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    options: ParserOptions,
+    tokens: Option<&mut usize>,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    let mut script: arena::Box<'alloc, Script<'alloc>> = parse(
+        allocator,
+        source,
+        START_STATE_SCRIPT,
+        options,
+        None,
+        None,
+        tokens,
+    )?
+    .to_ast()?;
+    if options.allow_hashbang {
+        script.hashbang = hashbang_comment(source);
+    }
+    Ok(script)
+}
+
+fn module<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    comments: Option<&mut Vec<Comment>>,
+    atoms: Option<&mut SourceAtomSet<'alloc>>,
+) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
+    let options = ParserOptions::default();
+    let mut module: arena::Box<'alloc, Module<'alloc>> = parse(
+        allocator,
+        source,
+        START_STATE_MODULE,
+        options,
+        comments,
+        atoms,
+        None,
+    )?
+    .to_ast()?;
+    if options.allow_hashbang {
+        module.hashbang = hashbang_comment(source);
+    }
+    Ok(module)
 }
 
+/// Parse `source`, and if `comments` is given, set it to the comments found.
+/// If `atoms` is given, set it to the names in `source`. If `tokens` is
+/// given, add the number of tokens read to it, even if parsing fails.
 fn parse<'alloc>(
     allocator: &'alloc bumpalo::Bump,
     source: &'alloc str,
     start_state: usize,
+    options: ParserOptions,
+    comments: Option<&mut Vec<Comment>>,
+    atoms: Option<&mut SourceAtomSet<'alloc>>,
+    mut tokens_read: Option<&mut usize>,
 ) -> Result<'alloc, StackValue<'alloc>> {
-    let mut tokens = Lexer::new(allocator, source.chars());
-
+    let mut tokens = Lexer::with_options(allocator, source.chars(), options);
+    if comments.is_some() {
+        tokens.collect_comments();
+    }
+    if atoms.is_some() {
+        tokens.collect_atoms(SourceAtomSet::new());
+    }
     TABLES.check();
 
-    let mut parser = Parser::new(AstBuilder::new(allocator), start_state);
+    let mut parser = Parser::new(AstBuilder::with_options(allocator, options), start_state);
 
     loop {
         let t = tokens.next(&parser)?;
         if t.terminal_id == TerminalId::End {
             break;
         }
+        if let Some(count) = tokens_read.as_deref_mut() {
+            *count += 1;
+        }
         parser.write_token(&t)?;
     }
+    if let Some(comments) = comments {
+        *comments = tokens.take_comments();
+    }
+    if let Some(atoms) = atoms {
+        *atoms = tokens.take_atoms();
+    }
     parser.close(tokens.offset())
 }
 
 /// Prompt the user for some JS code and read a script from stdin.
 /// Returns the parsed script.
 ///
+/// Lines are read, with `continue_prompt` before each one after the first,
+/// until they're a script or can't become one with more lines; see
+/// `is_incomplete`.
+///
 /// Errors can be `ParseError::UnexpectedEnd` if the user typed Ctrl-D,
 /// `ParseError::IOError` if reading stdin or writing stdout failed, or
 /// any other `ParseError` if the input isn't valid JS.
@@ -66,41 +278,93 @@ pub fn read_script_interactively<'alloc>(
     prompt: &str,
     continue_prompt: &str,
 ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
-    TABLES.check();
+    match read_interactively(allocator, START_STATE_SCRIPT, prompt, continue_prompt)? {
+        StackValue::Script(s) => Ok(s),
+        other => {
+            // Can't happen due to invariants provided by the parser generator;
+            // the only finish state reachable from START_STATE_SCRIPT produces
+            // a Script.
+            panic!("unexpected StackValue: {:?}", other);
+        }
+    }
+}
 
-    let mut parser = Parser::new(AstBuilder::new(allocator), START_STATE_SCRIPT);
-    let mut byte_total = 0;
+/// Prompt the user for some JS code and read a module from stdin.
+/// Returns the parsed module.
+///
+/// Errors are the same as `read_script_interactively`.
+pub fn read_module_interactively<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    prompt: &str,
+    continue_prompt: &str,
+) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
+    match read_interactively(allocator, START_STATE_MODULE, prompt, continue_prompt)? {
+        StackValue::Module(m) => Ok(m),
+        other => {
+            // Can't happen due to invariants provided by the parser generator;
+            // the only finish state reachable from START_STATE_MODULE produces
+            // a Module.
+            panic!("unexpected StackValue: {:?}", other);
+        }
+    }
+}
 
+fn read_interactively<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    start_state: usize,
+    prompt: &str,
+    continue_prompt: &str,
+) -> Result<'alloc, StackValue<'alloc>> {
+    let mut source = String::new();
     print!("{}", prompt);
     loop {
-        let mut line = String::new();
         io::stdout().flush()?;
-        if io::stdin().read_line(&mut line)? == 0 {
+        if io::stdin().read_line(&mut source)? == 0 {
             return Err(ParseError::UnexpectedEnd);
         }
-        let line_str: &'alloc str = arena::alloc_str(allocator, &line);
-
-        let mut tokens = Lexer::with_offset(allocator, line_str.chars(), byte_total);
-        byte_total += line_str.len();
-        loop {
-            let t = tokens.next(&parser)?;
-            if t.terminal_id == TerminalId::End {
-                break;
-            }
-            parser.write_token(&t)?;
-        }
-        if parser.can_close() {
+        if !ends_too_soon(&source, start_state) {
             break;
         }
         print!("{}", continue_prompt);
     }
-    match parser.close(0)? {
-        StackValue::Script(s) => Ok(s),
-        other => {
-            // Can't happen due to invariants provided by the parser generator;
-            // the only finish state reachable from START_STATE_SCRIPT produces
-            // a Script.
-            panic!("unexpected StackValue: {:?}", other);
+    let source = arena::alloc_str(allocator, &source);
+    parse(
+        allocator,
+        source,
+        start_state,
+        ParserOptions::default(),
+        None,
+        None,
+        None,
+    )
+}
+
+/// True if `source` isn't a script, but is the start of one: parsing it
+/// fails only because it ends too soon, as when a brace, a parenthesis, a
+/// template or a comment isn't closed, or a binary operator has no right
+/// operand. `read_script_interactively` uses this to tell when to prompt for
+/// more lines.
+pub fn is_incomplete(source: &str) -> bool {
+    ends_too_soon(source, START_STATE_SCRIPT)
+}
+
+fn ends_too_soon(source: &str, start_state: usize) -> bool {
+    let allocator = &bumpalo::Bump::new();
+    match parse(
+        allocator,
+        source,
+        start_state,
+        ParserOptions::default(),
+        None,
+        None,
+        None,
+    ) {
+        Err(ParseError::UnexpectedEnd) | Err(ParseError::UnterminatedMultiLineComment(_)) => true,
+        // Strings end at the end of the line, but templates don't. The error
+        // is at the start of the template, or of its part after `}`.
+        Err(ParseError::UnterminatedString(offset, _)) => {
+            matches!(source[offset..].chars().next(), Some('`') | Some('}'))
         }
+        _ => false,
     }
 }
@@ -0,0 +1,41 @@
+//! Counters for `parse_script_instrumented`.
+
+use ast::{source_location::SourceLocation, types::Script, visit::Visitor};
+
+/// What parsing a script took. See `parse_script_instrumented`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// The tokens read, not counting comments or the semicolons inserted by
+    /// automatic semicolon insertion.
+    pub tokens: usize,
+
+    /// The nodes of the AST, counted as the nodes with a source location.
+    pub nodes: usize,
+
+    /// The bytes the parser took in the arena, including the nodes it made
+    /// and threw away, and the unused ends of the chunks it filled.
+    pub bytes: usize,
+}
+
+/// The bytes used in `allocator`'s chunks: everything but what's left of the
+/// current one.
+pub(crate) fn arena_bytes(allocator: &bumpalo::Bump) -> usize {
+    allocator.allocated_bytes() - allocator.chunk_capacity()
+}
+
+pub(crate) fn count_nodes(script: &Script) -> usize {
+    let mut counter = NodeCounter { nodes: 0 };
+    counter.visit_script(script);
+    counter.nodes
+}
+
+// Each node with a location visits it once.
+struct NodeCounter {
+    nodes: usize,
+}
+
+impl<'alloc> Visitor<'alloc> for NodeCounter {
+    fn visit_source_location(&mut self, _loc: &SourceLocation) {
+        self.nodes += 1;
+    }
+}
@@ -0,0 +1,106 @@
+//! Parsing into an AST that owns its allocator, so it can be returned and
+//! stored like any other value.
+
+use crate::parse_script;
+use ast::{arena, types::Script};
+use generated_parser::ParseError;
+use std::error::Error;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The allocator of an `OwnedParse` or `OwnedParseError`, freed when it's
+/// dropped.
+///
+/// It's only kept as a pointer, so the references into it that the AST
+/// holds stay valid as the owner moves: a `Box` would be asserted to be
+/// unique each time it moved.
+struct Arena(NonNull<bumpalo::Bump>);
+
+impl Arena {
+    fn new() -> Self {
+        Arena(NonNull::from(Box::leak(Box::new(bumpalo::Bump::new()))))
+    }
+
+    /// The allocator, for the AST to borrow. The AST must only be handed
+    /// out with the lifetime of a borrow of the arena's owner.
+    unsafe fn allocator(&self) -> &'static bumpalo::Bump {
+        &*self.0.as_ptr()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.0.as_ptr())) }
+    }
+}
+
+/// A script, with the allocator it's in. See `parse_owned`.
+pub struct OwnedParse {
+    // Borrows from `arena`, so it's declared first and dropped first.
+    script: arena::Box<'static, Script<'static>>,
+    _arena: Arena,
+}
+
+impl OwnedParse {
+    pub fn script<'a>(&'a self) -> &'a Script<'a> {
+        // `Script` can't be shortened to the borrow's lifetime by the
+        // compiler, since its `Box`es are mutable references, but nothing can
+        // be stored in it through a shared reference.
+        let script: *const Script<'static> = &*self.script;
+        unsafe { &*script.cast::<Script<'a>>() }
+    }
+}
+
+impl fmt::Debug for OwnedParse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedParse").field(self.script()).finish()
+    }
+}
+
+/// An error from `parse_owned`, with the allocator the strings it refers
+/// to are in.
+pub struct OwnedParseError {
+    error: ParseError<'static>,
+    _arena: Arena,
+}
+
+impl OwnedParseError {
+    pub fn error(&self) -> &ParseError<'_> {
+        &self.error
+    }
+}
+
+impl fmt::Debug for OwnedParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedParseError")
+            .field(self.error())
+            .finish()
+    }
+}
+
+impl fmt::Display for OwnedParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.error(), f)
+    }
+}
+
+impl Error for OwnedParseError {}
+
+/// Parse a script, like `parse_script`, in an allocator of its own, which
+/// the result owns, so that it needn't be kept by the caller. `source` is
+/// copied into the allocator.
+pub fn parse_owned(source: &str) -> Result<OwnedParse, OwnedParseError> {
+    let arena = Arena::new();
+    let allocator = unsafe { arena.allocator() };
+    let source = allocator.alloc_str(source);
+    match parse_script(allocator, source) {
+        Ok(script) => Ok(OwnedParse {
+            script,
+            _arena: arena,
+        }),
+        Err(error) => Err(OwnedParseError {
+            error,
+            _arena: arena,
+        }),
+    }
+}
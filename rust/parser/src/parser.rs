@@ -1,7 +1,13 @@
+use crate::lexer::ParserState;
 use crate::simulator::Simulator;
-use ast::SourceLocation;
+use ast::{
+    arena,
+    types::{CallExpression, Expression, ExpressionOrSuper, FunctionBody, Statement},
+    SourceLocation,
+};
 use generated_parser::{
-    reduce, AstBuilder, ErrorCode, ParseError, Result, StackValue, TerminalId, Token, TABLES,
+    reduce, AstBuilder, ErrorCode, NonterminalId, ParseError, Result, StackValue, TerminalId,
+    Token, START_STATE_MODULE, START_STATE_SCRIPT, TABLES,
 };
 
 const ACCEPT: i64 = -0x7fff_ffff_ffff_ffff;
@@ -42,6 +48,22 @@ pub struct Parser<'alloc> {
     state_stack: Vec<usize>,
     node_stack: Vec<StackValue<'alloc>>,
     handler: AstBuilder<'alloc>,
+
+    /// The number of parentheses, brackets, braces and template
+    /// substitutions shifted and not closed yet, and how many there can be;
+    /// see `ParserOptions::max_nesting_depth`.
+    nesting_depth: usize,
+    max_nesting_depth: usize,
+
+    /// How deeply the expressions and statements of each value on
+    /// `node_stack` are nested in it, and how deep they can be; see
+    /// `ParserOptions::max_node_depth`.
+    node_depths: Vec<usize>,
+    max_node_depth: usize,
+
+    /// The offset of the last token shifted, if it's `await` shifted as an
+    /// identifier; see `await_expression_error`.
+    identifier_await: Option<usize>,
 }
 
 impl<'alloc> Parser<'alloc> {
@@ -49,13 +71,67 @@ impl<'alloc> Parser<'alloc> {
         TABLES.check();
         assert!(entry_state < TABLES.state_count);
 
+        let max_nesting_depth = handler.options().max_nesting_depth;
+        let max_node_depth = handler.options().max_node_depth;
         Self {
             state_stack: vec![entry_state],
             node_stack: vec![],
             handler,
+            nesting_depth: 0,
+            max_nesting_depth,
+            node_depths: vec![],
+            max_node_depth,
+            identifier_await: None,
+        }
+    }
+
+    /// Create a parser in the state it is in after the top-level
+    /// `statements`, when the next token starts another statement.
+    pub(crate) fn after_statements(
+        handler: AstBuilder<'alloc>,
+        entry_state: usize,
+        statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
+    ) -> Self {
+        let mut parser = Self::new(handler, entry_state);
+        parser.state_stack.push(parser.statement_list_state());
+        parser.node_stack.push(StackValue::VecStatement(statements));
+        parser.node_depths.push(0);
+        parser
+    }
+
+    // The state after the StatementList of a Script.
+    fn statement_list_state(&self) -> usize {
+        let tables = TABLES;
+        tables.goto_table
+            [self.state_stack[0] * tables.goto_width + NonterminalId::StatementList as usize]
+            as usize
+    }
+
+    /// The top-level statements, if the parser has nothing but them on its
+    /// stack, as before the first token of another statement.
+    pub(crate) fn statements_mut(&mut self) -> Option<&mut arena::Vec<'alloc, Statement<'alloc>>> {
+        if self.state_stack.len() != 2 || self.state_stack[1] != self.statement_list_state() {
+            return None;
+        }
+        match self.node_stack.last_mut() {
+            Some(StackValue::VecStatement(statements)) => Some(statements),
+            _ => None,
         }
     }
 
+    /// The function body the parser has just made, if it's on top of the
+    /// stack, as before shifting the `}` after it.
+    pub(crate) fn function_body_mut(&mut self) -> Option<&mut FunctionBody<'alloc>> {
+        match self.node_stack.last_mut() {
+            Some(StackValue::FunctionBody(body)) => Some(body),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn handler_mut(&mut self) -> &mut AstBuilder<'alloc> {
+        &mut self.handler
+    }
+
     fn state(&self) -> usize {
         *self.state_stack.last().unwrap()
     }
@@ -71,12 +147,20 @@ impl<'alloc> Parser<'alloc> {
         Action(TABLES.action_table[state * TABLES.action_width + t])
     }
 
-    fn reduce_all(&mut self, t: TerminalId) -> Result<'alloc, Action> {
+    // Make the reductions before `t`, which is at `offset`.
+    fn reduce_all(&mut self, t: TerminalId, offset: usize) -> Result<'alloc, Action> {
         let tables = TABLES;
         let mut action = self.action(t);
         while action.is_reduce() {
             let prod_index = action.reduce_prod_index();
+            let popped = tables.reduce_simulator[prod_index].0;
+            let bracketed = popped > 0
+                && matches!(
+                    &self.node_stack[self.node_stack.len() - popped],
+                    StackValue::Token(token) if opens_nesting(token.terminal_id)
+                );
             let nt = reduce(&mut self.handler, prod_index, &mut self.node_stack)?;
+            self.note_reduced_depth(popped, bracketed, offset)?;
             debug_assert!((nt as usize) < tables.goto_width);
             debug_assert!(self.state_stack.len() >= self.node_stack.len());
             self.state_stack.truncate(self.node_stack.len());
@@ -92,16 +176,59 @@ impl<'alloc> Parser<'alloc> {
         Ok(action)
     }
 
+    // Work out the depth of the value a reduction made from the `popped`
+    // values on top of the stack. It's one more than theirs if it's an
+    // expression or a statement made of more than one of them, as a unit
+    // production like `Expression : AssignmentExpression` doesn't nest,
+    // unless they're `bracketed`, starting with a bracket that
+    // `nesting_depth` counts instead.
+    fn note_reduced_depth(
+        &mut self,
+        popped: usize,
+        bracketed: bool,
+        offset: usize,
+    ) -> Result<'alloc, ()> {
+        let rest = self.node_depths.len() - popped;
+        let nested = self.node_depths[rest..].iter().copied().max().unwrap_or(0);
+        let depth = match self.node_stack.last() {
+            Some(StackValue::Expression(_)) | Some(StackValue::Statement(_))
+                if popped > 1 && !bracketed =>
+            {
+                nested + 1
+            }
+            _ => nested,
+        };
+        if depth > self.max_node_depth {
+            return Err(ParseError::TooDeeplyNested(offset));
+        }
+        self.node_depths.truncate(rest);
+        self.node_depths.push(depth);
+        Ok(())
+    }
+
     pub fn write_token(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        let action = self.reduce_before(token)?;
+        self.shift(token, action);
+        Ok(())
+    }
+
+    /// Do what `write_token` does before shifting `token`: the reductions
+    /// it makes, and the semicolons it inserts. Returns the shift action for
+    /// `shift`.
+    pub(crate) fn reduce_before(&mut self, token: &Token<'alloc>) -> Result<'alloc, Action> {
+        if opens_nesting(token.terminal_id) && self.nesting_depth >= self.max_nesting_depth {
+            return Err(ParseError::TooDeeplyNested(token.loc.start));
+        }
+        if token.is_on_new_line {
+            self.check_restricted_production(token)?;
+        }
+
         // Loop for error-handling. The normal path through this code reaches
         // the `return` statement.
         loop {
-            let action = self.reduce_all(token.terminal_id)?;
+            let action = self.reduce_all(token.terminal_id, token.loc.start)?;
             if action.is_shift() {
-                self.node_stack
-                    .push(StackValue::Token(self.handler.alloc(token.clone())));
-                self.state_stack.push(action.shift_state());
-                return Ok(());
+                return Ok(action);
             } else {
                 assert!(action.is_error());
                 self.try_error_handling(token)?;
@@ -109,10 +236,103 @@ impl<'alloc> Parser<'alloc> {
         }
     }
 
+    pub(crate) fn shift(&mut self, token: &Token<'alloc>, action: Action) {
+        if opens_nesting(token.terminal_id) {
+            self.nesting_depth += 1;
+        } else if closes_nesting(token.terminal_id) {
+            self.nesting_depth = self.nesting_depth.saturating_sub(1);
+        }
+        self.handler.note_legacy_octal(token);
+        self.identifier_await = match token.value {
+            Some("await") if token.terminal_id == TerminalId::Name => Some(token.loc.start),
+            _ => None,
+        };
+        self.node_stack
+            .push(StackValue::Token(self.handler.alloc(token.clone())));
+        self.node_depths.push(0);
+        self.state_stack.push(action.shift_state());
+    }
+
+    /// Handle `token`, the first token on its line, where the grammar says
+    /// `[no LineTerminator here]`. The tables don't know about line breaks,
+    /// so without this, `return\nx` would return `x`.
+    ///
+    /// After `return`, `break`, `continue` and `yield`, and before a postfix
+    /// `++` or `--`, a semicolon is inserted, as ASI requires even where the
+    /// token would be allowed (ECMAScript 2020 11.9.1, rule 3). So it is
+    /// before the legacy `assert` import attributes keyword. After `throw`
+    /// and before `=>`, there's no semicolon to insert, so it's a syntax
+    /// error.
+    fn check_restricted_production(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        if token.terminal_id == TerminalId::Semicolon {
+            return Ok(());
+        }
+        if token.terminal_id == TerminalId::Arrow {
+            return Self::parse_error(token);
+        }
+
+        // `import x from "m"\nassert(y)` is two statements. The semicolon
+        // is only inserted where it's allowed, so after a string that's
+        // anything but a module specifier, `assert` is parsed as usual.
+        if token.terminal_id == TerminalId::Assert {
+            if let Some(StackValue::Token(last)) = self.node_stack.last() {
+                if last.terminal_id == TerminalId::StringLiteral
+                    && self.can_accept_terminal(TerminalId::Semicolon)
+                {
+                    return self.insert_semicolon(token);
+                }
+            }
+        }
+
+        // The keyword is the last token shifted. Checking that a name could
+        // come next tells the keyword apart from a property name spelled the
+        // same, as in `{ return: 1 }`.
+        let after_keyword = match self.node_stack.last() {
+            Some(StackValue::Token(last)) => match last.terminal_id {
+                TerminalId::Return
+                | TerminalId::Break
+                | TerminalId::Continue
+                | TerminalId::Throw
+                | TerminalId::Yield => Some(last.terminal_id),
+                _ => None,
+            },
+            _ => None,
+        };
+        match after_keyword {
+            Some(keyword) if self.can_accept_terminal(TerminalId::Name) => {
+                if keyword == TerminalId::Throw {
+                    return Self::parse_error(token);
+                }
+                return self.insert_semicolon(token);
+            }
+            _ => {}
+        }
+
+        // `++` and `--` are postfix where a name couldn't start an operand.
+        match token.terminal_id {
+            TerminalId::Increment | TerminalId::Decrement
+                if !self.can_accept_terminal(TerminalId::Name) =>
+            {
+                self.insert_semicolon(token)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Write a semicolon before `token`, or fail on `token` if one isn't
+    /// allowed here.
+    fn insert_semicolon(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        if !self.can_accept_terminal(TerminalId::Semicolon) {
+            return Self::parse_error(token);
+        }
+        let loc = SourceLocation::new(token.loc.start, token.loc.start);
+        self.write_token(&Token::basic_token(TerminalId::Semicolon, loc))
+    }
+
     pub fn close(&mut self, position: usize) -> Result<'alloc, StackValue<'alloc>> {
         // Loop for error-handling.
         loop {
-            let action = self.reduce_all(TerminalId::End)?;
+            let action = self.reduce_all(TerminalId::End, position)?;
             if action.is_accept() {
                 assert_eq!(self.node_stack.len(), 1);
                 return Ok(self.node_stack.pop().unwrap());
@@ -137,7 +357,14 @@ impl<'alloc> Parser<'alloc> {
         // between this and write_terminal are commented below.
         assert!(t.terminal_id != TerminalId::ErrorToken);
 
-        let action = self.reduce_all(TerminalId::ErrorToken)?;
+        if self.mixes_coalesce_with_logical_operator(t.terminal_id) {
+            return Err(ParseError::CoalesceWithLogicalOperator(t.loc.start));
+        }
+        if self.follows_unary_expression(t.terminal_id) {
+            return Err(ParseError::UnaryBeforeExponentiation(t.loc.start));
+        }
+
+        let action = self.reduce_all(TerminalId::ErrorToken, t.loc.start)?;
         if action.is_shift() {
             let state = *self.state_stack.last().unwrap();
             let error_code = TABLES.error_codes[state]
@@ -149,10 +376,107 @@ impl<'alloc> Parser<'alloc> {
         } else {
             // On error, don't attempt error handling again.
             assert!(action.is_error());
+            if let Some(err) = self.await_expression_error() {
+                return Err(err);
+            }
+            if let Some(err) = self.module_declaration_in_script(t) {
+                return Err(err);
+            }
             Self::parse_error(t)
         }
     }
 
+    /// The error for the token after `await`, if it's a syntax error and
+    /// `await` is an identifier there, as in `function f() { await x; }`.
+    /// That's an await expression where there can't be one, and saying so
+    /// is more helpful than a syntax error on `x`.
+    fn await_expression_error(&self) -> Option<ParseError<'alloc>> {
+        let offset = self.identifier_await?;
+        // The parameters of `async (a = await b) => a` are arguments of a
+        // call until the `=>`.
+        let mut closed = 0;
+        for i in (1..self.node_stack.len()).rev() {
+            let terminal_id = match &self.node_stack[i] {
+                StackValue::Token(token) => token.terminal_id,
+                _ => continue,
+            };
+            match terminal_id {
+                TerminalId::CloseParenthesis => closed += 1,
+                TerminalId::OpenParenthesis if closed > 0 => closed -= 1,
+                TerminalId::OpenParenthesis => {
+                    let after_async = match &self.node_stack[i - 1] {
+                        StackValue::Token(token) => token.terminal_id == TerminalId::Async,
+                        StackValue::Expression(callee) => matches!(
+                            &**callee,
+                            Expression::IdentifierExpression(name) if name.name.value == "async"
+                        ),
+                        _ => false,
+                    };
+                    if after_async {
+                        return Some(ParseError::AwaitInParameters(offset));
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Some(ParseError::AwaitOutsideAsyncFunction(offset))
+    }
+
+    /// The error for `t`, which is a syntax error here, if this is a script
+    /// and `t` is where a module could have an `import` or `export`
+    /// declaration: `t` is `export`, or follows an `import` that doesn't
+    /// start `import(...)` or `import.meta` and isn't the end, at the top
+    /// level. A module parsed as a script fails there, and saying so is more
+    /// helpful than a syntax error on the token.
+    fn module_declaration_in_script(&self, t: &Token<'alloc>) -> Option<ParseError<'alloc>> {
+        if self.state_stack[0] != START_STATE_SCRIPT {
+            return None;
+        }
+        let (keyword, offset, depth) = match self.node_stack.last() {
+            Some(StackValue::Token(last))
+                if last.terminal_id == TerminalId::Import && t.terminal_id != TerminalId::End =>
+            {
+                ("import", last.loc.start, self.state_stack.len() - 1)
+            }
+            _ if t.terminal_id == TerminalId::Export => {
+                ("export", t.loc.start, self.state_stack.len())
+            }
+            _ => return None,
+        };
+        // It's at the top level if the script could end before the keyword.
+        Simulator::new(&self.state_stack[..depth])
+            .close(offset)
+            .ok()?;
+        Some(ParseError::ModuleDeclarationInScript(keyword, offset))
+    }
+
+    /// True if `t`, which is a syntax error here, is `||` or `&&` where `??`
+    /// would be allowed, or the other way around. The grammar doesn't allow
+    /// `a ?? b || c` or `a && b ?? c`, so this is the error there, and saying
+    /// so is more helpful than a syntax error on the operator.
+    fn mixes_coalesce_with_logical_operator(&self, t: TerminalId) -> bool {
+        match t {
+            TerminalId::LogicalOr | TerminalId::LogicalAnd => {
+                self.can_accept_terminal(TerminalId::Coalesce)
+            }
+            TerminalId::Coalesce => {
+                self.can_accept_terminal(TerminalId::LogicalOr)
+                    || self.can_accept_terminal(TerminalId::LogicalAnd)
+            }
+            _ => false,
+        }
+    }
+
+    /// True if `t`, which is a syntax error here, is `**` after a unary
+    /// expression, as in `-a ** b`: an operand has just ended, as a name
+    /// can't follow, and `*` could follow it, so only `**` is an error.
+    fn follows_unary_expression(&self, t: TerminalId) -> bool {
+        t == TerminalId::Exponentiate
+            && self.can_accept_terminal(TerminalId::Star)
+            && !self.can_accept_terminal(TerminalId::Name)
+    }
+
     fn recover(
         &mut self,
         t: &Token<'alloc>,
@@ -170,6 +494,8 @@ impl<'alloc> Parser<'alloc> {
                     // state.
                     *self.state_stack.last_mut().unwrap() = next_state;
                     Ok(())
+                } else if let Some(err) = self.await_expression_error() {
+                    Err(err)
                 } else {
                     Self::parse_error(t)
                 }
@@ -182,19 +508,157 @@ impl<'alloc> Parser<'alloc> {
         }
     }
 
+    /// Whether `await` is reserved where the next token goes: in an async
+    /// function, method or arrow function or its parameters, in a class
+    /// static block, or at the top level of a module.
+    ///
+    /// The grammar doesn't say, so the function or static block the parser
+    /// is in is found from the tokens before it on the stack: the `(`
+    /// starting its parameters, the `{` starting its body, or the `=>` of an
+    /// arrow function. None of them is reduced before the `)`, `}` or
+    /// expression ending it. The ones that have already ended are skipped, as
+    /// are the `{` of blocks and of class and object literals, and the `(` of
+    /// calls and statement heads.
+    fn await_is_reserved_here(&self) -> bool {
+        let stack = &self.node_stack;
+        let terminal = |i: usize| match &stack[i] {
+            StackValue::Token(token) => Some(token.terminal_id),
+            _ => None,
+        };
+        let mut closed_braces = 0;
+        let mut closed_parens = 0;
+        let mut ended_arrow = None;
+        for i in (0..stack.len()).rev() {
+            match terminal(i) {
+                Some(TerminalId::CloseBrace) => closed_braces += 1,
+                Some(TerminalId::OpenBrace) if closed_braces > 0 => {
+                    closed_braces -= 1;
+                    if closed_braces == 0 && i > 0 && terminal(i - 1) == Some(TerminalId::Arrow) {
+                        ended_arrow = Some(i - 1);
+                    }
+                }
+                _ if closed_braces > 0 => {}
+                Some(TerminalId::CloseParenthesis) => closed_parens += 1,
+                Some(TerminalId::OpenParenthesis) if closed_parens > 0 => closed_parens -= 1,
+                Some(TerminalId::OpenParenthesis) => {
+                    if let Some(is_async) = self.function_is_async(i) {
+                        return is_async;
+                    }
+                }
+                Some(TerminalId::OpenBrace) if i > 0 => match terminal(i - 1) {
+                    Some(TerminalId::Static) => return true,
+                    Some(TerminalId::CloseParenthesis) => {
+                        if let Some(is_async) = self.function_head_is_async(i - 1) {
+                            return is_async;
+                        }
+                    }
+                    _ => {}
+                },
+                Some(TerminalId::Arrow) if ended_arrow != Some(i) => {
+                    return self.arrow_head_is_async(i);
+                }
+                _ => {}
+            }
+        }
+        self.state_stack[0] == START_STATE_MODULE
+    }
+
+    /// If the `)` at `close` on the stack, followed by `{`, ends the
+    /// parameters of a function or method, whether it's async. None if it
+    /// ends the head of a statement instead, as in `if (a) {`.
+    fn function_head_is_async(&self, close: usize) -> Option<bool> {
+        // By now the parameters, or the expressions between the
+        // parentheses, are reduced, and so are the parentheses in them.
+        let open = (0..close).rev().find(|&i| match &self.node_stack[i] {
+            StackValue::Token(token) => token.terminal_id == TerminalId::OpenParenthesis,
+            _ => false,
+        })?;
+        self.function_is_async(open)
+    }
+
+    /// If the `(` at `open` on the stack starts the parameters of a function
+    /// or method, whether it's async. None if it starts anything else, as in
+    /// `f(a)` or `if (a)`.
+    fn function_is_async(&self, open: usize) -> Option<bool> {
+        let stack = &self.node_stack;
+        let terminal = |i: usize| match &stack[i] {
+            StackValue::Token(token) => Some(token.terminal_id),
+            _ => None,
+        };
+        let mut i = open.checked_sub(1)?;
+        match &stack[i] {
+            // The name of the function or method.
+            StackValue::BindingIdentifier(_)
+            | StackValue::PropertyName(_)
+            | StackValue::ClassElementName(_) => i = i.checked_sub(1)?,
+            StackValue::Token(token) if token.terminal_id == TerminalId::Function => {}
+            _ => return None,
+        }
+        while let Some(TerminalId::Star | TerminalId::Function) = terminal(i) {
+            i = i.checked_sub(1)?;
+        }
+        Some(terminal(i) == Some(TerminalId::Async))
+    }
+
+    /// Whether the arrow function whose `=>` is at `arrow` on the stack is
+    /// async: its parameters are a call of `async`, as in `async (a) =>`,
+    /// or `async` and a name, as in `async a =>`.
+    fn arrow_head_is_async(&self, arrow: usize) -> bool {
+        let stack = &self.node_stack;
+        match &stack[..arrow] {
+            [.., StackValue::Expression(head)] => matches!(
+                &**head,
+                Expression::CallExpression(CallExpression {
+                    callee: ExpressionOrSuper::Expression(callee),
+                    ..
+                }) if matches!(&**callee, Expression::IdentifierExpression(name) if name.name.value == "async")
+            ),
+            [.., StackValue::Token(token), StackValue::BindingIdentifier(_)] => {
+                token.terminal_id == TerminalId::Async
+            }
+            _ => false,
+        }
+    }
+
     fn simulator(&self) -> Simulator {
         Simulator::new(&self.state_stack)
     }
 
-    pub fn can_accept_terminal(&self, t: TerminalId) -> bool {
+    /// Return true if self.close() would succeed.
+    pub fn can_close(&self) -> bool {
+        self.simulator().close(0).is_ok()
+    }
+}
+
+fn opens_nesting(t: TerminalId) -> bool {
+    matches!(
+        t,
+        TerminalId::OpenParenthesis
+            | TerminalId::OpenBracket
+            | TerminalId::OpenBrace
+            | TerminalId::TemplateHead
+    )
+}
+
+fn closes_nesting(t: TerminalId) -> bool {
+    matches!(
+        t,
+        TerminalId::CloseParenthesis
+            | TerminalId::CloseBracket
+            | TerminalId::CloseBrace
+            | TerminalId::TemplateTail
+    )
+}
+
+impl<'alloc> ParserState for Parser<'alloc> {
+    fn can_accept_terminal(&self, t: TerminalId) -> bool {
         let bogus_loc = SourceLocation::new(0, 0);
         self.simulator()
             .write_token(&Token::basic_token(t, bogus_loc))
             .is_ok()
     }
 
-    /// Return true if self.close() would succeed.
-    pub fn can_close(&self) -> bool {
-        self.simulator().close(0).is_ok()
+    fn await_is_reserved(&self) -> bool {
+        self.await_is_reserved_here()
     }
 }
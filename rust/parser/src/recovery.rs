@@ -0,0 +1,286 @@
+//! Error-recovering parser entry points.
+//!
+//! The generated parser stops at the first error. To report every error in a
+//! file, the source is parsed in several attempts: when an attempt fails, the
+//! statements that were complete before the error are kept, the text around
+//! the error is replaced by an `ErrorStatement`, and parsing restarts from
+//! the next statement boundary.
+
+use crate::lexer::{hashbang_comment, Lexer};
+use crate::parser::Parser;
+use ast::{
+    arena,
+    source_location::SourceLocation,
+    source_location_accessor::SourceLocationAccessor,
+    types::{Script, Statement},
+};
+use generated_parser::{
+    AstBuilder, ParseError, ParserOptions, Result, TerminalId, START_STATE_SCRIPT,
+};
+
+/// Result of a failed parsing attempt starting at some offset.
+struct Failure<'alloc> {
+    error: ParseError<'alloc>,
+
+    /// End of the longest prefix of the attempt that is known to be a
+    /// complete script.
+    prefix_end: usize,
+
+    /// Where the next attempt should start.
+    resume: usize,
+
+    /// True if the source between `prefix_end` and `resume` should be
+    /// represented by an `ErrorStatement`. False if the error was recovered
+    /// without skipping any tokens (e.g. a missing semicolon).
+    skipped: bool,
+}
+
+/// Parse a script, recovering from errors.
+///
+/// Returns a `Script` containing every statement that could be parsed, with
+/// `Statement::ErrorStatement` placeholders for the parts of the source that
+/// couldn't, along with all errors found, in source order.
+///
+/// Recovery works at the top-level statement granularity: early errors are
+/// only detected among the statements parsed in the same attempt, and a
+/// syntax error inside a nested block skips the rest of that line. Once the
+/// directive prologue has been parsed, strict mode is forced for the other
+/// attempts if it has a "use strict" directive. Names aren't interned, so
+/// they have no `atom`.
+pub fn parse_script_recovering<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+) -> (arena::Box<'alloc, Script<'alloc>>, Vec<ParseError<'alloc>>) {
+    parse_script_recovering_with_options(allocator, source, ParserOptions::default())
+}
+
+/// Parse a script with `options`, recovering from errors, as
+/// `parse_script_recovering` does.
+pub fn parse_script_recovering_with_options<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    mut options: ParserOptions,
+) -> (arena::Box<'alloc, Script<'alloc>>, Vec<ParseError<'alloc>>) {
+    let mut statements = arena::Vec::new_in(allocator);
+    let mut errors = Vec::new();
+
+    let mut start = 0;
+    loop {
+        let failure = match try_parse_script_from(allocator, source, start, options) {
+            Ok(script) => {
+                options.strict |= script.strict;
+                statements.extend(script.unbox().statements);
+                break;
+            }
+            Err(failure) => failure,
+        };
+
+        let mut error_start = failure.prefix_end;
+        if failure.prefix_end > start {
+            match parse_script_range(allocator, source, start, failure.prefix_end, options) {
+                Ok(script) => {
+                    options.strict |= script.strict;
+                    statements.extend(script.unbox().statements)
+                }
+                Err(error) => {
+                    // The prefix was a complete script from the parser's
+                    // point of view, but an early error was found when
+                    // closing it.
+                    push_error(&mut errors, error);
+                    error_start = start;
+                }
+            }
+        }
+
+        if failure.skipped || error_start < failure.prefix_end {
+            statements.push(Statement::ErrorStatement {
+                loc: SourceLocation::new(error_start, failure.resume),
+            });
+        }
+        push_error(&mut errors, failure.error);
+
+        if failure.resume >= source.len() {
+            break;
+        }
+        start = failure.resume;
+    }
+
+    let loc = if statements.is_empty() {
+        SourceLocation::default()
+    } else {
+        SourceLocation::from_parts(
+            statements.first().unwrap().get_loc(),
+            statements.last().unwrap().get_loc(),
+        )
+    };
+    let script = arena::alloc(
+        allocator,
+        Script {
+            directives: arena::Vec::new_in(allocator),
+            statements,
+            hashbang: if options.allow_hashbang {
+                hashbang_comment(source)
+            } else {
+                None
+            },
+            strict: options.strict,
+            loc,
+        },
+    );
+
+    (script, errors)
+}
+
+// A failed attempt that resumes at the failing token is often followed by an
+// attempt that fails immediately on the same token; report it only once.
+fn push_error<'alloc>(errors: &mut Vec<ParseError<'alloc>>, error: ParseError<'alloc>) {
+    if errors.last() != Some(&error) {
+        errors.push(error);
+    }
+}
+
+fn parse_script_range<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    start: usize,
+    end: usize,
+    options: ParserOptions,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    let mut tokens =
+        Lexer::with_offset_and_options(allocator, source[start..end].chars(), start, options);
+    let mut parser = Parser::new(
+        AstBuilder::with_options(allocator, options),
+        START_STATE_SCRIPT,
+    );
+
+    loop {
+        let t = tokens.next(&parser)?;
+        if t.terminal_id == TerminalId::End {
+            break;
+        }
+        parser.write_token(&t)?;
+    }
+    Ok(parser.close(tokens.offset())?.to_ast()?)
+}
+
+fn try_parse_script_from<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc str,
+    start: usize,
+    options: ParserOptions,
+) -> std::result::Result<arena::Box<'alloc, Script<'alloc>>, Failure<'alloc>> {
+    let mut tokens =
+        Lexer::with_offset_and_options(allocator, source[start..].chars(), start, options);
+    let mut parser = Parser::new(
+        AstBuilder::with_options(allocator, options),
+        START_STATE_SCRIPT,
+    );
+
+    // The end of the last token written to the parser, and the end of the
+    // last token after which the parser could be closed at a statement
+    // boundary.
+    let mut prev_end = None;
+    let mut checkpoint = start;
+
+    loop {
+        let t = match tokens.next(&parser) {
+            Ok(t) => t,
+            Err(error) => {
+                // Lexical errors: skip the rest of the line, since tokens
+                // such as strings can't span lines.
+                let offset = error.offset().unwrap_or(tokens.offset());
+                return Err(Failure {
+                    error,
+                    prefix_end: checkpoint,
+                    resume: skip_line(source, offset.max(checkpoint)),
+                    skipped: true,
+                });
+            }
+        };
+        if t.terminal_id == TerminalId::End {
+            break;
+        }
+
+        if let Some(end) = prev_end {
+            if t.is_on_new_line && parser.can_close() {
+                checkpoint = end;
+            }
+        }
+
+        if let Err(error) = parser.write_token(&t) {
+            if let Some(end) = prev_end {
+                if parser.can_close() {
+                    // Everything up to the previous token is a complete
+                    // script, most likely a semicolon is missing. Resume at
+                    // the current token without skipping it.
+                    return Err(Failure {
+                        error,
+                        prefix_end: end,
+                        resume: t.loc.start,
+                        skipped: false,
+                    });
+                }
+            }
+
+            let offset = error.offset().unwrap_or(t.loc.start);
+            return Err(Failure {
+                error,
+                prefix_end: checkpoint,
+                resume: skip_statement(source, offset.max(checkpoint)),
+                skipped: true,
+            });
+        }
+        prev_end = Some(t.loc.end);
+
+        if (t.terminal_id == TerminalId::Semicolon || t.terminal_id == TerminalId::CloseBrace)
+            && parser.can_close()
+        {
+            checkpoint = t.loc.end;
+        }
+    }
+
+    match parser.close(tokens.offset()) {
+        Ok(result) => result.to_ast().map_err(|error| Failure {
+            error: error.into(),
+            prefix_end: checkpoint,
+            resume: source.len(),
+            skipped: true,
+        }),
+        Err(error) => Err(Failure {
+            error,
+            prefix_end: checkpoint,
+            resume: source.len(),
+            skipped: checkpoint < source.len(),
+        }),
+    }
+}
+
+fn is_line_terminator(c: char) -> bool {
+    c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}'
+}
+
+// Return the offset of the start of the line after `offset`.
+fn skip_line(source: &str, offset: usize) -> usize {
+    match source[offset..].find(is_line_terminator) {
+        Some(i) => {
+            let end = offset + i;
+            end + source[end..].chars().next().unwrap().len_utf8()
+        }
+        None => source.len(),
+    }
+}
+
+// Return the offset just after the end of the statement containing `offset`:
+// after a stray `}`, or after the next `;` or line terminator.
+fn skip_statement(source: &str, offset: usize) -> usize {
+    if source[offset..].starts_with('}') {
+        return offset + 1;
+    }
+    match source[offset..].find(|c| c == ';' || is_line_terminator(c)) {
+        Some(i) => {
+            let end = offset + i;
+            end + source[end..].chars().next().unwrap().len_utf8()
+        }
+        None => source.len(),
+    }
+}
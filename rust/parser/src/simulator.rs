@@ -50,12 +50,31 @@ impl<'parser> Simulator<'parser> {
     }
 
     pub fn write_token<'alloc>(mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.shift(token)?;
+        Ok(())
+    }
+
+    /// Like write_token, but return the state stack the parser would have
+    /// after shifting `token`.
+    pub fn write_token_to_stack<'alloc>(
+        mut self,
+        token: &Token<'alloc>,
+    ) -> Result<'alloc, Vec<usize>> {
+        let next_state = self.shift(token)?;
+        let mut stack = self.state_stack[..self.sp].to_vec();
+        stack.push(self.state);
+        stack.push(next_state);
+        Ok(stack)
+    }
+
+    // Return the state to push when shifting `token`.
+    fn shift<'alloc>(&mut self, token: &Token<'alloc>) -> Result<'alloc, usize> {
         // Loop for error-handling. The normal path through this code reaches
         // the `return` statement.
         loop {
             let action = self.reduce_all(token.terminal_id);
             if action.is_shift() {
-                return Ok(());
+                return Ok(action.shift_state());
             } else {
                 assert!(action.is_error());
                 self.try_error_handling(token)?;
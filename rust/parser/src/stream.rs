@@ -0,0 +1,99 @@
+//! Parsing a stream of scripts, one after another, from a reader.
+
+use crate::{decode_source, script};
+use ast::{arena, types::Script};
+use generated_parser::{ParserOptions, Result};
+use std::io::BufRead;
+
+/// The scripts read from `reader`, parsed one at a time with `next_script`,
+/// in an allocator that's reset before each one.
+///
+/// The scripts are separated by sentinel lines: a line that is just the
+/// sentinel, apart from the `\n` or `\r\n` ending it, ends the script before
+/// it, and isn't part of any script. The end of the input ends the last
+/// script, unless it comes right after a sentinel line, so a stream of `n`
+/// scripts can have a sentinel line after each one or only between them.
+/// Scripts are UTF-8, and offsets in each one's AST are from its start.
+pub struct ScriptStream<'alloc, R> {
+    allocator: &'alloc mut bumpalo::Bump,
+    reader: R,
+    sentinel: String,
+    options: ParserOptions,
+
+    // The source of the script being read, and the line being read, kept to
+    // reuse their memory.
+    source: Vec<u8>,
+    line: Vec<u8>,
+
+    // Whether the whole input has been read.
+    done: bool,
+}
+
+impl<'alloc, R: BufRead> ScriptStream<'alloc, R> {
+    pub fn new(allocator: &'alloc mut bumpalo::Bump, reader: R, sentinel: &str) -> Self {
+        Self::with_options(allocator, reader, sentinel, ParserOptions::default())
+    }
+
+    pub fn with_options(
+        allocator: &'alloc mut bumpalo::Bump,
+        reader: R,
+        sentinel: &str,
+        options: ParserOptions,
+    ) -> Self {
+        Self {
+            allocator,
+            reader,
+            sentinel: sentinel.to_string(),
+            options,
+            source: Vec::new(),
+            line: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Read the next script, up to the next sentinel line or the end of the
+    /// input, and parse it. Returns `None` once every script has been read.
+    ///
+    /// The AST is allocated in the stream's allocator, so it has to be
+    /// dropped before the next script is read. A script that doesn't parse
+    /// is still read to its end, so the next call goes on with the script
+    /// after it. Errors reading the input are `ParseError::IOError`, and
+    /// end the stream.
+    pub fn next_script(&mut self) -> Option<Result<'_, arena::Box<'_, Script<'_>>>> {
+        if self.done {
+            return None;
+        }
+        self.source.clear();
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(_) => {}
+            }
+            let content = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+            if content == self.sentinel.as_bytes() {
+                break;
+            }
+            self.source.extend_from_slice(&self.line);
+        }
+        if self.done && self.source.is_empty() {
+            // The input ended after a sentinel line, or had nothing in it.
+            return None;
+        }
+
+        self.allocator.reset();
+        let allocator = &*self.allocator;
+        Some(
+            decode_source(allocator, &self.source)
+                .and_then(|source| script(allocator, source, self.options, None)),
+        )
+    }
+}
@@ -0,0 +1,92 @@
+//! Standalone tokenizer, for tools that need tokens rather than an AST.
+
+use crate::lexer::{Lexer, ParserState};
+use crate::simulator::Simulator;
+use ast::SourceLocation;
+use generated_parser::{Result, TerminalId, Token, START_STATE_SCRIPT};
+
+/// Parser state stack, without the AST. Tokenizing shouldn't fail on syntax
+/// the AstBuilder doesn't support yet.
+struct StateStack(Vec<usize>);
+
+impl StateStack {
+    fn write_token<'alloc>(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.0 = Simulator::new(&self.0).write_token_to_stack(token)?;
+        Ok(())
+    }
+}
+
+impl ParserState for StateStack {
+    fn can_accept_terminal(&self, t: TerminalId) -> bool {
+        let bogus_loc = SourceLocation::new(0, 0);
+        Simulator::new(&self.0)
+            .write_token(&Token::basic_token(t, bogus_loc))
+            .is_ok()
+    }
+}
+
+/// Iterator over the tokens of a script. See `tokenize`.
+pub struct Tokens<'alloc> {
+    source: &'alloc str,
+    lexer: Lexer<'alloc>,
+
+    // The lexer needs the parser state to tell a RegularExpressionLiteral
+    // from a `/` or `/=`, and a TemplateMiddle/TemplateTail from a `}`, so
+    // every token is also fed to the parse tables.
+    parser: StateStack,
+
+    done: bool,
+}
+
+impl<'alloc> Iterator for Tokens<'alloc> {
+    /// A token and its raw text in the source.
+    type Item = Result<'alloc, (Token<'alloc>, &'alloc str)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.lexer.next(&self.parser).and_then(|t| {
+            if t.terminal_id != TerminalId::End {
+                self.parser.write_token(&t)?;
+            }
+            Ok(t)
+        });
+        match result {
+            Ok(t) if t.terminal_id == TerminalId::End => {
+                self.done = true;
+                None
+            }
+            Ok(t) => {
+                let raw = &self.source[t.loc.start..t.loc.end];
+                Some(Ok((t, raw)))
+            }
+            Err(err) => {
+                // The parser state is unknown after an error, so later
+                // tokens can't be lexed reliably.
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Split a script into tokens.
+///
+/// Tokens are lexed in the same context the parser uses, so `/` is a
+/// division or the start of a RegularExpressionLiteral depending on what
+/// precedes it, and template literals are split into TemplateHead,
+/// TemplateMiddle and TemplateTail tokens. Comments and whitespace are
+/// skipped.
+///
+/// Since the context depends on the syntax, the iterator yields an error and
+/// stops at the first lexical or syntax error. Early errors are not detected.
+pub fn tokenize<'alloc>(allocator: &'alloc bumpalo::Bump, source: &'alloc str) -> Tokens<'alloc> {
+    Tokens {
+        source,
+        lexer: Lexer::new(allocator, source.chars()),
+        parser: StateStack(vec![START_STATE_SCRIPT]),
+        done: false,
+    }
+}
@@ -0,0 +1,11 @@
+class A extends B {
+  #x = 1;
+  static y;
+  static { init(); }
+  constructor() {
+    super();
+  }
+  get z() {
+    return this.#x;
+  }
+}
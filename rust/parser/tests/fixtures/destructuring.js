@@ -0,0 +1,3 @@
+var [a, , b = 1, ...c] = d;
+var { e, f: { g }, ...h } = i;
+[j, k.l] = [k.l, j];
@@ -0,0 +1,5 @@
+x = a + b * c ** 2;
+y = a ? b : c ?? d;
+o = { a, b: 1, [c]: 2, ...d, get e() { return 1; } };
+f(...args, `t${x}`, /re/g, 0x1f, 1n);
+a?.b?.[c]?.(d);
@@ -0,0 +1,8 @@
+function f(a, b = 1, ...rest) {
+  return a + b;
+}
+const g = async (x) => await x;
+function* h() {
+  yield 1;
+  yield* h();
+}
@@ -0,0 +1,4 @@
+import a, { b as c } from "m";
+export const d = 1;
+export default function () {}
+export * from "n";
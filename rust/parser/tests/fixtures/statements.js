@@ -0,0 +1,13 @@
+outer: for (var i = 0; i < 3; i++) {
+  switch (i) {
+    case 0:
+      continue outer;
+    default:
+      break outer;
+  }
+}
+try {
+  throw new Error("e");
+} catch ({ message }) {
+} finally {
+}
@@ -0,0 +1,122 @@
+//! Golden-output tests: each script in `tests/fixtures`, or module if its
+//! extension is `.mjs`, is parsed, and its AST as pretty JSON, or the error
+//! if it doesn't parse, must be the same as the `.snap` file next to it.
+//!
+//! To accept the new output after changing the grammar or the AST, run
+//!
+//! ```text
+//! UPDATE_SNAPSHOTS=1 cargo test -p parser --test snapshots
+//! ```
+//!
+//! which writes the `.snap` files, and review their diff.
+
+use ast::types::Program;
+use bumpalo::Bump;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The extensions of the fixtures.
+const EXTENSIONS: &[&str] = &["js", "mjs"];
+
+/// The fixtures, sorted by path.
+fn fixtures(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("reading {}: {}", dir.display(), err))
+        .map(|entry| entry.expect("reading fixture entry").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| EXTENSIONS.iter().any(|fixture_ext| ext == *fixture_ext))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The snapshot of the fixture at `path`, whose text is `source`.
+fn snapshot(path: &Path, source: &str) -> String {
+    let allocator = &Bump::new();
+    let program = if path.extension().is_some_and(|ext| ext == "mjs") {
+        parser::parse_module(allocator, source).map(|module| Program::Module(module.unbox()))
+    } else {
+        parser::parse_script(allocator, source).map(|script| Program::Script(script.unbox()))
+    };
+    match program {
+        Ok(program) => ast::json::to_string_pretty(&program).unwrap() + "\n",
+        Err(err) => format!("error: {:?}\n", err),
+    }
+}
+
+/// What's different between `expected` and `actual`: the first line that
+/// differs, numbered from 1, in each.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(a), Some(b)) if a == b => line += 1,
+            (a, b) => {
+                return format!(
+                    "line {}:\n  expected: {}\n  actual:   {}",
+                    line,
+                    a.unwrap_or("<end>"),
+                    b.unwrap_or("<end>")
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn test_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let fixtures = fixtures(&dir);
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in &fixtures {
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err));
+        let actual = snapshot(path, &source);
+        let snap_path = path.with_extension(format!(
+            "{}.snap",
+            path.extension().unwrap().to_string_lossy()
+        ));
+        if update {
+            fs::write(&snap_path, &actual)
+                .unwrap_or_else(|err| panic!("writing {}: {}", snap_path.display(), err));
+            continue;
+        }
+        match fs::read_to_string(&snap_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{} differs from {}, at {}",
+                path.display(),
+                snap_path.display(),
+                first_difference(&expected, &actual)
+            )),
+            Err(err) => failures.push(format!("reading {}: {}", snap_path.display(), err)),
+        }
+    }
+
+    // Snapshots of fixtures that were removed or renamed.
+    for entry in fs::read_dir(&dir).unwrap() {
+        let snap_path = entry.unwrap().path();
+        if snap_path.extension().is_some_and(|ext| ext == "snap")
+            && !fixtures.contains(&snap_path.with_extension(""))
+        {
+            failures.push(format!("{} has no fixture", snap_path.display()));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} snapshots failed; run with UPDATE_SNAPSHOTS=1 to accept the new \
+         output:\n\n{}",
+        failures.len(),
+        fixtures.len(),
+        failures.join("\n\n")
+    );
+}
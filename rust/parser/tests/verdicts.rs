@@ -0,0 +1,53 @@
+//! Accept/reject tests: each source in `tests/verdicts.txt` must parse as a
+//! script, or fail with the error kind it's listed with. The fuzz target of
+//! the same name checks them with arbitrary comments added.
+
+use bumpalo::Bump;
+use std::fs;
+use std::path::Path;
+
+/// The verdict `parse_script` gives for `source`: `ok`, or the name of the
+/// kind of its error.
+fn verdict(source: &str) -> &'static str {
+    let allocator = &Bump::new();
+    match parser::parse_script(allocator, source) {
+        Ok(_) => "ok",
+        Err(err) => err.code(),
+    }
+}
+
+#[test]
+fn test_verdicts() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/verdicts.txt");
+    let list = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err));
+
+    let mut count = 0;
+    let mut failures = Vec::new();
+    for (index, line) in list.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        count += 1;
+        let (expected, source) = line.split_once(' ').unwrap_or((line, ""));
+        let actual = verdict(source);
+        if actual != expected {
+            failures.push(format!(
+                "line {}: {:?}\n  expected: {}\n  actual:   {}",
+                index + 1,
+                source,
+                expected,
+                actual
+            ));
+        }
+    }
+
+    assert!(count > 0, "no verdicts in {}", path.display());
+    assert!(
+        failures.is_empty(),
+        "{} of {} verdicts failed:\n\n{}",
+        failures.len(),
+        count,
+        failures.join("\n\n")
+    );
+}